@@ -9,8 +9,8 @@ fn main() {
 
     // calculate hashes of arbitrary content which implements std::io::Read with Hasher
     let mut hasher = Hasher::new();
-    let mut res = reqwest::get("https://example.com/").unwrap();
-    std::io::copy(&mut res, &mut hasher).unwrap();
+    let html = iocutil::scraper::get_html("https://example.com/").unwrap();
+    std::io::copy(&mut html.as_bytes(), &mut hasher).unwrap();
 
     let c: ContentHash = hasher.digests();
     println!("example.com => {:?}", c);
@@ -0,0 +1,37 @@
+/*
+ * C API of iocutil (built with `capi` feature)
+ *
+ *   cargo rustc --release --no-default-features --features capi --crate-type staticlib
+ *   (or `--crate-type cdylib` for a shared library)
+ *
+ * All strings are NUL-terminated UTF-8. Returned strings are owned by the caller and must be
+ * released by iocutil_free_string(). NULL is returned on invalid input (NULL or invalid UTF-8).
+ */
+
+#ifndef IOCUTIL_H
+#define IOCUTIL_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * unique indicators in (possibly defanged) text, in order of appearance
+ * a line for each indicator: kind ("hash", "domain", "url", "ip" or "email"), TAB, value
+ */
+char *iocutil_scrape_iocs(const char *text);
+
+/* defanged indicator (e.g. "hxxps://evil[.]example[.]com/"), NULL if it is not an indicator */
+char *iocutil_defang(const char *value);
+
+/* defanged text restored into original form */
+char *iocutil_refang(const char *text);
+
+/* release a string returned by the functions above (NULL is ignored) */
+void iocutil_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* IOCUTIL_H */
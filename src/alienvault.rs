@@ -1,28 +1,25 @@
 //! AlienVault OTX client and its utilities
 
-use crate::util::unwrap_try_into;
 use chrono::prelude::*;
 use derive_builder::Builder;
 use failure::Fail;
-use reqwest::header::HeaderValue;
 use serde::Deserialize;
 use std::convert::TryInto;
+use std::net::IpAddr;
 
 use crate::datetime::days_ago;
+use crate::http::{block_on, ClientConfig};
+use crate::ioc::{Ioc, IocKind};
+use crate::keys::{ApiKeyProvider, DefaultKeys};
+use crate::nonblocking;
+use crate::paginate::Paginated;
+use crate::provider::{Capabilities, IocProvider, ProviderError, ProviderReport, Verdict};
 use crate::{GenericResult, SampleHash};
 
 /// AlienVaultOTX API Client (default use `$OTX_APIKEY` environment variable as apikey)
+#[derive(Default)]
 pub struct AlienVaultOTXClient {
-    apikey: String,
-}
-
-impl Default for AlienVaultOTXClient {
-    fn default() -> Self {
-        AlienVaultOTXClient {
-            apikey: std::env::var("OTX_APIKEY")
-                .expect("please set AlienVault OTX API key to environment var $OTX_APIKEY"),
-        }
-    }
+    inner: nonblocking::AlienVaultOTXClient,
 }
 
 /// Errors in operating AlienVault OTX
@@ -30,9 +27,6 @@ impl Default for AlienVaultOTXClient {
 pub enum AlienVaultOTXError {
     #[fail(display = "invalid setting")]
     InvalidSetting(String),
-
-    #[fail(display = "request failed")]
-    RequestFailed,
 }
 
 /// QueryType when query about a indicator
@@ -54,7 +48,22 @@ impl std::fmt::Display for QueryType {
 impl AlienVaultOTXClient {
     /// make new client
     pub fn new(apikey: String) -> Self {
-        AlienVaultOTXClient { apikey }
+        AlienVaultOTXClient {
+            inner: nonblocking::AlienVaultOTXClient::new(apikey),
+        }
+    }
+
+    /// new client with apikey of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(AlienVaultOTXClient {
+            inner: nonblocking::AlienVaultOTXClient::from_keys(keys)?,
+        })
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.inner = self.inner.with_config(config);
+        self
     }
 
     /// get pulses modified from specified datetime
@@ -68,12 +77,31 @@ impl AlienVaultOTXClient {
     /// let pulses = client.pulses_from(days_ago(7));
     /// ```
     pub fn pulses_from(&self, datetime: impl Into<DateTime<Utc>>) -> GenericResult<Vec<Pulse>> {
-        Ok(PulsesBuilder::default()
-            .api_key(self.apikey.clone())
-            .modified_since(datetime.into())
-            .build()
-            .map_err(AlienVaultOTXError::InvalidSetting)?
-            .get_all())
+        block_on(self.inner.pulses_from(datetime))
+    }
+
+    /// pulses modified from specified datetime, fetched page by page lazily
+    pub fn pulses(&self, modified_since: impl Into<DateTime<Utc>>) -> Paginated<'_, Pulse> {
+        self.inner.pulses(modified_since)
+    }
+
+    /// request context for pulses subscribed api with this client (settings of this client are
+    /// used for all pages)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    ///
+    /// let client = AlienVaultOTXClient::default();
+    /// let hashes = client.subscribed().limit(20).build().unwrap().get_all_hashes();
+    /// ```
+    pub fn subscribed(&self) -> PulsesBuilder {
+        let mut builder = PulsesBuilder::default();
+        builder
+            .api_key(self.inner.apikey().to_owned())
+            .client(self.inner.clone());
+        builder
     }
 
     /// get pulses for x days
@@ -90,22 +118,6 @@ impl AlienVaultOTXClient {
         self.pulses_from(days_ago(days))
     }
 
-    /// make indicator url
-    fn indicator_url(&self, hash: impl AsRef<str>, section: QueryType) -> String {
-        format!(
-            "https://otx.alienvault.com/api/v1/indicators/file/{}/{}",
-            hash.as_ref(),
-            section
-        )
-    }
-
-    /// make get request
-    fn make_get_request(&self, url: impl AsRef<str>) -> reqwest::RequestBuilder {
-        reqwest::Client::new()
-            .get(url.as_ref())
-            .header("X-OTX-API-KEY", self.apikey.as_str())
-    }
-
     /// get raw json report about indicator
     ///
     /// # Example
@@ -122,17 +134,7 @@ impl AlienVaultOTXClient {
         hash: impl TryInto<SampleHash>,
         section: QueryType,
     ) -> GenericResult<String> {
-        let hash = unwrap_try_into(hash)?;
-
-        let mut res = self
-            .make_get_request(self.indicator_url(hash, section))
-            .send()?;
-
-        if !res.status().is_success() {
-            return Err(AlienVaultOTXError::RequestFailed.into());
-        }
-
-        Ok(res.text()?)
+        block_on(self.inner.get_raw_json(hash, section))
     }
 
     /// query with free format
@@ -157,25 +159,17 @@ impl AlienVaultOTXClient {
     where
         T: serde::de::DeserializeOwned,
     {
-        let hash = unwrap_try_into(hash)?;
-        let mut res = self
-            .make_get_request(self.indicator_url(hash, section))
-            .send()?;
-
-        if !res.status().is_success() {
-            return Err(AlienVaultOTXError::RequestFailed.into());
-        }
-
-        Ok(res.json()?)
+        block_on(self.inner.query(hash, section))
     }
 }
 
 /// request context object for pulses subscribed api
-#[derive(Builder, Debug)]
+///
+/// the client (given by `client` or `AlienVaultOTXClient::subscribed`) is reused across pages,
+/// otherwise a client with `api_key` is made at first request
+#[derive(Builder)]
 pub struct Pulses {
-    #[builder(
-        default = "std::env::var(\"OTX_APIKEY\").map_err(|_err| \"could not get OTX APIKEY\".to_owned())?"
-    )]
+    #[builder(default = "DefaultKeys.require(\"alienvault-otx\").map_err(|err| err.to_string())?")]
     api_key: String,
     #[builder(default = "50")]
     limit: u32,
@@ -185,6 +179,19 @@ pub struct Pulses {
     modified_since: DateTime<Utc>,
     #[builder(default = "false")]
     has_done: bool,
+    #[builder(setter(strip_option), default)]
+    client: Option<nonblocking::AlienVaultOTXClient>,
+}
+
+impl std::fmt::Debug for Pulses {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pulses")
+            .field("limit", &self.limit)
+            .field("page", &self.page)
+            .field("modified_since", &self.modified_since)
+            .field("has_done", &self.has_done)
+            .finish()
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -284,16 +291,11 @@ pub struct Response {
 impl Pulses {
     /// request page
     fn request(&mut self) -> GenericResult<Response> {
-        let res: Response = reqwest::Client::new()
-            .get(format!(
-                "https://otx.alienvault.com/api/v1/pulses/subscribed?limit={}&page={}&modified_since={}",
-                self.limit,
-                self.page,
-                self.modified_since.to_rfc3339()).as_str()
-            )
-            .header("X-OTX-API-KEY", HeaderValue::from_str(self.api_key.as_str()).unwrap())
-            .send()?
-            .json()?;
+        let api_key = &self.api_key;
+        let client = self
+            .client
+            .get_or_insert_with(|| nonblocking::AlienVaultOTXClient::new(api_key.clone()));
+        let res = block_on(client.pulses_page(self.modified_since, self.limit, self.page))?;
 
         // update page number
         self.page += 1;
@@ -334,3 +336,72 @@ impl Iterator for Pulses {
         }
     }
 }
+
+/// pulse summary in general section
+#[derive(Debug, Deserialize)]
+struct PulseSummary {
+    tags: Option<Vec<String>>,
+}
+
+/// pulse information in general section
+#[derive(Debug, Deserialize)]
+struct PulseInfo {
+    count: i64,
+    pulses: Option<Vec<PulseSummary>>,
+}
+
+/// general section (only fields used in lookup)
+#[derive(Debug, Deserialize)]
+struct General {
+    pulse_info: PulseInfo,
+}
+
+impl IocProvider for AlienVaultOTXClient {
+    fn name(&self) -> &str {
+        "alienvault-otx"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::lookup_only(vec![
+            IocKind::Hash,
+            IocKind::Domain,
+            IocKind::Url,
+            IocKind::IpAddress,
+        ])
+    }
+
+    fn lookup(&self, ioc: &Ioc) -> GenericResult<ProviderReport> {
+        self.ensure_lookup(ioc)?;
+
+        let (section, page) = match ioc {
+            Ioc::Hash(_) => ("file", "file"),
+            Ioc::Domain(_) => ("domain", "domain"),
+            Ioc::Url(_) => ("url", "url"),
+            Ioc::IpAddress(IpAddr::V4(_)) => ("IPv4", "ip"),
+            Ioc::IpAddress(IpAddr::V6(_)) => ("IPv6", "ip"),
+            _ => return Err(ProviderError::UnsupportedKind(ioc.kind()).into()),
+        };
+
+        let general: General = block_on(self.inner.general(section, ioc.to_string()))?;
+        if general.pulse_info.count == 0 {
+            return Ok(ProviderReport::not_found(self.name(), ioc.clone()));
+        }
+
+        // appearing in pulses does not always mean malicious
+        let mut r = ProviderReport::new(self.name(), ioc.clone(), Verdict::Suspicious);
+        r.permalink = Some(format!(
+            "https://otx.alienvault.com/indicator/{}/{}",
+            page, ioc
+        ));
+        r.tags = general
+            .pulse_info
+            .pulses
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|x| x.tags.unwrap_or_default())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        Ok(r)
+    }
+}
@@ -0,0 +1,852 @@
+//! allowlists of known benign indicators to suppress false positives
+//!
+//! `Allowlist` is implemented by built-in lists and composed by combinators:
+//!
+//! - `Warninglists`: [MISP warninglists](https://github.com/MISP/misp-warninglists)
+//!   (CIDRs of cloud providers, well-known domains, sinkholes and so on)
+//! - `Tranco`: top domains of the [Tranco list](https://tranco-list.eu/)
+//! - `CidrList`: networks, one CIDR per line
+//! - `CidrSet` (`iocutil::cidrset`): large feeds of labeled networks, with O(log n) lookups
+//! - `FileList`: custom entries (domains, CIDRs or exact values), one per line
+//! - `Union` (`Allowlist::union`) and `PerKind` (a list for each kind of indicator)
+//!
+//! Allowlists are applied at scrape time optionally (`allowlist` of `ScrapeOptions`), and at
+//! export time mandatorily: once installed by `set_export_allowlist`, allowed indicators are
+//! suppressed from every export and upload (CSV, STIX bundle, MISP event, Zeek intel, Suricata,
+//! KQL, SPL, Sigma, YARA, Elasticsearch queries, OpenIOC, CEF / LEEF lines, plain-text feed,
+//! report, Microsoft Graph and Falcon). The only exceptions are JSON Lines and Parquet, which are
+//! lossless dumps. Suppressed ones are returned by `audited` for the export, or recorded for
+//! `take_suppressed` (up to `MAX_SUPPRESSED`).
+
+use chrono::{DateTime, Utc};
+use failure::Fail;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::cidrset::Cidr;
+use crate::ioc::{host_of_url, Indicator, Ioc, IocKind};
+use crate::{GenericResult, SampleHash};
+
+/// Errors in building allowlist
+#[derive(Fail, Debug)]
+pub enum AllowlistError {
+    #[fail(display = "invalid cidr in warninglist {}: {}", _0, _1)]
+    InvalidCidr(String, String),
+    #[fail(display = "invalid entry in allowlist {}: {}", _0, _1)]
+    InvalidEntry(String, String),
+}
+
+/// how entries of a warninglist are matched
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WarninglistType {
+    /// same value
+    String,
+    /// value contains the entry
+    Substring,
+    /// same domain or its subdomain
+    Hostname,
+    /// ip address in the network
+    Cidr,
+    /// value matches the pattern
+    Regex,
+}
+
+/// a MISP warninglist (`list.json`)
+#[derive(Deserialize, Clone, Debug)]
+pub struct Warninglist {
+    pub name: String,
+    #[serde(default)]
+    pub version: u64,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "type")]
+    pub list_type: WarninglistType,
+    pub list: Vec<String>,
+    /// MISP attribute types the list applies to (all if empty)
+    #[serde(default)]
+    pub matching_attributes: Vec<String>,
+}
+
+impl Warninglist {
+    /// parse a warninglist in JSON format
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::allowlist::{Warninglist, WarninglistType};
+    ///
+    /// let wl = Warninglist::from_json(r#"{
+    ///     "name": "List of known sinkholes",
+    ///     "version": 20191120,
+    ///     "description": "sinkholes",
+    ///     "type": "cidr",
+    ///     "list": ["192.0.2.0/24"],
+    ///     "matching_attributes": ["ip-src", "ip-dst"]
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(wl.list_type, WarninglistType::Cidr);
+    /// assert_eq!(wl.list.len(), 1);
+    /// ```
+    pub fn from_json(json: impl AsRef<str>) -> GenericResult<Self> {
+        Ok(serde_json::from_str(json.as_ref())?)
+    }
+
+    /// read a warninglist file
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        Warninglist::from_json(std::fs::read_to_string(path)?)
+    }
+}
+
+/// kinds of indicators which MISP attribute type refers
+fn kinds_of_attribute(attribute: &str) -> Vec<IocKind> {
+    match attribute {
+        "md5" | "sha1" | "sha256" | "filename|md5" | "filename|sha1" | "filename|sha256" => {
+            vec![IocKind::Hash]
+        }
+        "domain" | "hostname" => vec![IocKind::Domain],
+        "domain|ip" | "hostname|port" => vec![IocKind::Domain, IocKind::IpAddress],
+        "url" | "uri" | "link" => vec![IocKind::Url],
+        x if x.starts_with("ip-") => vec![IocKind::IpAddress],
+        x if x.starts_with("email") => vec![IocKind::EmailAddress],
+        _ => vec![],
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Exact(HashSet<String>),
+    Substring(Vec<String>),
+    Hostname(HashSet<String>),
+    Cidr(Vec<Cidr>),
+    Regex(Vec<Regex>),
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    /// kinds the rule applies to (all if None)
+    kinds: Option<HashSet<IocKind>>,
+    matcher: Matcher,
+}
+
+/// domain part of an indicator (domain itself, host of url, or domain of email address)
+fn domain_of(ioc: &Ioc) -> Option<String> {
+    match ioc {
+        Ioc::Domain(x) => Some(x.clone()),
+        Ioc::Url(x) => match host_of_url(x)? {
+            Ioc::Domain(d) => Some(d),
+            _ => None,
+        },
+        Ioc::EmailAddress(x) => x.rsplit('@').next().map(|x| x.to_owned()),
+        _ => None,
+    }
+}
+
+/// ip address of an indicator (ip address itself, or host of url)
+fn ip_of(ioc: &Ioc) -> Option<IpAddr> {
+    match ioc {
+        Ioc::IpAddress(x) => Some(*x),
+        Ioc::Url(x) => match host_of_url(x)? {
+            Ioc::IpAddress(ip) => Some(ip),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// the domain and its parent domains (`a.example.com`, `example.com` and `com`)
+fn parent_domains(domain: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(domain), |x| x.find('.').map(|idx| &x[idx + 1..]))
+}
+
+/// entries of a list file (trimmed, except blank lines and `#` comments)
+fn entries(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|x| x.split(" #").next().unwrap_or_default().trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+}
+
+impl Rule {
+    fn matches(&self, ioc: &Ioc) -> bool {
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&ioc.kind()) {
+                return false;
+            }
+        }
+
+        let value = ioc.value().to_lowercase();
+        match &self.matcher {
+            Matcher::Exact(entries) => entries.contains(&value),
+            Matcher::Substring(entries) => entries.iter().any(|x| value.contains(x.as_str())),
+            Matcher::Hostname(entries) => domain_of(ioc)
+                .map(|d| parent_domains(&d).any(|x| entries.contains(x)))
+                .unwrap_or(false),
+            Matcher::Cidr(entries) => ip_of(ioc)
+                .map(|ip| entries.iter().any(|x| x.contains(&ip)))
+                .unwrap_or(false),
+            Matcher::Regex(entries) => entries.iter().any(|x| x.is_match(&ioc.value())),
+        }
+    }
+}
+/// list of known benign indicators
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::allowlist::{Allowlist, CidrList, PerKind, Tranco};
+///
+/// let tranco = Tranco::parse("1,google.com\n2,example.com\n3,example.org", 2);
+/// let cidrs = CidrList::parse("RFC 5737", "192.0.2.0/24\n198.51.100.0/24").unwrap();
+/// let allowlist = tranco.union(cidrs);
+///
+/// assert_eq!(allowlist.matched(&Ioc::new("www.example.com").unwrap()), Some("Tranco top 2"));
+/// assert_eq!(allowlist.matched(&Ioc::new("http://192.0.2.1/a").unwrap()), Some("RFC 5737"));
+/// assert!(!allowlist.is_allowed(&Ioc::new("example.org").unwrap()));
+///
+/// // top domains are allowed as domains, but not as hosts of URLs
+/// let per_kind = PerKind::new().with(IocKind::Domain, Tranco::parse("1,google.com", 1));
+/// assert!(per_kind.is_allowed(&Ioc::new("google.com").unwrap()));
+/// assert!(!per_kind.is_allowed(&Ioc::new("https://google.com/url?q=evil").unwrap()));
+/// ```
+pub trait Allowlist: Send + Sync {
+    /// name of the list which allows the indicator
+    fn matched(&self, ioc: &Ioc) -> Option<&str>;
+
+    /// the indicator is known benign?
+    fn is_allowed(&self, ioc: &Ioc) -> bool {
+        self.matched(ioc).is_some()
+    }
+
+    /// allowed by either of the lists
+    fn union<A: Allowlist>(self, other: A) -> Union<Self, A>
+    where
+        Self: Sized,
+    {
+        Union(self, other)
+    }
+
+    /// indicators except allowed ones, and suppressed ones
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::allowlist::{Allowlist, FileList};
+    ///
+    /// let allowlist = FileList::parse("ours", "# our assets\nexample.com\n192.0.2.0/24").unwrap();
+    /// let (kept, suppressed) = allowlist.audit(vec![
+    ///     Ioc::new("mail.example.com").unwrap(),
+    ///     Ioc::new("evil.example.net").unwrap(),
+    /// ]);
+    ///
+    /// assert_eq!(kept.len(), 1);
+    /// assert_eq!(suppressed[0].ioc, Ioc::new("mail.example.com").unwrap());
+    /// assert_eq!(suppressed[0].list, "ours");
+    /// ```
+    fn audit(
+        &self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    ) -> (Vec<Indicator>, Vec<Suppression>)
+    where
+        Self: Sized,
+    {
+        let now = Utc::now();
+        let mut kept = vec![];
+        let mut suppressed = vec![];
+        for indicator in indicators.into_iter().map(Into::<Indicator>::into) {
+            match self.matched(&indicator.ioc) {
+                Some(list) => suppressed.push(Suppression {
+                    list: list.to_owned(),
+                    at: now,
+                    ioc: indicator.ioc,
+                }),
+                None => kept.push(indicator),
+            }
+        }
+        (kept, suppressed)
+    }
+
+    /// indicators except allowed ones
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::allowlist::{Allowlist, Warninglists, Warninglist};
+    ///
+    /// let mut allowlist = Warninglists::new();
+    /// allowlist.add_warninglist(&Warninglist::from_json(r#"{
+    ///     "name": "RFC 5737",
+    ///     "type": "cidr",
+    ///     "list": ["192.0.2.0/24", "2001:db8::/32"]
+    /// }"#).unwrap()).unwrap();
+    ///
+    /// let indicators = vec![
+    ///     Ioc::new("192.0.2.1").unwrap(),
+    ///     Ioc::new("http://192.0.2.1/a").unwrap(),
+    ///     Ioc::new("2001:db8::1").unwrap(),
+    ///     Ioc::new("198.51.100.1").unwrap(),
+    /// ];
+    ///
+    /// let exported = iocutil::csv::to_csv(allowlist.filter(indicators), &Default::default());
+    /// assert!(!exported.contains("192.0.2.1"));
+    /// assert!(exported.contains("198.51.100.1"));
+    /// ```
+    fn filter(&self, indicators: impl IntoIterator<Item = impl Into<Indicator>>) -> Vec<Indicator>
+    where
+        Self: Sized,
+    {
+        self.audit(indicators).0
+    }
+
+    /// hashes in text except allowed ones
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::allowlist::{Allowlist, Warninglists, Warninglist};
+    ///
+    /// let mut allowlist = Warninglists::new();
+    /// allowlist.add_warninglist(&Warninglist::from_json(r#"{
+    ///     "name": "Hashes of empty file",
+    ///     "type": "string",
+    ///     "list": ["D41D8CD98F00B204E9800998ECF8427E"],
+    ///     "matching_attributes": ["md5"]
+    /// }"#).unwrap()).unwrap();
+    ///
+    /// let found: Vec<_> = allowlist.find_hashes(
+    ///     "d41d8cd98f00b204e9800998ecf8427e, 9fbdc5eca123e81571e8966b9b4e4a1e"
+    /// );
+    /// assert_eq!(found, vec![sample!("9fbdc5eca123e81571e8966b9b4e4a1e")]);
+    /// ```
+    fn find_hashes<T>(&self, text: impl AsRef<str>) -> T
+    where
+        Self: Sized,
+        T: std::iter::FromIterator<SampleHash>,
+    {
+        SampleHash::find::<Vec<_>>(text)
+            .into_iter()
+            .filter(|x| !self.is_allowed(&Ioc::Hash(x.clone())))
+            .collect()
+    }
+
+    /// scrape hashes from url except allowed ones
+    #[cfg(feature = "net")]
+    fn scrape_hashes<T>(&self, url: impl AsRef<str>) -> GenericResult<T>
+    where
+        Self: Sized,
+        T: std::iter::FromIterator<SampleHash>,
+    {
+        Ok(self.find_hashes(crate::scraper::get_article(url)?))
+    }
+}
+
+impl<A: Allowlist + ?Sized> Allowlist for Box<A> {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        (**self).matched(ioc)
+    }
+}
+
+impl<A: Allowlist + ?Sized> Allowlist for Arc<A> {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        (**self).matched(ioc)
+    }
+}
+
+/// allowed by any of the lists (the first one is reported)
+impl<A: Allowlist> Allowlist for Vec<A> {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        self.iter().find_map(|x| x.matched(ioc))
+    }
+}
+
+/// allowed by either of two lists (by `Allowlist::union`)
+#[derive(Debug, Clone)]
+pub struct Union<A, B>(A, B);
+
+impl<A: Allowlist, B: Allowlist> Allowlist for Union<A, B> {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        self.0.matched(ioc).or_else(|| self.1.matched(ioc))
+    }
+}
+
+/// a list for each kind of indicator (other kinds are not allowed)
+#[derive(Default)]
+pub struct PerKind {
+    lists: HashMap<IocKind, Box<dyn Allowlist>>,
+}
+
+impl PerKind {
+    pub fn new() -> Self {
+        PerKind::default()
+    }
+
+    /// indicators of the kind are allowed by the list (replaces previous one)
+    pub fn with(mut self, kind: IocKind, list: impl Allowlist + 'static) -> Self {
+        self.lists.insert(kind, Box::new(list));
+        self
+    }
+}
+
+impl Allowlist for PerKind {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        self.lists.get(&ioc.kind())?.matched(ioc)
+    }
+}
+
+/// set of MISP warninglists
+#[derive(Debug, Clone, Default)]
+pub struct Warninglists {
+    rules: Vec<Rule>,
+}
+
+impl Warninglists {
+    /// empty set (allows nothing)
+    pub fn new() -> Self {
+        Warninglists::default()
+    }
+
+    /// add entries of a warninglist
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::allowlist::{Allowlist, Warninglists, Warninglist};
+    ///
+    /// let wl = Warninglist::from_json(r#"{
+    ///     "name": "Top domains",
+    ///     "type": "hostname",
+    ///     "list": ["example.com", ".example.org."]
+    /// }"#).unwrap();
+    ///
+    /// let mut allowlist = Warninglists::new();
+    /// allowlist.add_warninglist(&wl).unwrap();
+    ///
+    /// assert!(allowlist.is_allowed(&Ioc::new("www.example.com").unwrap()));
+    /// assert!(allowlist.is_allowed(&Ioc::new("https://example.org/a").unwrap()));
+    /// assert!(allowlist.is_allowed(&Ioc::new("alice@example.com").unwrap()));
+    /// assert!(!allowlist.is_allowed(&Ioc::new("badexample.com").unwrap()));
+    /// assert_eq!(allowlist.matched(&Ioc::new("example.com").unwrap()), Some("Top domains"));
+    /// ```
+    pub fn add_warninglist(&mut self, warninglist: &Warninglist) -> GenericResult<()> {
+        let kinds: HashSet<_> = warninglist
+            .matching_attributes
+            .iter()
+            .flat_map(|x| kinds_of_attribute(x))
+            .collect();
+        let kinds = if warninglist.matching_attributes.is_empty() {
+            None
+        } else {
+            Some(kinds)
+        };
+
+        let entries = warninglist
+            .list
+            .iter()
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty());
+
+        let matcher = match warninglist.list_type {
+            WarninglistType::String => Matcher::Exact(entries.map(|x| x.to_lowercase()).collect()),
+            WarninglistType::Substring => {
+                Matcher::Substring(entries.map(|x| x.to_lowercase()).collect())
+            }
+            WarninglistType::Hostname => Matcher::Hostname(
+                entries
+                    .map(|x| x.trim_matches('.').to_lowercase())
+                    .collect(),
+            ),
+            WarninglistType::Cidr => Matcher::Cidr(
+                entries
+                    .map(|x| {
+                        Cidr::parse(x).ok_or_else(|| {
+                            AllowlistError::InvalidCidr(warninglist.name.clone(), x.to_owned())
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            WarninglistType::Regex => {
+                Matcher::Regex(entries.map(Regex::new).collect::<Result<_, _>>()?)
+            }
+        };
+
+        self.rules.push(Rule {
+            name: warninglist.name.clone(),
+            kinds,
+            matcher,
+        });
+        Ok(())
+    }
+
+    /// add warninglists in a directory recursively (e.g. `misp-warninglists/lists`)
+    ///
+    /// every `list.json` in the directory is loaded. returns number of loaded lists.
+    pub fn load_warninglists(&mut self, dir: impl AsRef<Path>) -> GenericResult<usize> {
+        let mut count = 0;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                count += self.load_warninglists(&path)?;
+            } else if path.file_name().map(|x| x == "list.json").unwrap_or(false) {
+                self.add_warninglist(&Warninglist::open(&path)?)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// number of loaded lists
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// no list is loaded?
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Allowlist for Warninglists {
+    /// name of the first warninglist which allows the indicator
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|x| x.matches(ioc))
+            .map(|x| x.name.as_str())
+    }
+}
+
+/// top domains of a Tranco list (`rank,domain` in CSV), and their subdomains
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::allowlist::{Allowlist, Tranco};
+///
+/// let tranco = Tranco::parse("1,google.com\n2,youtube.com\n3,facebook.com\n", 2);
+/// assert_eq!(tranco.len(), 2);
+/// assert_eq!(tranco.rank("youtube.com"), Some(2));
+/// assert!(tranco.is_allowed(&Ioc::new("https://www.youtube.com/watch").unwrap()));
+/// assert!(!tranco.is_allowed(&Ioc::new("facebook.com").unwrap()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tranco {
+    name: String,
+    ranks: HashMap<String, usize>,
+}
+
+impl Tranco {
+    /// domains ranked up to `top` in the list (lines without rank are ranked by position)
+    pub fn parse(csv: impl AsRef<str>, top: usize) -> Self {
+        let ranks = entries(csv.as_ref())
+            .enumerate()
+            .filter_map(|(idx, line)| {
+                let (rank, domain) = match line.split_once(',') {
+                    Some((rank, domain)) => (rank.trim().parse().ok()?, domain),
+                    None => (idx + 1, line),
+                };
+                Some((rank, domain.trim().trim_matches('.').to_lowercase()))
+            })
+            .filter(|(rank, _)| *rank <= top)
+            .map(|(rank, domain)| (domain, rank))
+            .collect();
+        Tranco {
+            name: format!("Tranco top {}", top),
+            ranks,
+        }
+    }
+
+    /// read a Tranco list file (e.g. `top-1m.csv`)
+    pub fn open(path: impl AsRef<Path>, top: usize) -> GenericResult<Self> {
+        Ok(Tranco::parse(std::fs::read_to_string(path)?, top))
+    }
+
+    /// rank of the domain (`None` if not in the top)
+    pub fn rank(&self, domain: &str) -> Option<usize> {
+        self.ranks.get(&domain.to_lowercase()).copied()
+    }
+
+    /// number of domains
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    /// no domain is loaded?
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+}
+
+impl Allowlist for Tranco {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        let domain = domain_of(ioc)?;
+        if parent_domains(&domain).any(|x| self.ranks.contains_key(x)) {
+            Some(&self.name)
+        } else {
+            None
+        }
+    }
+}
+
+/// networks (one CIDR or IP address per line, `#` for comments)
+#[derive(Debug, Clone)]
+pub struct CidrList {
+    name: String,
+    cidrs: Vec<Cidr>,
+}
+
+impl CidrList {
+    pub fn parse(name: impl Into<String>, text: impl AsRef<str>) -> GenericResult<Self> {
+        let name = name.into();
+        let cidrs = entries(text.as_ref())
+            .map(|x| {
+                Cidr::parse(x)
+                    .ok_or_else(|| AllowlistError::InvalidCidr(name.clone(), x.to_owned()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(CidrList { name, cidrs })
+    }
+
+    /// read a CIDR list file (named by the path)
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        let path = path.as_ref();
+        CidrList::parse(path.display().to_string(), std::fs::read_to_string(path)?)
+    }
+}
+
+impl Allowlist for CidrList {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        let ip = ip_of(ioc)?;
+        if self.cidrs.iter().any(|x| x.contains(&ip)) {
+            Some(&self.name)
+        } else {
+            None
+        }
+    }
+}
+
+/// custom entries (one per line, `#` for comments)
+///
+/// domains allow their subdomains, CIDRs allow addresses in them, and other indicators
+/// (hashes, URLs and email addresses) allow the same value.
+#[derive(Debug, Clone)]
+pub struct FileList {
+    name: String,
+    domains: HashSet<String>,
+    cidrs: Vec<Cidr>,
+    values: HashSet<String>,
+}
+
+impl FileList {
+    pub fn parse(name: impl Into<String>, text: impl AsRef<str>) -> GenericResult<Self> {
+        let mut list = FileList {
+            name: name.into(),
+            domains: HashSet::new(),
+            cidrs: vec![],
+            values: HashSet::new(),
+        };
+        for entry in entries(text.as_ref()) {
+            if let Some(cidr) = Cidr::parse(entry) {
+                list.cidrs.push(cidr);
+                continue;
+            }
+            match Ioc::new(entry) {
+                Ok(Ioc::Domain(x)) => list.domains.insert(x.to_lowercase()),
+                Ok(ioc) => list.values.insert(ioc.value().to_lowercase()),
+                Err(_) => {
+                    return Err(AllowlistError::InvalidEntry(list.name, entry.to_owned()).into())
+                }
+            };
+        }
+        Ok(list)
+    }
+
+    /// read a custom list file (named by the path)
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        let path = path.as_ref();
+        FileList::parse(path.display().to_string(), std::fs::read_to_string(path)?)
+    }
+}
+
+impl Allowlist for FileList {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        let allowed = self.values.contains(&ioc.value().to_lowercase())
+            || domain_of(ioc).is_some_and(|d| parent_domains(&d).any(|x| self.domains.contains(x)))
+            || ip_of(ioc).is_some_and(|ip| self.cidrs.iter().any(|x| x.contains(&ip)));
+        if allowed {
+            Some(&self.name)
+        } else {
+            None
+        }
+    }
+}
+
+/// shared allowlist (e.g. in `ScrapeOptions`), compared by identity
+#[derive(Clone)]
+pub struct SharedAllowlist(pub(crate) Arc<dyn Allowlist>);
+
+impl<A: Allowlist + 'static> From<A> for SharedAllowlist {
+    fn from(list: A) -> Self {
+        SharedAllowlist(Arc::new(list))
+    }
+}
+
+impl fmt::Debug for SharedAllowlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedAllowlist(..)")
+    }
+}
+
+impl PartialEq for SharedAllowlist {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedAllowlist {}
+
+/// record of an indicator suppressed by an allowlist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suppression {
+    pub ioc: Ioc,
+    /// name of the list which allows it
+    pub list: String,
+    pub at: DateTime<Utc>,
+}
+
+lazy_static! {
+    static ref EXPORT_ALLOWLIST: RwLock<Option<Arc<dyn Allowlist>>> = RwLock::new(None);
+    static ref SUPPRESSED: Mutex<VecDeque<Suppression>> = Mutex::new(VecDeque::new());
+}
+
+thread_local! {
+    /// suppressions of running `audited` exports (innermost last)
+    static AUDITS: RefCell<Vec<Vec<Suppression>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// max number of suppressions kept for `take_suppressed` (oldest ones are dropped)
+pub const MAX_SUPPRESSED: usize = 10_000;
+
+/// install the allowlist which every export and upload suppresses by (replaces previous one)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::allowlist::{self, CidrList};
+///
+/// allowlist::set_export_allowlist(CidrList::parse("sinkholes", "192.0.2.0/24").unwrap());
+///
+/// let text = iocutil::csv::to_csv(
+///     vec![Ioc::new("192.0.2.1").unwrap(), Ioc::new("198.51.100.1").unwrap()],
+///     &Default::default(),
+/// );
+/// assert!(!text.contains("192.0.2.1"));
+/// assert!(text.contains("198.51.100.1"));
+///
+/// let suppressed = allowlist::take_suppressed();
+/// assert_eq!(suppressed.len(), 1);
+/// assert_eq!(suppressed[0].list, "sinkholes");
+/// assert!(allowlist::take_suppressed().is_empty());
+/// ```
+pub fn set_export_allowlist(list: impl Allowlist + 'static) {
+    *EXPORT_ALLOWLIST.write().unwrap() = Some(Arc::new(list));
+}
+
+/// stop suppressing exports
+pub fn clear_export_allowlist() {
+    *EXPORT_ALLOWLIST.write().unwrap() = None;
+}
+
+/// indicators suppressed from exports since the last call (oldest first, up to
+/// `MAX_SUPPRESSED`), except ones of `audited` exports
+pub fn take_suppressed() -> Vec<Suppression> {
+    std::mem::take(&mut *SUPPRESSED.lock().unwrap()).into()
+}
+
+/// run the export and return indicators suppressed by it (instead of recording them for
+/// `take_suppressed`), so concurrent exports are audited separately
+///
+/// suppressions are collected in the calling thread, so the export must run in it (e.g. a
+/// future run by `block_on`).
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::allowlist::{self, FileList};
+///
+/// allowlist::set_export_allowlist(FileList::parse("mine", "audited.example.com").unwrap());
+///
+/// let (text, suppressed) = allowlist::audited(|| {
+///     iocutil::csv::to_csv(
+///         vec![Ioc::new("audited.example.com").unwrap(), Ioc::new("evil.example.net").unwrap()],
+///         &Default::default(),
+///     )
+/// });
+/// assert!(text.contains("evil.example.net"));
+/// assert_eq!(suppressed.len(), 1);
+/// assert_eq!(suppressed[0].ioc, Ioc::new("audited.example.com").unwrap());
+/// assert!(allowlist::take_suppressed()
+///     .iter()
+///     .all(|x| x.ioc.value() != "audited.example.com"));
+/// ```
+pub fn audited<T>(export: impl FnOnce() -> T) -> (T, Vec<Suppression>) {
+    struct Pop;
+    impl Drop for Pop {
+        fn drop(&mut self) {
+            AUDITS.with(|x| x.borrow_mut().pop());
+        }
+    }
+
+    AUDITS.with(|x| x.borrow_mut().push(vec![]));
+    let pop = Pop;
+    let result = export();
+    let suppressed = AUDITS.with(|x| x.borrow_mut().last_mut().map(std::mem::take));
+    drop(pop);
+    (result, suppressed.unwrap_or_default())
+}
+
+fn record(suppressed: Vec<Suppression>) {
+    let suppressed = AUDITS.with(|x| match x.borrow_mut().last_mut() {
+        Some(audit) => {
+            audit.extend(suppressed);
+            None
+        }
+        None => Some(suppressed),
+    });
+    if let Some(suppressed) = suppressed {
+        let mut buffer = SUPPRESSED.lock().unwrap();
+        buffer.extend(suppressed);
+        let over = buffer.len().saturating_sub(MAX_SUPPRESSED);
+        buffer.drain(..over);
+    }
+}
+
+/// indicators not allowed by the export allowlist (suppressed ones are recorded)
+pub(crate) fn suppress(indicators: impl IntoIterator<Item = Indicator>) -> Vec<Indicator> {
+    let list = EXPORT_ALLOWLIST.read().unwrap().clone();
+    let list = match list {
+        Some(x) => x,
+        None => return indicators.into_iter().collect(),
+    };
+    let (kept, suppressed) = list.audit(indicators);
+    #[cfg(feature = "tracing")]
+    for x in &suppressed {
+        tracing::info!(ioc = %x.ioc, list = %x.list, "suppressed from export");
+    }
+    record(suppressed);
+    kept
+}
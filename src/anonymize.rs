@@ -0,0 +1,183 @@
+//! keyed anonymization of indicators for sharing (HMAC-SHA256 pseudonyms)
+//!
+//! Pseudonyms are stable under the same key, so partners who share the key can find overlaps
+//! of their collections without revealing the original values.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::ioc::{Indicator, Ioc, IocKind};
+use crate::psl;
+use crate::SampleHash;
+
+/// length of pseudonym labels in hex digits
+const LABEL_LEN: usize = 16;
+
+/// anonymizer replaces indicators with pseudonyms of the same kind and shape
+///
+/// * hashes: hex digits of the same length (md5 stays 32 digits)
+/// * domains: pseudonym label with the original public suffix (e.g. `3f2a...c1.co.jp`)
+/// * URLs: same scheme with pseudonym host and path
+/// * IP addresses: address of the same version
+/// * email addresses: pseudonym local part at pseudonym domain
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::anonymize::Anonymizer;
+///
+/// let a = Anonymizer::new(b"shared secret");
+///
+/// let domain = a.anonymize(&Ioc::new("victim.example.co.jp").unwrap());
+/// assert_eq!(domain.kind(), IocKind::Domain);
+/// assert!(domain.value().ends_with(".co.jp"));
+/// assert_ne!(domain.value(), "victim.example.co.jp");
+///
+/// // stable under the same key
+/// assert_eq!(domain, a.anonymize(&Ioc::new("victim.example.co.jp").unwrap()));
+/// assert_ne!(domain, Anonymizer::new(b"other key").anonymize(&Ioc::new("victim.example.co.jp").unwrap()));
+///
+/// let email = a.anonymize(&Ioc::new("alice@victim.example.co.jp").unwrap());
+/// assert_eq!(email.kind(), IocKind::EmailAddress);
+/// assert!(email.value().ends_with(&format!("@{}", domain)));
+///
+/// // anonymize only kinds which may reveal victims
+/// let a = a.only(vec![IocKind::Domain, IocKind::EmailAddress]);
+/// let hash = Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap();
+/// assert_eq!(a.anonymize(&hash), hash);
+///
+/// let url = Anonymizer::new(b"shared secret").anonymize(&Ioc::new("https://192.0.2.1:8443/victim/login").unwrap());
+/// assert_eq!(url.kind(), IocKind::Url);
+/// assert!(url.value().starts_with("https://"));
+/// assert!(!url.value().contains("victim"));
+/// assert_eq!(Ioc::new(url.value()).unwrap(), url);
+/// ```
+#[derive(Clone)]
+pub struct Anonymizer {
+    key: Vec<u8>,
+    kinds: Option<HashSet<IocKind>>,
+}
+
+impl Anonymizer {
+    /// anonymizer with secret key (every kind of indicator is anonymized)
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Anonymizer {
+            key: key.as_ref().to_vec(),
+            kinds: None,
+        }
+    }
+
+    /// anonymize only these kinds (others are kept as is)
+    pub fn only(mut self, kinds: impl IntoIterator<Item = IocKind>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+    /// HMAC-SHA256 of value with domain separation by purpose
+    fn mac(&self, purpose: &str, value: &str) -> [u8; 32] {
+        // HMAC accepts keys of any length
+        let mut hmac = Hmac::<Sha256>::new_from_slice(&self.key).unwrap();
+        hmac.update(purpose.as_bytes());
+        hmac.update(&[0]);
+        hmac.update(value.as_bytes());
+        hmac.finalize().into_bytes().into()
+    }
+
+    fn hex(&self, purpose: &str, value: &str, len: usize) -> String {
+        // up to 64 digits (same as sha256)
+        let code = self.mac(purpose, value);
+        let mut hex: String = code.iter().map(|b| format!("{:02x}", b)).collect();
+        hex.truncate(len);
+        hex
+    }
+
+    fn domain(&self, domain: &str) -> String {
+        let suffix = psl::public_suffix(domain).unwrap_or_default();
+        format!("{}.{}", self.hex("domain", domain, LABEL_LEN), suffix)
+    }
+
+    fn host(&self, host: &str) -> String {
+        match host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            Ok(ip @ IpAddr::V4(_)) => self.ip(ip).to_string(),
+            Ok(ip @ IpAddr::V6(_)) => format!("[{}]", self.ip(ip)),
+            Err(_) => self.domain(host),
+        }
+    }
+
+    fn ip(&self, ip: IpAddr) -> IpAddr {
+        let code = self.mac("ip", &ip.to_string());
+        match ip {
+            IpAddr::V4(_) => Ipv4Addr::new(code[0], code[1], code[2], code[3]).into(),
+            IpAddr::V6(_) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&code[..16]);
+                Ipv6Addr::from(octets).into()
+            }
+        }
+    }
+
+    fn url(&self, url: &str) -> String {
+        let (scheme, rest) = match url.find("://") {
+            Some(i) => (&url[..i], &url[i + 3..]),
+            None => ("http", url),
+        };
+        let end = rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len());
+        // user info and port are dropped
+        let authority = &rest[..end];
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        let host = match host.rfind(':') {
+            Some(i) if !host.ends_with(']') => &host[..i],
+            _ => host,
+        };
+        format!(
+            "{}://{}/{}",
+            scheme,
+            self.host(&host.to_lowercase()),
+            self.hex("url", url, LABEL_LEN)
+        )
+    }
+
+    /// pseudonym of indicator (kinds not selected by `only` are kept as is)
+    pub fn anonymize(&self, ioc: &Ioc) -> Ioc {
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&ioc.kind()) {
+                return ioc.clone();
+            }
+        }
+        match ioc {
+            Ioc::Hash(h) => {
+                let hex = self.hex("hash", h.as_ref(), h.as_ref().len());
+                Ioc::Hash(match h {
+                    SampleHash::Md5(_) => SampleHash::Md5(hex),
+                    SampleHash::Sha1(_) => SampleHash::Sha1(hex),
+                    SampleHash::Sha256(_) => SampleHash::Sha256(hex),
+                })
+            }
+            Ioc::Domain(d) => Ioc::Domain(self.domain(d)),
+            Ioc::Url(u) => Ioc::Url(self.url(u)),
+            Ioc::IpAddress(ip) => Ioc::IpAddress(self.ip(*ip)),
+            Ioc::EmailAddress(e) => {
+                let domain = e.rsplit('@').next().unwrap_or_default();
+                Ioc::EmailAddress(format!(
+                    "{}@{}",
+                    self.hex("email", e, LABEL_LEN),
+                    self.domain(domain)
+                ))
+            }
+        }
+    }
+
+    /// pseudonym of indicator (description is removed because it may reveal the original value)
+    pub fn anonymize_indicator(&self, indicator: &Indicator) -> Indicator {
+        let mut meta = indicator.meta.clone();
+        meta.description = None;
+        Indicator::with_meta(self.anonymize(&indicator.ioc), meta)
+    }
+}
@@ -0,0 +1,317 @@
+//! hashing members of archives (zip / tar / tar.gz / 7z) along with the archive itself
+//!
+//! Attachments of phishing mails are often archives and the payload inside is the actual IoC,
+//! so `ArchiveHashes` holds hashes of the archive and a tree of its (nested) members.
+
+use derive_builder::Builder;
+use failure::Fail;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+use crate::contenthash::SampleHashes;
+use crate::hasher::IncrementalHasher;
+use crate::{GenericResult, SampleHash};
+
+/// Errors in reading archive
+#[derive(Fail, Debug)]
+pub enum ArchiveError {
+    #[fail(display = "failed to read {} archive: {}", _0, _1)]
+    ReadFailed(ArchiveFormat, String),
+}
+
+/// format of archive
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    SevenZip,
+}
+
+impl std::fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let name = match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::SevenZip => "7z",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+fn is_tar(bytes: &[u8]) -> bool {
+    bytes.get(257..262) == Some(b"ustar")
+}
+
+impl ArchiveFormat {
+    /// detect format by magic bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::archive::ArchiveFormat;
+    ///
+    /// assert_eq!(ArchiveFormat::detect(b"PK\x03\x04\x14\x00"), Some(ArchiveFormat::Zip));
+    /// assert_eq!(ArchiveFormat::detect(b"7z\xbc\xaf\x27\x1c\x00\x04"), Some(ArchiveFormat::SevenZip));
+    /// assert_eq!(ArchiveFormat::detect(b"MZ\x90\x00"), None);
+    /// ```
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+            return Some(ArchiveFormat::Zip);
+        }
+        if bytes.starts_with(b"7z\xbc\xaf\x27\x1c") {
+            return Some(ArchiveFormat::SevenZip);
+        }
+        if is_tar(bytes) {
+            return Some(ArchiveFormat::Tar);
+        }
+        if bytes.starts_with(b"\x1f\x8b") {
+            // gzip of something other than tar is not an archive
+            let mut header = vec![];
+            flate2::read::GzDecoder::new(bytes)
+                .take(512)
+                .read_to_end(&mut header)
+                .ok()?;
+            return if is_tar(&header) {
+                Some(ArchiveFormat::TarGz)
+            } else {
+                None
+            };
+        }
+        None
+    }
+}
+
+/// options of reading archives
+#[derive(Builder, Debug, Clone, Eq, PartialEq)]
+pub struct ArchiveOptions {
+    /// password of encrypted archives (e.g. `infected`)
+    #[builder(default = "None")]
+    password: Option<String>,
+    /// levels of nested archives to expand (members deeper than this are hashed only)
+    #[builder(default = "3")]
+    max_depth: usize,
+    /// members larger than this (in bytes) are hashed but not expanded
+    #[builder(default = "256 * 1024 * 1024")]
+    max_member_size: u64,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        ArchiveOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// hashes of a content and its members (if the content is an archive)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveHashes {
+    pub hashes: SampleHashes,
+    /// format of the content (`None` if not an archive)
+    pub format: Option<ArchiveFormat>,
+    /// path of each member in the archive and its hashes
+    pub members: Vec<(String, ArchiveHashes)>,
+}
+
+fn hash_bytes(bytes: &[u8]) -> SampleHashes {
+    let mut hasher = IncrementalHasher::new();
+    hasher.write_all(bytes).unwrap();
+    hasher.finish()
+}
+
+/// hash a member and expand it if it is a small enough archive
+fn read_member(
+    reader: &mut dyn Read,
+    options: &ArchiveOptions,
+    depth: usize,
+) -> GenericResult<ArchiveHashes> {
+    let mut hasher = IncrementalHasher::new();
+    let mut content: Option<Vec<u8>> = Some(vec![]);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.write_all(&buf[..n])?;
+        content = content
+            .filter(|x| (x.len() + n) as u64 <= options.max_member_size)
+            .map(|mut x| {
+                x.extend_from_slice(&buf[..n]);
+                x
+            });
+    }
+
+    let hashes = hasher.finish();
+    let (format, members) = match content {
+        Some(bytes) => {
+            let format = ArchiveFormat::detect(&bytes);
+            // broken nested archives are treated as ordinary members
+            let members = format
+                .filter(|_| depth < options.max_depth)
+                .and_then(|f| expand(f, &bytes, options, depth).ok())
+                .unwrap_or_default();
+            (format, members)
+        }
+        None => (None, vec![]),
+    };
+
+    Ok(ArchiveHashes {
+        hashes,
+        format,
+        members,
+    })
+}
+
+fn expand(
+    format: ArchiveFormat,
+    bytes: &[u8],
+    options: &ArchiveOptions,
+    depth: usize,
+) -> GenericResult<Vec<(String, ArchiveHashes)>> {
+    let failed = |e: &dyn std::fmt::Display| ArchiveError::ReadFailed(format, e.to_string());
+    let depth = depth + 1;
+    let mut members = vec![];
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| failed(&e))?;
+            for i in 0..archive.len() {
+                let mut file = match &options.password {
+                    Some(password) => archive.by_index_decrypt(i, password.as_bytes()),
+                    None => archive.by_index(i),
+                }
+                .map_err(|e| failed(&e))?;
+                if file.is_dir() {
+                    continue;
+                }
+                let path = file.name().to_owned();
+                members.push((path, read_member(&mut file, options, depth)?));
+            }
+        }
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            let reader: Box<dyn Read> = match format {
+                ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(bytes)),
+                _ => Box::new(bytes),
+            };
+            let mut archive = tar::Archive::new(reader);
+            for entry in archive.entries().map_err(|e| failed(&e))? {
+                let mut entry = entry.map_err(|e| failed(&e))?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let path = entry
+                    .path()
+                    .map_err(|e| failed(&e))?
+                    .to_string_lossy()
+                    .into_owned();
+                members.push((path, read_member(&mut entry, options, depth)?));
+            }
+        }
+        ArchiveFormat::SevenZip => {
+            let password = match &options.password {
+                Some(x) => sevenz_rust::Password::from(x.as_str()),
+                None => sevenz_rust::Password::empty(),
+            };
+            let mut archive =
+                sevenz_rust::SevenZReader::new(Cursor::new(bytes), bytes.len() as u64, password)
+                    .map_err(|e| failed(&e))?;
+            let mut error: Option<failure::Error> = None;
+            archive
+                .for_each_entries(|entry, reader| {
+                    if entry.is_directory() {
+                        return Ok(true);
+                    }
+                    match read_member(reader, options, depth) {
+                        Ok(x) => {
+                            members.push((entry.name().to_owned(), x));
+                            Ok(true)
+                        }
+                        Err(e) => {
+                            error = Some(e);
+                            Ok(false)
+                        }
+                    }
+                })
+                .map_err(|e| failed(&e))?;
+            if let Some(e) = error {
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(members)
+}
+
+impl ArchiveHashes {
+    /// hashes of content and its members
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::archive::{ArchiveFormat, ArchiveHashes, ArchiveOptions};
+    /// use iocutil::prelude::*;
+    ///
+    /// // tar containing a text and a zip with a "payload"
+    /// let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+    /// zip.start_file("invoice.exe", zip::write::SimpleFileOptions::default()).unwrap();
+    /// std::io::Write::write_all(&mut zip, b"MZ payload").unwrap();
+    /// let zip = zip.finish().unwrap().into_inner();
+    ///
+    /// let mut tar = tar::Builder::new(vec![]);
+    /// for (name, content) in [("readme.txt", &b"hello"[..]), ("invoice.zip", &zip[..])].iter() {
+    ///     let mut header = tar::Header::new_gnu();
+    ///     header.set_size(content.len() as u64);
+    ///     header.set_cksum();
+    ///     tar.append_data(&mut header, name, *content).unwrap();
+    /// }
+    /// let tar = tar.into_inner().unwrap();
+    ///
+    /// let hashes = ArchiveHashes::of_bytes(&tar, &ArchiveOptions::default()).unwrap();
+    /// assert_eq!(hashes.format, Some(ArchiveFormat::Tar));
+    /// assert_eq!(hashes.members.len(), 2);
+    /// assert_eq!(hashes.members[1].1.format, Some(ArchiveFormat::Zip));
+    ///
+    /// let payload = ContentHash::of_bytes(b"MZ payload").sha256;
+    /// assert_eq!(hashes.find(&payload), Some("invoice.zip/invoice.exe".to_owned()));
+    /// ```
+    pub fn of_bytes(bytes: impl AsRef<[u8]>, options: &ArchiveOptions) -> GenericResult<Self> {
+        let bytes = bytes.as_ref();
+        let hashes = hash_bytes(bytes);
+        let format = ArchiveFormat::detect(bytes);
+        let members = match format {
+            Some(f) if options.max_depth > 0 => expand(f, bytes, options, 0)?,
+            _ => vec![],
+        };
+        Ok(ArchiveHashes {
+            hashes,
+            format,
+            members,
+        })
+    }
+
+    /// hashes of file content and its members
+    pub fn of_file(path: impl AsRef<Path>, options: &ArchiveOptions) -> GenericResult<Self> {
+        ArchiveHashes::of_bytes(std::fs::read(path)?, options)
+    }
+
+    /// every member (including ones in nested archives) with path joined by `/`
+    pub fn flatten(&self) -> Vec<(String, &SampleHashes)> {
+        let mut items = vec![];
+        for (path, member) in &self.members {
+            items.push((path.clone(), &member.hashes));
+            for (inner, hashes) in member.flatten() {
+                items.push((format!("{}/{}", path, inner), hashes));
+            }
+        }
+        items
+    }
+
+    /// path of the first member having the hash
+    pub fn find(&self, hash: &SampleHash) -> Option<String> {
+        self.flatten()
+            .into_iter()
+            .find(|(_, hashes)| hashes.contains(hash))
+            .map(|(path, _)| path)
+    }
+}
@@ -0,0 +1,211 @@
+//! HTTP banners of servers (status, `Server` header, title and body hash) to cluster infrastructure
+//!
+//! Probing is active: servers (which adversaries may watch) see our requests, so `HttpProber`
+//! (feature `net`) refuses to send them until `allow_active_probing` is called explicitly.
+//! Redirects are followed up to a limit, and invalid certificates (common on C2 servers) are accepted.
+
+use failure::Fail;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+/// Errors in probing HTTP servers
+#[derive(Fail, Debug)]
+pub enum BannerError {
+    #[fail(display = "active HTTP probing is not allowed (see HttpProber::allow_active_probing)")]
+    NotAllowed,
+}
+
+/// banner of HTTP server (of the final response after redirects)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpBanner {
+    /// URL requested first
+    pub url: String,
+    /// URLs redirected to in order (the last one responded the banner)
+    pub redirects: Vec<String>,
+    pub status: u16,
+    /// `Server` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// headers in order of response (names are in lowercase)
+    pub headers: Vec<(String, String)>,
+    /// title of HTML body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// sha256 of body (`None` for HEAD requests, of the first `max_body` bytes if longer)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_sha256: Option<String>,
+}
+
+impl HttpBanner {
+    /// URL which responded the banner
+    pub fn final_url(&self) -> &str {
+        self.redirects.last().unwrap_or(&self.url)
+    }
+
+    /// first value of the header (name is case insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// title of HTML (whitespaces are collapsed, `None` if no or empty title)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::banner::html_title;
+///
+/// let html = "<html><head><title>\n  Index of /payloads &amp; tools\n</title></head></html>";
+/// assert_eq!(html_title(html), Some("Index of /payloads & tools".to_owned()));
+/// assert_eq!(html_title("<html><title> </title></html>"), None);
+/// ```
+pub fn html_title(html: &str) -> Option<String> {
+    let selector = Selector::parse("title").ok()?;
+    let document = Html::parse_document(html);
+    let title = document.select(&selector).next()?;
+    let text = title.text().collect::<Vec<_>>().join(" ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(text).filter(|x| !x.is_empty())
+}
+
+#[cfg(feature = "net")]
+pub use self::client::HttpProber;
+
+#[cfg(feature = "net")]
+mod client {
+    use std::time::Duration;
+
+    use super::HttpBanner;
+    use crate::http::{block_on, ProxyConfig};
+    use crate::ioc::Indicator;
+    use crate::nonblocking;
+    use crate::GenericResult;
+
+    /// careful prober of HTTP(S) servers (refuses to send requests unless `allow_active_probing`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::banner::HttpProber;
+    /// use iocutil::vcr::{clear_cassette, set_cassette, Cassette};
+    ///
+    /// let path = std::env::temp_dir().join("iocutil-banner-doctest.json");
+    /// std::fs::write(
+    ///     &path,
+    ///     r#"{"interactions": [{
+    ///         "provider": "http-banner",
+    ///         "method": "GET",
+    ///         "url": "http://evil.example.com/",
+    ///         "status": 302,
+    ///         "headers": [["location", "/login"]],
+    ///         "response": ""
+    ///     }, {
+    ///         "provider": "http-banner",
+    ///         "method": "GET",
+    ///         "url": "http://evil.example.com/login",
+    ///         "status": 200,
+    ///         "headers": [["server", "nginx"], ["content-type", "text/html"]],
+    ///         "response": "<html><title>Panel Login</title></html>"
+    ///     }]}"#,
+    /// )
+    /// .unwrap();
+    /// set_cassette(Cassette::replay(&path).unwrap());
+    ///
+    /// let prober = HttpProber::new()
+    ///     .allow_active_probing()
+    ///     .with_user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64)");
+    /// let banner = prober.probe("http://evil.example.com/").unwrap();
+    /// assert_eq!(banner.final_url(), "http://evil.example.com/login");
+    /// assert_eq!(banner.status, 200);
+    /// assert_eq!(banner.server.as_deref(), Some("nginx"));
+    /// assert_eq!(banner.title.as_deref(), Some("Panel Login"));
+    /// assert!(banner.body_sha256.is_some());
+    ///
+    /// // redirects are not followed beyond the limit
+    /// let banner = prober.clone().with_max_redirects(0).probe("http://evil.example.com/").unwrap();
+    /// assert_eq!(banner.status, 302);
+    /// clear_cassette();
+    /// ```
+    #[derive(Clone, Default)]
+    pub struct HttpProber {
+        inner: nonblocking::HttpProber,
+    }
+
+    impl HttpProber {
+        /// prober sending GET, following 3 redirects at most
+        /// (requests are refused until `allow_active_probing`)
+        pub fn new() -> Self {
+            HttpProber::default()
+        }
+
+        /// allow requests, accepting that servers of adversaries may notice them
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use iocutil::banner::HttpProber;
+        ///
+        /// assert!(HttpProber::new().probe("https://evil.example.com/").is_err());
+        /// ```
+        pub fn allow_active_probing(mut self) -> Self {
+            self.inner = self.inner.allow_active_probing();
+            self
+        }
+
+        /// use the HTTP client (redirects should be disabled in it, as the prober follows them)
+        pub fn with_client(mut self, client: reqwest::Client) -> Self {
+            self.inner = self.inner.with_client(client);
+            self
+        }
+
+        /// send requests via the proxy
+        pub fn with_proxy(mut self, proxy: &ProxyConfig) -> GenericResult<Self> {
+            self.inner = self.inner.with_proxy(proxy)?;
+            Ok(self)
+        }
+
+        /// `User-Agent` of requests (default `iocutil.rs/<version>`)
+        pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Self {
+            self.inner = self.inner.with_user_agent(user_agent);
+            self
+        }
+
+        /// redirects followed at most (default 3, 0 not to follow)
+        pub fn with_max_redirects(mut self, max: usize) -> Self {
+            self.inner = self.inner.with_max_redirects(max);
+            self
+        }
+
+        /// send HEAD instead of GET (no title nor body hash)
+        pub fn head_only(mut self) -> Self {
+            self.inner = self.inner.head_only();
+            self
+        }
+
+        /// bytes of body read at most (default 1 MiB)
+        pub fn with_max_body(mut self, max: usize) -> Self {
+            self.inner = self.inner.with_max_body(max);
+            self
+        }
+
+        /// total timeout of each request including body (default 10 seconds)
+        pub fn with_timeout(mut self, timeout: Duration) -> Self {
+            self.inner = self.inner.with_timeout(timeout);
+            self
+        }
+
+        /// banner of the URL
+        pub fn probe(&self, url: impl AsRef<str>) -> GenericResult<HttpBanner> {
+            block_on(self.inner.probe(url))
+        }
+
+        /// banner of the URL, or domain / IP address (HTTPS, and HTTP if failed) of indicator
+        /// (`None` for other kinds)
+        pub fn probe_indicator(&self, indicator: &Indicator) -> GenericResult<Option<HttpBanner>> {
+            block_on(self.inner.probe_indicator(indicator))
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! circuit breaker which stops calling a provider after consecutive failures
+//!
+//! After `threshold` consecutive failures the circuit opens, and lookups fail immediately with
+//! `ProviderError::CircuitOpen` for `cooldown`. Then a single lookup is let through as a probe
+//! (half-open): its success closes the circuit, and its failure opens it again.
+//! Parse errors, not found and unsupported kinds are not counted as failures.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{classify, Error};
+use crate::ioc::Ioc;
+use crate::provider::{Capabilities, IocProvider, ProviderError, ProviderReport};
+use crate::tlp::Audience;
+use crate::GenericResult;
+
+/// state of circuit
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum CircuitState {
+    /// calls go through
+    Closed,
+    /// calls fail immediately until cooldown is over
+    Open,
+    /// a probe is in flight (or the next call is the probe)
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct State {
+    failures: u32,
+    opened_at: Option<Instant>,
+    probing: bool,
+}
+
+/// provider wrapped with circuit breaker
+///
+/// # Example
+///
+/// ```
+/// use iocutil::breaker::{CircuitBreaker, CircuitState};
+/// use iocutil::prelude::*;
+/// use iocutil::provider::Capabilities;
+/// use std::time::Duration;
+///
+/// struct Dead;
+///
+/// impl IocProvider for Dead {
+///     fn name(&self) -> &str {
+///         "dead"
+///     }
+///
+///     fn capabilities(&self) -> Capabilities {
+///         Capabilities::lookup_only(vec![IocKind::Domain])
+///     }
+///
+///     fn lookup(&self, _ioc: &Ioc) -> Result<ProviderReport, failure::Error> {
+///         Err(failure::err_msg("connection refused"))
+///     }
+/// }
+///
+/// let provider = CircuitBreaker::new(Dead)
+///     .with_threshold(2)
+///     .with_cooldown(Duration::from_millis(50));
+/// let ioc = Ioc::new("example.com").unwrap();
+///
+/// assert!(provider.lookup(&ioc).is_err());
+/// assert!(provider.lookup(&ioc).is_err());
+/// assert_eq!(provider.state(), CircuitState::Open);
+///
+/// // short-circuited without calling the provider
+/// let e = provider.lookup(&ioc).unwrap_err();
+/// assert_eq!(e.to_string(), "dead is unavailable (circuit open)");
+///
+/// // after cooldown, a probe is sent (and its failure opens the circuit again)
+/// std::thread::sleep(Duration::from_millis(60));
+/// assert_eq!(provider.state(), CircuitState::HalfOpen);
+/// assert_eq!(provider.lookup(&ioc).unwrap_err().to_string(), "connection refused");
+/// assert_eq!(provider.state(), CircuitState::Open);
+/// ```
+#[derive(Debug)]
+pub struct CircuitBreaker<P> {
+    provider: P,
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl<P: IocProvider> CircuitBreaker<P> {
+    /// open after 5 consecutive failures for 30 seconds
+    pub fn new(provider: P) -> Self {
+        CircuitBreaker {
+            provider,
+            threshold: 5,
+            cooldown: Duration::from_secs(30),
+            state: Mutex::new(State {
+                failures: 0,
+                opened_at: None,
+                probing: false,
+            }),
+        }
+    }
+
+    /// number of consecutive failures to open the circuit
+    pub fn with_threshold(mut self, threshold: u32) -> Self {
+        self.threshold = threshold.max(1);
+        self
+    }
+
+    /// duration to short-circuit calls before a probe
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.provider
+    }
+
+    /// current state of circuit
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap();
+        match state.opened_at {
+            None => CircuitState::Closed,
+            Some(_) if state.probing => CircuitState::HalfOpen,
+            Some(t) if t.elapsed() >= self.cooldown => CircuitState::HalfOpen,
+            Some(_) => CircuitState::Open,
+        }
+    }
+
+    /// close the circuit (forget failures)
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = 0;
+        state.opened_at = None;
+        state.probing = false;
+    }
+
+    /// permission to call the provider (`true` if the call is a probe)
+    fn acquire(&self) -> GenericResult<bool> {
+        let mut state = self.state.lock().unwrap();
+        let opened_at = match state.opened_at {
+            None => return Ok(false),
+            Some(t) => t,
+        };
+        let elapsed = opened_at.elapsed();
+        if state.probing || elapsed < self.cooldown {
+            let retry_after = self.cooldown.checked_sub(elapsed).unwrap_or_default();
+            return Err(ProviderError::CircuitOpen {
+                provider: self.provider.name().to_owned(),
+                retry_after,
+            }
+            .into());
+        }
+        state.probing = true;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(provider = self.provider.name(), "circuit half-open");
+        Ok(true)
+    }
+
+    /// update state by the result of a call
+    fn record<T>(&self, probe: bool, result: &GenericResult<T>) {
+        let mut state = self.state.lock().unwrap();
+        if probe {
+            state.probing = false;
+        }
+        match result {
+            Err(e) if is_failure(e) => {
+                state.failures = state.failures.saturating_add(1);
+                if probe || (state.opened_at.is_none() && state.failures >= self.threshold) {
+                    state.opened_at = Some(Instant::now());
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        provider = self.provider.name(),
+                        failures = state.failures,
+                        "circuit open"
+                    );
+                }
+            }
+            _ => {
+                #[cfg(feature = "tracing")]
+                {
+                    if state.opened_at.is_some() {
+                        tracing::info!(provider = self.provider.name(), "circuit closed");
+                    }
+                }
+                state.failures = 0;
+                state.opened_at = None;
+            }
+        }
+    }
+
+    /// call through the circuit
+    fn call<T>(&self, f: impl FnOnce(&P) -> GenericResult<T>) -> GenericResult<T> {
+        let probe = self.acquire()?;
+        let result = f(&self.provider);
+        self.record(probe, &result);
+        result
+    }
+}
+
+/// the error is the fault of provider?
+fn is_failure(e: &failure::Error) -> bool {
+    if e.downcast_ref::<ProviderError>().is_some() {
+        return false;
+    }
+    !matches!(
+        classify(e),
+        Some(Error::Parse { .. }) | Some(Error::NotFound { .. })
+    )
+}
+
+impl<P: IocProvider> IocProvider for CircuitBreaker<P> {
+    fn name(&self) -> &str {
+        self.provider.name()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.provider.capabilities()
+    }
+
+    fn lookup(&self, ioc: &Ioc) -> GenericResult<ProviderReport> {
+        self.call(|p| p.lookup(ioc))
+    }
+
+    fn submit(&self, ioc: &Ioc) -> GenericResult<()> {
+        self.call(|p| p.submit(ioc))
+    }
+
+    fn audience(&self) -> Audience {
+        self.provider.audience()
+    }
+}
@@ -0,0 +1,216 @@
+//! looking up many indicators on multiple providers concurrently
+//!
+//! Each pair of an indicator and a provider which can lookup it is a task, and at most
+//! `concurrency` tasks run at a time (rate limits of providers are kept by their own limiter).
+//! Wrap providers with `iocutil::breaker::CircuitBreaker` so that a dead one fails fast instead of
+//! stalling the batch.
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::ThreadPoolBuilder;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::ioc::Ioc;
+use crate::provider::{IocProvider, ProviderReport, Verdict};
+use crate::GenericResult;
+
+/// progress of bulk lookup (given to callback on every completed lookup)
+#[derive(Debug)]
+pub struct Progress<'a> {
+    /// number of completed lookups
+    pub done: usize,
+    /// number of all lookups
+    pub total: usize,
+    pub ioc: &'a Ioc,
+    pub provider: &'a str,
+    pub succeeded: bool,
+}
+
+/// reports and errors about an indicator
+#[derive(Debug)]
+pub struct BulkResult {
+    pub ioc: Ioc,
+    pub reports: Vec<ProviderReport>,
+    /// name of provider and its error
+    pub errors: Vec<(String, failure::Error)>,
+}
+
+impl BulkResult {
+    /// most severe verdict of providers
+    pub fn verdict(&self) -> Verdict {
+        self.reports
+            .iter()
+            .map(|x| x.verdict)
+            .max()
+            .unwrap_or(Verdict::Unknown)
+    }
+
+    /// found by any provider?
+    pub fn found(&self) -> bool {
+        self.reports.iter().any(|x| x.found)
+    }
+}
+
+type ProgressCallback<'a> = Box<dyn Fn(&Progress<'_>) + Sync + 'a>;
+
+/// bulk lookup executor
+///
+/// # Example
+///
+/// ```
+/// use iocutil::bulk::BulkLookup;
+/// use iocutil::prelude::*;
+/// use iocutil::provider::Capabilities;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// struct Blocklist;
+///
+/// impl IocProvider for Blocklist {
+///     fn name(&self) -> &str {
+///         "blocklist"
+///     }
+///
+///     fn capabilities(&self) -> Capabilities {
+///         Capabilities::lookup_only(vec![IocKind::Domain])
+///     }
+///
+///     fn lookup(&self, ioc: &Ioc) -> Result<ProviderReport, failure::Error> {
+///         match ioc.value().as_str() {
+///             "evil.example.com" => Ok(ProviderReport::new(self.name(), ioc.clone(), Verdict::Malicious)),
+///             "broken.example.com" => Err(failure::err_msg("timeout")),
+///             _ => Ok(ProviderReport::not_found(self.name(), ioc.clone())),
+///         }
+///     }
+/// }
+///
+/// let completed = AtomicUsize::new(0);
+/// let results = BulkLookup::new()
+///     .provider(&Blocklist)
+///     .concurrency(4)
+///     .on_progress(|p| {
+///         completed.fetch_add(1, Ordering::SeqCst);
+///         assert!(p.done <= p.total);
+///     })
+///     .run(
+///         vec!["evil.example.com", "good.example.com", "broken.example.com", "192.0.2.1"]
+///             .into_iter()
+///             .map(|x| Ioc::new(x).unwrap()),
+///     )
+///     .unwrap();
+///
+/// assert_eq!(results.len(), 4);
+/// assert_eq!(results[0].verdict(), Verdict::Malicious);
+/// assert!(!results[1].found());
+/// assert_eq!(results[2].errors.len(), 1);
+/// // no provider can lookup IP addresses
+/// assert!(results[3].reports.is_empty() && results[3].errors.is_empty());
+/// assert_eq!(completed.load(Ordering::SeqCst), 3);
+/// ```
+pub struct BulkLookup<'a> {
+    providers: Vec<&'a (dyn IocProvider + Sync)>,
+    concurrency: usize,
+    progress: Option<ProgressCallback<'a>>,
+}
+
+impl<'a> Default for BulkLookup<'a> {
+    fn default() -> Self {
+        BulkLookup::new()
+    }
+}
+
+impl<'a> BulkLookup<'a> {
+    /// new executor without providers (4 lookups at a time)
+    pub fn new() -> Self {
+        BulkLookup {
+            providers: vec![],
+            concurrency: 4,
+            progress: None,
+        }
+    }
+
+    /// add a provider
+    pub fn provider(mut self, provider: &'a (dyn IocProvider + Sync)) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// number of lookups at a time
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// callback on every completed lookup
+    pub fn on_progress(mut self, callback: impl Fn(&Progress<'_>) + Sync + 'a) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// lookup indicators (duplicated ones, including equivalent URLs, are looked up once) on providers which can lookup them
+    /// results are in order of indicators.
+    pub fn run(
+        &self,
+        iocs: impl IntoIterator<Item = impl Into<Ioc>>,
+    ) -> GenericResult<Vec<BulkResult>> {
+        let mut seen = HashSet::new();
+        let unique: Vec<Ioc> = iocs
+            .into_iter()
+            .map(|x| x.into())
+            .filter(|x| seen.insert(x.normalized()))
+            .collect();
+
+        let tasks: Vec<(usize, &(dyn IocProvider + Sync))> = unique
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, ioc)| {
+                self.providers
+                    .iter()
+                    .filter(move |p| p.capabilities().can_lookup(ioc.kind()))
+                    .map(move |p| (idx, *p))
+            })
+            .collect();
+
+        let total = tasks.len();
+        let done = AtomicUsize::new(0);
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(self.concurrency)
+            .build()?;
+
+        let outcomes: Vec<_> = pool.install(|| {
+            tasks
+                .into_par_iter()
+                .map(|(idx, provider)| {
+                    let ioc = &unique[idx];
+                    let result = provider.lookup(ioc);
+                    if let Some(callback) = &self.progress {
+                        callback(&Progress {
+                            done: done.fetch_add(1, Ordering::SeqCst) + 1,
+                            total,
+                            ioc,
+                            provider: provider.name(),
+                            succeeded: result.is_ok(),
+                        });
+                    }
+                    (idx, provider.name().to_owned(), result)
+                })
+                .collect()
+        });
+
+        let mut results: Vec<BulkResult> = unique
+            .into_iter()
+            .map(|ioc| BulkResult {
+                ioc,
+                reports: vec![],
+                errors: vec![],
+            })
+            .collect();
+        for (idx, name, result) in outcomes {
+            match result {
+                Ok(report) => results[idx].reports.push(report),
+                Err(e) => results[idx].errors.push((name, e)),
+            }
+        }
+
+        Ok(results)
+    }
+}
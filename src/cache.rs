@@ -0,0 +1,196 @@
+//! disk-backed (SQLite) cache of API responses with TTL
+//!
+//! Responses are keyed by provider, endpoint and indicator, so overlapping lookups
+//! (e.g. the same hash appearing in several reports) are served locally within the TTL.
+//! Clones of a `ResponseCache` share one database connection.
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::metrics;
+use crate::singleflight::SingleFlight;
+use crate::GenericResult;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS responses (
+    provider TEXT NOT NULL,
+    endpoint TEXT NOT NULL,
+    key TEXT NOT NULL,
+    body TEXT NOT NULL,
+    stored_at INTEGER NOT NULL,
+    PRIMARY KEY (provider, endpoint, key)
+);
+";
+
+/// cache of API responses (clones share the database)
+#[derive(Clone)]
+pub struct ResponseCache {
+    conn: Arc<Mutex<Connection>>,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    fn with_connection(conn: Connection, ttl: Duration) -> GenericResult<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(ResponseCache {
+            conn: Arc::new(Mutex::new(conn)),
+            ttl,
+        })
+    }
+
+    /// open (or create) cache database file
+    pub fn open(path: impl AsRef<Path>, ttl: Duration) -> GenericResult<Self> {
+        ResponseCache::with_connection(Connection::open(path)?, ttl)
+    }
+
+    /// cache on memory (not persisted)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::cache::ResponseCache;
+    /// use std::time::Duration;
+    ///
+    /// let cache = ResponseCache::in_memory(Duration::from_secs(3600)).unwrap();
+    /// cache.put("virustotal", "file/report", "d41d8cd98f00b204e9800998ecf8427e", "{}").unwrap();
+    /// assert_eq!(
+    ///     cache.get("virustotal", "file/report", "d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+    ///     Some("{}".to_owned())
+    /// );
+    /// assert_eq!(cache.get("virustotal", "file/report", "other").unwrap(), None);
+    ///
+    /// // expired responses are not served
+    /// let cache = ResponseCache::in_memory(Duration::from_secs(0)).unwrap();
+    /// cache.put("virustotal", "file/report", "d41d8cd98f00b204e9800998ecf8427e", "{}").unwrap();
+    /// assert_eq!(cache.get("virustotal", "file/report", "d41d8cd98f00b204e9800998ecf8427e").unwrap(), None);
+    /// ```
+    pub fn in_memory(ttl: Duration) -> GenericResult<Self> {
+        ResponseCache::with_connection(Connection::open_in_memory()?, ttl)
+    }
+
+    /// time to live of cached responses
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn expires_before(&self) -> i64 {
+        Utc::now().timestamp_millis() - self.ttl.as_millis() as i64
+    }
+
+    /// cached response (`None` if not cached or expired)
+    pub fn get(
+        &self,
+        provider: impl AsRef<str>,
+        endpoint: impl AsRef<str>,
+        key: impl AsRef<str>,
+    ) -> GenericResult<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT body FROM responses
+                 WHERE provider = ?1 AND endpoint = ?2 AND key = ?3 AND stored_at > ?4",
+                params![
+                    provider.as_ref(),
+                    endpoint.as_ref(),
+                    key.as_ref(),
+                    self.expires_before()
+                ],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// store a response
+    pub fn put(
+        &self,
+        provider: impl AsRef<str>,
+        endpoint: impl AsRef<str>,
+        key: impl AsRef<str>,
+        body: impl AsRef<str>,
+    ) -> GenericResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO responses (provider, endpoint, key, body, stored_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                provider.as_ref(),
+                endpoint.as_ref(),
+                key.as_ref(),
+                body.as_ref(),
+                Utc::now().timestamp_millis()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// remove expired responses, returns number of removed ones
+    pub fn purge_expired(&self) -> GenericResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.execute(
+            "DELETE FROM responses WHERE stored_at <= ?1",
+            params![self.expires_before()],
+        )?)
+    }
+
+    /// remove all responses
+    pub fn clear(&self) -> GenericResult<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM responses", [])?;
+        Ok(())
+    }
+}
+
+/// serve response from cache or fetch (and store) it
+/// (concurrent lookups of the same resource are coalesced into a fetch)
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        name = "lookup",
+        skip_all,
+        fields(
+            provider = provider,
+            endpoint = endpoint,
+            kind = %crate::validate::kind_of(key).map_or("other".into(), |x| x.to_string()),
+            cache_hit = tracing::field::Empty,
+        )
+    )
+)]
+pub(crate) async fn cached<F>(
+    cache: &Option<ResponseCache>,
+    flights: &SingleFlight,
+    provider: &str,
+    endpoint: &str,
+    key: &str,
+    fetch: F,
+) -> GenericResult<String>
+where
+    F: Future<Output = GenericResult<String>>,
+{
+    let flight = format!("{}/{}/{}", provider, endpoint, key);
+    let cache = match cache {
+        Some(x) => x,
+        None => return flights.run(flight, fetch).await,
+    };
+    let hit = cache.get(provider, endpoint, key)?;
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("cache_hit", hit.is_some());
+    if let Some(body) = hit {
+        metrics::report(|m| m.cache_hit(provider, endpoint));
+        return Ok(body);
+    }
+    metrics::report(|m| m.cache_miss(provider, endpoint));
+    flights
+        .run(flight, async {
+            let body = fetch.await?;
+            cache.put(provider, endpoint, key, &body)?;
+            Ok(body)
+        })
+        .await
+}
@@ -0,0 +1,80 @@
+//! C API (`capi` feature, declared in `include/iocutil.h`)
+//!
+//! Strings are NUL-terminated UTF-8. Returned strings are owned by the caller and must be
+//! released by `iocutil_free_string`. `NULL` is returned on invalid input.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, UnwindSafe};
+use std::ptr;
+
+use crate::ioc::{self, Ioc};
+
+/// run `f` with the string, returning its result as C string (`NULL` on invalid input or panic)
+unsafe fn with_str(
+    s: *const c_char,
+    f: impl FnOnce(&str) -> Option<String> + UnwindSafe,
+) -> *mut c_char {
+    if s.is_null() {
+        return ptr::null_mut();
+    }
+    let s = match CStr::from_ptr(s).to_str() {
+        Ok(s) => s,
+        Err(_) => return ptr::null_mut(),
+    };
+    catch_unwind(|| f(s))
+        .ok()
+        .flatten()
+        .and_then(|x| CString::new(x).ok())
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+/// unique indicators in (possibly defanged) text, a line (`kind` TAB `value`) for each
+///
+/// # Safety
+///
+/// `text` must be `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn iocutil_scrape_iocs(text: *const c_char) -> *mut c_char {
+    with_str(text, |text| {
+        let iocs: Vec<Ioc> = Ioc::find(text);
+        Some(
+            iocs.iter()
+                .map(|x| format!("{}\t{}\n", x.kind(), x))
+                .collect(),
+        )
+    })
+}
+
+/// defanged indicator (`NULL` if it is not an indicator)
+///
+/// # Safety
+///
+/// `value` must be `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn iocutil_defang(value: *const c_char) -> *mut c_char {
+    with_str(value, |value| Ioc::new(value).ok().map(|x| x.defanged()))
+}
+
+/// defanged text restored into original form
+///
+/// # Safety
+///
+/// `text` must be `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn iocutil_refang(text: *const c_char) -> *mut c_char {
+    with_str(text, |text| Some(ioc::refang(text)))
+}
+
+/// release a string returned by the API (`NULL` is ignored)
+///
+/// # Safety
+///
+/// `s` must be `NULL` or a string returned by the API, which is not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn iocutil_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
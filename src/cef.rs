@@ -0,0 +1,228 @@
+//! CEF and LEEF formatting of indicators (for shipping them to SIEMs over syslog)
+
+use derive_builder::Builder;
+
+use crate::allowlist::suppress;
+use crate::ioc::{Indicator, Ioc};
+use crate::ttl::unexpired;
+
+/// options of formatted events
+#[derive(Builder, Debug, Clone)]
+pub struct EventFormatOptions {
+    #[builder(setter(into), default = "\"iocutil.rs\".to_owned()")]
+    vendor: String,
+    #[builder(setter(into), default = "\"iocutil\".to_owned()")]
+    product: String,
+    #[builder(setter(into), default = "env!(\"CARGO_PKG_VERSION\").to_owned()")]
+    version: String,
+    /// severity (0-10)
+    #[builder(default = "5")]
+    severity: u8,
+    /// format expired indicators too (see `iocutil::ttl`, by `to_cef_lines` and `to_leef_lines`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for EventFormatOptions {
+    fn default() -> Self {
+        EventFormatOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// escape text in header fields of CEF / LEEF
+fn header(text: impl AsRef<str>) -> String {
+    text.as_ref().replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// CEF extension key of an indicator
+fn cef_key(ioc: &Ioc) -> &'static str {
+    match ioc {
+        Ioc::Hash(_) => "fileHash",
+        Ioc::Domain(_) => "dhost",
+        Ioc::Url(_) => "request",
+        Ioc::IpAddress(_) => "dst",
+        Ioc::EmailAddress(_) => "suser",
+    }
+}
+
+/// LEEF attribute key of an indicator
+fn leef_key(ioc: &Ioc) -> &'static str {
+    match ioc {
+        Ioc::Hash(_) => "fileHash",
+        Ioc::Domain(_) => "domain",
+        Ioc::Url(_) => "url",
+        Ioc::IpAddress(_) => "dst",
+        Ioc::EmailAddress(_) => "email",
+    }
+}
+
+/// format an indicator as CEF line (as is, see `to_cef_lines` to export indicators)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::cef::{to_cef, EventFormatOptionsBuilder};
+///
+/// let mut i = Indicator::new(Ioc::new("http://evil.example.com/a=b").unwrap());
+/// i.meta.source = Some("feed|x".to_owned());
+///
+/// let options = EventFormatOptionsBuilder::default().version("1.0").build().unwrap();
+/// assert_eq!(
+///     to_cef(&i, &options),
+///     r"CEF:0|iocutil.rs|iocutil|1.0|url|IOC url|5|request=http://evil.example.com/a\=b cs1Label=source cs1=feed|x"
+/// );
+/// ```
+pub fn to_cef(indicator: &Indicator, options: &EventFormatOptions) -> String {
+    let escape = |x: &str| {
+        x.replace('\\', "\\\\")
+            .replace('=', "\\=")
+            .replace('\r', "\\r")
+            .replace('\n', "\\n")
+    };
+    let meta = &indicator.meta;
+    let kind = indicator.ioc.kind().to_string();
+
+    let mut ext = vec![format!(
+        "{}={}",
+        cef_key(&indicator.ioc),
+        escape(&indicator.ioc.value())
+    )];
+    if let Some(s) = &meta.source {
+        ext.push(format!("cs1Label=source cs1={}", escape(s)));
+    }
+    if !meta.tags.is_empty() {
+        ext.push(format!(
+            "cs2Label=tags cs2={}",
+            escape(&meta.tags.join(","))
+        ));
+    }
+    if let Some(d) = &meta.description {
+        ext.push(format!("msg={}", escape(d)));
+    }
+    if let Some(d) = meta.first_seen {
+        ext.push(format!(
+            "deviceCustomDate1Label=firstSeen deviceCustomDate1={}",
+            d.timestamp_millis()
+        ));
+    }
+    if let Some(d) = meta.last_seen {
+        ext.push(format!(
+            "deviceCustomDate2Label=lastSeen deviceCustomDate2={}",
+            d.timestamp_millis()
+        ));
+    }
+    if let Some(c) = meta.confidence {
+        ext.push(format!("cfp1Label=confidence cfp1={}", c.score()));
+    }
+
+    format!(
+        "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+        header(&options.vendor),
+        header(&options.product),
+        header(&options.version),
+        kind,
+        header(format!("IOC {}", kind)),
+        options.severity.min(10),
+        ext.join(" ")
+    )
+}
+
+/// format an indicator as LEEF 1.0 line (attributes are separated by tab, see `to_leef_lines` to
+/// export indicators)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::cef::{to_leef, EventFormatOptionsBuilder};
+///
+/// let mut i = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// i.meta.tags = vec!["c2".to_owned(), "botnet".to_owned()];
+///
+/// let options = EventFormatOptionsBuilder::default().version("1.0").build().unwrap();
+/// assert_eq!(
+///     to_leef(&i, &options),
+///     "LEEF:1.0|iocutil.rs|iocutil|1.0|ip|cat=ip\tsev=5\tdst=192.0.2.1\ttags=c2,botnet"
+/// );
+/// ```
+pub fn to_leef(indicator: &Indicator, options: &EventFormatOptions) -> String {
+    let escape = |x: &str| x.replace(&['\t', '\r', '\n'][..], " ");
+    let meta = &indicator.meta;
+    let kind = indicator.ioc.kind().to_string();
+
+    let mut attrs = vec![
+        format!("cat={}", kind),
+        format!("sev={}", options.severity.min(10)),
+        format!(
+            "{}={}",
+            leef_key(&indicator.ioc),
+            escape(&indicator.ioc.value())
+        ),
+    ];
+    if let Some(s) = &meta.source {
+        attrs.push(format!("source={}", escape(s)));
+    }
+    if !meta.tags.is_empty() {
+        attrs.push(format!("tags={}", escape(&meta.tags.join(","))));
+    }
+    if let Some(d) = &meta.description {
+        attrs.push(format!("description={}", escape(d)));
+    }
+    if let Some(d) = meta.first_seen {
+        attrs.push(format!("firstSeen={}", d.timestamp_millis()));
+    }
+    if let Some(d) = meta.last_seen {
+        attrs.push(format!("lastSeen={}", d.timestamp_millis()));
+    }
+    if let Some(c) = meta.confidence {
+        attrs.push(format!("confidence={}", c.score()));
+    }
+
+    format!(
+        "LEEF:1.0|{}|{}|{}|{}|{}",
+        header(&options.vendor),
+        header(&options.product),
+        header(&options.version),
+        kind,
+        attrs.join("\t")
+    )
+}
+
+/// CEF lines of indicators (allowed by the export allowlist are suppressed, and expired ones are
+/// excluded unless `include_expired`)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::cef::to_cef_lines;
+///
+/// let mut expired = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// expired.meta.valid_until = Some(day!(2019, 11, 1).unwrap().into());
+///
+/// let lines = to_cef_lines(vec![expired, Ioc::new("evil.example.com").unwrap().into()], &Default::default());
+/// assert_eq!(lines.len(), 1);
+/// assert!(lines[0].ends_with("|domain|IOC domain|5|dhost=evil.example.com"));
+/// ```
+pub fn to_cef_lines(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &EventFormatOptions,
+) -> Vec<String> {
+    suppress(unexpired(indicators, options.include_expired))
+        .iter()
+        .map(|x| to_cef(x, options))
+        .collect()
+}
+
+/// LEEF lines of indicators (allowed by the export allowlist are suppressed, and expired ones are
+/// excluded unless `include_expired`)
+pub fn to_leef_lines(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &EventFormatOptions,
+) -> Vec<String> {
+    suppress(unexpired(indicators, options.include_expired))
+        .iter()
+        .map(|x| to_leef(x, options))
+        .collect()
+}
@@ -0,0 +1,391 @@
+//! sets of IP networks with O(log n) lookups, for large CIDR feeds (e.g. ranges of cloud
+//! providers and bulletproof hosters)
+//!
+//! Networks are labeled (e.g. `AWS`), and flattened into sorted disjoint ranges at build time, so
+//! that the most specific network containing an address is found by binary search. A `CidrSet`
+//! is an `Allowlist`, and labels indicators by `tag`.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::allowlist::{Allowlist, AllowlistError, Warninglist, WarninglistType};
+use crate::ioc::{host_of_url, Indicator, Ioc};
+use crate::GenericResult;
+
+/// an IP network
+#[derive(Debug, Clone)]
+pub(crate) struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(2, '/');
+        let network: IpAddr = parts.next()?.trim().parse().ok()?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            Some(p) => p.trim().parse().ok().filter(|x| *x <= max)?,
+            None => max,
+        };
+        Some(Cidr { network, prefix })
+    }
+
+    pub(crate) fn contains(&self, ip: &IpAddr) -> bool {
+        let (a, b, bits) = match (self.network, ip) {
+            (IpAddr::V4(n), IpAddr::V4(i)) => {
+                (u128::from(u32::from(n)), u128::from(u32::from(*i)), 32)
+            }
+            (IpAddr::V6(n), IpAddr::V6(i)) => (u128::from(n), u128::from(*i), 128),
+            _ => return false,
+        };
+        let shift = bits - u32::from(self.prefix);
+        shift >= bits || (a >> shift) == (b >> shift)
+    }
+
+    /// first and last addresses (as integers)
+    fn range(&self) -> (u128, u128) {
+        let (n, bits) = match self.network {
+            IpAddr::V4(n) => (u128::from(u32::from(n)), 32),
+            IpAddr::V6(n) => (u128::from(n), 128),
+        };
+        let host_bits = bits - u32::from(self.prefix);
+        let hosts = if host_bits == 128 {
+            u128::MAX
+        } else {
+            (1u128 << host_bits) - 1
+        };
+        (n & !hosts, n | hosts)
+    }
+}
+
+/// integer of the address
+fn number(ip: &IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(x) => u128::from(u32::from(*x)),
+        IpAddr::V6(x) => u128::from(*x),
+    }
+}
+
+/// disjoint range with the label of the most specific network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    start: u128,
+    end: u128,
+    label: usize,
+}
+
+/// sorted disjoint segments of the ranges (inner ranges take precedence, and later ones for the same range)
+fn flatten(mut ranges: Vec<(u128, u128, usize)>) -> Vec<Segment> {
+    // outer ranges first for the same start
+    ranges.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+    let mut segments: Vec<Segment> = vec![];
+    let mut push = |start: u128, end: u128, label: usize| match segments.last_mut() {
+        Some(last) if last.label == label && last.end.checked_add(1) == Some(start) => {
+            last.end = end
+        }
+        _ => segments.push(Segment { start, end, label }),
+    };
+
+    // open ranges, and the first address not emitted yet (`None` past the last address)
+    let mut stack: Vec<(u128, u128, usize)> = vec![];
+    let mut cursor: Option<u128> = Some(0);
+    for (start, end, label) in ranges {
+        while let Some(&(_, top_end, top_label)) = stack.last() {
+            if top_end >= start {
+                break;
+            }
+            if let Some(c) = cursor.filter(|c| *c <= top_end) {
+                push(c, top_end, top_label);
+            }
+            cursor = cursor.map(|c| c.max(top_end + 1));
+            stack.pop();
+        }
+        if let (Some(&(_, _, top_label)), Some(c)) = (stack.last(), cursor) {
+            if c < start {
+                push(c, start - 1, top_label);
+            }
+        }
+        cursor = Some(cursor.map_or(start, |c| c.max(start)));
+        // ranges are nested or disjoint, so the same range replaces the open one
+        match stack.last_mut() {
+            Some(top) if top.0 == start && top.1 == end => top.2 = label,
+            _ => stack.push((start, end, label)),
+        }
+    }
+    while let Some((_, top_end, top_label)) = stack.pop() {
+        if let Some(c) = cursor.filter(|c| *c <= top_end) {
+            push(c, top_end, top_label);
+            cursor = top_end.checked_add(1);
+        }
+    }
+    segments
+}
+
+/// labeled IP networks with O(log n) lookups
+///
+/// # Example
+///
+/// ```
+/// use iocutil::cidrset::CidrSet;
+///
+/// let set = CidrSet::parse("hosting", "
+///     198.51.100.0/24 ExampleCloud  # network and label
+///     198.51.100.128/25 ExampleCloud us-east
+///     203.0.113.0/24 BulletproofHoster
+///     2001:db8::/32 ExampleCloud
+/// ").unwrap();
+///
+/// assert_eq!(set.label_of("198.51.100.7".parse().unwrap()), Some("ExampleCloud"));
+/// assert_eq!(set.label_of("198.51.100.200".parse().unwrap()), Some("ExampleCloud us-east"));
+/// assert_eq!(set.label_of("2001:db8::1".parse().unwrap()), Some("ExampleCloud"));
+/// assert!(set.contains("203.0.113.255".parse().unwrap()));
+/// assert!(!set.contains("192.0.2.1".parse().unwrap()));
+/// assert_eq!(set.len(), 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CidrSet {
+    name: String,
+    labels: Vec<String>,
+    v4: Vec<Segment>,
+    v6: Vec<Segment>,
+    len: usize,
+}
+
+impl CidrSet {
+    /// set of the networks (`CIDR` or IP address) and their labels, in order of precedence
+    /// for the same network
+    pub fn from_networks(
+        name: impl Into<String>,
+        networks: impl IntoIterator<Item = (impl AsRef<str>, impl Into<String>)>,
+    ) -> GenericResult<Self> {
+        let name = name.into();
+        let mut labels: Vec<String> = vec![];
+        let mut indices: HashMap<String, usize> = HashMap::new();
+        let (mut v4, mut v6) = (vec![], vec![]);
+        for (network, label) in networks {
+            let network = network.as_ref();
+            let cidr = Cidr::parse(network)
+                .ok_or_else(|| AllowlistError::InvalidCidr(name.clone(), network.to_owned()))?;
+            let label = label.into();
+            let index = *indices.entry(label).or_insert_with_key(|x| {
+                labels.push(x.clone());
+                labels.len() - 1
+            });
+            let (start, end) = cidr.range();
+            match cidr.network {
+                IpAddr::V4(_) => v4.push((start, end, index)),
+                IpAddr::V6(_) => v6.push((start, end, index)),
+            }
+        }
+        Ok(CidrSet {
+            name,
+            labels,
+            len: v4.len() + v6.len(),
+            v4: flatten(v4),
+            v6: flatten(v6),
+        })
+    }
+
+    /// parse list of networks, one per line with optional label (the name if omitted),
+    /// `#` for comments
+    pub fn parse(name: impl Into<String>, text: impl AsRef<str>) -> GenericResult<Self> {
+        let name = name.into();
+        let networks: Vec<(String, String)> = text
+            .as_ref()
+            .lines()
+            .map(|x| x.split('#').next().unwrap_or_default().trim())
+            .filter(|x| !x.is_empty())
+            .map(|x| match x.split_once(char::is_whitespace) {
+                Some((network, label)) => (network.to_owned(), label.trim().to_owned()),
+                None => (x.to_owned(), name.clone()),
+            })
+            .collect();
+        CidrSet::from_networks(name, networks)
+    }
+
+    /// read a list file (named by the path)
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        let path = path.as_ref();
+        CidrSet::parse(path.display().to_string(), std::fs::read_to_string(path)?)
+    }
+
+    /// networks of a MISP warninglist of type `cidr` (labeled by the name of list)
+    pub fn from_warninglist(warninglist: &Warninglist) -> GenericResult<Self> {
+        let list: &[String] = match warninglist.list_type {
+            WarninglistType::Cidr => &warninglist.list,
+            _ => &[],
+        };
+        CidrSet::from_networks(
+            warninglist.name.clone(),
+            list.iter().map(|x| (x, warninglist.name.clone())),
+        )
+    }
+
+    /// networks of [AWS IP address ranges](https://ip-ranges.amazonaws.com/ip-ranges.json)
+    /// labeled by region (e.g. `AWS us-east-1`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::cidrset::CidrSet;
+    ///
+    /// let set = CidrSet::from_aws_ip_ranges(r#"{
+    ///     "prefixes": [{"ip_prefix": "198.51.100.0/24", "region": "us-east-1", "service": "EC2"}],
+    ///     "ipv6_prefixes": [{"ipv6_prefix": "2001:db8::/32", "region": "eu-west-1", "service": "AMAZON"}]
+    /// }"#).unwrap();
+    /// assert_eq!(set.label_of("198.51.100.1".parse().unwrap()), Some("AWS us-east-1"));
+    /// assert_eq!(set.label_of("2001:db8::1".parse().unwrap()), Some("AWS eu-west-1"));
+    /// ```
+    pub fn from_aws_ip_ranges(json: impl AsRef<str>) -> GenericResult<Self> {
+        #[derive(Deserialize)]
+        struct Prefix {
+            #[serde(alias = "ipv6_prefix")]
+            ip_prefix: String,
+            region: String,
+        }
+        #[derive(Deserialize)]
+        struct Ranges {
+            #[serde(default)]
+            prefixes: Vec<Prefix>,
+            #[serde(default)]
+            ipv6_prefixes: Vec<Prefix>,
+        }
+        let ranges: Ranges = serde_json::from_str(json.as_ref())?;
+        CidrSet::from_networks(
+            "AWS",
+            ranges
+                .prefixes
+                .iter()
+                .chain(&ranges.ipv6_prefixes)
+                .map(|x| (&x.ip_prefix, format!("AWS {}", x.region))),
+        )
+    }
+
+    /// label of the most specific network containing the address
+    pub fn label_of(&self, ip: IpAddr) -> Option<&str> {
+        let segments = match ip {
+            IpAddr::V4(_) => &self.v4,
+            IpAddr::V6(_) => &self.v6,
+        };
+        let n = number(&ip);
+        let i = segments.partition_point(|x| x.start <= n);
+        let segment = segments.get(i.checked_sub(1)?)?;
+        if segment.end >= n {
+            Some(&self.labels[segment.label])
+        } else {
+            None
+        }
+    }
+
+    /// any network contains the address?
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.label_of(ip).is_some()
+    }
+
+    /// label of the network containing the IP address or host of URL of the indicator
+    pub fn label_of_indicator(&self, indicator: &Indicator) -> Option<&str> {
+        match &indicator.ioc {
+            Ioc::IpAddress(x) => self.label_of(*x),
+            Ioc::Url(x) => match host_of_url(x)? {
+                Ioc::IpAddress(ip) => self.label_of(ip),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// tag indicators in the networks by their labels, and return the number of them
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::cidrset::CidrSet;
+    /// use iocutil::ioc::{Indicator, Ioc};
+    ///
+    /// let set = CidrSet::parse("hosting", "198.51.100.0/24 hosted on ExampleCloud").unwrap();
+    /// let mut indicators = vec![
+    ///     Indicator::new(Ioc::new("http://198.51.100.7/gate.php").unwrap()),
+    ///     Indicator::new(Ioc::new("192.0.2.1").unwrap()),
+    /// ];
+    /// assert_eq!(set.tag(&mut indicators), 1);
+    /// assert_eq!(indicators[0].meta.tags, vec!["hosted on ExampleCloud"]);
+    /// ```
+    pub fn tag(&self, indicators: &mut [Indicator]) -> usize {
+        let mut count = 0;
+        for indicator in indicators.iter_mut() {
+            if let Some(label) = self.label_of_indicator(indicator) {
+                let tags = &mut indicator.meta.tags;
+                if !tags.iter().any(|x| x == label) {
+                    tags.push(label.to_owned());
+                }
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// number of networks
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// no network?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Allowlist for CidrSet {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        let ip = match ioc {
+            Ioc::IpAddress(x) => *x,
+            Ioc::Url(x) => match host_of_url(x)? {
+                Ioc::IpAddress(ip) => ip,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if self.contains(ip) {
+            Some(&self.name)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_works() {
+        let set = CidrSet::from_networks(
+            "test",
+            vec![
+                ("0.0.0.0/0", "any"),
+                ("10.0.0.0/8", "a"),
+                ("10.1.0.0/16", "b"),
+                ("10.1.2.0/24", "c"),
+                ("10.1.0.0/16", "d"),
+                ("10.2.0.0/16", "a"),
+                ("::/0", "v6"),
+            ],
+        )
+        .unwrap();
+        let label = |x: &str| set.label_of(x.parse().unwrap());
+        assert_eq!(label("0.0.0.0"), Some("any"));
+        assert_eq!(label("10.0.0.1"), Some("a"));
+        assert_eq!(label("10.1.1.1"), Some("d"));
+        assert_eq!(label("10.1.2.3"), Some("c"));
+        assert_eq!(label("10.1.3.0"), Some("d"));
+        assert_eq!(label("10.2.255.255"), Some("a"));
+        assert_eq!(label("11.0.0.0"), Some("any"));
+        assert_eq!(label("255.255.255.255"), Some("any"));
+        assert_eq!(label("ffff::1"), Some("v6"));
+        // adjacent segments of the same label are merged
+        assert_eq!(set.v4.len(), 7);
+    }
+}
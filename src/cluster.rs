@@ -0,0 +1,172 @@
+//! single-linkage clustering of samples by fuzzy hashes (ssdeep / TLSH), and servers by HTTP banners
+
+use crate::banner::HttpBanner;
+use crate::ssdeep::Ssdeep;
+use crate::tlsh::Tlsh;
+
+/// a cluster (labels are assigned from 0 in descending order of size)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cluster<T> {
+    pub label: usize,
+    pub members: Vec<T>,
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    // path compression
+    let mut i = i;
+    while parents[i] != root {
+        let next = parents[i];
+        parents[i] = root;
+        i = next;
+    }
+    root
+}
+
+/// group items transitively linked (a cluster may contain a pair not linked directly)
+///
+/// Items without any link form singleton clusters.
+/// Members keep the input order, and ties of cluster size are ordered by their first member.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::cluster::single_linkage;
+///
+/// let clusters = single_linkage(vec![1, 20, 2, 3, 21, 50], |a: &i32, b: &i32| (a - b).abs() <= 1);
+/// let members: Vec<_> = clusters.iter().map(|c| c.members.clone()).collect();
+/// assert_eq!(members, vec![vec![1, 2, 3], vec![20, 21], vec![50]]);
+/// assert_eq!(clusters[1].label, 1);
+/// ```
+pub fn single_linkage<T>(items: Vec<T>, linked: impl Fn(&T, &T) -> bool) -> Vec<Cluster<T>> {
+    let mut parents: Vec<usize> = (0..items.len()).collect();
+    for i in 0..items.len() {
+        for j in i + 1..items.len() {
+            let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+            if a != b && linked(&items[i], &items[j]) {
+                parents[b.max(a)] = a.min(b);
+            }
+        }
+    }
+
+    // roots are the smallest index in each cluster
+    let mut groups: Vec<(usize, Vec<T>)> = vec![];
+    let mut slot: Vec<Option<usize>> = vec![None; items.len()];
+    for (i, item) in items.into_iter().enumerate() {
+        let root = find(&mut parents, i);
+        match slot[root] {
+            Some(g) => groups[g].1.push(item),
+            None => {
+                slot[root] = Some(groups.len());
+                groups.push((root, vec![item]));
+            }
+        }
+    }
+    groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)));
+
+    groups
+        .into_iter()
+        .enumerate()
+        .map(|(label, (_, members))| Cluster { label, members })
+        .collect()
+}
+
+/// cluster ssdeep digests linked if their score is at least `min_score`
+///
+/// # Example
+///
+/// ```
+/// use iocutil::cluster::cluster_ssdeep;
+/// use iocutil::ssdeep::Ssdeep;
+///
+/// let text: String = (0..2000).map(|i| format!("line {} of a sample\n", i)).collect();
+/// let digests = vec![
+///     Ssdeep::of_bytes(&text),
+///     Ssdeep::of_bytes(b"Hello, World!"),
+///     Ssdeep::of_bytes(text.replace("line 1000 ", "line 9999 ")),
+/// ];
+///
+/// let clusters = cluster_ssdeep(digests.clone(), 80);
+/// assert_eq!(clusters.len(), 2);
+/// assert_eq!(clusters[0].members, vec![digests[0].clone(), digests[2].clone()]);
+/// ```
+pub fn cluster_ssdeep(
+    digests: impl IntoIterator<Item = Ssdeep>,
+    min_score: u8,
+) -> Vec<Cluster<Ssdeep>> {
+    single_linkage(digests.into_iter().collect(), |a, b| {
+        a.compare(b) >= min_score
+    })
+}
+
+/// cluster TLSH digests linked if their distance is at most `max_distance`
+///
+/// # Example
+///
+/// ```
+/// use iocutil::cluster::cluster_tlsh;
+/// use iocutil::tlsh::Tlsh;
+///
+/// let text: String = (0..200).map(|i| format!("line {} of a sample\n", i)).collect();
+/// let digests = vec![
+///     Tlsh::of_bytes(&text).unwrap(),
+///     Tlsh::of_bytes(text.replace("line 100 ", "line 999 ")).unwrap(),
+///     Tlsh::of_bytes((0..4000u32).map(|i| (i * 7919 % 251) as u8).collect::<Vec<_>>()).unwrap(),
+/// ];
+///
+/// let clusters = cluster_tlsh(digests, 50);
+/// assert_eq!(clusters.iter().map(|c| c.members.len()).collect::<Vec<_>>(), vec![2, 1]);
+/// ```
+pub fn cluster_tlsh(
+    digests: impl IntoIterator<Item = Tlsh>,
+    max_distance: u32,
+) -> Vec<Cluster<Tlsh>> {
+    single_linkage(digests.into_iter().collect(), |a, b| {
+        a.distance(b) <= max_distance
+    })
+}
+
+/// cluster HTTP banners linked if their bodies are identical, or their `Server` headers and titles are
+///
+/// # Example
+///
+/// ```
+/// use iocutil::banner::HttpBanner;
+/// use iocutil::cluster::cluster_banners;
+///
+/// let banner = |url: &str, server: &str, title: &str, body: &str| HttpBanner {
+///     url: url.to_owned(),
+///     redirects: vec![],
+///     status: 200,
+///     server: Some(server.to_owned()),
+///     headers: vec![("server".to_owned(), server.to_owned())],
+///     title: Some(title.to_owned()),
+///     body_sha256: Some(body.to_owned()),
+/// };
+/// let banners = vec![
+///     banner("https://a.example.com/", "nginx", "Login", "aa"),
+///     banner("https://b.example.net/", "Apache", "Welcome", "bb"),
+///     banner("https://c.example.org/", "nginx", "Login", "cc"),
+///     banner("https://d.example.org/", "Caddy", "It works", "bb"),
+/// ];
+///
+/// let clusters = cluster_banners(banners);
+/// let urls: Vec<Vec<&str>> = clusters
+///     .iter()
+///     .map(|c| c.members.iter().map(|b| b.url.as_str()).collect())
+///     .collect();
+/// assert_eq!(urls[0], vec!["https://a.example.com/", "https://c.example.org/"]);
+/// assert_eq!(urls[1], vec!["https://b.example.net/", "https://d.example.org/"]);
+/// ```
+pub fn cluster_banners(banners: impl IntoIterator<Item = HttpBanner>) -> Vec<Cluster<HttpBanner>> {
+    single_linkage(banners.into_iter().collect(), |a, b| {
+        let same_body = a.body_sha256.is_some() && a.body_sha256 == b.body_sha256;
+        let same_server = a.server.is_some()
+            && a.title.is_some()
+            && (&a.server, &a.title) == (&b.server, &b.title);
+        same_body || same_server
+    })
+}
@@ -0,0 +1,272 @@
+//! collection of indicators with set operations, per-kind views and merging of metadata
+//!
+//! Exporters, stores and allowlists take `impl IntoIterator<Item = impl Into<Indicator>>`, so
+//! collections are passed to them as is. Collections are serialized as arrays of objects in the
+//! schema of `iocutil::jsonl`.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+use crate::ioc::{Indicator, Ioc, IocKind};
+use crate::jsonl::Record;
+use crate::SampleHash;
+
+/// indicators unique by value (URLs by normalized ones, keeping the first form inserted)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::collection::IocCollection;
+///
+/// let mut c = IocCollection::new();
+/// let mut i = Indicator::new(Ioc::new("example.com").unwrap());
+/// i.meta.tags = vec!["phishing".to_owned()];
+/// assert!(c.insert(i));
+///
+/// // metadata of the same indicator is merged (by `Metadata::merge`)
+/// let mut i = Indicator::new(Ioc::new("EXAMPLE.com").unwrap());
+/// i.meta.tags = vec!["c2".to_owned()];
+/// assert!(!c.insert(i));
+/// assert!(c.insert(Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap()));
+///
+/// assert_eq!(c.len(), 2);
+/// let domain = c.get(&Ioc::new("example.com").unwrap()).unwrap();
+/// assert_eq!(domain.meta.tags, vec!["phishing", "c2"]);
+/// assert_eq!(c.hashes().count(), 1);
+/// assert_eq!(c.of_kind(IocKind::Domain).count(), 1);
+///
+/// // equivalent URLs are the same indicator
+/// assert!(c.insert(Ioc::new("http://Example.com:80/a/./b").unwrap()));
+/// assert!(!c.insert(Ioc::new("http://example.com/a/b").unwrap()));
+/// assert_eq!(c.len(), 3);
+/// let url = c.get(&Ioc::new("http://example.com/a/b").unwrap()).unwrap();
+/// assert_eq!(url.ioc.value(), "http://Example.com:80/a/./b");
+///
+/// assert!(c.remove(&Ioc::new("example.com").unwrap()).is_some());
+/// assert!(c.contains(&Ioc::new("http://example.com/a/b").unwrap()));
+/// assert!(c.contains(&Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap()));
+///
+/// let csv = iocutil::csv::to_csv(c, &Default::default());
+/// assert!(csv.contains("d41d8cd98f00b204e9800998ecf8427e"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IocCollection {
+    indicators: Vec<Indicator>,
+    /// positions of indicators by normalized values
+    index: HashMap<Ioc, usize>,
+}
+
+impl IocCollection {
+    /// empty collection
+    pub fn new() -> Self {
+        IocCollection::default()
+    }
+
+    /// add an indicator (returns true if it was not in the collection, otherwise metadata is
+    /// merged into stored one)
+    pub fn insert(&mut self, indicator: impl Into<Indicator>) -> bool {
+        let indicator = indicator.into();
+        match self.index.get(&indicator.ioc.normalized()) {
+            Some(&i) => {
+                let stored = &mut self.indicators[i];
+                stored.meta = std::mem::take(&mut stored.meta).merge(indicator.meta);
+                false
+            }
+            None => {
+                self.index
+                    .insert(indicator.ioc.normalized(), self.indicators.len());
+                self.indicators.push(indicator);
+                true
+            }
+        }
+    }
+
+    /// remove an indicator (returns it if it was in the collection, and the last indicator takes
+    /// its place)
+    pub fn remove(&mut self, ioc: &Ioc) -> Option<Indicator> {
+        let i = self.index.remove(&ioc.normalized())?;
+        let removed = self.indicators.swap_remove(i);
+        if let Some(moved) = self.indicators.get(i) {
+            self.index.insert(moved.ioc.normalized(), i);
+        }
+        Some(removed)
+    }
+
+    /// the indicator is in the collection?
+    pub fn contains(&self, ioc: &Ioc) -> bool {
+        self.index.contains_key(&ioc.normalized())
+    }
+
+    /// the indicator with metadata
+    pub fn get(&self, ioc: &Ioc) -> Option<&Indicator> {
+        self.index
+            .get(&ioc.normalized())
+            .map(|&i| &self.indicators[i])
+    }
+
+    /// number of indicators
+    pub fn len(&self) -> usize {
+        self.indicators.len()
+    }
+
+    /// no indicator?
+    pub fn is_empty(&self) -> bool {
+        self.indicators.is_empty()
+    }
+
+    /// indicators in order of insertion (unless some are removed)
+    pub fn iter(&self) -> std::slice::Iter<'_, Indicator> {
+        self.indicators.iter()
+    }
+
+    /// values of indicators
+    pub fn iocs(&self) -> impl Iterator<Item = &Ioc> {
+        self.indicators.iter().map(|x| &x.ioc)
+    }
+
+    /// indicators of the kind
+    pub fn of_kind(&self, kind: IocKind) -> impl Iterator<Item = &Indicator> {
+        self.indicators.iter().filter(move |x| x.ioc.kind() == kind)
+    }
+
+    /// sample hashes (e.g. to be collected into `HashSet<SampleHash>`)
+    pub fn hashes(&self) -> impl Iterator<Item = &SampleHash> {
+        self.indicators.iter().filter_map(|x| match &x.ioc {
+            Ioc::Hash(h) => Some(h),
+            _ => None,
+        })
+    }
+
+    /// number of indicators by kind
+    pub fn count_by_kind(&self) -> HashMap<IocKind, usize> {
+        let mut counts = HashMap::new();
+        for x in &self.indicators {
+            *counts.entry(x.ioc.kind()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// indicators in either collection (metadata of the same indicator is merged)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::collection::IocCollection;
+    ///
+    /// let a: IocCollection = vec![Ioc::new("example.com").unwrap(), Ioc::new("192.0.2.1").unwrap()]
+    ///     .into_iter()
+    ///     .collect();
+    /// let b: IocCollection = vec![Ioc::new("192.0.2.1").unwrap(), Ioc::new("example.net").unwrap()]
+    ///     .into_iter()
+    ///     .collect();
+    ///
+    /// assert_eq!(a.union(&b).len(), 3);
+    /// let both = a.intersection(&b);
+    /// assert_eq!(both.iocs().collect::<Vec<_>>(), vec![&Ioc::new("192.0.2.1").unwrap()]);
+    /// let only = a.difference(&b);
+    /// assert_eq!(only.iocs().collect::<Vec<_>>(), vec![&Ioc::new("example.com").unwrap()]);
+    /// assert!(both.is_subset(&a));
+    /// ```
+    pub fn union(&self, other: &IocCollection) -> IocCollection {
+        let mut c = self.clone();
+        c.extend(other.iter().cloned());
+        c
+    }
+
+    /// indicators in both collections (metadata is merged)
+    pub fn intersection(&self, other: &IocCollection) -> IocCollection {
+        self.iter()
+            .filter_map(|x| {
+                let theirs = other.get(&x.ioc)?;
+                Some(Indicator::with_meta(
+                    x.ioc.clone(),
+                    x.meta.clone().merge(theirs.meta.clone()),
+                ))
+            })
+            .collect()
+    }
+
+    /// indicators not in the other collection
+    pub fn difference(&self, other: &IocCollection) -> IocCollection {
+        self.iter()
+            .filter(|x| !other.contains(&x.ioc))
+            .cloned()
+            .collect()
+    }
+
+    /// all indicators are in the other collection?
+    pub fn is_subset(&self, other: &IocCollection) -> bool {
+        self.iocs().all(|x| other.contains(x))
+    }
+}
+
+/// collections of the same indicators with the same metadata are equal regardless of order
+impl PartialEq for IocCollection {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|x| other.get(&x.ioc) == Some(x))
+    }
+}
+
+impl<T: Into<Indicator>> FromIterator<T> for IocCollection {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut c = IocCollection::new();
+        c.extend(iter);
+        c
+    }
+}
+
+impl<T: Into<Indicator>> Extend<T> for IocCollection {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.insert(x);
+        }
+    }
+}
+
+impl IntoIterator for IocCollection {
+    type Item = Indicator;
+    type IntoIter = std::vec::IntoIter<Indicator>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.indicators.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a IocCollection {
+    type Item = &'a Indicator;
+    type IntoIter = std::slice::Iter<'a, Indicator>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.indicators.iter()
+    }
+}
+
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::collection::IocCollection;
+///
+/// let c: IocCollection = vec![Ioc::new("example.com").unwrap()].into_iter().collect();
+/// let json = serde_json::to_string(&c).unwrap();
+/// assert_eq!(json, r#"[{"kind":"domain","value":"example.com"}]"#);
+/// assert_eq!(serde_json::from_str::<IocCollection>(&json).unwrap(), c);
+/// ```
+impl Serialize for IocCollection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter().map(Record::from))
+    }
+}
+
+impl<'de> Deserialize<'de> for IocCollection {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Record>::deserialize(deserializer)?
+            .into_iter()
+            .map(|x| x.into_indicator().map_err(D::Error::custom))
+            .collect()
+    }
+}
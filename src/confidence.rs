@@ -0,0 +1,137 @@
+//! confidence of indicators (score 0-100 and qualitative band)
+//!
+//! Bands follow the "None / Low / Med / High" scale of STIX 2.1 (0, 1-29, 30-69 and 70-100).
+//!
+//! When the same indicator arrives from multiple sources, confidences are combined by
+//! `Confidence::merge`:
+//!
+//! - reports from the same source: the newer one replaces the older one (the source re-scored it)
+//! - reports from different (or unknown) sources: they corroborate each other, combined by
+//!   `Confidence::combine` (`100 - (100 - a) * (100 - b) / 100`, never lower than either)
+//! - missing confidence does not change the other one
+//!
+//! Only `core` is required (same as `iocutil::validate`).
+
+use core::fmt;
+
+/// qualitative band of confidence
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Ord, PartialOrd)]
+pub enum ConfidenceBand {
+    /// 0
+    None,
+    /// 1-29
+    Low,
+    /// 30-69
+    Medium,
+    /// 70-100
+    High,
+}
+
+impl ConfidenceBand {
+    /// name of band (as in STIX 2.1)
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfidenceBand::None => "none",
+            ConfidenceBand::Low => "low",
+            ConfidenceBand::Medium => "med",
+            ConfidenceBand::High => "high",
+        }
+    }
+}
+
+/// confidence score (0-100)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::confidence::{Confidence, ConfidenceBand};
+///
+/// let c = Confidence::new(80);
+/// assert_eq!(c.score(), 80);
+/// assert_eq!(c.band(), ConfidenceBand::High);
+/// assert_eq!(c.to_string(), "80 (high)");
+/// assert_eq!(Confidence::new(150).score(), 100);
+/// assert_eq!(Confidence::from(ConfidenceBand::Medium).band(), ConfidenceBand::Medium);
+///
+/// // two sources of 50 corroborate each other
+/// assert_eq!(Confidence::new(50).combine(Confidence::new(50)).score(), 75);
+/// // the same source re-scored it
+/// assert_eq!(Confidence::merge(Some(Confidence::new(80)), Some(Confidence::new(20)), true), Some(Confidence::new(20)));
+/// assert_eq!(Confidence::merge(None, Some(Confidence::new(20)), false), Some(Confidence::new(20)));
+/// ```
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "std",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "u8", into = "u8")
+)]
+pub struct Confidence(u8);
+
+impl Confidence {
+    /// confidence of score (clamped to 100)
+    pub fn new(score: u8) -> Self {
+        Confidence(score.min(100))
+    }
+
+    pub fn score(self) -> u8 {
+        self.0
+    }
+
+    pub fn band(self) -> ConfidenceBand {
+        match self.0 {
+            0 => ConfidenceBand::None,
+            1..=29 => ConfidenceBand::Low,
+            30..=69 => ConfidenceBand::Medium,
+            _ => ConfidenceBand::High,
+        }
+    }
+
+    /// combined confidence of independent sources (`100 - (100 - a) * (100 - b) / 100`, rounded)
+    pub fn combine(self, other: Confidence) -> Self {
+        let doubt = u32::from(100 - self.0) * u32::from(100 - other.0);
+        Confidence(100 - ((doubt + 50) / 100) as u8)
+    }
+
+    /// newer confidence merged into older one (`same_source`: both are from the same source)
+    pub fn merge(
+        old: Option<Confidence>,
+        new: Option<Confidence>,
+        same_source: bool,
+    ) -> Option<Self> {
+        match (old, new) {
+            (Some(_), Some(new)) if same_source => Some(new),
+            (Some(old), Some(new)) => Some(old.combine(new)),
+            (old, new) => new.or(old),
+        }
+    }
+}
+
+/// representative score of band (0, 15, 50 and 85)
+impl From<ConfidenceBand> for Confidence {
+    fn from(band: ConfidenceBand) -> Self {
+        Confidence(match band {
+            ConfidenceBand::None => 0,
+            ConfidenceBand::Low => 15,
+            ConfidenceBand::Medium => 50,
+            ConfidenceBand::High => 85,
+        })
+    }
+}
+
+impl From<u8> for Confidence {
+    fn from(score: u8) -> Self {
+        Confidence::new(score)
+    }
+}
+
+impl From<Confidence> for u8 {
+    fn from(confidence: Confidence) -> Self {
+        confidence.0
+    }
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.0, self.band().name())
+    }
+}
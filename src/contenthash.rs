@@ -1,13 +1,15 @@
-//! hash (sha256 / sha1 / md5) bundle and its utilities
+//! hash (sha256 / sha1 / md5) bundles and their utilities
 
 use crate::check_hashtype;
 use crate::hasher::Hasher;
 use crate::sample;
+use crate::tlsh::Tlsh;
 use crate::util::unwrap_try_into;
 use crate::GenericResult;
 use crate::SampleHash;
 use std::collections::HashSet;
 use std::convert::TryInto;
+use std::io::{Read, Write};
 use std::path::Path;
 
 /// hash bundle for a content
@@ -86,11 +88,88 @@ impl ContentHash {
     /// println!("{:?}", ch);
     /// ```
     pub fn of_file(path: impl AsRef<Path>) -> GenericResult<Self> {
-        let mut f = std::fs::File::open(path)?;
+        ContentHash::of_reader(std::fs::File::open(path)?)
+    }
+
+    /// content hash of all content in reader (calculated in a pass)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let ch = ContentHash::of_reader(std::io::empty()).unwrap();
+    /// assert_eq!(ch, ContentHash::default());
+    /// ```
+    pub fn of_reader(mut reader: impl Read) -> GenericResult<Self> {
         let mut hasher = Hasher::default();
-        std::io::copy(&mut f, &mut hasher)?;
+        std::io::copy(&mut reader, &mut hasher)?;
         Ok(hasher.digests())
     }
+
+    /// content hash of bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let ch = ContentHash::of_bytes(b"");
+    /// assert_eq!(ch, ContentHash::default());
+    /// ```
+    pub fn of_bytes(bytes: impl AsRef<[u8]>) -> Self {
+        let mut hasher = Hasher::default();
+        // writing into hasher never fails
+        hasher.write_all(bytes.as_ref()).unwrap();
+        hasher.digests()
+    }
+}
+
+/// hashes of a sample (md5 / sha1 / sha256 and others if known)
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct SampleHashes {
+    pub sha256: SampleHash,
+    pub sha1: SampleHash,
+    pub md5: SampleHash,
+    /// sha512 in hex
+    pub sha512: Option<String>,
+    /// size in bytes
+    pub size: Option<u64>,
+    pub tlsh: Option<Tlsh>,
+}
+
+impl SampleHashes {
+    /// hashes with only md5 / sha1 / sha256
+    pub fn from_content_hash(hashes: ContentHash) -> Self {
+        SampleHashes {
+            sha256: hashes.sha256,
+            sha1: hashes.sha1,
+            md5: hashes.md5,
+            sha512: None,
+            size: None,
+            tlsh: None,
+        }
+    }
+
+    /// md5 / sha1 / sha256
+    pub fn content_hash(&self) -> ContentHash {
+        ContentHash {
+            sha256: self.sha256.clone(),
+            sha1: self.sha1.clone(),
+            md5: self.md5.clone(),
+        }
+    }
+
+    /// whether the hash is one of md5 / sha1 / sha256
+    pub fn contains(&self, hash: &SampleHash) -> bool {
+        [&self.sha256, &self.sha1, &self.md5].contains(&hash)
+    }
+}
+
+impl From<ContentHash> for SampleHashes {
+    fn from(hashes: ContentHash) -> Self {
+        SampleHashes::from_content_hash(hashes)
+    }
 }
 
 /// remove overlap from target with blacklist
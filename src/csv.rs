@@ -0,0 +1,230 @@
+//! CSV export of indicators
+
+use derive_builder::Builder;
+use std::io::Write;
+
+use crate::allowlist::suppress;
+use crate::hashformat::HashFormat;
+use crate::ioc::{Indicator, IocKind};
+use crate::ttl::unexpired;
+
+/// a column of exported CSV
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Column {
+    Value,
+    Kind,
+    Defanged,
+    FirstSeen,
+    LastSeen,
+    Source,
+    Description,
+    Tags,
+    Confidence,
+    /// band of confidence (`none` / `low` / `med` / `high`)
+    ConfidenceBand,
+    ValidUntil,
+    /// country code of IP address (see `iocutil::geoip`)
+    Country,
+    City,
+    /// autonomous system number of IP address
+    Asn,
+    /// organization of autonomous system
+    AsOrg,
+}
+
+impl Column {
+    /// name in header row
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Value => "value",
+            Column::Kind => "kind",
+            Column::Defanged => "defanged",
+            Column::FirstSeen => "first_seen",
+            Column::LastSeen => "last_seen",
+            Column::Source => "source",
+            Column::Description => "description",
+            Column::Tags => "tags",
+            Column::Confidence => "confidence",
+            Column::ConfidenceBand => "confidence_band",
+            Column::ValidUntil => "valid_until",
+            Column::Country => "country",
+            Column::City => "city",
+            Column::Asn => "asn",
+            Column::AsOrg => "as_org",
+        }
+    }
+}
+
+/// options of CSV export
+#[derive(Builder, Debug, Clone)]
+pub struct CsvOptions {
+    /// columns in order
+    #[builder(default = "vec![Column::Value, Column::Kind]")]
+    columns: Vec<Column>,
+    /// emit header row
+    #[builder(default = "true")]
+    header: bool,
+    /// defang value column too
+    #[builder(default = "false")]
+    defang: bool,
+    #[builder(default = "','")]
+    delimiter: char,
+    /// separator of tags in a field
+    #[builder(setter(into), default = "\";\".to_owned()")]
+    tag_separator: String,
+    /// format of hashes in value / defanged columns
+    #[builder(default)]
+    hash_format: HashFormat,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptionsBuilder::default().build().unwrap()
+    }
+}
+
+impl CsvOptions {
+    /// quote field if needed (RFC 4180)
+    fn quote(&self, field: String) -> String {
+        if field.contains(&[self.delimiter, '"', '\n', '\r'][..]) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field
+        }
+    }
+
+    fn field(&self, indicator: &Indicator, column: Column) -> String {
+        let meta = &indicator.meta;
+        let geo = meta.geo.as_ref();
+        let date = |x: Option<chrono::DateTime<chrono::Utc>>| {
+            x.map(|d| d.to_rfc3339()).unwrap_or_default()
+        };
+        match column {
+            // hashes are never defanged
+            Column::Value | Column::Defanged if indicator.ioc.kind() == IocKind::Hash => {
+                indicator.ioc.formatted(&self.hash_format)
+            }
+            Column::Value if self.defang => indicator.ioc.defanged(),
+            Column::Value => indicator.ioc.value(),
+            Column::Kind => indicator.ioc.kind().to_string(),
+            Column::Defanged => indicator.ioc.defanged(),
+            Column::FirstSeen => date(meta.first_seen),
+            Column::LastSeen => date(meta.last_seen),
+            Column::Source => meta.source.clone().unwrap_or_default(),
+            Column::Description => meta.description.clone().unwrap_or_default(),
+            Column::Tags => meta.tags.join(&self.tag_separator),
+            Column::Confidence => meta
+                .confidence
+                .map(|x| x.score().to_string())
+                .unwrap_or_default(),
+            Column::ConfidenceBand => meta
+                .confidence
+                .map(|x| x.band().name().to_owned())
+                .unwrap_or_default(),
+            Column::ValidUntil => date(meta.valid_until),
+            Column::Country => geo.and_then(|x| x.country.clone()).unwrap_or_default(),
+            Column::City => geo.and_then(|x| x.city.clone()).unwrap_or_default(),
+            Column::Asn => geo
+                .and_then(|x| x.asn)
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            Column::AsOrg => geo.and_then(|x| x.as_org.clone()).unwrap_or_default(),
+        }
+    }
+
+    fn row(&self, fields: impl IntoIterator<Item = String>) -> String {
+        let fields: Vec<_> = fields.into_iter().map(|x| self.quote(x)).collect();
+        format!("{}\r\n", fields.join(&self.delimiter.to_string()))
+    }
+}
+
+/// write indicators as CSV
+pub fn write_csv(
+    mut writer: impl Write,
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &CsvOptions,
+) -> std::io::Result<()> {
+    if options.header {
+        let header = options.columns.iter().map(|x| x.name().to_owned());
+        writer.write_all(options.row(header).as_bytes())?;
+    }
+
+    for indicator in suppress(unexpired(indicators, options.include_expired)) {
+        let fields = options
+            .columns
+            .iter()
+            .map(|c| options.field(&indicator, *c));
+        writer.write_all(options.row(fields).as_bytes())?;
+    }
+
+    writer.flush()
+}
+
+/// export indicators as CSV
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::csv::{to_csv, Column, CsvOptionsBuilder};
+/// use iocutil::geoip::GeoInfo;
+/// use iocutil::hashformat::HashFormatBuilder;
+///
+/// let mut i = Indicator::new(Ioc::new("http://evil.example.com/a").unwrap());
+/// i.meta.tags = vec!["phishing".to_owned(), "kit, v2".to_owned()];
+///
+/// let options = CsvOptionsBuilder::default()
+///     .columns(vec![Column::Value, Column::Kind, Column::Tags])
+///     .tag_separator(",")
+///     .defang(true)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(
+///     to_csv(vec![i], &options),
+///     "value,kind,tags\r\nhxxp://evil[.]example[.]com/a,url,\"phishing,kit, v2\"\r\n"
+/// );
+///
+/// // hashes in stable format
+/// let options = CsvOptionsBuilder::default()
+///     .header(false)
+///     .hash_format(HashFormatBuilder::default().prefix(true).build().unwrap())
+///     .build()
+///     .unwrap();
+/// let hash = Ioc::new("D41D8CD98F00B204E9800998ECF8427E").unwrap();
+/// assert_eq!(to_csv(vec![hash], &options), "md5:d41d8cd98f00b204e9800998ecf8427e,hash\r\n");
+///
+/// // expired indicators are excluded unless included explicitly
+/// let mut expired = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// expired.meta.valid_until = Some(day!(2019, 12, 1).unwrap().into());
+/// assert_eq!(to_csv(vec![expired.clone()], &options), "");
+/// let options = CsvOptionsBuilder::default()
+///     .header(false)
+///     .columns(vec![Column::Value, Column::ValidUntil])
+///     .include_expired(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(to_csv(vec![expired], &options), "192.0.2.1,2019-12-01T00:00:00+00:00\r\n");
+///
+/// // geolocation (e.g. by `iocutil::geoip::GeoIp::enrich`)
+/// let mut ip = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// ip.meta.geo = Some(GeoInfo { country: Some("JP".to_owned()), asn: Some(64496), ..Default::default() });
+/// let options = CsvOptionsBuilder::default()
+///     .header(false)
+///     .columns(vec![Column::Value, Column::Country, Column::City, Column::Asn])
+///     .build()
+///     .unwrap();
+/// assert_eq!(to_csv(vec![ip], &options), "192.0.2.1,JP,,64496\r\n");
+/// ```
+pub fn to_csv(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &CsvOptions,
+) -> String {
+    let mut buf = vec![];
+    // writing into vec never fails
+    write_csv(&mut buf, indicators, options).unwrap();
+    String::from_utf8(buf).unwrap()
+}
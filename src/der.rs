@@ -0,0 +1,96 @@
+//! minimal DER reader (enough to walk PKCS#7 signed data and X.509 certificates)
+
+/// a DER element
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Tlv<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    /// whole element including tag and length
+    pub raw: &'a [u8],
+}
+
+pub(crate) const INTEGER: u8 = 0x02;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+pub(crate) const OID: u8 = 0x06;
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const SET: u8 = 0x31;
+pub(crate) const UTC_TIME: u8 = 0x17;
+pub(crate) const GENERALIZED_TIME: u8 = 0x18;
+
+/// context-specific constructed tag (e.g. `[0]`)
+pub(crate) const fn context(n: u8) -> u8 {
+    0xa0 | n
+}
+
+/// read an element and rest of input (indefinite length is not supported)
+pub(crate) fn read(input: &[u8]) -> Option<(Tlv<'_>, &[u8])> {
+    let tag = *input.first()?;
+    // multi-byte tags are not used in structures we read
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+    let first = *input.get(1)?;
+    let (len, header) = if first < 0x80 {
+        (first as usize, 2)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let len = input
+            .get(2..2 + n)?
+            .iter()
+            .fold(0usize, |acc, &b| acc << 8 | b as usize);
+        (len, 2 + n)
+    };
+    let end = header.checked_add(len)?;
+    let raw = input.get(..end)?;
+    Some((
+        Tlv {
+            tag,
+            content: &raw[header..],
+            raw,
+        },
+        &input[end..],
+    ))
+}
+
+/// read an element with expected tag
+pub(crate) fn expect(input: &[u8], tag: u8) -> Option<(Tlv<'_>, &[u8])> {
+    read(input).filter(|(x, _)| x.tag == tag)
+}
+
+/// elements in content of constructed element
+pub(crate) fn children(content: &[u8]) -> Option<Vec<Tlv<'_>>> {
+    let mut items = vec![];
+    let mut rest = content;
+    while !rest.is_empty() {
+        let (item, r) = read(rest)?;
+        items.push(item);
+        rest = r;
+    }
+    Some(items)
+}
+
+/// dotted notation of object identifier
+pub(crate) fn oid(content: &[u8]) -> String {
+    let mut arcs: Vec<u64> = vec![];
+    let mut v: u64 = 0;
+    for &b in content {
+        v = v << 7 | u64::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (v / 40).min(2);
+                arcs.push(first);
+                arcs.push(v - first * 40);
+            } else {
+                arcs.push(v);
+            }
+            v = 0;
+        }
+    }
+    arcs.iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
@@ -0,0 +1,229 @@
+//! DGA-likeness of domains (heuristic scores of algorithmically generated domain names)
+//!
+//! The label of registrable domain (e.g. `xjw3kq9vz` of `www.xjw3kq9vz.net`) is scored from 0
+//! (readable) to 1 (random) by bigram frequency against a model of English and common domain
+//! words, Shannon entropy, ratio of digits, length and runs of consonants. Scores are meant for
+//! triage (filters and sort keys), not for verdicts.
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::entropy::{normalized_entropy, shannon_entropy};
+use crate::ioc::{host_of_url, Indicator, Ioc};
+use crate::psl;
+
+/// words the bigram model is trained on (English and common in domain names)
+const CORPUS: &str = "
+    the of and to in is you that it he was for on are as with his they at be this have from or
+    one had by word but not what all were we when your can said there use an each which she do
+    how their if will up other about out many then them these so some her would make like him
+    into time has look two more write go see number no way could people my than first water
+    been call who oil its now find long down day did get come made may part over new sound take
+    only little work know place year live me back give most very after thing our just name good
+    sentence man think say great where help through much before line right too mean old any
+    same tell boy follow came want show also around form three small set put end does another
+    well large must big even such because turn here why ask went men read need land different
+    home us move try kind hand picture again change off play spell air away animal house point
+    page letter mother answer found study still learn should america world high every near add
+    food between own below country plant last school father keep tree never start city earth
+    eye light thought head under story saw left few while along might close something seem next
+    hard open example begin life always those both paper together group often run important
+    until children side feet car mile night walk white sea began grow took river four carry
+    state once book hear stop without second later miss idea enough eat face watch far indian
+    real almost let above girl sometimes mountain cut young talk soon list song being leave
+    family online shop store news blog cloud mail login secure account service support market
+    media group global tech digital data network systems solutions software web site portal
+    center travel hotel health care bank finance money pay payment insurance energy power green
+    design studio photo video music game games sport sports club team school university college
+    education learning academy library science research lab labs press times daily journal post
+    magazine radio channel live stream social chat forum community friends dating love life
+    style fashion beauty home garden kitchen food recipe coffee wine beer pizza restaurant cafe
+    auto cars motors parts repair service rental estate property homes realty building
+    construction consulting partners agency marketing advertising management capital invest
+    trading exchange crypto wallet security protect guard safe trust legal law office business
+    company corporation international national american europe asia china japan india london
+    paris berlin tokyo york california texas florida google facebook amazon microsoft apple
+    yahoo twitter instagram youtube linkedin wikipedia netflix paypal adobe oracle cisco github
+    mozilla wordpress dropbox spotify ebay reddit yandex baidu alibaba tencent weather maps
+    search free best top pro plus express direct first smart easy quick fast one world city
+    ";
+
+/// characters of labels (and the end of label)
+const ALPHABET: usize = 38;
+
+lazy_static! {
+    static ref MODEL: Bigrams = Bigrams::train(CORPUS);
+}
+
+/// bigram counts of the corpus (`^` and `$` for the start and end of words)
+struct Bigrams {
+    pairs: HashMap<(char, char), usize>,
+    firsts: HashMap<char, usize>,
+}
+
+impl Bigrams {
+    fn train(corpus: &str) -> Self {
+        let mut pairs = HashMap::new();
+        let mut firsts = HashMap::new();
+        for word in corpus.split_whitespace() {
+            let chars: Vec<char> = std::iter::once('^')
+                .chain(word.chars())
+                .chain(std::iter::once('$'))
+                .collect();
+            for w in chars.windows(2) {
+                *pairs.entry((w[0], w[1])).or_insert(0) += 1;
+                *firsts.entry(w[0]).or_insert(0) += 1;
+            }
+        }
+        Bigrams { pairs, firsts }
+    }
+
+    /// mean log2 probability of transitions in words of the label (add-one smoothed)
+    fn mean_log_probability(&self, label: &str) -> f64 {
+        let (mut sum, mut count) = (0.0, 0);
+        for word in label.split('-').filter(|x| !x.is_empty()) {
+            let chars: Vec<char> = std::iter::once('^')
+                .chain(word.chars())
+                .chain(std::iter::once('$'))
+                .collect();
+            for w in chars.windows(2) {
+                let pair = self.pairs.get(&(w[0], w[1])).copied().unwrap_or(0);
+                let first = self.firsts.get(&w[0]).copied().unwrap_or(0);
+                sum += ((pair + 1) as f64 / (first + ALPHABET) as f64).log2();
+                count += 1;
+            }
+        }
+        if count == 0 {
+            0.0
+        } else {
+            sum / count as f64
+        }
+    }
+}
+
+/// DGA-likeness of a domain and its features
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DgaScore {
+    /// label scored (of registrable domain)
+    pub label: String,
+    /// from 0 (readable) to 1 (random)
+    pub score: f64,
+    /// mean log2 probability of bigrams (higher is more English-like)
+    pub bigram: f64,
+    /// Shannon entropy in bits per character
+    pub entropy: f64,
+    /// ratio of digits in the label
+    pub digit_ratio: f64,
+}
+
+fn clamp(x: f64) -> f64 {
+    x.clamp(0.0, 1.0)
+}
+
+fn longest_consonant_run(s: &str) -> usize {
+    s.split(|c: char| "aeiouy0123456789-".contains(c))
+        .map(|x| x.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// DGA-likeness of the label of registrable domain of the domain
+/// (`None` if not a domain, or the label is internationalized)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::dga::dga_score;
+///
+/// assert!(dga_score("www.wikipedia.org").unwrap().score < 0.3);
+/// assert!(dga_score("secure-mail-login.com").unwrap().score < 0.3);
+/// assert!(dga_score("xjw3kq9vzt2h.net").unwrap().score > 0.7);
+/// assert!(dga_score("qhvfpbzkrgwd.biz").unwrap().score > 0.7);
+///
+/// let s = dga_score("a1b2c3d4e5f6.info").unwrap();
+/// assert_eq!(s.label, "a1b2c3d4e5f6");
+/// assert_eq!(s.digit_ratio, 0.5);
+/// assert!(dga_score("192.0.2.1").is_none());
+/// ```
+pub fn dga_score(domain: impl AsRef<str>) -> Option<DgaScore> {
+    if domain.as_ref().parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    let registrable = psl::registrable_domain(domain.as_ref())?;
+    let label = registrable.split('.').next()?.to_owned();
+    if label.is_empty() || label.starts_with("xn--") {
+        return None;
+    }
+    let len = label.chars().count();
+    let bigram = MODEL.mean_log_probability(&label);
+    let entropy = shannon_entropy(&label);
+    let digit_ratio = label.chars().filter(|c| c.is_ascii_digit()).count() as f64 / len as f64;
+
+    // words are about -4 bits per bigram by the model, random characters below -5.5
+    let rarity = clamp((-bigram - 4.2) / 1.4);
+    // random labels are near log2(len) bits per character (all characters distinct)
+    let randomness = if len < 4 {
+        0.0
+    } else {
+        clamp((normalized_entropy(&label) - 0.8) / 0.2)
+    };
+    let digits = clamp(digit_ratio * 2.5);
+    // of the longest word, as words joined by `-` are common in legitimate domains
+    let longest = label.split('-').map(|x| x.len()).max().unwrap_or(0);
+    let length = clamp((longest as f64 - 8.0) / 12.0);
+    let consonants = clamp((longest_consonant_run(&label) as f64 - 3.0) / 3.0);
+    let score =
+        clamp(0.55 * rarity + 0.1 * randomness + 0.15 * digits + 0.05 * length + 0.15 * consonants);
+    // short labels have too few bigrams to be told
+    let score = if len < 6 {
+        score * len as f64 / 6.0
+    } else {
+        score
+    };
+
+    Some(DgaScore {
+        label,
+        score,
+        bigram,
+        entropy,
+        digit_ratio,
+    })
+}
+
+/// DGA-likeness (from 0 to 1) of the domain or host of URL of the indicator
+/// (`None` for other kinds)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::dga::dga_likelihood;
+/// use iocutil::ioc::{Indicator, Ioc};
+///
+/// let mut indicators: Vec<Indicator> = ["google.com", "http://kq3xvhz8wj2p.ru/gate.php", "example.org"]
+///     .iter()
+///     .map(|x| Indicator::new(Ioc::new(x).unwrap()))
+///     .collect();
+///
+/// // most DGA-like first
+/// indicators.sort_by(|a, b| {
+///     let (a, b) = (dga_likelihood(a).unwrap_or(0.0), dga_likelihood(b).unwrap_or(0.0));
+///     b.partial_cmp(&a).unwrap()
+/// });
+/// assert_eq!(indicators[0].ioc.value(), "http://kq3xvhz8wj2p.ru/gate.php");
+///
+/// indicators.retain(|x| dga_likelihood(x).is_some_and(|x| x < 0.5));
+/// assert_eq!(indicators.len(), 2);
+/// ```
+pub fn dga_likelihood(indicator: &Indicator) -> Option<f64> {
+    match &indicator.ioc {
+        Ioc::Domain(x) => dga_score(x),
+        Ioc::Url(x) => match host_of_url(x)? {
+            Ioc::Domain(x) => dga_score(x),
+            _ => None,
+        },
+        _ => None,
+    }
+    .map(|x| x.score)
+}
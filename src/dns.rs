@@ -0,0 +1,439 @@
+//! DNS resolution of domains (A / AAAA / MX / NS / TXT) to expand them into current infrastructure,
+//! and of IP addresses (PTR) to find their host names
+//!
+//! Resolution is active: queries reach name servers which adversaries may control and watch,
+//! so resolving indicators can tip them off. `DnsClient` (feature `net`) refuses every query
+//! until `allow_active_resolution` is called explicitly.
+//!
+//! Queries are sent to the resolver of the system (`/etc/resolv.conf`), a specific server
+//! (UDP, and TCP if truncated) or a DNS over HTTPS service (RFC 8484).
+
+use failure::Fail;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::ioc::Ioc;
+
+/// Errors in DNS resolution
+#[derive(Fail, Debug)]
+pub enum DnsError {
+    #[fail(
+        display = "active DNS resolution is not allowed (see DnsClient::allow_active_resolution)"
+    )]
+    NotAllowed,
+    #[fail(display = "no name server is configured")]
+    NoNameserver,
+    #[fail(display = "malformed DNS message: {}", _0)]
+    Malformed(&'static str),
+    #[fail(display = "DNS server failed with rcode {}", _0)]
+    ServerFailure(u8),
+}
+
+/// type of DNS record
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum RecordType {
+    A,
+    Aaaa,
+    Mx,
+    Ns,
+    Txt,
+    Ptr,
+}
+
+impl RecordType {
+    /// all types (in order of `expand`, except TXT and PTR)
+    pub const ALL: [RecordType; 6] = [
+        RecordType::A,
+        RecordType::Aaaa,
+        RecordType::Mx,
+        RecordType::Ns,
+        RecordType::Txt,
+        RecordType::Ptr,
+    ];
+
+    /// code in DNS messages
+    pub fn code(self) -> u16 {
+        match self {
+            RecordType::A => 1,
+            RecordType::Ns => 2,
+            RecordType::Ptr => 12,
+            RecordType::Mx => 15,
+            RecordType::Txt => 16,
+            RecordType::Aaaa => 28,
+        }
+    }
+}
+
+/// data of DNS record
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Mx {
+        preference: u16,
+        exchange: String,
+    },
+    Ns(String),
+    /// character strings joined
+    Txt(String),
+    /// host name of the address
+    Ptr(String),
+}
+
+/// a DNS record in answer
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DnsRecord {
+    /// owner name (e.g. target of CNAME)
+    pub name: String,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+impl DnsRecord {
+    /// infrastructure of the record as indicator (address, mail server, name server or host name)
+    pub fn ioc(&self) -> Option<Ioc> {
+        match &self.data {
+            RecordData::A(x) => Some(Ioc::IpAddress((*x).into())),
+            RecordData::Aaaa(x) => Some(Ioc::IpAddress((*x).into())),
+            RecordData::Mx { exchange: x, .. } | RecordData::Ns(x) | RecordData::Ptr(x) => {
+                Ioc::new(x).ok()
+            }
+            RecordData::Txt(_) => None,
+        }
+    }
+
+    /// type of relationship from the domain (or address of PTR) to `ioc` (as `iocutil::graph::Relationship`)
+    pub fn relationship_type(&self) -> &'static str {
+        match &self.data {
+            RecordData::A(_) | RecordData::Aaaa(_) => "resolves-to",
+            RecordData::Mx { .. } => "mail-server",
+            RecordData::Ns(_) => "name-server",
+            RecordData::Txt(_) => "txt",
+            RecordData::Ptr(_) => "reverse-resolves-to",
+        }
+    }
+}
+
+/// query message (recursion desired) of the name and type
+///
+/// # Example
+///
+/// ```
+/// use iocutil::dns::{parse_response, query_message, RecordData, RecordType};
+///
+/// let query = query_message(0x1234, "evil.example.com", RecordType::A).unwrap();
+/// assert_eq!(&query[..4], &[0x12, 0x34, 0x01, 0x00]);
+///
+/// // response with an answer pointing to the name in question
+/// let mut response = query.clone();
+/// response[2] = 0x81;
+/// response[3] = 0x80;
+/// response[7] = 1;
+/// response.extend(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0x0e, 0x10, 0, 4, 192, 0, 2, 1]);
+///
+/// let records = parse_response(&response, 0x1234, RecordType::A).unwrap();
+/// assert_eq!(records[0].name, "evil.example.com");
+/// assert_eq!(records[0].ttl, 3600);
+/// assert_eq!(records[0].data, RecordData::A("192.0.2.1".parse().unwrap()));
+/// assert_eq!(records[0].ioc().unwrap().value(), "192.0.2.1");
+/// ```
+pub fn query_message(id: u16, name: &str, record_type: RecordType) -> Result<Vec<u8>, DnsError> {
+    let mut message = vec![];
+    message.extend(&id.to_be_bytes());
+    // recursion desired, one question
+    message.extend(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(DnsError::Malformed("invalid label"));
+        }
+        message.push(label.len() as u8);
+        message.extend(label.as_bytes());
+    }
+    message.push(0);
+    message.extend(&record_type.code().to_be_bytes());
+    message.extend(&[0, 1]);
+    Ok(message)
+}
+
+/// name of PTR record of the address (`in-addr.arpa` / `ip6.arpa`)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::dns::reverse_name;
+///
+/// assert_eq!(reverse_name("192.0.2.1".parse().unwrap()), "1.2.0.192.in-addr.arpa");
+/// assert!(reverse_name("2001:db8::1".parse().unwrap())
+///     .starts_with("1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"));
+/// ```
+pub fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let o = ip.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", o[3], o[2], o[1], o[0])
+        }
+        IpAddr::V6(ip) => {
+            let mut labels: Vec<String> = ip
+                .octets()
+                .iter()
+                .rev()
+                .flat_map(|x| vec![format!("{:x}", x & 0x0f), format!("{:x}", x >> 4)])
+                .collect();
+            labels.push("ip6.arpa".to_owned());
+            labels.join(".")
+        }
+    }
+}
+
+/// reader of DNS message
+struct Message<'a> {
+    data: &'a [u8],
+}
+
+impl Message<'_> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&[u8], DnsError> {
+        self.data
+            .get(offset..offset + len)
+            .ok_or(DnsError::Malformed("truncated message"))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, DnsError> {
+        let b = self.bytes(offset, 2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, DnsError> {
+        let b = self.bytes(offset, 4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// (possibly compressed) name at offset, and offset after it
+    fn name(&self, offset: usize) -> Result<(String, usize), DnsError> {
+        let mut labels: Vec<String> = vec![];
+        let mut pos = offset;
+        let mut end = None;
+        for _ in 0..128 {
+            let len = self.bytes(pos, 1)?[0];
+            match len {
+                0 => {
+                    let name = labels.join(".").to_lowercase();
+                    return Ok((name, end.unwrap_or(pos + 1)));
+                }
+                x if x & 0xc0 == 0xc0 => {
+                    let pointer = usize::from(self.u16(pos)? & 0x3fff);
+                    end = end.or(Some(pos + 2));
+                    pos = pointer;
+                }
+                x if x & 0xc0 == 0 => {
+                    let label = self.bytes(pos + 1, usize::from(x))?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    pos += 1 + usize::from(x);
+                }
+                _ => return Err(DnsError::Malformed("unknown label type")),
+            }
+        }
+        Err(DnsError::Malformed("too many labels or loop of pointers"))
+    }
+}
+
+/// records of the type in response to the query (empty if the name does not exist)
+pub fn parse_response(
+    response: &[u8],
+    id: u16,
+    record_type: RecordType,
+) -> Result<Vec<DnsRecord>, DnsError> {
+    let m = Message { data: response };
+    if m.u16(0)? != id || m.bytes(2, 1)?[0] & 0x80 == 0 {
+        return Err(DnsError::Malformed("not a response to the query"));
+    }
+    match m.bytes(3, 1)?[0] & 0x0f {
+        0 => {}
+        // NXDOMAIN
+        3 => return Ok(vec![]),
+        rcode => return Err(DnsError::ServerFailure(rcode)),
+    }
+    let questions = m.u16(4)?;
+    let answers = m.u16(6)?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = m.name(pos)?.1 + 4;
+    }
+
+    let mut records = vec![];
+    for _ in 0..answers {
+        let (name, next) = m.name(pos)?;
+        let code = m.u16(next)?;
+        let ttl = m.u32(next + 4)?;
+        let len = usize::from(m.u16(next + 8)?);
+        let rdata = next + 10;
+        let raw = m.bytes(rdata, len)?;
+        pos = rdata + len;
+        if code != record_type.code() {
+            // e.g. CNAME in chain
+            continue;
+        }
+
+        let data = match record_type {
+            RecordType::A if len == 4 => {
+                RecordData::A(Ipv4Addr::new(raw[0], raw[1], raw[2], raw[3]))
+            }
+            RecordType::Aaaa if len == 16 => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(raw);
+                RecordData::Aaaa(Ipv6Addr::from(octets))
+            }
+            RecordType::Mx => RecordData::Mx {
+                preference: m.u16(rdata)?,
+                exchange: m.name(rdata + 2)?.0,
+            },
+            RecordType::Ns => RecordData::Ns(m.name(rdata)?.0),
+            RecordType::Ptr => RecordData::Ptr(m.name(rdata)?.0),
+            RecordType::Txt => {
+                let mut text = vec![];
+                let mut i = 0;
+                while i < raw.len() {
+                    let n = usize::from(raw[i]);
+                    let s = raw
+                        .get(i + 1..i + 1 + n)
+                        .ok_or(DnsError::Malformed("truncated character string"))?;
+                    text.extend(s);
+                    i += 1 + n;
+                }
+                RecordData::Txt(String::from_utf8_lossy(&text).into_owned())
+            }
+            _ => return Err(DnsError::Malformed("invalid length of address")),
+        };
+        records.push(DnsRecord { name, ttl, data });
+    }
+    Ok(records)
+}
+
+/// the message is truncated (TC bit, retried over TCP)?
+#[cfg(feature = "net")]
+pub(crate) fn is_truncated(response: &[u8]) -> bool {
+    response.get(2).is_some_and(|x| x & 0x02 != 0)
+}
+
+/// first name server in `/etc/resolv.conf`
+#[cfg(feature = "net")]
+pub(crate) fn system_nameserver() -> Result<std::net::SocketAddr, DnsError> {
+    std::fs::read_to_string("/etc/resolv.conf")
+        .ok()
+        .and_then(|text| {
+            text.lines()
+                .filter_map(|x| x.trim().strip_prefix("nameserver"))
+                .find_map(|x| x.trim().parse::<std::net::IpAddr>().ok())
+        })
+        .map(|ip| (ip, 53).into())
+        .ok_or(DnsError::NoNameserver)
+}
+
+#[cfg(feature = "net")]
+pub use self::client::{DnsClient, DnsResolver};
+
+#[cfg(feature = "net")]
+mod client {
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    use super::{DnsRecord, RecordType};
+    use crate::graph::Relationship;
+    use crate::http::block_on;
+    use crate::ioc::Indicator;
+    use crate::nonblocking;
+    use crate::GenericResult;
+
+    pub use crate::nonblocking::dns::DnsResolver;
+
+    /// client resolving domains (refuses queries unless `allow_active_resolution`)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::dns::{DnsClient, DnsResolver, RecordType};
+    /// use iocutil::prelude::*;
+    ///
+    /// let client = DnsClient::new(DnsResolver::DoH("https://cloudflare-dns.com/dns-query".to_owned()))
+    ///     .allow_active_resolution();
+    /// for r in client.lookup("example.com", RecordType::Mx).unwrap() {
+    ///     println!("{:?}", r.data);
+    /// }
+    ///
+    /// // domain `resolves-to` addresses, and its `mail-server` / `name-server`
+    /// let relationships = client.expand(&Indicator::new(Ioc::new("example.com").unwrap())).unwrap();
+    ///
+    /// // address `reverse-resolves-to` host names, 8 PTR queries at a time
+    /// let indicators = vec![Indicator::new(Ioc::new("192.0.2.1").unwrap())];
+    /// for r in client.reverse_all(&indicators, 8).unwrap() {
+    ///     println!("{} -> {}", r.source.value(), r.target.value());
+    /// }
+    /// ```
+    #[derive(Clone)]
+    pub struct DnsClient {
+        inner: nonblocking::DnsClient,
+    }
+
+    impl DnsClient {
+        /// client of the resolver (queries are refused until `allow_active_resolution`)
+        pub fn new(resolver: DnsResolver) -> Self {
+            DnsClient {
+                inner: nonblocking::DnsClient::new(resolver),
+            }
+        }
+
+        /// allow queries, accepting that name servers of adversaries may notice them
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use iocutil::dns::{DnsClient, DnsResolver, RecordType};
+        ///
+        /// let client = DnsClient::new(DnsResolver::System);
+        /// assert!(client.lookup("evil.example.com", RecordType::A).is_err());
+        /// ```
+        pub fn allow_active_resolution(mut self) -> Self {
+            self.inner = self.inner.allow_active_resolution();
+            self
+        }
+
+        /// use the HTTP client for DNS over HTTPS instead of shared one
+        pub fn with_client(mut self, client: reqwest::Client) -> Self {
+            self.inner = self.inner.with_client(client);
+            self
+        }
+
+        /// timeout of each query (default 5 seconds)
+        pub fn with_timeout(mut self, timeout: Duration) -> Self {
+            self.inner = self.inner.with_timeout(timeout);
+            self
+        }
+
+        /// records of the type (empty if the name does not exist)
+        pub fn lookup(
+            &self,
+            name: impl AsRef<str>,
+            record_type: RecordType,
+        ) -> GenericResult<Vec<DnsRecord>> {
+            block_on(self.inner.lookup(name, record_type))
+        }
+
+        /// current infrastructure of a domain (or URL / email address on it) by A / AAAA / MX / NS
+        pub fn expand(&self, indicator: &Indicator) -> GenericResult<Vec<Relationship>> {
+            block_on(self.inner.expand(indicator))
+        }
+
+        /// host names of the address by PTR
+        pub fn reverse(&self, ip: IpAddr) -> GenericResult<Vec<String>> {
+            block_on(self.inner.reverse(ip))
+        }
+
+        /// host names of IP addresses (or URLs on them) by PTR, `concurrency` queries at a time
+        pub fn reverse_all(
+            &self,
+            indicators: &[Indicator],
+            concurrency: usize,
+        ) -> GenericResult<Vec<Relationship>> {
+            block_on(self.inner.reverse_all(indicators, concurrency))
+        }
+    }
+}
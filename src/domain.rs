@@ -0,0 +1,150 @@
+//! domain names in both unicode and ACE (punycode) forms, and IDN homograph detection
+//!
+//! Domains are compared by ACE form, so `пример.рф` and `xn--e1afmkfd.xn--p1ai` are the same
+//! domain. Indicators keep domains in ACE form (`Ioc::new` accepts unicode as well).
+
+use std::fmt;
+
+use crate::error::Error;
+use crate::validate::is_domain;
+use crate::GenericResult;
+
+/// scripts of letters (others are common to all scripts, e.g. digits and `-`)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Thai,
+    Hangul,
+    Han,
+    Hiragana,
+    Katakana,
+    Other,
+}
+
+impl Script {
+    fn of(c: char) -> Option<Script> {
+        Some(match c as u32 {
+            0x30..=0x39 | 0x2d | 0x5f => return None,
+            0x41..=0x5a | 0x61..=0x7a | 0xc0..=0x24f | 0x1e00..=0x1eff => Script::Latin,
+            0x370..=0x3ff | 0x1f00..=0x1fff => Script::Greek,
+            0x400..=0x52f | 0x1c80..=0x1c8f | 0x2de0..=0x2dff | 0xa640..=0xa69f => Script::Cyrillic,
+            0x530..=0x58f => Script::Armenian,
+            0x590..=0x5ff => Script::Hebrew,
+            0x600..=0x6ff | 0x750..=0x77f | 0x8a0..=0x8ff => Script::Arabic,
+            0xe00..=0xe7f => Script::Thai,
+            0x1100..=0x11ff | 0x3130..=0x318f | 0xac00..=0xd7af => Script::Hangul,
+            0x3005 | 0x3400..=0x4dbf | 0x4e00..=0x9fff | 0xf900..=0xfaff => Script::Han,
+            // CJK punctuation and prolonged sound mark are used with both kana
+            0x3000..=0x303f | 0x30fc => return None,
+            0x3040..=0x309f => Script::Hiragana,
+            0x30a0..=0x30ff | 0x31f0..=0x31ff => Script::Katakana,
+            _ => Script::Other,
+        })
+    }
+
+    /// scripts used together in CJK languages (with Latin)
+    fn is_cjk(self) -> bool {
+        matches!(
+            self,
+            Script::Han | Script::Hiragana | Script::Katakana | Script::Hangul
+        )
+    }
+}
+
+/// scripts of the label are mixed? (Latin with CJK scripts is not, as in UTS #39)
+fn is_mixed(label: &str) -> bool {
+    let mut scripts: Vec<Script> = vec![];
+    for s in label.chars().filter_map(Script::of) {
+        if !scripts.contains(&s) {
+            scripts.push(s);
+        }
+    }
+    match scripts.as_slice() {
+        [] | [_] => false,
+        xs => !xs.iter().all(|x| *x == Script::Latin || x.is_cjk()),
+    }
+}
+
+/// domain name (compared by ACE form)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::domain::DomainName;
+///
+/// let d = DomainName::new("Пример.рф").unwrap();
+/// assert_eq!(d.ascii(), "xn--e1afmkfd.xn--p1ai");
+/// assert_eq!(d.unicode(), "пример.рф");
+/// assert_eq!(d, DomainName::new("xn--e1afmkfd.xn--p1ai").unwrap());
+/// assert!(d.is_idn());
+/// assert!(!d.is_mixed_script());
+///
+/// // "pаypal" with Cyrillic "а"
+/// let phishing = DomainName::new("xn--pypal-4ve.com").unwrap();
+/// assert_eq!(phishing.unicode(), "pаypal.com");
+/// assert!(phishing.is_mixed_script());
+/// assert_ne!(phishing, DomainName::new("paypal.com").unwrap());
+///
+/// assert!(DomainName::new("not a domain").is_err());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DomainName {
+    ascii: String,
+}
+
+impl DomainName {
+    /// parse domain name in unicode or ACE form
+    pub fn new(domain: impl AsRef<str>) -> GenericResult<Self> {
+        let domain = domain.as_ref().trim().trim_end_matches('.');
+        match idna::domain_to_ascii(domain) {
+            Ok(ascii) if is_domain(&ascii) => Ok(DomainName { ascii }),
+            _ => Err(Error::parse("domain", domain).into()),
+        }
+    }
+
+    /// ACE (punycode) form (e.g. `xn--e1afmkfd.xn--p1ai`)
+    pub fn ascii(&self) -> &str {
+        &self.ascii
+    }
+
+    /// unicode form (e.g. `пример.рф`)
+    pub fn unicode(&self) -> String {
+        idna::domain_to_unicode(&self.ascii).0
+    }
+
+    /// internationalized (any label is punycode)?
+    pub fn is_idn(&self) -> bool {
+        self.ascii.split('.').any(|x| x.starts_with("xn--"))
+    }
+
+    /// any label mixes scripts (e.g. Latin and Cyrillic), as IDN homograph phishing domains do?
+    pub fn is_mixed_script(&self) -> bool {
+        self.is_idn() && self.unicode().split('.').any(is_mixed)
+    }
+}
+
+/// ACE form (unicode form is given by `unicode`, not to be fooled by homographs)
+impl fmt::Display for DomainName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ascii)
+    }
+}
+
+impl std::str::FromStr for DomainName {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DomainName::new(s)
+    }
+}
+
+impl AsRef<str> for DomainName {
+    fn as_ref(&self) -> &str {
+        &self.ascii
+    }
+}
@@ -0,0 +1,190 @@
+//! Elasticsearch query DSL and EQL generation
+
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::allowlist::suppress;
+use crate::ioc::{Indicator, Ioc};
+use crate::ttl::unexpired;
+use crate::SampleHash;
+
+/// names of fields matched with each kind of indicators
+#[derive(Debug, Clone)]
+pub struct FieldProfile {
+    pub md5: Vec<String>,
+    pub sha1: Vec<String>,
+    pub sha256: Vec<String>,
+    pub domain: Vec<String>,
+    pub url: Vec<String>,
+    pub ip: Vec<String>,
+    pub email: Vec<String>,
+    /// match expired indicators too (see `iocutil::ttl`)
+    pub include_expired: bool,
+}
+
+fn names(fields: &[&str]) -> Vec<String> {
+    fields.iter().map(|x| (*x).to_owned()).collect()
+}
+
+impl FieldProfile {
+    /// Elastic Common Schema
+    pub fn ecs() -> Self {
+        FieldProfile {
+            md5: names(&["file.hash.md5", "process.hash.md5"]),
+            sha1: names(&["file.hash.sha1", "process.hash.sha1"]),
+            sha256: names(&["file.hash.sha256", "process.hash.sha256"]),
+            domain: names(&["dns.question.name", "url.domain", "destination.domain"]),
+            url: names(&["url.full", "url.original"]),
+            ip: names(&["destination.ip", "source.ip"]),
+            email: names(&["email.from.address", "email.to.address"]),
+            include_expired: false,
+        }
+    }
+
+    fn fields(&self, ioc: &Ioc) -> &[String] {
+        match ioc {
+            Ioc::Hash(SampleHash::Md5(_)) => &self.md5,
+            Ioc::Hash(SampleHash::Sha1(_)) => &self.sha1,
+            Ioc::Hash(SampleHash::Sha256(_)) => &self.sha256,
+            Ioc::Domain(_) => &self.domain,
+            Ioc::Url(_) => &self.url,
+            Ioc::IpAddress(_) => &self.ip,
+            Ioc::EmailAddress(_) => &self.email,
+        }
+    }
+
+    /// values grouped by field
+    fn group(
+        &self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    ) -> BTreeMap<(&'static str, String), Vec<String>> {
+        let mut groups: BTreeMap<_, Vec<String>> = BTreeMap::new();
+        for indicator in suppress(unexpired(indicators, self.include_expired)) {
+            let value = indicator.ioc.value();
+            for f in self.fields(&indicator.ioc) {
+                let values = groups
+                    .entry((event_category(&indicator.ioc), f.to_owned()))
+                    .or_default();
+                if !values.contains(&value) {
+                    values.push(value.clone());
+                }
+            }
+        }
+        groups
+    }
+}
+
+impl Default for FieldProfile {
+    fn default() -> Self {
+        FieldProfile::ecs()
+    }
+}
+
+/// EQL event category in which the indicator is observed
+fn event_category(ioc: &Ioc) -> &'static str {
+    match ioc {
+        Ioc::Hash(_) => "file",
+        Ioc::Domain(_) | Ioc::Url(_) | Ioc::IpAddress(_) => "network",
+        Ioc::EmailAddress(_) => "any",
+    }
+}
+
+/// `terms` query matching any of indicators (expired ones are excluded unless `include_expired`
+/// of the profile)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::elastic::{to_terms_query, FieldProfile};
+///
+/// let iocs = vec![
+///     Ioc::new("192.0.2.1").unwrap(),
+///     Ioc::new("198.51.100.1").unwrap(),
+/// ];
+///
+/// let q = to_terms_query(iocs, &FieldProfile::ecs());
+/// let should = q["query"]["bool"]["should"].as_array().unwrap();
+/// assert_eq!(should.len(), 2); // destination.ip and source.ip
+/// assert_eq!(should[0]["terms"]["destination.ip"][1], "198.51.100.1");
+/// ```
+pub fn to_terms_query(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    profile: &FieldProfile,
+) -> serde_json::Value {
+    let should: Vec<_> = profile
+        .group(indicators)
+        .into_iter()
+        .map(|((_, field), values)| json!({ "terms": { field: values } }))
+        .collect();
+
+    json!({
+        "query": {
+            "bool": {
+                "should": should,
+                "minimum_should_match": 1,
+            }
+        }
+    })
+}
+
+/// string literal of EQL
+fn literal(text: impl AsRef<str>) -> String {
+    format!(
+        "\"{}\"",
+        text.as_ref().replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// EQL sequence skeleton (a step per event category) to be edited by hunters (expired indicators
+/// are excluded unless `include_expired` of the profile)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::elastic::{to_eql_sequence, FieldProfile};
+///
+/// let iocs = vec![
+///     Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+///     Ioc::new("evil.example.com").unwrap(),
+/// ];
+///
+/// let mut profile = FieldProfile::ecs();
+/// profile.md5 = vec!["file.hash.md5".to_owned()];
+/// profile.domain = vec!["dns.question.name".to_owned()];
+///
+/// assert_eq!(
+///     to_eql_sequence(iocs, &profile, "host.id", "1h"),
+///     "sequence by host.id with maxspan=1h\n  [file where file.hash.md5 in (\"d41d8cd98f00b204e9800998ecf8427e\")]\n  [network where dns.question.name in (\"evil.example.com\")]\n"
+/// );
+/// ```
+pub fn to_eql_sequence(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    profile: &FieldProfile,
+    by: impl AsRef<str>,
+    maxspan: impl AsRef<str>,
+) -> String {
+    let mut steps: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for ((category, field), values) in profile.group(indicators) {
+        let values: Vec<_> = values.iter().map(literal).collect();
+        steps
+            .entry(category)
+            .or_default()
+            .push(format!("{} in ({})", field, values.join(", ")));
+    }
+
+    let mut eql = format!(
+        "sequence by {} with maxspan={}\n",
+        by.as_ref(),
+        maxspan.as_ref()
+    );
+    for (category, conditions) in steps {
+        eql.push_str(&format!(
+            "  [{} where {}]\n",
+            category,
+            conditions.join(" or ")
+        ));
+    }
+    eql
+}
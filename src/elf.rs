@@ -0,0 +1,105 @@
+//! ELF file analysis (telfhash; requires `elf` feature)
+
+use goblin::elf::sym::{STB_GLOBAL, STT_FUNC, STV_DEFAULT};
+use goblin::elf::Elf;
+use std::path::Path;
+
+use crate::fingerprint::Fingerprint;
+use crate::tlsh::Tlsh;
+use crate::GenericResult;
+
+/// functions excluded from telfhash (same as the original implementation)
+const EXCLUDED: &[&str] = &[
+    "__libc_start_main",
+    "main",
+    "abort",
+    "cachectl",
+    "cacheflush",
+    "puts",
+    "atol",
+    "malloc_trim",
+];
+
+/// an ELF file
+#[derive(Clone, Debug)]
+pub struct ElfFile {
+    bytes: Vec<u8>,
+}
+
+impl ElfFile {
+    /// ELF file from bytes (fails if bytes are not ELF)
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> GenericResult<Self> {
+        let bytes = bytes.into();
+        Elf::parse(&bytes)?;
+        Ok(ElfFile { bytes })
+    }
+
+    /// open ELF file
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        ElfFile::from_bytes(std::fs::read(path)?)
+    }
+
+    /// raw bytes of the file
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn parse(&self) -> Elf<'_> {
+        // bytes are validated in construction
+        Elf::parse(&self.bytes).unwrap()
+    }
+
+    /// names of global functions with default visibility (lowercase, sorted)
+    ///
+    /// Dynamic symbols are used if the file has them, otherwise the symbol table is used.
+    /// Names excluded from telfhash (e.g. `main`, `_start`, `memcpy`, `strlen`) are removed.
+    pub fn symbols(&self) -> Vec<String> {
+        let elf = self.parse();
+        let (syms, strtab) = if elf.dynsyms.is_empty() {
+            (&elf.syms, &elf.strtab)
+        } else {
+            (&elf.dynsyms, &elf.dynstrtab)
+        };
+
+        let mut names: Vec<String> = syms
+            .iter()
+            .filter(|s| {
+                s.st_type() == STT_FUNC
+                    && s.st_bind() == STB_GLOBAL
+                    && s.st_visibility() == STV_DEFAULT
+            })
+            .filter_map(|s| strtab.get_at(s.st_name))
+            .filter(|name| {
+                !(EXCLUDED.contains(name)
+                    || name.starts_with('.')
+                    || name.starts_with('_')
+                    || name.starts_with("mem")
+                    || name.starts_with("str")
+                    || name.ends_with("64"))
+            })
+            .map(|name| name.to_lowercase())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// telfhash (TLSH of symbol names, `telfhash` in MalwareBazaar)
+    ///
+    /// `None` if the file has too few symbols to compute TLSH
+    /// (call destinations used by the original implementation in that case are not supported).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use iocutil::elf::ElfFile;
+    ///
+    /// let elf = ElfFile::open("sample.elf").unwrap();
+    /// if let Some(telfhash) = elf.telfhash() {
+    ///     println!("{}", telfhash);
+    /// }
+    /// ```
+    pub fn telfhash(&self) -> Option<Fingerprint> {
+        let tlsh = Tlsh::of_bytes(self.symbols().join(",")).ok()?;
+        Some(Fingerprint::Telfhash(tlsh.to_string().to_lowercase()))
+    }
+}
@@ -0,0 +1,503 @@
+//! hashing body parts and attachments of mails (EML / RFC 5322 with MIME)
+//!
+//! Each leaf MIME part is decoded (base64 / quoted-printable) and hashed, and tied back to
+//! its index and file name in the same way as mail gateways report attachments.
+
+use derive_builder::Builder;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::contenthash::ContentHash;
+use crate::ioc::{Indicator, Metadata};
+use crate::GenericResult;
+
+lazy_static! {
+    static ref ENCODED_WORD: Regex = Regex::new(r"=\?([^?]+)\?([bBqQ])\?([^?]*)\?=").unwrap();
+    static ref BETWEEN_ENCODED_WORDS: Regex = Regex::new(r"\?=\s+=\?").unwrap();
+}
+
+/// nesting levels of multipart to follow
+const MAX_DEPTH: usize = 32;
+
+/// options of reading mails
+#[derive(Builder, Debug, Clone, Eq, PartialEq)]
+pub struct EmlOptions {
+    /// detect actual type of parts by magic bytes
+    #[builder(default = "false")]
+    sniff: bool,
+    /// skip body parts (parts neither with file name nor `Content-Disposition: attachment`)
+    #[builder(default = "false")]
+    attachments_only: bool,
+}
+
+impl Default for EmlOptions {
+    fn default() -> Self {
+        EmlOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// a decoded leaf MIME part
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MimePart {
+    /// index of the part among leaf parts of the mail (from 0)
+    pub index: usize,
+    /// declared content type (e.g. `application/pdf`)
+    pub content_type: String,
+    /// content type detected by magic bytes (only if sniffing is enabled)
+    pub sniffed_type: Option<String>,
+    pub filename: Option<String>,
+    pub is_attachment: bool,
+    /// size of decoded content
+    pub size: usize,
+    /// hashes of decoded content
+    pub hashes: ContentHash,
+}
+
+impl MimePart {
+    /// hashes of the part as indicators (sha256 / sha1 / md5)
+    pub fn indicators(&self) -> Vec<Indicator> {
+        let mut description = format!("mime part {} ({})", self.index, self.content_type);
+        if let Some(name) = &self.filename {
+            description.push_str(&format!(": {}", name));
+        }
+        let mut tags = vec![format!("mime-part:{}", self.index)];
+        if self.is_attachment {
+            tags.push("attachment".to_owned());
+        }
+        if let Some(name) = &self.filename {
+            tags.push(format!("filename:{}", name));
+        }
+        let meta = Metadata {
+            source: Some("eml".to_owned()),
+            description: Some(description),
+            tags,
+            ..Metadata::default()
+        };
+
+        vec![&self.hashes.sha256, &self.hashes.sha1, &self.hashes.md5]
+            .into_iter()
+            .map(|x| Indicator::with_meta(x.clone(), meta.clone()))
+            .collect()
+    }
+}
+
+/// content type sniffed by magic bytes
+///
+/// # Example
+///
+/// ```
+/// use iocutil::eml::sniff;
+///
+/// assert_eq!(sniff(b"MZ\x90\x00\x03"), Some("application/x-dosexec"));
+/// assert_eq!(sniff(b"%PDF-1.7"), Some("application/pdf"));
+/// assert_eq!(sniff(b"Hello"), None);
+/// ```
+pub fn sniff(content: &[u8]) -> Option<&'static str> {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"MZ", "application/x-dosexec"),
+        (b"\x7fELF", "application/x-executable"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (
+            b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1",
+            "application/x-ole-storage",
+        ),
+        (b"Rar!\x1a\x07", "application/vnd.rar"),
+        (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"{\\rtf", "text/rtf"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+    ];
+    if let Some((_, t)) = MAGIC.iter().find(|(m, _)| content.starts_with(m)) {
+        return Some(t);
+    }
+
+    let head = String::from_utf8_lossy(&content[..content.len().min(256)]).to_lowercase();
+    let head = head.trim_start();
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        return Some("text/html");
+    }
+    None
+}
+
+/// split header section and body
+fn split_message(raw: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let (header, body) = if raw.starts_with(b"\r\n") {
+        // a part without headers
+        (&raw[..0], &raw[2..])
+    } else if raw.starts_with(b"\n") {
+        (&raw[..0], &raw[1..])
+    } else {
+        match (find(raw, b"\r\n\r\n"), find(raw, b"\n\n")) {
+            (Some(i), Some(j)) if j < i => (&raw[..j], &raw[j + 2..]),
+            (Some(i), _) => (&raw[..i], &raw[i + 4..]),
+            (None, Some(j)) => (&raw[..j], &raw[j + 2..]),
+            (None, None) => (raw, &raw[raw.len()..]),
+        }
+    };
+
+    let mut headers: Vec<(String, String)> = vec![];
+    for line in String::from_utf8_lossy(header).lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            // folded line
+            if let Some((_, v)) = headers.last_mut() {
+                v.push(' ');
+                v.push_str(line.trim());
+            }
+        } else if let Some(i) = line.find(':') {
+            headers.push((
+                line[..i].trim().to_lowercase(),
+                line[i + 1..].trim().to_owned(),
+            ));
+        }
+    }
+    (headers, body)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (c, _) => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// split value of structured header (e.g. `Content-Type`) into lowercased value and parameters
+///
+/// RFC 2231 extended / continued parameters (e.g. `filename*0*=utf-8''a%20b`) are joined and decoded.
+fn parse_params(value: &str) -> (String, BTreeMap<String, String>) {
+    let mut items = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => {
+                current.push(c);
+                escaped = false;
+            }
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+
+    let main = items[0].trim().to_lowercase();
+    // name -> (section -> (value, extended))
+    let mut sections: BTreeMap<String, BTreeMap<u32, (String, bool)>> = BTreeMap::new();
+    for item in &items[1..] {
+        let i = match item.find('=') {
+            Some(i) => i,
+            None => continue,
+        };
+        let key = item[..i].trim().to_lowercase();
+        let v = item[i + 1..].trim().to_owned();
+        let (key, extended) = match key.strip_suffix('*') {
+            Some(k) => (k.to_owned(), true),
+            None => (key, false),
+        };
+        let (name, section) = match key.rfind('*') {
+            Some(j) => match key[j + 1..].parse::<u32>() {
+                Ok(n) => (key[..j].to_owned(), n),
+                Err(_) => (key.clone(), 0),
+            },
+            None => (key.clone(), 0),
+        };
+        sections
+            .entry(name)
+            .or_default()
+            .insert(section, (v, extended));
+    }
+
+    let params = sections
+        .into_iter()
+        .map(|(name, parts)| {
+            let mut bytes = vec![];
+            for (n, (v, extended)) in parts {
+                if !extended {
+                    bytes.extend_from_slice(v.as_bytes());
+                    continue;
+                }
+                // charset and language are only in the first section
+                let v = match (n, v.splitn(3, '\'').collect::<Vec<_>>().as_slice()) {
+                    (0, [_, _, x]) => x.to_string(),
+                    _ => v.clone(),
+                };
+                bytes.extend(percent_decode(&v));
+            }
+            (name, String::from_utf8_lossy(&bytes).into_owned())
+        })
+        .collect();
+
+    (main, params)
+}
+
+/// decode RFC 2047 encoded words (e.g. `=?UTF-8?B?...?=`)
+fn decode_words(s: &str) -> String {
+    let s = BETWEEN_ENCODED_WORDS.replace_all(s, "?==?");
+    ENCODED_WORD
+        .replace_all(&s, |c: &regex::Captures<'_>| {
+            let bytes = if c[2].eq_ignore_ascii_case("b") {
+                decode_base64(c[3].as_bytes())
+            } else {
+                decode_quoted_printable(c[3].replace('_', " ").as_bytes())
+            };
+            String::from_utf8_lossy(&bytes).into_owned()
+        })
+        .into_owned()
+}
+
+/// decode base64 ignoring characters out of alphabet (e.g. line breaks)
+fn decode_base64(input: &[u8]) -> Vec<u8> {
+    let value = |c: u8| -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some(u32::from(c - b'A')),
+            b'a'..=b'z' => Some(u32::from(c - b'a') + 26),
+            b'0'..=b'9' => Some(u32::from(c - b'0') + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    };
+
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for &c in input {
+        if c == b'=' {
+            break;
+        }
+        if let Some(v) = value(c) {
+            acc = acc << 6 | v;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                out.push((acc >> bits) as u8);
+                acc &= (1 << bits) - 1;
+            }
+        }
+    }
+    out
+}
+
+fn decode_quoted_printable(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] != b'=' {
+            out.push(input[i]);
+            i += 1;
+            continue;
+        }
+        // soft line break
+        if input[i + 1..].starts_with(b"\r\n") {
+            i += 3;
+            continue;
+        }
+        if input[i + 1..].starts_with(b"\n") {
+            i += 2;
+            continue;
+        }
+        match input
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok())
+        {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(b'=');
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// contents of parts in multipart body
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = vec![];
+    let mut start: Option<usize> = None;
+    let mut offset = 0;
+    for line in body.split_inclusive(|&c| c == b'\n') {
+        let text = String::from_utf8_lossy(line);
+        let text = text.trim_end();
+        if text.starts_with(&delimiter) {
+            if let Some(s) = start {
+                // line break before delimiter belongs to the delimiter
+                let mut end = offset;
+                if body[..end].ends_with(b"\n") {
+                    end -= 1;
+                }
+                if body[..end].ends_with(b"\r") {
+                    end -= 1;
+                }
+                parts.push(&body[s..end.max(s)]);
+            }
+            if text[delimiter.len()..].starts_with("--") {
+                return parts;
+            }
+            start = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+    // missing close delimiter
+    if let Some(s) = start {
+        parts.push(&body[s..]);
+    }
+    parts
+}
+
+fn walk(
+    raw: &[u8],
+    options: &EmlOptions,
+    depth: usize,
+    parts: &mut Vec<MimePart>,
+    index: &mut usize,
+) {
+    let (headers, body) = split_message(raw);
+    let (content_type, type_params) =
+        parse_params(header(&headers, "content-type").unwrap_or("text/plain"));
+
+    if content_type.starts_with("multipart/") && depth < MAX_DEPTH {
+        if let Some(boundary) = type_params.get("boundary") {
+            for part in split_multipart(body, boundary) {
+                walk(part, options, depth + 1, parts, index);
+            }
+            return;
+        }
+    }
+
+    let (disposition, disposition_params) =
+        parse_params(header(&headers, "content-disposition").unwrap_or(""));
+    let filename = disposition_params
+        .get("filename")
+        .or_else(|| type_params.get("name"))
+        .map(|x| decode_words(x));
+    let is_attachment = disposition == "attachment" || filename.is_some();
+
+    let i = *index;
+    *index += 1;
+    if options.attachments_only && !is_attachment {
+        return;
+    }
+
+    let encoding = header(&headers, "content-transfer-encoding")
+        .unwrap_or("7bit")
+        .trim()
+        .to_lowercase();
+    let content = match encoding.as_str() {
+        "base64" => decode_base64(body),
+        "quoted-printable" => decode_quoted_printable(body),
+        _ => body.to_vec(),
+    };
+
+    parts.push(MimePart {
+        index: i,
+        content_type: if content_type.is_empty() {
+            "text/plain".to_owned()
+        } else {
+            content_type
+        },
+        sniffed_type: if options.sniff {
+            sniff(&content).map(|x| x.to_owned())
+        } else {
+            None
+        },
+        filename,
+        is_attachment,
+        size: content.len(),
+        hashes: ContentHash::of_bytes(&content),
+    });
+}
+
+/// decoded leaf MIME parts of a mail
+///
+/// # Example
+///
+/// ```
+/// use iocutil::eml::{parts, EmlOptionsBuilder};
+/// use iocutil::prelude::*;
+///
+/// let mail = concat!(
+///     "From: alice@example.com\r\n",
+///     "Subject: invoice\r\n",
+///     "MIME-Version: 1.0\r\n",
+///     "Content-Type: multipart/mixed; boundary=\"b1\"\r\n",
+///     "\r\n",
+///     "--b1\r\n",
+///     "Content-Type: text/plain; charset=utf-8\r\n",
+///     "\r\n",
+///     "see attached\r\n",
+///     "--b1\r\n",
+///     "Content-Type: application/octet-stream; name=\"=?UTF-8?B?aW52b2ljZS5leGU=?=\"\r\n",
+///     "Content-Disposition: attachment\r\n",
+///     "Content-Transfer-Encoding: base64\r\n",
+///     "\r\n",
+///     "TVqQAAMAAAAEAAAA\r\n",
+///     "--b1--\r\n",
+/// );
+///
+/// let options = EmlOptionsBuilder::default().sniff(true).build().unwrap();
+/// let parts = parts(mail, &options);
+/// assert_eq!(parts.len(), 2);
+/// assert_eq!(parts[0].hashes, ContentHash::of_bytes("see attached"));
+///
+/// let attachment = &parts[1];
+/// assert_eq!(attachment.index, 1);
+/// assert_eq!(attachment.filename.as_deref(), Some("invoice.exe"));
+/// assert_eq!(attachment.sniffed_type.as_deref(), Some("application/x-dosexec"));
+/// assert_eq!(attachment.hashes, ContentHash::of_bytes(b"MZ\x90\x00\x03\x00\x00\x00\x04\x00\x00\x00"));
+/// ```
+pub fn parts(raw: impl AsRef<[u8]>, options: &EmlOptions) -> Vec<MimePart> {
+    let mut parts = vec![];
+    walk(raw.as_ref(), options, 0, &mut parts, &mut 0);
+    parts
+}
+
+/// decoded leaf MIME parts of a mail file
+pub fn parts_of_file(path: impl AsRef<Path>, options: &EmlOptions) -> GenericResult<Vec<MimePart>> {
+    Ok(parts(std::fs::read(path)?, options))
+}
+
+/// hashes of parts of a mail as indicators
+pub fn indicators(raw: impl AsRef<[u8]>, options: &EmlOptions) -> Vec<Indicator> {
+    parts(raw, options)
+        .iter()
+        .flat_map(|x| x.indicators())
+        .collect()
+}
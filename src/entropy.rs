@@ -0,0 +1,249 @@
+//! Shannon entropy and character classes of strings, to tell random-looking labels, URL paths
+//! and file names (e.g. `a8Fk2Lq9xZ.exe`) from readable ones
+//!
+//! `Ioc::randomness` applies `analyze` to the part of each kind which adversaries generate: the
+//! label of registrable domain, the host label and path segments of URLs, and the local part of
+//! email addresses. See `iocutil::dga` for domains specifically.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Shannon entropy in bits per character (0 for empty strings)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::entropy::shannon_entropy;
+///
+/// assert_eq!(shannon_entropy("aaaa"), 0.0);
+/// assert_eq!(shannon_entropy("abab"), 1.0);
+/// assert_eq!(shannon_entropy("abcd"), 2.0);
+/// ```
+pub fn shannon_entropy(s: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in s.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    let len = s.chars().count() as f64;
+    counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum::<f64>()
+        .abs()
+}
+
+/// entropy relative to the maximum for the length (1 if all characters are distinct,
+/// 0 for strings shorter than 2 characters)
+pub fn normalized_entropy(s: &str) -> f64 {
+    let len = s.chars().count();
+    if len < 2 {
+        0.0
+    } else {
+        shannon_entropy(s) / (len as f64).log2()
+    }
+}
+
+/// class of characters
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Class {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+    Other,
+}
+
+impl Class {
+    fn of(c: char) -> Class {
+        match c {
+            'a'..='z' => Class::Lowercase,
+            'A'..='Z' => Class::Uppercase,
+            '0'..='9' => Class::Digit,
+            c if c.is_ascii() => Class::Symbol,
+            _ => Class::Other,
+        }
+    }
+}
+
+/// counts of characters by class
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CharClasses {
+    pub lowercase: usize,
+    pub uppercase: usize,
+    pub digits: usize,
+    /// ASCII punctuation, spaces and controls
+    pub symbols: usize,
+    /// non-ASCII characters
+    pub other: usize,
+    /// changes of class between adjacent characters (e.g. 3 in `aB1c`, but none in `Ab`)
+    pub switches: usize,
+}
+
+impl CharClasses {
+    /// counts of the string
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::entropy::CharClasses;
+    ///
+    /// let c = CharClasses::of("x9Kq-2");
+    /// assert_eq!((c.lowercase, c.uppercase, c.digits, c.symbols), (2, 1, 2, 1));
+    /// assert_eq!(c.switches, 4);
+    /// assert_eq!(c.total(), 6);
+    /// ```
+    pub fn of(s: &str) -> Self {
+        let mut classes = CharClasses::default();
+        let mut last: Option<Class> = None;
+        for class in s.chars().map(Class::of) {
+            match class {
+                Class::Lowercase => classes.lowercase += 1,
+                Class::Uppercase => classes.uppercase += 1,
+                Class::Digit => classes.digits += 1,
+                Class::Symbol => classes.symbols += 1,
+                Class::Other => classes.other += 1,
+            }
+            // cases of letters are switched only in mixed case (e.g. `aB`), not after capitals
+            let letter = |x: Class| matches!(x, Class::Lowercase | Class::Uppercase);
+            if let Some(last) = last {
+                let switched = if letter(last) && letter(class) {
+                    last == Class::Lowercase && class == Class::Uppercase
+                } else {
+                    last != class
+                };
+                if switched {
+                    classes.switches += 1;
+                }
+            }
+            last = Some(class);
+        }
+        classes
+    }
+
+    /// number of characters
+    pub fn total(&self) -> usize {
+        self.lowercase + self.uppercase + self.digits + self.symbols + self.other
+    }
+
+    /// ratio of digits to characters
+    pub fn digit_ratio(&self) -> f64 {
+        ratio(self.digits, self.total())
+    }
+
+    /// ratio of class switches to adjacent pairs
+    pub fn switch_ratio(&self) -> f64 {
+        ratio(self.switches, self.total().saturating_sub(1))
+    }
+}
+
+fn ratio(n: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        n as f64 / total as f64
+    }
+}
+
+/// randomness of a string
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Randomness {
+    /// string analyzed
+    pub text: String,
+    /// Shannon entropy in bits per character
+    pub entropy: f64,
+    /// entropy relative to the maximum for the length
+    pub normalized_entropy: f64,
+    pub classes: CharClasses,
+    /// from 0 (readable) to 1 (random)
+    pub score: f64,
+}
+
+impl Randomness {
+    /// looks random (score is 0.6 or higher)?
+    pub fn is_random(&self) -> bool {
+        self.score >= 0.6
+    }
+}
+
+/// randomness of the string
+///
+/// Scores are high for long strings of distinct characters switching between classes often,
+/// and scaled down for strings shorter than 8 characters.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::entropy::analyze;
+///
+/// assert!(analyze("a8Fk2Lq9xZ").is_random());
+/// assert!(analyze("7hX2kQ9pWm4z").is_random());
+/// assert!(!analyze("invoice").is_random());
+/// assert!(!analyze("wp-content").is_random());
+/// assert!(!analyze("index").is_random());
+/// ```
+pub fn analyze(s: &str) -> Randomness {
+    let classes = CharClasses::of(s);
+    let len = classes.total();
+    let normalized = normalized_entropy(s);
+    // readable words of 6 or more characters mostly repeat some of them
+    let distinct = ((normalized - 0.85) / 0.15).clamp(0.0, 1.0);
+    let switches = (classes.switch_ratio() * 2.5).min(1.0);
+    let digits = (classes.digit_ratio() * 2.5).min(1.0);
+    let mut score = 0.4 * distinct + 0.4 * switches + 0.2 * digits;
+    if len < 8 {
+        score *= len as f64 / 8.0;
+    }
+    Randomness {
+        text: s.to_owned(),
+        entropy: shannon_entropy(s),
+        normalized_entropy: normalized,
+        classes,
+        score,
+    }
+}
+
+/// randomness of the file name without extension
+/// (e.g. of `a8Fk2Lq9xZ` for `C:\Users\Public\a8Fk2Lq9xZ.exe`)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::entropy::filename_randomness;
+///
+/// assert!(filename_randomness(r"C:\Users\Public\a8Fk2Lq9xZ.exe").is_random());
+/// assert!(!filename_randomness("/tmp/quarterly_report.pdf").is_random());
+/// ```
+pub fn filename_randomness(path: &str) -> Randomness {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let stem = match name.rfind('.') {
+        Some(i) if i > 0 => &name[..i],
+        _ => name,
+    };
+    analyze(stem)
+}
+
+/// the most random segment of the URL path (file names without extension)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::entropy::path_randomness;
+///
+/// let r = path_randomness("/wp-content/uploads/Xk29fQz7Lp.php").unwrap();
+/// assert_eq!(r.text, "Xk29fQz7Lp");
+/// assert!(r.is_random());
+/// assert!(path_randomness("/").is_none());
+/// ```
+pub fn path_randomness(path: &str) -> Option<Randomness> {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    path.split('/')
+        .filter(|x| !x.is_empty())
+        .map(filename_randomness)
+        .fold(None, |best: Option<Randomness>, x| match best {
+            Some(best) if best.score >= x.score => Some(best),
+            _ => Some(x),
+        })
+}
@@ -0,0 +1,216 @@
+//! classified errors (parse / auth / quota / not found / transport) for retry and alerting logic
+//!
+//! Functions of this crate return `failure::Error`, and errors classified here can be picked out
+//! of it by `classify` (or `failure::Error::downcast_ref`).
+
+use std::time::Duration;
+
+/// classified error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// input could not be parsed (e.g. invalid hash or unknown kind of indicator)
+    #[error("could not parse as {kind}: {value}")]
+    Parse { kind: &'static str, value: String },
+
+    /// credentials are rejected
+    #[error("{provider}: authentication failed (status {status})")]
+    Auth { provider: &'static str, status: u16 },
+
+    /// no API key of the provider is given
+    #[error("{provider}: API key is not set")]
+    MissingKey { provider: &'static str },
+
+    /// rate limit or quota is exhausted (`retry_after` is given by the provider)
+    #[error("{provider}: quota exhausted")]
+    Quota {
+        provider: &'static str,
+        retry_after: Option<Duration>,
+    },
+
+    /// the provider does not know the resource
+    #[error("{provider}: not found")]
+    NotFound { provider: &'static str },
+
+    /// the provider responded other error status
+    #[error("{provider}: request failed (status {status})")]
+    Status { provider: &'static str, status: u16 },
+
+    /// the request did not complete (e.g. connection refused or timed out)
+    #[error("{provider}: transport error: {source}")]
+    Transport {
+        provider: &'static str,
+        retryable: bool,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl Error {
+    pub(crate) fn parse(kind: &'static str, value: impl Into<String>) -> Self {
+        Error::Parse {
+            kind,
+            value: value.into(),
+        }
+    }
+
+    /// classify error status of provider
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::error::Error;
+    ///
+    /// assert!(Error::from_status("virustotal", 403).is_auth());
+    /// assert!(Error::from_status("virustotal", 429).is_quota());
+    /// assert!(Error::from_status("virustotal", 404).is_not_found());
+    /// assert!(Error::from_status("virustotal", 503).is_retryable());
+    /// assert!(!Error::from_status("virustotal", 400).is_retryable());
+    /// ```
+    pub fn from_status(provider: &'static str, status: u16) -> Self {
+        match status {
+            401 | 403 => Error::Auth { provider, status },
+            429 => Error::Quota {
+                provider,
+                retry_after: None,
+            },
+            404 => Error::NotFound { provider },
+            _ => Error::Status { provider, status },
+        }
+    }
+
+    /// classify error response of provider (honoring `Retry-After`)
+    #[cfg(feature = "net")]
+    pub(crate) fn from_response(provider: &'static str, res: &reqwest::Response) -> Self {
+        match Error::from_status(provider, res.status().as_u16()) {
+            Error::Quota { provider, .. } => Error::Quota {
+                provider,
+                retry_after: crate::retry::retry_after(res),
+            },
+            e => e,
+        }
+    }
+
+    /// request failed before response
+    #[cfg(feature = "net")]
+    pub(crate) fn transport(provider: &'static str, e: reqwest::Error) -> Self {
+        Error::Transport {
+            provider,
+            retryable: e.is_timeout() || e.is_connect(),
+            source: Box::new(e),
+        }
+    }
+
+    /// same error (source of transport error is kept as its message) to share with other callers
+    #[cfg(feature = "net")]
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            Error::Parse { kind, value } => Error::Parse {
+                kind,
+                value: value.clone(),
+            },
+            Error::Auth { provider, status } => Error::Auth {
+                provider,
+                status: *status,
+            },
+            Error::Quota {
+                provider,
+                retry_after,
+            } => Error::Quota {
+                provider,
+                retry_after: *retry_after,
+            },
+            Error::MissingKey { provider } => Error::MissingKey { provider },
+            Error::NotFound { provider } => Error::NotFound { provider },
+            Error::Status { provider, status } => Error::Status {
+                provider,
+                status: *status,
+            },
+            Error::Transport {
+                provider,
+                retryable,
+                source,
+            } => Error::Transport {
+                provider,
+                retryable: *retryable,
+                source: source.to_string().into(),
+            },
+        }
+    }
+
+    /// name of provider (`None` for parse errors)
+    pub fn provider(&self) -> Option<&'static str> {
+        match self {
+            Error::Parse { .. } => None,
+            Error::Auth { provider, .. }
+            | Error::MissingKey { provider }
+            | Error::Quota { provider, .. }
+            | Error::NotFound { provider }
+            | Error::Status { provider, .. }
+            | Error::Transport { provider, .. } => Some(provider),
+        }
+    }
+
+    /// worth retrying later? (quota, server errors, timeouts and connection failures)
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Quota { .. } => true,
+            Error::Status { status, .. } => *status == 408 || *status >= 500,
+            Error::Transport { retryable, .. } => *retryable,
+            _ => false,
+        }
+    }
+
+    /// rate limit or quota is exhausted?
+    pub fn is_quota(&self) -> bool {
+        matches!(self, Error::Quota { .. })
+    }
+
+    /// credentials are missing or rejected?
+    pub fn is_auth(&self) -> bool {
+        matches!(self, Error::Auth { .. } | Error::MissingKey { .. })
+    }
+
+    /// the provider does not know the resource?
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::NotFound { .. })
+    }
+
+    /// delay requested by the provider before retrying
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::Quota { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// classified error in the error (`None` if it is not classified)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::error::classify;
+/// use iocutil::prelude::*;
+///
+/// let e = Ioc::new("not an indicator").unwrap_err();
+/// let classified = classify(&e).unwrap();
+/// assert!(!classified.is_retryable());
+/// assert_eq!(classified.to_string(), "could not parse as indicator: not an indicator");
+/// ```
+pub fn classify(e: &failure::Error) -> Option<&Error> {
+    e.downcast_ref::<Error>()
+}
+
+/// the error is worth retrying later? (unclassified errors are not)
+pub fn is_retryable(e: &failure::Error) -> bool {
+    if let Some(e) = classify(e) {
+        return e.is_retryable();
+    }
+    #[cfg(feature = "net")]
+    {
+        if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+            return e.is_timeout() || e.is_connect();
+        }
+    }
+    false
+}
@@ -0,0 +1,379 @@
+//! CrowdStrike Falcon IOC Management API client
+
+use chrono::{DateTime, Utc};
+use derive_builder::Builder;
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::net::IpAddr;
+use time::Duration;
+
+use crate::http::{block_on, ClientConfig};
+use crate::ioc::{Indicator, Ioc};
+use crate::keys::ApiKeyProvider;
+use crate::nonblocking;
+use crate::tlp::Audience;
+use crate::{GenericResult, SampleHash};
+
+/// max number of indicators in a create request
+pub const MAX_BATCH_SIZE: usize = 200;
+
+/// client for Falcon IOC Management API (default use `$FALCON_TOKEN` environment variable as access token)
+#[derive(Default)]
+pub struct FalconClient {
+    inner: nonblocking::FalconClient,
+}
+
+/// Errors in operating Falcon
+#[derive(Fail, Debug)]
+pub enum FalconError {
+    #[fail(
+        display = "Falcon does not accept this kind of indicator (sha1, url and email are not supported)"
+    )]
+    UnsupportedIndicator(String),
+}
+
+/// action applied when the indicator matched
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    NoAction,
+    Allow,
+    Detect,
+    Prevent,
+    PreventNoUi,
+}
+
+/// severity of the indicator
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Informational,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// platform which the indicator is applied to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Windows,
+    Mac,
+    Linux,
+}
+
+/// options of created indicators
+#[derive(Builder, Debug, Clone)]
+pub struct FalconIocOptions {
+    #[builder(default = "Action::Detect")]
+    action: Action,
+    #[builder(default = "Severity::Medium")]
+    severity: Severity,
+    #[builder(default = "vec![Platform::Windows, Platform::Mac, Platform::Linux]")]
+    platforms: Vec<Platform>,
+    /// indicators expire after this duration from creation (never expire if None), unless
+    /// `valid_until` is set
+    #[builder(default = "Some(Duration::days(30))")]
+    expiration: Option<Duration>,
+    #[builder(setter(into), default = "\"created by iocutil.rs\".to_owned()")]
+    description: String,
+    #[builder(setter(into), default = "\"iocutil.rs\".to_owned()")]
+    source: String,
+    /// audience of the tenant (creation fails if TLP of any indicator does not allow it)
+    #[builder(default = "Audience::Organization")]
+    pub(crate) audience: Audience,
+    /// upload expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    pub(crate) include_expired: bool,
+}
+
+impl Default for FalconIocOptions {
+    fn default() -> Self {
+        FalconIocOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// a custom indicator on Falcon
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FalconIndicator {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub _type: String,
+    pub value: String,
+    pub action: Option<Action>,
+    pub severity: Option<Severity>,
+    pub platforms: Option<Vec<Platform>>,
+    pub expiration: Option<String>,
+    pub expired: Option<bool>,
+    pub description: Option<String>,
+    pub source: Option<String>,
+    pub created_on: Option<String>,
+    pub modified_on: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct Resources<T> {
+    pub(crate) resources: Option<Vec<T>>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TokenResponse {
+    pub(crate) access_token: String,
+}
+
+/// type and value of indicator on Falcon
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::falcon::falcon_type;
+///
+/// let (t, v) = falcon_type(&Ioc::new("Example.com").unwrap()).unwrap();
+/// assert_eq!((t, v.as_str()), ("domain", "example.com"));
+///
+/// assert!(falcon_type(&Ioc::new("da39a3ee5e6b4b0d3255bfef95601890afd80709").unwrap()).is_err());
+/// ```
+pub fn falcon_type(ioc: &Ioc) -> GenericResult<(&'static str, String)> {
+    let t = match ioc {
+        Ioc::Hash(SampleHash::Sha256(_)) => "sha256",
+        Ioc::Hash(SampleHash::Md5(_)) => "md5",
+        Ioc::Domain(_) => "domain",
+        Ioc::IpAddress(IpAddr::V4(_)) => "ipv4",
+        Ioc::IpAddress(IpAddr::V6(_)) => "ipv6",
+        _ => return Err(FalconError::UnsupportedIndicator(ioc.value()).into()),
+    };
+    Ok((t, ioc.value()))
+}
+
+/// convert an indicator into Falcon custom indicator object
+///
+/// `valid_until` of the indicator takes precedence over `expiration` of options, and its TLP is
+/// tagged (e.g. `tlp:amber`).
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::falcon::{to_falcon_indicator, Action, FalconIocOptionsBuilder, Platform, Severity};
+/// use iocutil::tlp::Tlp;
+///
+/// let options = FalconIocOptionsBuilder::default()
+///     .action(Action::Prevent)
+///     .severity(Severity::High)
+///     .platforms(vec![Platform::Windows])
+///     .expiration(None)
+///     .build()
+///     .unwrap();
+///
+/// let ioc = Ioc::new("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap();
+/// let v = to_falcon_indicator(ioc.clone(), &options, chrono::Utc::now()).unwrap();
+/// assert_eq!(v["type"], "sha256");
+/// assert_eq!(v["action"], "prevent");
+/// assert_eq!(v["severity"], "high");
+/// assert_eq!(v["platforms"][0], "windows");
+/// assert!(v.get("expiration").is_none());
+///
+/// let mut i = Indicator::new(ioc);
+/// i.meta.valid_until = Some(day!(2019, 11, 3).unwrap().into());
+/// i.meta.tlp = Some(Tlp::Amber);
+/// let v = to_falcon_indicator(i, &options, chrono::Utc::now()).unwrap();
+/// assert_eq!(v["expiration"], "2019-11-03T00:00:00+00:00");
+/// assert_eq!(v["tags"][0], "tlp:amber");
+/// ```
+pub fn to_falcon_indicator(
+    indicator: impl Into<Indicator>,
+    options: &FalconIocOptions,
+    now: DateTime<Utc>,
+) -> GenericResult<serde_json::Value> {
+    let Indicator { ioc, meta } = indicator.into();
+    let (t, value) = falcon_type(&ioc)?;
+    let mut v = json!({
+        "type": t,
+        "value": value,
+        "action": options.action,
+        "severity": options.severity,
+        "platforms": options.platforms,
+        "description": options.description,
+        "source": options.source,
+        "applied_globally": true,
+    });
+    if let Some(e) = meta
+        .valid_until
+        .or_else(|| options.expiration.map(|x| now + x))
+    {
+        v["expiration"] = json!(e.to_rfc3339());
+    }
+    if let Some(tlp) = meta.tlp {
+        v["tags"] = json!([tlp.misp_tag()]);
+    }
+    Ok(v)
+}
+
+impl FalconClient {
+    /// new client with access token (for US-1 cloud)
+    pub fn new(token: impl AsRef<str>) -> Self {
+        FalconClient {
+            inner: nonblocking::FalconClient::new(token),
+        }
+    }
+
+    /// new client with access token of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(FalconClient {
+            inner: nonblocking::FalconClient::from_keys(keys)?,
+        })
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.inner = self.inner.with_config(config);
+        self
+    }
+
+    /// set base url for other clouds (e.g. `https://api.eu-1.crowdstrike.com`)
+    pub fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
+        self.inner = self.inner.with_base_url(base_url);
+        self
+    }
+
+    /// new client with access token acquired by API client credentials (for US-1 cloud)
+    /// (the API client requires IOC Management read/write scope)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::falcon::FalconClient;
+    ///
+    /// let client = FalconClient::from_client_credentials("client id", "secret")
+    ///     .expect("failed to authenticate");
+    /// ```
+    pub fn from_client_credentials(
+        client_id: impl AsRef<str>,
+        client_secret: impl AsRef<str>,
+    ) -> GenericResult<Self> {
+        let inner = block_on(nonblocking::FalconClient::from_client_credentials(
+            client_id,
+            client_secret,
+        ))?;
+        Ok(FalconClient { inner })
+    }
+
+    /// create custom indicators (split into batches of `MAX_BATCH_SIZE`)
+    /// returns created indicators. It stops at the first failed batch.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::falcon::{FalconClient, FalconIocOptions};
+    ///
+    /// let client = FalconClient::default();
+    /// let iocs = vec![Ioc::new("evil.example.com").unwrap()];
+    /// let created = client.create(iocs, &FalconIocOptions::default()).expect("failed to create");
+    /// ```
+    ///
+    /// indicators are checked by TLP before creation
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::falcon::{FalconClient, FalconIocOptionsBuilder};
+    /// use iocutil::tlp::{Audience, Tlp};
+    ///
+    /// let client = FalconClient::new("token");
+    /// let options = FalconIocOptionsBuilder::default()
+    ///     .audience(Audience::Clients)
+    ///     .build()
+    ///     .unwrap();
+    /// let mut i = Indicator::new(Ioc::new("evil.example.com").unwrap());
+    /// i.meta.tlp = Some(Tlp::AmberStrict);
+    /// let err = client.create(vec![i], &options).unwrap_err();
+    /// assert_eq!(err.to_string(), "TLP:AMBER+STRICT does not allow sharing with Clients");
+    /// ```
+    ///
+    /// indicators allowed by the export allowlist are not created
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::allowlist::{self, FileList};
+    /// use iocutil::falcon::{FalconClient, FalconIocOptions};
+    ///
+    /// allowlist::set_export_allowlist(FileList::parse("ours", "192.0.2.0/24").unwrap());
+    ///
+    /// let client = FalconClient::new("token");
+    /// let iocs = vec![Ioc::new("192.0.2.1").unwrap()];
+    /// let (created, suppressed) = allowlist::audited(|| client.create(iocs, &FalconIocOptions::default()));
+    /// assert!(created.unwrap().is_empty());
+    /// assert_eq!(suppressed[0].ioc, Ioc::new("192.0.2.1").unwrap());
+    /// ```
+    pub fn create(
+        &self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+        options: &FalconIocOptions,
+    ) -> GenericResult<Vec<FalconIndicator>> {
+        block_on(self.inner.create(indicators, options))
+    }
+
+    /// query ids of custom indicators with FQL filter
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::falcon::FalconClient;
+    ///
+    /// let client = FalconClient::default();
+    /// let ids = client.query("type:'domain'+severity:'high'").expect("failed to query");
+    /// ```
+    pub fn query(&self, filter: impl AsRef<str>) -> GenericResult<Vec<String>> {
+        block_on(self.inner.query(filter))
+    }
+
+    /// find ids of custom indicators of an indicator
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::falcon::FalconClient;
+    ///
+    /// let client = FalconClient::default();
+    /// let ids = client.find(&Ioc::new("evil.example.com").unwrap()).expect("failed to query");
+    /// ```
+    pub fn find(&self, ioc: &Ioc) -> GenericResult<Vec<String>> {
+        block_on(self.inner.find(ioc))
+    }
+
+    /// get details of custom indicators
+    pub fn get(
+        &self,
+        ids: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> GenericResult<Vec<FalconIndicator>> {
+        block_on(self.inner.get(ids))
+    }
+
+    /// expire custom indicators now (they remain in console as expired)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::falcon::FalconClient;
+    ///
+    /// let client = FalconClient::default();
+    /// let ids = client.find(&Ioc::new("evil.example.com").unwrap()).unwrap();
+    /// client.expire(ids).expect("failed to expire");
+    /// ```
+    pub fn expire(
+        &self,
+        ids: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> GenericResult<Vec<FalconIndicator>> {
+        block_on(self.inner.expire(ids))
+    }
+
+    /// delete custom indicators
+    pub fn delete(&self, ids: impl IntoIterator<Item = impl AsRef<str>>) -> GenericResult<()> {
+        block_on(self.inner.delete(ids))
+    }
+}
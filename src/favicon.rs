@@ -0,0 +1,102 @@
+//! favicon hash (MurmurHash3 of base64 encoded favicon, `http.favicon.hash` in Shodan)
+
+use crate::fingerprint::Fingerprint;
+#[cfg(feature = "net")]
+use crate::http::{self, block_on};
+#[cfg(feature = "net")]
+use crate::GenericResult;
+
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// base64 with a newline after every 76 characters and at the end (same as python's `base64.encodebytes`)
+fn encode_base64_lines(bytes: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    // 57 bytes are encoded into 76 characters
+    for line in bytes.chunks(57) {
+        for c in line.chunks(3) {
+            let n = u32::from(c[0]) << 16
+                | u32::from(*c.get(1).unwrap_or(&0)) << 8
+                | u32::from(*c.get(2).unwrap_or(&0));
+            for i in 0..4 {
+                if i <= c.len() {
+                    out.push(B64[(n >> (18 - 6 * i) & 0x3f) as usize]);
+                } else {
+                    out.push(b'=');
+                }
+            }
+        }
+        out.push(b'\n');
+    }
+    out
+}
+
+/// MurmurHash3 (x86, 32 bit)
+fn murmur3_32(bytes: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut h = seed;
+    let mut blocks = bytes.chunks_exact(4);
+    for b in &mut blocks {
+        h ^= mix(u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .rev()
+            .fold(0u32, |acc, &b| acc << 8 | u32::from(b));
+        h ^= mix(k);
+    }
+
+    h ^= bytes.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ h >> 16
+}
+
+/// favicon hash of favicon bytes
+///
+/// # Example
+///
+/// ```
+/// use iocutil::favicon::favicon_hash;
+///
+/// let hash = favicon_hash(b"\x00\x00\x01\x00");
+/// assert_eq!(hash.value(), "-216455174");
+/// assert_eq!(hash.shodan_query(), Some("http.favicon.hash:-216455174".to_owned()));
+/// ```
+pub fn favicon_hash(bytes: impl AsRef<[u8]>) -> Fingerprint {
+    let hash = murmur3_32(&encode_base64_lines(bytes.as_ref()), 0) as i32;
+    Fingerprint::FaviconHash(hash.to_string())
+}
+
+/// fetch favicon from url (e.g. `https://example.com/favicon.ico`) and calculate favicon hash
+///
+/// # Example
+///
+/// ```ignore
+/// use iocutil::favicon::fetch_favicon_hash;
+///
+/// let hash = fetch_favicon_hash("https://example.com/favicon.ico").unwrap();
+/// println!("{}", hash.shodan_query().unwrap());
+/// ```
+#[cfg(feature = "net")]
+pub fn fetch_favicon_hash(url: impl AsRef<str>) -> GenericResult<Fingerprint> {
+    let bytes = block_on(async {
+        http::shared_client()
+            .get(url.as_ref())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+    })?;
+    Ok(favicon_hash(bytes))
+}
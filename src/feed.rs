@@ -0,0 +1,323 @@
+//! plain-text feed (one defanged indicator per line, `#` for comments)
+//!
+//! ```text
+//! # Emotet C2 (2019-11-20)
+//! hxxp://evil[.]example[.]com/a.php
+//! 192.0.2[.]1  # seen in spam
+//!
+//! # Emotet hashes
+//! d41d8cd98f00b204e9800998ecf8427e
+//! ```
+//!
+//! Indicators have the last comment line above them as `source` (a blank line resets it), and
+//! the trailing comment on the same line as `description`.
+//!
+//! `Feed::parse` keeps every line as is, so that `Feed::to_text` reproduces
+//! the same bytes unless indicators are modified (modified or added ones are written defanged).
+
+use derive_builder::Builder;
+use std::io::{Read, Write};
+
+use crate::allowlist::suppress;
+use crate::ioc::{refang, Indicator, Ioc, Metadata};
+use crate::ttl::unexpired;
+use crate::GenericResult;
+
+#[derive(Debug, Clone)]
+enum Line {
+    /// comment, blank line, or unrecognized text (kept as is)
+    Verbatim(String),
+    Entry(Box<Entry>),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    raw: String,
+    original: Indicator,
+    indicator: Indicator,
+}
+
+/// plain-text feed
+#[derive(Debug, Clone)]
+pub struct Feed {
+    /// lines and its terminators
+    lines: Vec<(Line, String)>,
+    newline: String,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Feed {
+            lines: vec![],
+            newline: "\n".to_owned(),
+        }
+    }
+}
+
+fn comment_of(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with('#') {
+        Some(line.trim_start_matches('#').trim())
+    } else {
+        None
+    }
+}
+
+/// split into value and trailing comment (`#` following whitespace, so url fragments are kept)
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut prev_space = false;
+    for (i, c) in line.char_indices() {
+        if c == '#' && prev_space {
+            return (line[..i].trim(), Some(line[i + 1..].trim()));
+        }
+        prev_space = c.is_whitespace();
+    }
+    (line.trim(), None)
+}
+
+impl Feed {
+    /// empty feed
+    pub fn new() -> Self {
+        Feed::default()
+    }
+
+    /// parse plain-text feed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::feed::Feed;
+    ///
+    /// let text = "# Emotet C2\r\nhxxp://evil[.]example[.]com/a.php\r\n192.0.2[.]1 # spam\r\n\r\nnot an indicator\r\n";
+    /// let feed = Feed::parse(text);
+    ///
+    /// let indicators: Vec<_> = feed.indicators().collect();
+    /// assert_eq!(indicators.len(), 2);
+    /// assert_eq!(indicators[0].ioc, Ioc::new("http://evil.example.com/a.php").unwrap());
+    /// assert_eq!(indicators[0].meta.source, Some("Emotet C2".to_owned()));
+    /// assert_eq!(indicators[1].meta.description, Some("spam".to_owned()));
+    ///
+    /// // unchanged feed is exported as is
+    /// assert_eq!(feed.to_text(), text);
+    /// ```
+    pub fn parse(text: impl AsRef<str>) -> Self {
+        let text = text.as_ref();
+        let mut feed = Feed::new();
+        if text.contains("\r\n") {
+            feed.newline = "\r\n".to_owned();
+        }
+
+        let mut source: Option<String> = None;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let (line, eol) = match rest.find('\n') {
+                Some(idx) if idx > 0 && rest.as_bytes()[idx - 1] == b'\r' => {
+                    (&rest[..idx - 1], "\r\n")
+                }
+                Some(idx) => (&rest[..idx], "\n"),
+                None => (rest, ""),
+            };
+            rest = &rest[line.len() + eol.len()..];
+
+            if let Some(c) = comment_of(line) {
+                source = Some(c.to_owned()).filter(|x| !x.is_empty());
+                feed.lines
+                    .push((Line::Verbatim(line.to_owned()), eol.to_owned()));
+                continue;
+            }
+
+            let (value, comment) = split_comment(line);
+            if value.is_empty() && comment.is_none() {
+                source = None;
+            }
+            let parsed = if value.is_empty() {
+                None
+            } else {
+                refang(value).parse::<Ioc>().ok()
+            };
+            let entry = match parsed {
+                Some(ioc) => {
+                    let meta = Metadata {
+                        source: source.clone(),
+                        description: comment.filter(|x| !x.is_empty()).map(|x| x.to_owned()),
+                        ..Default::default()
+                    };
+                    let indicator = Indicator::with_meta(ioc, meta);
+                    Line::Entry(Box::new(Entry {
+                        raw: line.to_owned(),
+                        original: indicator.clone(),
+                        indicator,
+                    }))
+                }
+                None => Line::Verbatim(line.to_owned()),
+            };
+            feed.lines.push((entry, eol.to_owned()));
+        }
+        feed
+    }
+
+    /// read plain-text feed
+    pub fn read_from(mut reader: impl Read) -> GenericResult<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Feed::parse(text))
+    }
+
+    /// indicators in the feed
+    pub fn indicators(&self) -> impl Iterator<Item = &Indicator> {
+        self.lines.iter().filter_map(|(l, _)| match l {
+            Line::Entry(e) => Some(&e.indicator),
+            _ => None,
+        })
+    }
+
+    /// mutable indicators in the feed (modified ones are written defanged)
+    pub fn indicators_mut(&mut self) -> impl Iterator<Item = &mut Indicator> {
+        self.lines.iter_mut().filter_map(|(l, _)| match l {
+            Line::Entry(e) => Some(&mut e.indicator),
+            _ => None,
+        })
+    }
+
+    /// append an indicator
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::feed::Feed;
+    ///
+    /// let mut feed = Feed::parse("# C2\nexample[.]com");
+    /// feed.push(Ioc::new("https://example.net/a").unwrap());
+    /// assert_eq!(feed.to_text(), "# C2\nexample[.]com\nhxxps://example[.]net/a\n");
+    /// ```
+    pub fn push(&mut self, indicator: impl Into<Indicator>) {
+        // terminate last line before appending
+        if let Some((_, eol)) = self.lines.last_mut() {
+            if eol.is_empty() {
+                *eol = self.newline.clone();
+            }
+        }
+
+        // raw is empty so that it is always written from indicator
+        let indicator = indicator.into();
+        let line = Line::Entry(Box::new(Entry {
+            raw: String::new(),
+            original: indicator.clone(),
+            indicator,
+        }));
+        self.lines.push((line, self.newline.clone()));
+    }
+
+    /// keep indicators which satisfy predicate (other lines are kept)
+    pub fn retain(&mut self, mut f: impl FnMut(&Indicator) -> bool) {
+        self.lines.retain(|(l, _)| match l {
+            Line::Entry(e) => f(&e.indicator),
+            _ => true,
+        })
+    }
+
+    /// write plain-text feed
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        for (line, eol) in &self.lines {
+            match line {
+                Line::Verbatim(x) => writer.write_all(x.as_bytes())?,
+                Line::Entry(e) => {
+                    if !e.raw.is_empty() && e.original == e.indicator {
+                        writer.write_all(e.raw.as_bytes())?;
+                    } else {
+                        writer.write_all(format_entry(&e.indicator).as_bytes())?;
+                    }
+                }
+            }
+            writer.write_all(eol.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// plain-text feed as string
+    pub fn to_text(&self) -> String {
+        let mut buf = vec![];
+        // writing into vec never fails
+        self.write_to(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+fn format_entry(indicator: &Indicator) -> String {
+    match &indicator.meta.description {
+        Some(d) => format!("{}  # {}", indicator.ioc.defanged(), d),
+        None => indicator.ioc.defanged(),
+    }
+}
+
+/// options of exported feed
+#[derive(Builder, Debug, Clone)]
+pub struct FeedOptions {
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        FeedOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// write indicators as plain-text feed (grouped by source as comment)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::feed::{to_feed, Feed};
+///
+/// let mut a = Indicator::new(Ioc::new("evil.example.com").unwrap());
+/// a.meta.source = Some("Emotet C2".to_owned());
+/// let b = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// let mut expired = Indicator::new(Ioc::new("198.51.100.1").unwrap());
+/// expired.meta.valid_until = Some(day!(2019, 11, 1).unwrap().into());
+///
+/// let text = to_feed(vec![a.clone(), b, expired], &Default::default());
+/// assert_eq!(text, "# Emotet C2\nevil[.]example[.]com\n\n192[.]0[.]2[.]1\n");
+///
+/// // byte-stable round-trip
+/// assert_eq!(Feed::parse(&text).to_text(), text);
+/// assert_eq!(Feed::parse(&text).indicators().next(), Some(&a));
+/// ```
+pub fn write_feed(
+    mut writer: impl Write,
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &FeedOptions,
+) -> std::io::Result<()> {
+    let mut source: Option<String> = None;
+    let mut first = true;
+    for indicator in suppress(unexpired(indicators, options.include_expired)) {
+        if first || indicator.meta.source != source {
+            if !first {
+                writer.write_all(b"\n")?;
+            }
+            // indicators without source after sourced ones are separated by blank line only
+            if let Some(s) = &indicator.meta.source {
+                writeln!(writer, "# {}", s)?;
+            }
+            source = indicator.meta.source.clone();
+            first = false;
+        }
+        writeln!(writer, "{}", format_entry(&indicator))?;
+    }
+    Ok(())
+}
+
+/// plain-text feed of indicators as string
+pub fn to_feed(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &FeedOptions,
+) -> String {
+    let mut buf = vec![];
+    // writing into vec never fails
+    write_feed(&mut buf, indicators, options).unwrap();
+    String::from_utf8(buf).unwrap()
+}
@@ -0,0 +1,178 @@
+//! fingerprints of samples for pivoting (e.g. `imphash:` search in VirusTotal intelligence)
+
+use failure::Fail;
+use std::str::FromStr;
+
+/// Errors in parsing fingerprints
+#[derive(Fail, Debug)]
+pub enum FingerprintError {
+    #[fail(display = "could not recognize as fingerprint: {}", _0)]
+    UnknownFingerprint(String),
+}
+
+/// a fingerprint of sample (displayed as `kind:value`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Fingerprint {
+    /// import hash of PE file (md5 of normalized import table)
+    Imphash(String),
+    /// hash of PE rich header (md5 of decoded header)
+    RichHeaderHash(String),
+    /// authenticode digest of PE file (sha256)
+    Authentihash(String),
+    /// thumbprint of signing certificate (sha1)
+    CertificateThumbprint(String),
+    /// symbol hash of ELF file (TLSH of symbol names, e.g. `t1…`)
+    Telfhash(String),
+    /// hash of favicon (MurmurHash3 of base64 encoded favicon in signed decimal)
+    FaviconHash(String),
+    /// JA3 fingerprint of TLS client (md5 of JA3 string)
+    Ja3(String),
+    /// JARM fingerprint of TLS server (62 hex characters)
+    Jarm(String),
+}
+
+impl Fingerprint {
+    /// kind of fingerprint (e.g. `imphash`)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Fingerprint::Imphash(_) => "imphash",
+            Fingerprint::RichHeaderHash(_) => "rich_pe_header_hash",
+            Fingerprint::Authentihash(_) => "authentihash",
+            Fingerprint::CertificateThumbprint(_) => "thumbprint",
+            Fingerprint::Telfhash(_) => "telfhash",
+            Fingerprint::FaviconHash(_) => "favicon_hash",
+            Fingerprint::Ja3(_) => "ja3",
+            Fingerprint::Jarm(_) => "jarm",
+        }
+    }
+
+    /// value of fingerprint
+    pub fn value(&self) -> &str {
+        match self {
+            Fingerprint::Imphash(x)
+            | Fingerprint::RichHeaderHash(x)
+            | Fingerprint::Authentihash(x)
+            | Fingerprint::CertificateThumbprint(x)
+            | Fingerprint::Telfhash(x)
+            | Fingerprint::FaviconHash(x)
+            | Fingerprint::Ja3(x)
+            | Fingerprint::Jarm(x) => x,
+        }
+    }
+
+    /// search query in VirusTotal intelligence (thumbprints are searched in signature info)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::fingerprint::Fingerprint;
+    ///
+    /// let f: Fingerprint = "imphash:F34D5F2D4577ED6D9CEEC516C1F5A744".parse().unwrap();
+    /// assert_eq!(f.vt_query(), "imphash:f34d5f2d4577ed6d9ceec516c1f5a744");
+    /// ```
+    pub fn vt_query(&self) -> String {
+        match self {
+            Fingerprint::CertificateThumbprint(x) => format!("signature:{}", x),
+            _ => format!("{}:{}", self.kind(), self.value()),
+        }
+    }
+
+    /// parameters of search in MalwareBazaar API (`None` if the kind is not searchable)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::fingerprint::Fingerprint;
+    ///
+    /// let f: Fingerprint = "telfhash:T15CE012A5627FE96AB7B57C348B350B9310E7DD43229D24F3FEA0061F9A4333DA454AB3"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     f.bazaar_query(),
+    ///     Some(vec![
+    ///         ("query", "get_telfhash"),
+    ///         ("telfhash", "t15ce012a5627fe96ab7b57c348b350b9310e7dd43229d24f3fea0061f9a4333da454ab3")
+    ///     ])
+    /// );
+    /// ```
+    pub fn bazaar_query(&self) -> Option<Vec<(&'static str, &str)>> {
+        let query = match self {
+            Fingerprint::Imphash(_) => "get_imphash",
+            Fingerprint::Telfhash(_) => "get_telfhash",
+            _ => return None,
+        };
+        Some(vec![("query", query), (self.kind(), self.value())])
+    }
+
+    /// search query in Shodan (`None` if the kind is not searchable)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::fingerprint::Fingerprint;
+    ///
+    /// let f: Fingerprint = "http.favicon.hash:-1234567".parse().unwrap();
+    /// assert_eq!(f.to_string(), "favicon_hash:-1234567");
+    /// assert_eq!(f.shodan_query(), Some("http.favicon.hash:-1234567".to_owned()));
+    ///
+    /// let f: Fingerprint = "jarm:2AD2AD0002AD2AD00042D42D00000069D641F34FE76ACDC05C40262F8815E5"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     f.shodan_query(),
+    ///     Some("ssl.jarm:2ad2ad0002ad2ad00042d42d00000069d641f34fe76acdc05c40262f8815e5".to_owned())
+    /// );
+    /// ```
+    pub fn shodan_query(&self) -> Option<String> {
+        match self {
+            Fingerprint::FaviconHash(x) => Some(format!("http.favicon.hash:{}", x)),
+            Fingerprint::Jarm(x) => Some(format!("ssl.jarm:{}", x)),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}:{}", self.kind(), self.value())
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = failure::Error;
+
+    /// parse `kind:value`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || FingerprintError::UnknownFingerprint(s.to_owned());
+        let (kind, value) = s.trim().split_once(':').ok_or_else(unknown)?;
+        let is_hex =
+            |len: usize| value.len() == len && value.bytes().all(|c| c.is_ascii_hexdigit());
+        let is_md5 = is_hex(32);
+        match kind.to_lowercase().as_str() {
+            "imphash" if is_md5 => Ok(Fingerprint::Imphash(value.to_lowercase())),
+            "rich_pe_header_hash" if is_md5 => {
+                Ok(Fingerprint::RichHeaderHash(value.to_lowercase()))
+            }
+            "ja3" if is_md5 => Ok(Fingerprint::Ja3(value.to_lowercase())),
+            "jarm" | "ssl.jarm" if is_hex(62) => Ok(Fingerprint::Jarm(value.to_lowercase())),
+            "authentihash" if is_hex(64) => Ok(Fingerprint::Authentihash(value.to_lowercase())),
+            "thumbprint" if is_hex(40) => {
+                Ok(Fingerprint::CertificateThumbprint(value.to_lowercase()))
+            }
+            "favicon_hash" | "http.favicon.hash" if value.parse::<i32>().is_ok() => {
+                Ok(Fingerprint::FaviconHash(value.parse::<i32>()?.to_string()))
+            }
+            // TLSH with or without version prefix
+            "telfhash" => {
+                let digest = value.to_lowercase();
+                let digest = digest.strip_prefix("t1").unwrap_or(&digest).to_owned();
+                if digest.len() == 70 && digest.bytes().all(|c| c.is_ascii_hexdigit()) {
+                    Ok(Fingerprint::Telfhash(format!("t1{}", digest)))
+                } else {
+                    Err(unknown().into())
+                }
+            }
+            _ => Err(unknown().into()),
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! geolocation and ASN of IP addresses from local MaxMind databases (GeoLite2 / GeoIP2 `.mmdb`)
+//!
+//! `GeoIp` (feature `geoip`) reads City / Country and ASN databases, and annotates indicators
+//! (IP addresses and URLs on them) with `GeoInfo` in `Metadata::geo` without any network calls.
+//! Annotations are exported as `country` / `city` / `asn` / `as_org` columns of CSV and `geo`
+//! of JSON Lines.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// location and network of an IP address
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 code of country (e.g. `JP`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// name of city in English
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// autonomous system number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+    /// organization of autonomous system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_org: Option<String>,
+}
+
+impl GeoInfo {
+    /// no field is known?
+    pub fn is_empty(&self) -> bool {
+        self == &GeoInfo::default()
+    }
+
+    /// fields of newer info merged into this one (known fields win)
+    pub fn merge(self, new: GeoInfo) -> GeoInfo {
+        GeoInfo {
+            country: new.country.or(self.country),
+            city: new.city.or(self.city),
+            asn: new.asn.or(self.asn),
+            as_org: new.as_org.or(self.as_org),
+        }
+    }
+}
+
+/// e.g. `JP Tokyo AS64496 (Example Net)`
+impl fmt::Display for GeoInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        parts.extend(self.country.clone());
+        parts.extend(self.city.clone());
+        parts.extend(self.asn.map(|x| format!("AS{}", x)));
+        parts.extend(self.as_org.as_ref().map(|x| format!("({})", x)));
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[cfg(feature = "geoip")]
+pub use self::reader::{GeoIp, GeoIpError};
+
+#[cfg(feature = "geoip")]
+mod reader {
+    use failure::Fail;
+    use std::net::IpAddr;
+    use std::path::Path;
+
+    use super::GeoInfo;
+    use crate::ioc::{host_of_url, Indicator, Ioc};
+    use crate::GenericResult;
+
+    /// start of metadata section
+    const METADATA_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+
+    /// nesting of maps / arrays more than this is refused
+    const MAX_DEPTH: usize = 32;
+
+    /// Errors in reading MaxMind databases
+    #[derive(Fail, Debug)]
+    pub enum GeoIpError {
+        #[fail(display = "invalid MaxMind database: {}", _0)]
+        InvalidDatabase(&'static str),
+    }
+
+    /// decoded value of data section
+    #[derive(Debug, Clone, PartialEq)]
+    enum Value {
+        String(String),
+        Uint(u128),
+        Int(i32),
+        Double(f64),
+        Bool(bool),
+        Bytes(Vec<u8>),
+        Map(Vec<(String, Value)>),
+        Array(Vec<Value>),
+    }
+
+    impl Value {
+        fn get(&self, path: &[&str]) -> Option<&Value> {
+            path.iter().try_fold(self, |value, key| match value {
+                Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+                _ => None,
+            })
+        }
+
+        fn str(&self, path: &[&str]) -> Option<String> {
+            match self.get(path)? {
+                Value::String(x) => Some(x.clone()),
+                _ => None,
+            }
+        }
+
+        fn uint(&self, path: &[&str]) -> Option<u128> {
+            match self.get(path)? {
+                Value::Uint(x) => Some(*x),
+                _ => None,
+            }
+        }
+    }
+
+    fn invalid(reason: &'static str) -> GeoIpError {
+        GeoIpError::InvalidDatabase(reason)
+    }
+
+    /// decoder of data section (and metadata, which is encoded the same)
+    struct Decoder<'a> {
+        data: &'a [u8],
+    }
+
+    impl Decoder<'_> {
+        fn bytes(&self, offset: usize, len: usize) -> Result<&[u8], GeoIpError> {
+            self.data
+                .get(offset..offset + len)
+                .ok_or_else(|| invalid("truncated data"))
+        }
+
+        fn uint(&self, offset: usize, len: usize) -> Result<u128, GeoIpError> {
+            if len > 16 {
+                return Err(invalid("too long integer"));
+            }
+            Ok(self
+                .bytes(offset, len)?
+                .iter()
+                .fold(0, |acc, x| (acc << 8) | u128::from(*x)))
+        }
+
+        /// value at offset, and offset after it
+        fn decode(&self, offset: usize, depth: usize) -> Result<(Value, usize), GeoIpError> {
+            if depth > MAX_DEPTH {
+                return Err(invalid("too deep nesting"));
+            }
+            let ctrl = self.bytes(offset, 1)?[0];
+            let mut offset = offset + 1;
+
+            let mut kind = ctrl >> 5;
+            if kind == 1 {
+                // pointer (never to another pointer)
+                let extra = usize::from((ctrl >> 3) & 3) + 1;
+                let low = self.uint(offset, extra)? as usize;
+                let high = usize::from(ctrl & 7);
+                let target = match extra {
+                    1 => high << 8 | low,
+                    2 => (high << 16 | low) + 2048,
+                    3 => (high << 24 | low) + 526_336,
+                    _ => low,
+                };
+                if self.bytes(target, 1)?[0] >> 5 == 1 {
+                    return Err(invalid("pointer to pointer"));
+                }
+                let (value, _) = self.decode(target, depth + 1)?;
+                return Ok((value, offset + extra));
+            }
+            if kind == 0 {
+                kind = 7 + self.bytes(offset, 1)?[0];
+                offset += 1;
+            }
+
+            let mut size = usize::from(ctrl & 0x1f);
+            if size >= 29 {
+                let extra = size - 28;
+                let base = [29, 285, 65_821][extra - 1];
+                size = base + self.uint(offset, extra)? as usize;
+                offset += extra;
+            }
+
+            let value = match kind {
+                2 => Value::String(
+                    String::from_utf8(self.bytes(offset, size)?.to_vec())
+                        .map_err(|_| invalid("invalid utf-8 string"))?,
+                ),
+                3 if size == 8 => Value::Double(f64::from_bits(self.uint(offset, 8)? as u64)),
+                4 => Value::Bytes(self.bytes(offset, size)?.to_vec()),
+                5 | 6 | 9 | 10 => Value::Uint(self.uint(offset, size)?),
+                8 => Value::Int(self.uint(offset, size)? as u32 as i32),
+                14 => return Ok((Value::Bool(size != 0), offset)),
+                15 if size == 4 => {
+                    Value::Double(f64::from(f32::from_bits(self.uint(offset, 4)? as u32)))
+                }
+                7 => {
+                    let mut entries = Vec::with_capacity(size.min(64));
+                    for _ in 0..size {
+                        let (key, next) = self.decode(offset, depth + 1)?;
+                        let key = match key {
+                            Value::String(x) => x,
+                            _ => return Err(invalid("non-string key of map")),
+                        };
+                        let (value, next) = self.decode(next, depth + 1)?;
+                        entries.push((key, value));
+                        offset = next;
+                    }
+                    return Ok((Value::Map(entries), offset));
+                }
+                11 => {
+                    let mut items = Vec::with_capacity(size.min(64));
+                    for _ in 0..size {
+                        let (value, next) = self.decode(offset, depth + 1)?;
+                        items.push(value);
+                        offset = next;
+                    }
+                    return Ok((Value::Array(items), offset));
+                }
+                _ => return Err(invalid("unknown data type")),
+            };
+            Ok((value, offset + size))
+        }
+    }
+
+    /// a MaxMind database
+    #[derive(Debug, Clone)]
+    struct Database {
+        data: Vec<u8>,
+        node_count: usize,
+        record_size: usize,
+        ip_version: u16,
+        /// start of data section
+        data_start: usize,
+        /// node of `::/96` (IPv4 addresses in IPv6 database)
+        ipv4_start: usize,
+    }
+
+    impl Database {
+        fn parse(data: Vec<u8>) -> Result<Self, GeoIpError> {
+            let marker = data
+                .windows(METADATA_MARKER.len())
+                .rposition(|x| x == METADATA_MARKER)
+                .ok_or_else(|| invalid("missing metadata"))?;
+            let metadata = Decoder {
+                data: &data[marker + METADATA_MARKER.len()..],
+            }
+            .decode(0, 0)?
+            .0;
+
+            let field = |name| {
+                metadata
+                    .uint(&[name])
+                    .ok_or_else(|| invalid("missing metadata"))
+            };
+            let node_count = field("node_count")? as usize;
+            let record_size = field("record_size")? as usize;
+            let ip_version = field("ip_version")? as u16;
+            if ![24, 28, 32].contains(&record_size) {
+                return Err(invalid("unsupported record size"));
+            }
+            let data_start = node_count * record_size / 4 + 16;
+            if data_start > marker {
+                return Err(invalid("truncated search tree"));
+            }
+
+            let mut db = Database {
+                data,
+                node_count,
+                record_size,
+                ip_version,
+                data_start,
+                ipv4_start: 0,
+            };
+            if ip_version == 6 {
+                let mut node = 0;
+                for _ in 0..96 {
+                    if node >= node_count {
+                        break;
+                    }
+                    node = db.record(node, false);
+                }
+                db.ipv4_start = node;
+            }
+            Ok(db)
+        }
+
+        /// left (`false`) or right (`true`) record of node
+        fn record(&self, node: usize, right: bool) -> usize {
+            let bytes = self.record_size / 4;
+            let b = &self.data[node * bytes..(node + 1) * bytes];
+            let be = |x: &[u8]| x.iter().fold(0, |acc, x| (acc << 8) | usize::from(*x));
+            match (self.record_size, right) {
+                (28, false) => usize::from(b[3] & 0xf0) << 20 | be(&b[0..3]),
+                (28, true) => usize::from(b[3] & 0x0f) << 24 | be(&b[4..7]),
+                (_, false) => be(&b[..bytes / 2]),
+                (_, true) => be(&b[bytes / 2..]),
+            }
+        }
+
+        fn lookup(&self, ip: IpAddr) -> Result<Option<GeoInfo>, GeoIpError> {
+            let (bits, len, mut node) = match ip {
+                IpAddr::V4(x) => {
+                    let start = if self.ip_version == 6 {
+                        self.ipv4_start
+                    } else {
+                        0
+                    };
+                    (u128::from(u32::from(x)) << 96, 32, start)
+                }
+                IpAddr::V6(_) if self.ip_version == 4 => return Ok(None),
+                IpAddr::V6(x) => (u128::from(x), 128, 0),
+            };
+            for i in 0..len {
+                if node >= self.node_count {
+                    break;
+                }
+                node = self.record(node, bits >> (127 - i) & 1 == 1);
+            }
+            if node <= self.node_count {
+                // not found (`node_count`), or the tree is shorter than the address
+                return Ok(None);
+            }
+
+            let offset = (node - self.node_count)
+                .checked_sub(16)
+                .ok_or_else(|| invalid("invalid record"))?;
+            let value = Decoder {
+                data: &self.data[self.data_start..],
+            }
+            .decode(offset, 0)?
+            .0;
+            Ok(Some(GeoInfo {
+                country: value
+                    .str(&["country", "iso_code"])
+                    .or_else(|| value.str(&["registered_country", "iso_code"])),
+                city: value.str(&["city", "names", "en"]),
+                asn: value.uint(&["autonomous_system_number"]).map(|x| x as u32),
+                as_org: value.str(&["autonomous_system_organization"]),
+            }))
+        }
+    }
+
+    /// local MaxMind databases (e.g. GeoLite2-City and GeoLite2-ASN)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::geoip::GeoIp;
+    /// use iocutil::prelude::*;
+    ///
+    /// let mut geoip = GeoIp::open("GeoLite2-City.mmdb").unwrap();
+    /// geoip.add("GeoLite2-ASN.mmdb").unwrap();
+    ///
+    /// let mut indicator = Indicator::new(Ioc::new("http://1.1.1.1/a").unwrap());
+    /// assert!(geoip.enrich(&mut indicator));
+    /// println!("{}", indicator.meta.geo.unwrap());
+    /// ```
+    #[derive(Debug, Clone, Default)]
+    pub struct GeoIp {
+        databases: Vec<Database>,
+    }
+
+    impl GeoIp {
+        /// no database (annotates nothing)
+        pub fn new() -> Self {
+            GeoIp::default()
+        }
+
+        /// read a database file
+        pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+            let mut geoip = GeoIp::new();
+            geoip.add(path)?;
+            Ok(geoip)
+        }
+
+        /// add a database file (e.g. ASN database to City database)
+        pub fn add(&mut self, path: impl AsRef<Path>) -> GenericResult<()> {
+            self.add_bytes(std::fs::read(path)?)
+        }
+
+        /// add a database in memory
+        pub fn add_bytes(&mut self, data: Vec<u8>) -> GenericResult<()> {
+            self.databases.push(Database::parse(data)?);
+            Ok(())
+        }
+
+        /// info of IP address in all databases (`None` if not found in any)
+        pub fn lookup(&self, ip: IpAddr) -> GenericResult<Option<GeoInfo>> {
+            let mut found: Option<GeoInfo> = None;
+            for db in &self.databases {
+                if let Some(info) = db.lookup(ip)? {
+                    found = Some(found.unwrap_or_default().merge(info));
+                }
+            }
+            Ok(found.filter(|x| !x.is_empty()))
+        }
+
+        /// annotate an IP address (or URL on it) with `geo` of metadata. returns true if found.
+        /// (invalid records in databases are not found)
+        pub fn enrich(&self, indicator: &mut Indicator) -> bool {
+            let ip = match &indicator.ioc {
+                Ioc::IpAddress(ip) => *ip,
+                Ioc::Url(x) => match host_of_url(x) {
+                    Some(Ioc::IpAddress(ip)) => ip,
+                    _ => return false,
+                },
+                _ => return false,
+            };
+            match self.lookup(ip) {
+                Ok(Some(info)) => {
+                    let geo = indicator.meta.geo.take().unwrap_or_default();
+                    indicator.meta.geo = Some(geo.merge(info));
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        /// annotate indicators. returns number of found ones.
+        pub fn enrich_all<'a>(
+            &self,
+            indicators: impl IntoIterator<Item = &'a mut Indicator>,
+        ) -> usize {
+            indicators
+                .into_iter()
+                .map(|x| self.enrich(x))
+                .filter(|x| *x)
+                .count()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn string(x: &str) -> Vec<u8> {
+            let mut v = vec![2 << 5 | x.len() as u8];
+            v.extend(x.as_bytes());
+            v
+        }
+
+        fn map(entries: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+            let mut v = vec![7 << 5 | entries.len() as u8];
+            for (k, x) in entries {
+                v.extend(string(k));
+                v.extend(x);
+            }
+            v
+        }
+
+        fn uint32(x: u32) -> Vec<u8> {
+            let mut v = vec![6 << 5 | 4];
+            v.extend(&x.to_be_bytes());
+            v
+        }
+
+        fn uint16(x: u16) -> Vec<u8> {
+            let mut v = vec![5 << 5 | 2];
+            v.extend(&x.to_be_bytes());
+            v
+        }
+
+        /// IPv4 database of 24-bit records with only 192.0.2.0/24
+        fn database() -> Vec<u8> {
+            let network = u32::from(std::net::Ipv4Addr::new(192, 0, 2, 0));
+            let node_count = 24u32;
+            let mut tree = vec![];
+            for i in 0..24 {
+                let bit = network >> (31 - i) & 1;
+                // the last node points to data (offset 0)
+                let next = if i == 23 { node_count + 16 } else { i + 1 };
+                let (left, right) = if bit == 1 {
+                    (node_count, next)
+                } else {
+                    (next, node_count)
+                };
+                tree.extend(&left.to_be_bytes()[1..]);
+                tree.extend(&right.to_be_bytes()[1..]);
+            }
+            let mut db = tree;
+            db.extend(&[0; 16]);
+            db.extend(map(vec![
+                (
+                    "city",
+                    map(vec![("names", map(vec![("en", string("Tokyo"))]))]),
+                ),
+                ("country", map(vec![("iso_code", string("JP"))])),
+                ("autonomous_system_number", uint32(64496)),
+            ]));
+            db.extend(METADATA_MARKER);
+            db.extend(map(vec![
+                ("node_count", uint32(node_count)),
+                ("record_size", uint16(24)),
+                ("ip_version", uint16(4)),
+                ("database_type", string("Test")),
+            ]));
+            db
+        }
+
+        #[test]
+        fn lookup() {
+            let mut geoip = GeoIp::new();
+            geoip.add_bytes(database()).unwrap();
+
+            let info = geoip.lookup("192.0.2.1".parse().unwrap()).unwrap().unwrap();
+            assert_eq!(info.country.as_deref(), Some("JP"));
+            assert_eq!(info.city.as_deref(), Some("Tokyo"));
+            assert_eq!(info.asn, Some(64496));
+            assert_eq!(info.to_string(), "JP Tokyo AS64496");
+            assert_eq!(geoip.lookup("198.51.100.1".parse().unwrap()).unwrap(), None);
+            assert_eq!(geoip.lookup("2001:db8::1".parse().unwrap()).unwrap(), None);
+
+            let mut indicators = vec![
+                Indicator::new(Ioc::new("http://192.0.2.7/a").unwrap()),
+                Indicator::new(Ioc::new("example.com").unwrap()),
+            ];
+            assert_eq!(geoip.enrich_all(&mut indicators), 1);
+            assert_eq!(indicators[0].meta.geo, Some(info));
+        }
+
+        #[test]
+        fn invalid() {
+            assert!(GeoIp::new().add_bytes(b"not a database".to_vec()).is_err());
+            let mut db = database();
+            db.truncate(100);
+            assert!(GeoIp::new().add_bytes(db).is_err());
+        }
+    }
+}
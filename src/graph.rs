@@ -0,0 +1,298 @@
+//! relationship graph of indicators and its export (Graphviz DOT / GraphML)
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use crate::ioc::{host_of_url, Indicator, Ioc, IocKind};
+use crate::util::escape_xml;
+
+/// a directed relationship between indicators (e.g. url `hosted-on` domain)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Relationship {
+    pub source: Ioc,
+    pub target: Ioc,
+    pub relationship_type: String,
+}
+
+/// relationships found in the indicator itself: url `hosted-on` its host (domain, or literal
+/// IP address), email address `belongs-to` its domain
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::graph::decompose;
+///
+/// let r = decompose(&Ioc::new("alice@evil.example.com").unwrap());
+/// assert_eq!(r[0].target, Ioc::new("evil.example.com").unwrap());
+/// assert_eq!(r[0].relationship_type, "belongs-to");
+/// assert!(decompose(&Ioc::new("192.0.2.1").unwrap()).is_empty());
+/// ```
+pub fn decompose(ioc: &Ioc) -> Vec<Relationship> {
+    let related = match ioc {
+        Ioc::Url(u) => host_of_url(u).map(|h| (h, "hosted-on")),
+        Ioc::EmailAddress(e) => e
+            .rsplit('@')
+            .next()
+            .map(|d| (Ioc::Domain(d.to_owned()), "belongs-to")),
+        _ => None,
+    };
+    related
+        .into_iter()
+        .map(|(target, t)| Relationship {
+            source: ioc.clone(),
+            target,
+            relationship_type: t.to_owned(),
+        })
+        .collect()
+}
+
+/// graph of indicators (nodes) and their relationships (edges)
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    nodes: Vec<Indicator>,
+    index: HashMap<Ioc, usize>,
+    edges: Vec<(usize, usize, String)>,
+    seen_edges: HashSet<(usize, usize, String)>,
+    decompose: bool,
+}
+
+impl Graph {
+    /// empty graph
+    pub fn new() -> Self {
+        Graph::default()
+    }
+
+    /// graph of indicators with relationships found in themselves
+    /// (url `hosted-on` its host, email address `belongs-to` its domain)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::graph::Graph;
+    ///
+    /// let g = Graph::from_indicators(vec![
+    ///     Ioc::new("http://evil.example.com/a").unwrap(),
+    ///     Ioc::new("alice@evil.example.com").unwrap(),
+    /// ]);
+    /// assert_eq!(g.nodes().count(), 3);
+    /// assert_eq!(g.relationships().count(), 2);
+    /// ```
+    pub fn from_indicators(indicators: impl IntoIterator<Item = impl Into<Indicator>>) -> Self {
+        let mut g = Graph::new().with_decomposition(true);
+        for indicator in indicators {
+            g.add(indicator);
+        }
+        g
+    }
+
+    /// relate components of composite indicators added later (as `decompose`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::graph::Graph;
+    ///
+    /// let mut g = Graph::new().with_decomposition(true);
+    /// g.add(Ioc::new("http://192.0.2.1/gate.php").unwrap());
+    /// let r = g.relationships().next().unwrap();
+    /// assert_eq!(r.target, Ioc::new("192.0.2.1").unwrap());
+    /// assert_eq!(r.relationship_type, "hosted-on");
+    /// ```
+    pub fn with_decomposition(mut self, decompose: bool) -> Self {
+        self.decompose = decompose;
+        self
+    }
+
+    /// add an indicator as node (returns its index, existing node is kept as is)
+    pub fn add(&mut self, indicator: impl Into<Indicator>) -> usize {
+        let indicator = indicator.into();
+        if let Some(i) = self.index.get(&indicator.ioc) {
+            return *i;
+        }
+        let i = self.nodes.len();
+        let related = if self.decompose {
+            decompose(&indicator.ioc)
+        } else {
+            vec![]
+        };
+        self.index.insert(indicator.ioc.clone(), i);
+        self.nodes.push(indicator);
+        for r in related {
+            self.relate(r.source, r.target, r.relationship_type);
+        }
+        i
+    }
+
+    /// add a relationship (nodes are added if not exist)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::graph::Graph;
+    ///
+    /// let mut g = Graph::new();
+    /// let sample = Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap();
+    /// g.relate(sample.clone(), Ioc::new("192.0.2.1").unwrap(), "communicates-with");
+    /// g.relate(sample, Ioc::new("192.0.2.1").unwrap(), "communicates-with");
+    /// assert_eq!(g.relationships().count(), 1);
+    /// ```
+    pub fn relate(
+        &mut self,
+        source: impl Into<Indicator>,
+        target: impl Into<Indicator>,
+        relationship_type: impl AsRef<str>,
+    ) {
+        let s = self.add(source);
+        let t = self.add(target);
+        let edge = (s, t, relationship_type.as_ref().to_owned());
+        if self.seen_edges.insert(edge.clone()) {
+            self.edges.push(edge);
+        }
+    }
+
+    /// indicators in the graph
+    pub fn nodes(&self) -> impl Iterator<Item = &Indicator> {
+        self.nodes.iter()
+    }
+
+    /// relationships in the graph
+    pub fn relationships(&self) -> impl Iterator<Item = Relationship> + '_ {
+        self.edges.iter().map(move |(s, t, r)| Relationship {
+            source: self.nodes[*s].ioc.clone(),
+            target: self.nodes[*t].ioc.clone(),
+            relationship_type: r.clone(),
+        })
+    }
+
+    /// write graph in Graphviz DOT
+    pub fn write_dot(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let quote = |x: &str| format!("\"{}\"", x.replace('\\', "\\\\").replace('"', "\\\""));
+        writeln!(writer, "digraph iocs {{")?;
+        for (i, n) in self.nodes.iter().enumerate() {
+            writeln!(
+                writer,
+                "  n{} [label={}, shape={}, kind={}];",
+                i,
+                quote(&n.ioc.value()),
+                shape(n.ioc.kind()),
+                quote(&n.ioc.kind().to_string())
+            )?;
+        }
+        for (s, t, r) in &self.edges {
+            writeln!(writer, "  n{} -> n{} [label={}];", s, t, quote(r))?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// graph in Graphviz DOT
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::graph::Graph;
+    ///
+    /// let g = Graph::from_indicators(vec![Ioc::new("http://example.com/a").unwrap()]);
+    /// assert_eq!(g.to_dot(), r#"digraph iocs {
+    ///   n0 [label="http://example.com/a", shape=box, kind="url"];
+    ///   n1 [label="example.com", shape=ellipse, kind="domain"];
+    ///   n0 -> n1 [label="hosted-on"];
+    /// }
+    /// "#);
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut buf = vec![];
+        // writing into vec never fails
+        self.write_dot(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// write graph in GraphML
+    pub fn write_graphml(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (id, target) in &[
+            ("value", "node"),
+            ("kind", "node"),
+            ("source", "node"),
+            ("tags", "node"),
+            ("label", "edge"),
+        ] {
+            writeln!(
+                writer,
+                r#"  <key id="{0}" for="{1}" attr.name="{0}" attr.type="string"/>"#,
+                id, target
+            )?;
+        }
+        writeln!(writer, r#"  <graph id="iocs" edgedefault="directed">"#)?;
+        for (i, n) in self.nodes.iter().enumerate() {
+            writeln!(writer, r#"    <node id="n{}">"#, i)?;
+            let mut data = vec![("value", n.ioc.value()), ("kind", n.ioc.kind().to_string())];
+            if let Some(s) = &n.meta.source {
+                data.push(("source", s.clone()));
+            }
+            if !n.meta.tags.is_empty() {
+                data.push(("tags", n.meta.tags.join(",")));
+            }
+            for (k, v) in data {
+                writeln!(
+                    writer,
+                    r#"      <data key="{}">{}</data>"#,
+                    k,
+                    escape_xml(v)
+                )?;
+            }
+            writeln!(writer, "    </node>")?;
+        }
+        for (i, (s, t, r)) in self.edges.iter().enumerate() {
+            writeln!(
+                writer,
+                r#"    <edge id="e{}" source="n{}" target="n{}"><data key="label">{}</data></edge>"#,
+                i,
+                s,
+                t,
+                escape_xml(r)
+            )?;
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")
+    }
+
+    /// graph in GraphML
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::graph::Graph;
+    ///
+    /// let g = Graph::from_indicators(vec![Ioc::new("http://example.com/?a&b").unwrap()]);
+    /// let xml = g.to_graphml();
+    /// assert!(xml.contains(r#"<data key="value">http://example.com/?a&amp;b</data>"#));
+    /// assert!(xml.contains(r#"<edge id="e0" source="n0" target="n1">"#));
+    /// ```
+    pub fn to_graphml(&self) -> String {
+        let mut buf = vec![];
+        // writing into vec never fails
+        self.write_graphml(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+/// node shape of indicator kind in DOT
+fn shape(kind: IocKind) -> &'static str {
+    match kind {
+        IocKind::Hash => "note",
+        IocKind::Domain => "ellipse",
+        IocKind::Url => "box",
+        IocKind::IpAddress => "diamond",
+        IocKind::EmailAddress => "house",
+    }
+}
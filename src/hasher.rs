@@ -1,12 +1,17 @@
-//! calculating hashes (sha256 / sha1 / md5)
+//! calculating hashes (sha256 / sha1 / md5 and others) of contents, blocks of contents and directory trees
 
-use crate::contenthash::ContentHash;
-use crate::SampleHash;
-use crypto::digest::Digest;
-use crypto::md5::Md5;
-use crypto::sha1::Sha1;
-use crypto::sha2::Sha256;
-use std::io::{Error, Write};
+use crate::contenthash::{ContentHash, SampleHashes};
+use crate::tlsh;
+use crate::{GenericResult, SampleHash};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use md5::Md5;
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use rayon::ThreadPoolBuilder;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::io::{Error, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
 
 /// Hash calculator
 pub struct Hasher {
@@ -45,18 +50,18 @@ impl Hasher {
     /// ```
     pub fn digests(&mut self) -> ContentHash {
         ContentHash {
-            sha256: SampleHash::new(self.sha256.result_str()).unwrap(),
-            sha1: SampleHash::new(self.sha1.result_str()).unwrap(),
-            md5: SampleHash::new(self.md5.result_str()).unwrap(),
+            sha256: SampleHash::new(format!("{:x}", self.sha256.clone().finalize())).unwrap(),
+            sha1: SampleHash::new(format!("{:x}", self.sha1.clone().finalize())).unwrap(),
+            md5: SampleHash::new(format!("{:x}", self.md5.clone().finalize())).unwrap(),
         }
     }
 }
 
 impl Write for Hasher {
     fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        self.sha256.input(buf);
-        self.sha1.input(buf);
-        self.md5.input(buf);
+        self.sha256.update(buf);
+        self.sha1.update(buf);
+        self.md5.update(buf);
         Ok(buf.len())
     }
 
@@ -64,3 +69,369 @@ impl Write for Hasher {
         Ok(())
     }
 }
+
+/// Hash calculator of every algorithm in `SampleHashes` fed from streaming source
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::hasher::IncrementalHasher;
+/// use std::io::Write;
+///
+/// let mut hasher = IncrementalHasher::new();
+/// // e.g. chunks of response body
+/// for chunk in ["Lorem ipsum dolor sit amet, ", "consectetur adipiscing elit"].iter() {
+///     hasher.write_all(chunk.as_bytes()).unwrap();
+/// }
+/// let hashes = hasher.finish();
+///
+/// let whole = "Lorem ipsum dolor sit amet, consectetur adipiscing elit";
+/// assert_eq!(hashes.content_hash(), ContentHash::of_bytes(whole));
+/// assert_eq!(hashes.size, Some(55));
+/// assert_eq!(
+///     hashes.tlsh.unwrap().to_string(),
+///     "T12D900249414E0BD59A46503F3ADA802AE50825242B2590561CF690599112214C051556"
+/// );
+/// ```
+pub struct IncrementalHasher {
+    hasher: Hasher,
+    sha512: Sha512,
+    size: u64,
+    tlsh: tlsh::State,
+}
+
+impl Default for IncrementalHasher {
+    fn default() -> Self {
+        IncrementalHasher {
+            hasher: Hasher::default(),
+            sha512: Sha512::new(),
+            size: 0,
+            tlsh: tlsh::State::new(),
+        }
+    }
+}
+
+impl IncrementalHasher {
+    /// create new hasher
+    pub fn new() -> Self {
+        IncrementalHasher::default()
+    }
+
+    /// get hashes (`tlsh` is `None` if content is too short or too uniform)
+    pub fn finish(mut self) -> SampleHashes {
+        let mut hashes = SampleHashes::from(self.hasher.digests());
+        hashes.sha512 = Some(format!("{:x}", self.sha512.finalize()));
+        hashes.size = Some(self.size);
+        hashes.tlsh = self.tlsh.finish().ok();
+        hashes
+    }
+}
+
+impl Write for IncrementalHasher {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.hasher.write_all(buf)?;
+        self.sha512.update(buf);
+        self.tlsh.update(buf);
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// default block size of `BlockHasher` (1 MiB)
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 20;
+
+/// sha256 of each fixed size block of a content (the last block may be shorter)
+#[derive(Eq, PartialEq, Clone, Hash, Debug)]
+pub struct BlockHashes {
+    pub block_size: usize,
+    /// total length of the content
+    pub len: u64,
+    /// sha256 of blocks in order
+    pub blocks: Vec<String>,
+    /// sha256 of concatenated (binary) digests of blocks
+    pub root: String,
+}
+
+impl BlockHashes {
+    /// block hashes of bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::hasher::BlockHashes;
+    ///
+    /// let original = vec![0x41u8; 10000];
+    /// let hashes = BlockHashes::of_bytes(&original, 4096);
+    /// assert_eq!(hashes.blocks.len(), 3);
+    ///
+    /// // acquisition truncated in the middle of the 3rd block
+    /// let truncated = BlockHashes::of_bytes(&original[..9000], 4096);
+    /// assert_ne!(truncated.root, hashes.root);
+    /// assert_eq!(truncated.matching_blocks(&hashes), vec![0, 1]);
+    /// assert!(truncated.is_truncation_of(&hashes));
+    /// ```
+    pub fn of_bytes(bytes: impl AsRef<[u8]>, block_size: usize) -> Self {
+        let mut hasher = BlockHasher::new(block_size);
+        // never fails
+        hasher.write_all(bytes.as_ref()).unwrap();
+        hasher.finish()
+    }
+
+    /// block hashes of all content in reader
+    pub fn of_reader(mut reader: impl Read, block_size: usize) -> GenericResult<Self> {
+        let mut hasher = BlockHasher::new(block_size);
+        std::io::copy(&mut reader, &mut hasher)?;
+        Ok(hasher.finish())
+    }
+
+    /// block hashes of file content
+    pub fn of_file(path: impl AsRef<Path>, block_size: usize) -> GenericResult<Self> {
+        BlockHashes::of_reader(std::fs::File::open(path)?, block_size)
+    }
+
+    /// indexes of blocks identical to the block at the same position of other
+    /// (empty if block sizes differ)
+    pub fn matching_blocks(&self, other: &BlockHashes) -> Vec<usize> {
+        if self.block_size != other.block_size {
+            return vec![];
+        }
+        self.blocks
+            .iter()
+            .zip(other.blocks.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a == b)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// whether the content seems a truncated copy of other
+    /// (every complete block matches and the content is shorter)
+    pub fn is_truncation_of(&self, other: &BlockHashes) -> bool {
+        let complete = (self.len / self.block_size as u64) as usize;
+        self.block_size == other.block_size
+            && self.len < other.len
+            && self.blocks[..complete] == other.blocks[..complete]
+    }
+}
+
+/// Hash calculator of fixed size blocks (see `BlockHashes`)
+pub struct BlockHasher {
+    block_size: usize,
+    len: u64,
+    filled: usize,
+    current: Sha256,
+    blocks: Vec<String>,
+    root: Sha256,
+}
+
+impl BlockHasher {
+    /// create new hasher (`block_size` must not be 0)
+    pub fn new(block_size: usize) -> Self {
+        assert!(block_size > 0, "block size must not be 0");
+        BlockHasher {
+            block_size,
+            len: 0,
+            filled: 0,
+            current: Sha256::new(),
+            blocks: vec![],
+            root: Sha256::new(),
+        }
+    }
+
+    fn close_block(&mut self) {
+        let digest = self.current.finalize_reset();
+        self.root.update(digest);
+        self.blocks.push(format!("{:x}", digest));
+        self.filled = 0;
+    }
+
+    /// get block hashes
+    pub fn finish(mut self) -> BlockHashes {
+        if self.filled > 0 {
+            self.close_block();
+        }
+        BlockHashes {
+            block_size: self.block_size,
+            len: self.len,
+            blocks: self.blocks,
+            root: format!("{:x}", self.root.finalize()),
+        }
+    }
+}
+
+impl Default for BlockHasher {
+    fn default() -> Self {
+        BlockHasher::new(DEFAULT_BLOCK_SIZE)
+    }
+}
+
+impl Write for BlockHasher {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let n = (self.block_size - self.filled).min(rest.len());
+            self.current.update(&rest[..n]);
+            self.filled += n;
+            rest = &rest[n..];
+            if self.filled == self.block_size {
+                self.close_block();
+            }
+        }
+        self.len += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// builder of parallel hashing of files in a directory tree (see `hash_tree`)
+pub struct HashTree {
+    root: PathBuf,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    threads: Option<usize>,
+}
+
+/// hash every file under a directory recursively in parallel
+///
+/// Glob patterns of `include` / `exclude` are matched with relative path from the directory
+/// (all files are included if no `include` pattern is specified).
+/// Results are yielded as they complete, so order of them is not determined.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::hasher::hash_tree;
+///
+/// let results: Vec<_> = hash_tree("./src")
+///     .include("**/*.rs")
+///     .exclude("store/**")
+///     .walk()
+///     .unwrap()
+///     .collect();
+///
+/// let (path, hashes) = results
+///     .into_iter()
+///     .find(|(p, _)| p.ends_with("lib.rs"))
+///     .unwrap();
+/// assert_eq!(hashes.unwrap(), ContentHash::of_file(path).unwrap());
+/// ```
+pub fn hash_tree(root: impl AsRef<Path>) -> HashTree {
+    HashTree {
+        root: root.as_ref().to_owned(),
+        include: vec![],
+        exclude: vec![],
+        threads: None,
+    }
+}
+
+fn globset(patterns: &[String]) -> GenericResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for p in patterns {
+        builder.add(Glob::new(p)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// files under root (directories are not followed if they are symbolic links)
+fn walk_files(root: PathBuf) -> impl Iterator<Item = (PathBuf, std::io::Result<()>)> {
+    let mut dirs = vec![root];
+    let mut files: Vec<(PathBuf, std::io::Result<()>)> = vec![];
+    std::iter::from_fn(move || loop {
+        if let Some(f) = files.pop() {
+            return Some(f);
+        }
+        let dir = dirs.pop()?;
+        match std::fs::read_dir(&dir) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(e) => match e.file_type() {
+                            Ok(t) if t.is_dir() => dirs.push(e.path()),
+                            Ok(t) if t.is_file() => files.push((e.path(), Ok(()))),
+                            Ok(_) => {}
+                            Err(err) => files.push((e.path(), Err(err))),
+                        },
+                        Err(err) => files.push((dir.clone(), Err(err))),
+                    }
+                }
+            }
+            Err(err) => files.push((dir, Err(err))),
+        }
+    })
+}
+
+impl HashTree {
+    /// hash only files match the glob pattern (e.g. `**/*.exe`)
+    pub fn include(mut self, pattern: impl AsRef<str>) -> Self {
+        self.include.push(pattern.as_ref().to_owned());
+        self
+    }
+
+    /// skip files match the glob pattern (e.g. `**/.git/**`)
+    pub fn exclude(mut self, pattern: impl AsRef<str>) -> Self {
+        self.exclude.push(pattern.as_ref().to_owned());
+        self
+    }
+
+    /// number of worker threads (default: number of CPUs)
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads.max(1));
+        self
+    }
+
+    /// start hashing in background (fails if glob patterns are invalid)
+    pub fn walk(self) -> GenericResult<HashTreeResults> {
+        let include = globset(&self.include)?;
+        let exclude = globset(&self.exclude)?;
+        let mut pool = ThreadPoolBuilder::new();
+        if let Some(n) = self.threads {
+            pool = pool.num_threads(n);
+        }
+        let pool = pool.build()?;
+
+        let (sender, receiver) = channel();
+        let root = self.root;
+        std::thread::spawn(move || {
+            let matches = |path: &Path| {
+                let rel = path.strip_prefix(&root).unwrap_or(path);
+                (include.is_empty() || include.is_match(rel)) && !exclude.is_match(rel)
+            };
+            pool.install(|| {
+                walk_files(root.clone())
+                    .filter(|(p, _)| matches(p))
+                    .par_bridge()
+                    .for_each_with(sender, |s, (path, walked)| {
+                        let hashes = walked
+                            .map_err(|e| e.into())
+                            .and_then(|_| ContentHash::of_file(&path));
+                        // receiver may be dropped (stop silently)
+                        let _ = s.send((path, hashes));
+                    });
+            });
+        });
+
+        Ok(HashTreeResults { receiver })
+    }
+}
+
+/// results of `HashTree::walk` (path and its hashes)
+pub struct HashTreeResults {
+    receiver: Receiver<(PathBuf, GenericResult<ContentHash>)>,
+}
+
+impl Iterator for HashTreeResults {
+    type Item = (PathBuf, GenericResult<ContentHash>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
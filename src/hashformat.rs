@@ -0,0 +1,76 @@
+//! display format of hashes (letter case, algorithm prefix and colon grouping)
+
+use derive_builder::Builder;
+
+use crate::SampleHash;
+
+/// letter case of hex digits
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HexCase {
+    Lower,
+    Upper,
+}
+
+/// display format of hashes
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::hashformat::{HashFormatBuilder, HexCase};
+///
+/// let hash = sample!("da39a3ee5e6b4b0d3255bfef95601890afd80709");
+///
+/// let thumbprint = HashFormatBuilder::default()
+///     .case(HexCase::Upper)
+///     .colon_grouped(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(thumbprint.format(&hash), "DA:39:A3:EE:5E:6B:4B:0D:32:55:BF:EF:95:60:18:90:AF:D8:07:09");
+///
+/// let prefixed = HashFormatBuilder::default().prefix(true).build().unwrap();
+/// assert_eq!(prefixed.format(&hash), "sha1:da39a3ee5e6b4b0d3255bfef95601890afd80709");
+/// ```
+#[derive(Builder, Debug, Clone, Eq, PartialEq)]
+pub struct HashFormat {
+    #[builder(default = "HexCase::Lower")]
+    case: HexCase,
+    /// prepend algorithm (e.g. `sha256:`)
+    #[builder(default = "false")]
+    prefix: bool,
+    /// separate each byte with colon (e.g. `da:39:a3`)
+    #[builder(default = "false")]
+    colon_grouped: bool,
+}
+
+impl Default for HashFormat {
+    /// lowercase hex digits without prefix (same as `Display` of `SampleHash`)
+    fn default() -> Self {
+        HashFormatBuilder::default().build().unwrap()
+    }
+}
+
+impl HashFormat {
+    /// format hash
+    pub fn format(&self, hash: &SampleHash) -> String {
+        let digits = match self.case {
+            HexCase::Lower => hash.as_ref().to_lowercase(),
+            HexCase::Upper => hash.as_ref().to_uppercase(),
+        };
+        let digits = if self.colon_grouped {
+            digits
+                .as_bytes()
+                .chunks(2)
+                .map(|x| String::from_utf8_lossy(x).into_owned())
+                .collect::<Vec<_>>()
+                .join(":")
+        } else {
+            digits
+        };
+        if self.prefix {
+            format!("{}:{}", hash.algorithm(), digits)
+        } else {
+            digits
+        }
+    }
+}
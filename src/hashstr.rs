@@ -2,47 +2,56 @@
 
 use std::collections::HashSet;
 
-use lazy_static::lazy_static;
-use regex::Regex;
-
-lazy_static! {
-    static ref MD5_PATTERN: Regex =
-        Regex::new(r"(^|[[:^xdigit:]])(?P<target>[[:xdigit:]]{32})([[:^xdigit:]]|$)").unwrap();
-    static ref SHA1_PATTERN: Regex =
-        Regex::new(r"(^|[[:^xdigit:]])(?P<target>[[:xdigit:]]{40})([[:^xdigit:]]|$)").unwrap();
-    static ref SHA256_PATTERN: Regex =
-        Regex::new(r"(^|[[:^xdigit:]])(?P<target>[[:xdigit:]]{64})([[:^xdigit:]]|$)").unwrap();
-    static ref HASH_PATTERN: Regex = Regex::new(
-        r"(^|[[:^xdigit:]])(?P<target>[[:xdigit:]]{32}|[[:xdigit:]]{40}|[[:xdigit:]]{64})([[:^xdigit:]]|$)"
-    )
-    .unwrap();
-}
+use crate::scan::{self, HASH_LENGTHS};
+use crate::validate;
 
 /// kind of hash
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 pub enum HashType {
     Unknown,
     MD5,
     SHA1,
     SHA256,
+    SHA384,
+    SHA512,
+    NTLM,
+    CRC32,
+}
+
+/// a candidate kind of hash string with confidence (0-100)
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Candidate {
+    pub kind: HashType,
+    pub confidence: u8,
 }
 
+/// NTLM hash of empty password
+const EMPTY_NTLM: &str = "31d6cfe0d16ae931b73c59d7e0c089c0";
+
+/// words suggest 32 hex digits are NTLM hashes rather than md5
+const NTLM_HINTS: &[&str] = &[
+    "ntlm",
+    "nthash",
+    "nt hash",
+    "ntds",
+    "pwdump",
+    "secretsdump",
+    "mimikatz",
+];
+
 /// is specified text is md5 hex digest?
 pub fn is_md5(target: &impl AsRef<str>) -> bool {
-    let target = target.as_ref();
-    MD5_PATTERN.is_match(target) && (target.len() == 32)
+    validate::is_md5(target)
 }
 
 /// is specified text is sha1 hex digest?
 pub fn is_sha1(target: &impl AsRef<str>) -> bool {
-    let target = target.as_ref();
-    SHA1_PATTERN.is_match(target.as_ref()) && (target.len() == 40)
+    validate::is_sha1(target)
 }
 
 /// is specified text is sha256 hex digest?
 pub fn is_sha256(target: &impl AsRef<str>) -> bool {
-    let target = target.as_ref();
-    SHA256_PATTERN.is_match(target.as_ref()) && (target.len() == 64)
+    validate::is_sha256(target)
 }
 
 /// detect hash type of specified text
@@ -55,6 +64,119 @@ pub fn detect(target: &impl AsRef<str>) -> HashType {
     }
 }
 
+fn kind_of_prefix(prefix: &str) -> Option<HashType> {
+    let prefix: String = prefix
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != '-' && *c != '_')
+        .collect();
+    match prefix.as_str() {
+        "md5" => Some(HashType::MD5),
+        "sha1" => Some(HashType::SHA1),
+        "sha256" => Some(HashType::SHA256),
+        "sha384" => Some(HashType::SHA384),
+        "sha512" => Some(HashType::SHA512),
+        "ntlm" | "nt" | "nthash" => Some(HashType::NTLM),
+        "crc32" | "crc" => Some(HashType::CRC32),
+        _ => None,
+    }
+}
+
+fn hex_len(kind: HashType) -> usize {
+    match kind {
+        HashType::CRC32 => 8,
+        HashType::MD5 | HashType::NTLM => 32,
+        HashType::SHA1 => 40,
+        HashType::SHA256 => 64,
+        HashType::SHA384 => 96,
+        HashType::SHA512 => 128,
+        HashType::Unknown => 0,
+    }
+}
+
+/// candidate kinds of hash string ranked by confidence (empty if it can not be a hash)
+///
+/// Explicit prefix like `sha256:` / `ntlm=` is trusted if the length matches.
+/// 32 hex digits are ranked md5 first (see `identify_with_context` for NTLM).
+///
+/// # Example
+///
+/// ```
+/// use iocutil::hashstr::{identify, HashType};
+///
+/// let c = identify(&"5d41402abc4b2a76b9719d911017c592");
+/// assert_eq!(c[0].kind, HashType::MD5);
+/// assert_eq!(c[1].kind, HashType::NTLM);
+///
+/// assert_eq!(identify(&"ntlm:8846F7EAEE8FB117AD06BDD830B7586C")[0].kind, HashType::NTLM);
+/// assert_eq!(identify(&"cbcc65e2")[0].kind, HashType::CRC32);
+/// assert!(identify(&"sha1:d41d8cd98f00b204e9800998ecf8427e").is_empty());
+/// assert!(identify(&"not a hash").is_empty());
+/// ```
+pub fn identify(target: &impl AsRef<str>) -> Vec<Candidate> {
+    identify_with_context(target, &"")
+}
+
+/// candidate kinds of hash string with hints in text around it (e.g. a line of log)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::hashstr::{identify_with_context, HashType};
+///
+/// let hash = "8846f7eaee8fb117ad06bdd830b7586c";
+/// let c = identify_with_context(&hash, &"Administrator:500:aad3b435b51404eeaad3b435b51404ee:8846f7eaee8fb117ad06bdd830b7586c::: (pwdump)");
+/// assert_eq!(c[0].kind, HashType::NTLM);
+/// ```
+pub fn identify_with_context(
+    target: &impl AsRef<str>,
+    context: &impl AsRef<str>,
+) -> Vec<Candidate> {
+    let target = target.as_ref().trim();
+    let candidate = |kind, confidence| Candidate { kind, confidence };
+
+    if let Some(i) = target.find([':', '=']) {
+        let (prefix, value) = (&target[..i], &target[i + 1..]);
+        if let Some(kind) = kind_of_prefix(prefix) {
+            let valid =
+                value.len() == hex_len(kind) && value.bytes().all(|c| c.is_ascii_hexdigit());
+            return if valid {
+                vec![candidate(kind, 100)]
+            } else {
+                vec![]
+            };
+        }
+        return vec![];
+    }
+
+    if !target.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return vec![];
+    }
+
+    let lower = target.to_lowercase();
+    match target.len() {
+        8 => vec![candidate(HashType::CRC32, 90)],
+        32 => {
+            let context = context.as_ref().to_lowercase();
+            let hinted = NTLM_HINTS.iter().any(|x| context.contains(x));
+            if lower == EMPTY_NTLM {
+                vec![candidate(HashType::NTLM, 95), candidate(HashType::MD5, 5)]
+            } else if is_empty(&lower) {
+                vec![candidate(HashType::MD5, 100)]
+            } else if hinted {
+                vec![candidate(HashType::NTLM, 80), candidate(HashType::MD5, 20)]
+            } else {
+                vec![candidate(HashType::MD5, 80), candidate(HashType::NTLM, 20)]
+            }
+        }
+        40 => vec![candidate(HashType::SHA1, 100)],
+        64 => vec![candidate(HashType::SHA256, 100)],
+        96 => vec![candidate(HashType::SHA384, 100)],
+        128 => vec![candidate(HashType::SHA512, 100)],
+        _ => vec![],
+    }
+}
+
 /// is empty hash value?
 pub fn is_empty(target: &impl AsRef<str>) -> bool {
     match target.as_ref().to_lowercase().as_str() {
@@ -67,33 +189,29 @@ pub fn is_empty(target: &impl AsRef<str>) -> bool {
 
 /// find md5 hash string from specified text
 pub fn find_md5(target: &impl AsRef<str>) -> HashSet<String> {
-    MD5_PATTERN
-        .captures_iter(target.as_ref())
-        .map(|x| x["target"].to_lowercase())
+    scan::hex_runs(target.as_ref(), &[32])
+        .map(|x| x.to_lowercase())
         .collect()
 }
 
 /// find sha1 hash string from specified text
 pub fn find_sha1(target: &impl AsRef<str>) -> HashSet<String> {
-    SHA1_PATTERN
-        .captures_iter(target.as_ref())
-        .map(|x| x["target"].to_lowercase())
+    scan::hex_runs(target.as_ref(), &[40])
+        .map(|x| x.to_lowercase())
         .collect()
 }
 
 /// find sha256 hash string from specified text
 pub fn find_sha256(target: &impl AsRef<str>) -> HashSet<String> {
-    SHA256_PATTERN
-        .captures_iter(target.as_ref())
-        .map(|x| x["target"].to_lowercase())
+    scan::hex_runs(target.as_ref(), &[64])
+        .map(|x| x.to_lowercase())
         .collect()
 }
 
 /// find hash string from specified text
 pub fn find(target: &impl AsRef<str>) -> HashSet<String> {
-    HASH_PATTERN
-        .captures_iter(target.as_ref())
-        .map(|x| x["target"].to_lowercase())
+    scan::hex_runs(target.as_ref(), &HASH_LENGTHS)
+        .map(|x| x.to_lowercase())
         .collect()
 }
 
@@ -163,6 +281,36 @@ mod tests {
         assert_eq!(HashType::Unknown, detect(&"something not hash text"));
     }
 
+    #[test]
+    fn test_identify() {
+        let sha384 = "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b";
+        assert_eq!(HashType::SHA384, identify(&sha384)[0].kind);
+        assert_eq!(
+            HashType::SHA512,
+            identify(&format!("{}{}", sha384, &sha384[..32]))[0].kind
+        );
+        assert_eq!(
+            HashType::SHA256,
+            identify(&"SHA-256=e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+                [0]
+            .kind
+        );
+        assert_eq!(
+            HashType::NTLM,
+            identify(&"31D6CFE0D16AE931B73C59D7E0C089C0")[0].kind
+        );
+        assert_eq!(
+            vec![Candidate {
+                kind: HashType::MD5,
+                confidence: 100
+            }],
+            identify(&"d41d8cd98f00b204e9800998ecf8427e")
+        );
+        assert_eq!(1, identify(&"crc32:CBCC65E2").len());
+        assert!(identify(&"unknown:cbcc65e2").is_empty());
+        assert!(identify(&"cbcc65e").is_empty());
+    }
+
     #[test]
     fn test_is_empty() {
         assert_eq!(true, is_empty(&"d41d8cd98f00b204e9800998ecf8427e"));
@@ -0,0 +1,163 @@
+//! Have I Been Pwned client and its utilities
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub use crate::ioc::is_email_address;
+
+use crate::http::{block_on, ClientConfig};
+use crate::ioc::{Ioc, IocKind};
+use crate::keys::ApiKeyProvider;
+use crate::nonblocking;
+use crate::provider::{Capabilities, IocProvider, ProviderReport, Verdict};
+use crate::GenericResult;
+
+/// client for Have I Been Pwned API v3 (default use `$HIBP_APIKEY` environment variable as apikey)
+#[derive(Default)]
+pub struct HaveIBeenPwnedClient {
+    inner: nonblocking::HaveIBeenPwnedClient,
+}
+
+/// a breach record
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Breach {
+    pub name: String,
+    pub title: Option<String>,
+    pub domain: Option<String>,
+    pub breach_date: Option<String>,
+    pub added_date: Option<String>,
+    pub pwn_count: Option<u64>,
+    pub data_classes: Option<Vec<String>>,
+    pub is_verified: Option<bool>,
+    pub is_sensitive: Option<bool>,
+}
+
+impl HaveIBeenPwnedClient {
+    /// new client with apikey
+    pub fn new(apikey: impl AsRef<str>) -> Self {
+        HaveIBeenPwnedClient {
+            inner: nonblocking::HaveIBeenPwnedClient::new(apikey),
+        }
+    }
+
+    /// new client with apikey of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(HaveIBeenPwnedClient {
+            inner: nonblocking::HaveIBeenPwnedClient::from_keys(keys)?,
+        })
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.inner = self.inner.with_config(config);
+        self
+    }
+
+    /// set user agent (HIBP refuses requests without it)
+    pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Self {
+        self.inner = self.inner.with_user_agent(user_agent);
+        self
+    }
+
+    /// set interval between requests in batch operation (depends on your subscription)
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.with_interval(interval);
+        self
+    }
+
+    /// get breaches which specified email address appeared in
+    /// (empty if the email address is not pwned)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    ///
+    /// let client = HaveIBeenPwnedClient::default();
+    /// let breaches = client.breaches_of("test@example.com").expect("failed to query");
+    /// ```
+    pub fn breaches_of(&self, email: impl AsRef<str>) -> GenericResult<Vec<Breach>> {
+        block_on(self.inner.breaches_of(email))
+    }
+
+    /// is specified email address pwned?
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    ///
+    /// let client = HaveIBeenPwnedClient::default();
+    /// let pwned = client.is_pwned("test@example.com").expect("failed to query");
+    /// ```
+    pub fn is_pwned(&self, email: impl AsRef<str>) -> GenericResult<bool> {
+        block_on(self.inner.is_pwned(email))
+    }
+
+    /// batch query breaches of email addresses
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    ///
+    /// let client = HaveIBeenPwnedClient::default();
+    /// let targets = &["alice@example.com", "bob@example.com"];
+    /// for (email, breaches) in client.batch_query(targets) {
+    ///     println!("{} => {:?}", email, breaches);
+    /// }
+    /// ```
+    pub fn batch_query(
+        &self,
+        emails: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Vec<(String, GenericResult<Vec<Breach>>)> {
+        block_on(self.inner.batch_query(emails))
+    }
+
+    /// search breached accounts on your domain (the domain must be verified on HIBP dashboard)
+    /// returns map of alias (local part of email address) and breach names.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    ///
+    /// let client = HaveIBeenPwnedClient::default();
+    /// let aliases = client.breached_domain("example.com").expect("failed to search");
+    /// for (alias, breaches) in aliases {
+    ///     println!("{}@example.com => {}", alias, breaches.join(", "));
+    /// }
+    /// ```
+    pub fn breached_domain(
+        &self,
+        domain: impl AsRef<str>,
+    ) -> GenericResult<HashMap<String, Vec<String>>> {
+        block_on(self.inner.breached_domain(domain))
+    }
+}
+
+impl IocProvider for HaveIBeenPwnedClient {
+    fn name(&self) -> &str {
+        "haveibeenpwned"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::lookup_only(vec![IocKind::EmailAddress])
+    }
+
+    fn lookup(&self, ioc: &Ioc) -> GenericResult<ProviderReport> {
+        self.ensure_lookup(ioc)?;
+
+        let breaches = self.breaches_of(ioc.value())?;
+        if breaches.is_empty() {
+            return Ok(ProviderReport::not_found(self.name(), ioc.clone()));
+        }
+
+        // exposure of the address says nothing about maliciousness
+        let mut r = ProviderReport::new(self.name(), ioc.clone(), Verdict::Unknown);
+        r.tags = breaches.into_iter().map(|x| x.name).collect();
+        Ok(r)
+    }
+}
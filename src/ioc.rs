@@ -0,0 +1,873 @@
+//! typed indicators of compromise (hash / domain / url / ip address / email address)
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::confidence::Confidence;
+use crate::domain::DomainName;
+use crate::entropy::{self, Randomness};
+use crate::error::Error;
+use crate::geoip::GeoInfo;
+use crate::hashformat::HashFormat;
+use crate::provider::ProviderReport;
+use crate::psl;
+use crate::scan;
+use crate::tlp::Tlp;
+use crate::urlnorm::normalize_url;
+use crate::validate::{check, defang, kind_of, url_host, Rejection};
+pub use crate::validate::{is_domain, is_email_address, is_url, IocKind};
+use crate::whois::Registration;
+use crate::{GenericResult, SampleHash};
+
+lazy_static! {
+    static ref DEFANGED_DOT: Regex =
+        Regex::new(r"(?i)\[\.\]|\(\.\)|\{\.\}|\[dot\]|\(dot\)").unwrap();
+    static ref DEFANGED_AT: Regex = Regex::new(r"(?i)\[@\]|\(@\)|\[at\]|\(at\)").unwrap();
+    static ref DEFANGED_SCHEME: Regex = Regex::new(r"(?i)\b(hxxps?|fxp)(\[:\]|:)//").unwrap();
+}
+
+/// an indicator of compromise
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Ioc {
+    Hash(SampleHash),
+    Domain(String),
+    Url(String),
+    IpAddress(IpAddr),
+    EmailAddress(String),
+}
+
+impl Ioc {
+    /// parse indicator (kind is detected automatically)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// assert_eq!(Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap().kind(), IocKind::Hash);
+    /// assert_eq!(Ioc::new("Example.COM").unwrap(), Ioc::Domain("example.com".to_owned()));
+    /// assert_eq!(Ioc::new("http://example.com/a").unwrap().kind(), IocKind::Url);
+    /// assert_eq!(Ioc::new("192.0.2.1").unwrap().kind(), IocKind::IpAddress);
+    /// assert_eq!(Ioc::new("alice@example.com").unwrap().kind(), IocKind::EmailAddress);
+    /// assert!(Ioc::new("not an indicator").is_err());
+    /// ```
+    pub fn new(value: impl AsRef<str>) -> GenericResult<Self> {
+        value.as_ref().parse()
+    }
+
+    /// parse and validate indicator, with the reason if rejected (see `iocutil::validate::check`)
+    ///
+    /// Unlike `Ioc::new`, IP addresses (and hosts of URLs) have to be globally routable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::ipscope::IpScope;
+    /// use iocutil::prelude::*;
+    /// use iocutil::validate::Rejection;
+    ///
+    /// assert_eq!(Ioc::validate("8.8.8.8"), Ok(Ioc::new("8.8.8.8").unwrap()));
+    /// assert_eq!(Ioc::validate("пример.рф"), Ok(Ioc::Domain("xn--e1afmkfd.xn--p1ai".to_owned())));
+    /// assert_eq!(Ioc::validate("192.168.0.1"), Err(Rejection::NonRoutable(IpScope::Private)));
+    /// assert_eq!(Ioc::validate("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca49599"),
+    ///            Err(Rejection::BadChecksum { digits: 54 }));
+    /// assert_eq!(Ioc::validate("evil-.example.com"), Err(Rejection::InvalidHyphen));
+    ///
+    /// // for feed ingestion
+    /// let rejected: Vec<String> = ["example.com", "example.invalid1", ""]
+    ///     .iter()
+    ///     .filter_map(|line| Ioc::validate(line).err().map(|e| format!("{}: {}", line, e)))
+    ///     .collect();
+    /// assert_eq!(rejected, vec![
+    ///     "example.invalid1: invalid top level domain \"invalid1\"".to_owned(),
+    ///     ": empty value".to_owned(),
+    /// ]);
+    /// ```
+    pub fn validate(value: impl AsRef<str>) -> Result<Ioc, Rejection> {
+        let value = value.as_ref().trim();
+        if !value.is_ascii() && !value.contains(char::is_whitespace) {
+            if let Ok(d) = DomainName::new(value) {
+                return Ok(Ioc::Domain(d.ascii().to_owned()));
+            }
+        }
+        let kind = check(value)?;
+        Ioc::of_kind(kind, value).ok_or(Rejection::InvalidIpAddress)
+    }
+
+    /// find unique indicators in (possibly defanged) text, in order of appearance
+    /// (equivalent URLs are deduplicated by `Ioc::normalized`, keeping the first one)
+    ///
+    /// Text is split into words by whitespaces and quotes / brackets, then they are refanged.
+    /// (`iocutil::scan::iocs` finds them without allocating, borrowing from the text)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let text = "C2 is hxxps://evil[.]example[.]com/gate.php (192.0.2.1), payload: d41d8cd98f00b204e9800998ecf8427e.";
+    /// let iocs: Vec<Ioc> = Ioc::find(text);
+    /// assert_eq!(
+    ///     iocs,
+    ///     vec![
+    ///         Ioc::new("https://evil.example.com/gate.php").unwrap(),
+    ///         Ioc::new("192.0.2.1").unwrap(),
+    ///         Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+    ///     ]
+    /// );
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            name = "scrape",
+            skip_all,
+            fields(bytes = text.as_ref().len(), found = tracing::field::Empty)
+        )
+    )]
+    pub fn find<T>(text: impl AsRef<str>) -> T
+    where
+        T: std::iter::FromIterator<Ioc>,
+    {
+        let mut seen = HashSet::new();
+        let iocs: Vec<Ioc> = scan::iocs(text.as_ref())
+            .map(|x| x.to_owned())
+            .filter(|x| seen.insert(x.normalized()))
+            .collect();
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("found", iocs.len());
+        iocs.into_iter().collect()
+    }
+
+    /// indicator of detected kind (without building error for others, which are common in scraping)
+    /// (unicode domains are converted into ACE form)
+    pub(crate) fn detect(s: &str) -> Option<Ioc> {
+        match kind_of(s) {
+            Some(kind) => Ioc::of_kind(kind, s),
+            None if !s.is_ascii() && !s.contains(char::is_whitespace) => DomainName::new(s)
+                .ok()
+                .map(|d| Ioc::Domain(d.ascii().to_owned())),
+            None => None,
+        }
+    }
+
+    /// indicator of the kind already detected
+    pub(crate) fn of_kind(kind: IocKind, s: &str) -> Option<Ioc> {
+        Some(match kind {
+            IocKind::Hash => Ioc::Hash(SampleHash::new(s).ok()?),
+            IocKind::IpAddress => Ioc::IpAddress(s.parse().ok()?),
+            IocKind::Url => Ioc::Url(s.to_owned()),
+            IocKind::EmailAddress => Ioc::EmailAddress(s.to_lowercase()),
+            IocKind::Domain => Ioc::Domain(s.to_lowercase()),
+        })
+    }
+
+    /// kind of indicator
+    pub fn kind(&self) -> IocKind {
+        match self {
+            Ioc::Hash(_) => IocKind::Hash,
+            Ioc::Domain(_) => IocKind::Domain,
+            Ioc::Url(_) => IocKind::Url,
+            Ioc::IpAddress(_) => IocKind::IpAddress,
+            Ioc::EmailAddress(_) => IocKind::EmailAddress,
+        }
+    }
+
+    /// indicator to compare and deduplicate by (URLs are normalized by `urlnorm::normalize_url`,
+    /// and others are same)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let a = Ioc::new("HTTP://Example.com:80/a/../b").unwrap();
+    /// let b = Ioc::new("http://example.com/b").unwrap();
+    /// assert_ne!(a, b);
+    /// assert_eq!(a.normalized(), b.normalized());
+    /// ```
+    pub fn normalized(&self) -> Ioc {
+        match self {
+            Ioc::Url(x) => Ioc::Url(normalize_url(x)),
+            x => x.clone(),
+        }
+    }
+
+    /// domain name of domain indicator (to get its unicode form or check homographs)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let d = Ioc::new("pаypal.com").unwrap(); // with Cyrillic "а"
+    /// assert_eq!(d, Ioc::new("xn--pypal-4ve.com").unwrap());
+    /// assert_eq!(d.value(), "xn--pypal-4ve.com");
+    /// assert!(d.domain_name().unwrap().is_mixed_script());
+    /// ```
+    pub fn domain_name(&self) -> Option<DomainName> {
+        match self {
+            Ioc::Domain(x) => DomainName::new(x).ok(),
+            _ => None,
+        }
+    }
+
+    /// registrable domain of domain, host of URL or domain of email address
+    /// (by `iocutil::psl`, `None` for other kinds and IP addresses)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let url = Ioc::new("https://login.evil.example.co.uk/a").unwrap();
+    /// assert_eq!(url.registrable_domain(), Some("example.co.uk".to_owned()));
+    /// assert_eq!(Ioc::new("alice@mail.example.com").unwrap().registrable_domain(), Some("example.com".to_owned()));
+    /// assert_eq!(Ioc::new("192.0.2.1").unwrap().registrable_domain(), None);
+    /// ```
+    pub fn registrable_domain(&self) -> Option<String> {
+        match self {
+            Ioc::Domain(x) => psl::registrable_domain(x),
+            Ioc::Url(x) => match host_of_url(x)? {
+                Ioc::Domain(x) => psl::registrable_domain(x),
+                _ => None,
+            },
+            Ioc::EmailAddress(x) => psl::registrable_domain(x.rsplit('@').next()?),
+            _ => None,
+        }
+    }
+
+    /// randomness of the part adversaries generate (by `iocutil::entropy`): the label of
+    /// registrable domain, the most random of host label and path segments of URL, or the local
+    /// part of email address (`None` for hashes and IP addresses)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let url = Ioc::new("https://example.com/uploads/Xk29fQz7Lp.php").unwrap();
+    /// assert_eq!(url.randomness().unwrap().text, "Xk29fQz7Lp");
+    /// assert!(url.randomness().unwrap().is_random());
+    /// assert!(!Ioc::new("alice@example.com").unwrap().randomness().unwrap().is_random());
+    /// assert!(Ioc::new("192.0.2.1").unwrap().randomness().is_none());
+    /// ```
+    pub fn randomness(&self) -> Option<Randomness> {
+        let label = |domain: &str| {
+            let registrable = psl::registrable_domain(domain)?;
+            Some(entropy::analyze(registrable.split('.').next()?))
+        };
+        match self {
+            Ioc::Domain(x) => label(x),
+            Ioc::Url(x) => {
+                let host = match host_of_url(x) {
+                    Some(Ioc::Domain(host)) => label(&host),
+                    _ => None,
+                };
+                let rest = x.split_once("://").map_or(x.as_str(), |(_, r)| r);
+                let path = rest
+                    .find('/')
+                    .and_then(|i| entropy::path_randomness(&rest[i..]));
+                match (host, path) {
+                    (Some(h), Some(p)) if h.score >= p.score => Some(h),
+                    (h, None) => h,
+                    (_, p) => p,
+                }
+            }
+            Ioc::EmailAddress(x) => Some(entropy::analyze(x.split('@').next()?)),
+            _ => None,
+        }
+    }
+
+    /// value as string
+    pub fn value(&self) -> String {
+        self.to_string()
+    }
+
+    /// value with hashes formatted (other kinds are same as `value`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::hashformat::{HashFormatBuilder, HexCase};
+    ///
+    /// let format = HashFormatBuilder::default().case(HexCase::Upper).prefix(true).build().unwrap();
+    /// assert_eq!(Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap().formatted(&format), "md5:D41D8CD98F00B204E9800998ECF8427E");
+    /// assert_eq!(Ioc::new("example.com").unwrap().formatted(&format), "example.com");
+    /// ```
+    pub fn formatted(&self, format: &HashFormat) -> String {
+        match self {
+            Ioc::Hash(x) => format.format(x),
+            _ => self.value(),
+        }
+    }
+
+    /// defanged value which is not clickable nor resolvable (hashes are kept as is)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// assert_eq!(Ioc::new("https://evil.example.com/a.b").unwrap().defanged(), "hxxps://evil[.]example[.]com/a.b");
+    /// assert_eq!(Ioc::new("example.com").unwrap().defanged(), "example[.]com");
+    /// assert_eq!(Ioc::new("192.0.2.1").unwrap().defanged(), "192[.]0[.]2[.]1");
+    /// assert_eq!(Ioc::new("alice@example.com").unwrap().defanged(), "alice[@]example[.]com");
+    /// ```
+    pub fn defanged(&self) -> String {
+        defang(self.kind(), &self.value())
+    }
+}
+
+impl std::fmt::Display for Ioc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Ioc::Hash(x) => write!(f, "{}", x),
+            Ioc::Domain(x) => write!(f, "{}", x),
+            Ioc::Url(x) => write!(f, "{}", x),
+            Ioc::IpAddress(x) => write!(f, "{}", x),
+            Ioc::EmailAddress(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl FromStr for Ioc {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Ioc::detect(s).ok_or_else(|| Error::parse("indicator", s).into())
+    }
+}
+
+impl TryFrom<&str> for Ioc {
+    type Error = failure::Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<String> for Ioc {
+    type Error = failure::Error;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<SampleHash> for Ioc {
+    fn from(hash: SampleHash) -> Self {
+        Ioc::Hash(hash)
+    }
+}
+
+impl From<IpAddr> for Ioc {
+    fn from(ip: IpAddr) -> Self {
+        Ioc::IpAddress(ip)
+    }
+}
+
+/// phase of Lockheed Martin Cyber Kill Chain
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KillChainPhase {
+    Reconnaissance,
+    Weaponization,
+    Delivery,
+    Exploitation,
+    Installation,
+    CommandAndControl,
+    ActionsOnObjectives,
+}
+
+impl KillChainPhase {
+    /// all phases in order
+    pub const ALL: [KillChainPhase; 7] = [
+        KillChainPhase::Reconnaissance,
+        KillChainPhase::Weaponization,
+        KillChainPhase::Delivery,
+        KillChainPhase::Exploitation,
+        KillChainPhase::Installation,
+        KillChainPhase::CommandAndControl,
+        KillChainPhase::ActionsOnObjectives,
+    ];
+
+    /// phase name in STIX (`phase_name` of `lockheed-martin-cyber-kill-chain`)
+    pub fn name(self) -> &'static str {
+        match self {
+            KillChainPhase::Reconnaissance => "reconnaissance",
+            KillChainPhase::Weaponization => "weaponization",
+            KillChainPhase::Delivery => "delivery",
+            KillChainPhase::Exploitation => "exploitation",
+            KillChainPhase::Installation => "installation",
+            KillChainPhase::CommandAndControl => "command-and-control",
+            KillChainPhase::ActionsOnObjectives => "actions-on-objectives",
+        }
+    }
+
+    /// value in MISP `kill-chain` taxonomy
+    pub fn misp_name(self) -> &'static str {
+        match self {
+            KillChainPhase::Reconnaissance => "Reconnaissance",
+            KillChainPhase::Weaponization => "Weaponization",
+            KillChainPhase::Delivery => "Delivery",
+            KillChainPhase::Exploitation => "Exploitation",
+            KillChainPhase::Installation => "Installation",
+            KillChainPhase::CommandAndControl => "Command and Control",
+            KillChainPhase::ActionsOnObjectives => "Actions on Objectives",
+        }
+    }
+}
+
+impl std::fmt::Display for KillChainPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// phase by STIX or MISP name (case and separators are ignored, e.g. `C2` is not accepted)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+///
+/// assert_eq!("command-and-control".parse::<KillChainPhase>().unwrap(), KillChainPhase::CommandAndControl);
+/// assert_eq!("Actions on Objectives".parse::<KillChainPhase>().unwrap(), KillChainPhase::ActionsOnObjectives);
+/// assert!("lateral-movement".parse::<KillChainPhase>().is_err());
+/// ```
+impl FromStr for KillChainPhase {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = |x: &str| -> String {
+            x.chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase()
+        };
+        let k = key(s);
+        KillChainPhase::ALL
+            .iter()
+            .find(|p| key(p.name()) == k)
+            .copied()
+            .ok_or_else(|| Error::parse("kill chain phase", s).into())
+    }
+}
+
+/// metadata of an indicator (serialized in the same schema as `iocutil::jsonl`, without kind /
+/// value)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+///
+/// let meta = Metadata {
+///     tags: vec!["tlp:amber".to_owned()],
+///     kill_chain_phases: vec![KillChainPhase::Delivery],
+///     malware_family: Some("Emotet".to_owned()),
+///     threat_actor: Some("TA542".to_owned()),
+///     ..Metadata::default()
+/// };
+/// let json = serde_json::to_string(&meta).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"tags":["tlp:amber"],"kill_chain_phases":["delivery"],"malware_family":"Emotet","threat_actor":"TA542"}"#
+/// );
+/// assert_eq!(serde_json::from_str::<Metadata>(&json).unwrap(), meta);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// free-form tags
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// confidence (0-100 with qualitative band)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
+    /// expired after this time (see `iocutil::ttl`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kill_chain_phases: Vec<KillChainPhase>,
+    /// attribution to malware family (e.g. `Emotet`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub malware_family: Option<String>,
+    /// attribution to threat actor (e.g. `APT28`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threat_actor: Option<String>,
+    /// TLP marking (see `iocutil::tlp`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tlp: Option<Tlp>,
+    /// location and network of IP address (see `iocutil::geoip`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoInfo>,
+    /// registration of domain or IP address (see `iocutil::whois`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration: Option<Registration>,
+}
+
+impl Metadata {
+    /// newer metadata of the same indicator merged into this one
+    ///
+    /// `first_seen` / `last_seen` / `valid_until` widen the range, tags and kill chain phases are
+    /// joined, newer source / description / attributions / geolocation / registration win, and the
+    /// stricter TLP wins. Confidences are combined by `Confidence::merge` (replaced if both are from the same
+    /// source, otherwise corroborated).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::confidence::Confidence;
+    /// use iocutil::prelude::*;
+    ///
+    /// let meta = |source: &str, confidence| Metadata {
+    ///     source: Some(source.to_owned()),
+    ///     confidence: Some(Confidence::new(confidence)),
+    ///     ..Metadata::default()
+    /// };
+    ///
+    /// let merged = meta("feed-a", 60).merge(meta("feed-b", 50));
+    /// assert_eq!(merged.source.as_deref(), Some("feed-b"));
+    /// assert_eq!(merged.confidence, Some(Confidence::new(80)));
+    ///
+    /// let rescored = meta("feed-a", 60).merge(meta("feed-a", 30));
+    /// assert_eq!(rescored.confidence, Some(Confidence::new(30)));
+    /// ```
+    pub fn merge(self, new: Metadata) -> Metadata {
+        let min = |a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let max = |a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+
+        let mut tags = self.tags;
+        for t in new.tags {
+            if !tags.contains(&t) {
+                tags.push(t);
+            }
+        }
+
+        let mut kill_chain_phases = self.kill_chain_phases;
+        for p in new.kill_chain_phases {
+            if !kill_chain_phases.contains(&p) {
+                kill_chain_phases.push(p);
+            }
+        }
+
+        let same_source = self.source.is_some() && self.source == new.source;
+        Metadata {
+            first_seen: min(self.first_seen, new.first_seen),
+            last_seen: max(self.last_seen, new.last_seen),
+            confidence: Confidence::merge(self.confidence, new.confidence, same_source),
+            valid_until: max(self.valid_until, new.valid_until),
+            kill_chain_phases,
+            malware_family: new.malware_family.or(self.malware_family),
+            threat_actor: new.threat_actor.or(self.threat_actor),
+            tlp: self.tlp.max(new.tlp),
+            geo: match (self.geo, new.geo) {
+                (Some(old), Some(new)) => Some(old.merge(new)),
+                (old, new) => new.or(old),
+            },
+            registration: match (self.registration, new.registration) {
+                (Some(old), Some(new)) => Some(old.merge(new)),
+                (old, new) => new.or(old),
+            },
+            source: new.source.or(self.source),
+            description: new.description.or(self.description),
+            tags,
+        }
+    }
+}
+
+/// an indicator with its metadata
+#[derive(Clone, Debug, PartialEq)]
+pub struct Indicator {
+    pub ioc: Ioc,
+    pub meta: Metadata,
+}
+
+impl Indicator {
+    /// new indicator without metadata
+    pub fn new(ioc: impl Into<Ioc>) -> Self {
+        Indicator {
+            ioc: ioc.into(),
+            meta: Metadata::default(),
+        }
+    }
+
+    /// new indicator with metadata
+    pub fn with_meta(ioc: impl Into<Ioc>, meta: Metadata) -> Self {
+        Indicator {
+            ioc: ioc.into(),
+            meta,
+        }
+    }
+
+    /// expired (`valid_until` is past)?
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Utc::now())
+    }
+
+    /// expired at the time?
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.meta.valid_until.is_some_and(|x| x <= now)
+    }
+
+    /// enrich metadata by report of provider (tags are joined, and confidence is corroborated
+    /// by `ProviderReport::confidence` as another source)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::confidence::{Confidence, ConfidenceBand};
+    /// use iocutil::prelude::*;
+    ///
+    /// let ioc = Ioc::new("evil.example.com").unwrap();
+    /// let mut i = Indicator::new(ioc.clone());
+    /// i.meta.confidence = Some(Confidence::new(40));
+    ///
+    /// i.enrich(&ProviderReport::not_found("internal", ioc.clone()));
+    /// assert_eq!(i.meta.confidence, Some(Confidence::new(40)));
+    ///
+    /// let mut report = ProviderReport::new("virustotal", ioc, Verdict::Malicious);
+    /// report.tags = vec!["c2".to_owned()];
+    /// i.enrich(&report);
+    /// assert_eq!(i.meta.confidence, Some(Confidence::new(91)));
+    /// assert_eq!(i.meta.confidence.unwrap().band(), ConfidenceBand::High);
+    /// assert_eq!(i.meta.tags, vec!["c2".to_owned()]);
+    /// ```
+    pub fn enrich(&mut self, report: &ProviderReport) {
+        for t in &report.tags {
+            if !self.meta.tags.contains(t) {
+                self.meta.tags.push(t.clone());
+            }
+        }
+        self.meta.confidence = Confidence::merge(self.meta.confidence, report.confidence(), false);
+    }
+}
+
+impl From<Ioc> for Indicator {
+    fn from(ioc: Ioc) -> Self {
+        Indicator::new(ioc)
+    }
+}
+
+impl From<SampleHash> for Indicator {
+    fn from(hash: SampleHash) -> Self {
+        Indicator::new(hash)
+    }
+}
+
+/// host part of url as indicator (domain or ip address)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::ioc::host_of_url;
+///
+/// assert_eq!(host_of_url("https://user@Example.com:8443/a?b#c"), Some(Ioc::Domain("example.com".to_owned())));
+/// assert_eq!(host_of_url("http://[2001:db8::1]:80/"), Some(Ioc::IpAddress("2001:db8::1".parse().unwrap())));
+/// assert_eq!(host_of_url("http://192.0.2.1"), Some(Ioc::IpAddress("192.0.2.1".parse().unwrap())));
+/// assert_eq!(host_of_url("example.com/a"), None);
+/// ```
+pub fn host_of_url(url: impl AsRef<str>) -> Option<Ioc> {
+    let host = url_host(url.as_ref())?;
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        Some(Ioc::IpAddress(ip))
+    } else if is_domain(host) {
+        Some(Ioc::Domain(host.trim_end_matches('.').to_lowercase()))
+    } else {
+        None
+    }
+}
+
+/// may the text contain defanged forms? (they are bracketed except schemes `hxxp` and `fxp`)
+pub(crate) fn may_be_defanged(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.iter().any(|b| b"[({".contains(b))
+        || bytes.windows(4).any(|x| x.eq_ignore_ascii_case(b"hxxp"))
+        || bytes.windows(3).any(|x| x.eq_ignore_ascii_case(b"fxp"))
+}
+
+/// restore defanged text (e.g. `hxxps://example[.]com`) into original form
+///
+/// # Example
+///
+/// ```
+/// use iocutil::ioc::refang;
+///
+/// assert_eq!(refang("hxxps://evil[.]example(.)com/a.b"), "https://evil.example.com/a.b");
+/// assert_eq!(refang("alice[@]example[dot]com"), "alice@example.com");
+/// assert_eq!(refang("2001[:]db8[:][:]1"), "2001:db8::1");
+/// assert_eq!(refang("example.com"), "example.com");
+/// ```
+pub fn refang(text: impl AsRef<str>) -> String {
+    let text = text.as_ref();
+    if !may_be_defanged(text) {
+        return text.to_owned();
+    }
+
+    let text = DEFANGED_SCHEME.replace_all(text, |c: &regex::Captures<'_>| {
+        let scheme = match c[1].to_lowercase().as_str() {
+            "fxp" => "ftp".to_owned(),
+            x => x.replace("xx", "tt"),
+        };
+        format!("{}://", scheme)
+    });
+    let text = DEFANGED_DOT.replace_all(&text, ".");
+    let text = DEFANGED_AT.replace_all(&text, "@");
+    text.replace("[:]", ":")
+}
+
+/// whether a token is defanged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FangState {
+    /// an indicator as is
+    Fanged,
+    /// an indicator defanged wholly (as `validate::defang` does)
+    Defanged,
+    /// an indicator defanged partly (e.g. `evil[.]example.com`), often copied from reports
+    Mixed,
+    /// not an indicator even if refanged
+    NotAnIoc,
+}
+
+/// whether the token is an indicator fanged, defanged or partly defanged
+///
+/// # Example
+///
+/// ```
+/// use iocutil::ioc::{fang_state, FangState};
+///
+/// assert_eq!(fang_state("https://evil.example.com/a"), FangState::Fanged);
+/// assert_eq!(fang_state("hxxps://evil[.]example[.]com/a.b"), FangState::Defanged);
+/// assert_eq!(fang_state("hxxps://evil[.]example.com/"), FangState::Mixed);
+/// assert_eq!(fang_state("https://evil[.]example[.]com/"), FangState::Mixed);
+/// assert_eq!(fang_state("evil[.]example(.)com"), FangState::Defanged);
+/// assert_eq!(fang_state("evil[.]example.com"), FangState::Mixed);
+/// assert_eq!(fang_state("alice[@]example[.]com"), FangState::Defanged);
+/// assert_eq!(fang_state("alice@example[.]com"), FangState::Mixed);
+/// assert_eq!(fang_state("2001[:]db8[:][:]1"), FangState::Defanged);
+/// assert_eq!(fang_state("d41d8cd98f00b204e9800998ecf8427e"), FangState::Fanged);
+/// assert_eq!(fang_state("not[.]an indicator"), FangState::NotAnIoc);
+/// ```
+pub fn fang_state(text: impl AsRef<str>) -> FangState {
+    let text = text.as_ref().trim();
+    let refanged = refang(text);
+    let ioc = match Ioc::new(&refanged) {
+        Ok(x) => x,
+        Err(_) => return FangState::NotAnIoc,
+    };
+    if refanged == text {
+        return FangState::Fanged;
+    }
+
+    // separators left fanged, once defanged ones are removed
+    let live = DEFANGED_SCHEME.replace_all(text, "");
+    let live = DEFANGED_DOT.replace_all(&live, "");
+    let live = DEFANGED_AT.replace_all(&live, "").replace("[:]", "");
+    let mixed = match &ioc {
+        Ioc::Hash(_) => false,
+        Ioc::Domain(_) => live.contains('.'),
+        Ioc::IpAddress(IpAddr::V4(_)) => live.contains('.'),
+        Ioc::IpAddress(IpAddr::V6(_)) => live.contains(':'),
+        Ioc::EmailAddress(_) => live.contains('.') || live.contains('@'),
+        Ioc::Url(_) => {
+            // only scheme and authority are defanged to keep path readable
+            let authority = match live.find("//") {
+                Some(i) => &live[i + 2..],
+                None => &live,
+            };
+            let end = authority
+                .find(&['/', '?', '#'][..])
+                .unwrap_or(authority.len());
+            let defangable = refanged
+                .get(..refanged.find("://").unwrap_or(0))
+                .is_some_and(|x| ["http", "https", "ftp"].contains(&x.to_lowercase().as_str()));
+            authority[..end].contains('.') || (defangable && !DEFANGED_SCHEME.is_match(text))
+        }
+    };
+    if mixed {
+        FangState::Mixed
+    } else {
+        FangState::Defanged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        let h: Ioc = "D41D8CD98F00B204E9800998ECF8427E".parse().unwrap();
+        assert_eq!(
+            h,
+            Ioc::Hash(SampleHash::new("d41d8cd98f00b204e9800998ecf8427e").unwrap())
+        );
+
+        let ip: Ioc = "2001:db8::1".parse().unwrap();
+        assert_eq!(ip, Ioc::IpAddress("2001:db8::1".parse().unwrap()));
+
+        let url: Ioc = " https://Example.com/Path ".parse().unwrap();
+        assert_eq!(url, Ioc::Url("https://Example.com/Path".to_owned()));
+
+        let email: Ioc = "Alice@Example.com".parse().unwrap();
+        assert_eq!(email, Ioc::EmailAddress("alice@example.com".to_owned()));
+
+        let domain: Ioc = "www.Example.co.jp".parse().unwrap();
+        assert_eq!(domain, Ioc::Domain("www.example.co.jp".to_owned()));
+
+        assert!("".parse::<Ioc>().is_err());
+        assert!("a b.com".parse::<Ioc>().is_err());
+        assert!("example.c0m".parse::<Ioc>().is_err());
+    }
+
+    #[test]
+    fn display_works() {
+        let v = vec![
+            "d41d8cd98f00b204e9800998ecf8427e",
+            "example.com",
+            "http://example.com/",
+            "192.0.2.1",
+            "alice@example.com",
+        ];
+        for x in v {
+            assert_eq!(Ioc::new(x).unwrap().to_string().as_str(), x);
+        }
+    }
+
+    #[test]
+    fn find_dedups_equivalent_urls() {
+        let text = "http://Example.com:80/a/./b hxxp://example[.]com/a/b http://example.com/a/c";
+        let iocs: Vec<Ioc> = Ioc::find(text);
+        assert_eq!(
+            iocs,
+            vec![
+                Ioc::Url("http://Example.com:80/a/./b".to_owned()),
+                Ioc::Url("http://example.com/a/c".to_owned()),
+            ]
+        );
+    }
+}
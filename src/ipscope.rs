@@ -0,0 +1,147 @@
+//! scope of IP addresses (private / loopback / link-local / reserved / public) and bogon check
+//!
+//! Only `core` is required (same as `iocutil::validate`).
+
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// scope of IP address
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum IpScope {
+    /// private networks (RFC 1918, shared address space of RFC 6598 and unique local of RFC 4193)
+    Private,
+    Loopback,
+    LinkLocal,
+    /// documentation, benchmarking, multicast, unspecified and other special-purpose or unallocated
+    Reserved,
+    /// globally routable
+    Public,
+}
+
+const fn v4(a: u8, b: u8, c: u8, d: u8) -> u32 {
+    u32::from_be_bytes([a, b, c, d])
+}
+
+/// IPv4 bogons (network, prefix length and scope)
+const BOGONS_V4: &[(u32, u32, IpScope)] = &[
+    (v4(0, 0, 0, 0), 8, IpScope::Reserved),
+    (v4(10, 0, 0, 0), 8, IpScope::Private),
+    (v4(100, 64, 0, 0), 10, IpScope::Private),
+    (v4(127, 0, 0, 0), 8, IpScope::Loopback),
+    (v4(169, 254, 0, 0), 16, IpScope::LinkLocal),
+    (v4(172, 16, 0, 0), 12, IpScope::Private),
+    (v4(192, 0, 0, 0), 24, IpScope::Reserved),
+    (v4(192, 0, 2, 0), 24, IpScope::Reserved),
+    (v4(192, 88, 99, 0), 24, IpScope::Reserved),
+    (v4(192, 168, 0, 0), 16, IpScope::Private),
+    (v4(198, 18, 0, 0), 15, IpScope::Reserved),
+    (v4(198, 51, 100, 0), 24, IpScope::Reserved),
+    (v4(203, 0, 113, 0), 24, IpScope::Reserved),
+    (v4(224, 0, 0, 0), 4, IpScope::Reserved),
+    (v4(240, 0, 0, 0), 4, IpScope::Reserved),
+];
+
+/// IPv6 bogons (addresses out of global unicast `2000::/3` are also bogons)
+const BOGONS_V6: &[(u128, u32, IpScope)] = &[
+    (0, 128, IpScope::Reserved),
+    (1, 128, IpScope::Loopback),
+    (0x0100_0000_0000_0000 << 64, 64, IpScope::Reserved),
+    (0x2001_0002 << 96, 48, IpScope::Reserved),
+    (0x2001_0010 << 96, 28, IpScope::Reserved),
+    (0x2001_0db8 << 96, 32, IpScope::Reserved),
+    (0x3fff << 112, 20, IpScope::Reserved),
+    (0xfc00 << 112, 7, IpScope::Private),
+    (0xfe80 << 112, 10, IpScope::LinkLocal),
+    (0xfec0 << 112, 10, IpScope::Reserved),
+    (0xff00 << 112, 8, IpScope::Reserved),
+];
+
+fn in_prefix4(ip: u32, network: u32, prefix: u32) -> bool {
+    prefix == 0 || (ip ^ network) >> (32 - prefix) == 0
+}
+
+fn in_prefix6(ip: u128, network: u128, prefix: u32) -> bool {
+    prefix == 0 || (ip ^ network) >> (128 - prefix) == 0
+}
+
+fn scope_v4(ip: &Ipv4Addr) -> IpScope {
+    let ip = u32::from(*ip);
+    BOGONS_V4
+        .iter()
+        .find(|(network, prefix, _)| in_prefix4(ip, *network, *prefix))
+        .map(|(_, _, scope)| *scope)
+        .unwrap_or(IpScope::Public)
+}
+
+fn scope_v6(ip: &Ipv6Addr) -> IpScope {
+    let bits = u128::from(*ip);
+    // IPv4-mapped (`::ffff:0:0/96`) and NAT64 (`64:ff9b::/96`) addresses are scoped as embedded ones
+    if in_prefix6(bits, 0xffff << 32, 96) || in_prefix6(bits, 0x0064_ff9b << 96, 96) {
+        return scope_v4(&Ipv4Addr::from(bits as u32));
+    }
+    match BOGONS_V6
+        .iter()
+        .find(|(network, prefix, _)| in_prefix6(bits, *network, *prefix))
+    {
+        Some((_, _, scope)) => *scope,
+        None if in_prefix6(bits, 0x2000 << 112, 3) => IpScope::Public,
+        None => IpScope::Reserved,
+    }
+}
+
+/// scope of IP addresses
+///
+/// # Example
+///
+/// ```
+/// use iocutil::ipscope::{IpAddressExt, IpScope};
+/// use std::net::IpAddr;
+///
+/// let ip = |x: &str| x.parse::<IpAddr>().unwrap();
+/// assert_eq!(ip("10.1.2.3").scope(), IpScope::Private);
+/// assert_eq!(ip("127.0.0.1").scope(), IpScope::Loopback);
+/// assert_eq!(ip("169.254.169.254").scope(), IpScope::LinkLocal);
+/// assert_eq!(ip("192.0.2.1").scope(), IpScope::Reserved);
+/// assert_eq!(ip("8.8.8.8").scope(), IpScope::Public);
+/// assert_eq!(ip("fd00::1").scope(), IpScope::Private);
+/// assert_eq!(ip("::ffff:192.168.0.1").scope(), IpScope::Private);
+/// assert_eq!(ip("2001:db8::1").scope(), IpScope::Reserved);
+/// assert_eq!(ip("2606:4700::1111").scope(), IpScope::Public);
+///
+/// assert!(ip("100.64.0.1").is_bogon());
+/// assert!(!ip("1.1.1.1").is_bogon());
+/// ```
+pub trait IpAddressExt {
+    /// scope of the address
+    fn scope(&self) -> IpScope;
+
+    /// is the address globally routable?
+    fn is_public(&self) -> bool {
+        self.scope() == IpScope::Public
+    }
+
+    /// is the address in bogon prefixes (which should not appear on the Internet)?
+    fn is_bogon(&self) -> bool {
+        !self.is_public()
+    }
+}
+
+impl IpAddressExt for Ipv4Addr {
+    fn scope(&self) -> IpScope {
+        scope_v4(self)
+    }
+}
+
+impl IpAddressExt for Ipv6Addr {
+    fn scope(&self) -> IpScope {
+        scope_v6(self)
+    }
+}
+
+impl IpAddressExt for IpAddr {
+    fn scope(&self) -> IpScope {
+        match self {
+            IpAddr::V4(x) => scope_v4(x),
+            IpAddr::V6(x) => scope_v6(x),
+        }
+    }
+}
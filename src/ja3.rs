@@ -0,0 +1,208 @@
+//! JA3 fingerprint of TLS client (computed from raw ClientHello)
+
+use failure::Fail;
+use md5::{Digest, Md5};
+
+use crate::fingerprint::Fingerprint;
+
+const HANDSHAKE: u8 = 0x16;
+const CLIENT_HELLO: u8 = 0x01;
+const SUPPORTED_GROUPS: u16 = 10;
+const EC_POINT_FORMATS: u16 = 11;
+
+/// Errors in parsing ClientHello
+#[derive(Fail, Debug)]
+pub enum Ja3Error {
+    #[fail(display = "not a TLS ClientHello")]
+    NotClientHello,
+    #[fail(display = "truncated ClientHello")]
+    Truncated,
+}
+
+/// fields of ClientHello used in JA3
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ja3 {
+    /// version in ClientHello (e.g. `771` for TLS 1.2)
+    pub version: u16,
+    pub ciphers: Vec<u16>,
+    pub extensions: Vec<u16>,
+    /// supported groups (elliptic curves)
+    pub curves: Vec<u16>,
+    pub point_formats: Vec<u8>,
+}
+
+/// GREASE values (RFC 8701) are ignored in JA3
+fn is_grease(x: u16) -> bool {
+    x & 0x0f0f == 0x0a0a && x >> 8 == x & 0xff
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Ja3Error> {
+        if self.bytes.len() < n {
+            return Err(Ja3Error::Truncated);
+        }
+        let (x, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(x)
+    }
+
+    fn u8(&mut self) -> Result<u8, Ja3Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Ja3Error> {
+        let x = self.take(2)?;
+        Ok(u16::from_be_bytes([x[0], x[1]]))
+    }
+
+    fn u24(&mut self) -> Result<usize, Ja3Error> {
+        let x = self.take(3)?;
+        Ok(usize::from(x[0]) << 16 | usize::from(x[1]) << 8 | usize::from(x[2]))
+    }
+
+    /// vector with length prefix of `len` bytes
+    fn vector(&mut self, len: usize) -> Result<Reader<'a>, Ja3Error> {
+        let n = match len {
+            1 => usize::from(self.u8()?),
+            _ => usize::from(self.u16()?),
+        };
+        Ok(Reader {
+            bytes: self.take(n)?,
+        })
+    }
+
+    fn u16s(mut self) -> Result<Vec<u16>, Ja3Error> {
+        let mut items = vec![];
+        while !self.bytes.is_empty() {
+            items.push(self.u16()?);
+        }
+        Ok(items)
+    }
+}
+
+/// handshake messages in TLS records (fragmented handshake is reassembled)
+fn handshake(bytes: &[u8]) -> Result<Vec<u8>, Ja3Error> {
+    if bytes.first() != Some(&HANDSHAKE) {
+        return Ok(bytes.to_vec());
+    }
+    let mut messages = vec![];
+    let mut r = Reader { bytes };
+    while r.bytes.first() == Some(&HANDSHAKE) {
+        r.take(3)?;
+        let len = usize::from(r.u16()?);
+        // the last record may be cut by the capture
+        messages.extend_from_slice(r.take(len.min(r.bytes.len()))?);
+    }
+    Ok(messages)
+}
+
+impl Ja3 {
+    /// parse ClientHello in TLS records or handshake message
+    ///
+    /// Bytes may start with TLS record header (e.g. TCP payload extracted from pcap)
+    /// or with handshake header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::ja3::Ja3;
+    ///
+    /// let hello: Vec<u8> = vec![
+    ///     0x16, 0x03, 0x01, 0x00, 0x43, // record header
+    ///     0x01, 0x00, 0x00, 0x3f, 0x03, 0x03, // handshake header, version
+    ///     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // random
+    ///     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ///     0x00, // session id
+    ///     0x00, 0x04, 0x0a, 0x0a, 0xc0, 0x2f, // cipher suites
+    ///     0x01, 0x00, // compression methods
+    ///     0x00, 0x12, // extensions
+    ///     0x00, 0x0a, 0x00, 0x08, 0x00, 0x06, 0x1a, 0x1a, 0x00, 0x1d, 0x00, 0x17,
+    ///     0x00, 0x0b, 0x00, 0x02, 0x01, 0x00,
+    /// ];
+    ///
+    /// let ja3 = Ja3::from_client_hello(&hello).unwrap();
+    /// assert_eq!(ja3.to_string(), "771,49199,10-11,29-23,0");
+    /// assert_eq!(ja3.md5(), "4f6e8df46158540f013f52802673ad75");
+    /// ```
+    pub fn from_client_hello(bytes: impl AsRef<[u8]>) -> Result<Self, Ja3Error> {
+        let message = handshake(bytes.as_ref())?;
+        let mut r = Reader { bytes: &message };
+        if r.u8()? != CLIENT_HELLO {
+            return Err(Ja3Error::NotClientHello);
+        }
+        let len = r.u24()?;
+        let mut r = Reader {
+            bytes: r.take(len)?,
+        };
+
+        let version = r.u16()?;
+        r.take(32)?;
+        r.vector(1)?;
+        let ciphers = r.vector(2)?.u16s()?;
+        r.vector(1)?;
+
+        let mut extensions = vec![];
+        let mut curves = vec![];
+        let mut point_formats = vec![];
+        // extensions are absent in old clients
+        if !r.bytes.is_empty() {
+            let mut exts = r.vector(2)?;
+            while !exts.bytes.is_empty() {
+                let kind = exts.u16()?;
+                let mut data = exts.vector(2)?;
+                match kind {
+                    SUPPORTED_GROUPS => curves = data.vector(2)?.u16s()?,
+                    EC_POINT_FORMATS => point_formats = data.vector(1)?.bytes.to_vec(),
+                    _ => {}
+                }
+                extensions.push(kind);
+            }
+        }
+
+        let without_grease = |x: Vec<u16>| x.into_iter().filter(|&x| !is_grease(x)).collect();
+        Ok(Ja3 {
+            version,
+            ciphers: without_grease(ciphers),
+            extensions: without_grease(extensions),
+            curves: without_grease(curves),
+            point_formats,
+        })
+    }
+
+    /// md5 of JA3 string
+    pub fn md5(&self) -> String {
+        format!("{:x}", Md5::digest(self.to_string()))
+    }
+
+    /// JA3 hash as fingerprint
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::Ja3(self.md5())
+    }
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl std::fmt::Display for Ja3 {
+    /// JA3 string (`version,ciphers,extensions,curves,point_formats`)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{},{},{},{},{}",
+            self.version,
+            join(&self.ciphers),
+            join(&self.extensions),
+            join(&self.curves),
+            join(&self.point_formats)
+        )
+    }
+}
@@ -0,0 +1,225 @@
+//! JSON Lines (ndjson) serialization of indicators
+//!
+//! Each line is an object in following schema (optional fields are omitted when empty).
+//!
+//! | field               | type             | note                                     |
+//! |---------------------|------------------|------------------------------------------|
+//! | `kind`              | string           | `hash`, `domain`, `url`, `ip` or `email` |
+//! | `value`             | string           | value of indicator (not defanged)        |
+//! | `first_seen`        | string           | RFC 3339 (optional)                      |
+//! | `last_seen`         | string           | RFC 3339 (optional)                      |
+//! | `source`            | string           | optional                                 |
+//! | `description`       | string           | optional                                 |
+//! | `tags`              | array of strings | optional                                 |
+//! | `confidence`        | number           | 0-100 (optional)                         |
+//! | `valid_until`       | string           | RFC 3339 (optional)                      |
+//! | `kill_chain_phases` | array of strings | e.g. `delivery` (optional)               |
+//! | `malware_family`    | string           | optional                                 |
+//! | `threat_actor`      | string           | optional                                 |
+//! | `tlp`               | string           | e.g. `amber+strict` (optional)           |
+//! | `geo`               | object           | location of IP address (optional)        |
+//! | `registration`      | object           | registrar / creation date (optional)     |
+//!
+//! Expired indicators are kept (JSON Lines is a lossless dump, see `iocutil::ttl`).
+
+use chrono::{DateTime, Utc};
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+
+use crate::confidence::Confidence;
+use crate::geoip::GeoInfo;
+use crate::ioc::{Indicator, Ioc, KillChainPhase, Metadata};
+use crate::tlp::Tlp;
+use crate::whois::Registration;
+use crate::GenericResult;
+
+/// Errors in reading JSON Lines
+#[derive(Fail, Debug)]
+pub enum JsonlError {
+    #[fail(display = "kind of value is mismatched")]
+    KindMismatch(String, String),
+}
+
+/// an indicator in the schema above (also used by `iocutil::collection`)
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Record {
+    kind: String,
+    value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    first_seen: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_seen: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confidence: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    valid_until: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    kill_chain_phases: Vec<KillChainPhase>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    malware_family: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    threat_actor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tlp: Option<Tlp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    geo: Option<GeoInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    registration: Option<Registration>,
+}
+
+impl From<&Indicator> for Record {
+    fn from(i: &Indicator) -> Self {
+        let meta = &i.meta;
+        Record {
+            kind: i.ioc.kind().to_string(),
+            value: i.ioc.value(),
+            first_seen: meta.first_seen.map(|x| x.to_rfc3339()),
+            last_seen: meta.last_seen.map(|x| x.to_rfc3339()),
+            source: meta.source.clone(),
+            description: meta.description.clone(),
+            tags: meta.tags.clone(),
+            confidence: meta.confidence.map(Confidence::score),
+            valid_until: meta.valid_until.map(|x| x.to_rfc3339()),
+            kill_chain_phases: meta.kill_chain_phases.clone(),
+            malware_family: meta.malware_family.clone(),
+            threat_actor: meta.threat_actor.clone(),
+            tlp: meta.tlp,
+            geo: meta.geo.clone(),
+            registration: meta.registration.clone(),
+        }
+    }
+}
+
+fn parse_datetime(text: Option<String>) -> GenericResult<Option<DateTime<Utc>>> {
+    match text {
+        Some(t) => Ok(Some(DateTime::parse_from_rfc3339(&t)?.into())),
+        None => Ok(None),
+    }
+}
+
+impl Record {
+    pub(crate) fn into_indicator(self) -> GenericResult<Indicator> {
+        let ioc = Ioc::new(&self.value)?;
+        if ioc.kind().to_string() != self.kind {
+            return Err(JsonlError::KindMismatch(self.kind, self.value).into());
+        }
+
+        let meta = Metadata {
+            first_seen: parse_datetime(self.first_seen)?,
+            last_seen: parse_datetime(self.last_seen)?,
+            source: self.source,
+            description: self.description,
+            tags: self.tags,
+            confidence: self.confidence.map(Confidence::new),
+            valid_until: parse_datetime(self.valid_until)?,
+            kill_chain_phases: self.kill_chain_phases,
+            malware_family: self.malware_family,
+            threat_actor: self.threat_actor,
+            tlp: self.tlp,
+            geo: self.geo,
+            registration: self.registration,
+        };
+        Ok(Indicator::with_meta(ioc, meta))
+    }
+}
+
+/// write indicators as JSON Lines
+pub fn write_jsonl(
+    mut writer: impl Write,
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+) -> GenericResult<()> {
+    for indicator in indicators.into_iter().map(|x| x.into()) {
+        serde_json::to_writer(&mut writer, &Record::from(&indicator))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// export indicators as JSON Lines
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::jsonl::to_jsonl;
+///
+/// let mut i = Indicator::new(Ioc::new("example.com").unwrap());
+/// i.meta.tags = vec!["phishing".to_owned()];
+///
+/// assert_eq!(
+///     to_jsonl(vec![i]),
+///     "{\"kind\":\"domain\",\"value\":\"example.com\",\"tags\":[\"phishing\"]}\n"
+/// );
+/// ```
+pub fn to_jsonl(indicators: impl IntoIterator<Item = impl Into<Indicator>>) -> String {
+    let mut buf = vec![];
+    // serializing records into vec never fails
+    write_jsonl(&mut buf, indicators).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+/// streaming reader of JSON Lines
+pub struct JsonlReader<R> {
+    lines: std::io::Lines<R>,
+}
+
+impl<R: BufRead> Iterator for JsonlReader<R> {
+    type Item = GenericResult<Indicator>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(l) => l,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            return Some(
+                serde_json::from_str::<Record>(&line)
+                    .map_err(|e| e.into())
+                    .and_then(|r| r.into_indicator()),
+            );
+        }
+    }
+}
+
+/// read indicators from JSON Lines one by one
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::confidence::Confidence;
+/// use iocutil::tlp::Tlp;
+/// use iocutil::jsonl::{from_jsonl, to_jsonl};
+///
+/// let mut i = Indicator::new(Ioc::new("http://example.com/a").unwrap());
+/// i.meta.first_seen = Some(day!(2019, 11, 1).unwrap().into());
+/// i.meta.confidence = Some(Confidence::new(80));
+/// i.meta.valid_until = Some(day!(2019, 12, 1).unwrap().into());
+/// i.meta.kill_chain_phases = vec![KillChainPhase::Delivery];
+/// i.meta.malware_family = Some("Emotet".to_owned());
+/// i.meta.tlp = Some(Tlp::AmberStrict);
+///
+/// let jsonl = to_jsonl(vec![i.clone()]);
+/// let imported: Result<Vec<_>, _> = from_jsonl(jsonl.as_bytes()).collect();
+/// assert_eq!(imported.unwrap(), vec![i]);
+///
+/// let mismatched = r#"{"kind":"hash","value":"example.com"}"#;
+/// assert!(from_jsonl(mismatched.as_bytes()).next().unwrap().is_err());
+/// ```
+pub fn from_jsonl<R: BufRead>(reader: R) -> JsonlReader<R> {
+    JsonlReader {
+        lines: reader.lines(),
+    }
+}
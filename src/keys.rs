@@ -0,0 +1,273 @@
+//! loading API keys of providers (explicit strings, environment variables and config file)
+//!
+//! API clients are created from an `ApiKeyProvider` by `from_keys`, and their `Default` uses
+//! `DefaultKeys` (environment variables, OS credential store with `keyring` feature, then
+//! `~/.config/iocutil/config.toml`).
+//!
+//! ```toml
+//! # ~/.config/iocutil/config.toml
+//! [virustotal]
+//! api_key = "..."
+//!
+//! [alienvault-otx]
+//! api_key = "..."
+//! ```
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::GenericResult;
+
+/// source of API keys by provider name (e.g. `virustotal` or `alienvault-otx`)
+pub trait ApiKeyProvider {
+    /// API key of the provider (`None` if it does not have one)
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>>;
+
+    /// API key of the provider, or error if it does not have one
+    fn require(&self, provider: &'static str) -> GenericResult<String> {
+        match self.api_key(provider)? {
+            Some(x) => Ok(x),
+            None => Err(Error::MissingKey { provider }.into()),
+        }
+    }
+
+    /// use `other` for providers which this does not have keys of
+    fn or<P: ApiKeyProvider>(self, other: P) -> Or<Self, P>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+}
+
+/// an explicit key is used for any provider
+impl ApiKeyProvider for str {
+    fn api_key(&self, _provider: &str) -> GenericResult<Option<String>> {
+        Ok(Some(self.to_owned()))
+    }
+}
+
+impl ApiKeyProvider for String {
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>> {
+        self.as_str().api_key(provider)
+    }
+}
+
+impl<P: ApiKeyProvider + ?Sized> ApiKeyProvider for &P {
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>> {
+        (**self).api_key(provider)
+    }
+}
+
+impl<P: ApiKeyProvider + ?Sized> ApiKeyProvider for Box<P> {
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>> {
+        (**self).api_key(provider)
+    }
+}
+
+/// keys of the first provider, then of the second one
+pub struct Or<A, B>(A, B);
+
+impl<A: ApiKeyProvider, B: ApiKeyProvider> ApiKeyProvider for Or<A, B> {
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>> {
+        match self.0.api_key(provider)? {
+            Some(x) => Ok(Some(x)),
+            None => self.1.api_key(provider),
+        }
+    }
+}
+
+/// environment variable of the provider
+///
+/// # Example
+///
+/// ```
+/// use iocutil::keys::env_var;
+///
+/// assert_eq!(env_var("virustotal"), "VTAPIKEY");
+/// assert_eq!(env_var("alienvault-otx"), "OTX_APIKEY");
+/// assert_eq!(env_var("example-intel"), "IOCUTIL_EXAMPLE_INTEL_APIKEY");
+/// ```
+pub fn env_var(provider: &str) -> String {
+    match provider {
+        "virustotal" => "VTAPIKEY".to_owned(),
+        "alienvault-otx" => "OTX_APIKEY".to_owned(),
+        "haveibeenpwned" => "HIBP_APIKEY".to_owned(),
+        "malwarebazaar" => "MALWAREBAZAAR_AUTH_KEY".to_owned(),
+        "msgraph" => "MSGRAPH_TOKEN".to_owned(),
+        "falcon" => "FALCON_TOKEN".to_owned(),
+        x => format!("IOCUTIL_{}_APIKEY", x.to_uppercase().replace('-', "_")),
+    }
+}
+
+/// keys in environment variables (`env_var` of each provider, e.g. `$VTAPIKEY`)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Env;
+
+impl ApiKeyProvider for Env {
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>> {
+        Ok(std::env::var(env_var(provider))
+            .ok()
+            .filter(|x| !x.is_empty()))
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ProviderConfig {
+    api_key: Option<String>,
+}
+
+/// keys in TOML config file (`api_key` in table of each provider)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::keys::{ApiKeyProvider, ConfigFile};
+///
+/// let config = ConfigFile::parse(r#"
+/// [virustotal]
+/// api_key = "vt-key"
+/// "#).unwrap();
+/// assert_eq!(config.api_key("virustotal").unwrap(), Some("vt-key".to_owned()));
+/// assert_eq!(config.api_key("haveibeenpwned").unwrap(), None);
+/// assert!(config.require("haveibeenpwned").is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct ConfigFile {
+    providers: HashMap<String, ProviderConfig>,
+}
+
+impl ConfigFile {
+    /// default path (`$XDG_CONFIG_HOME/iocutil/config.toml`, or `~/.config/iocutil/config.toml`)
+    pub fn default_path() -> Option<PathBuf> {
+        let config = match std::env::var_os("XDG_CONFIG_HOME").filter(|x| !x.is_empty()) {
+            Some(x) => PathBuf::from(x),
+            None => {
+                PathBuf::from(std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?)
+                    .join(".config")
+            }
+        };
+        Some(config.join("iocutil").join("config.toml"))
+    }
+
+    /// parse content of config file
+    pub fn parse(content: impl AsRef<str>) -> GenericResult<Self> {
+        Ok(ConfigFile {
+            providers: toml::from_str(content.as_ref())?,
+        })
+    }
+
+    /// read config file
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        ConfigFile::parse(std::fs::read_to_string(path)?)
+    }
+
+    /// read config file at default path (empty if it does not exist)
+    pub fn open_default() -> GenericResult<Self> {
+        match ConfigFile::default_path() {
+            Some(x) if x.exists() => ConfigFile::open(x),
+            _ => Ok(ConfigFile::default()),
+        }
+    }
+}
+
+impl ApiKeyProvider for ConfigFile {
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>> {
+        Ok(self.providers.get(provider).and_then(|x| x.api_key.clone()))
+    }
+}
+
+/// keys in OS credential store (Keychain on macOS, Credential Manager on Windows and
+/// Secret Service on Linux), stored under the service (`iocutil` by default) by provider name
+///
+/// # Example
+///
+/// ```no_run
+/// use iocutil::keys::Keyring;
+/// use iocutil::prelude::*;
+///
+/// // once on the workstation
+/// Keyring::new().set("virustotal", "your-apikey").unwrap();
+///
+/// let client = VirusTotalClient::from_keys(Keyring::new()).unwrap();
+/// ```
+#[cfg(feature = "keyring")]
+#[derive(Clone, Debug)]
+pub struct Keyring {
+    service: String,
+}
+
+#[cfg(feature = "keyring")]
+impl Default for Keyring {
+    fn default() -> Self {
+        Keyring {
+            service: "iocutil".to_owned(),
+        }
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl Keyring {
+    pub fn new() -> Self {
+        Keyring::default()
+    }
+
+    /// store keys under other service name
+    pub fn with_service(service: impl Into<String>) -> Self {
+        Keyring {
+            service: service.into(),
+        }
+    }
+
+    fn entry(&self, provider: &str) -> GenericResult<keyring::Entry> {
+        Ok(keyring::Entry::new(&self.service, provider)?)
+    }
+
+    /// store API key of the provider (replaces previous one)
+    pub fn set(&self, provider: &str, api_key: &str) -> GenericResult<()> {
+        Ok(self.entry(provider)?.set_password(api_key)?)
+    }
+
+    /// remove API key of the provider (does nothing if it is not stored)
+    pub fn delete(&self, provider: &str) -> GenericResult<()> {
+        match self.entry(provider)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl ApiKeyProvider for Keyring {
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>> {
+        match self.entry(provider)?.get_password() {
+            Ok(x) => Ok(Some(x)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// keys in environment variables, in OS credential store (with `keyring` feature), then in
+/// config file at default path (used by `Default` of API clients)
+///
+/// Unavailable credential store (e.g. no Secret Service on servers) is skipped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultKeys;
+
+impl ApiKeyProvider for DefaultKeys {
+    fn api_key(&self, provider: &str) -> GenericResult<Option<String>> {
+        if let Some(x) = Env.api_key(provider)? {
+            return Ok(Some(x));
+        }
+        #[cfg(feature = "keyring")]
+        {
+            if let Ok(Some(x)) = Keyring::new().api_key(provider) {
+                return Ok(Some(x));
+            }
+        }
+        ConfigFile::open_default()?.api_key(provider)
+    }
+}
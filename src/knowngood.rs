@@ -0,0 +1,295 @@
+//! suppression of known good hashes (NSRL, golden image manifests and trusted verdicts of
+//! VirusTotal) before they reach exports and detections
+//!
+//! `KnownGoodHashes` is an `Allowlist` of hash indicators, so it can be installed as the
+//! export allowlist (`allowlist::set_export_allowlist`) to drop them from every export, or
+//! applied as a stage by `apply`, which marks (tags `known-good`) or drops them.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::allowlist::{Allowlist, Suppression};
+use crate::ioc::{Indicator, Ioc};
+use crate::GenericResult;
+use crate::SampleHash;
+
+/// tag of indicators marked by `KnownGoodHashes`
+pub const KNOWN_GOOD_TAG: &str = "known-good";
+
+/// what to do with known good hashes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KnownGoodAction {
+    /// keep them tagged by `KNOWN_GOOD_TAG`
+    Mark,
+    /// remove them (recorded as suppressions)
+    Drop,
+}
+
+/// fields of a CSV record (quoted fields may contain commas)
+fn fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// hashes known to be good, with their sources
+///
+/// # Example
+///
+/// ```
+/// use iocutil::knowngood::KnownGoodHashes;
+/// use iocutil::SampleHash;
+///
+/// let mut known = KnownGoodHashes::new();
+/// let nsrl = r#""SHA-1","MD5","CRC32","FileName","FileSize","ProductCode","OpSystemCode","SpecialCode"
+/// "0000002D9D62AEBE1E0E9DB6C4C4C7C16A163D2C","1D6EBB5A789ABD108FF578263E1F40F3","FFFFFFFF","setup, part 1.exe","4109","21000","358","""#;
+/// assert_eq!(known.load_nsrl(nsrl.as_bytes()).unwrap(), 2);
+///
+/// // output of sha256sum on the golden image
+/// let manifest = "# build 42\ne3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  /usr/bin/empty\n";
+/// assert_eq!(known.load_manifest("golden-image", manifest).unwrap(), 1);
+///
+/// let md5 = SampleHash::new("1d6ebb5a789abd108ff578263e1f40f3").unwrap();
+/// assert_eq!(known.source_of(&md5), Some("nsrl"));
+/// let sha256 = SampleHash::new("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap();
+/// assert_eq!(known.source_of(&sha256), Some("golden-image"));
+/// assert_eq!(known.len(), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KnownGoodHashes {
+    sources: Vec<String>,
+    hashes: HashMap<String, usize>,
+}
+
+impl KnownGoodHashes {
+    /// no hash is known good
+    pub fn new() -> Self {
+        KnownGoodHashes::default()
+    }
+
+    fn source(&mut self, name: &str) -> usize {
+        match self.sources.iter().position(|x| x == name) {
+            Some(i) => i,
+            None => {
+                self.sources.push(name.to_owned());
+                self.sources.len() - 1
+            }
+        }
+    }
+
+    /// add the hash (earlier sources are kept for the same hash)
+    pub fn add(&mut self, hash: &SampleHash, source: &str) {
+        let source = self.source(source);
+        self.hashes
+            .entry(hash.as_ref().to_lowercase())
+            .or_insert(source);
+    }
+
+    /// add hashes of a NSRL file list in CSV (`NSRLFile.txt` of RDS, columns `SHA-1`, `MD5` and
+    /// `SHA-256` are read by header), as source `nsrl`. returns the number of hashes read.
+    pub fn load_nsrl(&mut self, reader: impl BufRead) -> GenericResult<usize> {
+        let source = self.source("nsrl");
+        let mut lines = reader.lines();
+        let header = match lines.next() {
+            Some(x) => fields(&x?),
+            None => return Ok(0),
+        };
+        let columns: Vec<usize> = header
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| {
+                let x = x.to_lowercase().replace('-', "");
+                x == "sha1" || x == "md5" || x == "sha256"
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut count = 0;
+        for line in lines {
+            let record = fields(&line?);
+            for hash in columns.iter().filter_map(|&i| record.get(i)) {
+                if let Ok(hash) = SampleHash::new(hash) {
+                    self.hashes
+                        .entry(hash.as_ref().to_lowercase())
+                        .or_insert(source);
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// read a NSRL file list
+    pub fn open_nsrl(&mut self, path: impl AsRef<Path>) -> GenericResult<usize> {
+        let file = std::fs::File::open(path)?;
+        self.load_nsrl(std::io::BufReader::new(file))
+    }
+
+    /// add hashes of a manifest (one hash per line followed by optional path, as output of
+    /// `sha256sum`, `#` for comments) as the source. returns the number of hashes read.
+    pub fn load_manifest(&mut self, source: &str, text: impl AsRef<str>) -> GenericResult<usize> {
+        let mut count = 0;
+        for line in text.as_ref().lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let hash = line.split_whitespace().next().unwrap_or_default();
+            self.add(&SampleHash::new(hash)?, source);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// add hashes of a file object of VirusTotal API v3 (`/api/v3/files/{id}`) if its trusted
+    /// verdict is `goodware`, as source `virustotal trusted (<organization>)`. returns whether added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::knowngood::KnownGoodHashes;
+    /// use iocutil::SampleHash;
+    ///
+    /// let mut known = KnownGoodHashes::new();
+    /// let added = known.add_vt_file_object(r#"{"data": {"type": "file", "attributes": {
+    ///     "sha256": "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+    ///     "md5": "d41d8cd98f00b204e9800998ecf8427e",
+    ///     "trusted_verdict": {"verdict": "goodware", "organization": "Microsoft"}
+    /// }}}"#).unwrap();
+    /// assert!(added);
+    /// let md5 = SampleHash::new("d41d8cd98f00b204e9800998ecf8427e").unwrap();
+    /// assert_eq!(known.source_of(&md5), Some("virustotal trusted (Microsoft)"));
+    ///
+    /// assert!(!known.add_vt_file_object(r#"{"data": {"attributes": {"md5": "0cc175b9c0f1b6a831c399e269772661"}}}"#).unwrap());
+    /// ```
+    pub fn add_vt_file_object(&mut self, json: impl AsRef<str>) -> GenericResult<bool> {
+        #[derive(Deserialize)]
+        struct TrustedVerdict {
+            verdict: String,
+            #[serde(default)]
+            organization: Option<String>,
+        }
+        #[derive(Deserialize)]
+        struct Attributes {
+            sha256: Option<String>,
+            sha1: Option<String>,
+            md5: Option<String>,
+            trusted_verdict: Option<TrustedVerdict>,
+        }
+        #[derive(Deserialize)]
+        struct Data {
+            attributes: Attributes,
+        }
+        #[derive(Deserialize)]
+        struct Object {
+            data: Data,
+        }
+
+        let attributes = serde_json::from_str::<Object>(json.as_ref())?
+            .data
+            .attributes;
+        let organization = match &attributes.trusted_verdict {
+            Some(x) if x.verdict == "goodware" => x.organization.clone().unwrap_or_default(),
+            _ => return Ok(false),
+        };
+        let source = format!("virustotal trusted ({})", organization);
+        let hashes = [&attributes.sha256, &attributes.sha1, &attributes.md5];
+        for hash in hashes.iter().filter_map(|x| x.as_ref()) {
+            self.add(&SampleHash::new(hash)?, &source);
+        }
+        Ok(true)
+    }
+
+    /// source of the hash (`None` if not known good)
+    pub fn source_of(&self, hash: &SampleHash) -> Option<&str> {
+        self.hashes
+            .get(&hash.as_ref().to_lowercase())
+            .map(|&i| self.sources[i].as_str())
+    }
+
+    /// the hash is known good?
+    pub fn contains(&self, hash: &SampleHash) -> bool {
+        self.source_of(hash).is_some()
+    }
+
+    /// number of hashes
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// no hash?
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// mark or drop indicators of known good hashes, with suppressed ones (empty for `Mark`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::knowngood::{KnownGoodAction, KnownGoodHashes, KNOWN_GOOD_TAG};
+    /// use iocutil::prelude::*;
+    ///
+    /// let mut known = KnownGoodHashes::new();
+    /// known.load_manifest("golden-image", "d41d8cd98f00b204e9800998ecf8427e  C:\\Windows\\empty.dll").unwrap();
+    /// let indicators = vec![
+    ///     Indicator::new(Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap()),
+    ///     Indicator::new(Ioc::new("0cc175b9c0f1b6a831c399e269772661").unwrap()),
+    /// ];
+    ///
+    /// let (marked, _) = known.apply(indicators.clone(), KnownGoodAction::Mark);
+    /// assert_eq!(marked[0].meta.tags, vec![KNOWN_GOOD_TAG]);
+    /// assert!(marked[1].meta.tags.is_empty());
+    ///
+    /// let (kept, suppressed) = known.apply(indicators, KnownGoodAction::Drop);
+    /// assert_eq!(kept.len(), 1);
+    /// assert_eq!(suppressed[0].list, "golden-image");
+    /// ```
+    pub fn apply(
+        &self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+        action: KnownGoodAction,
+    ) -> (Vec<Indicator>, Vec<Suppression>) {
+        match action {
+            KnownGoodAction::Drop => self.audit(indicators),
+            KnownGoodAction::Mark => {
+                let marked = indicators
+                    .into_iter()
+                    .map(Into::<Indicator>::into)
+                    .map(|mut x| {
+                        let tags = &mut x.meta.tags;
+                        if self.is_allowed(&x.ioc) && !tags.iter().any(|t| t == KNOWN_GOOD_TAG) {
+                            tags.push(KNOWN_GOOD_TAG.to_owned());
+                        }
+                        x
+                    })
+                    .collect();
+                (marked, vec![])
+            }
+        }
+    }
+}
+
+impl Allowlist for KnownGoodHashes {
+    fn matched(&self, ioc: &Ioc) -> Option<&str> {
+        match ioc {
+            Ioc::Hash(x) => self.source_of(x),
+            _ => None,
+        }
+    }
+}
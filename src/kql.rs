@@ -0,0 +1,131 @@
+//! KQL hunting query generation (Microsoft Defender advanced hunting / Sentinel)
+
+use derive_builder::Builder;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::allowlist::suppress;
+use crate::ioc::{Indicator, Ioc};
+use crate::ttl::unexpired;
+use crate::SampleHash;
+
+/// where the queries are run (decides the name of time column)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Workspace {
+    /// `Timestamp`
+    Defender,
+    /// `TimeGenerated`
+    Sentinel,
+}
+
+/// options of generated queries
+#[derive(Builder, Debug, Clone)]
+pub struct KqlOptions {
+    #[builder(default = "Workspace::Defender")]
+    workspace: Workspace,
+    /// look back period in days
+    #[builder(default = "7")]
+    lookback_days: u32,
+    /// max number of literals in a query (long queries are rejected by service)
+    #[builder(default = "500")]
+    chunk_size: usize,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for KqlOptions {
+    fn default() -> Self {
+        KqlOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// table, column and operator which match an indicator
+fn target(ioc: &Ioc) -> (&'static str, &'static str, &'static str) {
+    match ioc {
+        Ioc::Hash(SampleHash::Md5(_)) => ("DeviceFileEvents", "MD5", "in~"),
+        Ioc::Hash(SampleHash::Sha1(_)) => ("DeviceFileEvents", "SHA1", "in~"),
+        Ioc::Hash(SampleHash::Sha256(_)) => ("DeviceFileEvents", "SHA256", "in~"),
+        Ioc::Domain(_) => ("DeviceNetworkEvents", "RemoteUrl", "has_any"),
+        Ioc::Url(_) => ("DeviceNetworkEvents", "RemoteUrl", "has_any"),
+        Ioc::IpAddress(_) => ("DeviceNetworkEvents", "RemoteIP", "in"),
+        Ioc::EmailAddress(_) => ("EmailEvents", "SenderFromAddress", "in~"),
+    }
+}
+
+/// string literal of KQL
+fn literal(text: impl AsRef<str>) -> String {
+    format!(
+        "\"{}\"",
+        text.as_ref().replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// generate hunting queries (a query per table/column and chunk)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::kql::{to_kql, KqlOptionsBuilder, Workspace};
+///
+/// let iocs = vec![
+///     Ioc::new("evil.example.com").unwrap(),
+///     Ioc::new("bad.example.net").unwrap(),
+///     Ioc::new("http://192.0.2.1/gate.php").unwrap(),
+///     Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+/// ];
+///
+/// let options = KqlOptionsBuilder::default()
+///     .workspace(Workspace::Sentinel)
+///     .chunk_size(2)
+///     .build()
+///     .unwrap();
+///
+/// let queries = to_kql(iocs, &options);
+/// assert_eq!(queries.len(), 3);
+/// assert_eq!(
+///     queries[0],
+///     "DeviceFileEvents\n| where TimeGenerated > ago(7d)\n| where MD5 in~ (\"d41d8cd98f00b204e9800998ecf8427e\")"
+/// );
+/// assert!(queries[1].ends_with("| where RemoteUrl has_any (\"evil.example.com\", \"bad.example.net\")"));
+/// assert!(queries[2].ends_with("| where RemoteUrl has_any (\"http://192.0.2.1/gate.php\")"));
+/// ```
+pub fn to_kql(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &KqlOptions,
+) -> Vec<String> {
+    let mut groups: BTreeMap<_, Vec<String>> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for indicator in suppress(unexpired(indicators, options.include_expired)) {
+        let key = target(&indicator.ioc);
+        let value = literal(indicator.ioc.value());
+        if seen.insert((key, value.clone())) {
+            groups.entry(key).or_default().push(value);
+        }
+    }
+
+    let time_column = match options.workspace {
+        Workspace::Defender => "Timestamp",
+        Workspace::Sentinel => "TimeGenerated",
+    };
+
+    groups
+        .into_iter()
+        .flat_map(|((table, column, operator), values)| {
+            values
+                .chunks(options.chunk_size.max(1))
+                .map(|chunk| {
+                    format!(
+                        "{}\n| where {} > ago({}d)\n| where {} {} ({})",
+                        table,
+                        time_column,
+                        options.lookback_days,
+                        column,
+                        operator,
+                        chunk.join(", ")
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
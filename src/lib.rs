@@ -1,23 +1,200 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use crate::contenthash::ContentHash;
+#[cfg(feature = "std")]
 use crate::util::unwrap_try_into;
+#[cfg(feature = "std")]
 use failure::_core::fmt::{Error, Formatter};
+#[cfg(feature = "std")]
 use std::collections::HashSet;
+#[cfg(feature = "std")]
 use std::convert::{TryFrom, TryInto};
+#[cfg(feature = "std")]
 use std::str::FromStr;
 
+#[cfg(feature = "std")]
 type GenericResult<T> = std::result::Result<T, failure::Error>;
 
+#[cfg(feature = "net")]
 pub mod alienvault;
+#[cfg(feature = "std")]
+pub mod allowlist;
+#[cfg(feature = "std")]
+pub mod anonymize;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "std")]
+pub mod banner;
+#[cfg(feature = "std")]
+pub mod breaker;
+#[cfg(feature = "std")]
+pub mod bulk;
+#[cfg(feature = "net")]
+pub mod cache;
+#[cfg(feature = "capi")]
+mod capi;
+#[cfg(feature = "std")]
+pub mod cef;
+#[cfg(feature = "std")]
+pub mod cidrset;
+#[cfg(feature = "std")]
+pub mod cluster;
+#[cfg(feature = "std")]
+pub mod collection;
+pub mod confidence;
+#[cfg(feature = "std")]
 pub mod contenthash;
+#[cfg(feature = "std")]
+pub mod csv;
+#[cfg(feature = "std")]
 pub mod datetime;
+#[cfg(feature = "std")]
+mod der;
+#[cfg(feature = "std")]
+pub mod dga;
+#[cfg(feature = "std")]
+pub mod dns;
+#[cfg(feature = "std")]
+pub mod domain;
+#[cfg(feature = "std")]
+pub mod elastic;
+#[cfg(feature = "elf")]
+pub mod elf;
+#[cfg(feature = "std")]
+pub mod eml;
+#[cfg(feature = "std")]
+pub mod entropy;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "net")]
+pub mod falcon;
+#[cfg(feature = "std")]
+pub mod favicon;
+#[cfg(feature = "std")]
+pub mod feed;
+#[cfg(feature = "std")]
+pub mod fingerprint;
+#[cfg(feature = "std")]
+pub mod geoip;
+#[cfg(feature = "std")]
+pub mod graph;
+#[cfg(feature = "std")]
 pub mod hasher;
+#[cfg(feature = "std")]
+pub mod hashformat;
+#[cfg(feature = "std")]
 pub mod hashstr;
+#[cfg(feature = "net")]
+pub mod hibp;
+#[cfg(feature = "net")]
+pub mod http;
+#[cfg(feature = "std")]
+pub mod ioc;
+pub mod ipscope;
+#[cfg(feature = "std")]
+pub mod ja3;
+#[cfg(feature = "std")]
+pub mod jsonl;
+#[cfg(feature = "net")]
+pub mod keys;
+#[cfg(feature = "std")]
+pub mod knowngood;
+#[cfg(feature = "std")]
+pub mod kql;
+#[cfg(feature = "net")]
+pub mod malwarebazaar;
+#[cfg(feature = "net")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod misp;
+#[cfg(feature = "net")]
+pub mod msgraph;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+#[cfg(all(feature = "net", not(feature = "async")))]
+#[allow(dead_code)]
+mod nonblocking;
+#[cfg(feature = "std")]
+pub mod openioc;
+#[cfg(feature = "net")]
+pub mod paginate;
+#[cfg(all(feature = "parquet", feature = "std"))]
+pub mod parquet;
+#[cfg(feature = "pe")]
+pub mod pe;
+#[cfg(feature = "std")]
 pub mod prelude;
+#[cfg(feature = "std")]
+pub mod provider;
+#[cfg(feature = "std")]
+pub mod psl;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "net")]
+pub mod ratelimit;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub mod resolve;
+#[cfg(feature = "net")]
+pub mod retry;
+#[cfg(feature = "std")]
+pub mod scan;
+#[cfg(feature = "std")]
 pub mod scraper;
+#[cfg(feature = "std")]
+pub mod sigma;
+#[cfg(feature = "net")]
+pub mod singleflight;
+#[cfg(feature = "std")]
+pub mod spl;
+#[cfg(feature = "std")]
+pub mod ssdeep;
+#[cfg(feature = "std")]
+pub mod stix;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod suricata;
+#[cfg(feature = "net")]
+pub mod taxii;
+#[cfg(feature = "std")]
+pub mod tlp;
+#[cfg(feature = "std")]
+pub mod tls;
+#[cfg(feature = "std")]
+pub mod tlsh;
+#[cfg(feature = "std")]
+pub mod ttl;
+#[cfg(feature = "std")]
+pub mod typosquat;
+#[cfg(feature = "std")]
+pub mod unshorten;
+#[cfg(feature = "std")]
+pub mod urlnorm;
+#[cfg(feature = "std")]
 mod util;
+pub mod validate;
+#[cfg(feature = "net")]
+pub mod vcr;
+#[cfg(feature = "net")]
 pub mod virusbay;
+#[cfg(feature = "net")]
 pub mod virustotal;
+#[cfg(feature = "std")]
+pub mod whois;
+#[cfg(feature = "std")]
+pub mod x509;
+#[cfg(feature = "std")]
+pub mod yara;
+#[cfg(feature = "std")]
+pub mod zeek;
 
 /// manage a hash value (sha256/sha1/md5)
+#[cfg(feature = "std")]
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum SampleHash {
     Sha1(String),
@@ -25,12 +202,14 @@ pub enum SampleHash {
     Md5(String),
 }
 
+#[cfg(feature = "std")]
 impl std::fmt::Display for SampleHash {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         write!(f, "{}", self.as_ref())
     }
 }
 
+#[cfg(feature = "std")]
 impl Into<String> for SampleHash {
     fn into(self) -> String {
         match self {
@@ -41,6 +220,7 @@ impl Into<String> for SampleHash {
     }
 }
 
+#[cfg(feature = "std")]
 impl AsRef<str> for SampleHash {
     fn as_ref(&self) -> &str {
         match self {
@@ -52,16 +232,18 @@ impl AsRef<str> for SampleHash {
 }
 
 /// conversion
-fn to_sample(value: impl AsRef<str>) -> Result<SampleHash, std::io::Error> {
+#[cfg(feature = "std")]
+fn to_sample(value: impl AsRef<str>) -> Result<SampleHash, error::Error> {
     let s = value.as_ref().to_lowercase();
     match hashstr::detect(&s) {
         hashstr::HashType::MD5 => Ok(SampleHash::Md5(s)),
         hashstr::HashType::SHA1 => Ok(SampleHash::Sha1(s)),
         hashstr::HashType::SHA256 => Ok(SampleHash::Sha256(s)),
-        _ => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+        _ => Err(error::Error::parse("hash", value.as_ref())),
     }
 }
 
+#[cfg(feature = "std")]
 impl TryFrom<&str> for SampleHash {
     type Error = failure::Error;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
@@ -69,6 +251,7 @@ impl TryFrom<&str> for SampleHash {
     }
 }
 
+#[cfg(feature = "std")]
 impl TryFrom<&&str> for SampleHash {
     type Error = failure::Error;
     fn try_from(value: &&str) -> Result<Self, Self::Error> {
@@ -76,6 +259,7 @@ impl TryFrom<&&str> for SampleHash {
     }
 }
 
+#[cfg(feature = "std")]
 impl TryFrom<String> for SampleHash {
     type Error = failure::Error;
     fn try_from(value: String) -> Result<Self, Self::Error> {
@@ -83,6 +267,7 @@ impl TryFrom<String> for SampleHash {
     }
 }
 
+#[cfg(feature = "std")]
 impl TryFrom<&String> for SampleHash {
     type Error = failure::Error;
     fn try_from(value: &String) -> Result<Self, Self::Error> {
@@ -90,6 +275,7 @@ impl TryFrom<&String> for SampleHash {
     }
 }
 
+#[cfg(feature = "std")]
 impl FromStr for SampleHash {
     type Err = failure::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -97,12 +283,22 @@ impl FromStr for SampleHash {
     }
 }
 
+#[cfg(feature = "std")]
 impl SampleHash {
     /// new SampleHash from md5/sha1/sha256 string
     pub fn new(hash: impl AsRef<str>) -> GenericResult<Self> {
         hash.as_ref().to_lowercase().parse()
     }
 
+    /// name of hash algorithm (`md5`, `sha1` or `sha256`)
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            SampleHash::Md5(_) => "md5",
+            SampleHash::Sha1(_) => "sha1",
+            SampleHash::Sha256(_) => "sha256",
+        }
+    }
+
     /// map strings to SampleHash
     /// `try_map` is a better way if you want to map to Result (like Result<Vec<_>, _>).
     ///
@@ -226,12 +422,24 @@ impl SampleHash {
     /// assert_eq!(hashes.len(), 3);
     /// ```
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            name = "scrape_hashes",
+            skip_all,
+            fields(bytes = text.as_ref().len(), found = tracing::field::Empty)
+        )
+    )]
     pub fn find<T>(text: impl AsRef<str>) -> T
     where
         T: std::iter::FromIterator<SampleHash>,
     {
         let v: GenericResult<HashSet<_>> = SampleHash::map(hashstr::find(&text));
-        v.unwrap().into_iter().collect()
+        let v = v.unwrap();
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("found", v.len());
+        v.into_iter().collect()
     }
 
     /// scrape hashes from specified url
@@ -246,12 +454,47 @@ impl SampleHash {
     /// assert!(hashes.contains(&sample!("7f335f990851510ab9654e9fc1add2acec2c38a64563b711031769c58ecd45c0")));
     /// assert!(hashes.contains(&sample!("5a7042e698ce8e5cf6c4615e41a4205a52d9bb18a6ff214a967724c866cb72b4")));
     /// ```
+    #[cfg(feature = "net")]
     pub fn scrape<T>(url: impl AsRef<str>) -> GenericResult<T>
     where
         T: std::iter::FromIterator<SampleHash>,
     {
         Ok(SampleHash::find(scraper::get_article(url)?))
     }
+
+    /// hashes (sha256 / sha1 / md5) of file content (calculated in a pass)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let c = SampleHash::of_file("./Cargo.toml").unwrap();
+    /// assert_eq!(c, ContentHash::of_bytes(std::fs::read("./Cargo.toml").unwrap()));
+    /// ```
+    pub fn of_file(path: impl AsRef<std::path::Path>) -> GenericResult<ContentHash> {
+        ContentHash::of_file(path)
+    }
+
+    /// hashes (sha256 / sha1 / md5) of all content in reader (calculated in a pass)
+    pub fn of_reader(reader: impl std::io::Read) -> GenericResult<ContentHash> {
+        ContentHash::of_reader(reader)
+    }
+
+    /// hashes (sha256 / sha1 / md5) of bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    ///
+    /// let c = SampleHash::of_bytes(b"");
+    /// assert_eq!(c.md5, sample!("d41d8cd98f00b204e9800998ecf8427e"));
+    /// assert_eq!(c.sha1, sample!("da39a3ee5e6b4b0d3255bfef95601890afd80709"));
+    /// ```
+    pub fn of_bytes(bytes: impl AsRef<[u8]>) -> ContentHash {
+        ContentHash::of_bytes(bytes)
+    }
 }
 
 /// # Example
@@ -277,6 +520,7 @@ impl SampleHash {
 /// // panic if hash type does not match
 /// let s3 = sample!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855" => md5); // panic
 /// ```
+#[cfg(feature = "std")]
 #[macro_export]
 macro_rules! sample {
     ($hash:literal) => {
@@ -294,36 +538,39 @@ macro_rules! sample {
 }
 
 /// get sample if it is md5
+#[cfg(feature = "std")]
 pub fn sample_md5(hash: impl TryInto<SampleHash>) -> GenericResult<SampleHash> {
     let hash = unwrap_try_into(hash)?;
     if let SampleHash::Md5(_) = hash {
         Ok(hash)
     } else {
-        Err(std::io::Error::from(std::io::ErrorKind::InvalidInput).into())
+        Err(error::Error::parse("md5", hash.as_ref()).into())
     }
 }
 
 /// get sample if it is sha1
+#[cfg(feature = "std")]
 pub fn sample_sha1(hash: impl TryInto<SampleHash>) -> GenericResult<SampleHash> {
     let hash = unwrap_try_into(hash)?;
     if let SampleHash::Sha1(_) = hash {
         Ok(hash)
     } else {
-        Err(std::io::Error::from(std::io::ErrorKind::InvalidInput).into())
+        Err(error::Error::parse("sha1", hash.as_ref()).into())
     }
 }
 
 /// get sample if it is sha256
+#[cfg(feature = "std")]
 pub fn sample_sha256(hash: impl TryInto<SampleHash>) -> GenericResult<SampleHash> {
     let hash = unwrap_try_into(hash)?;
     if let SampleHash::Sha256(_) = hash {
         Ok(hash)
     } else {
-        Err(std::io::Error::from(std::io::ErrorKind::InvalidInput).into())
+        Err(error::Error::parse("sha256", hash.as_ref()).into())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     #[test]
@@ -0,0 +1,122 @@
+//! MalwareBazaar client (default use `$MALWAREBAZAAR_AUTH_KEY` environment variable as auth key)
+
+use failure::Fail;
+use serde::Deserialize;
+use std::convert::TryInto;
+
+use crate::contenthash::{ContentHash, SampleHashes};
+use crate::http::{block_on, ClientConfig};
+use crate::keys::ApiKeyProvider;
+use crate::nonblocking;
+use crate::resolve::HashResolver;
+use crate::{GenericResult, SampleHash};
+
+pub(crate) const API_URL: &str = "https://mb-api.abuse.ch/api/v1/";
+
+/// Errors in MalwareBazaar operation
+#[derive(Fail, Debug)]
+pub enum MalwareBazaarError {
+    #[fail(display = "query failed: {}", _0)]
+    QueryFailed(String),
+}
+
+/// client for MalwareBazaar API
+#[derive(Default)]
+pub struct MalwareBazaarClient {
+    inner: nonblocking::MalwareBazaarClient,
+}
+
+/// information of a sample (fields commonly used)
+#[derive(Deserialize, Debug, Clone)]
+pub struct SampleInfo {
+    pub sha256_hash: String,
+    pub sha1_hash: String,
+    pub md5_hash: String,
+    pub file_size: Option<u64>,
+    pub file_type: Option<String>,
+    pub signature: Option<String>,
+    pub first_seen: Option<String>,
+    pub imphash: Option<String>,
+    pub tlsh: Option<String>,
+    pub telfhash: Option<String>,
+    pub ssdeep: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Response<T> {
+    pub(crate) query_status: String,
+    pub(crate) data: Option<T>,
+}
+
+impl MalwareBazaarClient {
+    /// new client with auth key
+    pub fn new(auth_key: impl AsRef<str>) -> Self {
+        MalwareBazaarClient {
+            inner: nonblocking::MalwareBazaarClient::new(auth_key),
+        }
+    }
+
+    /// new client with auth key of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(MalwareBazaarClient {
+            inner: nonblocking::MalwareBazaarClient::from_keys(keys)?,
+        })
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.inner = self.inner.with_config(config);
+        self
+    }
+
+    /// send query (`None` if nothing is found)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::fingerprint::Fingerprint;
+    /// use iocutil::malwarebazaar::{MalwareBazaarClient, SampleInfo};
+    ///
+    /// let client = MalwareBazaarClient::default();
+    /// let f: Fingerprint = "imphash:f34d5f2d4577ed6d9ceec516c1f5a744".parse().unwrap();
+    /// let samples: Option<Vec<SampleInfo>> = client.query(&f.bazaar_query().unwrap()).unwrap();
+    /// ```
+    pub fn query<T>(&self, params: &[(&str, &str)]) -> GenericResult<Option<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        block_on(self.inner.query(params))
+    }
+
+    /// information of a sample (`None` if not found)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::malwarebazaar::MalwareBazaarClient;
+    ///
+    /// let client = MalwareBazaarClient::default();
+    /// let info = client.get_info("094fd325049b8a9cf6d3e5ef2a6d4cc6a567d7d49c35f8bb8dd9e3c6acf3d78d").unwrap();
+    /// ```
+    pub fn get_info(&self, hash: impl TryInto<SampleHash>) -> GenericResult<Option<SampleInfo>> {
+        block_on(self.inner.get_info(hash))
+    }
+}
+
+impl HashResolver for MalwareBazaarClient {
+    fn resolve(&self, hash: &SampleHash) -> GenericResult<Option<SampleHashes>> {
+        let info = match self.get_info(hash.clone())? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let mut hashes = SampleHashes::from(ContentHash::new(
+            info.sha256_hash,
+            info.sha1_hash,
+            info.md5_hash,
+        )?);
+        hashes.size = info.file_size;
+        hashes.tlsh = info.tlsh.and_then(|x| x.parse().ok());
+        Ok(Some(hashes))
+    }
+}
@@ -0,0 +1,143 @@
+//! hooks counting requests, errors, rate limit waits and cache hits of API clients
+//!
+//! Implement `Metrics` to export the counts (e.g. to Prometheus) and install it by `set_metrics`.
+//! All API clients and the response cache report to the installed one.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// receiver of counts (every method does nothing by default)
+pub trait Metrics: Send + Sync {
+    /// a request is sent to the provider (retries are counted as well)
+    fn request(&self, _provider: &str) {}
+
+    /// a request to the provider failed (`error` is classified, e.g. `error.is_quota()`)
+    fn error(&self, _provider: &str, _error: &Error) {}
+
+    /// waited for rate limit (of `RateLimiter` or requested by the provider) before a request
+    fn rate_limit_wait(&self, _provider: &str, _waited: Duration) {}
+
+    /// a response is served from the cache
+    fn cache_hit(&self, _provider: &str, _endpoint: &str) {}
+
+    /// a response is not in the cache (and fetched from the provider)
+    fn cache_miss(&self, _provider: &str, _endpoint: &str) {}
+}
+
+lazy_static! {
+    static ref METRICS: RwLock<Option<Arc<dyn Metrics>>> = RwLock::new(None);
+}
+
+/// install metrics which all API clients report to (replaces previous one)
+pub fn set_metrics(metrics: impl Metrics + 'static) {
+    *METRICS.write().unwrap() = Some(Arc::new(metrics));
+}
+
+/// stop reporting to installed metrics
+pub fn clear_metrics() {
+    *METRICS.write().unwrap() = None;
+}
+
+/// report to installed metrics (if any)
+pub(crate) fn report(f: impl FnOnce(&dyn Metrics)) {
+    let metrics = METRICS.read().unwrap().clone();
+    if let Some(m) = metrics {
+        f(m.as_ref());
+    }
+}
+
+/// counts of a provider
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Counts {
+    pub requests: u64,
+    pub errors: u64,
+    /// errors of exhausted quota (included in `errors`)
+    pub quota_errors: u64,
+    pub rate_limit_waits: u64,
+    pub rate_limit_waited: Duration,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+/// in-memory counters by provider (clones share the counts)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::metrics::{set_metrics, Counters, Metrics};
+///
+/// let counters = Counters::new();
+/// set_metrics(counters.clone());
+///
+/// // API clients report like this
+/// counters.request("virustotal");
+/// counters.cache_hit("virustotal", "file/report");
+///
+/// let vt = counters.of("virustotal");
+/// assert_eq!((vt.requests, vt.cache_hits, vt.errors), (1, 1, 0));
+/// assert_eq!(counters.of("virusbay").requests, 0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Counters {
+    counts: Arc<Mutex<HashMap<String, Counts>>>,
+}
+
+impl Counters {
+    pub fn new() -> Self {
+        Counters::default()
+    }
+
+    /// counts of the provider
+    pub fn of(&self, provider: impl AsRef<str>) -> Counts {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(provider.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// counts of all providers
+    pub fn snapshot(&self) -> HashMap<String, Counts> {
+        self.counts.lock().unwrap().clone()
+    }
+
+    fn update(&self, provider: &str, f: impl FnOnce(&mut Counts)) {
+        let mut counts = self.counts.lock().unwrap();
+        f(counts.entry(provider.to_owned()).or_default());
+    }
+}
+
+impl Metrics for Counters {
+    fn request(&self, provider: &str) {
+        self.update(provider, |c| c.requests += 1);
+    }
+
+    fn error(&self, provider: &str, error: &Error) {
+        self.update(provider, |c| {
+            c.errors += 1;
+            if error.is_quota() {
+                c.quota_errors += 1;
+            }
+        });
+    }
+
+    fn rate_limit_wait(&self, provider: &str, waited: Duration) {
+        self.update(provider, |c| {
+            c.rate_limit_waits += 1;
+            c.rate_limit_waited += waited;
+        });
+    }
+
+    fn cache_hit(&self, provider: &str, _endpoint: &str) {
+        self.update(provider, |c| c.cache_hits += 1);
+    }
+
+    fn cache_miss(&self, provider: &str, _endpoint: &str) {
+        self.update(provider, |c| c.cache_misses += 1);
+    }
+}
@@ -0,0 +1,273 @@
+//! MISP event utilities
+
+use chrono::{DateTime, Utc};
+use derive_builder::Builder;
+use serde_json::json;
+use std::collections::HashSet;
+
+use crate::allowlist::suppress;
+use crate::ioc::{Indicator, Ioc, Metadata};
+use crate::tlp::{Audience, Tlp};
+use crate::ttl::unexpired;
+use crate::util::deterministic_uuid;
+use crate::SampleHash;
+
+/// distribution level of MISP event
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Distribution {
+    YourOrganisationOnly = 0,
+    ThisCommunityOnly = 1,
+    ConnectedCommunities = 2,
+    AllCommunities = 3,
+}
+
+impl Distribution {
+    /// audience of the distribution (indicators whose TLP does not allow it are not exported)
+    pub fn audience(self) -> Audience {
+        match self {
+            Distribution::YourOrganisationOnly => Audience::Organization,
+            Distribution::ThisCommunityOnly | Distribution::ConnectedCommunities => {
+                Audience::Community
+            }
+            Distribution::AllCommunities => Audience::Public,
+        }
+    }
+}
+
+/// threat level of MISP event
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ThreatLevel {
+    High = 1,
+    Medium = 2,
+    Low = 3,
+    Undefined = 4,
+}
+
+/// analysis status of MISP event
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Analysis {
+    Initial = 0,
+    Ongoing = 1,
+    Completed = 2,
+}
+
+/// distribution of attributes which inherits the event's one
+const INHERIT_EVENT: u8 = 5;
+
+/// options of MISP event export
+#[derive(Builder, Debug, Clone)]
+pub struct MispEventOptions {
+    /// title of the event
+    #[builder(setter(into), default = "\"exported by iocutil.rs\".to_owned()")]
+    info: String,
+    #[builder(default = "Distribution::YourOrganisationOnly")]
+    distribution: Distribution,
+    #[builder(default = "ThreatLevel::Undefined")]
+    threat_level: ThreatLevel,
+    #[builder(default = "Analysis::Initial")]
+    analysis: Analysis,
+    /// tlp tag added to the event (raised to the strictest TLP of indicators)
+    #[builder(default = "None")]
+    tlp: Option<Tlp>,
+    /// tags added to the event
+    #[builder(default = "vec![]")]
+    tags: Vec<String>,
+    /// set `to_ids` flag of attributes
+    #[builder(default = "true")]
+    to_ids: bool,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for MispEventOptions {
+    fn default() -> Self {
+        MispEventOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// attribute type and category of an indicator
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::misp::attribute_type;
+///
+/// assert_eq!(attribute_type(&Ioc::new("example.com").unwrap()), ("domain", "Network activity"));
+/// assert_eq!(attribute_type(&Ioc::new("1.1.1.1").unwrap()), ("ip-dst", "Network activity"));
+/// ```
+pub fn attribute_type(ioc: &Ioc) -> (&'static str, &'static str) {
+    match ioc {
+        Ioc::Hash(SampleHash::Md5(_)) => ("md5", "Payload delivery"),
+        Ioc::Hash(SampleHash::Sha1(_)) => ("sha1", "Payload delivery"),
+        Ioc::Hash(SampleHash::Sha256(_)) => ("sha256", "Payload delivery"),
+        Ioc::Domain(_) => ("domain", "Network activity"),
+        Ioc::Url(_) => ("url", "Network activity"),
+        Ioc::IpAddress(_) => ("ip-dst", "Network activity"),
+        Ioc::EmailAddress(_) => ("email-src", "Payload delivery"),
+    }
+}
+
+fn tags(names: impl IntoIterator<Item = impl AsRef<str>>) -> serde_json::Value {
+    names
+        .into_iter()
+        .map(|x| json!({ "name": x.as_ref() }))
+        .collect()
+}
+
+/// free-form tags, `kill-chain` taxonomy and galaxy tags of attributions
+fn tag_names(meta: &Metadata) -> Vec<String> {
+    let mut names = meta.tags.clone();
+    names.extend(meta.tlp.map(Tlp::misp_tag));
+    names.extend(
+        meta.kill_chain_phases
+            .iter()
+            .map(|p| format!("kill-chain:{}", p.misp_name())),
+    );
+    if let Some(f) = &meta.malware_family {
+        names.push(format!("misp-galaxy:malpedia=\"{}\"", f));
+    }
+    if let Some(a) = &meta.threat_actor {
+        names.push(format!("misp-galaxy:threat-actor=\"{}\"", a));
+    }
+    names
+}
+
+/// convert an indicator into MISP attribute
+fn to_misp_attribute(
+    event_uuid: &str,
+    indicator: &Indicator,
+    options: &MispEventOptions,
+    now: DateTime<Utc>,
+) -> serde_json::Value {
+    let meta = &indicator.meta;
+    let (t, category) = attribute_type(&indicator.ioc);
+    let value = indicator.ioc.value();
+
+    let mut v = json!({
+        "uuid": deterministic_uuid(format!("{}:{}:{}", event_uuid, t, value)).to_string(),
+        "type": t,
+        "category": category,
+        "value": value,
+        "to_ids": options.to_ids,
+        "distribution": INHERIT_EVENT.to_string(),
+        "timestamp": now.timestamp().to_string(),
+        "comment": meta.description.clone().unwrap_or_default(),
+        "disable_correlation": false,
+    });
+
+    if let Some(d) = meta.first_seen {
+        v["first_seen"] = json!(d.to_rfc3339());
+    }
+    if let Some(d) = meta.last_seen {
+        v["last_seen"] = json!(d.to_rfc3339());
+    }
+    let names = tag_names(meta);
+    if !names.is_empty() {
+        v["Tag"] = tags(names);
+    }
+
+    v
+}
+
+/// export indicators as standalone MISP event (importable with "Import from... MISP JSON")
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::misp::{to_misp_event, Distribution, MispEventOptions, MispEventOptionsBuilder};
+/// use iocutil::tlp::Tlp;
+///
+/// let iocs = vec![
+///     Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+///     Ioc::new("http://evil.example.com/payload.exe").unwrap(),
+/// ];
+///
+/// let options = MispEventOptionsBuilder::default()
+///     .info("example campaign")
+///     .distribution(Distribution::ThisCommunityOnly)
+///     .tlp(Some(Tlp::Green))
+///     .build()
+///     .unwrap();
+///
+/// let event = to_misp_event(iocs, &options);
+/// let event = &event["Event"];
+/// assert_eq!(event["info"], "example campaign");
+/// assert_eq!(event["distribution"], "1");
+/// assert_eq!(event["Tag"][0]["name"], "tlp:green");
+/// assert_eq!(event["Attribute"][0]["type"], "md5");
+/// assert_eq!(event["Attribute"][1]["category"], "Network activity");
+///
+/// // kill chain phases and attributions are tags in taxonomy / galaxy
+/// let mut i = Indicator::new(Ioc::new("evil.example.com").unwrap());
+/// i.meta.kill_chain_phases = vec![KillChainPhase::CommandAndControl];
+/// i.meta.threat_actor = Some("APT28".to_owned());
+/// let event = to_misp_event(vec![i], &options);
+/// let tags = &event["Event"]["Attribute"][0]["Tag"];
+/// assert_eq!(tags[0]["name"], "kill-chain:Command and Control");
+/// assert_eq!(tags[1]["name"], "misp-galaxy:threat-actor=\"APT28\"");
+///
+/// // the event (of own organization) is marked by the strictest TLP, and RED indicators are
+/// // not exported
+/// let mut amber = Indicator::new(Ioc::new("evil.example.com").unwrap());
+/// amber.meta.tlp = Some(Tlp::Amber);
+/// let mut red = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// red.meta.tlp = Some(Tlp::Red);
+/// let event = to_misp_event(vec![amber, red], &MispEventOptions::default());
+/// assert_eq!(event["Event"]["Tag"][0]["name"], "tlp:amber");
+/// assert_eq!(event["Event"]["Attribute"].as_array().unwrap().len(), 1);
+/// assert_eq!(event["Event"]["Attribute"][0]["Tag"][0]["name"], "tlp:amber");
+/// ```
+pub fn to_misp_event(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &MispEventOptions,
+) -> serde_json::Value {
+    let now = Utc::now();
+    let mut seen = HashSet::new();
+    let audience = options.distribution.audience();
+    let indicators: Vec<Indicator> = suppress(unexpired(indicators, options.include_expired))
+        .into_iter()
+        .filter(|x| x.meta.tlp.is_none_or(|t| t.allows(audience)))
+        .filter(|x| seen.insert(x.ioc.clone()))
+        .collect();
+
+    let seed = indicators.iter().fold(options.info.clone(), |acc, x| {
+        format!("{}\n{}", acc, x.ioc.value())
+    });
+    let uuid = deterministic_uuid(seed).to_string();
+
+    let attributes: Vec<_> = indicators
+        .iter()
+        .map(|x| to_misp_attribute(&uuid, x, options, now))
+        .collect();
+
+    let first_seen = indicators.iter().filter_map(|x| x.meta.first_seen).min();
+
+    let tlp = Tlp::strictest(
+        indicators
+            .iter()
+            .map(|x| x.meta.tlp)
+            .chain(std::iter::once(options.tlp)),
+    );
+    let mut event_tags: Vec<String> = tlp.map(Tlp::misp_tag).into_iter().collect();
+    event_tags.extend(options.tags.iter().cloned());
+
+    let mut v = json!({
+        "uuid": uuid,
+        "info": options.info,
+        "date": first_seen.unwrap_or(now).format("%Y-%m-%d").to_string(),
+        "timestamp": now.timestamp().to_string(),
+        "published": false,
+        "distribution": (options.distribution as u8).to_string(),
+        "threat_level_id": (options.threat_level as u8).to_string(),
+        "analysis": (options.analysis as u8).to_string(),
+        "Attribute": attributes,
+    });
+    if !event_tags.is_empty() {
+        v["Tag"] = tags(event_tags);
+    }
+
+    json!({ "Event": v })
+}
@@ -0,0 +1,287 @@
+//! Microsoft Graph threat intelligence indicator client (Microsoft Sentinel / Defender)
+
+use chrono::{DateTime, Utc};
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::net::IpAddr;
+use time::Duration;
+
+use crate::http::{block_on, ClientConfig};
+use crate::ioc::{Indicator, Ioc};
+use crate::keys::ApiKeyProvider;
+use crate::nonblocking;
+use crate::tlp::{Audience, Tlp};
+use crate::GenericResult;
+
+/// max number of indicators in a submitTiIndicators request
+pub const MAX_BATCH_SIZE: usize = 100;
+
+/// client for Microsoft Graph tiIndicators API (default use `$MSGRAPH_TOKEN` environment variable as access token)
+#[derive(Default)]
+pub struct MicrosoftGraphClient {
+    inner: nonblocking::MicrosoftGraphClient,
+}
+
+/// action applied when the indicator matched
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Alert,
+    Block,
+    Allow,
+}
+
+/// product which receives the indicator
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TargetProduct {
+    #[serde(rename = "Azure Sentinel")]
+    Sentinel,
+    #[serde(rename = "Microsoft Defender ATP")]
+    DefenderATP,
+}
+
+/// threat type of the indicator
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ThreatType {
+    Botnet,
+    C2,
+    CryptoMining,
+    Darknet,
+    DDoS,
+    MaliciousUrl,
+    Malware,
+    Phishing,
+    Proxy,
+    PUA,
+    WatchList,
+}
+
+/// TLP level in Microsoft Graph (ordered from the least strict)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum TlpLevel {
+    Unknown,
+    White,
+    Green,
+    Amber,
+    Red,
+}
+
+/// TLP 2.0 in Microsoft Graph (which lacks AMBER+STRICT, sent as RED not to loosen it)
+impl From<Tlp> for TlpLevel {
+    fn from(tlp: Tlp) -> Self {
+        match tlp {
+            Tlp::Clear => TlpLevel::White,
+            Tlp::Green => TlpLevel::Green,
+            Tlp::Amber => TlpLevel::Amber,
+            Tlp::AmberStrict | Tlp::Red => TlpLevel::Red,
+        }
+    }
+}
+
+/// options of submitted indicators
+#[derive(Builder, Debug, Clone)]
+pub struct TiIndicatorOptions {
+    #[builder(default = "Action::Alert")]
+    action: Action,
+    #[builder(default = "TargetProduct::Sentinel")]
+    target_product: TargetProduct,
+    #[builder(default = "ThreatType::WatchList")]
+    threat_type: ThreatType,
+    /// TLP of indicators (`iocutil::tlp::Tlp` is converted), raised to TLP of each indicator
+    #[builder(setter(into), default = "TlpLevel::Amber")]
+    tlp_level: TlpLevel,
+    /// indicators expire after this duration from submission (unless `valid_until` is set)
+    #[builder(default = "Duration::days(30)")]
+    expiration: Duration,
+    #[builder(setter(into), default = "\"submitted by iocutil.rs\".to_owned()")]
+    description: String,
+    /// audience of the tenant (submission fails if TLP of any indicator does not allow it)
+    #[builder(default = "Audience::Organization")]
+    pub(crate) audience: Audience,
+    /// upload expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    pub(crate) include_expired: bool,
+}
+
+impl Default for TiIndicatorOptions {
+    fn default() -> Self {
+        TiIndicatorOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// convert an indicator into tiIndicator object
+///
+/// `valid_until` and TLP of the indicator take precedence over `expiration` and `tlp_level` of
+/// options (TLP is never loosened).
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::msgraph::{to_ti_indicator, Action, TiIndicatorOptionsBuilder};
+/// use iocutil::tlp::Tlp;
+///
+/// let options = TiIndicatorOptionsBuilder::default()
+///     .action(Action::Block)
+///     .expiration(Duration::days(7))
+///     .build()
+///     .unwrap();
+///
+/// let now = day!(2019, 11, 1).unwrap().into();
+/// let v = to_ti_indicator(Ioc::new("example.com").unwrap(), &options, now);
+/// assert_eq!(v["domainName"], "example.com");
+/// assert_eq!(v["action"], "block");
+/// assert_eq!(v["targetProduct"], "Azure Sentinel");
+/// assert_eq!(v["tlpLevel"], "amber");
+/// assert_eq!(v["expirationDateTime"], "2019-11-08T00:00:00Z");
+///
+/// let v = to_ti_indicator(Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(), &options, now);
+/// assert_eq!(v["fileHashType"], "md5");
+///
+/// let mut i = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// i.meta.valid_until = Some(day!(2019, 11, 3).unwrap().into());
+/// i.meta.tlp = Some(Tlp::Red);
+/// let v = to_ti_indicator(i, &options, now);
+/// assert_eq!(v["expirationDateTime"], "2019-11-03T00:00:00Z");
+/// assert_eq!(v["tlpLevel"], "red");
+/// ```
+pub fn to_ti_indicator(
+    indicator: impl Into<Indicator>,
+    options: &TiIndicatorOptions,
+    now: DateTime<Utc>,
+) -> serde_json::Value {
+    let Indicator { ioc, meta } = indicator.into();
+    let tlp_level = meta
+        .tlp
+        .map(TlpLevel::from)
+        .map_or(options.tlp_level, |x| x.max(options.tlp_level));
+    let expiration = meta.valid_until.unwrap_or(now + options.expiration);
+    let mut v = json!({
+        "action": options.action,
+        "targetProduct": options.target_product,
+        "threatType": options.threat_type,
+        "tlpLevel": tlp_level,
+        "description": options.description,
+        "expirationDateTime": expiration.format("%FT%TZ").to_string(),
+    });
+
+    let (field, value) = match ioc {
+        Ioc::Hash(h) => {
+            v["fileHashType"] = json!(h.algorithm());
+            ("fileHashValue", h.to_string())
+        }
+        Ioc::Domain(d) => ("domainName", d),
+        Ioc::Url(u) => ("url", u),
+        Ioc::IpAddress(IpAddr::V4(ip)) => ("networkIPv4", ip.to_string()),
+        Ioc::IpAddress(IpAddr::V6(ip)) => ("networkIPv6", ip.to_string()),
+        Ioc::EmailAddress(e) => ("emailSenderAddress", e),
+    };
+    v[field] = json!(value);
+
+    v
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TokenResponse {
+    pub(crate) access_token: String,
+}
+
+impl MicrosoftGraphClient {
+    /// new client with access token
+    pub fn new(token: impl AsRef<str>) -> Self {
+        MicrosoftGraphClient {
+            inner: nonblocking::MicrosoftGraphClient::new(token),
+        }
+    }
+
+    /// new client with access token of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(MicrosoftGraphClient {
+            inner: nonblocking::MicrosoftGraphClient::from_keys(keys)?,
+        })
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.inner = self.inner.with_config(config);
+        self
+    }
+
+    /// new client with access token acquired by client credentials of an app registration
+    /// (the app requires `ThreatIndicators.ReadWrite.OwnedBy` permission)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::msgraph::MicrosoftGraphClient;
+    ///
+    /// let client = MicrosoftGraphClient::from_client_credentials("tenant id", "client id", "secret")
+    ///     .expect("failed to authenticate");
+    /// ```
+    pub fn from_client_credentials(
+        tenant_id: impl AsRef<str>,
+        client_id: impl AsRef<str>,
+        client_secret: impl AsRef<str>,
+    ) -> GenericResult<Self> {
+        let inner = block_on(nonblocking::MicrosoftGraphClient::from_client_credentials(
+            tenant_id,
+            client_id,
+            client_secret,
+        ))?;
+        Ok(MicrosoftGraphClient { inner })
+    }
+
+    /// submit indicators (split into batches of `MAX_BATCH_SIZE`)
+    /// returns number of submitted indicators. It stops at the first failed batch.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::msgraph::{MicrosoftGraphClient, TiIndicatorOptions};
+    ///
+    /// let client = MicrosoftGraphClient::default();
+    /// let iocs = vec![Ioc::new("evil.example.com").unwrap()];
+    /// let n = client.submit(iocs, &TiIndicatorOptions::default()).expect("failed to submit");
+    /// assert_eq!(n, 1);
+    /// ```
+    ///
+    /// indicators are checked by TLP before submission
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::msgraph::{MicrosoftGraphClient, TiIndicatorOptions};
+    /// use iocutil::tlp::Tlp;
+    ///
+    /// let client = MicrosoftGraphClient::new("token");
+    /// let mut i = Indicator::new(Ioc::new("evil.example.com").unwrap());
+    /// i.meta.tlp = Some(Tlp::Red);
+    /// let err = client.submit(vec![i], &TiIndicatorOptions::default()).unwrap_err();
+    /// assert_eq!(err.to_string(), "TLP:RED does not allow sharing with Organization");
+    /// ```
+    ///
+    /// indicators allowed by the export allowlist are not submitted
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::allowlist::{self, FileList};
+    /// use iocutil::msgraph::{MicrosoftGraphClient, TiIndicatorOptions};
+    ///
+    /// allowlist::set_export_allowlist(FileList::parse("ours", "example.com").unwrap());
+    ///
+    /// let client = MicrosoftGraphClient::new("token");
+    /// let iocs = vec![Ioc::new("www.example.com").unwrap()];
+    /// let (n, suppressed) = allowlist::audited(|| client.submit(iocs, &TiIndicatorOptions::default()));
+    /// assert_eq!(n.unwrap(), 0);
+    /// assert_eq!(suppressed[0].list, "ours");
+    /// ```
+    pub fn submit(
+        &self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+        options: &TiIndicatorOptions,
+    ) -> GenericResult<usize> {
+        block_on(self.inner.submit(indicators, options))
+    }
+}
@@ -0,0 +1,32 @@
+//! async (tokio) API clients (blocking clients in other modules are thin wrappers of these)
+//!
+//! Clients use one shared HTTP client (`iocutil::http::shared_client`) by default,
+//! and `with_client` (`with_config` of API clients) makes them use another one.
+
+pub mod alienvault;
+pub mod banner;
+pub mod dns;
+pub mod falcon;
+pub mod hibp;
+pub mod malwarebazaar;
+pub mod msgraph;
+pub mod taxii;
+pub mod tls;
+pub mod unshorten;
+pub mod virusbay;
+pub mod virustotal;
+pub mod whois;
+
+pub use self::alienvault::AlienVaultOTXClient;
+pub use self::banner::HttpProber;
+pub use self::dns::DnsClient;
+pub use self::falcon::FalconClient;
+pub use self::hibp::HaveIBeenPwnedClient;
+pub use self::malwarebazaar::MalwareBazaarClient;
+pub use self::msgraph::MicrosoftGraphClient;
+pub use self::taxii::TaxiiClient;
+pub use self::tls::TlsClient;
+pub use self::unshorten::UrlExpander;
+pub use self::virusbay::VirusBayClient;
+pub use self::virustotal::VirusTotalClient;
+pub use self::whois::WhoisClient;
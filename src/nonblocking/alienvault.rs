@@ -0,0 +1,232 @@
+//! async client for AlienVault OTX API
+
+use chrono::prelude::*;
+use reqwest::header::HeaderValue;
+use std::convert::TryInto;
+use std::time::Duration;
+
+use crate::alienvault::{Pulse, QueryType, Response};
+use crate::cache;
+use crate::datetime::days_ago;
+use crate::http::{self, ClientConfig};
+use crate::keys::{ApiKeyProvider, DefaultKeys};
+use crate::paginate::{Cursor, Page, Paginated};
+use crate::singleflight::SingleFlight;
+use crate::util::unwrap_try_into;
+use crate::{GenericResult, SampleHash};
+
+const PROVIDER: &str = "alienvault-otx";
+
+/// async AlienVaultOTX API Client (default use `$OTX_APIKEY` environment variable as apikey)
+#[derive(Clone)]
+pub struct AlienVaultOTXClient {
+    apikey: String,
+    config: ClientConfig,
+    flights: SingleFlight,
+}
+
+impl Default for AlienVaultOTXClient {
+    fn default() -> Self {
+        AlienVaultOTXClient::from_keys(DefaultKeys).expect(
+            "please set AlienVault OTX API key to environment var $OTX_APIKEY or ~/.config/iocutil/config.toml",
+        )
+    }
+}
+
+impl AlienVaultOTXClient {
+    /// make new client
+    pub fn new(apikey: String) -> Self {
+        AlienVaultOTXClient {
+            apikey,
+            config: ClientConfig::default(),
+            flights: SingleFlight::new(),
+        }
+    }
+
+    /// new client with apikey of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(AlienVaultOTXClient::new(keys.require(PROVIDER)?))
+    }
+
+    pub(crate) fn apikey(&self) -> &str {
+        &self.apikey
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// make get request
+    fn make_get_request(&self, url: impl AsRef<str>) -> reqwest::RequestBuilder {
+        self.config
+            .client
+            .get(url.as_ref())
+            .header("X-OTX-API-KEY", self.apikey.as_str())
+    }
+
+    async fn get(&self, url: impl AsRef<str>) -> GenericResult<reqwest::Response> {
+        let res = self
+            .config
+            .send(PROVIDER, self.make_get_request(url))
+            .await?;
+        Ok(http::check(PROVIDER, res)?)
+    }
+
+    /// response of indicator api as text (served from cache if any)
+    async fn get_text(
+        &self,
+        endpoint: &str,
+        indicator: &str,
+        url: String,
+    ) -> GenericResult<String> {
+        cache::cached(
+            &self.config.cache,
+            &self.flights,
+            "alienvault",
+            endpoint,
+            indicator,
+            async { Ok(self.get(url).await?.text().await?) },
+        )
+        .await
+    }
+
+    fn indicator_url(&self, hash: impl AsRef<str>, section: QueryType) -> String {
+        format!(
+            "https://otx.alienvault.com/api/v1/indicators/file/{}/{}",
+            hash.as_ref(),
+            section
+        )
+    }
+
+    /// a page of subscribed pulses modified from specified datetime
+    pub async fn pulses_page(
+        &self,
+        modified_since: DateTime<Utc>,
+        limit: u32,
+        page: u32,
+    ) -> GenericResult<Response> {
+        let url = format!(
+            "https://otx.alienvault.com/api/v1/pulses/subscribed?limit={}&page={}&modified_since={}",
+            limit,
+            page,
+            modified_since.to_rfc3339()
+        );
+        let res = self
+            .config
+            .send(
+                PROVIDER,
+                self.config.client.get(url.as_str()).header(
+                    "X-OTX-API-KEY",
+                    HeaderValue::from_str(self.apikey.as_str())?,
+                ),
+            )
+            .await?;
+        Ok(http::check(PROVIDER, res)?.json().await?)
+    }
+
+    /// get pulses modified from specified datetime
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::nonblocking::AlienVaultOTXClient;
+    ///
+    /// # async fn run() {
+    /// let client = AlienVaultOTXClient::default();
+    /// let pulses = client.pulses_from(days_ago(7)).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn pulses_from(
+        &self,
+        datetime: impl Into<DateTime<Utc>>,
+    ) -> GenericResult<Vec<Pulse>> {
+        let datetime = datetime.into();
+        let mut pulses = vec![];
+        for page in 1.. {
+            if page != 1 {
+                //  wait 1 second before request
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            let res = match self.pulses_page(datetime, 50, page).await {
+                Ok(x) => x,
+                Err(_) => break,
+            };
+            pulses.extend(res.results);
+            // it's done if next is null(or maybe empty).
+            if res.next.is_none() {
+                break;
+            }
+        }
+        Ok(pulses)
+    }
+
+    /// pulses modified from specified datetime, fetched page by page lazily
+    pub fn pulses(&self, modified_since: impl Into<DateTime<Utc>>) -> Paginated<'_, Pulse> {
+        let modified_since = modified_since.into();
+        Paginated::new(move |cursor| {
+            let page = match cursor {
+                Some(Cursor::Page(n)) => n,
+                _ => 1,
+            };
+            Box::pin(async move {
+                let res = self.pulses_page(modified_since, 50, page).await?;
+                Ok(Page {
+                    next: res.next.as_ref().map(|_| Cursor::Page(page + 1)),
+                    items: res.results,
+                })
+            })
+        })
+    }
+
+    /// get pulses for x days
+    pub async fn pulses_for(&self, days: i64) -> GenericResult<Vec<Pulse>> {
+        self.pulses_from(days_ago(days)).await
+    }
+
+    /// get raw json report about indicator
+    pub async fn get_raw_json(
+        &self,
+        hash: impl TryInto<SampleHash>,
+        section: QueryType,
+    ) -> GenericResult<String> {
+        let hash = unwrap_try_into(hash)?;
+        let endpoint = format!("file/{}", section);
+        self.get_text(&endpoint, hash.as_ref(), self.indicator_url(&hash, section))
+            .await
+    }
+
+    /// query with free format
+    pub async fn query<T>(
+        &self,
+        hash: impl TryInto<SampleHash>,
+        section: QueryType,
+    ) -> GenericResult<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let hash = unwrap_try_into(hash)?;
+        let endpoint = format!("file/{}", section);
+        let text = self
+            .get_text(&endpoint, hash.as_ref(), self.indicator_url(&hash, section))
+            .await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// general section of any kind of indicator (e.g. `section` is `domain` or `IPv4`)
+    pub async fn general<T>(&self, section: &str, indicator: impl AsRef<str>) -> GenericResult<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let url = format!(
+            "https://otx.alienvault.com/api/v1/indicators/{}/{}/general",
+            section,
+            indicator.as_ref()
+        );
+        let endpoint = format!("{}/general", section);
+        let text = self.get_text(&endpoint, indicator.as_ref(), url).await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+}
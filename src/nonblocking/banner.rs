@@ -0,0 +1,207 @@
+//! async prober of HTTP banners (see `iocutil::banner`)
+
+use reqwest::header::{LOCATION, USER_AGENT};
+use reqwest::{redirect, Method, Url};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::banner::{html_title, BannerError, HttpBanner};
+use crate::http::{self, ProxyConfig};
+use crate::ioc::{Indicator, Ioc};
+use crate::retry::RetryPolicy;
+use crate::GenericResult;
+
+const PROVIDER: &str = "http-banner";
+
+/// total timeout of requests unless `with_timeout`
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// async careful prober of HTTP(S) servers (refuses to send requests unless `allow_active_probing`)
+#[derive(Clone)]
+pub struct HttpProber {
+    allowed: bool,
+    client: reqwest::Client,
+    user_agent: Option<String>,
+    max_redirects: usize,
+    method: Method,
+    max_body: usize,
+    timeout: Duration,
+}
+
+/// client following no redirects and accepting invalid certificates
+fn prober_client(builder: reqwest::ClientBuilder) -> GenericResult<reqwest::Client> {
+    Ok(builder
+        .redirect(redirect::Policy::none())
+        .danger_accept_invalid_certs(true)
+        .build()?)
+}
+
+impl Default for HttpProber {
+    fn default() -> Self {
+        HttpProber {
+            allowed: false,
+            client: prober_client(http::client_builder()).expect("failed to build HTTP client"),
+            user_agent: None,
+            max_redirects: 3,
+            method: Method::GET,
+            max_body: 1 << 20,
+            timeout: TIMEOUT,
+        }
+    }
+}
+
+impl HttpProber {
+    /// prober sending GET, following 3 redirects at most
+    /// (requests are refused until `allow_active_probing`)
+    pub fn new() -> Self {
+        HttpProber::default()
+    }
+
+    /// allow requests, accepting that servers of adversaries may notice them
+    pub fn allow_active_probing(mut self) -> Self {
+        self.allowed = true;
+        self
+    }
+
+    /// use the HTTP client (redirects should be disabled in it, as the prober follows them)
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// send requests via the proxy
+    pub fn with_proxy(self, proxy: &ProxyConfig) -> GenericResult<Self> {
+        let client = prober_client(proxy.apply(http::client_builder())?)?;
+        Ok(self.with_client(client))
+    }
+
+    /// `User-Agent` of requests (default `iocutil.rs/<version>`)
+    pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Self {
+        self.user_agent = Some(user_agent.as_ref().to_owned());
+        self
+    }
+
+    /// redirects followed at most (default 3, 0 not to follow)
+    pub fn with_max_redirects(mut self, max: usize) -> Self {
+        self.max_redirects = max;
+        self
+    }
+
+    /// send HEAD instead of GET (no title nor body hash)
+    pub fn head_only(mut self) -> Self {
+        self.method = Method::HEAD;
+        self
+    }
+
+    /// bytes of body read at most (default 1 MiB)
+    pub fn with_max_body(mut self, max: usize) -> Self {
+        self.max_body = max;
+        self
+    }
+
+    /// total timeout of each request including body (default 10 seconds)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// banner of the URL
+    pub async fn probe(&self, url: impl AsRef<str>) -> GenericResult<HttpBanner> {
+        if !self.allowed {
+            return Err(BannerError::NotAllowed.into());
+        }
+        let first = Url::parse(url.as_ref())?;
+        let mut url = first.clone();
+        let mut redirects = vec![];
+        let mut res = loop {
+            let mut req = self.client.request(self.method.clone(), url.clone());
+            if let Some(ua) = &self.user_agent {
+                req = req.header(USER_AGENT, ua);
+            }
+            let res = http::send(
+                PROVIDER,
+                req,
+                &None,
+                &RetryPolicy::none(),
+                Some(self.timeout),
+            )
+            .await?;
+            let next = res
+                .headers()
+                .get(LOCATION)
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| url.join(x).ok());
+            match next {
+                Some(next)
+                    if res.status().is_redirection() && redirects.len() < self.max_redirects =>
+                {
+                    redirects.push(next.to_string());
+                    url = next;
+                }
+                _ => break res,
+            }
+        };
+
+        let status = res.status().as_u16();
+        let headers: Vec<(String, String)> = res
+            .headers()
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.as_str().to_owned(),
+                    String::from_utf8_lossy(v.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        let (title, body_sha256) = if self.method == Method::HEAD {
+            (None, None)
+        } else {
+            let mut body: Vec<u8> = vec![];
+            while let Some(chunk) = res.chunk().await? {
+                body.extend(&chunk);
+                if body.len() >= self.max_body {
+                    body.truncate(self.max_body);
+                    break;
+                }
+            }
+            (
+                html_title(&String::from_utf8_lossy(&body)),
+                Some(format!("{:x}", Sha256::digest(&body))),
+            )
+        };
+
+        Ok(HttpBanner {
+            url: first.to_string(),
+            redirects,
+            status,
+            server: headers
+                .iter()
+                .find(|(k, _)| k == "server")
+                .map(|(_, v)| v.clone()),
+            headers,
+            title,
+            body_sha256,
+        })
+    }
+
+    /// banner of the URL, or domain / IP address (HTTPS, and HTTP if failed) of indicator
+    /// (`None` for other kinds)
+    pub async fn probe_indicator(
+        &self,
+        indicator: &Indicator,
+    ) -> GenericResult<Option<HttpBanner>> {
+        let host = match &indicator.ioc {
+            Ioc::Url(x) => return Ok(Some(self.probe(x).await?)),
+            Ioc::Domain(x) => x.clone(),
+            Ioc::IpAddress(IpAddr::V6(x)) => format!("[{}]", x),
+            Ioc::IpAddress(x) => x.to_string(),
+            _ => return Ok(None),
+        };
+        match self.probe(format!("https://{}/", host)).await {
+            Ok(x) => Ok(Some(x)),
+            Err(e) if e.downcast_ref::<BannerError>().is_some() => Err(e),
+            Err(_) => Ok(Some(self.probe(format!("http://{}/", host)).await?)),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! async DNS client (see `iocutil::dns`)
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Semaphore;
+
+use crate::dns::{
+    is_truncated, parse_response, query_message, reverse_name, system_nameserver, DnsError,
+    DnsRecord, RecordData, RecordType,
+};
+use crate::graph::Relationship;
+use crate::http;
+use crate::ioc::{host_of_url, Indicator, Ioc};
+use crate::retry::RetryPolicy;
+use crate::GenericResult;
+
+const PROVIDER: &str = "doh";
+
+/// timeout of queries unless `with_timeout`
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// resolver which queries are sent to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DnsResolver {
+    /// first name server in `/etc/resolv.conf`
+    System,
+    /// specific server (e.g. `9.9.9.9:53`)
+    Server(SocketAddr),
+    /// DNS over HTTPS service (e.g. `https://cloudflare-dns.com/dns-query`)
+    DoH(String),
+}
+
+/// async client resolving domains (refuses queries unless `allow_active_resolution`)
+#[derive(Clone)]
+pub struct DnsClient {
+    resolver: DnsResolver,
+    allowed: bool,
+    client: reqwest::Client,
+    timeout: Duration,
+}
+
+/// random id of query
+fn query_id() -> u16 {
+    RandomState::new().build_hasher().finish() as u16
+}
+
+impl DnsClient {
+    /// client of the resolver (queries are refused until `allow_active_resolution`)
+    pub fn new(resolver: DnsResolver) -> Self {
+        DnsClient {
+            resolver,
+            allowed: false,
+            client: http::shared_client(),
+            timeout: TIMEOUT,
+        }
+    }
+
+    /// allow queries, accepting that name servers of adversaries may notice them
+    pub fn allow_active_resolution(mut self) -> Self {
+        self.allowed = true;
+        self
+    }
+
+    /// use the HTTP client for DNS over HTTPS instead of shared one
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// timeout of each query (default 5 seconds)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// response over UDP, or TCP if truncated
+    async fn exchange(&self, server: SocketAddr, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let bind: SocketAddr = if server.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind).await?;
+        socket.connect(server).await?;
+        socket.send(query).await?;
+        let mut buf = vec![0; 4096];
+        let n = socket.recv(&mut buf).await?;
+        buf.truncate(n);
+        if !is_truncated(&buf) {
+            return Ok(buf);
+        }
+
+        let mut stream = TcpStream::connect(server).await?;
+        stream
+            .write_all(&(query.len() as u16).to_be_bytes())
+            .await?;
+        stream.write_all(query).await?;
+        let len = stream.read_u16().await?;
+        let mut buf = vec![0; usize::from(len)];
+        stream.read_exact(&mut buf).await?;
+        Ok(buf)
+    }
+
+    /// response over HTTPS (RFC 8484)
+    async fn exchange_https(&self, url: &str, query: Vec<u8>) -> GenericResult<Vec<u8>> {
+        let res = http::send(
+            PROVIDER,
+            self.client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/dns-message")
+                .header(reqwest::header::ACCEPT, "application/dns-message")
+                .body(query),
+            &None,
+            &RetryPolicy::none(),
+            Some(self.timeout),
+        )
+        .await?;
+        Ok(http::check(PROVIDER, res)?.bytes().await?.to_vec())
+    }
+
+    /// records of the type (empty if the name does not exist)
+    pub async fn lookup(
+        &self,
+        name: impl AsRef<str>,
+        record_type: RecordType,
+    ) -> GenericResult<Vec<DnsRecord>> {
+        if !self.allowed {
+            return Err(DnsError::NotAllowed.into());
+        }
+        let id = query_id();
+        let query = query_message(id, name.as_ref(), record_type)?;
+
+        let response = match &self.resolver {
+            DnsResolver::DoH(url) => {
+                // the id should be 0 for HTTP caches
+                let mut query = query;
+                query[..2].copy_from_slice(&[0, 0]);
+                let response = self.exchange_https(url, query).await?;
+                return Ok(parse_response(&response, 0, record_type)?);
+            }
+            DnsResolver::System => {
+                let server = system_nameserver()?;
+                tokio::time::timeout(self.timeout, self.exchange(server, &query)).await??
+            }
+            DnsResolver::Server(server) => {
+                tokio::time::timeout(self.timeout, self.exchange(*server, &query)).await??
+            }
+        };
+        Ok(parse_response(&response, id, record_type)?)
+    }
+
+    /// current infrastructure of a domain (or URL / email address on it) by A / AAAA / MX / NS
+    ///
+    /// relationships are `resolves-to` (addresses), `mail-server` and `name-server` (domains).
+    pub async fn expand(&self, indicator: &Indicator) -> GenericResult<Vec<Relationship>> {
+        let domain = match &indicator.ioc {
+            Ioc::Domain(x) => x.clone(),
+            Ioc::Url(x) => match host_of_url(x) {
+                Some(Ioc::Domain(x)) => x,
+                _ => return Ok(vec![]),
+            },
+            Ioc::EmailAddress(x) => match x.rsplit('@').next() {
+                Some(x) => x.to_owned(),
+                None => return Ok(vec![]),
+            },
+            _ => return Ok(vec![]),
+        };
+
+        let mut relationships = vec![];
+        for t in &[
+            RecordType::A,
+            RecordType::Aaaa,
+            RecordType::Mx,
+            RecordType::Ns,
+        ] {
+            for record in self.lookup(&domain, *t).await? {
+                if let Some(target) = record.ioc() {
+                    let r = Relationship {
+                        source: Ioc::Domain(domain.clone()),
+                        target,
+                        relationship_type: record.relationship_type().to_owned(),
+                    };
+                    if !relationships.contains(&r) {
+                        relationships.push(r);
+                    }
+                }
+            }
+        }
+        Ok(relationships)
+    }
+
+    /// host names of the address by PTR
+    pub async fn reverse(&self, ip: IpAddr) -> GenericResult<Vec<String>> {
+        let mut names = vec![];
+        for record in self.lookup(reverse_name(ip), RecordType::Ptr).await? {
+            if let RecordData::Ptr(x) = record.data {
+                if !names.contains(&x) {
+                    names.push(x);
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// host names of IP addresses (or URLs on them) by PTR, `concurrency` queries at a time
+    ///
+    /// relationships are address `reverse-resolves-to` domain, to be added to the graph of
+    /// indicators as related ones (indicators are not modified).
+    /// addresses whose server failed (e.g. SERVFAIL of unconfigured zones) have no host names.
+    pub async fn reverse_all(
+        &self,
+        indicators: &[Indicator],
+        concurrency: usize,
+    ) -> GenericResult<Vec<Relationship>> {
+        if !self.allowed {
+            return Err(DnsError::NotAllowed.into());
+        }
+        let mut ips: Vec<IpAddr> = vec![];
+        for indicator in indicators {
+            let ip = match &indicator.ioc {
+                Ioc::IpAddress(x) => *x,
+                Ioc::Url(x) => match host_of_url(x) {
+                    Some(Ioc::IpAddress(x)) => x,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if !ips.contains(&ip) {
+                ips.push(ip);
+            }
+        }
+
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        let tasks: Vec<_> = ips
+            .into_iter()
+            .map(|ip| {
+                let client = self.clone();
+                let semaphore = semaphore.clone();
+                tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await;
+                    (ip, client.reverse(ip).await)
+                })
+            })
+            .collect();
+
+        let mut relationships = vec![];
+        for task in tasks {
+            let (ip, names) = task.await?;
+            let names = match names {
+                Ok(x) => x,
+                Err(e) => match e.downcast_ref::<DnsError>() {
+                    Some(DnsError::ServerFailure(_)) => continue,
+                    _ => return Err(e),
+                },
+            };
+            for name in names {
+                if let Ok(target @ Ioc::Domain(_)) = Ioc::new(&name) {
+                    relationships.push(Relationship {
+                        source: Ioc::IpAddress(ip),
+                        target,
+                        relationship_type: "reverse-resolves-to".to_owned(),
+                    });
+                }
+            }
+        }
+        Ok(relationships)
+    }
+}
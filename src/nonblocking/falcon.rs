@@ -0,0 +1,187 @@
+//! async client for CrowdStrike Falcon IOC Management API
+
+use chrono::Utc;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_json::json;
+
+use crate::allowlist::suppress;
+use crate::falcon::{
+    falcon_type, to_falcon_indicator, FalconIndicator, FalconIocOptions, Resources, TokenResponse,
+    MAX_BATCH_SIZE,
+};
+use crate::http::{self, ClientConfig};
+use crate::ioc::{Indicator, Ioc};
+use crate::keys::{ApiKeyProvider, DefaultKeys};
+use crate::retry::RetryPolicy;
+use crate::tlp;
+use crate::ttl::unexpired;
+use crate::GenericResult;
+
+const PROVIDER: &str = "falcon";
+
+/// async client for Falcon IOC Management API (default use `$FALCON_TOKEN` environment variable as access token)
+#[derive(Clone)]
+pub struct FalconClient {
+    base_url: String,
+    token: String,
+    config: ClientConfig,
+}
+
+impl Default for FalconClient {
+    fn default() -> Self {
+        FalconClient::from_keys(DefaultKeys).expect(
+            "please set Falcon API access token to environment var $FALCON_TOKEN or ~/.config/iocutil/config.toml",
+        )
+    }
+}
+
+impl FalconClient {
+    /// new client with access token (for US-1 cloud)
+    pub fn new(token: impl AsRef<str>) -> Self {
+        FalconClient {
+            base_url: "https://api.crowdstrike.com".to_owned(),
+            token: token.as_ref().to_owned(),
+            config: ClientConfig::default(),
+        }
+    }
+
+    /// new client with access token of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(FalconClient::new(keys.require(PROVIDER)?))
+    }
+
+    /// set base url for other clouds (e.g. `https://api.eu-1.crowdstrike.com`)
+    pub fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// new client with access token acquired by API client credentials (for US-1 cloud)
+    /// (the API client requires IOC Management read/write scope)
+    pub async fn from_client_credentials(
+        client_id: impl AsRef<str>,
+        client_secret: impl AsRef<str>,
+    ) -> GenericResult<Self> {
+        let req = http::shared_client()
+            .post("https://api.crowdstrike.com/oauth2/token")
+            .form(&[
+                ("client_id", client_id.as_ref()),
+                ("client_secret", client_secret.as_ref()),
+            ]);
+        let res = http::send(PROVIDER, req, &None, &RetryPolicy::none(), None).await?;
+        let res = http::check_token(PROVIDER, res)?;
+
+        let t: TokenResponse = res.json().await?;
+        Ok(FalconClient::new(t.access_token))
+    }
+
+    fn indicators_url(&self) -> String {
+        format!("{}/iocs/entities/indicators/v1", self.base_url)
+    }
+
+    fn ids_query(ids: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+        ids.into_iter()
+            .map(|x| format!("ids={}", utf8_percent_encode(x.as_ref(), NON_ALPHANUMERIC)))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    async fn send<T>(&self, req: reqwest::RequestBuilder) -> GenericResult<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let res = self
+            .config
+            .send(PROVIDER, req.bearer_auth(self.token.as_str()))
+            .await?;
+        let r: Resources<T> = http::check(PROVIDER, res)?.json().await?;
+        Ok(r.resources.unwrap_or_default())
+    }
+
+    /// create custom indicators (split into batches of `MAX_BATCH_SIZE`)
+    /// returns created indicators. It stops at the first failed batch.
+    /// Nothing is created if TLP of any indicator does not allow the audience of options.
+    pub async fn create(
+        &self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+        options: &FalconIocOptions,
+    ) -> GenericResult<Vec<FalconIndicator>> {
+        let now = Utc::now();
+        let indicators = suppress(unexpired(indicators, options.include_expired));
+        tlp::ensure_all(&indicators, options.audience)?;
+        let indicators = indicators
+            .into_iter()
+            .map(|x| to_falcon_indicator(x, options, now))
+            .collect::<GenericResult<Vec<_>>>()?;
+
+        let mut created = vec![];
+        for batch in indicators.chunks(MAX_BATCH_SIZE) {
+            let req = self
+                .config
+                .client
+                .post(self.indicators_url().as_str())
+                .json(&json!({ "indicators": batch }));
+            created.extend(self.send(req).await?);
+        }
+        Ok(created)
+    }
+
+    /// query ids of custom indicators with FQL filter
+    pub async fn query(&self, filter: impl AsRef<str>) -> GenericResult<Vec<String>> {
+        let url = format!(
+            "{}/iocs/queries/indicators/v1?limit=2000&filter={}",
+            self.base_url,
+            utf8_percent_encode(filter.as_ref(), NON_ALPHANUMERIC)
+        );
+        self.send(self.config.client.get(url.as_str())).await
+    }
+
+    /// find ids of custom indicators of an indicator
+    pub async fn find(&self, ioc: &Ioc) -> GenericResult<Vec<String>> {
+        let (t, v) = falcon_type(ioc)?;
+        self.query(format!("type:'{}'+value:'{}'", t, v)).await
+    }
+
+    /// get details of custom indicators
+    pub async fn get(
+        &self,
+        ids: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> GenericResult<Vec<FalconIndicator>> {
+        let url = format!("{}?{}", self.indicators_url(), FalconClient::ids_query(ids));
+        self.send(self.config.client.get(url.as_str())).await
+    }
+
+    /// expire custom indicators now (they remain in console as expired)
+    pub async fn expire(
+        &self,
+        ids: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> GenericResult<Vec<FalconIndicator>> {
+        let now = Utc::now().to_rfc3339();
+        let indicators: Vec<_> = ids
+            .into_iter()
+            .map(|x| json!({ "id": x.as_ref(), "expiration": now }))
+            .collect();
+        let req = self
+            .config
+            .client
+            .patch(self.indicators_url().as_str())
+            .json(&json!({ "indicators": indicators }));
+        self.send(req).await
+    }
+
+    /// delete custom indicators
+    pub async fn delete(
+        &self,
+        ids: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> GenericResult<()> {
+        let url = format!("{}?{}", self.indicators_url(), FalconClient::ids_query(ids));
+        let _: Vec<serde_json::Value> = self.send(self.config.client.delete(url.as_str())).await?;
+        Ok(())
+    }
+}
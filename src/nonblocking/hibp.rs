@@ -0,0 +1,176 @@
+//! async client for Have I Been Pwned API
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::hibp::Breach;
+use crate::http::{self, ClientConfig};
+use crate::ioc::is_email_address;
+use crate::keys::{ApiKeyProvider, DefaultKeys};
+use crate::metrics;
+use crate::retry;
+use crate::GenericResult;
+
+const PROVIDER: &str = "haveibeenpwned";
+
+/// async client for Have I Been Pwned API v3 (default use `$HIBP_APIKEY` environment variable as apikey)
+#[derive(Clone)]
+pub struct HaveIBeenPwnedClient {
+    apikey: String,
+    user_agent: String,
+    interval: Duration,
+    config: ClientConfig,
+}
+
+impl Default for HaveIBeenPwnedClient {
+    fn default() -> Self {
+        HaveIBeenPwnedClient::from_keys(DefaultKeys).expect(
+            "please set Have I Been Pwned API key to environment var $HIBP_APIKEY or ~/.config/iocutil/config.toml",
+        )
+    }
+}
+
+impl HaveIBeenPwnedClient {
+    /// new client with apikey
+    pub fn new(apikey: impl AsRef<str>) -> Self {
+        HaveIBeenPwnedClient {
+            apikey: apikey.as_ref().to_owned(),
+            user_agent: format!("iocutil.rs/{}", env!("CARGO_PKG_VERSION")),
+            // the lowest subscription allows 10 requests per minute
+            interval: Duration::from_secs(6),
+            config: ClientConfig::default(),
+        }
+    }
+
+    /// new client with apikey of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(HaveIBeenPwnedClient::new(keys.require(PROVIDER)?))
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// set user agent (HIBP refuses requests without it)
+    pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Self {
+        self.user_agent = user_agent.as_ref().to_owned();
+        self
+    }
+
+    /// set interval between requests in batch operation (depends on your subscription)
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    fn breached_account_url(&self, email: impl AsRef<str>) -> String {
+        format!(
+            "https://haveibeenpwned.com/api/v3/breachedaccount/{}?truncateResponse=false",
+            utf8_percent_encode(email.as_ref(), NON_ALPHANUMERIC)
+        )
+    }
+
+    fn breached_domain_url(&self, domain: impl AsRef<str>) -> String {
+        format!(
+            "https://haveibeenpwned.com/api/v3/breacheddomain/{}",
+            utf8_percent_encode(domain.as_ref(), NON_ALPHANUMERIC)
+        )
+    }
+
+    /// send request (retry once after `Retry-After` if rate limited)
+    /// returns None if the resource was not found.
+    async fn request(&self, url: impl AsRef<str>) -> GenericResult<Option<reqwest::Response>> {
+        let mut throttled = false;
+        loop {
+            let res = self
+                .config
+                .send(
+                    PROVIDER,
+                    self.config
+                        .client
+                        .get(url.as_ref())
+                        .header("hibp-api-key", self.apikey.as_str())
+                        .header(reqwest::header::USER_AGENT, self.user_agent.as_str()),
+                )
+                .await?;
+
+            match res.status() {
+                StatusCode::NOT_FOUND => return Ok(None),
+                StatusCode::TOO_MANY_REQUESTS if !throttled => {
+                    throttled = true;
+                    let wait = retry::retry_after(&res).unwrap_or(self.interval);
+                    metrics::report(|m| m.rate_limit_wait(PROVIDER, wait));
+                    tokio::time::sleep(wait).await;
+                }
+                _ => return Ok(Some(http::check(PROVIDER, res)?)),
+            }
+        }
+    }
+
+    /// get breaches which specified email address appeared in
+    /// (empty if the email address is not pwned)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::nonblocking::HaveIBeenPwnedClient;
+    ///
+    /// # async fn run() {
+    /// let client = HaveIBeenPwnedClient::default();
+    /// let breaches = client.breaches_of("test@example.com").await.expect("failed to query");
+    /// # }
+    /// ```
+    pub async fn breaches_of(&self, email: impl AsRef<str>) -> GenericResult<Vec<Breach>> {
+        let email = email.as_ref().trim();
+        if !is_email_address(email) {
+            return Err(Error::parse("email address", email).into());
+        }
+
+        match self.request(self.breached_account_url(email)).await? {
+            Some(res) => Ok(res.json().await?),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// is specified email address pwned?
+    pub async fn is_pwned(&self, email: impl AsRef<str>) -> GenericResult<bool> {
+        Ok(!self.breaches_of(email).await?.is_empty())
+    }
+
+    /// batch query breaches of email addresses
+    pub async fn batch_query(
+        &self,
+        emails: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Vec<(String, GenericResult<Vec<Breach>>)> {
+        let mut results = vec![];
+        for (idx, email) in emails.into_iter().enumerate() {
+            if idx != 0 {
+                tokio::time::sleep(self.interval).await;
+            }
+            let email = email.as_ref().to_owned();
+            let breaches = self.breaches_of(&email).await;
+            results.push((email, breaches));
+        }
+        results
+    }
+
+    /// search breached accounts on your domain (the domain must be verified on HIBP dashboard)
+    /// returns map of alias (local part of email address) and breach names.
+    pub async fn breached_domain(
+        &self,
+        domain: impl AsRef<str>,
+    ) -> GenericResult<HashMap<String, Vec<String>>> {
+        match self
+            .request(self.breached_domain_url(domain.as_ref().trim()))
+            .await?
+        {
+            Some(res) => Ok(res.json().await?),
+            None => Ok(HashMap::new()),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! async client for MalwareBazaar API
+
+use std::convert::TryInto;
+
+use crate::cache;
+use crate::http::{self, ClientConfig};
+use crate::keys::{ApiKeyProvider, DefaultKeys};
+use crate::malwarebazaar::{MalwareBazaarError, Response, SampleInfo, API_URL};
+use crate::singleflight::SingleFlight;
+use crate::util::unwrap_try_into;
+use crate::{GenericResult, SampleHash};
+
+const PROVIDER: &str = "malwarebazaar";
+
+/// async client for MalwareBazaar API (default use `$MALWAREBAZAAR_AUTH_KEY` environment variable as auth key)
+#[derive(Clone)]
+pub struct MalwareBazaarClient {
+    auth_key: String,
+    config: ClientConfig,
+    flights: SingleFlight,
+}
+
+impl Default for MalwareBazaarClient {
+    fn default() -> Self {
+        MalwareBazaarClient::from_keys(DefaultKeys).expect(
+            "please set MalwareBazaar auth key to environment var $MALWAREBAZAAR_AUTH_KEY or ~/.config/iocutil/config.toml",
+        )
+    }
+}
+
+impl MalwareBazaarClient {
+    /// new client with auth key
+    pub fn new(auth_key: impl AsRef<str>) -> Self {
+        MalwareBazaarClient {
+            auth_key: auth_key.as_ref().to_owned(),
+            config: ClientConfig::default(),
+            flights: SingleFlight::new(),
+        }
+    }
+
+    /// new client with auth key of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(MalwareBazaarClient::new(keys.require(PROVIDER)?))
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// send query (`None` if nothing is found)
+    pub async fn query<T>(&self, params: &[(&str, &str)]) -> GenericResult<Option<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let endpoint = params
+            .iter()
+            .find(|(k, _)| *k == "query")
+            .map(|(_, v)| *v)
+            .unwrap_or_default();
+        let key = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+        let text = cache::cached(
+            &self.config.cache,
+            &self.flights,
+            PROVIDER,
+            endpoint,
+            &key,
+            async {
+                let res = self
+                    .config
+                    .send(
+                        PROVIDER,
+                        self.config
+                            .client
+                            .post(API_URL)
+                            .header("Auth-Key", self.auth_key.as_str())
+                            .form(params),
+                    )
+                    .await?;
+                Ok(http::check(PROVIDER, res)?.text().await?)
+            },
+        )
+        .await?;
+        let res: Response<T> = serde_json::from_str(&text)?;
+        match res.query_status.as_str() {
+            "ok" => Ok(res.data),
+            "hash_not_found" | "no_results" | "illegal_hash" => Ok(None),
+            _ => Err(MalwareBazaarError::QueryFailed(res.query_status).into()),
+        }
+    }
+
+    /// information of a sample (`None` if not found)
+    pub async fn get_info(
+        &self,
+        hash: impl TryInto<SampleHash>,
+    ) -> GenericResult<Option<SampleInfo>> {
+        let hash = unwrap_try_into(hash)?;
+        let data: Option<Vec<SampleInfo>> = self
+            .query(&[("query", "get_info"), ("hash", hash.as_ref())])
+            .await?;
+        Ok(data.and_then(|x| x.into_iter().next()))
+    }
+}
@@ -0,0 +1,113 @@
+//! async client for Microsoft Graph threat intelligence indicator API
+
+use chrono::Utc;
+use serde_json::json;
+
+use crate::allowlist::suppress;
+use crate::http::{self, ClientConfig};
+use crate::ioc::Indicator;
+use crate::keys::{ApiKeyProvider, DefaultKeys};
+use crate::msgraph::{to_ti_indicator, TiIndicatorOptions, TokenResponse, MAX_BATCH_SIZE};
+use crate::retry::RetryPolicy;
+use crate::tlp;
+use crate::ttl::unexpired;
+use crate::GenericResult;
+
+const PROVIDER: &str = "msgraph";
+
+/// async client for Microsoft Graph tiIndicators API (default use `$MSGRAPH_TOKEN` environment variable as access token)
+#[derive(Clone)]
+pub struct MicrosoftGraphClient {
+    token: String,
+    config: ClientConfig,
+}
+
+impl Default for MicrosoftGraphClient {
+    fn default() -> Self {
+        MicrosoftGraphClient::from_keys(DefaultKeys).expect(
+            "please set Microsoft Graph access token to environment var $MSGRAPH_TOKEN or ~/.config/iocutil/config.toml",
+        )
+    }
+}
+
+impl MicrosoftGraphClient {
+    /// new client with access token
+    pub fn new(token: impl AsRef<str>) -> Self {
+        MicrosoftGraphClient {
+            token: token.as_ref().to_owned(),
+            config: ClientConfig::default(),
+        }
+    }
+
+    /// new client with access token of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(MicrosoftGraphClient::new(keys.require(PROVIDER)?))
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// new client with access token acquired by client credentials of an app registration
+    /// (the app requires `ThreatIndicators.ReadWrite.OwnedBy` permission)
+    pub async fn from_client_credentials(
+        tenant_id: impl AsRef<str>,
+        client_id: impl AsRef<str>,
+        client_secret: impl AsRef<str>,
+    ) -> GenericResult<Self> {
+        let req = http::shared_client()
+            .post(
+                format!(
+                    "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+                    tenant_id.as_ref()
+                )
+                .as_str(),
+            )
+            .form(&[
+                ("client_id", client_id.as_ref()),
+                ("client_secret", client_secret.as_ref()),
+                ("scope", "https://graph.microsoft.com/.default"),
+                ("grant_type", "client_credentials"),
+            ]);
+        let res = http::send(PROVIDER, req, &None, &RetryPolicy::none(), None).await?;
+        let res = http::check_token(PROVIDER, res)?;
+
+        let t: TokenResponse = res.json().await?;
+        Ok(MicrosoftGraphClient::new(t.access_token))
+    }
+
+    /// submit indicators (split into batches of `MAX_BATCH_SIZE`)
+    /// returns number of submitted indicators. It stops at the first failed batch.
+    /// Nothing is submitted if TLP of any indicator does not allow the audience of options.
+    pub async fn submit(
+        &self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+        options: &TiIndicatorOptions,
+    ) -> GenericResult<usize> {
+        let now = Utc::now();
+        let indicators = suppress(unexpired(indicators, options.include_expired));
+        tlp::ensure_all(&indicators, options.audience)?;
+        let indicators: Vec<_> = indicators
+            .into_iter()
+            .map(|x| to_ti_indicator(x, options, now))
+            .collect();
+
+        let mut submitted = 0;
+        for batch in indicators.chunks(MAX_BATCH_SIZE) {
+            let res = self.config.send(                PROVIDER,
+                self.config.client
+                    .post(
+                        "https://graph.microsoft.com/beta/security/tiIndicators/submitTiIndicators",
+                    )
+                    .bearer_auth(self.token.as_str())
+                    .json(&json!({ "value": batch })))
+            .await?;
+            http::check(PROVIDER, res)?;
+            submitted += batch.len();
+        }
+
+        Ok(submitted)
+    }
+}
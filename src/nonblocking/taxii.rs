@@ -0,0 +1,233 @@
+//! async client for TAXII 2.1 server
+
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+use crate::http::{self, ClientConfig};
+use crate::ioc::Indicator;
+use crate::paginate::{Cursor, Page, Paginated};
+use crate::stix::from_stix_objects;
+use crate::taxii::{
+    timestamp, ApiRoot, Collection, Collections, Discovery, Envelope, TaxiiError, TAXII_MEDIA_TYPE,
+};
+use crate::GenericResult;
+
+const PROVIDER: &str = "taxii";
+
+/// async client for TAXII 2.1 server
+#[derive(Clone)]
+pub struct TaxiiClient {
+    server: String,
+    credential: Option<(String, String)>,
+    config: ClientConfig,
+}
+
+impl TaxiiClient {
+    /// new client for server (e.g. `https://example.com/`)
+    pub fn new(server: impl AsRef<str>) -> Self {
+        TaxiiClient {
+            server: server.as_ref().trim_end_matches('/').to_owned(),
+            credential: None,
+            config: ClientConfig::default(),
+        }
+    }
+
+    /// set credential for basic authentication
+    pub fn with_credential(mut self, username: impl AsRef<str>, password: impl AsRef<str>) -> Self {
+        self.credential = Some((username.as_ref().to_owned(), password.as_ref().to_owned()));
+        self
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// make get request
+    async fn get<T>(&self, url: impl AsRef<str>) -> GenericResult<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let req = self
+            .config
+            .client
+            .get(url.as_ref())
+            .header(reqwest::header::ACCEPT, TAXII_MEDIA_TYPE);
+
+        let req = match &self.credential {
+            Some((user, pass)) => req.basic_auth(user, Some(pass)),
+            None => req,
+        };
+
+        let res = self.config.send(PROVIDER, req).await?;
+        Ok(http::check(PROVIDER, res)?.json().await?)
+    }
+
+    /// resolve api root url (relative urls are joined to server)
+    fn api_root_url(&self, api_root: impl AsRef<str>) -> String {
+        let api_root = api_root.as_ref().trim_end_matches('/');
+        if api_root.starts_with("http://") || api_root.starts_with("https://") {
+            api_root.to_owned()
+        } else {
+            format!("{}/{}", self.server, api_root.trim_start_matches('/'))
+        }
+    }
+
+    /// discover server information and api roots
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::nonblocking::TaxiiClient;
+    ///
+    /// # async fn run() {
+    /// let client = TaxiiClient::new("https://example.com/").with_credential("user", "pass");
+    /// let discovery = client.discover().await.expect("failed to discover");
+    /// println!("{:?}", discovery.api_roots);
+    /// # }
+    /// ```
+    pub async fn discover(&self) -> GenericResult<Discovery> {
+        self.get(format!("{}/taxii2/", self.server)).await
+    }
+
+    /// get information of api root
+    pub async fn api_root(&self, api_root: impl AsRef<str>) -> GenericResult<ApiRoot> {
+        self.get(format!("{}/", self.api_root_url(api_root))).await
+    }
+
+    /// list collections on api root
+    pub async fn collections(&self, api_root: impl AsRef<str>) -> GenericResult<Vec<Collection>> {
+        let c: Collections = self
+            .get(format!("{}/collections/", self.api_root_url(api_root)))
+            .await?;
+        Ok(c.collections.unwrap_or_default())
+    }
+
+    /// poll objects in collection by pages
+    pub fn poll(
+        &self,
+        api_root: impl AsRef<str>,
+        collection_id: impl AsRef<str>,
+        added_after: Option<DateTime<Utc>>,
+    ) -> Objects<'_> {
+        Objects {
+            client: self,
+            url: format!(
+                "{}/collections/{}/objects/",
+                self.api_root_url(api_root),
+                collection_id.as_ref()
+            ),
+            added_after,
+            limit: 100,
+            next: None,
+            has_done: false,
+        }
+    }
+}
+
+impl TaxiiClient {
+    /// objects in collection, fetched page by page lazily
+    pub fn objects(
+        &self,
+        api_root: impl AsRef<str>,
+        collection_id: impl AsRef<str>,
+        added_after: Option<DateTime<Utc>>,
+    ) -> Paginated<'_, serde_json::Value> {
+        let objects = self.poll(api_root, collection_id, added_after);
+        let url = objects.url;
+        Paginated::new(move |cursor| {
+            let mut objects = Objects {
+                client: self,
+                url: url.clone(),
+                added_after,
+                limit: 100,
+                next: None,
+                has_done: false,
+            };
+            if let Some(Cursor::Token(next)) = cursor {
+                objects.next = Some(next);
+            }
+            Box::pin(async move {
+                let items = objects.do_poll().await?;
+                let next = if objects.has_done {
+                    None
+                } else {
+                    objects.next.map(Cursor::Token)
+                };
+                Ok(Page { items, next })
+            })
+        })
+    }
+}
+
+/// context object for objects api
+pub struct Objects<'a> {
+    client: &'a TaxiiClient,
+    url: String,
+    added_after: Option<DateTime<Utc>>,
+    limit: u32,
+    next: Option<String>,
+    has_done: bool,
+}
+
+impl<'a> Objects<'a> {
+    /// set number of objects per a page (server may cap it)
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    fn objects_url(&self) -> String {
+        let mut url = format!("{}?limit={}", self.url, self.limit);
+        if let Some(a) = self.added_after {
+            url.push_str(&format!(
+                "&added_after={}",
+                utf8_percent_encode(&timestamp(a), NON_ALPHANUMERIC)
+            ));
+        }
+        if let Some(n) = &self.next {
+            url.push_str(&format!(
+                "&next={}",
+                utf8_percent_encode(n, NON_ALPHANUMERIC)
+            ));
+        }
+        url
+    }
+
+    /// no more pages?
+    pub fn has_done(&self) -> bool {
+        self.has_done
+    }
+
+    /// request a page
+    pub async fn do_poll(&mut self) -> GenericResult<Vec<serde_json::Value>> {
+        if self.has_done {
+            return Err(TaxiiError::AlreadyReachToEnd.into());
+        }
+
+        let envelope: Envelope = self.client.get(self.objects_url()).await?;
+
+        // it's done if server says no more objects or gives no marker for next page
+        if envelope.more != Some(true) || envelope.next.is_none() {
+            self.has_done = true;
+        }
+        self.next = envelope.next;
+
+        Ok(envelope.objects.unwrap_or_default())
+    }
+
+    /// get all objects (fails if any page fails)
+    pub async fn get_all(mut self) -> GenericResult<Vec<serde_json::Value>> {
+        let mut objects = vec![];
+        while !self.has_done {
+            objects.extend(self.do_poll().await?);
+        }
+        Ok(objects)
+    }
+
+    /// get all indicators in all objects (converted with STIX importer)
+    pub async fn get_all_indicators(self) -> GenericResult<Vec<Indicator>> {
+        Ok(from_stix_objects(self.get_all().await?))
+    }
+}
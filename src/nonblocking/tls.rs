@@ -0,0 +1,164 @@
+//! async client grabbing TLS certificates and JARM (see `iocutil::tls`)
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::fingerprint::Fingerprint;
+use crate::ioc::{host_of_url, Indicator, Ioc};
+use crate::tls::{
+    client_hello, jarm_component, jarm_hash, presented_certificates, TlsError, TlsProbe, PROBES,
+};
+use crate::x509::Certificate;
+use crate::GenericResult;
+
+/// timeout of connections unless `with_timeout`
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// bytes of the response to a probe read for JARM (as the reference implementation)
+const JARM_RESPONSE: usize = 1484;
+
+/// handshake records are not read beyond this
+const MAX_HANDSHAKE: usize = 1 << 20;
+
+/// async client grabbing certificates and JARM of servers (refuses to connect unless `allow_active_probing`)
+#[derive(Clone)]
+pub struct TlsClient {
+    port: u16,
+    allowed: bool,
+    timeout: Duration,
+}
+
+impl Default for TlsClient {
+    fn default() -> Self {
+        TlsClient {
+            port: 443,
+            allowed: false,
+            timeout: TIMEOUT,
+        }
+    }
+}
+
+impl TlsClient {
+    /// client connecting to port 443 (connections are refused until `allow_active_probing`)
+    pub fn new() -> Self {
+        TlsClient::default()
+    }
+
+    /// allow connections, accepting that servers of adversaries may notice them
+    pub fn allow_active_probing(mut self) -> Self {
+        self.allowed = true;
+        self
+    }
+
+    /// port to connect (default 443)
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// timeout of each connection including handshake (default 5 seconds)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn check_allowed(&self) -> GenericResult<()> {
+        if self.allowed {
+            Ok(())
+        } else {
+            Err(TlsError::NotAllowed.into())
+        }
+    }
+
+    /// certificates presented by the server (leaf first)
+    pub async fn certificates(&self, host: impl AsRef<str>) -> GenericResult<Vec<Certificate>> {
+        self.check_allowed()?;
+        let host = host.as_ref();
+        let io = async {
+            let mut stream = TcpStream::connect((host, self.port)).await?;
+            stream.write_all(&client_hello(host, &PROBES[0])).await?;
+            let mut records = vec![];
+            let mut buf = vec![0; 16 * 1024];
+            loop {
+                let n = stream.read(&mut buf).await?;
+                if n == 0 {
+                    return Err(TlsError::Malformed("connection closed in handshake").into());
+                }
+                records.extend(&buf[..n]);
+                if let Some(certificates) = presented_certificates(&records)? {
+                    return Ok(certificates);
+                }
+                if records.len() > MAX_HANDSHAKE {
+                    return Err(TlsError::Malformed("too long handshake").into());
+                }
+            }
+        };
+        tokio::time::timeout(self.timeout, io).await?
+    }
+
+    /// first bytes of the response to a probe
+    async fn exchange(&self, host: &str, hello: &[u8]) -> std::io::Result<Vec<u8>> {
+        let io = async {
+            let mut stream = TcpStream::connect((host, self.port)).await?;
+            stream.write_all(hello).await?;
+            let mut buf = vec![0; JARM_RESPONSE];
+            let n = stream.read(&mut buf).await?;
+            buf.truncate(n);
+            Ok(buf)
+        };
+        tokio::time::timeout(self.timeout, io).await?
+    }
+
+    /// JARM of the server (error if no probe could connect)
+    pub async fn jarm(&self, host: impl AsRef<str>) -> GenericResult<Fingerprint> {
+        self.check_allowed()?;
+        let host = host.as_ref();
+        let mut components = vec![];
+        let mut error = None;
+        for probe in &PROBES {
+            match self.exchange(host, &client_hello(host, probe)).await {
+                Ok(response) => components.push(jarm_component(&response)),
+                Err(e) => {
+                    components.push("|||".to_owned());
+                    error = Some(e);
+                }
+            }
+        }
+        match error {
+            Some(e) if components.iter().all(|x| x == "|||") => Err(e.into()),
+            _ => Ok(Fingerprint::Jarm(jarm_hash(&components.join(",")))),
+        }
+    }
+
+    /// certificates and JARM of the server
+    pub async fn probe(&self, host: impl AsRef<str>) -> GenericResult<TlsProbe> {
+        let host = host.as_ref();
+        let jarm = self.jarm(host).await?;
+        let certificates = match self.certificates(host).await {
+            Ok(x) => x,
+            // e.g. servers speaking only TLS 1.3
+            Err(e) if e.downcast_ref::<TlsError>().is_some() => vec![],
+            Err(e) => return Err(e),
+        };
+        Ok(TlsProbe {
+            host: host.to_owned(),
+            port: self.port,
+            certificates,
+            jarm,
+        })
+    }
+
+    /// probe the domain or IP address (or host of URL) of indicator (`None` for other kinds)
+    pub async fn probe_indicator(&self, indicator: &Indicator) -> GenericResult<Option<TlsProbe>> {
+        let host = match &indicator.ioc {
+            Ioc::Url(x) => host_of_url(x),
+            x => Some(x.clone()),
+        };
+        match host {
+            Some(Ioc::Domain(x)) => Ok(Some(self.probe(x).await?)),
+            Some(Ioc::IpAddress(x)) => Ok(Some(self.probe(x.to_string()).await?)),
+            _ => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! async expander of shortened URLs (see `iocutil::unshorten`)
+
+use reqwest::header::{LOCATION, USER_AGENT};
+use reqwest::{redirect, Url};
+use std::time::{Duration, Instant};
+
+use crate::http::{self, ProxyConfig};
+use crate::retry::RetryPolicy;
+use crate::unshorten::{is_shortened, ChainEnd, RedirectChain, UnshortenError};
+use crate::GenericResult;
+
+const PROVIDER: &str = "unshorten";
+
+/// async expander of shortened URLs (refuses to send requests unless `allow_active_probing`)
+#[derive(Clone)]
+pub struct UrlExpander {
+    allowed: bool,
+    only_shorteners: bool,
+    client: reqwest::Client,
+    user_agent: Option<String>,
+    max_hops: usize,
+    hop_timeout: Duration,
+    total_timeout: Duration,
+}
+
+fn expander_client(builder: reqwest::ClientBuilder) -> GenericResult<reqwest::Client> {
+    Ok(builder.redirect(redirect::Policy::none()).build()?)
+}
+
+impl Default for UrlExpander {
+    fn default() -> Self {
+        UrlExpander {
+            allowed: false,
+            only_shorteners: false,
+            client: expander_client(http::client_builder()).expect("failed to build HTTP client"),
+            user_agent: None,
+            max_hops: 10,
+            hop_timeout: Duration::from_secs(5),
+            total_timeout: Duration::from_secs(20),
+        }
+    }
+}
+
+impl UrlExpander {
+    /// expander following 10 hops at most, 5 seconds per hop and 20 seconds in total
+    /// (requests are refused until `allow_active_probing`)
+    pub fn new() -> Self {
+        UrlExpander::default()
+    }
+
+    /// allow requests, accepting that servers of adversaries may notice them
+    pub fn allow_active_probing(mut self) -> Self {
+        self.allowed = true;
+        self
+    }
+
+    /// stop before the first hop outside known shorteners (it is not requested)
+    pub fn only_shorteners(mut self) -> Self {
+        self.only_shorteners = true;
+        self
+    }
+
+    /// use the HTTP client (redirects should be disabled in it, as the expander follows them)
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// send requests via the proxy
+    pub fn with_proxy(self, proxy: &ProxyConfig) -> GenericResult<Self> {
+        let client = expander_client(proxy.apply(http::client_builder())?)?;
+        Ok(self.with_client(client))
+    }
+
+    /// `User-Agent` of requests (default `iocutil.rs/<version>`)
+    pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Self {
+        self.user_agent = Some(user_agent.as_ref().to_owned());
+        self
+    }
+
+    /// redirects followed at most (default 10)
+    pub fn with_max_hops(mut self, max: usize) -> Self {
+        self.max_hops = max;
+        self
+    }
+
+    /// timeout of each request (default 5 seconds)
+    pub fn with_hop_timeout(mut self, timeout: Duration) -> Self {
+        self.hop_timeout = timeout;
+        self
+    }
+
+    /// cap of total time of expansion (default 20 seconds, the chain so far is returned)
+    pub fn with_total_timeout(mut self, timeout: Duration) -> Self {
+        self.total_timeout = timeout;
+        self
+    }
+
+    /// status and redirected URL of the hop (bodies are not read)
+    async fn hop(&self, url: &Url, timeout: Duration) -> GenericResult<(u16, Option<Url>)> {
+        let mut req = self.client.get(url.clone());
+        if let Some(ua) = &self.user_agent {
+            req = req.header(USER_AGENT, ua);
+        }
+        let res = http::send(PROVIDER, req, &None, &RetryPolicy::none(), Some(timeout)).await?;
+        let next = res
+            .headers()
+            .get(LOCATION)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| url.join(x).ok())
+            .filter(|_| res.status().is_redirection());
+        Ok((res.status().as_u16(), next))
+    }
+
+    /// redirect chain of the URL
+    pub async fn expand(&self, url: impl AsRef<str>) -> GenericResult<RedirectChain> {
+        if !self.allowed {
+            return Err(UnshortenError::NotAllowed.into());
+        }
+        let first = Url::parse(url.as_ref())?;
+        let started = Instant::now();
+        let mut chain = RedirectChain {
+            url: first.to_string(),
+            hops: vec![],
+            statuses: vec![],
+            end: ChainEnd::Resolved,
+        };
+        let mut url = first;
+        loop {
+            if self.only_shorteners && !is_shortened(url.as_str()) {
+                chain.end = ChainEnd::LeftShorteners;
+                break;
+            }
+            let left = match self.total_timeout.checked_sub(started.elapsed()) {
+                Some(x) if !x.is_zero() => x,
+                _ => {
+                    chain.end = ChainEnd::TimedOut;
+                    break;
+                }
+            };
+            let (status, next) = match self.hop(&url, self.hop_timeout.min(left)).await {
+                Ok(x) => x,
+                // the total cap elapsed while waiting for the hop
+                Err(_) if started.elapsed() >= self.total_timeout => {
+                    chain.end = ChainEnd::TimedOut;
+                    break;
+                }
+                Err(_) if !chain.hops.is_empty() => {
+                    chain.end = ChainEnd::Unreachable;
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            chain.statuses.push(status);
+            let next = match next {
+                Some(x) => x,
+                None => break,
+            };
+            if chain.urls().any(|x| x == next.as_str()) {
+                chain.end = ChainEnd::Loop;
+                break;
+            }
+            chain.hops.push(next.to_string());
+            if chain.hops.len() >= self.max_hops {
+                chain.end = ChainEnd::MaxHops;
+                break;
+            }
+            url = next;
+        }
+        Ok(chain)
+    }
+}
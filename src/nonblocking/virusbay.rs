@@ -0,0 +1,82 @@
+//! async client for VirusBay API (exprimental)
+
+use std::convert::TryInto;
+
+use crate::cache;
+use crate::error::Error;
+use crate::http::{self, ClientConfig};
+use crate::singleflight::SingleFlight;
+use crate::util::unwrap_try_into;
+use crate::virusbay::{Response, SearchResult};
+use crate::{GenericResult, SampleHash};
+
+const PROVIDER: &str = "virusbay";
+
+/// async client for VirusBay API
+#[derive(Clone)]
+pub struct VirusBayClient {
+    config: ClientConfig,
+    flights: SingleFlight,
+}
+
+impl Default for VirusBayClient {
+    fn default() -> Self {
+        VirusBayClient {
+            config: ClientConfig::default(),
+            flights: SingleFlight::new(),
+        }
+    }
+}
+
+impl VirusBayClient {
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// search result as text (served from cache if any)
+    async fn get(&self, hash: impl TryInto<SampleHash>) -> GenericResult<String> {
+        let hash = unwrap_try_into(hash)?;
+        let url = format!("https://beta.virusbay.io/sample/search?q={}", hash.as_ref());
+        cache::cached(
+            &self.config.cache,
+            &self.flights,
+            PROVIDER,
+            "sample/search",
+            hash.as_ref(),
+            async {
+                let res = self
+                    .config
+                    .send(PROVIDER, self.config.client.get(url.as_str()))
+                    .await?;
+                Ok(http::check(PROVIDER, res)?.text().await?)
+            },
+        )
+        .await
+    }
+
+    /// get raw json
+    pub async fn get_raw_json(&self, hash: impl TryInto<SampleHash>) -> GenericResult<String> {
+        self.get(hash).await
+    }
+
+    /// query a sample (free format)
+    pub async fn query<T>(&self, hash: impl TryInto<SampleHash>) -> GenericResult<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Ok(serde_json::from_str(&self.get(hash).await?)?)
+    }
+
+    /// query a sample (formatted)
+    /// there are no guarantee for correctness of format.
+    pub async fn fquery(&self, hash: impl TryInto<SampleHash>) -> GenericResult<Vec<SearchResult>> {
+        let r: Response = self.query(hash).await?;
+        if r.search.is_empty() {
+            return Err(Error::NotFound { provider: PROVIDER }.into());
+        }
+
+        Ok(r.search)
+    }
+}
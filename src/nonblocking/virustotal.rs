@@ -0,0 +1,390 @@
+//! async client for VirusTotal API
+
+use chrono::Utc;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::convert::TryInto;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::cache;
+use crate::http::{self, ClientConfig};
+use crate::keys::{ApiKeyProvider, DefaultKeys};
+use crate::paginate::{Cursor, Page, Paginated};
+use crate::singleflight::SingleFlight;
+use crate::util::unwrap_try_into;
+use crate::virustotal::{scan_id, FileReport, RawFileReport, SearchResponse, VTError};
+use crate::{GenericResult, SampleHash};
+
+const PROVIDER: &str = "virustotal";
+
+/// async client for VirusTotal API (default use `$VTAPIKEY` environment variable as apikey)
+#[derive(Clone)]
+pub struct VirusTotalClient {
+    apikey: String,
+    config: ClientConfig,
+    flights: SingleFlight,
+}
+
+impl VirusTotalClient {
+    /// new client with apikey
+    pub fn new(apikey: impl AsRef<str>) -> Self {
+        VirusTotalClient {
+            apikey: apikey.as_ref().to_owned(),
+            config: ClientConfig::default(),
+            flights: SingleFlight::new(),
+        }
+    }
+
+    /// new client with apikey of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(VirusTotalClient::new(keys.require(PROVIDER)?))
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`, quota exceeded `204` is retried
+    /// as well)
+    pub fn with_config(mut self, mut config: ClientConfig) -> Self {
+        config.retry = config.retry.retry_status(204);
+        self.config = config;
+        self
+    }
+
+    fn file_report_url(&self, resource: impl AsRef<str>, allinfo: bool) -> String {
+        format!(
+            "https://www.virustotal.com/vtapi/v2/file/report?apikey={}&allinfo={}&resource={}",
+            self.apikey,
+            allinfo,
+            resource.as_ref()
+        )
+    }
+
+    fn download_url(&self, hash: impl AsRef<str>) -> String {
+        format!(
+            "https://www.virustotal.com/vtapi/v2/file/download?apikey={}&hash={}",
+            self.apikey,
+            hash.as_ref()
+        )
+    }
+
+    async fn get_report(
+        &self,
+        resource: impl AsRef<str>,
+        allinfo: bool,
+    ) -> GenericResult<reqwest::Response> {
+        let res = self
+            .config
+            .send(
+                PROVIDER,
+                self.config
+                    .client
+                    .get(self.file_report_url(resource, allinfo).as_str()),
+            )
+            .await?;
+        Ok(http::check(PROVIDER, res)?)
+    }
+
+    /// file report as text (served from cache if any)
+    async fn report_text(&self, resource: impl AsRef<str>, allinfo: bool) -> GenericResult<String> {
+        let resource = resource.as_ref();
+        let endpoint = if allinfo {
+            "file/report?allinfo"
+        } else {
+            "file/report"
+        };
+        cache::cached(
+            &self.config.cache,
+            &self.flights,
+            PROVIDER,
+            endpoint,
+            resource,
+            async { Ok(self.get_report(resource, allinfo).await?.text().await?) },
+        )
+        .await
+    }
+
+    pub(crate) async fn internal_query<T>(
+        &self,
+        resource: impl AsRef<str>,
+        allinfo: bool,
+    ) -> GenericResult<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Ok(serde_json::from_str(
+            &self.report_text(resource, allinfo).await?,
+        )?)
+    }
+
+    /// get file report of VirusTotal (with allinfo option)
+    pub async fn query_filereport_allinfo<T>(&self, resource: impl AsRef<str>) -> GenericResult<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.internal_query(resource, true).await
+    }
+
+    /// get raw filereport as text
+    pub async fn get_raw_filereport_json(
+        &self,
+        resource: impl AsRef<str>,
+        allinfo: bool,
+    ) -> GenericResult<String> {
+        self.report_text(resource, allinfo).await
+    }
+
+    /// get raw filereport json at specified datetime
+    pub async fn get_raw_filereport_json_at(
+        &self,
+        hash: impl TryInto<SampleHash>,
+        allinfo: bool,
+        datetime: chrono::DateTime<Utc>,
+    ) -> GenericResult<String> {
+        let hash = unwrap_try_into(hash)?;
+        let r = scan_id(hash, datetime);
+        self.get_raw_filereport_json(r, allinfo).await
+    }
+
+    /// query file report at specified datetime
+    pub async fn query_filereport_at(
+        &self,
+        hash: impl TryInto<SampleHash>,
+        datetime: chrono::DateTime<Utc>,
+    ) -> GenericResult<FileReport> {
+        let hash = unwrap_try_into(hash)?;
+        let r = scan_id(hash, datetime);
+        self.query_filereport(r).await
+    }
+
+    /// query file report (without allinfo)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::nonblocking::VirusTotalClient;
+    ///
+    /// # async fn run() {
+    /// let client = VirusTotalClient::default();
+    /// let report = client.query_filereport("d41d8cd98f00b204e9800998ecf8427e").await.unwrap();
+    /// # }
+    /// ```
+    pub async fn query_filereport(&self, resource: impl AsRef<str>) -> GenericResult<FileReport> {
+        let report: RawFileReport = self.internal_query(resource, false).await?;
+        Ok(report.try_into()?)
+    }
+
+    /// batch query file report (with allinfo option)
+    pub async fn batch_query_allinfo<T>(
+        &self,
+        resources: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Vec<GenericResult<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut results = vec![];
+        for (idx, item) in resources.into_iter().enumerate() {
+            if idx != 0 {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            results.push(self.query_filereport_allinfo(item).await);
+        }
+        results
+    }
+
+    /// batch query file report
+    pub async fn batch_query(
+        &self,
+        resources: impl IntoIterator<Item = impl AsRef<str>>,
+        public_api: bool,
+    ) -> Vec<GenericResult<FileReport>> {
+        let sleeptime = if public_api {
+            Duration::from_secs(15)
+        } else {
+            Duration::from_secs(1)
+        };
+        let mut results = vec![];
+        for (idx, item) in resources.into_iter().enumerate() {
+            if idx != 0 {
+                tokio::time::sleep(sleeptime).await;
+            }
+            results.push(self.query_filereport(item).await);
+        }
+        results
+    }
+
+    /// download a file from hash
+    pub async fn download(
+        &self,
+        hash: impl TryInto<SampleHash>,
+        into: impl AsRef<std::path::Path>,
+    ) -> GenericResult<()> {
+        let h = unwrap_try_into(hash)?;
+        let h = h.as_ref();
+
+        let res = self
+            .config
+            .send(
+                PROVIDER,
+                self.config.client.get(self.download_url(h).as_str()),
+            )
+            .await?;
+        let mut res = http::check(PROVIDER, res)?;
+
+        let mut f = std::fs::File::create(into)?;
+        while let Some(chunk) = res.chunk().await? {
+            f.write_all(&chunk)?;
+        }
+
+        Ok(())
+    }
+
+    /// search by page (Private API required)
+    pub fn search_by_pages(&self, query: impl AsRef<str>, goal: Option<usize>) -> Search {
+        let mut search = Search::new(&self.apikey, query, goal);
+        search.config = self.config.clone();
+        search
+    }
+
+    /// search samples page by page lazily (Private API required)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use futures::StreamExt;
+    /// use iocutil::nonblocking::VirusTotalClient;
+    ///
+    /// # async fn run() {
+    /// let client = VirusTotalClient::default();
+    /// let mut hashes = client.search_paginated("type:peexe positives:5+").limit(1000);
+    /// while let Some(hash) = hashes.next().await {
+    ///     println!("{}", hash.unwrap());
+    /// }
+    /// # }
+    /// ```
+    pub fn search_paginated(&self, query: impl AsRef<str>) -> Paginated<'_, SampleHash> {
+        let query = query.as_ref().to_owned();
+        Paginated::new(move |cursor| {
+            let mut search = self.search_by_pages(&query, None);
+            if let Some(Cursor::Token(offset)) = cursor {
+                search.offset = Some(offset);
+            }
+            Box::pin(async move {
+                let items = search.do_search().await?;
+                Ok(Page {
+                    items,
+                    next: search.offset.map(Cursor::Token),
+                })
+            })
+        })
+    }
+
+    /// search samples (Private API required)
+    pub async fn search<T>(&self, query: impl AsRef<str>, goal: Option<usize>) -> T
+    where
+        T: std::iter::FromIterator<SampleHash>,
+    {
+        let mut pages = self.search_by_pages(query, goal);
+        let mut hashes: Vec<SampleHash> = vec![];
+        while let Ok(page) = pages.do_search::<Vec<_>>().await {
+            hashes.extend(page);
+        }
+        hashes.into_iter().collect()
+    }
+}
+
+impl Default for VirusTotalClient {
+    fn default() -> Self {
+        VirusTotalClient::from_keys(DefaultKeys).expect(
+            "please set VirusTotal API key to environment var $VTAPIKEY or ~/.config/iocutil/config.toml",
+        )
+    }
+}
+
+/// context object for search api
+pub struct Search {
+    apikey: String,
+    query: String,
+    goal: Option<usize>,
+    offset: Option<String>,
+    current: usize,
+    has_done: bool,
+    config: ClientConfig,
+}
+
+impl Search {
+    /// create new object
+    pub fn new(apikey: impl AsRef<str>, query: impl AsRef<str>, goal: Option<usize>) -> Self {
+        Search {
+            apikey: apikey.as_ref().to_owned(),
+            query: utf8_percent_encode(query.as_ref(), NON_ALPHANUMERIC).to_string(),
+            offset: None,
+            current: 0,
+            has_done: false,
+            goal,
+            config: ClientConfig::default(),
+        }
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`, quota exceeded `204` is retried
+    /// as well)
+    pub fn with_config(mut self, mut config: ClientConfig) -> Self {
+        config.retry = config.retry.retry_status(204);
+        self.config = config;
+        self
+    }
+
+    fn search_url(&self, offset: &Option<String>) -> String {
+        match offset {
+            Some(o) => format!(
+                "https://www.virustotal.com/vtapi/v2/file/search?apikey={}&query={}&offset={}",
+                self.apikey.as_str(),
+                self.query.as_str(),
+                o,
+            ),
+            None => format!(
+                "https://www.virustotal.com/vtapi/v2/file/search?apikey={}&query={}",
+                self.apikey.as_str(),
+                self.query.as_str(),
+            ),
+        }
+    }
+
+    /// request once (most 300 samples per a page)
+    pub async fn do_search<T>(&mut self) -> GenericResult<T>
+    where
+        T: std::iter::FromIterator<SampleHash>,
+    {
+        if self.has_done {
+            return Err(VTError::AlreadyReachToGoal.into());
+        }
+
+        let url = self.search_url(&self.offset);
+
+        let res = self
+            .config
+            .send(PROVIDER, self.config.client.get(url.as_str()))
+            .await?;
+
+        let result: SearchResponse = http::check(PROVIDER, res)?.json().await?;
+        if result.response_code != 1 {
+            return Err(VTError::ResponseCodeError(result.response_code).into());
+        }
+
+        let hashes = result
+            .hashes
+            .ok_or_else(|| VTError::MissingFields("hashes".to_string()))?;
+
+        if let Some(x) = self.goal {
+            self.current += hashes.len();
+            if x <= self.current {
+                self.has_done = true;
+            }
+        }
+
+        if result.offset.is_none() {
+            self.has_done = true;
+        }
+
+        self.offset = result.offset;
+
+        SampleHash::try_map(hashes)
+    }
+}
@@ -0,0 +1,198 @@
+//! async client of RDAP (WHOIS as fallback)
+
+use chrono::{Duration, Utc};
+use reqwest::StatusCode;
+use std::net::IpAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::domain::DomainName;
+use crate::http::{self, ClientConfig};
+use crate::ioc::{host_of_url, Indicator, Ioc};
+use crate::psl;
+use crate::whois::{Registration, NEWLY_REGISTERED_TAG};
+use crate::GenericResult;
+
+const PROVIDER: &str = "rdap";
+
+/// WHOIS server referring to the servers of TLDs and RIRs
+const IANA_WHOIS: &str = "whois.iana.org";
+
+/// timeout of WHOIS queries unless set by `with_config`
+const WHOIS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// WHOIS responses are truncated to this
+const MAX_WHOIS_RESPONSE: u64 = 1 << 20;
+
+/// async client of RDAP (WHOIS as fallback)
+#[derive(Clone)]
+pub struct WhoisClient {
+    rdap_base: String,
+    whois: bool,
+    window: Duration,
+    config: ClientConfig,
+}
+
+impl Default for WhoisClient {
+    fn default() -> Self {
+        WhoisClient {
+            rdap_base: "https://rdap.org".to_owned(),
+            whois: true,
+            window: Duration::days(30),
+            config: ClientConfig::default(),
+        }
+    }
+}
+
+/// WHOIS server referred in response (`refer: whois.verisign-grs.com` and so on)
+fn referral(text: &str, keys: &[&str]) -> Option<String> {
+    text.lines()
+        .filter_map(|x| x.split_once(':'))
+        .find(|(k, _)| keys.contains(&k.trim().to_lowercase().as_str()))
+        .map(|(_, v)| v.trim().trim_start_matches("whois://").to_lowercase())
+        // e.g. `rwhois://rwhois.example.net:4321` is not WHOIS
+        .filter(|x| !x.is_empty() && !x.contains("://"))
+}
+
+impl WhoisClient {
+    pub fn new() -> Self {
+        WhoisClient::default()
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`, timeout applies to WHOIS queries
+    /// too, which time out in 10 seconds by default, while others are for RDAP only)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// base URL of RDAP bootstrap service (default `https://rdap.org`)
+    pub fn with_rdap_base(mut self, base: impl AsRef<str>) -> Self {
+        self.rdap_base = base.as_ref().trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// do not fall back to WHOIS (e.g. where port 43 is blocked)
+    pub fn without_whois(mut self) -> Self {
+        self.whois = false;
+        self
+    }
+
+    /// domains registered within the window are newly registered (default 30 days)
+    pub fn with_newly_registered_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// registration by RDAP (None if not found)
+    async fn rdap(&self, path: String) -> GenericResult<Option<Registration>> {
+        let url = format!("{}/{}", self.rdap_base, path);
+        let res = self
+            .config
+            .send(
+                PROVIDER,
+                self.config
+                    .client
+                    .get(&url)
+                    .header(reqwest::header::ACCEPT, "application/rdap+json"),
+            )
+            .await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let json: serde_json::Value = http::check(PROVIDER, res)?.json().await?;
+        Ok(Some(Registration::from_rdap(&json)).filter(|x| !x.is_empty()))
+    }
+
+    /// raw response of WHOIS server
+    async fn query(&self, server: &str, query: &str) -> GenericResult<String> {
+        let io = async {
+            let mut stream = TcpStream::connect((server, 43)).await?;
+            stream
+                .write_all(format!("{}\r\n", query).as_bytes())
+                .await?;
+            let mut buf = vec![];
+            stream
+                .take(MAX_WHOIS_RESPONSE)
+                .read_to_end(&mut buf)
+                .await?;
+            Ok::<_, std::io::Error>(String::from_utf8_lossy(&buf).into_owned())
+        };
+        Ok(tokio::time::timeout(self.config.timeout.unwrap_or(WHOIS_TIMEOUT), io).await??)
+    }
+
+    /// registration by WHOIS, following referrals from IANA (and from thin registries to registrars)
+    async fn whois(&self, query: &str) -> GenericResult<Option<Registration>> {
+        let iana = self.query(IANA_WHOIS, query).await?;
+        let server = match referral(&iana, &["refer", "whois"]) {
+            Some(x) => x,
+            None => return Ok(Some(Registration::from_whois(&iana)).filter(|x| !x.is_empty())),
+        };
+
+        let text = self.query(&server, query).await?;
+        let mut registration = Registration::from_whois(&text);
+        if let Some(next) = referral(&text, &["registrar whois server", "referralserver"]) {
+            if next != server {
+                // registrars answer in more detail, but thin registries already answered
+                if let Ok(text) = self.query(&next, query).await {
+                    registration = registration.merge(Registration::from_whois(&text));
+                }
+            }
+        }
+        Ok(Some(registration).filter(|x| !x.is_empty()))
+    }
+
+    /// RDAP, and WHOIS if RDAP failed or did not find it
+    async fn lookup(&self, path: String, query: &str) -> GenericResult<Option<Registration>> {
+        match self.rdap(path).await {
+            Ok(Some(x)) => Ok(Some(x)),
+            _ if self.whois => self.whois(query).await,
+            other => other,
+        }
+    }
+
+    /// registration of the (registrable) domain (`None` if not registered)
+    pub async fn domain(&self, domain: impl AsRef<str>) -> GenericResult<Option<Registration>> {
+        let domain = DomainName::new(domain)?;
+        let domain =
+            psl::registrable_domain(domain.ascii()).unwrap_or_else(|| domain.ascii().to_owned());
+        self.lookup(format!("domain/{}", domain), &domain).await
+    }
+
+    /// registration of the network of IP address
+    pub async fn ip(&self, ip: IpAddr) -> GenericResult<Option<Registration>> {
+        self.lookup(format!("ip/{}", ip), &ip.to_string()).await
+    }
+
+    /// annotate a domain or IP address (or URL / email address on them) with `registration`
+    /// of metadata, and tag newly registered domains. returns true if found.
+    pub async fn enrich(&self, indicator: &mut Indicator) -> GenericResult<bool> {
+        let host = match &indicator.ioc {
+            Ioc::Url(x) => host_of_url(x),
+            Ioc::EmailAddress(x) => x.rsplit('@').next().and_then(|x| Ioc::new(x).ok()),
+            x => Some(x.clone()),
+        };
+        let (registration, is_domain) = match host {
+            Some(Ioc::Domain(x)) => (self.domain(x).await?, true),
+            Some(Ioc::IpAddress(x)) => (self.ip(x).await?, false),
+            _ => return Ok(false),
+        };
+        let registration = match registration {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+
+        let meta = &mut indicator.meta;
+        if is_domain
+            && registration.is_newly_registered(Utc::now(), self.window)
+            && !meta.tags.iter().any(|x| x == NEWLY_REGISTERED_TAG)
+        {
+            meta.tags.push(NEWLY_REGISTERED_TAG.to_owned());
+        }
+        meta.registration = Some(match meta.registration.take() {
+            Some(old) => old.merge(registration),
+            None => registration,
+        });
+        Ok(true)
+    }
+}
@@ -0,0 +1,273 @@
+//! OpenIOC 1.x import and export
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use derive_builder::Builder;
+use std::net::IpAddr;
+
+use crate::allowlist::suppress;
+use crate::ioc::{is_domain, is_email_address, is_url, Indicator, Ioc, Metadata};
+use crate::ttl::unexpired;
+use crate::util::{deterministic_uuid, escape_xml};
+use crate::{GenericResult, SampleHash};
+
+fn parse_datetime(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(d) = DateTime::parse_from_rfc3339(text) {
+        return Some(d.into());
+    }
+    let naive = NaiveDateTime::parse_from_str(text.trim_end_matches('Z'), "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f"))
+        .ok()?;
+    Some(DateTime::from_utc(naive, Utc))
+}
+
+/// indicator of an IndicatorItem (only widely used search terms are supported)
+fn item_to_ioc(search: &str, content: &str) -> Option<Ioc> {
+    let content = content.trim();
+    let ioc = match search {
+        s if s.ends_with("Md5sum") || s.ends_with("Sha1sum") || s.ends_with("Sha256sum") => {
+            Ioc::Hash(SampleHash::new(content).ok()?)
+        }
+        "DnsEntryItem/Host"
+        | "Network/DNS"
+        | "UrlHistoryItem/HostName"
+        | "PortItem/remoteHostname" => {
+            if !is_domain(content) {
+                return None;
+            }
+            Ioc::Domain(content.trim_end_matches('.').to_lowercase())
+        }
+        "UrlHistoryItem/URL" | "Network/URL" => {
+            if !is_url(content) {
+                return None;
+            }
+            Ioc::Url(content.to_owned())
+        }
+        "PortItem/remoteIP" | "DnsEntryItem/RecordData/IPv4Address" | "Network/IP" => {
+            Ioc::IpAddress(content.parse().ok()?)
+        }
+        "Email/From" | "Email/To" | "Email/ReplyTo" | "Email/Sender" => {
+            // "name <address>" style is also accepted
+            let address = match (content.rfind('<'), content.rfind('>')) {
+                (Some(s), Some(e)) if s < e => &content[s + 1..e],
+                _ => content,
+            };
+            if !is_email_address(address) {
+                return None;
+            }
+            Ioc::EmailAddress(address.to_lowercase())
+        }
+        _ => return None,
+    };
+    Some(ioc)
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children()
+        .find(|x| x.is_element() && x.tag_name().name() == name)
+}
+
+fn walk(node: roxmltree::Node, base: &Metadata, out: &mut Vec<Indicator>) {
+    let operator = node.attribute("operator").unwrap_or("OR").to_uppercase();
+    let group = node.attribute("id").unwrap_or_default();
+
+    for c in node.children().filter(|x| x.is_element()) {
+        match c.tag_name().name() {
+            "Indicator" => walk(c, base, out),
+            "IndicatorItem" => {
+                // only equality conditions are evaluated
+                if c.attribute("condition") != Some("is") {
+                    continue;
+                }
+                let search = child(c, "Context")
+                    .and_then(|x| x.attribute("search"))
+                    .unwrap_or_default();
+                let content = child(c, "Content")
+                    .and_then(|x| x.text())
+                    .unwrap_or_default();
+
+                if let Some(ioc) = item_to_ioc(search, content) {
+                    let mut meta = base.clone();
+                    meta.tags.push(format!("openioc:operator={}", operator));
+                    meta.tags.push(format!("openioc:indicator={}", group));
+                    out.push(Indicator::with_meta(ioc, meta));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// read indicators from OpenIOC 1.x document
+/// The tree is flattened, and parent operator and id of Indicator are kept in tags
+/// (`openioc:operator=AND`, `openioc:indicator=<id>`).
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::openioc::from_openioc;
+///
+/// let xml = r#"<?xml version="1.0" encoding="us-ascii"?>
+/// <ioc id="7f6c5e56-0fd3-4f3e-a2e1-4e6b1d0e4b55" last-modified="2013-02-05T16:32:10" xmlns="http://schemas.mandiant.com/2010/ioc">
+///   <short_description>Example</short_description>
+///   <definition>
+///     <Indicator operator="OR" id="a">
+///       <IndicatorItem id="b" condition="is">
+///         <Context document="FileItem" search="FileItem/Md5sum" type="mir" />
+///         <Content type="md5">d41d8cd98f00b204e9800998ecf8427e</Content>
+///       </IndicatorItem>
+///       <Indicator operator="AND" id="c">
+///         <IndicatorItem id="d" condition="is">
+///           <Context document="DnsEntryItem" search="DnsEntryItem/Host" type="mir" />
+///           <Content type="string">evil.example.com</Content>
+///         </IndicatorItem>
+///         <IndicatorItem id="e" condition="contains">
+///           <Context document="FileItem" search="FileItem/FileName" type="mir" />
+///           <Content type="string">payload</Content>
+///         </IndicatorItem>
+///       </Indicator>
+///     </Indicator>
+///   </definition>
+/// </ioc>"#;
+///
+/// let indicators = from_openioc(xml).unwrap();
+/// assert_eq!(indicators.len(), 2);
+/// assert_eq!(indicators[1].ioc, Ioc::new("evil.example.com").unwrap());
+/// assert!(indicators[1].meta.tags.contains(&"openioc:operator=AND".to_owned()));
+/// assert_eq!(indicators[1].meta.source.as_ref().unwrap(), "Example");
+/// ```
+pub fn from_openioc(xml: impl AsRef<str>) -> GenericResult<Vec<Indicator>> {
+    let doc = roxmltree::Document::parse(xml.as_ref())?;
+    let root = doc.root_element();
+
+    let text_of = |name: &str| {
+        child(root, name)
+            .and_then(|x| x.text())
+            .map(|x| x.trim().to_owned())
+    };
+
+    let base = Metadata {
+        first_seen: text_of("authored_date").and_then(|x| parse_datetime(&x)),
+        last_seen: root.attribute("last-modified").and_then(parse_datetime),
+        source: text_of("short_description"),
+        description: text_of("description"),
+        ..Default::default()
+    };
+
+    let mut out = vec![];
+    if let Some(definition) = child(root, "definition") {
+        walk(definition, &base, &mut out);
+    }
+    Ok(out)
+}
+
+/// search term, document, and content type of an indicator
+fn item_of(ioc: &Ioc) -> (&'static str, &'static str, &'static str) {
+    match ioc {
+        Ioc::Hash(SampleHash::Md5(_)) => ("FileItem", "FileItem/Md5sum", "md5"),
+        Ioc::Hash(SampleHash::Sha1(_)) => ("FileItem", "FileItem/Sha1sum", "string"),
+        Ioc::Hash(SampleHash::Sha256(_)) => ("FileItem", "FileItem/Sha256sum", "string"),
+        Ioc::Domain(_) => ("DnsEntryItem", "DnsEntryItem/Host", "string"),
+        Ioc::Url(_) => ("UrlHistoryItem", "UrlHistoryItem/URL", "string"),
+        Ioc::IpAddress(IpAddr::V4(_)) | Ioc::IpAddress(IpAddr::V6(_)) => {
+            ("PortItem", "PortItem/remoteIP", "IP")
+        }
+        Ioc::EmailAddress(_) => ("Email", "Email/From", "string"),
+    }
+}
+
+/// options of OpenIOC export
+#[derive(Builder, Debug, Clone)]
+pub struct OpenIocOptions {
+    #[builder(setter(into), default = "\"generated by iocutil.rs\".to_owned()")]
+    short_description: String,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for OpenIocOptions {
+    fn default() -> Self {
+        OpenIocOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// export indicators as flat OpenIOC 1.x document (all items are joined with OR)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::openioc::{from_openioc, to_openioc, OpenIocOptionsBuilder};
+///
+/// let iocs = vec![
+///     Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+///     Ioc::new("http://evil.example.com/?a=1&b=2").unwrap(),
+/// ];
+///
+/// let options = OpenIocOptionsBuilder::default()
+///     .short_description("example campaign")
+///     .build()
+///     .unwrap();
+/// let xml = to_openioc(iocs.clone(), &options);
+/// assert!(xml.contains("<short_description>example campaign</short_description>"));
+/// let imported: Vec<_> = from_openioc(xml).unwrap().into_iter().map(|x| x.ioc).collect();
+/// assert_eq!(imported, iocs);
+///
+/// // expired indicators are excluded
+/// let mut expired = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// expired.meta.valid_until = Some(day!(2019, 11, 1).unwrap().into());
+/// assert!(!to_openioc(vec![expired], &options).contains("192.0.2.1"));
+/// ```
+pub fn to_openioc(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &OpenIocOptions,
+) -> String {
+    let now = Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+
+    let items: Vec<_> = suppress(unexpired(indicators, options.include_expired))
+        .into_iter()
+        .map(|i| {
+            let (document, search, t) = item_of(&i.ioc);
+            let value = i.ioc.value();
+            format!(
+                r#"      <IndicatorItem id="{}" condition="is">
+        <Context document="{}" search="{}" type="mir" />
+        <Content type="{}">{}</Content>
+      </IndicatorItem>
+"#,
+                deterministic_uuid(format!("{}:{}", search, value)),
+                document,
+                search,
+                t,
+                escape_xml(value)
+            )
+        })
+        .collect();
+
+    let id = deterministic_uuid(items.concat());
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<ioc xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" id="{id}" last-modified="{now}" xmlns="http://schemas.mandiant.com/2010/ioc">
+  <short_description>{desc}</short_description>
+  <authored_by>iocutil.rs</authored_by>
+  <authored_date>{now}</authored_date>
+  <links />
+  <definition>
+    <Indicator operator="OR" id="{root}">
+{items}    </Indicator>
+  </definition>
+</ioc>
+"#,
+        id = id,
+        now = now,
+        desc = escape_xml(&options.short_description),
+        root = deterministic_uuid(format!("{}:root", id)),
+        items = items.concat(),
+    )
+}
@@ -0,0 +1,156 @@
+//! pagination of APIs as `Stream` (async) and `Iterator` (blocking)
+//!
+//! Providers paginate differently (page numbers, offsets or cursor tokens), and
+//! `Paginated` hides it. Pages are fetched lazily when items of previous ones are consumed.
+
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::http::block_on;
+use crate::GenericResult;
+
+/// boxed future of a page
+pub type PageFuture<'a, T> = Pin<Box<dyn Future<Output = GenericResult<Page<T>>> + Send + 'a>>;
+
+/// position of next page
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Cursor {
+    /// page number
+    Page(u32),
+    /// offset / cursor token given by provider
+    Token(String),
+}
+
+/// items in a page and position of next one (`None` if it is the last page)
+#[derive(Clone, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<Cursor>,
+}
+
+type Fetcher<'a, T> = Box<dyn FnMut(Option<Cursor>) -> PageFuture<'a, T> + Send + 'a>;
+
+/// items of paginated API
+///
+/// As `Stream` or `Iterator`, it yields items (or an error, after which it ends).
+/// Do not use it as `Iterator` in async context.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::paginate::{Cursor, Page, Paginated};
+///
+/// // pages of [0, 1, 2], [3, 4, 5], [6, 7]
+/// let pages = Paginated::new(|cursor| {
+///     Box::pin(async move {
+///         let n = match cursor {
+///             Some(Cursor::Page(n)) => n,
+///             _ => 0,
+///         };
+///         let items: Vec<u32> = (n * 3..(n * 3 + 3).min(8)).collect();
+///         let next = if n < 2 { Some(Cursor::Page(n + 1)) } else { None };
+///         Ok(Page { items, next })
+///     })
+/// });
+///
+/// let items: Vec<u32> = pages.map(|x| x.unwrap()).collect();
+/// assert_eq!(items, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+/// ```
+pub struct Paginated<'a, T> {
+    fetch: Fetcher<'a, T>,
+    next: Option<Cursor>,
+    pending: Option<PageFuture<'a, T>>,
+    buffer: VecDeque<T>,
+    done: bool,
+    limit: Option<usize>,
+    yielded: usize,
+}
+
+// no field is structurally pinned (pending future is boxed)
+impl<'a, T> Unpin for Paginated<'a, T> {}
+
+impl<'a, T> Paginated<'a, T> {
+    /// pages fetched by `fetch` (called with `None` for the first page, then `next` of previous one)
+    pub fn new(fetch: impl FnMut(Option<Cursor>) -> PageFuture<'a, T> + Send + 'a) -> Self {
+        Paginated {
+            fetch: Box::new(fetch),
+            next: None,
+            pending: None,
+            buffer: VecDeque::new(),
+            done: false,
+            limit: None,
+            yielded: 0,
+        }
+    }
+
+    /// stop after `limit` items (no more pages are fetched)
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// next item (async)
+    pub async fn next_item(&mut self) -> Option<GenericResult<T>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// all items (async; fails if a page fails)
+    pub async fn try_collect(mut self) -> GenericResult<Vec<T>> {
+        let mut items = vec![];
+        while let Some(x) = self.next_item().await {
+            items.push(x?);
+        }
+        Ok(items)
+    }
+}
+
+impl<'a, T> Stream for Paginated<'a, T> {
+    type Item = GenericResult<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.limit.map(|l| this.yielded >= l).unwrap_or(false) {
+                return Poll::Ready(None);
+            }
+            if let Some(x) = this.buffer.pop_front() {
+                this.yielded += 1;
+                return Poll::Ready(Some(Ok(x)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            let next = this.next.take();
+            let fetch = &mut this.fetch;
+            let pending = this.pending.get_or_insert_with(|| fetch(next));
+            let page = match pending.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(x) => x,
+            };
+            this.pending = None;
+            match page {
+                Ok(page) => {
+                    this.done = page.next.is_none();
+                    this.next = page.next;
+                    this.buffer.extend(page.items);
+                }
+                Err(e) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for Paginated<'a, T> {
+    type Item = GenericResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(self.next_item())
+    }
+}
@@ -0,0 +1,207 @@
+//! Parquet export of indicators (requires `parquet` feature)
+//!
+//! Schema of exported file is following.
+//!
+//! ```text
+//! message indicator {
+//!   REQUIRED BYTE_ARRAY kind (UTF8);
+//!   REQUIRED BYTE_ARRAY value (UTF8);
+//!   OPTIONAL INT64 first_seen (TIMESTAMP_MILLIS);
+//!   OPTIONAL INT64 last_seen (TIMESTAMP_MILLIS);
+//!   OPTIONAL BYTE_ARRAY source (UTF8);
+//!   OPTIONAL BYTE_ARRAY description (UTF8);
+//!   REQUIRED GROUP tags (LIST) {
+//!     REPEATED GROUP list {
+//!       REQUIRED BYTE_ARRAY element (UTF8);
+//!     }
+//!   }
+//!   OPTIONAL INT32 confidence (UINT_8);
+//! }
+//! ```
+
+use ::parquet::data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type};
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use ::parquet::schema::parser::parse_message_type;
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::ioc::Indicator;
+use crate::GenericResult;
+
+/// number of rows in a row group
+pub const ROW_GROUP_SIZE: usize = 100_000;
+
+const SCHEMA: &str = "
+message indicator {
+  REQUIRED BYTE_ARRAY kind (UTF8);
+  REQUIRED BYTE_ARRAY value (UTF8);
+  OPTIONAL INT64 first_seen (TIMESTAMP_MILLIS);
+  OPTIONAL INT64 last_seen (TIMESTAMP_MILLIS);
+  OPTIONAL BYTE_ARRAY source (UTF8);
+  OPTIONAL BYTE_ARRAY description (UTF8);
+  REQUIRED GROUP tags (LIST) {
+    REPEATED GROUP list {
+      REQUIRED BYTE_ARRAY element (UTF8);
+    }
+  }
+  OPTIONAL INT32 confidence (UINT_8);
+}
+";
+
+/// values and definition levels of a nullable column
+fn nullable<T, U>(
+    values: impl Iterator<Item = Option<T>>,
+    f: impl Fn(T) -> U,
+) -> (Vec<U>, Vec<i16>) {
+    let mut out = vec![];
+    let mut levels = vec![];
+    for v in values {
+        match v {
+            Some(v) => {
+                out.push(f(v));
+                levels.push(1);
+            }
+            None => levels.push(0),
+        }
+    }
+    (out, levels)
+}
+
+fn strings(values: impl Iterator<Item = String>) -> Vec<ByteArray> {
+    values.map(|x| ByteArray::from(x.into_bytes())).collect()
+}
+
+fn millis(d: DateTime<Utc>) -> i64 {
+    d.timestamp_millis()
+}
+
+fn write_row_group<W: Write>(
+    writer: &mut SerializedFileWriter<W>,
+    rows: &[Indicator],
+) -> GenericResult<()> {
+    let mut group = writer.next_row_group()?;
+    let mut index = 0;
+
+    while let Some(mut column) = group.next_column()? {
+        write_column(&mut column, index, rows)?;
+        column.close()?;
+        index += 1;
+    }
+
+    group.close()?;
+    Ok(())
+}
+
+fn write_column(
+    column: &mut SerializedColumnWriter<'_>,
+    index: usize,
+    rows: &[Indicator],
+) -> GenericResult<()> {
+    let metas = || rows.iter().map(|x| &x.meta);
+    match index {
+        0 => {
+            let v = strings(rows.iter().map(|x| x.ioc.kind().to_string()));
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&v, None, None)?;
+        }
+        1 => {
+            let v = strings(rows.iter().map(|x| x.ioc.value()));
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&v, None, None)?;
+        }
+        2 | 3 => {
+            let (v, d) = if index == 2 {
+                nullable(metas().map(|x| x.first_seen), millis)
+            } else {
+                nullable(metas().map(|x| x.last_seen), millis)
+            };
+            column
+                .typed::<Int64Type>()
+                .write_batch(&v, Some(&d), None)?;
+        }
+        4 | 5 => {
+            let (v, d) = if index == 4 {
+                nullable(metas().map(|x| x.source.clone()), |x| {
+                    ByteArray::from(x.into_bytes())
+                })
+            } else {
+                nullable(metas().map(|x| x.description.clone()), |x| {
+                    ByteArray::from(x.into_bytes())
+                })
+            };
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&v, Some(&d), None)?;
+        }
+        6 => {
+            // empty list has no element (definition level 0)
+            let mut v = vec![];
+            let mut d = vec![];
+            let mut r = vec![];
+            for m in metas() {
+                if m.tags.is_empty() {
+                    d.push(0);
+                    r.push(0);
+                }
+                for (i, t) in m.tags.iter().enumerate() {
+                    v.push(ByteArray::from(t.as_str()));
+                    d.push(1);
+                    r.push(if i == 0 { 0 } else { 1 });
+                }
+            }
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&v, Some(&d), Some(&r))?;
+        }
+        _ => {
+            let (v, d) = nullable(metas().map(|x| x.confidence.map(|c| c.score())), i32::from);
+            column
+                .typed::<Int32Type>()
+                .write_batch(&v, Some(&d), None)?;
+        }
+    }
+    Ok(())
+}
+
+/// write indicators as Parquet file
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::parquet::write_parquet;
+///
+/// let mut i = Indicator::new(Ioc::new("example.com").unwrap());
+/// i.meta.tags = vec!["phishing".to_owned(), "kit".to_owned()];
+///
+/// let mut buf = vec![];
+/// write_parquet(&mut buf, vec![i, Indicator::new(Ioc::new("192.0.2.1").unwrap())]).unwrap();
+/// assert_eq!(&buf[..4], b"PAR1");
+/// ```
+pub fn write_parquet<W: Write>(
+    writer: W,
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+) -> GenericResult<()> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(writer, schema, props)?;
+
+    let mut rows = Vec::with_capacity(ROW_GROUP_SIZE);
+    for indicator in indicators.into_iter().map(|x| x.into()) {
+        rows.push(indicator);
+        if rows.len() >= ROW_GROUP_SIZE {
+            write_row_group(&mut writer, &rows)?;
+            rows.clear();
+        }
+    }
+    if !rows.is_empty() {
+        write_row_group(&mut writer, &rows)?;
+    }
+
+    writer.close()?;
+    Ok(())
+}
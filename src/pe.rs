@@ -0,0 +1,384 @@
+//! PE file analysis (imphash / rich header / authenticode; requires `pe` feature)
+
+use goblin::pe::certificate_table::AttributeCertificateType;
+use goblin::pe::import::SyntheticImportLookupTableEntry;
+use goblin::pe::PE;
+use md5::Md5;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::digest::{Digest, DynDigest};
+use sha2::{Sha256, Sha384, Sha512};
+use std::convert::TryInto;
+use std::path::Path;
+
+use crate::fingerprint::Fingerprint;
+use crate::GenericResult;
+
+pub mod authenticode;
+mod ordinal;
+
+use authenticode::Signature;
+
+const RICH: &[u8] = b"Rich";
+const DANS: u32 = 0x536e_6144;
+
+/// an entry of rich header (a tool used in build and number of objects built by it)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RichEntry {
+    /// product id (kind and version of tool)
+    pub product_id: u16,
+    /// build number of tool
+    pub build: u16,
+    /// number of objects
+    pub count: u32,
+}
+
+impl RichEntry {
+    /// comp.id (`product_id << 16 | build`)
+    pub fn comp_id(&self) -> u32 {
+        u32::from(self.product_id) << 16 | u32::from(self.build)
+    }
+}
+
+impl std::fmt::Display for RichEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{:08x} (product {:#06x}, build {}) x{}",
+            self.comp_id(),
+            self.product_id,
+            self.build,
+            self.count
+        )
+    }
+}
+
+/// rich header (undocumented header written by Microsoft linkers, between DOS stub and PE header)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RichHeader {
+    /// file offset of the header (`DanS` tag)
+    pub offset: usize,
+    /// xor key (also known as checksum)
+    pub key: u32,
+    /// decoded entries
+    pub entries: Vec<RichEntry>,
+    /// decoded header (from `DanS` tag to `Rich` tag)
+    pub clear_data: Vec<u8>,
+}
+
+impl RichHeader {
+    /// parse rich header in bytes before PE header
+    fn parse(stub: &[u8]) -> Option<Self> {
+        let rich = stub.windows(4).rposition(|x| x == RICH)?;
+        let key = u32::from_le_bytes(stub.get(rich + 4..rich + 8)?.try_into().ok()?);
+        let dans = (DANS ^ key).to_le_bytes();
+        let offset = stub[..rich].windows(4).rposition(|x| x == dans)?;
+
+        let clear_data: Vec<u8> = stub[offset..rich]
+            .iter()
+            .zip(key.to_le_bytes().iter().cycle())
+            .map(|(x, k)| x ^ k)
+            .collect();
+
+        // DanS tag and 3 dwords of padding are followed by entries
+        let entries = clear_data
+            .get(16..)
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|x| RichEntry {
+                build: u16::from_le_bytes([x[0], x[1]]),
+                product_id: u16::from_le_bytes([x[2], x[3]]),
+                count: u32::from_le_bytes([x[4], x[5], x[6], x[7]]),
+            })
+            .collect();
+
+        Some(RichHeader {
+            offset,
+            key,
+            entries,
+            clear_data,
+        })
+    }
+
+    /// rich header hash (`rich_pe_header_hash` in VirusTotal intelligence)
+    pub fn hash(&self) -> Fingerprint {
+        Fingerprint::RichHeaderHash(format!("{:x}", Md5::digest(&self.clear_data)))
+    }
+}
+
+/// hashes of a section
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SectionHash {
+    /// section name (e.g. `.text`)
+    pub name: String,
+    pub virtual_address: u32,
+    pub virtual_size: u32,
+    /// file offset of raw data
+    pub raw_offset: u32,
+    /// size of raw data (in the file)
+    pub raw_size: u32,
+    /// md5 of raw data
+    pub md5: String,
+    /// sha256 of raw data
+    pub sha256: String,
+}
+
+/// data appended after the last section
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Overlay {
+    /// file offset where the overlay starts
+    pub offset: u64,
+    pub size: u64,
+    pub md5: String,
+    pub sha256: String,
+}
+
+/// hashes of sections and overlay
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SectionReport {
+    /// sections in order of section table
+    pub sections: Vec<SectionHash>,
+    /// `None` if nothing is appended after the last section
+    pub overlay: Option<Overlay>,
+}
+
+impl SectionReport {
+    /// first section with the name
+    pub fn section(&self, name: impl AsRef<str>) -> Option<&SectionHash> {
+        self.sections.iter().find(|x| x.name == name.as_ref())
+    }
+}
+
+fn md5_sha256(data: &[u8]) -> (String, String) {
+    (
+        format!("{:x}", Md5::digest(data)),
+        format!("{:x}", Sha256::digest(data)),
+    )
+}
+
+/// a PE file
+#[derive(Clone, Debug)]
+pub struct PeFile {
+    bytes: Vec<u8>,
+}
+
+impl PeFile {
+    /// PE file from bytes (fails if bytes are not PE)
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> GenericResult<Self> {
+        let bytes = bytes.into();
+        PE::parse(&bytes)?;
+        Ok(PeFile { bytes })
+    }
+
+    /// open PE file
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        PeFile::from_bytes(std::fs::read(path)?)
+    }
+
+    /// raw bytes of the file
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn parse(&self) -> PE<'_> {
+        // bytes are validated in construction
+        PE::parse(&self.bytes).unwrap()
+    }
+
+    /// imported functions (`library.function` in lowercase, same normalization as `pefile`)
+    ///
+    /// Extensions `.dll`, `.sys` and `.ocx` are removed from library names,
+    /// and functions imported by ordinal are named by well-known ordinals (or `ord{N}`).
+    pub fn imports(&self) -> Vec<String> {
+        let pe = self.parse();
+        let mut imports = vec![];
+        for entry in pe.import_data.iter().flat_map(|x| x.import_data.iter()) {
+            let library = entry.name.to_lowercase();
+            let stem = match library.rsplit_once('.') {
+                Some((stem, "dll")) | Some((stem, "sys")) | Some((stem, "ocx")) => stem,
+                _ => &library,
+            };
+            for f in entry.import_lookup_table.iter().flatten() {
+                let function = match f {
+                    SyntheticImportLookupTableEntry::OrdinalNumber(n) => {
+                        match ordinal::lookup(entry.name, *n) {
+                            Some(name) => name.to_lowercase(),
+                            None => format!("ord{}", n),
+                        }
+                    }
+                    SyntheticImportLookupTableEntry::HintNameTableRVA((_, hint)) => {
+                        hint.name.to_lowercase()
+                    }
+                };
+                imports.push(format!("{}.{}", stem, function));
+            }
+        }
+        imports
+    }
+
+    /// import hash (`None` if the file imports nothing)
+    ///
+    /// The value is same as `pefile`'s `get_imphash()` and `imphash:` in VirusTotal intelligence.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use iocutil::pe::PeFile;
+    ///
+    /// let pe = PeFile::open("sample.exe").unwrap();
+    /// if let Some(imphash) = pe.imphash() {
+    ///     println!("{}", imphash.vt_query());
+    /// }
+    /// ```
+    pub fn imphash(&self) -> Option<Fingerprint> {
+        let imports = self.imports();
+        if imports.is_empty() {
+            return None;
+        }
+        Some(Fingerprint::Imphash(format!(
+            "{:x}",
+            Md5::digest(imports.join(","))
+        )))
+    }
+
+    /// rich header (`None` if the file has no rich header)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use iocutil::pe::PeFile;
+    ///
+    /// let pe = PeFile::open("sample.exe").unwrap();
+    /// if let Some(rich) = pe.rich_header() {
+    ///     println!("{}", rich.hash().vt_query());
+    ///     for entry in rich.entries {
+    ///         println!("{}", entry);
+    ///     }
+    /// }
+    /// ```
+    pub fn rich_header(&self) -> Option<RichHeader> {
+        let pe_pointer = self.parse().header.dos_header.pe_pointer as usize;
+        RichHeader::parse(&self.bytes[..pe_pointer.min(self.bytes.len())])
+    }
+
+    /// md5 / sha256 of each section and overlay
+    ///
+    /// Raw data beyond the end of file is truncated.
+    /// The overlay starts where the section which ends last in the file ends
+    /// (same as `pe.overlay` in YARA), so it includes certificate table of signed files.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use iocutil::pe::PeFile;
+    ///
+    /// let report = PeFile::open("sample.exe").unwrap().section_report();
+    /// if let Some(text) = report.section(".text") {
+    ///     println!("{} {}", text.name, text.sha256);
+    /// }
+    /// if let Some(overlay) = report.overlay {
+    ///     println!("overlay at {:#x} ({} bytes): {}", overlay.offset, overlay.size, overlay.sha256);
+    /// }
+    /// ```
+    pub fn section_report(&self) -> SectionReport {
+        let pe = self.parse();
+        let len = self.bytes.len();
+        let mut sections = vec![];
+        let mut end: Option<u64> = None;
+        for s in pe.sections.iter() {
+            let name = match &s.real_name {
+                Some(x) => x.clone(),
+                None => s.name().unwrap_or_default().to_owned(),
+            };
+            let start = (s.pointer_to_raw_data as usize).min(len);
+            let stop = start.saturating_add(s.size_of_raw_data as usize).min(len);
+            let (md5, sha256) = md5_sha256(&self.bytes[start..stop]);
+            let section_end = u64::from(s.pointer_to_raw_data) + u64::from(s.size_of_raw_data);
+            end = Some(end.map_or(section_end, |x| x.max(section_end)));
+            sections.push(SectionHash {
+                name,
+                virtual_address: s.virtual_address,
+                virtual_size: s.virtual_size,
+                raw_offset: s.pointer_to_raw_data,
+                raw_size: s.size_of_raw_data,
+                md5,
+                sha256,
+            });
+        }
+
+        let overlay = end.filter(|&x| x < len as u64).map(|offset| {
+            let (md5, sha256) = md5_sha256(&self.bytes[offset as usize..]);
+            Overlay {
+                offset,
+                size: len as u64 - offset,
+                md5,
+                sha256,
+            }
+        });
+
+        SectionReport { sections, overlay }
+    }
+
+    /// authenticode digest with algorithm (`md5`, `sha1`, `sha256`, `sha384` or `sha512`)
+    ///
+    /// `None` if the algorithm is not supported.
+    pub fn authenticode_digest(&self, algorithm: impl AsRef<str>) -> Option<String> {
+        let mut digest: Box<dyn DynDigest> = match algorithm.as_ref().to_lowercase().as_str() {
+            "md5" => Box::new(Md5::default()),
+            "sha1" => Box::new(Sha1::default()),
+            "sha256" => Box::new(Sha256::default()),
+            "sha384" => Box::new(Sha384::default()),
+            "sha512" => Box::new(Sha512::default()),
+            _ => return None,
+        };
+        for range in self.parse().authenticode_ranges() {
+            digest.update(range);
+        }
+        Some(
+            digest
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+        )
+    }
+
+    /// authentihash (sha256 authenticode digest, `authentihash` in VirusTotal intelligence)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use iocutil::pe::PeFile;
+    ///
+    /// let pe = PeFile::open("signed.exe").unwrap();
+    /// println!("{}", pe.authentihash().vt_query());
+    ///
+    /// for signature in pe.signatures().unwrap() {
+    ///     // the file is not modified after signing
+    ///     let digest = pe.authenticode_digest(&signature.digest_algorithm);
+    ///     assert_eq!(digest, Some(signature.signed_digest));
+    ///
+    ///     for cert in signature.chain {
+    ///         println!("{} {}", cert.thumbprint(), cert.subject);
+    ///     }
+    /// }
+    /// ```
+    pub fn authentihash(&self) -> Fingerprint {
+        // sha256 is always supported
+        Fingerprint::Authentihash(self.authenticode_digest("sha256").unwrap())
+    }
+
+    /// authenticode signatures in certificate table (empty if the file is not signed)
+    pub fn signatures(&self) -> GenericResult<Vec<Signature>> {
+        let pe = self.parse();
+        let mut signatures = vec![];
+        for c in pe
+            .certificates
+            .iter()
+            .filter(|c| c.certificate_type == AttributeCertificateType::PkcsSignedData)
+        {
+            signatures.push(Signature::parse(c.certificate)?);
+        }
+        Ok(signatures)
+    }
+}
@@ -0,0 +1,125 @@
+//! authenticode signatures of PE files (signed digest and certificate chain)
+
+use failure::Fail;
+
+use crate::der::{self, Tlv};
+use crate::x509::hex;
+
+pub use crate::x509::Certificate;
+
+/// Errors in parsing authenticode signature
+#[derive(Fail, Debug)]
+pub enum AuthenticodeError {
+    #[fail(display = "invalid authenticode signature: {}", _0)]
+    InvalidSignature(&'static str),
+}
+
+/// an authenticode signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    /// digest algorithm of authenticode digest (e.g. `sha256`)
+    pub digest_algorithm: String,
+    /// authenticode digest of the file when it was signed (in hex)
+    pub signed_digest: String,
+    /// certificates from the signer to the root (as far as included in the signature)
+    pub chain: Vec<Certificate>,
+    /// every certificate in the signed data (ones in nested timestamp tokens are not included)
+    pub certificates: Vec<Certificate>,
+}
+
+fn digest_name(oid: &str) -> String {
+    match oid {
+        "1.2.840.113549.2.5" => "md5".to_owned(),
+        "1.3.14.3.2.26" => "sha1".to_owned(),
+        "2.16.840.1.101.3.4.2.1" => "sha256".to_owned(),
+        "2.16.840.1.101.3.4.2.2" => "sha384".to_owned(),
+        "2.16.840.1.101.3.4.2.3" => "sha512".to_owned(),
+        x => x.to_owned(),
+    }
+}
+
+impl Signature {
+    /// parse PKCS#7 signed data in certificate table
+    pub(crate) fn parse(pkcs7: &[u8]) -> Result<Self, AuthenticodeError> {
+        Signature::parse_signed_data(pkcs7)
+            .ok_or(AuthenticodeError::InvalidSignature("malformed signed data"))
+    }
+
+    fn parse_signed_data(pkcs7: &[u8]) -> Option<Self> {
+        // ContentInfo { contentType, [0] SignedData }
+        let (content_info, _) = der::expect(pkcs7, der::SEQUENCE)?;
+        let (_, rest) = der::expect(content_info.content, der::OID)?;
+        let (explicit, _) = der::expect(rest, der::context(0))?;
+        let (signed_data, _) = der::expect(explicit.content, der::SEQUENCE)?;
+
+        // SignedData { version, digestAlgorithms, encapContentInfo, [0] certificates, [1] crls, signerInfos }
+        let fields = der::children(signed_data.content)?;
+        let encap = fields.get(2).filter(|x| x.tag == der::SEQUENCE)?;
+        let (digest_algorithm, signed_digest) = indirect_data_digest(encap)?;
+
+        let certificates: Vec<Certificate> = match fields.iter().find(|x| x.tag == der::context(0))
+        {
+            Some(x) => der::children(x.content)?
+                .iter()
+                .filter_map(|c| Certificate::from_der(c.raw))
+                .collect(),
+            None => vec![],
+        };
+
+        // SignerInfo { version, IssuerAndSerialNumber { issuer, serialNumber }, ... }
+        let signer = fields
+            .iter()
+            .rev()
+            .find(|x| x.tag == der::SET)
+            .and_then(|x| der::children(x.content))
+            .and_then(|x| {
+                let info = der::children(x.first()?.content)?;
+                let sid = der::children(info.get(1)?.content)?;
+                Some((sid.first()?.raw.to_vec(), sid.get(1)?.content.to_vec()))
+            });
+
+        let mut chain: Vec<Certificate> = vec![];
+        if let Some((issuer, serial)) = signer {
+            let mut current = certificates
+                .iter()
+                .find(|c| c.raw_issuer == issuer && c.raw_serial == serial);
+            while let Some(c) = current {
+                if chain.iter().any(|x| x.sha1 == c.sha1) {
+                    break;
+                }
+                chain.push(c.clone());
+                if c.is_self_signed() {
+                    break;
+                }
+                current = certificates.iter().find(|x| x.raw_subject == c.raw_issuer);
+            }
+        }
+
+        Some(Signature {
+            digest_algorithm,
+            signed_digest,
+            chain,
+            certificates,
+        })
+    }
+
+    /// certificate of the signer
+    pub fn signer(&self) -> Option<&Certificate> {
+        self.chain.first()
+    }
+}
+
+/// digest in SpcIndirectDataContent { data, DigestInfo { algorithm, digest } }
+fn indirect_data_digest(encap: &Tlv<'_>) -> Option<(String, String)> {
+    let (_, rest) = der::expect(encap.content, der::OID)?;
+    let (explicit, _) = der::expect(rest, der::context(0))?;
+    let (indirect, _) = der::expect(explicit.content, der::SEQUENCE)?;
+    let fields = der::children(indirect.content)?;
+    let digest_info = der::children(fields.get(1)?.content)?;
+    let algorithm = der::children(digest_info.first()?.content)?;
+    let oid = algorithm.first().filter(|x| x.tag == der::OID)?;
+    Some((
+        digest_name(&der::oid(oid.content)),
+        hex(digest_info.get(1)?.content, ""),
+    ))
+}
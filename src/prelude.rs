@@ -2,14 +2,24 @@
 pub extern crate chrono;
 pub extern crate time;
 
+#[cfg(feature = "net")]
 pub use crate::alienvault::{hashes_in, AlienVaultOTXClient, Pulse, QueryType};
-pub use crate::contenthash::{remove_overlap, ContentHash};
+pub use crate::collection::IocCollection;
+pub use crate::contenthash::{remove_overlap, ContentHash, SampleHashes};
 pub use crate::datetime::{days_ago, vtdatetime};
 pub use crate::hasher::Hasher;
+#[cfg(feature = "net")]
+pub use crate::hibp::HaveIBeenPwnedClient;
+pub use crate::ioc::{Indicator, Ioc, IocKind, KillChainPhase, Metadata};
+pub use crate::provider::{IocProvider, ProviderReport, Verdict};
 pub use crate::sample;
+#[cfg(feature = "net")]
 pub use crate::virustotal::{scan_id, VirusTotalClient};
+#[cfg(feature = "net")]
+pub use crate::whois::WhoisClient;
 pub use crate::SampleHash;
 pub use crate::{at, day};
+#[cfg(feature = "net")]
 pub use crate::{fs, la, ls, p};
 pub use chrono::{DateTime, Utc};
 pub use std::convert::{TryFrom, TryInto};
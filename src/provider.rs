@@ -0,0 +1,237 @@
+//! pluggable interface for threat intelligence providers
+//!
+//! Built-in clients implement `IocProvider`, and you can implement it for your internal systems
+//! to handle them in the same way.
+
+use failure::Fail;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::confidence::{Confidence, ConfidenceBand};
+use crate::ioc::{Indicator, Ioc, IocKind};
+use crate::tlp::Audience;
+use crate::GenericResult;
+
+/// verdict of a provider about an indicator
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Ord, PartialOrd)]
+pub enum Verdict {
+    Unknown,
+    Harmless,
+    Suspicious,
+    Malicious,
+}
+
+impl Verdict {
+    /// verdict from AV detections (no detection => harmless, 1-2 => suspicious, otherwise malicious)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::provider::Verdict;
+    ///
+    /// assert_eq!(Verdict::from_detections(0, 70), Verdict::Harmless);
+    /// assert_eq!(Verdict::from_detections(2, 70), Verdict::Suspicious);
+    /// assert_eq!(Verdict::from_detections(30, 70), Verdict::Malicious);
+    /// assert_eq!(Verdict::from_detections(0, 0), Verdict::Unknown);
+    /// ```
+    pub fn from_detections(positives: u32, total: u32) -> Self {
+        match (positives, total) {
+            (_, 0) => Verdict::Unknown,
+            (0, _) => Verdict::Harmless,
+            (1..=2, _) => Verdict::Suspicious,
+            _ => Verdict::Malicious,
+        }
+    }
+}
+
+/// capabilities of a provider (which kinds of indicator it can lookup / accept)
+#[derive(Clone, Debug, Default)]
+pub struct Capabilities {
+    pub lookup: HashSet<IocKind>,
+    pub submit: HashSet<IocKind>,
+}
+
+impl Capabilities {
+    /// capabilities of lookup only provider
+    pub fn lookup_only(kinds: impl IntoIterator<Item = IocKind>) -> Self {
+        Capabilities {
+            lookup: kinds.into_iter().collect(),
+            submit: HashSet::new(),
+        }
+    }
+
+    /// can lookup the kind of indicator?
+    pub fn can_lookup(&self, kind: IocKind) -> bool {
+        self.lookup.contains(&kind)
+    }
+
+    /// can submit the kind of indicator?
+    pub fn can_submit(&self, kind: IocKind) -> bool {
+        self.submit.contains(&kind)
+    }
+}
+
+/// report about an indicator from a provider
+#[derive(Clone, Debug)]
+pub struct ProviderReport {
+    pub provider: String,
+    pub ioc: Ioc,
+    pub found: bool,
+    pub verdict: Verdict,
+    pub permalink: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl ProviderReport {
+    /// report of indicator which the provider knows
+    pub fn new(provider: impl AsRef<str>, ioc: Ioc, verdict: Verdict) -> Self {
+        ProviderReport {
+            provider: provider.as_ref().to_owned(),
+            ioc,
+            found: true,
+            verdict,
+            permalink: None,
+            tags: vec![],
+        }
+    }
+
+    /// confidence the report gives to the indicator (high if malicious, medium if suspicious)
+    ///
+    /// Harmless / unknown verdicts are not evidence against the indicator, so they give none.
+    pub fn confidence(&self) -> Option<Confidence> {
+        match self.verdict {
+            Verdict::Malicious if self.found => Some(ConfidenceBand::High.into()),
+            Verdict::Suspicious if self.found => Some(ConfidenceBand::Medium.into()),
+            _ => None,
+        }
+    }
+
+    /// report of indicator which the provider does not know
+    pub fn not_found(provider: impl AsRef<str>, ioc: Ioc) -> Self {
+        ProviderReport {
+            found: false,
+            ..ProviderReport::new(provider, ioc, Verdict::Unknown)
+        }
+    }
+}
+
+/// Errors in operating providers
+#[derive(Fail, Debug)]
+pub enum ProviderError {
+    #[fail(display = "the provider does not support this kind of indicator")]
+    UnsupportedKind(IocKind),
+    /// short-circuited by `iocutil::breaker::CircuitBreaker` (retry after the duration)
+    #[fail(display = "{} is unavailable (circuit open)", provider)]
+    CircuitOpen {
+        provider: String,
+        retry_after: Duration,
+    },
+}
+
+/// threat intelligence provider
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::provider::Capabilities;
+///
+/// struct InternalBlocklist;
+///
+/// impl IocProvider for InternalBlocklist {
+///     fn name(&self) -> &str {
+///         "internal"
+///     }
+///
+///     fn capabilities(&self) -> Capabilities {
+///         Capabilities::lookup_only(vec![IocKind::Domain])
+///     }
+///
+///     fn lookup(&self, ioc: &Ioc) -> Result<ProviderReport, failure::Error> {
+///         self.ensure_lookup(ioc)?;
+///         if ioc.value() == "evil.example.com" {
+///             Ok(ProviderReport::new(self.name(), ioc.clone(), Verdict::Malicious))
+///         } else {
+///             Ok(ProviderReport::not_found(self.name(), ioc.clone()))
+///         }
+///     }
+/// }
+///
+/// let p = InternalBlocklist;
+/// let r = p.lookup(&Ioc::new("evil.example.com").unwrap()).unwrap();
+/// assert_eq!(r.verdict, Verdict::Malicious);
+/// assert!(p.lookup(&Ioc::new("192.0.2.1").unwrap()).is_err());
+/// assert!(p.submit(&Ioc::new("evil.example.com").unwrap()).is_err());
+/// ```
+pub trait IocProvider {
+    /// name of provider
+    fn name(&self) -> &str;
+
+    /// capabilities of provider
+    fn capabilities(&self) -> Capabilities;
+
+    /// lookup an indicator
+    fn lookup(&self, ioc: &Ioc) -> GenericResult<ProviderReport>;
+
+    /// submit an indicator (not supported by default)
+    fn submit(&self, ioc: &Ioc) -> GenericResult<()> {
+        Err(ProviderError::UnsupportedKind(ioc.kind()).into())
+    }
+
+    /// audience of indicators submitted to the provider (third-party services are public unless
+    /// overridden, e.g. internal systems are `Audience::Organization`)
+    fn audience(&self) -> Audience {
+        Audience::Public
+    }
+
+    /// submit an indicator if its TLP allows the audience of the provider
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::provider::Capabilities;
+    /// use iocutil::tlp::Tlp;
+    ///
+    /// struct PublicSandbox;
+    ///
+    /// impl IocProvider for PublicSandbox {
+    ///     fn name(&self) -> &str {
+    ///         "sandbox"
+    ///     }
+    ///
+    ///     fn capabilities(&self) -> Capabilities {
+    ///         Capabilities::default()
+    ///     }
+    ///
+    ///     fn lookup(&self, ioc: &Ioc) -> Result<ProviderReport, failure::Error> {
+    ///         Ok(ProviderReport::not_found(self.name(), ioc.clone()))
+    ///     }
+    ///
+    ///     fn submit(&self, _ioc: &Ioc) -> Result<(), failure::Error> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut i = Indicator::new(Ioc::new("evil.example.com").unwrap());
+    /// assert!(PublicSandbox.share(&i).is_ok());
+    /// i.meta.tlp = Some(Tlp::Red);
+    /// let err = PublicSandbox.share(&i).unwrap_err();
+    /// assert_eq!(err.to_string(), "TLP:RED does not allow sharing with Public");
+    /// ```
+    fn share(&self, indicator: &Indicator) -> GenericResult<()> {
+        if let Some(tlp) = indicator.meta.tlp {
+            tlp.ensure(self.audience())?;
+        }
+        self.submit(&indicator.ioc)
+    }
+
+    /// error if the provider can not lookup the kind of indicator
+    fn ensure_lookup(&self, ioc: &Ioc) -> GenericResult<()> {
+        if self.capabilities().can_lookup(ioc.kind()) {
+            Ok(())
+        } else {
+            Err(ProviderError::UnsupportedKind(ioc.kind()).into())
+        }
+    }
+}
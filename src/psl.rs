@@ -0,0 +1,207 @@
+//! Public Suffix List (registrable domains like `example.co.uk`)
+//!
+//! A snapshot of <https://publicsuffix.org/list/public_suffix_list.dat> is embedded, and a newer
+//! list can be loaded at runtime by `load_list`. Private suffixes (e.g. internal TLD `corp`) can
+//! be registered by `add_private_suffix`; they are kept over reloaded lists.
+//! Everything in this crate computing registrable domains uses the current list.
+
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::GenericResult;
+
+/// embedded snapshot of the list
+const SNAPSHOT: &str = include_str!("../data/public_suffix_list.dat");
+
+/// rules of public suffixes
+///
+/// # Example
+///
+/// ```
+/// use iocutil::psl::PublicSuffixList;
+///
+/// let list = PublicSuffixList::parse("com\n*.ck\n!www.ck\n// comment\nco.uk\n");
+/// assert_eq!(list.public_suffix("www.Example.COM"), Some("com".to_owned()));
+/// assert_eq!(list.registrable_domain("a.b.example.co.uk"), Some("example.co.uk".to_owned()));
+/// assert_eq!(list.registrable_domain("a.b.ck"), Some("a.b.ck".to_owned()));
+/// assert_eq!(list.registrable_domain("a.www.ck"), Some("www.ck".to_owned()));
+/// assert_eq!(list.registrable_domain("co.uk"), None);
+/// // unlisted TLDs are public suffixes
+/// assert_eq!(list.registrable_domain("host.example.test"), Some("example.test".to_owned()));
+///
+/// let list = list.with_suffix("corp").with_suffix("*.公司.cn");
+/// assert_eq!(list.registrable_domain("dc01.ad.corp"), Some("ad.corp".to_owned()));
+/// assert_eq!(list.registrable_domain("a.b.xn--55qx5d.cn"), Some("a.b.xn--55qx5d.cn".to_owned()));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PublicSuffixList {
+    rules: HashSet<String>,
+    /// `*.x` rules (stored as `x`)
+    wildcards: HashSet<String>,
+    /// `!x` rules (stored as `x`)
+    exceptions: HashSet<String>,
+}
+
+impl PublicSuffixList {
+    /// parse list (format of `public_suffix_list.dat`)
+    pub fn parse(text: impl AsRef<str>) -> Self {
+        let mut list = PublicSuffixList::default();
+        for line in text.as_ref().lines() {
+            // rule is the first word of line
+            match line.split_whitespace().next() {
+                Some(x) if !x.starts_with("//") => list.add_suffix(x),
+                _ => {}
+            }
+        }
+        list
+    }
+
+    /// embedded snapshot
+    pub fn embedded() -> Self {
+        PublicSuffixList::parse(SNAPSHOT)
+    }
+
+    /// read list file
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        Ok(PublicSuffixList::parse(std::fs::read_to_string(path)?))
+    }
+
+    /// add a rule (`x`, `*.x` or `!x`, unicode rules are converted into ACE form)
+    pub fn add_suffix(&mut self, rule: impl AsRef<str>) {
+        let rule = rule.as_ref().trim().trim_matches('.').to_lowercase();
+        let (set, name) = if let Some(x) = rule.strip_prefix("*.") {
+            (&mut self.wildcards, x)
+        } else if let Some(x) = rule.strip_prefix('!') {
+            (&mut self.exceptions, x)
+        } else {
+            (&mut self.rules, rule.as_str())
+        };
+        if name.is_ascii() {
+            if !name.is_empty() {
+                set.insert(name.to_owned());
+            }
+        } else if let Ok(x) = idna::domain_to_ascii(name) {
+            set.insert(x);
+        }
+    }
+
+    /// the list with the rule added
+    pub fn with_suffix(mut self, rule: impl AsRef<str>) -> Self {
+        self.add_suffix(rule);
+        self
+    }
+
+    /// number of rules
+    pub fn len(&self) -> usize {
+        self.rules.len() + self.wildcards.len() + self.exceptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// byte offset of public suffix in (lowercased) domain
+    fn suffix_offset(&self, domain: &str) -> usize {
+        let starts: Vec<usize> = std::iter::once(0)
+            .chain(domain.match_indices('.').map(|(i, _)| i + 1))
+            .collect();
+        for (n, &start) in starts.iter().enumerate() {
+            let candidate = &domain[start..];
+            let parent = starts.get(n + 1).map(|&i| &domain[i..]);
+            if self.exceptions.contains(candidate) {
+                return starts.get(n + 1).copied().unwrap_or(start);
+            }
+            if self.rules.contains(candidate) || parent.is_some_and(|p| self.wildcards.contains(p))
+            {
+                return start;
+            }
+        }
+        // default rule `*`
+        *starts.last().unwrap()
+    }
+
+    /// public suffix of domain (lowercased)
+    pub fn public_suffix(&self, domain: impl AsRef<str>) -> Option<String> {
+        let domain = normalize(domain.as_ref())?;
+        Some(domain[self.suffix_offset(&domain)..].to_owned())
+    }
+
+    /// registrable domain (public suffix and one more label, lowercased)
+    /// (`None` if the domain is a public suffix)
+    pub fn registrable_domain(&self, domain: impl AsRef<str>) -> Option<String> {
+        let domain = normalize(domain.as_ref())?;
+        let offset = self.suffix_offset(&domain);
+        if offset == 0 {
+            return None;
+        }
+        let start = domain[..offset - 1].rfind('.').map(|i| i + 1).unwrap_or(0);
+        Some(domain[start..].to_owned())
+    }
+}
+
+fn normalize(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+    if domain.is_empty() || domain.split('.').any(|x| x.is_empty()) {
+        None
+    } else {
+        Some(domain)
+    }
+}
+
+lazy_static! {
+    static ref LIST: RwLock<Arc<PublicSuffixList>> =
+        RwLock::new(Arc::new(PublicSuffixList::embedded()));
+    static ref PRIVATE: Mutex<Vec<String>> = Mutex::new(vec![]);
+}
+
+/// current list (embedded snapshot unless replaced)
+pub fn list() -> Arc<PublicSuffixList> {
+    LIST.read().unwrap().clone()
+}
+
+/// replace current list (registered private suffixes are added to it)
+pub fn set_list(mut list: PublicSuffixList) {
+    let private = PRIVATE.lock().unwrap();
+    for x in private.iter() {
+        list.add_suffix(x);
+    }
+    *LIST.write().unwrap() = Arc::new(list);
+}
+
+/// replace current list with newer one in the file
+pub fn load_list(path: impl AsRef<Path>) -> GenericResult<()> {
+    set_list(PublicSuffixList::open(path)?);
+    Ok(())
+}
+
+/// register private suffix (e.g. internal TLD `corp` or `branch.example.com`)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::psl::{add_private_suffix, registrable_domain};
+///
+/// assert_eq!(registrable_domain("www.example.co.uk"), Some("example.co.uk".to_owned()));
+/// assert_eq!(registrable_domain("dc01.tokyo.intra.example.com"), Some("example.com".to_owned()));
+///
+/// add_private_suffix("intra.example.com");
+/// assert_eq!(registrable_domain("dc01.tokyo.intra.example.com"), Some("tokyo.intra.example.com".to_owned()));
+/// ```
+pub fn add_private_suffix(suffix: impl AsRef<str>) {
+    let mut private = PRIVATE.lock().unwrap();
+    private.push(suffix.as_ref().to_owned());
+    let mut list = LIST.write().unwrap();
+    Arc::make_mut(&mut list).add_suffix(suffix);
+}
+
+/// public suffix of domain by current list
+pub fn public_suffix(domain: impl AsRef<str>) -> Option<String> {
+    list().public_suffix(domain)
+}
+
+/// registrable domain by current list (`None` if the domain is a public suffix)
+pub fn registrable_domain(domain: impl AsRef<str>) -> Option<String> {
+    list().registrable_domain(domain)
+}
@@ -0,0 +1,263 @@
+//! Python bindings (`iocutil` module built by maturin with `python` feature)
+//!
+//! ```python
+//! import iocutil
+//!
+//! hashes = iocutil.find_hashes(iocutil.scrape_text(html))
+//! iocs = iocutil.IocSet([iocutil.refang(x) for x in lines])
+//! print([ioc.defanged() for ioc in iocs])
+//! ```
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::contenthash::ContentHash;
+use crate::ioc::{self, Ioc};
+use crate::scraper;
+use crate::SampleHash;
+
+fn value_error(e: failure::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// hash of a sample (sha256 / sha1 / md5)
+#[pyclass(
+    name = "SampleHash",
+    module = "iocutil",
+    frozen,
+    eq,
+    hash,
+    skip_from_py_object
+)]
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct PySampleHash(SampleHash);
+
+#[pymethods]
+impl PySampleHash {
+    #[new]
+    fn new(value: &str) -> PyResult<Self> {
+        Ok(PySampleHash(SampleHash::new(value).map_err(value_error)?))
+    }
+
+    /// `md5`, `sha1` or `sha256`
+    #[getter]
+    fn algorithm(&self) -> &'static str {
+        self.0.algorithm()
+    }
+
+    /// hex digest (lowercase)
+    #[getter]
+    fn value(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SampleHash('{}')", self.0)
+    }
+}
+
+/// indicator of compromise (hash / domain / url / ip / email)
+#[pyclass(
+    name = "Ioc",
+    module = "iocutil",
+    frozen,
+    eq,
+    hash,
+    skip_from_py_object
+)]
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct PyIoc(Ioc);
+
+#[pymethods]
+impl PyIoc {
+    #[new]
+    fn new(value: &str) -> PyResult<Self> {
+        Ok(PyIoc(Ioc::new(value).map_err(value_error)?))
+    }
+
+    /// `hash`, `domain`, `url`, `ip` or `email`
+    #[getter]
+    fn kind(&self) -> String {
+        self.0.kind().to_string()
+    }
+
+    #[getter]
+    fn value(&self) -> String {
+        self.0.value()
+    }
+
+    /// defanged value which is not clickable nor resolvable
+    fn defanged(&self) -> String {
+        self.0.defanged()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.value()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Ioc('{}')", self.0)
+    }
+}
+
+/// set of unique indicators (in order of insertion)
+#[pyclass(name = "IocSet", module = "iocutil")]
+#[derive(Default)]
+struct PyIocSet {
+    iocs: Vec<Ioc>,
+    seen: HashSet<Ioc>,
+}
+
+#[pymethods]
+impl PyIocSet {
+    #[new]
+    #[pyo3(signature = (values = vec![]))]
+    fn new(values: Vec<String>) -> PyResult<Self> {
+        let mut set = PyIocSet::default();
+        for x in values {
+            set.add(&x)?;
+        }
+        Ok(set)
+    }
+
+    /// add an indicator (returns `True` if it was not in the set)
+    fn add(&mut self, value: &str) -> PyResult<bool> {
+        let ioc = Ioc::new(value).map_err(value_error)?;
+        if !self.seen.insert(ioc.normalized()) {
+            return Ok(false);
+        }
+        self.iocs.push(ioc);
+        Ok(true)
+    }
+
+    /// indicators of the kind (`hash`, `domain`, `url`, `ip` or `email`)
+    fn of_kind(&self, kind: &str) -> Vec<PyIoc> {
+        self.iocs
+            .iter()
+            .filter(|x| x.kind().to_string() == kind)
+            .cloned()
+            .map(PyIoc)
+            .collect()
+    }
+
+    /// defanged values of all indicators
+    fn defanged(&self) -> Vec<String> {
+        self.iocs.iter().map(|x| x.defanged()).collect()
+    }
+
+    fn __contains__(&self, value: &str) -> bool {
+        Ioc::new(value)
+            .map(|x| self.seen.contains(&x.normalized()))
+            .unwrap_or(false)
+    }
+
+    fn __len__(&self) -> usize {
+        self.iocs.len()
+    }
+
+    fn __iter__(&self) -> PyIocSetIter {
+        PyIocSetIter {
+            iocs: self.iocs.clone().into_iter(),
+        }
+    }
+}
+
+#[pyclass(name = "IocSetIterator", module = "iocutil")]
+struct PyIocSetIter {
+    iocs: std::vec::IntoIter<Ioc>,
+}
+
+#[pymethods]
+impl PyIocSetIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>) -> Option<PyIoc> {
+        slf.iocs.next().map(PyIoc)
+    }
+}
+
+/// unique hashes (sha256 / sha1 / md5) in text (sorted)
+#[pyfunction]
+fn find_hashes(text: &str) -> Vec<PySampleHash> {
+    let mut hashes: Vec<SampleHash> = SampleHash::find(text);
+    hashes.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    hashes.into_iter().map(PySampleHash).collect()
+}
+
+/// article (or body) text in html
+#[pyfunction]
+fn scrape_text(html: &str) -> PyResult<String> {
+    scraper::article_text(html).map_err(value_error)
+}
+
+/// defanged indicator (e.g. `hxxps://example[.]com`)
+#[pyfunction]
+fn defang(value: &str) -> PyResult<String> {
+    Ok(Ioc::new(value).map_err(value_error)?.defanged())
+}
+
+/// restore defanged text into original form
+#[pyfunction]
+fn refang(text: &str) -> String {
+    ioc::refang(text)
+}
+
+/// whether the token is defanged (`fanged`, `defanged`, `mixed` or `not_an_ioc`)
+#[pyfunction]
+fn fang_state(text: &str) -> &'static str {
+    match ioc::fang_state(text) {
+        ioc::FangState::Fanged => "fanged",
+        ioc::FangState::Defanged => "defanged",
+        ioc::FangState::Mixed => "mixed",
+        ioc::FangState::NotAnIoc => "not_an_ioc",
+    }
+}
+
+/// parse hash (`ValueError` if it is not sha256 / sha1 / md5 hex digest)
+#[pyfunction]
+fn parse_hash(value: &str) -> PyResult<PySampleHash> {
+    PySampleHash::new(value)
+}
+
+/// parse indicator (`ValueError` if it is not recognized)
+#[pyfunction]
+fn parse_ioc(value: &str) -> PyResult<PyIoc> {
+    PyIoc::new(value)
+}
+
+/// md5 / sha1 / sha256 of bytes
+#[pyfunction]
+fn hash_bytes(data: &[u8]) -> HashMap<&'static str, String> {
+    let c = ContentHash::of_bytes(data);
+    vec![
+        ("md5", c.md5.to_string()),
+        ("sha1", c.sha1.to_string()),
+        ("sha256", c.sha256.to_string()),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// IoC utilities for malware researchers
+#[pymodule]
+fn iocutil(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySampleHash>()?;
+    m.add_class::<PyIoc>()?;
+    m.add_class::<PyIocSet>()?;
+    m.add_function(wrap_pyfunction!(find_hashes, m)?)?;
+    m.add_function(wrap_pyfunction!(scrape_text, m)?)?;
+    m.add_function(wrap_pyfunction!(defang, m)?)?;
+    m.add_function(wrap_pyfunction!(refang, m)?)?;
+    m.add_function(wrap_pyfunction!(fang_state, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_ioc, m)?)?;
+    m.add_function(wrap_pyfunction!(hash_bytes, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! token bucket rate limiter shared by API clients
+//!
+//! Clones of a `RateLimiter` share one bucket, so passing clones of it to several clients
+//! (or tasks) keeps their requests in total within the limit of the provider.
+//! Waiting tasks take tokens in the order they started waiting.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::http::block_on;
+use crate::metrics;
+
+#[derive(Debug)]
+struct State {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    /// time to refill a token
+    refill: Duration,
+    state: Mutex<State>,
+    /// waiters queue (tokio mutex is fair, so that tokens are given in FIFO order)
+    queue: tokio::sync::Mutex<()>,
+}
+
+impl Bucket {
+    /// take a token, or returns duration to wait for next token
+    fn take(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.updated_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed / self.refill.as_secs_f64()).min(self.capacity);
+        state.updated_at = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.refill.mul_f64(1.0 - state.tokens))
+        }
+    }
+}
+
+/// token bucket rate limiter (clones share the bucket)
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    bucket: Arc<Bucket>,
+}
+
+impl RateLimiter {
+    /// allow `requests` per `per` (bursts up to `requests` are allowed)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::ratelimit::RateLimiter;
+    /// use std::time::Duration;
+    ///
+    /// let limiter = RateLimiter::new(2, Duration::from_secs(60));
+    /// assert!(limiter.try_acquire());
+    /// assert!(limiter.clone().try_acquire());
+    /// // the bucket is shared with the clone
+    /// assert!(!limiter.try_acquire());
+    /// ```
+    pub fn new(requests: u32, per: Duration) -> Self {
+        let requests = requests.max(1);
+        RateLimiter {
+            bucket: Arc::new(Bucket {
+                capacity: f64::from(requests),
+                refill: per / requests,
+                state: Mutex::new(State {
+                    tokens: f64::from(requests),
+                    updated_at: Instant::now(),
+                }),
+                queue: tokio::sync::Mutex::new(()),
+            }),
+        }
+    }
+
+    /// allow `requests` per second
+    pub fn per_second(requests: u32) -> Self {
+        RateLimiter::new(requests, Duration::from_secs(1))
+    }
+
+    /// allow `requests` per minute
+    pub fn per_minute(requests: u32) -> Self {
+        RateLimiter::new(requests, Duration::from_secs(60))
+    }
+
+    /// limit of VirusTotal public API (4 requests per minute)
+    pub fn virustotal_public() -> Self {
+        RateLimiter::per_minute(4)
+    }
+
+    /// limit of the lowest Have I Been Pwned subscription (10 requests per minute)
+    pub fn hibp() -> Self {
+        RateLimiter::per_minute(10)
+    }
+
+    /// take a token if available (without waiting)
+    pub fn try_acquire(&self) -> bool {
+        match self.bucket.queue.try_lock() {
+            Ok(_turn) => self.bucket.take().is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// wait until a token is available and take it
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::ratelimit::RateLimiter;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let limiter = RateLimiter::new(1, Duration::from_millis(200));
+    /// let start = Instant::now();
+    /// limiter.acquire_blocking();
+    /// limiter.acquire_blocking();
+    /// assert!(start.elapsed() >= Duration::from_millis(150));
+    /// ```
+    pub async fn acquire(&self) {
+        let _turn = self.bucket.queue.lock().await;
+        while let Err(wait) = self.bucket.take() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// wait until a token is available and take it (blocking)
+    pub fn acquire_blocking(&self) {
+        block_on(self.acquire())
+    }
+}
+
+/// wait for a token if rate limited
+pub(crate) async fn wait(provider: &str, limiter: &Option<RateLimiter>) {
+    if let Some(l) = limiter {
+        let started = Instant::now();
+        l.acquire().await;
+        let waited = started.elapsed();
+        if waited >= Duration::from_millis(1) {
+            metrics::report(|m| m.rate_limit_wait(provider, waited));
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Markdown / HTML report of indicators (all values are defanged)
+
+use std::collections::BTreeMap;
+
+use crate::allowlist::suppress;
+use crate::ioc::{Indicator, IocKind};
+use crate::provider::{ProviderReport, Verdict};
+use crate::ttl::unexpired;
+use crate::util::escape_xml;
+
+/// report builder (when rendered, indicators allowed by the export allowlist are suppressed and
+/// expired ones are excluded unless `include_expired`)
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    title: String,
+    indicators: Vec<Indicator>,
+    verdicts: Vec<ProviderReport>,
+    include_expired: bool,
+}
+
+/// a cell of table
+struct Cell {
+    text: String,
+    link: Option<String>,
+}
+
+impl Cell {
+    fn text(text: impl AsRef<str>) -> Self {
+        Cell {
+            text: text.as_ref().to_owned(),
+            link: None,
+        }
+    }
+}
+
+fn heading(kind: IocKind) -> &'static str {
+    match kind {
+        IocKind::Hash => "Hashes",
+        IocKind::Domain => "Domains",
+        IocKind::Url => "URLs",
+        IocKind::IpAddress => "IP addresses",
+        IocKind::EmailAddress => "Email addresses",
+    }
+}
+
+fn verdict_name(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::Unknown => "unknown",
+        Verdict::Harmless => "harmless",
+        Verdict::Suspicious => "suspicious",
+        Verdict::Malicious => "malicious",
+    }
+}
+
+impl Report {
+    /// new report with title
+    pub fn new(title: impl AsRef<str>) -> Self {
+        Report {
+            title: title.as_ref().to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// add indicators
+    pub fn indicators(
+        mut self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    ) -> Self {
+        self.indicators
+            .extend(indicators.into_iter().map(|x| x.into()));
+        self
+    }
+
+    /// add verdicts of providers (a column per provider)
+    pub fn verdicts(mut self, reports: impl IntoIterator<Item = ProviderReport>) -> Self {
+        self.verdicts.extend(reports);
+        self
+    }
+
+    /// report expired indicators too (see `iocutil::ttl`, excluded by default)
+    pub fn include_expired(mut self, include_expired: bool) -> Self {
+        self.include_expired = include_expired;
+        self
+    }
+
+    fn providers(&self) -> Vec<String> {
+        let mut providers = vec![];
+        for r in &self.verdicts {
+            if !providers.contains(&r.provider) {
+                providers.push(r.provider.clone());
+            }
+        }
+        providers
+    }
+
+    /// header and rows of tables grouped by kind
+    fn tables(&self) -> BTreeMap<IocKind, (Vec<String>, Vec<Vec<Cell>>)> {
+        let providers = self.providers();
+        let mut tables = BTreeMap::new();
+
+        for i in suppress(unexpired(
+            self.indicators.iter().cloned(),
+            self.include_expired,
+        )) {
+            let (_, rows) = tables.entry(i.ioc.kind()).or_insert_with(|| {
+                let mut header: Vec<_> = vec!["value", "first seen", "source"]
+                    .into_iter()
+                    .map(|x| x.to_owned())
+                    .collect();
+                header.extend(providers.iter().cloned());
+                (header, vec![])
+            });
+
+            let mut row = vec![
+                Cell::text(i.ioc.defanged()),
+                Cell::text(
+                    i.meta
+                        .first_seen
+                        .map(|x| x.format("%Y-%m-%d").to_string())
+                        .unwrap_or_else(|| "-".to_owned()),
+                ),
+                Cell::text(i.meta.source.as_deref().unwrap_or("-")),
+            ];
+
+            for p in &providers {
+                let r = self
+                    .verdicts
+                    .iter()
+                    .find(|r| &r.provider == p && r.ioc == i.ioc);
+                row.push(match r {
+                    Some(r) if !r.found => Cell::text("not found"),
+                    Some(r) => Cell {
+                        text: verdict_name(r.verdict).to_owned(),
+                        link: r.permalink.clone(),
+                    },
+                    None => Cell::text("-"),
+                });
+            }
+            rows.push(row);
+        }
+
+        tables
+    }
+
+    /// render as Markdown
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::report::Report;
+    ///
+    /// let ioc = Ioc::new("evil.example.com").unwrap();
+    /// let mut verdict = ProviderReport::new("VirusTotal", ioc.clone(), Verdict::Malicious);
+    /// verdict.permalink = Some("https://www.virustotal.com/gui/domain/evil.example.com".to_owned());
+    ///
+    /// let md = Report::new("Incident #1")
+    ///     .indicators(vec![ioc, Ioc::new("192.0.2.1").unwrap()])
+    ///     .verdicts(vec![verdict])
+    ///     .to_markdown();
+    ///
+    /// assert!(md.starts_with("# Incident #1\n\n## Domains (1)\n\n| value | first seen | source | VirusTotal |\n"));
+    /// assert!(md.contains("| evil[.]example[.]com | - | - | [malicious](https://www.virustotal.com/gui/domain/evil.example.com) |\n"));
+    /// assert!(md.contains("| 192[.]0[.]2[.]1 | - | - | - |\n"));
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        let escape = |x: &str| x.replace('|', "\\|").replace('\n', " ");
+        let mut md = format!("# {}\n", escape(&self.title));
+
+        for (kind, (header, rows)) in self.tables() {
+            md.push_str(&format!("\n## {} ({})\n\n", heading(kind), rows.len()));
+            let header: Vec<_> = header.iter().map(|h| escape(h)).collect();
+            md.push_str(&format!("| {} |\n", header.join(" | ")));
+            md.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+            for row in rows {
+                let cells: Vec<_> = row
+                    .iter()
+                    .map(|c| match &c.link {
+                        Some(l) => format!("[{}]({})", escape(&c.text), l),
+                        None => escape(&c.text),
+                    })
+                    .collect();
+                md.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+        }
+
+        md
+    }
+
+    /// render as HTML fragment
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::report::Report;
+    ///
+    /// let html = Report::new("<Incident>")
+    ///     .indicators(vec![Ioc::new("http://evil.example.com/?a=1&b=2").unwrap()])
+    ///     .to_html();
+    ///
+    /// assert!(html.starts_with("<h1>&lt;Incident&gt;</h1>\n<h2>URLs (1)</h2>\n<table>\n"));
+    /// assert!(html.contains("<td>hxxp://evil[.]example[.]com/?a=1&amp;b=2</td>"));
+    /// ```
+    pub fn to_html(&self) -> String {
+        let mut html = format!("<h1>{}</h1>\n", escape_xml(&self.title));
+
+        for (kind, (header, rows)) in self.tables() {
+            html.push_str(&format!(
+                "<h2>{} ({})</h2>\n<table>\n<thead><tr>",
+                heading(kind),
+                rows.len()
+            ));
+            for h in header {
+                html.push_str(&format!("<th>{}</th>", escape_xml(h)));
+            }
+            html.push_str("</tr></thead>\n<tbody>\n");
+            for row in rows {
+                html.push_str("<tr>");
+                for c in row {
+                    match &c.link {
+                        Some(l) => html.push_str(&format!(
+                            "<td><a href=\"{}\">{}</a></td>",
+                            escape_xml(l),
+                            escape_xml(&c.text)
+                        )),
+                        None => html.push_str(&format!("<td>{}</td>", escape_xml(&c.text))),
+                    }
+                }
+                html.push_str("</tr>\n");
+            }
+            html.push_str("</tbody>\n</table>\n");
+        }
+
+        html
+    }
+}
@@ -0,0 +1,135 @@
+//! cross-algorithm hash resolution (e.g. sha256 of a sample known only by md5)
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::Mutex;
+
+use crate::contenthash::SampleHashes;
+use crate::util::unwrap_try_into;
+use crate::{GenericResult, SampleHash};
+
+/// source of hashes of samples (e.g. VirusTotal, MalwareBazaar or local sample store)
+pub trait HashResolver {
+    /// every known hash of the sample (`None` if the sample is unknown)
+    fn resolve(&self, hash: &SampleHash) -> GenericResult<Option<SampleHashes>>;
+}
+
+/// hashes of local samples (e.g. results of `hash_tree`)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::resolve::{HashIndex, HashResolver};
+///
+/// let mut index = HashIndex::new();
+/// index.insert(SampleHash::of_bytes(b""));
+///
+/// let found = index.resolve(&sample!("d41d8cd98f00b204e9800998ecf8427e")).unwrap().unwrap();
+/// assert_eq!(found.sha256, sample!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+/// ```
+#[derive(Default, Debug, Clone)]
+pub struct HashIndex {
+    hashes: HashMap<SampleHash, SampleHashes>,
+}
+
+impl HashIndex {
+    /// empty index
+    pub fn new() -> Self {
+        HashIndex::default()
+    }
+
+    /// add hashes of a sample
+    pub fn insert(&mut self, hashes: impl Into<SampleHashes>) {
+        let hashes = hashes.into();
+        for h in hashes.content_hash() {
+            self.hashes.insert(h, hashes.clone());
+        }
+    }
+
+    /// number of samples
+    pub fn len(&self) -> usize {
+        self.hashes.len() / 3
+    }
+
+    /// the index has no sample?
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+}
+
+impl HashResolver for HashIndex {
+    fn resolve(&self, hash: &SampleHash) -> GenericResult<Option<SampleHashes>> {
+        Ok(self.hashes.get(hash).cloned())
+    }
+}
+
+/// resolver consults resolvers in order and caches results
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::resolve::{HashIndex, Resolver};
+///
+/// let mut local = HashIndex::new();
+/// local.insert(SampleHash::of_bytes(b""));
+///
+/// // e.g. `.with(VirusTotalClient::default())` to ask VirusTotal if local store does not know
+/// let resolver = Resolver::new().with(local);
+///
+/// let hashes = resolver.resolve("d41d8cd98f00b204e9800998ecf8427e").unwrap().unwrap();
+/// assert_eq!(hashes.sha1, sample!("da39a3ee5e6b4b0d3255bfef95601890afd80709"));
+/// assert!(resolver.resolve("9fbdc5eca123e81571e8966b9b4e4a1e").unwrap().is_none());
+/// ```
+#[derive(Default)]
+pub struct Resolver {
+    resolvers: Vec<Box<dyn HashResolver>>,
+    cache: Mutex<HashIndex>,
+}
+
+impl Resolver {
+    /// resolver without any source
+    pub fn new() -> Self {
+        Resolver::default()
+    }
+
+    /// add a source (consulted after sources added before)
+    pub fn with(mut self, resolver: impl HashResolver + 'static) -> Self {
+        self.resolvers.push(Box::new(resolver));
+        self
+    }
+
+    /// every known hash of the sample
+    ///
+    /// Results are cached, so each sample is asked at most once as long as it is found.
+    /// Errors of a source are ignored if another source knows the sample.
+    pub fn resolve(&self, hash: impl TryInto<SampleHash>) -> GenericResult<Option<SampleHashes>> {
+        let hash = unwrap_try_into(hash)?;
+        if let Some(x) = self.cache.lock().unwrap().resolve(&hash)? {
+            return Ok(Some(x));
+        }
+
+        let mut error = None;
+        for r in self.resolvers.iter() {
+            match r.resolve(&hash) {
+                Ok(Some(x)) => {
+                    self.cache.lock().unwrap().insert(x.clone());
+                    return Ok(Some(x));
+                }
+                Ok(None) => {}
+                Err(e) => error = error.or(Some(e)),
+            }
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    /// seed the cache with known hashes
+    pub fn remember(&self, hashes: impl Into<SampleHashes>) {
+        self.cache.lock().unwrap().insert(hashes);
+    }
+}
@@ -0,0 +1,140 @@
+//! retrying requests with jittered exponential backoff (honoring `Retry-After`)
+
+use derive_builder::Builder;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// policy of retrying throttled / failed requests
+#[derive(Builder, Debug, Clone, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// number of attempts including the first one (1 means no retry)
+    #[builder(default = "4")]
+    max_attempts: u32,
+    /// delay before the first retry (doubled on each retry)
+    #[builder(default = "Duration::from_millis(500)")]
+    base_delay: Duration,
+    /// upper bound of delay (`Retry-After` longer than this gives up retrying)
+    #[builder(default = "Duration::from_secs(60)")]
+    max_delay: Duration,
+    /// status codes worth retrying
+    #[builder(default = "vec![429, 500, 502, 503, 504]")]
+    statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicyBuilder::default().build().unwrap()
+    }
+}
+
+/// random number in `[0, 1)` (enough for jitter)
+fn random() -> f64 {
+    let r = RandomState::new().build_hasher().finish();
+    (r >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// parse `Retry-After` (delay seconds or HTTP date)
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(secs as u64))
+}
+
+/// delay requested by `Retry-After` of response
+pub(crate) fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    res.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|x| x.to_str().ok())
+        .and_then(parse_retry_after)
+}
+
+impl RetryPolicy {
+    /// never retry
+    pub fn none() -> Self {
+        RetryPolicyBuilder::default()
+            .max_attempts(1)
+            .build()
+            .unwrap()
+    }
+
+    /// number of attempts including the first one
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// also retry on the status code (e.g. VirusTotal returns `204` when quota exceeded)
+    pub fn retry_status(mut self, status: u16) -> Self {
+        if !self.statuses.contains(&status) {
+            self.statuses.push(status);
+        }
+        self
+    }
+
+    /// whether the status code is worth retrying
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::retry::RetryPolicy;
+    ///
+    /// let policy = RetryPolicy::default();
+    /// assert!(policy.is_retryable(429));
+    /// assert!(policy.is_retryable(503));
+    /// assert!(!policy.is_retryable(404));
+    /// assert!(policy.retry_status(204).is_retryable(204));
+    /// ```
+    pub fn is_retryable(&self, status: u16) -> bool {
+        self.statuses.contains(&status)
+    }
+
+    /// jittered delay before `retry`-th retry (starting from 1)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::retry::RetryPolicyBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicyBuilder::default()
+    ///     .base_delay(Duration::from_secs(1))
+    ///     .max_delay(Duration::from_secs(5))
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(policy.backoff(1) <= Duration::from_secs(1));
+    /// assert!(policy.backoff(10) <= Duration::from_secs(5));
+    /// ```
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .checked_mul(1 << retry.saturating_sub(1).min(31))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        // half fixed and half random ("equal jitter")
+        exp / 2 + exp.mul_f64(random() / 2.0)
+    }
+
+    /// delay before `retry`-th retry of a response, `None` if it should not be retried
+    pub(crate) fn delay(&self, retry: u32, res: &reqwest::Response) -> Option<Duration> {
+        if retry >= self.max_attempts || !self.is_retryable(res.status().as_u16()) {
+            return None;
+        }
+        match retry_after(res) {
+            Some(d) if d > self.max_delay => None,
+            Some(d) => Some(d),
+            None => Some(self.backoff(retry)),
+        }
+    }
+
+    /// delay before `retry`-th retry of a failed request, `None` if it should not be retried
+    pub(crate) fn delay_on_error(&self, retry: u32, e: &reqwest::Error) -> Option<Duration> {
+        if retry >= self.max_attempts || !(e.is_timeout() || e.is_connect()) {
+            return None;
+        }
+        Some(self.backoff(retry))
+    }
+}
@@ -0,0 +1,492 @@
+//! single pass scanning engine of the scraper
+//!
+//! Tokens are split and checked for anchors of indicators (`.`, `:`, `@`, `://` and defanged
+//! forms like `[.]` or `[at]`) by one Aho-Corasick automaton in the same pass, so that only
+//! tokens which may be indicators are refanged and parsed. Hashes are found as runs of hex digits
+//! in a pass as well, instead of a regex scan for each kind.
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, FindIter, MatchKind};
+use derive_builder::Builder;
+use lazy_static::lazy_static;
+use std::iter::Peekable;
+use std::net::IpAddr;
+
+use crate::allowlist::{Allowlist, SharedAllowlist};
+use crate::confidence::Confidence;
+use crate::ioc::{host_of_url, may_be_defanged, refang, Indicator, Ioc, IocKind};
+use crate::ipscope::IpAddressExt;
+use crate::validate::kind_of;
+
+/// anchors of a character, which count only if followed by a part of the token
+const CHAR_ANCHORS: [&str; 3] = [".", ":", "@"];
+
+/// defanged forms
+const DEFANGED: [&str; 10] = [
+    "[.]", "(.)", "{.}", "[dot]", "(dot)", "[@]", "(@)", "[at]", "(at)", "[:]",
+];
+
+/// defanged forms trimmed from end of tokens (as `.` and `:`)
+const DEFANGED_END: [&str; 6] = ["[.]", "(.)", "{.}", "[dot]", "(dot)", "[:]"];
+
+lazy_static! {
+    static ref ANCHORS: AhoCorasick = AhoCorasickBuilder::new()
+        .ascii_case_insensitive(true)
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(
+            CHAR_ANCHORS
+                .iter()
+                .chain(["://"].iter().chain(DEFANGED.iter()))
+        )
+        .expect("failed to build anchors of indicators");
+}
+
+/// lengths of hex digest of md5, sha1 and sha256
+pub(crate) const HASH_LENGTHS: [usize; 3] = [32, 40, 64];
+
+/// characters trimmed from start of tokens
+pub(crate) const TRIM_START: [char; 3] = ['(', '[', '{'];
+
+/// characters trimmed from end of tokens
+pub(crate) const TRIM_END: [char; 7] = ['.', ':', '!', '?', ')', ']', '}'];
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "\"'`<>,;|".contains(c)
+}
+
+/// token trimmed of surrounding brackets and punctuations
+pub(crate) fn trim(token: &str) -> &str {
+    token
+        .trim_start_matches(&TRIM_START[..])
+        .trim_end_matches(&TRIM_END[..])
+}
+
+fn starts_with_ignore_case(x: &str, prefix: &str) -> bool {
+    x.len() >= prefix.len() && x.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+fn ends_with_ignore_case(x: &str, suffix: &str) -> bool {
+    x.len() >= suffix.len()
+        && x.as_bytes()[x.len() - suffix.len()..].eq_ignore_ascii_case(suffix.as_bytes())
+}
+
+/// defanged token trimmed as if it was refanged (brackets of defanged forms are kept)
+fn trim_defanged(token: &str) -> &str {
+    let mut x = token;
+    while x.starts_with(&TRIM_START[..]) && !DEFANGED.iter().any(|p| starts_with_ignore_case(x, p))
+    {
+        x = &x[1..];
+    }
+    loop {
+        if let Some(p) = DEFANGED_END.iter().find(|p| ends_with_ignore_case(x, p)) {
+            x = &x[..x.len() - p.len()];
+        } else if x.ends_with(&TRIM_END[..])
+            && !DEFANGED.iter().any(|p| ends_with_ignore_case(x, p))
+        {
+            x = &x[..x.len() - 1];
+        } else {
+            return x;
+        }
+    }
+}
+
+/// indicator found in text, borrowing its span from the text
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::scan::iocs;
+///
+/// let text = "beacon to (evil[.]example[.]com) and 192.0.2.1.";
+/// let found: Vec<_> = iocs(text).collect();
+/// assert_eq!(found[0].as_str(), "evil[.]example[.]com");
+/// assert_eq!((found[0].offset(), found[0].kind()), (11, IocKind::Domain));
+/// assert!(found[0].is_defanged());
+/// assert_eq!(found[1].as_str(), "192.0.2.1");
+///
+/// // owned (refanged) indicator to persist
+/// assert_eq!(found[0].to_owned(), Ioc::new("evil.example.com").unwrap());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct IocRef<'a> {
+    kind: IocKind,
+    span: &'a str,
+    offset: usize,
+    defanged: bool,
+}
+
+impl<'a> IocRef<'a> {
+    /// detect indicator in token of text
+    fn detect(text: &'a str, token: &'a str) -> Option<Self> {
+        let defanged = may_be_defanged(token);
+        let (span, kind) = if defanged {
+            let span = trim_defanged(token);
+            (span, kind_of(trim(&refang(span)))?)
+        } else {
+            let span = trim(token);
+            (span, kind_of(span)?)
+        };
+        Some(IocRef {
+            kind,
+            span,
+            offset: span.as_ptr() as usize - text.as_ptr() as usize,
+            defanged,
+        })
+    }
+
+    /// kind of indicator
+    pub fn kind(&self) -> IocKind {
+        self.kind
+    }
+
+    /// span of the indicator in text, as written (possibly defanged)
+    pub fn as_str(&self) -> &'a str {
+        self.span
+    }
+
+    /// byte offset of the span in text
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// is the span defanged? (it may contain brackets or defanged scheme)
+    pub fn is_defanged(&self) -> bool {
+        self.defanged
+    }
+
+    /// owned indicator (refanged and normalized, e.g. domains are lowercased)
+    pub fn to_owned(self) -> Ioc {
+        let ioc = if self.defanged {
+            Ioc::of_kind(self.kind, trim(&refang(self.span)))
+        } else {
+            Ioc::of_kind(self.kind, self.span)
+        };
+        ioc.expect("kind of indicator was detected in scanning")
+    }
+}
+
+impl<'a> From<IocRef<'a>> for Ioc {
+    fn from(x: IocRef<'a>) -> Self {
+        x.to_owned()
+    }
+}
+
+/// indicators in (possibly defanged) text, borrowing from the text (not deduplicated)
+pub fn iocs(text: &str) -> impl Iterator<Item = IocRef<'_>> {
+    candidates(text).filter_map(move |token| IocRef::detect(text, token))
+}
+
+/// options of scraping indicators
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::allowlist::Tranco;
+/// use iocutil::scan::ScrapeOptionsBuilder;
+///
+/// let text = "beacon from 10.0.0.5 to 198.51.100.7 and 203.0.114.9, admin panel http://192.168.1.1/login";
+/// let options = ScrapeOptionsBuilder::default().public_ips_only(true).build().unwrap();
+/// let iocs: Vec<Ioc> = options.find(text);
+/// assert_eq!(iocs, vec![Ioc::new("203.0.114.9").unwrap()]);
+///
+/// // top domains in the article are not indicators
+/// let tranco = Tranco::parse("1,google.com\n2,microsoft.com", 100);
+/// let options = ScrapeOptionsBuilder::default().allowlist(tranco).build().unwrap();
+/// let iocs: Vec<Ioc> = options.find("searched on google[.]com, then visited evil[.]example");
+/// assert_eq!(iocs, vec![Ioc::new("evil.example").unwrap()]);
+/// ```
+#[derive(Builder, Debug, Clone, Default, Eq, PartialEq)]
+pub struct ScrapeOptions {
+    /// drop IP addresses which are not public (private, loopback, link-local and reserved)
+    /// and URLs / email addresses on them, not to leak internal addresses into shared feeds
+    #[builder(default = "false")]
+    public_ips_only: bool,
+    /// confidence given to indicators by `indicators` (e.g. by trust in the source document)
+    #[builder(setter(strip_option), default)]
+    confidence: Option<Confidence>,
+    /// drop indicators allowed by the list (e.g. top domains mentioned in articles)
+    #[builder(setter(into, strip_option), default)]
+    allowlist: Option<SharedAllowlist>,
+}
+
+impl ScrapeOptions {
+    /// the indicator is kept by the options?
+    pub fn accepts(&self, ioc: &Ioc) -> bool {
+        if self.allowlist.as_ref().is_some_and(|x| x.0.is_allowed(ioc)) {
+            return false;
+        }
+        if !self.public_ips_only {
+            return true;
+        }
+        let ip = match ioc {
+            Ioc::IpAddress(ip) => Some(*ip),
+            Ioc::Url(x) => match host_of_url(x) {
+                Some(Ioc::IpAddress(ip)) => Some(ip),
+                _ => None,
+            },
+            Ioc::EmailAddress(x) => x
+                .rsplit('@')
+                .next()
+                .map(|x| x.trim_start_matches('[').trim_end_matches(']'))
+                .and_then(|x| x.parse().ok()),
+            _ => None,
+        };
+        ip.is_none_or(|ip: IpAddr| ip.is_public())
+    }
+
+    /// unique indicators in (possibly defanged) text kept by the options, in order of appearance
+    pub fn find<T>(&self, text: impl AsRef<str>) -> T
+    where
+        T: std::iter::FromIterator<Ioc>,
+    {
+        Ioc::find::<Vec<Ioc>>(text)
+            .into_iter()
+            .filter(|x| self.accepts(x))
+            .collect()
+    }
+
+    /// unique indicators kept by the options with confidence of the options
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::confidence::{Confidence, ConfidenceBand};
+    /// use iocutil::scan::ScrapeOptionsBuilder;
+    ///
+    /// let options = ScrapeOptionsBuilder::default()
+    ///     .confidence(ConfidenceBand::Medium.into())
+    ///     .build()
+    ///     .unwrap();
+    /// let i = options.indicators("C2: evil[.]example[.]com");
+    /// assert_eq!(i[0].meta.confidence, Some(Confidence::new(50)));
+    /// ```
+    pub fn indicators(&self, text: impl AsRef<str>) -> Vec<Indicator> {
+        self.find::<Vec<Ioc>>(text)
+            .into_iter()
+            .map(|ioc| {
+                let mut i = Indicator::new(ioc);
+                i.meta.confidence = self.confidence;
+                i
+            })
+            .collect()
+    }
+}
+
+/// tokens which may be indicators (possibly defanged and not trimmed yet), in order of appearance
+///
+/// # Example
+///
+/// ```
+/// use iocutil::scan::candidates;
+///
+/// let text = "see hxxp://evil[.]example/a, or 192.0.2.1. d41d8cd98f00b204e9800998ecf8427e done.";
+/// assert_eq!(
+///     candidates(text).collect::<Vec<_>>(),
+///     vec!["hxxp://evil[.]example/a", "192.0.2.1.", "d41d8cd98f00b204e9800998ecf8427e"]
+/// );
+/// ```
+pub fn candidates(text: &str) -> Candidates<'_> {
+    Candidates {
+        text,
+        pos: 0,
+        anchors: ANCHORS.find_iter(text).peekable(),
+    }
+}
+
+/// iterator of `candidates`
+pub struct Candidates<'a> {
+    text: &'a str,
+    pos: usize,
+    anchors: Peekable<FindIter<'static, 'a>>,
+}
+
+impl<'a> Candidates<'a> {
+    /// skip delimiters from `pos`, returning start of next token
+    fn skip_delimiters(&self, mut pos: usize) -> usize {
+        let bytes = self.text.as_bytes();
+        while pos < bytes.len() {
+            let c = match bytes[pos] {
+                b if b.is_ascii() => b as char,
+                _ => self.text[pos..].chars().next().unwrap(),
+            };
+            if !is_delimiter(c) {
+                break;
+            }
+            pos += c.len_utf8();
+        }
+        pos
+    }
+
+    /// end of token starting at `pos`
+    fn token_end(&self, mut pos: usize) -> usize {
+        let bytes = self.text.as_bytes();
+        while pos < bytes.len() {
+            let c = match bytes[pos] {
+                b if b.is_ascii() => b as char,
+                _ => self.text[pos..].chars().next().unwrap(),
+            };
+            if is_delimiter(c) {
+                break;
+            }
+            pos += c.len_utf8();
+        }
+        pos
+    }
+
+    /// consume anchors in the token, returning whether any of them counts
+    fn anchored(&mut self, end: usize) -> bool {
+        let bytes = self.text.as_bytes();
+        let mut anchored = false;
+        while let Some(m) = self.anchors.peek() {
+            if m.start() >= end {
+                break;
+            }
+            anchored = anchored
+                || m.pattern().as_usize() >= CHAR_ANCHORS.len()
+                || (m.end() < end && !TRIM_END.contains(&(bytes[m.end()] as char)));
+            self.anchors.next();
+        }
+        anchored
+    }
+}
+
+impl<'a> Iterator for Candidates<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        while self.pos < self.text.len() {
+            let start = self.skip_delimiters(self.pos);
+            let end = self.token_end(start);
+            self.pos = end;
+            if start == end {
+                continue;
+            }
+
+            let token = &self.text[start..end];
+            if self.anchored(end) || is_hex_digest(trim(token)) {
+                return Some(token);
+            }
+        }
+        None
+    }
+}
+
+fn is_hex_digest(x: &str) -> bool {
+    HASH_LENGTHS.contains(&x.len()) && x.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// runs of hex digits of specified lengths (bounded by non hex digits), in order of appearance
+///
+/// # Example
+///
+/// ```
+/// use iocutil::scan::hex_runs;
+///
+/// let text = "md5=d41d8cd98f00b204e9800998ecf8427e-da39a3ee5e6b4b0d3255bfef95601890afd80709 0123";
+/// assert_eq!(
+///     hex_runs(text, &[32, 40]).collect::<Vec<_>>(),
+///     vec!["d41d8cd98f00b204e9800998ecf8427e", "da39a3ee5e6b4b0d3255bfef95601890afd80709"]
+/// );
+/// ```
+pub fn hex_runs<'a>(text: &'a str, lengths: &'a [usize]) -> impl Iterator<Item = &'a str> + 'a {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        while pos < bytes.len() {
+            while pos < bytes.len() && !bytes[pos].is_ascii_hexdigit() {
+                pos += 1;
+            }
+            let start = pos;
+            while pos < bytes.len() && bytes[pos].is_ascii_hexdigit() {
+                pos += 1;
+            }
+            if lengths.contains(&(pos - start)) {
+                return Some(&text[start..pos]);
+            }
+        }
+        None
+    })
+}
+
+/// size of windows scanning a mapped file
+#[cfg(feature = "mmap")]
+const WINDOW_SIZE: usize = 8 << 20;
+
+/// windows of data ending at delimiters (so that no token is split unless it is longer than a window)
+#[cfg(feature = "mmap")]
+fn windows(data: &[u8]) -> Vec<std::ops::Range<usize>> {
+    let mut windows = vec![];
+    let mut start = 0;
+    while start < data.len() {
+        let mut end = (start + WINDOW_SIZE).min(data.len());
+        if end < data.len() {
+            // ascii delimiters never appear inside multibyte characters
+            if let Some(i) = data[start..end]
+                .iter()
+                .rposition(|b| b.is_ascii_whitespace() || b"\"'`<>,;|".contains(b))
+            {
+                end = start + i + 1;
+            }
+        }
+        windows.push(start..end);
+        start = end;
+    }
+    windows
+}
+
+/// unique indicators in (possibly defanged) text of a large file, in order of appearance
+///
+/// The file is memory mapped instead of read into memory, and scanned in windows in parallel.
+/// Invalid UTF-8 sequences are replaced (as `String::from_utf8_lossy`), so that binary data
+/// in logs does not stop scanning.
+/// The file must not be modified while scanning.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::scan::scan_file_mmap;
+///
+/// let path = std::env::temp_dir().join("iocutil-scan-file-mmap.log");
+/// std::fs::write(&path, b"GET hxxp://evil[.]example/a from 192.0.2.1 \xff\xfe 192.0.2.1\n").unwrap();
+/// let iocs: Vec<Ioc> = scan_file_mmap(&path).unwrap();
+/// assert_eq!(
+///     iocs,
+///     vec![Ioc::new("http://evil.example/a").unwrap(), Ioc::new("192.0.2.1").unwrap()]
+/// );
+/// ```
+#[cfg(feature = "mmap")]
+pub fn scan_file_mmap<T>(path: impl AsRef<std::path::Path>) -> crate::GenericResult<T>
+where
+    T: std::iter::FromIterator<Ioc>,
+{
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+    use std::collections::HashSet;
+
+    let file = std::fs::File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(std::iter::empty().collect());
+    }
+    // safety: the file is not modified while mapped (required to callers)
+    let data = unsafe { memmap2::Mmap::map(&file)? };
+
+    let found: Vec<Vec<Ioc>> = windows(&data)
+        .into_par_iter()
+        .map(|range| {
+            let text = String::from_utf8_lossy(&data[range]);
+            let mut seen = HashSet::new();
+            iocs(&text)
+                .map(IocRef::to_owned)
+                .filter(|x| seen.insert(x.normalized()))
+                .collect()
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    Ok(found
+        .into_iter()
+        .flatten()
+        .filter(|x| seen.insert(x.normalized()))
+        .collect())
+}
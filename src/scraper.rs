@@ -1,5 +1,7 @@
 //! utilities for scraping web pages
 
+#[cfg(feature = "net")]
+use crate::http::{self, block_on};
 use crate::GenericResult;
 use failure::Fail;
 use lazy_static::lazy_static;
@@ -11,8 +13,20 @@ lazy_static! {
 }
 
 /// get html from specified url
+#[cfg(feature = "net")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(url = url.as_ref()))
+)]
 pub fn get_html(url: impl AsRef<str>) -> GenericResult<String> {
-    Ok(reqwest::get(url.as_ref())?.text()?)
+    Ok(block_on(async {
+        http::shared_client()
+            .get(url.as_ref())
+            .send()
+            .await?
+            .text()
+            .await
+    })?)
 }
 
 /// scrape articles from html text
@@ -40,16 +54,36 @@ pub enum ScrapingError {
     TargetNotFound,
 }
 
-/// get article (or body) text from specified url
-pub fn get_article(url: impl AsRef<str>) -> GenericResult<String> {
-    let html = get_html(url)?;
-    let articles = scrape_articles(&html);
+/// article (or body) text in html text
+///
+/// # Example
+///
+/// ```
+/// use iocutil::scraper::article_text;
+///
+/// let html = "<html><body><nav>menu</nav><article>C2: evil[.]example[.]com</article></body></html>";
+/// assert_eq!(article_text(html).unwrap(), "C2: evil[.]example[.]com");
+/// assert!(article_text("").unwrap().trim().is_empty());
+/// ```
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bytes = html.as_ref().len()))
+)]
+pub fn article_text(html: impl AsRef<str>) -> GenericResult<String> {
+    let html = html.as_ref();
+    let articles = scrape_articles(html);
     if !articles.is_empty() {
         return Ok(articles.join("\n"));
     }
-    let body = scrape_body(&html);
+    let body = scrape_body(html);
     if body.is_empty() {
         return Err(ScrapingError::TargetNotFound.into());
     }
     Ok(body.join("\n"))
 }
+
+/// get article (or body) text from specified url
+#[cfg(feature = "net")]
+pub fn get_article(url: impl AsRef<str>) -> GenericResult<String> {
+    article_text(get_html(url)?)
+}
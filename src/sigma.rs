@@ -0,0 +1,239 @@
+//! Sigma rule generation for host indicators
+
+use chrono::Utc;
+use derive_builder::Builder;
+
+use crate::allowlist::suppress;
+use crate::ioc::{Indicator, Ioc};
+use crate::ttl::unexpired;
+use crate::util::deterministic_uuid;
+use crate::SampleHash;
+
+/// an indicator observed on host
+#[derive(Clone, PartialEq, Debug)]
+pub enum HostIndicator {
+    Hash(SampleHash),
+    FilePath(String),
+    RegistryKey(String),
+    Mutex(String),
+    /// indicator with metadata (only hashes are ruled, others are skipped)
+    Indicator(Box<Indicator>),
+}
+
+impl From<SampleHash> for HostIndicator {
+    fn from(hash: SampleHash) -> Self {
+        HostIndicator::Hash(hash)
+    }
+}
+
+impl From<Indicator> for HostIndicator {
+    fn from(indicator: Indicator) -> Self {
+        HostIndicator::Indicator(Box::new(indicator))
+    }
+}
+
+/// level of generated rules
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Level {
+    Informational,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Informational => "informational",
+            Level::Low => "low",
+            Level::Medium => "medium",
+            Level::High => "high",
+            Level::Critical => "critical",
+        }
+    }
+}
+
+/// options of generated rules
+///
+/// Each kind of indicator has its own logsource category.
+/// (Mutex has no standard category in Sigma taxonomy, so specify the one your backend uses.)
+#[derive(Builder, Debug, Clone)]
+pub struct SigmaOptions {
+    #[builder(setter(into), default = "\"IOC matched\".to_owned()")]
+    title: String,
+    #[builder(setter(into), default = "\"generated by iocutil.rs\".to_owned()")]
+    description: String,
+    #[builder(setter(into), default = "\"iocutil.rs\".to_owned()")]
+    author: String,
+    #[builder(default = "Level::High")]
+    level: Level,
+    #[builder(default = "vec![]")]
+    tags: Vec<String>,
+    #[builder(setter(into), default = "\"windows\".to_owned()")]
+    product: String,
+    #[builder(setter(into), default = "\"process_creation\".to_owned()")]
+    hash_category: String,
+    #[builder(setter(into), default = "\"file_event\".to_owned()")]
+    file_category: String,
+    #[builder(setter(into), default = "\"registry_set\".to_owned()")]
+    registry_category: String,
+    #[builder(setter(into), default = "\"create_mutex\".to_owned()")]
+    mutex_category: String,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for SigmaOptions {
+    fn default() -> Self {
+        SigmaOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// quote text as YAML single quoted scalar
+fn quote(text: impl AsRef<str>) -> String {
+    format!("'{}'", text.as_ref().replace('\'', "''"))
+}
+
+fn rule(
+    options: &SigmaOptions,
+    category: &str,
+    field: &str,
+    values: &[String],
+    date: &str,
+) -> String {
+    let mut r = String::new();
+    r.push_str(&format!("title: {}\n", quote(&options.title)));
+    r.push_str(&format!(
+        "id: {}\n",
+        deterministic_uuid(format!("{}:{}:{}", category, field, values.join("\n")))
+    ));
+    r.push_str("status: experimental\n");
+    r.push_str(&format!("description: {}\n", quote(&options.description)));
+    r.push_str(&format!("author: {}\n", quote(&options.author)));
+    r.push_str(&format!("date: {}\n", date));
+    if !options.tags.is_empty() {
+        r.push_str("tags:\n");
+        for t in &options.tags {
+            r.push_str(&format!("    - {}\n", quote(t)));
+        }
+    }
+    r.push_str("logsource:\n");
+    r.push_str(&format!("    category: {}\n", category));
+    r.push_str(&format!("    product: {}\n", options.product));
+    r.push_str("detection:\n");
+    r.push_str("    selection:\n");
+    r.push_str(&format!("        {}:\n", field));
+    for v in values {
+        r.push_str(&format!("            - {}\n", quote(v)));
+    }
+    r.push_str("    condition: selection\n");
+    r.push_str(&format!("level: {}\n", options.level.name()));
+    r
+}
+
+/// generate Sigma rules (one rule per logsource, joined as YAML documents)
+///
+/// Hashes allowed by the export allowlist (e.g. `knowngood::KnownGoodHashes`) are suppressed,
+/// and expired ones are excluded unless `include_expired`.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::sigma::{to_sigma_rules, HostIndicator, SigmaOptionsBuilder};
+///
+/// let iocs = vec![
+///     HostIndicator::from(SampleHash::new("d41d8cd98f00b204e9800998ecf8427e").unwrap()),
+///     HostIndicator::RegistryKey(r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run\updater".to_owned()),
+///     HostIndicator::FilePath(r"C:\Users\Public\it's.exe".to_owned()),
+/// ];
+///
+/// let options = SigmaOptionsBuilder::default().title("campaign X").build().unwrap();
+/// let rules = to_sigma_rules(iocs, &options);
+///
+/// assert_eq!(rules.matches("title: 'campaign X'").count(), 3);
+/// assert!(rules.contains("    category: process_creation\n"));
+/// assert!(rules.contains("        Hashes|contains:\n            - 'MD5=d41d8cd98f00b204e9800998ecf8427e'\n"));
+/// assert!(rules.contains(r"            - 'C:\Users\Public\it''s.exe'"));
+/// assert!(rules.contains("\n---\n"));
+/// ```
+///
+/// known good hashes are not ruled
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::allowlist;
+/// use iocutil::knowngood::KnownGoodHashes;
+/// use iocutil::sigma::{to_sigma_rules, HostIndicator};
+///
+/// let nsrl = "\"SHA-1\",\"MD5\",\"FileName\"\n\"\",\"1D6EBB5A789ABD108FF578263E1F40F3\",\"setup.exe\"";
+/// let mut known = KnownGoodHashes::new();
+/// known.load_nsrl(nsrl.as_bytes()).unwrap();
+/// allowlist::set_export_allowlist(known);
+///
+/// let iocs = vec![
+///     HostIndicator::from(SampleHash::new("1d6ebb5a789abd108ff578263e1f40f3").unwrap()),
+///     HostIndicator::from(Indicator::new(Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap())),
+/// ];
+/// let rules = to_sigma_rules(iocs, &Default::default());
+/// assert!(!rules.contains("1d6ebb5a789abd108ff578263e1f40f3"));
+/// assert!(rules.contains("MD5=d41d8cd98f00b204e9800998ecf8427e"));
+/// ```
+pub fn to_sigma_rules(
+    indicators: impl IntoIterator<Item = impl Into<HostIndicator>>,
+    options: &SigmaOptions,
+) -> String {
+    let mut iocs = vec![];
+    let mut files = vec![];
+    let mut registries = vec![];
+    let mut mutexes = vec![];
+
+    for i in indicators.into_iter().map(|x| x.into()) {
+        match i {
+            HostIndicator::Hash(h) => iocs.push(Indicator::from(h)),
+            HostIndicator::Indicator(x) => iocs.push(*x),
+            HostIndicator::FilePath(x) => files.push(x),
+            HostIndicator::RegistryKey(x) => registries.push(x),
+            HostIndicator::Mutex(x) => mutexes.push(x),
+        }
+    }
+
+    let hashes = suppress(unexpired(iocs, options.include_expired))
+        .into_iter()
+        .filter_map(|x| match x.ioc {
+            Ioc::Hash(h) => {
+                let prefix = match h {
+                    SampleHash::Md5(_) => "MD5",
+                    SampleHash::Sha1(_) => "SHA1",
+                    SampleHash::Sha256(_) => "SHA256",
+                };
+                Some(format!("{}={}", prefix, h))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let date = Utc::now().format("%Y/%m/%d").to_string();
+    let groups = vec![
+        (&options.hash_category, "Hashes|contains", hashes),
+        (&options.file_category, "TargetFilename", files),
+        (&options.registry_category, "TargetObject", registries),
+        (&options.mutex_category, "MutexName", mutexes),
+    ];
+
+    let rules: Vec<_> = groups
+        .into_iter()
+        .filter_map(|(category, field, mut values)| {
+            if values.is_empty() {
+                return None;
+            }
+            values.sort();
+            values.dedup();
+            Some(rule(options, category, field, &values, &date))
+        })
+        .collect();
+
+    rules.join("---\n")
+}
@@ -0,0 +1,162 @@
+//! coalescing concurrent lookups of the same key into a single upstream request
+//!
+//! While a lookup is in flight, other callers asking for the same key wait for it and share its
+//! result instead of sending their own requests. API clients coalesce their lookups by this
+//! (clones of a client share the lookups in flight).
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+use crate::error::Error;
+use crate::GenericResult;
+
+/// error shared with waiting callers
+#[derive(Debug)]
+enum SharedError {
+    Classified(Error),
+    Other(String),
+}
+
+impl SharedError {
+    fn new(e: &failure::Error) -> Self {
+        match e.downcast_ref::<Error>() {
+            Some(x) => SharedError::Classified(x.duplicate()),
+            None => SharedError::Other(e.to_string()),
+        }
+    }
+
+    fn to_error(&self) -> failure::Error {
+        match self {
+            SharedError::Classified(x) => x.duplicate().into(),
+            SharedError::Other(x) => failure::err_msg(x.clone()),
+        }
+    }
+}
+
+type Shared<T> = Option<Arc<Result<T, SharedError>>>;
+
+/// lookups in flight by key (clones share them)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::singleflight::SingleFlight;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let flights = SingleFlight::new();
+/// let requests = Arc::new(AtomicUsize::new(0));
+///
+/// let rt = tokio::runtime::Runtime::new().unwrap();
+/// let tasks: Vec<_> = (0..4)
+///     .map(|_| {
+///         let (flights, requests) = (flights.clone(), requests.clone());
+///         rt.spawn(async move {
+///             flights
+///                 .run("virustotal/file/report/d41d8cd98f00b204e9800998ecf8427e", async {
+///                     requests.fetch_add(1, Ordering::SeqCst);
+///                     tokio::time::sleep(Duration::from_millis(100)).await;
+///                     Ok("{}".to_owned())
+///                 })
+///                 .await
+///                 .unwrap()
+///         })
+///     })
+///     .collect();
+///
+/// for t in tasks {
+///     assert_eq!(rt.block_on(t).unwrap(), "{}");
+/// }
+/// assert_eq!(requests.load(Ordering::SeqCst), 1);
+/// ```
+pub struct SingleFlight<T = String> {
+    calls: Arc<Mutex<HashMap<String, watch::Receiver<Shared<T>>>>>,
+}
+
+impl<T> Clone for SingleFlight<T> {
+    fn clone(&self) -> Self {
+        SingleFlight {
+            calls: self.calls.clone(),
+        }
+    }
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        SingleFlight {
+            calls: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+/// removes the call when the leading lookup finished (or was dropped)
+struct Leading<'a, T> {
+    calls: &'a Mutex<HashMap<String, watch::Receiver<Shared<T>>>>,
+    key: &'a str,
+}
+
+impl<'a, T> Drop for Leading<'a, T> {
+    fn drop(&mut self) {
+        self.calls.lock().unwrap().remove(self.key);
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        SingleFlight::default()
+    }
+
+    /// result of `fetch`, or of the lookup of the same key in flight
+    ///
+    /// If the lookup in flight is dropped before it finished, `fetch` is used instead.
+    pub async fn run<F>(&self, key: impl Into<String>, fetch: F) -> GenericResult<T>
+    where
+        F: Future<Output = GenericResult<T>>,
+    {
+        let key = key.into();
+        let waiting = {
+            let mut calls = self.calls.lock().unwrap();
+            match calls.get(&key) {
+                Some(rx) => Err(rx.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    calls.insert(key.clone(), rx);
+                    Ok(tx)
+                }
+            }
+        };
+
+        let mut rx = match waiting {
+            Ok(tx) => {
+                let _leading = Leading {
+                    calls: &self.calls,
+                    key: &key,
+                };
+                let result = fetch.await;
+                let shared = match &result {
+                    Ok(x) => Ok(x.clone()),
+                    Err(e) => Err(SharedError::new(e)),
+                };
+                let _ = tx.send(Some(Arc::new(shared)));
+                return result;
+            }
+            Err(rx) => rx,
+        };
+
+        loop {
+            if let Some(shared) = rx.borrow_and_update().clone() {
+                return match shared.as_ref() {
+                    Ok(x) => Ok(x.clone()),
+                    Err(e) => Err(e.to_error()),
+                };
+            }
+            if rx.changed().await.is_err() {
+                // the leading lookup was dropped
+                return fetch.await;
+            }
+        }
+    }
+}
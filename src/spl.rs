@@ -0,0 +1,175 @@
+//! Splunk SPL query generation
+
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+
+use crate::allowlist::suppress;
+use crate::csv::{to_csv, CsvOptions};
+use crate::ioc::{Indicator, IocKind};
+use crate::ttl::unexpired;
+
+/// style of generated searches
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SplMode {
+    /// a raw search with OR'd values
+    Search,
+    /// tstats searches on CIM data models (one per data model)
+    Tstats,
+    /// a lookup table (CSV) and a search referencing it by name (e.g. `iocutil_iocs.csv`)
+    Lookup(String),
+}
+
+/// options of generated searches
+#[derive(Builder, Debug, Clone)]
+pub struct SplOptions {
+    #[builder(default = "SplMode::Search")]
+    mode: SplMode,
+    /// look back period in days
+    #[builder(default = "7")]
+    lookback_days: u32,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for SplOptions {
+    fn default() -> Self {
+        SplOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// generated searches (and lookup table in lookup mode)
+#[derive(Debug, Clone, Default)]
+pub struct SplOutput {
+    pub searches: Vec<String>,
+    /// content of lookup table (columns are `value` and `kind`)
+    pub lookup: Option<String>,
+}
+
+/// CIM field and data model (with dataset) for indicator kind
+fn cim_field(kind: IocKind) -> (&'static str, &'static str) {
+    match kind {
+        IocKind::Hash => ("file_hash", "Endpoint.Filesystem"),
+        IocKind::Domain => ("query", "Network_Resolution.DNS"),
+        IocKind::Url => ("url", "Web"),
+        IocKind::IpAddress => ("dest_ip", "Network_Traffic.All_Traffic"),
+        IocKind::EmailAddress => ("src_user", "Email.All_Email"),
+    }
+}
+
+/// quoted string of SPL
+fn quote(text: impl AsRef<str>) -> String {
+    format!(
+        "\"{}\"",
+        text.as_ref().replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// generate Splunk searches
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::spl::{to_spl, SplMode, SplOptionsBuilder};
+///
+/// let iocs = vec![
+///     Ioc::new("evil.example.com").unwrap(),
+///     Ioc::new("192.0.2.1").unwrap(),
+/// ];
+///
+/// let out = to_spl(iocs.clone(), &Default::default());
+/// assert_eq!(out.searches, vec!["search earliest=-7d (query=\"evil.example.com\" OR dest_ip=\"192.0.2.1\")"]);
+///
+/// let options = SplOptionsBuilder::default().mode(SplMode::Tstats).build().unwrap();
+/// let out = to_spl(iocs.clone(), &options);
+/// assert_eq!(
+///     out.searches[0],
+///     "| tstats summariesonly=true count from datamodel=Network_Resolution.DNS where earliest=-7d DNS.query IN (\"evil.example.com\") by DNS.src DNS.query"
+/// );
+///
+/// let options = SplOptionsBuilder::default().mode(SplMode::Lookup("iocs.csv".to_owned())).build().unwrap();
+/// let out = to_spl(iocs, &options);
+/// assert!(out.lookup.unwrap().starts_with("value,kind\r\n"));
+/// assert!(out.searches[0].contains("[| inputlookup iocs.csv where kind=\"ip\" | rename value AS dest_ip | fields dest_ip]"));
+/// ```
+pub fn to_spl(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &SplOptions,
+) -> SplOutput {
+    let indicators: Vec<Indicator> = suppress(unexpired(indicators, options.include_expired));
+    let earliest = format!("earliest=-{}d", options.lookback_days);
+
+    let mut groups: BTreeMap<IocKind, Vec<String>> = BTreeMap::new();
+    for i in &indicators {
+        let values = groups.entry(i.ioc.kind()).or_default();
+        let value = quote(i.ioc.value());
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    match &options.mode {
+        SplMode::Search => {
+            let terms: Vec<_> = groups
+                .iter()
+                .flat_map(|(k, values)| {
+                    let (field, _) = cim_field(*k);
+                    values.iter().map(move |v| format!("{}={}", field, v))
+                })
+                .collect();
+            SplOutput {
+                searches: vec![format!("search {} ({})", earliest, terms.join(" OR "))],
+                lookup: None,
+            }
+        }
+        SplMode::Tstats => {
+            let searches = groups
+                .iter()
+                .map(|(k, values)| {
+                    let (field, model) = cim_field(*k);
+                    // fields are prefixed with the last dataset name
+                    let dataset = model.rsplit('.').next().unwrap_or(model);
+                    format!(
+                        "| tstats summariesonly=true count from datamodel={} where {} {}.{} IN ({}) by {}.src {}.{}",
+                        model,
+                        earliest,
+                        dataset,
+                        field,
+                        values.join(", "),
+                        dataset,
+                        dataset,
+                        field
+                    )
+                })
+                .collect();
+            SplOutput {
+                searches,
+                lookup: None,
+            }
+        }
+        SplMode::Lookup(name) => {
+            let subsearches: Vec<_> = groups
+                .keys()
+                .map(|k| {
+                    let (field, _) = cim_field(*k);
+                    format!(
+                        "[| inputlookup {} where kind={} | rename value AS {} | fields {}]",
+                        name,
+                        quote(k.to_string()),
+                        field,
+                        field
+                    )
+                })
+                .collect();
+            SplOutput {
+                searches: vec![format!(
+                    "search {} ({})",
+                    earliest,
+                    subsearches.join(" OR ")
+                )],
+                lookup: Some(to_csv(indicators, &CsvOptions::default())),
+            }
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! ssdeep (context triggered piecewise hashing) computation and comparison
+//!
+//! Digests are compatible with [ssdeep](https://ssdeep-project.github.io/ssdeep/) 2.13
+//! (e.g. `3:hMCEpn:hupn`), and `Ssdeep::compare` gives the same score as `ssdeep -d`.
+
+use failure::Fail;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::GenericResult;
+
+const ROLLING_WINDOW: usize = 7;
+const MIN_BLOCKSIZE: u32 = 3;
+const HASH_PRIME: u32 = 0x0100_0193;
+const HASH_INIT: u32 = 0x2802_1967;
+const SPAMSUM_LENGTH: usize = 64;
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Errors in parsing ssdeep digest
+#[derive(Fail, Debug)]
+pub enum SsdeepError {
+    #[fail(display = "invalid ssdeep digest: {}", _0)]
+    InvalidDigest(String),
+}
+
+/// ssdeep digest (`blocksize:digest:digest_of_double_blocksize`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ssdeep {
+    pub block_size: u32,
+    pub digest: String,
+    pub double_digest: String,
+}
+
+#[derive(Default)]
+struct Roll {
+    window: [u32; ROLLING_WINDOW],
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    n: usize,
+}
+
+impl Roll {
+    fn update(&mut self, c: u8) -> u32 {
+        let c = u32::from(c);
+        self.h2 = self.h2.wrapping_sub(self.h1);
+        self.h2 = self.h2.wrapping_add(ROLLING_WINDOW as u32 * c);
+        self.h1 = self.h1.wrapping_add(c);
+        self.h1 = self.h1.wrapping_sub(self.window[self.n]);
+        self.window[self.n] = c;
+        self.n = (self.n + 1) % ROLLING_WINDOW;
+        self.h3 = (self.h3 << 5) ^ c;
+        self.sum()
+    }
+
+    fn sum(&self) -> u32 {
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+fn sum_hash(c: u8, h: u32) -> u32 {
+    h.wrapping_mul(HASH_PRIME) ^ u32::from(c)
+}
+
+/// digest of a block size truncated into max length
+struct Piece {
+    block_size: u32,
+    max: usize,
+    h: u32,
+    digest: Vec<u8>,
+}
+
+impl Piece {
+    fn new(block_size: u32, max: usize) -> Self {
+        Piece {
+            block_size,
+            max,
+            h: HASH_INIT,
+            digest: vec![],
+        }
+    }
+
+    fn update(&mut self, c: u8, rolling: u32) {
+        self.h = sum_hash(c, self.h);
+        if rolling % self.block_size != self.block_size - 1 {
+            return;
+        }
+        // last character is overwritten (and hash is kept) when the digest is full
+        if self.digest.len() == self.max {
+            self.digest.pop();
+            self.digest.push(B64[(self.h % 64) as usize]);
+            return;
+        }
+        self.digest.push(B64[(self.h % 64) as usize]);
+        if self.digest.len() < self.max {
+            self.h = HASH_INIT;
+        }
+    }
+
+    fn finish(mut self, rolling: u32) -> String {
+        if rolling != 0 {
+            if self.digest.len() == self.max {
+                self.digest.pop();
+            }
+            self.digest.push(B64[(self.h % 64) as usize]);
+        }
+        String::from_utf8(self.digest).unwrap()
+    }
+}
+
+/// both digests of a block size and number of pieces in the first one
+fn digests(bytes: &[u8], block_size: u32) -> (usize, String, String) {
+    let mut roll = Roll::default();
+    let mut first = Piece::new(block_size, SPAMSUM_LENGTH);
+    let mut second = Piece::new(block_size * 2, SPAMSUM_LENGTH / 2);
+    for &c in bytes {
+        let r = roll.update(c);
+        first.update(c, r);
+        second.update(c, r);
+    }
+    let r = roll.sum();
+    // the last piece (appended in finish) is not counted
+    let pieces = first.digest.len().min(SPAMSUM_LENGTH - 1);
+    (pieces, first.finish(r), second.finish(r))
+}
+
+/// remove characters repeated more than 3 times (they carry little information)
+fn eliminate_sequences(s: &str) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(s.len());
+    for &c in s.as_bytes() {
+        let n = out.len();
+        if n >= 3 && out[n - 1] == c && out[n - 2] == c && out[n - 3] == c {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn has_common_substring(s1: &[u8], s2: &[u8]) -> bool {
+    if s1.len() < ROLLING_WINDOW || s2.len() < ROLLING_WINDOW {
+        return false;
+    }
+    s1.windows(ROLLING_WINDOW)
+        .any(|w| s2.windows(ROLLING_WINDOW).any(|x| x == w))
+}
+
+/// edit distance with costs of insertion / deletion 1 and substitution 2
+fn edit_distance(s1: &[u8], s2: &[u8]) -> u32 {
+    let mut prev: Vec<u32> = (0..=s2.len() as u32).collect();
+    for (i, a) in s1.iter().enumerate() {
+        let mut row = vec![i as u32 + 1];
+        for (j, b) in s2.iter().enumerate() {
+            let replace = prev[j] + if a == b { 0 } else { 2 };
+            row.push(replace.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[s2.len()]
+}
+
+fn score_strings(s1: &[u8], s2: &[u8], block_size: u32) -> u32 {
+    if s1.len() > SPAMSUM_LENGTH || s2.len() > SPAMSUM_LENGTH || !has_common_substring(s1, s2) {
+        return 0;
+    }
+
+    let (l1, l2) = (s1.len() as u32, s2.len() as u32);
+    let score = edit_distance(s1, s2) * SPAMSUM_LENGTH as u32 / (l1 + l2);
+    let score = 100 * score / SPAMSUM_LENGTH as u32;
+    if score >= 100 {
+        return 0;
+    }
+    let score = 100 - score;
+
+    // small block sizes can not match well
+    let threshold = (99 + ROLLING_WINDOW as u32) / ROLLING_WINDOW as u32 * MIN_BLOCKSIZE;
+    if block_size >= threshold {
+        return score;
+    }
+    score.min(block_size / MIN_BLOCKSIZE * l1.min(l2))
+}
+
+impl Ssdeep {
+    /// ssdeep of bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::ssdeep::Ssdeep;
+    ///
+    /// assert_eq!(Ssdeep::of_bytes(b"").to_string(), "3::");
+    /// assert_eq!(Ssdeep::of_bytes(b"Hello, World!").to_string(), "3:aaX8n:aF");
+    /// ```
+    pub fn of_bytes(bytes: impl AsRef<[u8]>) -> Self {
+        let bytes = bytes.as_ref();
+
+        let mut block_size = MIN_BLOCKSIZE;
+        while (block_size as usize) * SPAMSUM_LENGTH < bytes.len() {
+            block_size *= 2;
+        }
+
+        loop {
+            let (pieces, digest, double_digest) = digests(bytes, block_size);
+            // retry with smaller block size if digest is too short
+            if block_size > MIN_BLOCKSIZE && pieces < SPAMSUM_LENGTH / 2 {
+                block_size /= 2;
+                continue;
+            }
+            return Ssdeep {
+                block_size,
+                digest,
+                double_digest,
+            };
+        }
+    }
+
+    /// ssdeep of all content in reader
+    pub fn of_reader(mut reader: impl Read) -> GenericResult<Self> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        Ok(Ssdeep::of_bytes(bytes))
+    }
+
+    /// ssdeep of file content
+    pub fn of_file(path: impl AsRef<Path>) -> GenericResult<Self> {
+        Ok(Ssdeep::of_bytes(std::fs::read(path)?))
+    }
+
+    /// similarity score (0-100) of two digests
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::ssdeep::Ssdeep;
+    ///
+    /// let text: String = (0..2000).map(|i| format!("line {} of a sample\n", i)).collect();
+    /// let a = Ssdeep::of_bytes(&text);
+    /// let b = Ssdeep::of_bytes(text.replace("line 1000 ", "line 9999 "));
+    ///
+    /// assert_eq!(a.compare(&a), 100);
+    /// assert!(a.compare(&b) > 80);
+    /// assert_eq!(a.compare(&Ssdeep::of_bytes(b"Hello, World!")), 0);
+    /// ```
+    pub fn compare(&self, other: &Ssdeep) -> u8 {
+        let (b1, b2) = (self.block_size, other.block_size);
+        if b1 != b2 && b1 != b2.wrapping_mul(2) && b2 != b1.wrapping_mul(2) {
+            return 0;
+        }
+
+        let (s1a, s1b) = (
+            eliminate_sequences(&self.digest),
+            eliminate_sequences(&self.double_digest),
+        );
+        let (s2a, s2b) = (
+            eliminate_sequences(&other.digest),
+            eliminate_sequences(&other.double_digest),
+        );
+
+        if b1 == b2 && s1a == s2a {
+            return 100;
+        }
+
+        let score = if b1 == b2 {
+            score_strings(&s1a, &s2a, b1).max(score_strings(&s1b, &s2b, b1 * 2))
+        } else if b1 == b2 * 2 {
+            score_strings(&s1a, &s2b, b1)
+        } else {
+            score_strings(&s1b, &s2a, b2)
+        };
+        score as u8
+    }
+}
+
+impl std::fmt::Display for Ssdeep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.block_size, self.digest, self.double_digest
+        )
+    }
+}
+
+impl FromStr for Ssdeep {
+    type Err = failure::Error;
+
+    /// parse digest (trailing file name like `,"/path/to/file"` is ignored)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SsdeepError::InvalidDigest(s.to_owned());
+        let mut parts = s.trim().splitn(3, ':');
+        let block_size: u32 = parts
+            .next()
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(invalid)?;
+        let digest = parts.next().ok_or_else(invalid)?;
+        let double_digest = parts
+            .next()
+            .ok_or_else(invalid)?
+            .split(',')
+            .next()
+            .unwrap_or_default();
+
+        let valid = |x: &str| x.bytes().all(|c| B64.contains(&c));
+        if block_size < MIN_BLOCKSIZE
+            || digest.len() > SPAMSUM_LENGTH
+            || double_digest.len() > SPAMSUM_LENGTH
+            || !valid(digest)
+            || !valid(double_digest)
+        {
+            return Err(invalid().into());
+        }
+
+        Ok(Ssdeep {
+            block_size,
+            digest: digest.to_owned(),
+            double_digest: double_digest.to_owned(),
+        })
+    }
+}
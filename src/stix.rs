@@ -0,0 +1,508 @@
+//! STIX 2.1 utilities
+
+pub mod pattern;
+
+use chrono::{DateTime, Utc};
+use derive_builder::Builder;
+use serde_json::json;
+use std::collections::{BTreeSet, HashSet};
+use std::io::BufRead;
+
+use self::pattern::comparison_to_ioc;
+use crate::allowlist::suppress;
+use crate::confidence::Confidence;
+use crate::graph::decompose;
+use crate::ioc::{Indicator, Ioc, Metadata};
+use crate::tlp::{Audience, Tlp};
+use crate::ttl::unexpired;
+use crate::util::deterministic_uuid;
+use crate::GenericResult;
+
+/// format datetime as STIX (and TAXII) timestamp
+///
+/// # Example
+///
+/// ```
+/// use iocutil::stix::timestamp;
+/// use chrono::{DateTime, Utc};
+///
+/// let d: DateTime<Utc> = DateTime::parse_from_rfc3339("2019-11-01T09:00:00+09:00").unwrap().into();
+/// assert_eq!(timestamp(d).as_str(), "2019-11-01T00:00:00.000Z");
+/// ```
+pub fn timestamp(datetime: impl Into<DateTime<Utc>>) -> String {
+    datetime.into().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// options of STIX bundle export
+#[derive(Builder, Debug, Clone)]
+pub struct StixOptions {
+    /// TLP marking of the bundle (indicators marked more strictly keep their own markings)
+    #[builder(default = "None")]
+    tlp: Option<Tlp>,
+    /// audience of the bundle (indicators whose TLP does not allow it are excluded)
+    #[builder(setter(strip_option), default = "None")]
+    audience: Option<Audience>,
+    /// add components of composite indicators as related indicators (as `graph::decompose`,
+    /// host of url and domain of email address, related by `related-to`)
+    #[builder(default = "true")]
+    decompose: bool,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for StixOptions {
+    fn default() -> Self {
+        StixOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// STIX pattern of an indicator (same as `pattern::generate`)
+pub fn pattern(ioc: &Ioc) -> String {
+    pattern::generate(ioc)
+}
+
+/// deterministic STIX identifier
+fn object_id(object_type: &str, seed: impl AsRef<str>) -> String {
+    format!("{}--{}", object_type, deterministic_uuid(seed))
+}
+
+/// convert an indicator into STIX indicator object
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::stix::{from_stix_object, to_stix_indicator, StixOptions};
+/// use iocutil::tlp::Tlp;
+///
+/// let mut i = Indicator::new(Ioc::new("example.com").unwrap());
+/// i.meta.tags = vec!["phishing".to_owned()];
+/// i.meta.first_seen = Some(day!(2019, 11, 1).unwrap().into());
+/// i.meta.kill_chain_phases = vec![KillChainPhase::Delivery];
+/// i.meta.malware_family = Some("Emotet".to_owned());
+/// i.meta.tlp = Some(Tlp::AmberStrict);
+///
+/// let v = to_stix_indicator(&i, &StixOptions::default(), chrono::Utc::now());
+/// assert_eq!(v["type"], "indicator");
+/// assert_eq!(v["pattern"], "[domain-name:value = 'example.com']");
+/// assert_eq!(v["valid_from"], "2019-11-01T00:00:00.000Z");
+/// assert_eq!(v["labels"], serde_json::json!(["phishing", "malware-family:Emotet"]));
+/// assert_eq!(v["kill_chain_phases"][0]["phase_name"], "delivery");
+///
+/// let imported = from_stix_object(&v);
+/// assert_eq!(imported[0].meta.tags, i.meta.tags);
+/// assert_eq!(imported[0].meta.kill_chain_phases, i.meta.kill_chain_phases);
+/// assert_eq!(imported[0].meta.malware_family, i.meta.malware_family);
+/// assert_eq!(imported[0].meta.tlp, Some(Tlp::AmberStrict));
+/// ```
+pub fn to_stix_indicator(
+    indicator: &Indicator,
+    options: &StixOptions,
+    now: DateTime<Utc>,
+) -> serde_json::Value {
+    let meta = &indicator.meta;
+    let p = pattern(&indicator.ioc);
+    let created = meta.first_seen.unwrap_or(now);
+    let modified = meta.last_seen.unwrap_or(now).max(created);
+
+    let mut v = json!({
+        "type": "indicator",
+        "spec_version": "2.1",
+        "id": object_id("indicator", &p),
+        "created": timestamp(created),
+        "modified": timestamp(modified),
+        "name": indicator.ioc.value(),
+        "pattern": p,
+        "pattern_type": "stix",
+        "valid_from": timestamp(created),
+    });
+
+    if let Some(d) = &meta.description {
+        v["description"] = json!(d);
+    }
+    let labels = labels_of(meta);
+    if !labels.is_empty() {
+        v["labels"] = json!(labels);
+    }
+    if !meta.kill_chain_phases.is_empty() {
+        let phases: Vec<_> = meta
+            .kill_chain_phases
+            .iter()
+            .map(|p| json!({ "kill_chain_name": KILL_CHAIN_NAME, "phase_name": p.name() }))
+            .collect();
+        v["kill_chain_phases"] = json!(phases);
+    }
+    if let Some(c) = meta.confidence {
+        v["confidence"] = json!(c.score());
+    }
+    // valid_until must be later than valid_from
+    if let Some(t) = meta.valid_until.filter(|t| *t > created) {
+        v["valid_until"] = json!(timestamp(t));
+    }
+    if let Some(s) = &meta.source {
+        v["external_references"] = json!([{ "source_name": s }]);
+    }
+    if let Some(t) = Tlp::strictest(vec![meta.tlp, options.tlp]) {
+        v["object_marking_refs"] = json!([t.stix_id()]);
+    }
+
+    v
+}
+
+/// relationship object between two objects
+fn relationship(
+    source_ref: &str,
+    target_ref: &str,
+    tlp: Option<Tlp>,
+    now: DateTime<Utc>,
+) -> serde_json::Value {
+    let mut v = json!({
+        "type": "relationship",
+        "spec_version": "2.1",
+        "id": object_id("relationship", format!("{}>{}", source_ref, target_ref)),
+        "created": timestamp(now),
+        "modified": timestamp(now),
+        "relationship_type": "related-to",
+        "source_ref": source_ref,
+        "target_ref": target_ref,
+    });
+    if let Some(t) = tlp {
+        v["object_marking_refs"] = json!([t.stix_id()]);
+    }
+    v
+}
+
+/// export indicators as STIX 2.1 bundle
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::stix::{to_stix_bundle, StixOptionsBuilder};
+/// use iocutil::tlp::{Audience, Tlp};
+///
+/// let iocs = vec![
+///     Ioc::new("http://evil.example.com/payload.exe").unwrap(),
+///     Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+///     Ioc::new("alice@evil.example.com").unwrap(),
+/// ];
+///
+/// let options = StixOptionsBuilder::default().tlp(Some(Tlp::Amber)).build().unwrap();
+/// let bundle = to_stix_bundle(iocs.clone(), &options);
+/// let objects = bundle["objects"].as_array().unwrap();
+///
+/// let count = |t: &str| objects.iter().filter(|x| x["type"] == t).count();
+/// assert_eq!(bundle["type"], "bundle");
+/// assert_eq!(count("marking-definition"), 1);
+/// assert_eq!(count("indicator"), 4); // url, hash, email address and their domain
+/// assert_eq!(count("relationship"), 2);
+/// assert_eq!(objects[1]["object_marking_refs"][0], Tlp::Amber.stix_id());
+///
+/// // RED indicators are not shared with community
+/// let mut red = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// red.meta.tlp = Some(Tlp::Red);
+/// let options = StixOptionsBuilder::default()
+///     .tlp(Some(Tlp::Green))
+///     .audience(Audience::Community)
+///     .build()
+///     .unwrap();
+/// let bundle = to_stix_bundle(vec![red, Indicator::new(iocs[1].clone())], &options);
+/// let objects = bundle["objects"].as_array().unwrap();
+/// assert_eq!(objects.len(), 2);
+/// assert_eq!(objects[0]["name"], "TLP:GREEN");
+/// assert_eq!(objects[1]["name"], "d41d8cd98f00b204e9800998ecf8427e");
+/// ```
+pub fn to_stix_bundle(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &StixOptions,
+) -> serde_json::Value {
+    let now = Utc::now();
+    let mut seen = HashSet::new();
+    let mut objects = vec![];
+    let mut relationships = vec![];
+    let mut markings = BTreeSet::new();
+
+    for indicator in suppress(unexpired(indicators, options.include_expired)) {
+        let tlp = Tlp::strictest(vec![indicator.meta.tlp, options.tlp]);
+        if let (Some(t), Some(a)) = (tlp, options.audience) {
+            if !t.allows(a) {
+                continue;
+            }
+        }
+        markings.extend(tlp);
+
+        let v = to_stix_indicator(&indicator, options, now);
+        let id = v["id"].as_str().unwrap_or_default().to_owned();
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        objects.push(v);
+
+        if !options.decompose {
+            continue;
+        }
+
+        for r in decompose(&indicator.ioc) {
+            let target = Indicator::with_meta(r.target, indicator.meta.clone());
+            let t = to_stix_indicator(&target, options, now);
+            let target_id = t["id"].as_str().unwrap_or_default().to_owned();
+            relationships.push(relationship(&id, &target_id, tlp, now));
+            if seen.insert(target_id) {
+                objects.push(t);
+            }
+        }
+    }
+
+    objects.extend(relationships);
+    let objects: Vec<_> = markings
+        .into_iter()
+        .map(Tlp::stix_definition)
+        .chain(objects)
+        .collect();
+
+    let seed = objects
+        .iter()
+        .map(|x| x["id"].as_str().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    json!({
+        "type": "bundle",
+        "id": object_id("bundle", seed),
+        "objects": objects,
+    })
+}
+
+/// indicators in simple comparison pattern (same as `pattern::parse`)
+pub fn parse_pattern(pattern: impl AsRef<str>) -> Vec<Ioc> {
+    pattern::parse(pattern)
+}
+
+/// indicators in a cyber observable object (STIX 2.1 SCO)
+fn observable_to_iocs(object: &serde_json::Value) -> Vec<Ioc> {
+    let t = object["type"].as_str().unwrap_or_default();
+    if t == "file" {
+        return object["hashes"]
+            .as_object()
+            .map(|hashes| {
+                hashes
+                    .iter()
+                    .filter_map(|(k, v)| {
+                        comparison_to_ioc("file", &format!("hashes.{}", k), v.as_str()?.to_owned())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    object["value"]
+        .as_str()
+        .and_then(|v| comparison_to_ioc(t, "value", v.to_owned()))
+        .into_iter()
+        .collect()
+}
+
+fn parse_timestamp(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+    Some(DateTime::parse_from_rfc3339(value.as_str()?).ok()?.into())
+}
+
+/// kill chain of `KillChainPhase`
+const KILL_CHAIN_NAME: &str = "lockheed-martin-cyber-kill-chain";
+
+/// labels of attributions (`malware-family:` / `threat-actor:` followed by the name)
+const FAMILY_LABEL: &str = "malware-family:";
+const ACTOR_LABEL: &str = "threat-actor:";
+
+/// tags and attributions as labels
+fn labels_of(meta: &Metadata) -> Vec<String> {
+    let mut labels = meta.tags.clone();
+    if let Some(f) = &meta.malware_family {
+        labels.push(format!("{}{}", FAMILY_LABEL, f));
+    }
+    if let Some(a) = &meta.threat_actor {
+        labels.push(format!("{}{}", ACTOR_LABEL, a));
+    }
+    labels
+}
+
+/// metadata in common properties of STIX object
+fn metadata_of(object: &serde_json::Value) -> Metadata {
+    let labels: Vec<&str> = object["labels"]
+        .as_array()
+        .map(|x| x.iter().filter_map(|l| l.as_str()).collect())
+        .unwrap_or_default();
+    let attribution = |prefix: &str| {
+        labels
+            .iter()
+            .find_map(|l| l.strip_prefix(prefix))
+            .map(|x| x.to_owned())
+    };
+
+    Metadata {
+        first_seen: parse_timestamp(&object["valid_from"])
+            .or_else(|| parse_timestamp(&object["first_observed"]))
+            .or_else(|| parse_timestamp(&object["created"])),
+        last_seen: parse_timestamp(&object["last_observed"])
+            .or_else(|| parse_timestamp(&object["modified"])),
+        source: object["external_references"][0]["source_name"]
+            .as_str()
+            .map(|x| x.to_owned()),
+        description: object["description"].as_str().map(|x| x.to_owned()),
+        tags: labels
+            .iter()
+            .filter(|l| !l.starts_with(FAMILY_LABEL) && !l.starts_with(ACTOR_LABEL))
+            .map(|l| (*l).to_owned())
+            .collect(),
+        confidence: object["confidence"]
+            .as_u64()
+            .map(|x| Confidence::new(x.min(100) as u8)),
+        valid_until: parse_timestamp(&object["valid_until"]),
+        kill_chain_phases: object["kill_chain_phases"]
+            .as_array()
+            .map(|x| {
+                x.iter()
+                    .filter(|p| p["kill_chain_name"] == KILL_CHAIN_NAME)
+                    .filter_map(|p| p["phase_name"].as_str()?.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        malware_family: attribution(FAMILY_LABEL),
+        threat_actor: attribution(ACTOR_LABEL),
+        tlp: object["object_marking_refs"]
+            .as_array()
+            .and_then(|x| Tlp::strictest(x.iter().map(|r| Tlp::from_stix_id(r.as_str()?)))),
+        geo: None,
+        registration: None,
+    }
+}
+
+/// indicators in a STIX object (indicator, observed-data and cyber observable objects)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::confidence::Confidence;
+/// use iocutil::stix::from_stix_object;
+///
+/// let o: serde_json::Value = serde_json::from_str(r#"{
+///     "type": "indicator",
+///     "pattern": "[ipv4-addr:value = '192.0.2.1']",
+///     "pattern_type": "stix",
+///     "labels": ["c2"],
+///     "confidence": 80
+/// }"#).unwrap();
+///
+/// let i = from_stix_object(&o);
+/// assert_eq!(i.len(), 1);
+/// assert_eq!(i[0].ioc, Ioc::new("192.0.2.1").unwrap());
+/// assert_eq!(i[0].meta.tags, vec!["c2".to_owned()]);
+/// assert_eq!(i[0].meta.confidence, Some(Confidence::new(80)));
+/// ```
+pub fn from_stix_object(object: &serde_json::Value) -> Vec<Indicator> {
+    let iocs = match object["type"].as_str().unwrap_or_default() {
+        "indicator" => match object["pattern_type"].as_str() {
+            // STIX 2.0 has no pattern_type
+            Some("stix") | None => parse_pattern(object["pattern"].as_str().unwrap_or_default()),
+            _ => vec![],
+        },
+        // STIX 2.0 observed-data embeds observables
+        "observed-data" => object["objects"]
+            .as_object()
+            .map(|x| x.values().flat_map(observable_to_iocs).collect())
+            .unwrap_or_default(),
+        _ => observable_to_iocs(object),
+    };
+
+    let meta = metadata_of(object);
+    iocs.into_iter()
+        .map(|x| Indicator::with_meta(x, meta.clone()))
+        .collect()
+}
+
+/// indicators in STIX objects
+pub fn from_stix_objects(
+    objects: impl IntoIterator<Item = impl std::borrow::Borrow<serde_json::Value>>,
+) -> Vec<Indicator> {
+    objects
+        .into_iter()
+        .flat_map(|x| from_stix_object(x.borrow()))
+        .collect()
+}
+
+/// indicators in STIX bundle (single object or array of objects are also accepted)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::stix::{from_stix_bundle, to_stix_bundle, StixOptions};
+///
+/// let bundle = to_stix_bundle(vec![Ioc::new("example.com").unwrap()], &StixOptions::default());
+/// let imported = from_stix_bundle(bundle.to_string()).unwrap();
+/// assert_eq!(imported[0].ioc, Ioc::new("example.com").unwrap());
+/// ```
+pub fn from_stix_bundle(json: impl AsRef<str>) -> GenericResult<Vec<Indicator>> {
+    let v: serde_json::Value = serde_json::from_str(json.as_ref())?;
+    Ok(match &v {
+        serde_json::Value::Array(objects) => from_stix_objects(objects),
+        serde_json::Value::Object(_) if v["type"] == "bundle" => {
+            from_stix_objects(v["objects"].as_array().cloned().unwrap_or_default())
+        }
+        _ => from_stix_object(&v),
+    })
+}
+
+/// streaming reader of newline delimited STIX objects
+pub struct StixNdjsonReader<R> {
+    lines: std::io::Lines<R>,
+    buffer: std::collections::VecDeque<Indicator>,
+}
+
+impl<R: BufRead> Iterator for StixNdjsonReader<R> {
+    type Item = GenericResult<Indicator>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(i) = self.buffer.pop_front() {
+                return Some(Ok(i));
+            }
+
+            let line = match self.lines.next()? {
+                Ok(l) => l,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<serde_json::Value>(&line) {
+                Ok(v) => self.buffer.extend(from_stix_object(&v)),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+/// read indicators from newline delimited STIX objects
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::stix::from_stix_ndjson;
+///
+/// let ndjson = r#"{"type": "domain-name", "value": "example.com"}
+/// {"type": "file", "hashes": {"MD5": "d41d8cd98f00b204e9800998ecf8427e"}}
+/// "#;
+///
+/// let indicators: Result<Vec<_>, _> = from_stix_ndjson(ndjson.as_bytes()).collect();
+/// assert_eq!(indicators.unwrap().len(), 2);
+/// ```
+pub fn from_stix_ndjson<R: BufRead>(reader: R) -> StixNdjsonReader<R> {
+    StixNdjsonReader {
+        lines: reader.lines(),
+        buffer: Default::default(),
+    }
+}
@@ -0,0 +1,190 @@
+//! STIX pattern language (generation from indicators and parsing of simple patterns)
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::net::IpAddr;
+
+use crate::ioc::Ioc;
+use crate::SampleHash;
+
+lazy_static! {
+    static ref COMPARISON_PATTERN: Regex = Regex::new(
+        r"(?P<object>[a-z0-9-]+):(?P<path>[A-Za-z0-9_.'-]+)\s*(?P<op>!=|<=|>=|=|<|>|NOT\s+\w+|\w+)\s*(?:'(?P<value>(?:[^'\\]|\\.)*)'|\((?P<set>(?:\s*'(?:[^'\\]|\\.)*'\s*,?)*)\))"
+    )
+    .unwrap();
+    static ref SET_ELEMENT: Regex = Regex::new(r"'(?P<value>(?:[^'\\]|\\.)*)'").unwrap();
+}
+
+/// a comparison expression in pattern (e.g. `file:hashes.MD5 = '...'`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comparison {
+    /// object type (e.g. `file`)
+    pub object: String,
+    /// object path (e.g. `hashes.MD5`)
+    pub path: String,
+    /// operator (e.g. `=`, `LIKE`, `IN`)
+    pub operator: String,
+    /// unescaped values (multiple for `IN`)
+    pub values: Vec<String>,
+}
+
+impl Comparison {
+    /// indicators of the comparison (only `=` and `IN` are evaluated)
+    pub fn iocs(&self) -> Vec<Ioc> {
+        match self.operator.as_str() {
+            "=" | "IN" => self
+                .values
+                .iter()
+                .filter_map(|v| comparison_to_ioc(&self.object, &self.path, v.clone()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// escape value in pattern
+fn escape(value: impl AsRef<str>) -> String {
+    value.as_ref().replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// unescape value in pattern
+fn unescape(value: &str) -> String {
+    let mut s = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(n) = chars.next() {
+                s.push(n);
+            }
+        } else {
+            s.push(c);
+        }
+    }
+    s
+}
+
+/// object path of an indicator (e.g. `file:hashes.'SHA-256'`)
+fn path_of(ioc: &Ioc) -> &'static str {
+    match ioc {
+        Ioc::Hash(SampleHash::Md5(_)) => "file:hashes.MD5",
+        Ioc::Hash(SampleHash::Sha1(_)) => "file:hashes.'SHA-1'",
+        Ioc::Hash(SampleHash::Sha256(_)) => "file:hashes.'SHA-256'",
+        Ioc::Domain(_) => "domain-name:value",
+        Ioc::Url(_) => "url:value",
+        Ioc::IpAddress(IpAddr::V4(_)) => "ipv4-addr:value",
+        Ioc::IpAddress(IpAddr::V6(_)) => "ipv6-addr:value",
+        Ioc::EmailAddress(_) => "email-addr:value",
+    }
+}
+
+/// STIX pattern of an indicator
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::stix::pattern::generate;
+///
+/// let p = generate(&Ioc::new("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap());
+/// assert_eq!(p, "[file:hashes.'SHA-256' = 'e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855']");
+///
+/// let p = generate(&Ioc::new("http://example.com/it's").unwrap());
+/// assert_eq!(p, r"[url:value = 'http://example.com/it\'s']");
+/// ```
+pub fn generate(ioc: &Ioc) -> String {
+    format!("[{} = '{}']", path_of(ioc), escape(ioc.value()))
+}
+
+/// STIX pattern matching any of indicators (observation expressions joined by `OR`)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::stix::pattern::{generate_any, parse};
+///
+/// let iocs = vec![Ioc::new("example.com").unwrap(), Ioc::new("192.0.2.1").unwrap()];
+/// let p = generate_any(&iocs);
+/// assert_eq!(p, "[domain-name:value = 'example.com'] OR [ipv4-addr:value = '192.0.2.1']");
+/// assert_eq!(parse(&p), iocs);
+/// ```
+pub fn generate_any<'a>(iocs: impl IntoIterator<Item = &'a Ioc>) -> String {
+    iocs.into_iter()
+        .map(generate)
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// indicator of an equality comparison (e.g. `file:hashes.MD5` and its value)
+pub(crate) fn comparison_to_ioc(object: &str, path: &str, value: String) -> Option<Ioc> {
+    let path = path.replace('\'', "").to_lowercase();
+    let ioc = match (object, path.as_str()) {
+        ("file", "hashes.md5")
+        | ("file", "hashes.sha-1")
+        | ("file", "hashes.sha1")
+        | ("file", "hashes.sha-256")
+        | ("file", "hashes.sha256") => Ioc::Hash(SampleHash::new(value).ok()?),
+        ("domain-name", "value") => Ioc::Domain(value.to_lowercase()),
+        ("url", "value") => Ioc::Url(value),
+        ("ipv4-addr", "value") | ("ipv6-addr", "value") => Ioc::IpAddress(value.parse().ok()?),
+        ("email-addr", "value") => Ioc::EmailAddress(value.to_lowercase()),
+        _ => return None,
+    };
+    Some(ioc)
+}
+
+/// comparison expressions in pattern (boolean operators and qualifiers are ignored)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::stix::pattern::comparisons;
+///
+/// let c = comparisons("[url:value LIKE '%evil%' AND ipv4-addr:value IN ('192.0.2.1', '192.0.2.2')]");
+/// assert_eq!(c.len(), 2);
+/// assert_eq!(c[0].operator, "LIKE");
+/// assert_eq!(c[1].values, vec!["192.0.2.1".to_owned(), "192.0.2.2".to_owned()]);
+/// ```
+pub fn comparisons(pattern: impl AsRef<str>) -> Vec<Comparison> {
+    COMPARISON_PATTERN
+        .captures_iter(pattern.as_ref())
+        .map(|x| {
+            let values = match (x.name("value"), x.name("set")) {
+                (Some(v), _) => vec![unescape(v.as_str())],
+                (None, Some(set)) => SET_ELEMENT
+                    .captures_iter(set.as_str())
+                    .map(|e| unescape(&e["value"]))
+                    .collect(),
+                _ => vec![],
+            };
+            Comparison {
+                object: x["object"].to_owned(),
+                path: x["path"].to_owned(),
+                operator: x["op"].split_whitespace().collect::<Vec<_>>().join(" "),
+                values,
+            }
+        })
+        .collect()
+}
+
+/// indicators in simple comparison pattern
+/// Only equality (`=`) and set (`IN`) comparisons are evaluated (others like `MATCHES` or `LIKE` are ignored).
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::stix::pattern::parse;
+///
+/// let iocs = parse("[file:hashes.'SHA-256' = 'e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855' OR file:hashes.MD5 = 'd41d8cd98f00b204e9800998ecf8427e']");
+/// assert_eq!(iocs.len(), 2);
+///
+/// let iocs = parse("[domain-name:value = 'example.com'] OR [url:value LIKE '%evil%']");
+/// assert_eq!(iocs, vec![Ioc::Domain("example.com".to_owned())]);
+///
+/// let iocs = parse("[ipv4-addr:value IN ('192.0.2.1', '192.0.2.2')] WITHIN 600 SECONDS");
+/// assert_eq!(iocs.len(), 2);
+/// ```
+pub fn parse(pattern: impl AsRef<str>) -> Vec<Ioc> {
+    comparisons(pattern).iter().flat_map(|x| x.iocs()).collect()
+}
@@ -0,0 +1,30 @@
+//! persistent stores of indicators
+
+pub mod bloom;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use crate::ioc::Ioc;
+use crate::GenericResult;
+
+/// set of indicators backed by a store (shared by processes, or persisted)
+pub trait IocSet {
+    /// add an indicator (returns true if it was not in the set)
+    fn insert(&self, ioc: &Ioc) -> GenericResult<bool>;
+
+    /// the indicator is in the set?
+    fn contains(&self, ioc: &Ioc) -> GenericResult<bool>;
+
+    /// remove an indicator (returns true if it was in the set)
+    fn remove(&self, ioc: &Ioc) -> GenericResult<bool>;
+
+    /// number of indicators in the set
+    fn len(&self) -> GenericResult<usize>;
+
+    /// the set has no indicator?
+    fn is_empty(&self) -> GenericResult<bool> {
+        Ok(self.len()? == 0)
+    }
+}
@@ -0,0 +1,298 @@
+//! bloom filter of indicators distributable as a compact file
+//!
+//! `BloomSet` answers "have we seen this?" in constant time with no false negative.
+//! False positive rate for `n` inserted indicators is `(1 - e^(-k * n / m)) ^ k`
+//! (`m` bits, `k` hash functions), and it is close to the rate specified in `BloomSet::with_rate`
+//! as long as the number of inserted indicators does not exceed the capacity.
+//!
+//! Indicators can not be removed or enumerated, so `BloomSet` does not implement `IocSet`.
+//!
+//! # Format
+//!
+//! All integers are little endian.
+//!
+//! | offset | size      | content                                      |
+//! |--------|-----------|----------------------------------------------|
+//! | 0      | 8         | magic `IOCBLOOM`                             |
+//! | 8      | 2         | format version (currently `1`)               |
+//! | 10     | 2         | number of hash functions `k`                 |
+//! | 12     | 8         | number of bits `m`                           |
+//! | 20     | 8         | number of inserted indicators `n`            |
+//! | 28     | 8 * ⌈m/64⌉ | bits (array of u64, bit `i` is `1 << i % 64` of word `i / 64`) |
+//!
+//! Bit positions of an indicator are `(h1 + i * h2) % m` for `i` in `0..k`,
+//! where `h1` and `h2` are the first and second 8 bytes (little endian) of
+//! SHA-256 of `{kind}:{value}` (e.g. `domain:example.com`).
+
+use failure::Fail;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::ioc::Ioc;
+use crate::GenericResult;
+
+/// magic bytes at the head of file
+pub const MAGIC: &[u8; 8] = b"IOCBLOOM";
+
+/// current version of the format
+pub const FORMAT_VERSION: u16 = 1;
+
+const HEADER_SIZE: usize = 28;
+
+/// Errors in reading a bloom filter
+#[derive(Fail, Debug)]
+pub enum BloomError {
+    #[fail(display = "not a bloom filter of iocutil")]
+    InvalidMagic,
+
+    #[fail(display = "unsupported format version: {}", _0)]
+    UnsupportedVersion(u16),
+
+    #[fail(display = "broken bloom filter: {}", _0)]
+    Broken(String),
+}
+
+/// bloom filter of indicators
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomSet {
+    bits: Vec<u64>,
+    nbits: u64,
+    nhashes: u16,
+    count: u64,
+}
+
+impl BloomSet {
+    /// empty filter sized for capacity indicators with the false positive rate
+    ///
+    /// # Panics
+    ///
+    /// panics if `fp_rate` is not in `(0, 1)`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::store::bloom::BloomSet;
+    ///
+    /// let mut set = BloomSet::with_rate(1_000_000, 0.001);
+    /// assert!(set.insert(&Ioc::new("example.com").unwrap()));
+    ///
+    /// assert!(set.contains(&Ioc::new("example.com").unwrap()));
+    /// assert!(!set.contains(&Ioc::new("example.net").unwrap()));
+    ///
+    /// // about 1.7 MiB for a million indicators
+    /// assert_eq!(set.size_in_bytes(), 1_797_228);
+    /// ```
+    pub fn with_rate(capacity: usize, fp_rate: f64) -> Self {
+        assert!(
+            fp_rate > 0.0 && fp_rate < 1.0,
+            "false positive rate must be in (0, 1)"
+        );
+
+        let n = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let nbits = (-n * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let nhashes = (nbits as f64 / n * ln2)
+            .round()
+            .max(1.0)
+            .min(f64::from(u16::MAX)) as u16;
+
+        BloomSet {
+            bits: vec![0; words(nbits)],
+            nbits,
+            nhashes,
+            count: 0,
+        }
+    }
+
+    /// filter of indicators sized for them with the false positive rate
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::store::bloom::BloomSet;
+    ///
+    /// let iocs: Vec<_> = (0..1000)
+    ///     .map(|i| Ioc::new(format!("host{}.example.com", i)).unwrap())
+    ///     .collect();
+    ///
+    /// let set = BloomSet::from_iocs(&iocs, 0.01);
+    /// assert!(iocs.iter().all(|x| set.contains(x)));
+    /// assert!(set.false_positive_rate() < 0.011);
+    /// ```
+    pub fn from_iocs<'a>(iocs: impl IntoIterator<Item = &'a Ioc>, fp_rate: f64) -> Self {
+        let iocs: Vec<_> = iocs.into_iter().collect();
+        let mut set = BloomSet::with_rate(iocs.len(), fp_rate);
+        set.extend(iocs);
+        set
+    }
+
+    fn positions(&self, ioc: &Ioc) -> impl Iterator<Item = u64> {
+        let digest = Sha256::digest(format!("{}:{}", ioc.kind(), ioc.value()));
+
+        let h1 = le_u64(&digest[..8]);
+        let h2 = le_u64(&digest[8..16]);
+        let nbits = self.nbits;
+        (0..u64::from(self.nhashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % nbits)
+    }
+
+    /// add an indicator (returns true if it was not in the set)
+    pub fn insert(&mut self, ioc: &Ioc) -> bool {
+        let mut added = false;
+        for p in self.positions(ioc) {
+            let (word, mask) = ((p / 64) as usize, 1u64 << (p % 64));
+            added |= self.bits[word] & mask == 0;
+            self.bits[word] |= mask;
+        }
+        if added {
+            self.count += 1;
+        }
+        added
+    }
+
+    /// the indicator may be in the set? (never returns false for inserted ones)
+    pub fn contains(&self, ioc: &Ioc) -> bool {
+        self.positions(ioc)
+            .all(|p| self.bits[(p / 64) as usize] & (1u64 << (p % 64)) != 0)
+    }
+
+    /// number of inserted indicators (duplicates are not counted unless they are false positive)
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    /// no indicator is inserted?
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// estimated false positive rate at current number of indicators
+    pub fn false_positive_rate(&self) -> f64 {
+        let k = f64::from(self.nhashes);
+        let exp = -k * self.count as f64 / self.nbits as f64;
+        (1.0 - exp.exp()).powf(k)
+    }
+
+    /// size of serialized filter
+    pub fn size_in_bytes(&self) -> usize {
+        HEADER_SIZE + self.bits.len() * 8
+    }
+
+    /// serialize into writer
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&self.nhashes.to_le_bytes())?;
+        writer.write_all(&self.nbits.to_le_bytes())?;
+        writer.write_all(&self.count.to_le_bytes())?;
+        for w in &self.bits {
+            writer.write_all(&w.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// serialize into bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.size_in_bytes());
+        // writing into vec never fails
+        self.write_to(&mut buf).unwrap();
+        buf
+    }
+
+    /// save into file
+    pub fn save(&self, path: impl AsRef<Path>) -> GenericResult<()> {
+        self.write_to(BufWriter::new(File::create(path)?))?;
+        Ok(())
+    }
+
+    /// deserialize from bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::store::bloom::BloomSet;
+    ///
+    /// let mut set = BloomSet::with_rate(100, 0.01);
+    /// set.insert(&Ioc::new("192.0.2.1").unwrap());
+    ///
+    /// let bytes = set.to_bytes();
+    /// assert_eq!(&bytes[..8], b"IOCBLOOM");
+    ///
+    /// let mounted = BloomSet::from_bytes(&bytes).unwrap();
+    /// assert_eq!(mounted, set);
+    /// assert!(mounted.contains(&Ioc::new("192.0.2.1").unwrap()));
+    ///
+    /// assert!(BloomSet::from_bytes(b"not a filter").is_err());
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> GenericResult<Self> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(BloomError::Broken("header is truncated".to_owned()).into());
+        }
+        if &bytes[..8] != MAGIC {
+            return Err(BloomError::InvalidMagic.into());
+        }
+
+        let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+        if version != FORMAT_VERSION {
+            return Err(BloomError::UnsupportedVersion(version).into());
+        }
+
+        let nhashes = u16::from_le_bytes([bytes[10], bytes[11]]);
+        let nbits = le_u64(&bytes[12..20]);
+        let count = le_u64(&bytes[20..28]);
+        if nhashes == 0 || nbits == 0 {
+            return Err(BloomError::Broken("empty parameter".to_owned()).into());
+        }
+
+        let body = &bytes[HEADER_SIZE..];
+        if (body.len() as u64) != words(nbits) as u64 * 8 {
+            return Err(BloomError::Broken(format!(
+                "expected {} words of bits but {} bytes",
+                words(nbits),
+                body.len()
+            ))
+            .into());
+        }
+
+        Ok(BloomSet {
+            bits: body.chunks(8).map(le_u64).collect(),
+            nbits,
+            nhashes,
+            count,
+        })
+    }
+
+    /// deserialize from reader
+    pub fn read_from(mut reader: impl Read) -> GenericResult<Self> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        BloomSet::from_bytes(&bytes)
+    }
+
+    /// load from file
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        BloomSet::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<'a> Extend<&'a Ioc> for BloomSet {
+    fn extend<T: IntoIterator<Item = &'a Ioc>>(&mut self, iter: T) {
+        for ioc in iter {
+            self.insert(ioc);
+        }
+    }
+}
+
+fn words(nbits: u64) -> usize {
+    nbits.div_ceil(64) as usize
+}
+
+fn le_u64(bytes: &[u8]) -> u64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(bytes);
+    u64::from_le_bytes(b)
+}
@@ -0,0 +1,199 @@
+//! Redis-backed shared set of indicators (requires `redis` feature)
+//!
+//! Indicators are stored as hashes (`{prefix}:ioc:{kind}:{value}`) which may expire,
+//! and a set (`{prefix}:members`) indexes them.
+
+use chrono::Utc;
+use std::sync::Mutex;
+
+use crate::ioc::{Indicator, Ioc};
+use crate::store::IocSet;
+use crate::GenericResult;
+
+/// set of indicators on Redis shared by workers
+pub struct RedisStore {
+    conn: Mutex<::redis::Connection>,
+    prefix: String,
+    ttl: Option<u64>,
+}
+
+impl RedisStore {
+    /// connect to redis (e.g. `redis://127.0.0.1/`) and use keys under prefix
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::store::IocSet;
+    /// use iocutil::store::redis::RedisStore;
+    ///
+    /// let store = RedisStore::open("redis://127.0.0.1/", "iocutil")
+    ///     .unwrap()
+    ///     .with_ttl(24 * 60 * 60);
+    ///
+    /// let ioc = Ioc::new("evil.example.com").unwrap();
+    /// if store.insert(&ioc).unwrap() {
+    ///     println!("first time to see: {}", ioc);
+    /// }
+    /// assert!(store.contains(&ioc).unwrap());
+    ///
+    /// // index entries of expired indicators are removed by a periodic job
+    /// store.prune().unwrap();
+    /// ```
+    pub fn open(url: impl AsRef<str>, prefix: impl AsRef<str>) -> GenericResult<Self> {
+        let client = ::redis::Client::open(url.as_ref())?;
+        Ok(RedisStore {
+            conn: Mutex::new(client.get_connection()?),
+            prefix: prefix.as_ref().to_owned(),
+            ttl: None,
+        })
+    }
+
+    /// indicators expire after seconds from last insertion
+    pub fn with_ttl(mut self, seconds: u64) -> Self {
+        self.ttl = Some(seconds);
+        self
+    }
+
+    fn member(ioc: &Ioc) -> String {
+        format!("{}:{}", ioc.kind(), ioc.value())
+    }
+
+    fn key(&self, member: &str) -> String {
+        format!("{}:ioc:{}", self.prefix, member)
+    }
+
+    fn members_key(&self) -> String {
+        format!("{}:members", self.prefix)
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, ::redis::Connection> {
+        // connection is usable even if other thread panicked while holding it
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// add an indicator with its metadata (first_seen is kept, others are overwritten)
+    pub fn insert_indicator(&self, indicator: impl Into<Indicator>) -> GenericResult<bool> {
+        let indicator = indicator.into();
+        let member = Self::member(&indicator.ioc);
+        let key = self.key(&member);
+        let meta = &indicator.meta;
+        let now = Utc::now().to_rfc3339();
+
+        let mut pipe = ::redis::pipe();
+        pipe.atomic()
+            .cmd("EXISTS")
+            .arg(&key)
+            .cmd("HSETNX")
+            .arg(&key)
+            .arg("first_seen")
+            .arg(
+                meta.first_seen
+                    .map(|x| x.to_rfc3339())
+                    .unwrap_or_else(|| now.clone()),
+            )
+            .ignore()
+            .cmd("HSET")
+            .arg(&key)
+            .arg("last_seen")
+            .arg(meta.last_seen.map(|x| x.to_rfc3339()).unwrap_or(now))
+            .ignore();
+        if let Some(s) = &meta.source {
+            pipe.cmd("HSET").arg(&key).arg("source").arg(s).ignore();
+        }
+        if !meta.tags.is_empty() {
+            pipe.cmd("HSET")
+                .arg(&key)
+                .arg("tags")
+                .arg(meta.tags.join(","))
+                .ignore();
+        }
+        if let Some(ttl) = self.ttl {
+            pipe.cmd("EXPIRE").arg(&key).arg(ttl).ignore();
+        }
+        pipe.cmd("SADD")
+            .arg(self.members_key())
+            .arg(&member)
+            .ignore();
+
+        let (existed,): (bool,) = pipe.query(&mut *self.conn())?;
+        Ok(!existed)
+    }
+
+    /// index entries with whether their indicators exist (not expired)
+    fn entries(&self) -> GenericResult<Vec<(String, bool)>> {
+        let mut conn = self.conn();
+        let members: Vec<String> = ::redis::cmd("SMEMBERS")
+            .arg(self.members_key())
+            .query(&mut *conn)?;
+        if members.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut pipe = ::redis::pipe();
+        for m in &members {
+            pipe.cmd("EXISTS").arg(self.key(m));
+        }
+        let exists: Vec<bool> = pipe.query(&mut *conn)?;
+        Ok(members.into_iter().zip(exists).collect())
+    }
+
+    /// all indicators in the set (expired ones are skipped, see `prune`)
+    pub fn members(&self) -> GenericResult<Vec<Ioc>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|(_, exists)| *exists)
+            .filter_map(|(m, _)| m.split_once(':').and_then(|(_, v)| Ioc::new(v).ok()))
+            .collect())
+    }
+
+    /// remove index entries of expired indicators (returns the number of them)
+    pub fn prune(&self) -> GenericResult<usize> {
+        let expired: Vec<String> = self
+            .entries()?
+            .into_iter()
+            .filter(|(_, exists)| !exists)
+            .map(|(m, _)| m)
+            .collect();
+        if expired.is_empty() {
+            return Ok(0);
+        }
+        let removed: usize = ::redis::cmd("SREM")
+            .arg(self.members_key())
+            .arg(&expired)
+            .query(&mut *self.conn())?;
+        Ok(removed)
+    }
+}
+
+impl IocSet for RedisStore {
+    fn insert(&self, ioc: &Ioc) -> GenericResult<bool> {
+        self.insert_indicator(ioc.clone())
+    }
+
+    fn contains(&self, ioc: &Ioc) -> GenericResult<bool> {
+        let exists: bool = ::redis::cmd("EXISTS")
+            .arg(self.key(&Self::member(ioc)))
+            .query(&mut *self.conn())?;
+        Ok(exists)
+    }
+
+    fn remove(&self, ioc: &Ioc) -> GenericResult<bool> {
+        let member = Self::member(ioc);
+        let (deleted,): (u32,) = ::redis::pipe()
+            .atomic()
+            .cmd("DEL")
+            .arg(self.key(&member))
+            .cmd("SREM")
+            .arg(self.members_key())
+            .arg(&member)
+            .ignore()
+            .query(&mut *self.conn())?;
+        Ok(deleted > 0)
+    }
+
+    fn len(&self) -> GenericResult<usize> {
+        Ok(self.members()?.len())
+    }
+}
@@ -0,0 +1,553 @@
+//! SQLite-backed persistent store of indicators
+
+use chrono::{DateTime, TimeZone, Utc};
+use derive_builder::Builder;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+use crate::confidence::Confidence;
+use crate::graph::{decompose, Relationship};
+use crate::ioc::{Indicator, Ioc, IocKind, Metadata};
+use crate::store::IocSet;
+use crate::tlp::Tlp;
+use crate::GenericResult;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS indicators (
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+    first_seen INTEGER,
+    last_seen INTEGER,
+    source TEXT,
+    description TEXT,
+    confidence INTEGER,
+    valid_until INTEGER,
+    kill_chain_phases TEXT,
+    malware_family TEXT,
+    threat_actor TEXT,
+    tlp TEXT,
+    geo TEXT,
+    registration TEXT,
+    PRIMARY KEY (kind, value)
+);
+CREATE TABLE IF NOT EXISTS tags (
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (kind, value, tag)
+);
+CREATE INDEX IF NOT EXISTS tags_by_tag ON tags (tag);
+CREATE TABLE IF NOT EXISTS relationships (
+    source_kind TEXT NOT NULL,
+    source_value TEXT NOT NULL,
+    target_kind TEXT NOT NULL,
+    target_value TEXT NOT NULL,
+    relationship_type TEXT NOT NULL,
+    PRIMARY KEY (source_kind, source_value, target_kind, target_value, relationship_type)
+);
+CREATE INDEX IF NOT EXISTS relationships_by_target ON relationships (target_kind, target_value);
+";
+
+/// columns added after the first schema (added to older stores on open)
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("valid_until", "INTEGER"),
+    ("kill_chain_phases", "TEXT"),
+    ("malware_family", "TEXT"),
+    ("threat_actor", "TEXT"),
+    ("tlp", "TEXT"),
+    ("geo", "TEXT"),
+    ("registration", "TEXT"),
+];
+
+/// conditions of query (all conditions are joined with AND)
+#[derive(Builder, Debug, Clone, Default)]
+pub struct Query {
+    #[builder(default = "None")]
+    kind: Option<IocKind>,
+    #[builder(setter(into, strip_option), default = "None")]
+    tag: Option<String>,
+    /// seen at or after this time (by last_seen)
+    #[builder(default = "None")]
+    seen_after: Option<DateTime<Utc>>,
+    /// seen at or before this time (by first_seen)
+    #[builder(default = "None")]
+    seen_before: Option<DateTime<Utc>>,
+}
+
+/// persistent collection of indicators on SQLite
+pub struct SqliteStore {
+    conn: Connection,
+    decompose: bool,
+}
+
+fn millis(d: Option<DateTime<Utc>>) -> Option<i64> {
+    d.map(|x| x.timestamp_millis())
+}
+
+fn datetime(ms: Option<i64>) -> Option<DateTime<Utc>> {
+    ms.map(|x| Utc.timestamp_millis(x))
+}
+
+impl SqliteStore {
+    /// open (or create) store in file
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// open volatile store in memory
+    pub fn open_in_memory() -> GenericResult<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> GenericResult<Self> {
+        conn.execute_batch(SCHEMA)?;
+        for (column, t) in ADDED_COLUMNS {
+            let exists = conn
+                .prepare(&format!("SELECT {} FROM indicators LIMIT 0", column))
+                .is_ok();
+            if !exists {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE indicators ADD COLUMN {} {}",
+                    column, t
+                ))?;
+            }
+        }
+        Ok(SqliteStore {
+            conn,
+            decompose: false,
+        })
+    }
+
+    /// on upsert, add components of composite indicators (host of url, domain of email address)
+    /// with relationships to them (as `iocutil::graph::decompose`). Components inherit source
+    /// and TLP of the composite one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::store::sqlite::SqliteStore;
+    ///
+    /// let store = SqliteStore::open_in_memory().unwrap().with_decomposition(true);
+    /// let url = Ioc::new("http://evil.example.com/gate.php").unwrap();
+    /// let mut i = Indicator::new(url.clone());
+    /// i.meta.source = Some("feed".to_owned());
+    /// store.upsert(i).unwrap();
+    /// store.upsert(Indicator::new(Ioc::new("alice@evil.example.com").unwrap())).unwrap();
+    ///
+    /// let domain = Ioc::new("evil.example.com").unwrap();
+    /// assert_eq!(store.get(&domain).unwrap().unwrap().meta.source.as_deref(), Some("feed"));
+    /// assert_eq!(store.len().unwrap(), 3);
+    ///
+    /// let r = store.relationships_of(&url).unwrap();
+    /// assert_eq!((&r[0].target, r[0].relationship_type.as_str()), (&domain, "hosted-on"));
+    /// assert_eq!(store.relationships_of(&domain).unwrap().len(), 2);
+    ///
+    /// store.remove(&url).unwrap();
+    /// assert_eq!(store.relationships().unwrap().len(), 1);
+    /// ```
+    pub fn with_decomposition(mut self, decompose: bool) -> Self {
+        self.decompose = decompose;
+        self
+    }
+
+    fn tags_of(&self, kind: &str, value: &str) -> GenericResult<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT tag FROM tags WHERE kind = ?1 AND value = ?2 ORDER BY rowid")?;
+        let tags = stmt
+            .query_map(params![kind, value], |r| r.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(tags)
+    }
+
+    /// get an indicator in store
+    pub fn get(&self, ioc: &Ioc) -> GenericResult<Option<Indicator>> {
+        let kind = ioc.kind().to_string();
+        let value = ioc.value();
+
+        let meta = self
+            .conn
+            .query_row(
+                "SELECT first_seen, last_seen, source, description, confidence, valid_until, kill_chain_phases, malware_family, threat_actor, tlp, geo, registration FROM indicators WHERE kind = ?1 AND value = ?2",
+                params![kind, value],
+                |r| {
+                    Ok(Metadata {
+                        first_seen: datetime(r.get(0)?),
+                        last_seen: datetime(r.get(1)?),
+                        source: r.get(2)?,
+                        description: r.get(3)?,
+                        tags: vec![],
+                        confidence: r.get::<_, Option<u8>>(4)?.map(Confidence::new),
+                        valid_until: datetime(r.get(5)?),
+                        kill_chain_phases: r
+                            .get::<_, Option<String>>(6)?
+                            .unwrap_or_default()
+                            .split(',')
+                            .filter_map(|x| x.parse().ok())
+                            .collect(),
+                        malware_family: r.get(7)?,
+                        threat_actor: r.get(8)?,
+                        tlp: r
+                            .get::<_, Option<String>>(9)?
+                            .and_then(|x| x.parse().ok()),
+                        geo: r
+                            .get::<_, Option<String>>(10)?
+                            .and_then(|x| serde_json::from_str(&x).ok()),
+                        registration: r
+                            .get::<_, Option<String>>(11)?
+                            .and_then(|x| serde_json::from_str(&x).ok()),
+                    })
+                },
+            )
+            .optional()?;
+
+        match meta {
+            Some(mut m) => {
+                m.tags = self.tags_of(&kind, &value)?;
+                Ok(Some(Indicator::with_meta(ioc.clone(), m)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// insert or update an indicator
+    /// Metadata is merged with stored one by `Metadata::merge`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::store::sqlite::SqliteStore;
+    ///
+    /// let store = SqliteStore::open_in_memory().unwrap();
+    /// let ioc = Ioc::new("evil.example.com").unwrap();
+    ///
+    /// let mut i = Indicator::new(ioc.clone());
+    /// i.meta.first_seen = Some(day!(2019, 11, 2).unwrap().into());
+    /// i.meta.tags = vec!["c2".to_owned()];
+    /// store.upsert(i).unwrap();
+    ///
+    /// let mut i = Indicator::new(ioc.clone());
+    /// i.meta.first_seen = Some(day!(2019, 11, 1).unwrap().into());
+    /// i.meta.last_seen = Some(day!(2019, 11, 5).unwrap().into());
+    /// i.meta.tags = vec!["botnet".to_owned()];
+    /// i.meta.kill_chain_phases = vec![KillChainPhase::CommandAndControl];
+    /// i.meta.malware_family = Some("Emotet".to_owned());
+    /// store.upsert(i).unwrap();
+    ///
+    /// let stored = store.get(&ioc).unwrap().unwrap();
+    /// assert_eq!(stored.meta.first_seen, Some(day!(2019, 11, 1).unwrap().into()));
+    /// assert_eq!(stored.meta.last_seen, Some(day!(2019, 11, 5).unwrap().into()));
+    /// assert_eq!(stored.meta.tags, vec!["c2".to_owned(), "botnet".to_owned()]);
+    /// assert_eq!(stored.meta.kill_chain_phases, vec![KillChainPhase::CommandAndControl]);
+    /// assert_eq!(stored.meta.malware_family.as_deref(), Some("Emotet"));
+    /// assert_eq!(store.len().unwrap(), 1);
+    /// ```
+    pub fn upsert(&self, indicator: impl Into<Indicator>) -> GenericResult<()> {
+        let indicator = indicator.into();
+        let meta = match self.get(&indicator.ioc)? {
+            Some(old) => old.meta.merge(indicator.meta),
+            None => indicator.meta,
+        };
+
+        let kind = indicator.ioc.kind().to_string();
+        let value = indicator.ioc.value();
+        let phases: Option<String> = match meta.kill_chain_phases.as_slice() {
+            [] => None,
+            xs => Some(xs.iter().map(|x| x.name()).collect::<Vec<_>>().join(",")),
+        };
+
+        self.conn.execute(
+            "INSERT OR REPLACE INTO indicators (kind, value, first_seen, last_seen, source, description, confidence, valid_until, kill_chain_phases, malware_family, threat_actor, tlp, geo, registration) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                kind,
+                value,
+                millis(meta.first_seen),
+                millis(meta.last_seen),
+                meta.source,
+                meta.description,
+                meta.confidence.map(Confidence::score),
+                millis(meta.valid_until),
+                phases,
+                meta.malware_family,
+                meta.threat_actor,
+                meta.tlp.map(Tlp::name),
+                meta.geo.as_ref().map(serde_json::to_string).transpose()?,
+                meta.registration.as_ref().map(serde_json::to_string).transpose()?
+            ],
+        )?;
+
+        let mut stmt = self
+            .conn
+            .prepare_cached("INSERT OR IGNORE INTO tags (kind, value, tag) VALUES (?1, ?2, ?3)")?;
+        for t in &meta.tags {
+            stmt.execute(params![kind, value, t])?;
+        }
+
+        if self.decompose {
+            for r in decompose(&indicator.ioc) {
+                let mut component = Indicator::new(r.target.clone());
+                component.meta.source = meta.source.clone();
+                component.meta.tlp = meta.tlp;
+                self.upsert(component)?;
+                self.relate(&r)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// add a relationship between indicators (they are not added to store)
+    pub fn relate(&self, relationship: &Relationship) -> GenericResult<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO relationships (source_kind, source_value, target_kind, target_value, relationship_type) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                relationship.source.kind().to_string(),
+                relationship.source.value(),
+                relationship.target.kind().to_string(),
+                relationship.target.value(),
+                relationship.relationship_type
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn query_relationships(
+        &self,
+        condition: &str,
+        args: &[&dyn rusqlite::ToSql],
+    ) -> GenericResult<Vec<Relationship>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT source_value, target_value, relationship_type FROM relationships WHERE {} ORDER BY rowid",
+            condition
+        ))?;
+        let rows = stmt
+            .query_map(args, |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|(source, target, t)| {
+                Ok(Relationship {
+                    source: Ioc::new(&source)?,
+                    target: Ioc::new(&target)?,
+                    relationship_type: t,
+                })
+            })
+            .collect()
+    }
+
+    /// relationships from or to the indicator
+    pub fn relationships_of(&self, ioc: &Ioc) -> GenericResult<Vec<Relationship>> {
+        self.query_relationships(
+            "(source_kind = ?1 AND source_value = ?2) OR (target_kind = ?1 AND target_value = ?2)",
+            params![ioc.kind().to_string(), ioc.value()],
+        )
+    }
+
+    /// all stored relationships
+    pub fn relationships(&self) -> GenericResult<Vec<Relationship>> {
+        self.query_relationships("1 = 1", params![])
+    }
+
+    /// insert or update indicators in a transaction (bulk import)
+    pub fn upsert_all(
+        &mut self,
+        indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    ) -> GenericResult<usize> {
+        self.conn.execute_batch("BEGIN")?;
+        let mut count = 0;
+        for i in indicators {
+            if let Err(e) = self.upsert(i) {
+                self.conn.execute_batch("ROLLBACK")?;
+                return Err(e);
+            }
+            count += 1;
+        }
+        self.conn.execute_batch("COMMIT")?;
+        Ok(count)
+    }
+
+    /// remove an indicator (returns true if it was stored)
+    pub fn remove(&self, ioc: &Ioc) -> GenericResult<bool> {
+        let kind = ioc.kind().to_string();
+        let value = ioc.value();
+        self.conn.execute(
+            "DELETE FROM tags WHERE kind = ?1 AND value = ?2",
+            params![kind, value],
+        )?;
+        self.conn.execute(
+            "DELETE FROM relationships WHERE (source_kind = ?1 AND source_value = ?2) OR (target_kind = ?1 AND target_value = ?2)",
+            params![kind, value],
+        )?;
+        let n = self.conn.execute(
+            "DELETE FROM indicators WHERE kind = ?1 AND value = ?2",
+            params![kind, value],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// remove indicators expired at the time (by `valid_until`), returns number of removed ones
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::store::sqlite::SqliteStore;
+    ///
+    /// let store = SqliteStore::open_in_memory().unwrap();
+    /// let mut i = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+    /// i.meta.valid_until = Some(day!(2019, 12, 1).unwrap().into());
+    /// store.upsert(i).unwrap();
+    /// store.upsert(Indicator::new(Ioc::new("example.com").unwrap())).unwrap();
+    ///
+    /// assert_eq!(store.expire(day!(2019, 11, 1).unwrap().into()).unwrap(), 0);
+    /// assert_eq!(store.expire(day!(2020, 1, 1).unwrap().into()).unwrap(), 1);
+    /// assert_eq!(store.len().unwrap(), 1);
+    /// ```
+    pub fn expire(&self, now: DateTime<Utc>) -> GenericResult<usize> {
+        let now = now.timestamp_millis();
+        self.conn.execute(
+            "DELETE FROM tags WHERE (kind, value) IN (SELECT kind, value FROM indicators WHERE valid_until <= ?1)",
+            params![now],
+        )?;
+        self.conn.execute(
+            "DELETE FROM relationships WHERE (source_kind, source_value) IN (SELECT kind, value FROM indicators WHERE valid_until <= ?1) OR (target_kind, target_value) IN (SELECT kind, value FROM indicators WHERE valid_until <= ?1)",
+            params![now],
+        )?;
+        let n = self.conn.execute(
+            "DELETE FROM indicators WHERE valid_until <= ?1",
+            params![now],
+        )?;
+        Ok(n)
+    }
+
+    /// number of stored indicators
+    pub fn len(&self) -> GenericResult<usize> {
+        let n: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM indicators", params![], |r| r.get(0))?;
+        Ok(n as usize)
+    }
+
+    /// store has no indicator?
+    pub fn is_empty(&self) -> GenericResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// find indicators matched with query
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::store::sqlite::{QueryBuilder, SqliteStore};
+    ///
+    /// let mut store = SqliteStore::open_in_memory().unwrap();
+    ///
+    /// let mut tagged = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+    /// tagged.meta.tags = vec!["c2".to_owned()];
+    /// tagged.meta.last_seen = Some(day!(2019, 11, 5).unwrap().into());
+    ///
+    /// store.upsert_all(vec![
+    ///     tagged,
+    ///     Indicator::new(Ioc::new("198.51.100.1").unwrap()),
+    ///     Indicator::new(Ioc::new("example.com").unwrap()),
+    /// ]).unwrap();
+    ///
+    /// let q = QueryBuilder::default().kind(Some(IocKind::IpAddress)).build().unwrap();
+    /// assert_eq!(store.query(&q).unwrap().len(), 2);
+    ///
+    /// let q = QueryBuilder::default()
+    ///     .tag("c2")
+    ///     .seen_after(Some(day!(2019, 11, 1).unwrap().into()))
+    ///     .build()
+    ///     .unwrap();
+    /// let found = store.query(&q).unwrap();
+    /// assert_eq!(found.len(), 1);
+    /// assert_eq!(found[0].ioc, Ioc::new("192.0.2.1").unwrap());
+    ///
+    /// assert_eq!(store.all().unwrap().len(), 3);
+    /// ```
+    pub fn query(&self, query: &Query) -> GenericResult<Vec<Indicator>> {
+        let mut conditions = vec!["1 = 1".to_owned()];
+        let mut args: Vec<Box<dyn rusqlite::ToSql>> = vec![];
+
+        if let Some(k) = query.kind {
+            args.push(Box::new(k.to_string()));
+            conditions.push(format!("i.kind = ?{}", args.len()));
+        }
+        if let Some(t) = &query.tag {
+            args.push(Box::new(t.clone()));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM tags t WHERE t.kind = i.kind AND t.value = i.value AND t.tag = ?{})",
+                args.len()
+            ));
+        }
+        if let Some(d) = query.seen_after {
+            args.push(Box::new(d.timestamp_millis()));
+            conditions.push(format!(
+                "COALESCE(i.last_seen, i.first_seen) >= ?{}",
+                args.len()
+            ));
+        }
+        if let Some(d) = query.seen_before {
+            args.push(Box::new(d.timestamp_millis()));
+            conditions.push(format!(
+                "COALESCE(i.first_seen, i.last_seen) <= ?{}",
+                args.len()
+            ));
+        }
+
+        let sql = format!(
+            "SELECT i.kind, i.value FROM indicators i WHERE {} ORDER BY i.rowid",
+            conditions.join(" AND ")
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let keys = stmt
+            .query_map(
+                rusqlite::params_from_iter(args.iter().map(|x| x.as_ref())),
+                |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)),
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut out = vec![];
+        for (_, value) in keys {
+            let ioc = Ioc::new(&value)?;
+            if let Some(i) = self.get(&ioc)? {
+                out.push(i);
+            }
+        }
+        Ok(out)
+    }
+
+    /// all stored indicators (bulk export)
+    pub fn all(&self) -> GenericResult<Vec<Indicator>> {
+        self.query(&Query::default())
+    }
+}
+
+impl IocSet for SqliteStore {
+    fn insert(&self, ioc: &Ioc) -> GenericResult<bool> {
+        let new = self.get(ioc)?.is_none();
+        self.upsert(ioc.clone())?;
+        Ok(new)
+    }
+
+    fn contains(&self, ioc: &Ioc) -> GenericResult<bool> {
+        Ok(self.get(ioc)?.is_some())
+    }
+
+    fn remove(&self, ioc: &Ioc) -> GenericResult<bool> {
+        SqliteStore::remove(self, ioc)
+    }
+
+    fn len(&self) -> GenericResult<usize> {
+        SqliteStore::len(self)
+    }
+}
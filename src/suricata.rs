@@ -0,0 +1,186 @@
+//! Suricata rule generation from network indicators
+
+use derive_builder::Builder;
+use failure::Fail;
+use std::collections::HashSet;
+
+use crate::allowlist::suppress;
+use crate::ioc::{host_of_url, Indicator, Ioc};
+use crate::ttl::unexpired;
+use crate::GenericResult;
+
+/// Errors in generating rules
+#[derive(Fail, Debug)]
+pub enum SuricataError {
+    #[fail(display = "sid range is exhausted")]
+    SidRangeExhausted(u32),
+}
+
+/// options of generated rules
+///
+/// `{kind}`, `{value}` (defanged) and `{source}` in msg template are replaced.
+#[derive(Builder, Debug, Clone)]
+pub struct SuricataOptions {
+    /// first sid (local rules should be in 1000000-1999999)
+    #[builder(default = "1_000_000")]
+    sid_start: u32,
+    /// last sid which can be used (unlimited if None)
+    #[builder(default = "None")]
+    sid_end: Option<u32>,
+    #[builder(setter(into), default = "\"IOCUTIL {kind} {value}\".to_owned()")]
+    msg: String,
+    #[builder(setter(into), default = "\"trojan-activity\".to_owned()")]
+    classtype: String,
+    #[builder(setter(into), default = "\"alert\".to_owned()")]
+    action: String,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for SuricataOptions {
+    fn default() -> Self {
+        SuricataOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// escape text in content keyword
+fn content(text: impl AsRef<str>) -> String {
+    text.as_ref()
+        .replace('\\', "|5C|")
+        .replace('"', "|22|")
+        .replace(';', "|3B|")
+}
+
+/// escape text in msg keyword
+fn msg(text: impl AsRef<str>) -> String {
+    text.as_ref()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(';', "\\;")
+}
+
+/// header and detection options of rules for an indicator
+fn detections(ioc: &Ioc) -> Vec<(&'static str, String)> {
+    match ioc {
+        Ioc::Domain(d) => {
+            let c = content(format!(".{}", d));
+            vec![
+                (
+                    "dns $HOME_NET any -> any any",
+                    format!("dns.query; dotprefix; content:\"{}\"; nocase; endswith;", c),
+                ),
+                (
+                    "tls $HOME_NET any -> $EXTERNAL_NET any",
+                    format!(
+                        "flow:established,to_server; tls.sni; dotprefix; content:\"{}\"; nocase; endswith;",
+                        c
+                    ),
+                ),
+            ]
+        }
+        Ioc::Url(u) => {
+            let host = match host_of_url(u) {
+                Some(h) => h.value(),
+                None => return vec![],
+            };
+            let rest = &u[u.find("://").map(|x| x + 3).unwrap_or(0)..];
+            let uri = match rest.find(&['/', '?'][..]) {
+                Some(idx) => &rest[idx..],
+                None => "/",
+            };
+            // fragment is never sent to server
+            let uri = uri.split('#').next().unwrap_or("/");
+            vec![(
+                "http $HOME_NET any -> $EXTERNAL_NET any",
+                format!(
+                    "flow:established,to_server; http.host; content:\"{}\"; bsize:{}; nocase; http.uri; content:\"{}\"; bsize:{};",
+                    content(&host),
+                    host.len(),
+                    content(uri),
+                    uri.len()
+                ),
+            )]
+        }
+        Ioc::IpAddress(ip) => vec![("ip $HOME_NET any -> ", format!("[{}] any", ip))],
+        _ => vec![],
+    }
+}
+
+/// generate Suricata rules from network indicators (domain / url / ip address)
+/// Other kinds are ignored.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::suricata::{to_suricata_rules, SuricataOptionsBuilder};
+///
+/// let iocs = vec![
+///     Ioc::new("evil.example.com").unwrap(),
+///     Ioc::new("http://192.0.2.1/gate.php?id=1").unwrap(),
+///     Ioc::new("198.51.100.1").unwrap(),
+///     Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+/// ];
+///
+/// let options = SuricataOptionsBuilder::default().sid_start(3_000_000).build().unwrap();
+/// let rules = to_suricata_rules(iocs, &options).unwrap();
+/// let rules: Vec<_> = rules.lines().collect();
+///
+/// assert_eq!(rules.len(), 4); // dns + tls, http, ip
+/// assert_eq!(
+///     rules[0],
+///     "alert dns $HOME_NET any -> any any (msg:\"IOCUTIL domain evil[.]example[.]com\"; dns.query; dotprefix; content:\".evil.example.com\"; nocase; endswith; classtype:trojan-activity; sid:3000000; rev:1;)"
+/// );
+/// assert!(rules[2].contains("http.uri; content:\"/gate.php?id=1\"; bsize:14;"));
+/// assert!(rules[3].starts_with("alert ip $HOME_NET any -> [198.51.100.1] any (msg:"));
+///
+/// let options = SuricataOptionsBuilder::default().sid_start(1).sid_end(Some(2)).build().unwrap();
+/// assert!(to_suricata_rules(vec![Ioc::new("evil.example.com").unwrap(), Ioc::new("198.51.100.1").unwrap()], &options).is_err());
+/// ```
+pub fn to_suricata_rules(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &SuricataOptions,
+) -> GenericResult<String> {
+    let mut seen = HashSet::new();
+    let mut sid = options.sid_start;
+    let mut rules = String::new();
+
+    for indicator in suppress(unexpired(indicators, options.include_expired)) {
+        let indicator: Indicator = indicator;
+        if !seen.insert(indicator.ioc.clone()) {
+            continue;
+        }
+
+        let m = msg(options
+            .msg
+            .replace("{kind}", &indicator.ioc.kind().to_string())
+            .replace("{value}", &indicator.ioc.defanged())
+            .replace(
+                "{source}",
+                indicator.meta.source.as_deref().unwrap_or_default(),
+            ));
+
+        for (header, detection) in detections(&indicator.ioc) {
+            if sid > options.sid_end.unwrap_or(u32::MAX) {
+                return Err(SuricataError::SidRangeExhausted(sid).into());
+            }
+
+            let rule = match indicator.ioc {
+                // destination of ip rules is placed in header
+                Ioc::IpAddress(_) => format!(
+                    "{} {}{} (msg:\"{}\"; classtype:{}; sid:{}; rev:1;)\n",
+                    options.action, header, detection, m, options.classtype, sid
+                ),
+                _ => format!(
+                    "{} {} (msg:\"{}\"; {} classtype:{}; sid:{}; rev:1;)\n",
+                    options.action, header, m, detection, options.classtype, sid
+                ),
+            };
+            rules.push_str(&rule);
+            sid += 1;
+        }
+    }
+
+    Ok(rules)
+}
@@ -0,0 +1,526 @@
+//! TAXII 2.1 client, helpers to serve collections and its utilities
+
+use chrono::{DateTime, Utc};
+use failure::Fail;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+
+use crate::http::{block_on, ClientConfig};
+use crate::ioc::Indicator;
+use crate::nonblocking;
+use crate::paginate::Paginated;
+use crate::stix::from_stix_objects;
+pub use crate::stix::timestamp;
+use crate::GenericResult;
+
+/// media type of TAXII 2.1
+pub const TAXII_MEDIA_TYPE: &str = "application/taxii+json;version=2.1";
+
+/// client for TAXII 2.1 server
+pub struct TaxiiClient {
+    inner: nonblocking::TaxiiClient,
+}
+
+/// Errors in operating TAXII server
+#[derive(Fail, Debug)]
+pub enum TaxiiError {
+    #[fail(display = "no more pages")]
+    AlreadyReachToEnd,
+
+    #[fail(display = "invalid parameter: {}", _0)]
+    InvalidParameter(String),
+}
+
+/// discovery information of a server
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Discovery {
+    pub title: String,
+    pub description: Option<String>,
+    pub contact: Option<String>,
+    pub default: Option<String>,
+    pub api_roots: Option<Vec<String>>,
+}
+
+/// information of an api root
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiRoot {
+    pub title: String,
+    pub description: Option<String>,
+    pub versions: Vec<String>,
+    pub max_content_length: u64,
+}
+
+/// a collection on api root
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Collection {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub alias: Option<String>,
+    pub can_read: bool,
+    pub can_write: bool,
+    pub media_types: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Collections {
+    pub(crate) collections: Option<Vec<Collection>>,
+}
+
+/// envelope of objects (a page)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Envelope {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub more: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objects: Option<Vec<serde_json::Value>>,
+}
+
+/// manifest of an object
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestRecord {
+    pub id: String,
+    pub date_added: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+}
+
+/// manifest of objects (a page)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Manifest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub more: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objects: Option<Vec<ManifestRecord>>,
+}
+
+impl TaxiiClient {
+    /// new client for server (e.g. `https://example.com/`)
+    pub fn new(server: impl AsRef<str>) -> Self {
+        TaxiiClient {
+            inner: nonblocking::TaxiiClient::new(server),
+        }
+    }
+
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.inner = self.inner.with_config(config);
+        self
+    }
+
+    /// set credential for basic authentication
+    pub fn with_credential(mut self, username: impl AsRef<str>, password: impl AsRef<str>) -> Self {
+        self.inner = self.inner.with_credential(username, password);
+        self
+    }
+
+    /// discover server information and api roots
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::taxii::TaxiiClient;
+    ///
+    /// let client = TaxiiClient::new("https://example.com/").with_credential("user", "pass");
+    /// let discovery = client.discover().expect("failed to discover");
+    /// println!("{:?}", discovery.api_roots);
+    /// ```
+    pub fn discover(&self) -> GenericResult<Discovery> {
+        block_on(self.inner.discover())
+    }
+
+    /// get information of api root
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::taxii::TaxiiClient;
+    ///
+    /// let client = TaxiiClient::new("https://example.com/");
+    /// let root = client.api_root("api1").expect("failed to get api root");
+    /// assert!(root.versions.contains(&iocutil::taxii::TAXII_MEDIA_TYPE.to_string()));
+    /// ```
+    pub fn api_root(&self, api_root: impl AsRef<str>) -> GenericResult<ApiRoot> {
+        block_on(self.inner.api_root(api_root))
+    }
+
+    /// list collections on api root
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::taxii::TaxiiClient;
+    ///
+    /// let client = TaxiiClient::new("https://example.com/");
+    /// for c in client.collections("api1").expect("failed to list collections") {
+    ///     println!("{}: {}", c.id, c.title);
+    /// }
+    /// ```
+    pub fn collections(&self, api_root: impl AsRef<str>) -> GenericResult<Vec<Collection>> {
+        block_on(self.inner.collections(api_root))
+    }
+
+    /// objects in collection, fetched page by page lazily
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::taxii::TaxiiClient;
+    ///
+    /// let client = TaxiiClient::new("https://example.com/");
+    /// for object in client
+    ///     .objects("api1", "91a7b528-80eb-42ed-a74d-c6fbd5a26116", Some(days_ago(1)))
+    ///     .limit(500)
+    /// {
+    ///     println!("{}", object.unwrap()["id"]);
+    /// }
+    /// ```
+    pub fn objects(
+        &self,
+        api_root: impl AsRef<str>,
+        collection_id: impl AsRef<str>,
+        added_after: Option<DateTime<Utc>>,
+    ) -> Paginated<'_, serde_json::Value> {
+        self.inner.objects(api_root, collection_id, added_after)
+    }
+
+    /// poll objects in collection by pages
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::taxii::TaxiiClient;
+    ///
+    /// let client = TaxiiClient::new("https://example.com/");
+    /// for page in client.poll("api1", "91a7b528-80eb-42ed-a74d-c6fbd5a26116", Some(days_ago(1))) {
+    ///     for object in page.unwrap() {
+    ///         println!("{}", object["id"]);
+    ///     }
+    /// }
+    /// ```
+    pub fn poll(
+        &self,
+        api_root: impl AsRef<str>,
+        collection_id: impl AsRef<str>,
+        added_after: Option<DateTime<Utc>>,
+    ) -> Objects<'_> {
+        Objects {
+            inner: self.inner.poll(api_root, collection_id, added_after),
+            failed: false,
+        }
+    }
+}
+
+/// context object for objects api
+pub struct Objects<'a> {
+    inner: nonblocking::taxii::Objects<'a>,
+    failed: bool,
+}
+
+impl<'a> Objects<'a> {
+    /// set number of objects per a page (server may cap it)
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.inner = self.inner.limit(limit);
+        self
+    }
+
+    /// request a page
+    pub fn do_poll(&mut self) -> GenericResult<Vec<serde_json::Value>> {
+        block_on(self.inner.do_poll())
+    }
+
+    /// no more pages?
+    pub fn has_done(&self) -> bool {
+        self.inner.has_done()
+    }
+
+    /// get all objects (fails if any page fails)
+    pub fn get_all(self) -> GenericResult<Vec<serde_json::Value>> {
+        let mut objects = vec![];
+        for page in self {
+            objects.extend(page?);
+        }
+        Ok(objects)
+    }
+
+    /// get all indicators in all objects (converted with STIX importer)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    /// use iocutil::taxii::TaxiiClient;
+    ///
+    /// let client = TaxiiClient::new("https://example.com/");
+    /// let indicators = client
+    ///     .poll("api1", "91a7b528-80eb-42ed-a74d-c6fbd5a26116", Some(days_ago(1)))
+    ///     .get_all_indicators()
+    ///     .unwrap();
+    /// ```
+    pub fn get_all_indicators(self) -> GenericResult<Vec<Indicator>> {
+        Ok(from_stix_objects(self.get_all()?))
+    }
+}
+
+impl<'a> Iterator for Objects<'a> {
+    type Item = GenericResult<Vec<serde_json::Value>>;
+
+    /// pages until the last one or an error
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.has_done() {
+            return None;
+        }
+        let page = self.do_poll();
+        self.failed = page.is_err();
+        Some(page)
+    }
+}
+
+/// parameters of objects / manifest request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageRequest {
+    pub added_after: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+    pub next: Option<String>,
+}
+
+impl PageRequest {
+    /// parse query string of request (e.g. `added_after=2019-11-01T00:00:00.000Z&limit=10`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::taxii::PageRequest;
+    ///
+    /// let r = PageRequest::from_query("added_after=2019-11-01T00%3A00%3A00.000Z&limit=10&next=20").unwrap();
+    /// assert_eq!(r.limit, Some(10));
+    /// assert_eq!(r.next, Some("20".to_owned()));
+    /// assert!(r.added_after.is_some());
+    ///
+    /// assert!(PageRequest::from_query("limit=many").is_err());
+    /// ```
+    pub fn from_query(query: impl AsRef<str>) -> GenericResult<Self> {
+        let mut req = PageRequest::default();
+        for pair in query.as_ref().trim_start_matches('?').split('&') {
+            let mut kv = pair.splitn(2, '=');
+            let (key, value) = match (kv.next(), kv.next()) {
+                (Some(k), Some(v)) => (k, percent_decode_str(v).decode_utf8()?.into_owned()),
+                _ => continue,
+            };
+            let invalid = || TaxiiError::InvalidParameter(pair.to_owned());
+            match key {
+                "added_after" => {
+                    req.added_after = Some(
+                        DateTime::parse_from_rfc3339(&value)
+                            .map_err(|_| invalid())?
+                            .into(),
+                    )
+                }
+                "limit" => req.limit = Some(value.parse().map_err(|_| invalid())?),
+                "next" => req.next = Some(value),
+                _ => {}
+            }
+        }
+        Ok(req)
+    }
+}
+
+/// a page of response with its date range
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub body: T,
+    pub date_added_first: Option<DateTime<Utc>>,
+    pub date_added_last: Option<DateTime<Utc>>,
+}
+
+impl<T> Page<T> {
+    /// `X-TAXII-Date-Added-First` / `X-TAXII-Date-Added-Last` headers of response
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![];
+        if let Some(d) = self.date_added_first {
+            headers.push(("X-TAXII-Date-Added-First", timestamp(d)));
+        }
+        if let Some(d) = self.date_added_last {
+            headers.push(("X-TAXII-Date-Added-Last", timestamp(d)));
+        }
+        headers
+    }
+}
+
+/// object and its date added
+type AddedObject = (DateTime<Utc>, serde_json::Value);
+
+/// objects of a collection to serve (ordered by date added)
+///
+/// pagination marker (`next`) is valid for requests with same `added_after`.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::stix::{to_stix_bundle, StixOptions};
+/// use iocutil::taxii::{PageRequest, ServerCollection};
+///
+/// let bundle = to_stix_bundle(
+///     vec![Ioc::new("example.com").unwrap(), Ioc::new("192.0.2.1").unwrap()],
+///     &StixOptions::default(),
+/// );
+///
+/// let mut collection = ServerCollection::new();
+/// collection.add_bundle(&bundle, days_ago(1));
+///
+/// // a page with an object
+/// let page = collection.objects(&PageRequest { limit: Some(1), ..Default::default() }).unwrap();
+/// assert_eq!(page.body.objects.as_ref().unwrap().len(), 1);
+/// assert_eq!(page.body.more, Some(true));
+/// assert_eq!(page.headers().len(), 2);
+///
+/// // next (last) page
+/// let next = PageRequest { limit: Some(1), next: page.body.next.clone(), ..Default::default() };
+/// let page = collection.objects(&next).unwrap();
+/// assert_eq!(page.body.objects.unwrap().len(), 1);
+/// assert_eq!(page.body.more, Some(false));
+///
+/// // objects added after now
+/// let later = PageRequest { added_after: Some(Utc::now()), ..Default::default() };
+/// assert!(collection.manifest(&later).unwrap().body.objects.is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServerCollection {
+    objects: Vec<AddedObject>,
+    max_page_size: usize,
+}
+
+impl Default for ServerCollection {
+    fn default() -> Self {
+        ServerCollection {
+            objects: vec![],
+            max_page_size: 100,
+        }
+    }
+}
+
+impl ServerCollection {
+    /// empty collection (100 objects per page at most)
+    pub fn new() -> Self {
+        ServerCollection::default()
+    }
+
+    /// set maximum number of objects per a page
+    pub fn with_max_page_size(mut self, size: usize) -> Self {
+        self.max_page_size = size.max(1);
+        self
+    }
+
+    /// add an object
+    pub fn add_object(&mut self, object: serde_json::Value, date_added: impl Into<DateTime<Utc>>) {
+        let date_added = date_added.into();
+        // keep order of objects added at same time
+        let idx = self.objects.partition_point(|(d, _)| *d <= date_added);
+        self.objects.insert(idx, (date_added, object));
+    }
+
+    /// add objects in STIX bundle
+    pub fn add_bundle(&mut self, bundle: &serde_json::Value, date_added: impl Into<DateTime<Utc>>) {
+        let date_added = date_added.into();
+        if let Some(objects) = bundle["objects"].as_array() {
+            for o in objects {
+                self.add_object(o.clone(), date_added);
+            }
+        }
+    }
+
+    /// number of objects
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// has no object?
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// objects in the page, and whether more objects follow
+    fn page(&self, req: &PageRequest) -> GenericResult<(&[AddedObject], Option<String>)> {
+        let start = match req.added_after {
+            Some(a) => self.objects.partition_point(|(d, _)| *d <= a),
+            None => 0,
+        };
+        let offset: usize = match &req.next {
+            Some(n) => n
+                .parse()
+                .map_err(|_| TaxiiError::InvalidParameter(format!("next={}", n)))?,
+            None => 0,
+        };
+        let limit = req
+            .limit
+            .unwrap_or(self.max_page_size)
+            .min(self.max_page_size)
+            .max(1);
+
+        let filtered = &self.objects[start..];
+        let begin = offset.min(filtered.len());
+        let end = (begin + limit).min(filtered.len());
+        let next = if end < filtered.len() {
+            Some(end.to_string())
+        } else {
+            None
+        };
+        Ok((&filtered[begin..end], next))
+    }
+
+    fn wrap<T>(body: T, page: &[AddedObject]) -> Page<T> {
+        Page {
+            body,
+            date_added_first: page.first().map(|x| x.0),
+            date_added_last: page.last().map(|x| x.0),
+        }
+    }
+
+    /// envelope of objects for the request (response of objects api)
+    pub fn objects(&self, req: &PageRequest) -> GenericResult<Page<Envelope>> {
+        let (page, next) = self.page(req)?;
+        let envelope = Envelope {
+            more: Some(next.is_some()),
+            next,
+            objects: if page.is_empty() {
+                None
+            } else {
+                Some(page.iter().map(|x| x.1.clone()).collect())
+            },
+        };
+        Ok(ServerCollection::wrap(envelope, page))
+    }
+
+    /// manifest of objects for the request (response of manifest api)
+    pub fn manifest(&self, req: &PageRequest) -> GenericResult<Page<Manifest>> {
+        let (page, next) = self.page(req)?;
+        let records: Vec<_> = page
+            .iter()
+            .map(|(d, o)| ManifestRecord {
+                id: o["id"].as_str().unwrap_or_default().to_owned(),
+                date_added: timestamp(*d),
+                version: o["modified"]
+                    .as_str()
+                    .or_else(|| o["created"].as_str())
+                    .map(|x| x.to_owned())
+                    .unwrap_or_else(|| timestamp(*d)),
+                media_type: Some("application/stix+json;version=2.1".to_owned()),
+            })
+            .collect();
+        let manifest = Manifest {
+            more: Some(next.is_some()),
+            objects: if records.is_empty() {
+                None
+            } else {
+                Some(records)
+            },
+        };
+        Ok(ServerCollection::wrap(manifest, page))
+    }
+}
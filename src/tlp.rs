@@ -0,0 +1,211 @@
+//! Traffic Light Protocol 2.0 markings and sharing restrictions
+//!
+//! Indicators (`Metadata::tlp`) and collections (e.g. `tlp` of `StixOptions` and
+//! `MispEventOptions`) are marked by `Tlp`. Collections are never marked less strictly than the
+//! indicators in them. Indicators are refused by `IocProvider::share` when the TLP does not
+//! allow the audience of the provider (e.g. RED to public services), and excluded from STIX
+//! bundles / MISP events whose audience the TLP does not allow.
+
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+#[cfg(feature = "net")]
+use crate::ioc::Indicator;
+
+/// STIX extension definition of TLP 2.0 marking definitions
+const TLP_2_0_EXTENSION: &str = "extension-definition--60a3c5c5-0d10-413e-aab3-9e08dde9e88d";
+
+/// TLP 2.0 label (ordered from the least to the most strict)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::tlp::{Audience, Tlp};
+///
+/// let tlp: Tlp = "TLP:AMBER+STRICT".parse().unwrap();
+/// assert_eq!(tlp, Tlp::AmberStrict);
+/// assert_eq!(tlp.to_string(), "TLP:AMBER+STRICT");
+/// assert_eq!(tlp.misp_tag(), "tlp:amber+strict");
+/// // TLP 1.0 WHITE is CLEAR
+/// assert_eq!("tlp:white".parse::<Tlp>().unwrap(), Tlp::Clear);
+///
+/// assert!(Tlp::Clear.allows(Audience::Public));
+/// assert!(Tlp::Green.allows(Audience::Community));
+/// assert!(!Tlp::Green.allows(Audience::Public));
+/// assert!(Tlp::Amber.allows(Audience::Clients));
+/// assert!(!Tlp::AmberStrict.allows(Audience::Clients));
+/// assert!(!Tlp::Red.allows(Audience::Organization));
+/// assert!(Tlp::Red > Tlp::Amber);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Tlp {
+    #[serde(rename = "clear", alias = "white")]
+    Clear,
+    #[serde(rename = "green")]
+    Green,
+    #[serde(rename = "amber")]
+    Amber,
+    #[serde(rename = "amber+strict")]
+    AmberStrict,
+    #[serde(rename = "red")]
+    Red,
+}
+
+/// audience sharing indicators (ordered from the narrowest to the widest)
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Audience {
+    /// named recipients only
+    Recipients,
+    /// members of own organization
+    Organization,
+    /// own organization and its clients
+    Clients,
+    /// community of peer organizations (e.g. ISAC or MISP communities)
+    Community,
+    /// anyone (e.g. public services and open feeds)
+    Public,
+}
+
+/// Errors in sharing marked indicators
+#[derive(Fail, Debug)]
+pub enum TlpError {
+    #[fail(display = "{} does not allow sharing with {:?}", tlp, audience)]
+    NotAllowed { tlp: Tlp, audience: Audience },
+}
+
+impl Tlp {
+    /// all labels from the least to the most strict
+    pub const ALL: [Tlp; 5] = [
+        Tlp::Clear,
+        Tlp::Green,
+        Tlp::Amber,
+        Tlp::AmberStrict,
+        Tlp::Red,
+    ];
+
+    /// lowercase name (e.g. `amber+strict`)
+    pub fn name(self) -> &'static str {
+        match self {
+            Tlp::Clear => "clear",
+            Tlp::Green => "green",
+            Tlp::Amber => "amber",
+            Tlp::AmberStrict => "amber+strict",
+            Tlp::Red => "red",
+        }
+    }
+
+    /// tag in MISP `tlp` taxonomy
+    pub fn misp_tag(self) -> String {
+        format!("tlp:{}", self.name())
+    }
+
+    /// the widest audience allowed
+    pub fn audience(self) -> Audience {
+        match self {
+            Tlp::Clear => Audience::Public,
+            Tlp::Green => Audience::Community,
+            Tlp::Amber => Audience::Clients,
+            Tlp::AmberStrict => Audience::Organization,
+            Tlp::Red => Audience::Recipients,
+        }
+    }
+
+    /// may be shared with the audience?
+    pub fn allows(self, audience: Audience) -> bool {
+        audience <= self.audience()
+    }
+
+    /// error unless the audience is allowed
+    pub fn ensure(self, audience: Audience) -> Result<(), TlpError> {
+        if self.allows(audience) {
+            Ok(())
+        } else {
+            Err(TlpError::NotAllowed {
+                tlp: self,
+                audience,
+            })
+        }
+    }
+
+    /// id of TLP 2.0 marking definition in STIX 2.1
+    pub fn stix_id(self) -> &'static str {
+        match self {
+            Tlp::Clear => "marking-definition--94868c89-83c2-464b-929b-a1a8aa3c8487",
+            Tlp::Green => "marking-definition--bab4a63c-aed9-4cf5-a766-dfca5abac2bb",
+            Tlp::Amber => "marking-definition--55d920b0-5e8b-4f79-9ee9-91f868d9b421",
+            Tlp::AmberStrict => "marking-definition--939a9414-2ddd-4d32-a0cd-375ea402b003",
+            Tlp::Red => "marking-definition--e828b379-4e03-4974-9ac4-e53a884c97c1",
+        }
+    }
+
+    /// TLP of STIX marking definition id (TLP 1.0 ones predefined in STIX 2.1 as well)
+    pub fn from_stix_id(id: &str) -> Option<Self> {
+        Some(match id {
+            "marking-definition--613f2e26-407d-48c7-9eca-b8e91df99dc9" => Tlp::Clear,
+            "marking-definition--34098fce-860f-48ae-8e50-ebd3cc5e41da" => Tlp::Green,
+            "marking-definition--f88d31f6-486f-44da-b317-01333bde0b82" => Tlp::Amber,
+            "marking-definition--5e57c739-391a-4eb3-b6be-7d15ca92d5ed" => Tlp::Red,
+            _ => *Tlp::ALL.iter().find(|x| x.stix_id() == id)?,
+        })
+    }
+
+    /// TLP 2.0 marking definition object in STIX 2.1
+    pub fn stix_definition(self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "marking-definition",
+            "spec_version": "2.1",
+            "id": self.stix_id(),
+            "created": "2022-10-01T00:00:00.000Z",
+            "name": self.to_string(),
+            "extensions": {
+                TLP_2_0_EXTENSION: {
+                    "extension_type": "property-extension",
+                    "tlp_2_0": self.name(),
+                }
+            },
+        })
+    }
+
+    /// the strictest TLP of markings (`None` if not marked at all)
+    pub fn strictest(markings: impl IntoIterator<Item = Option<Tlp>>) -> Option<Tlp> {
+        markings.into_iter().flatten().max()
+    }
+}
+
+/// error unless TLP of every indicator allows the audience (as `IocProvider::share`)
+#[cfg(feature = "net")]
+pub(crate) fn ensure_all<'a>(
+    indicators: impl IntoIterator<Item = &'a Indicator>,
+    audience: Audience,
+) -> Result<(), TlpError> {
+    indicators
+        .into_iter()
+        .filter_map(|x| x.meta.tlp)
+        .try_for_each(|x| x.ensure(audience))
+}
+
+impl fmt::Display for Tlp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TLP:{}", self.name().to_uppercase())
+    }
+}
+
+impl FromStr for Tlp {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        let name = name.strip_prefix("tlp:").unwrap_or(&name);
+        Ok(match name.trim() {
+            "clear" | "white" => Tlp::Clear,
+            "green" => Tlp::Green,
+            "amber" => Tlp::Amber,
+            "amber+strict" => Tlp::AmberStrict,
+            "red" => Tlp::Red,
+            _ => return Err(Error::parse("TLP", s).into()),
+        })
+    }
+}
@@ -0,0 +1,808 @@
+//! live TLS certificates and JARM fingerprints of servers (to pivot on C2 infrastructure)
+//!
+//! Probing is active: servers (which adversaries may watch) see our connections, so
+//! `TlsClient` (feature `net`) refuses to connect until `allow_active_probing` is called explicitly.
+//!
+//! Certificates are read from the handshake in clear, so TLS 1.2 at most is offered when grabbing them
+//! (servers speaking only TLS 1.3 present none). JARM is computed by the 10 probes of the reference
+//! implementation (<https://github.com/salesforce/jarm>).
+
+use failure::Fail;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "net")]
+use std::collections::hash_map::RandomState;
+#[cfg(feature = "net")]
+use std::hash::{BuildHasher, Hasher};
+
+use crate::fingerprint::Fingerprint;
+use crate::graph::Relationship;
+use crate::ioc::{Ioc, IocKind};
+use crate::x509::{hex, Certificate};
+
+/// Errors in TLS probing
+#[derive(Fail, Debug)]
+pub enum TlsError {
+    #[fail(display = "active TLS probing is not allowed (see TlsClient::allow_active_probing)")]
+    NotAllowed,
+    #[fail(display = "server sent alert {}", _0)]
+    Alert(u8),
+    #[fail(display = "server presented no certificate")]
+    NoCertificate,
+    #[fail(display = "malformed TLS handshake: {}", _0)]
+    Malformed(&'static str),
+}
+
+/// JARM of a server which answered no probe
+const NO_JARM: &str = "00000000000000000000000000000000000000000000000000000000000000";
+
+/// cipher suites offered by JARM probes
+#[cfg(feature = "net")]
+const CIPHERS: [u16; 69] = [
+    0x0016, 0x0033, 0x0067, 0xc09e, 0xc0a2, 0x009e, 0x0039, 0x006b, 0xc09f, 0xc0a3, 0x009f, 0x0045,
+    0x00be, 0x0088, 0x00c4, 0x009a, 0xc008, 0xc009, 0xc023, 0xc0ac, 0xc0ae, 0xc02b, 0xc00a, 0xc024,
+    0xc0ad, 0xc0af, 0xc02c, 0xc072, 0xc073, 0xcca9, 0x1302, 0x1301, 0xcc14, 0xc007, 0xc012, 0xc013,
+    0xc027, 0xc02f, 0xc014, 0xc028, 0xc030, 0xc060, 0xc061, 0xc076, 0xc077, 0xcca8, 0x1305, 0x1304,
+    0x1303, 0xcc13, 0xc011, 0x000a, 0x002f, 0x003c, 0xc09c, 0xc0a0, 0x009c, 0x0035, 0x003d, 0xc09d,
+    0xc0a1, 0x009d, 0x0041, 0x00ba, 0x0084, 0x00c0, 0x0007, 0x0004, 0x0005,
+];
+
+/// cipher suites in order of JARM (position of the selected one is in JARM)
+const JARM_CIPHERS: [u16; 69] = [
+    0x0004, 0x0005, 0x0007, 0x000a, 0x0016, 0x002f, 0x0033, 0x0035, 0x0039, 0x003c, 0x003d, 0x0041,
+    0x0045, 0x0067, 0x006b, 0x0084, 0x0088, 0x009a, 0x009c, 0x009d, 0x009e, 0x009f, 0x00ba, 0x00be,
+    0x00c0, 0x00c4, 0xc007, 0xc008, 0xc009, 0xc00a, 0xc011, 0xc012, 0xc013, 0xc014, 0xc023, 0xc024,
+    0xc027, 0xc028, 0xc02b, 0xc02c, 0xc02f, 0xc030, 0xc060, 0xc061, 0xc072, 0xc073, 0xc076, 0xc077,
+    0xc09c, 0xc09d, 0xc09e, 0xc09f, 0xc0a0, 0xc0a1, 0xc0a2, 0xc0a3, 0xc0ac, 0xc0ad, 0xc0ae, 0xc0af,
+    0xcc13, 0xcc14, 0xcca8, 0xcca9, 0x1301, 0x1302, 0x1303, 0x1304, 0x1305,
+];
+
+#[cfg(feature = "net")]
+const ALPNS: [&str; 9] = [
+    "http/0.9", "http/1.0", "http/1.1", "spdy/1", "spdy/2", "spdy/3", "h2", "h2c", "hq",
+];
+
+#[cfg(feature = "net")]
+const RARE_ALPNS: [&str; 7] = [
+    "http/0.9", "http/1.0", "spdy/1", "spdy/2", "spdy/3", "h2c", "hq",
+];
+
+/// order of cipher suites / ALPNs / versions in probes
+#[cfg(feature = "net")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Order {
+    Forward,
+    Reverse,
+    TopHalf,
+    BottomHalf,
+    MiddleOut,
+}
+
+/// versions in supported_versions extension
+#[cfg(feature = "net")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Support {
+    None,
+    Tls12,
+    Tls13,
+}
+
+/// a client hello of JARM
+#[cfg(feature = "net")]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Probe {
+    /// minor version (1.1 -> 2, 1.2 -> 3, 1.3 -> 4)
+    version: u8,
+    tls13_ciphers: bool,
+    cipher_order: Order,
+    grease: bool,
+    rare_alpn: bool,
+    support: Support,
+    extension_order: Order,
+}
+
+#[cfg(feature = "net")]
+const fn probe(
+    version: u8,
+    cipher_order: Order,
+    grease: bool,
+    rare_alpn: bool,
+    support: Support,
+    extension_order: Order,
+) -> Probe {
+    Probe {
+        version,
+        tls13_ciphers: true,
+        cipher_order,
+        grease,
+        rare_alpn,
+        support,
+        extension_order,
+    }
+}
+
+/// probes of JARM in order (the first one is also used to grab certificates)
+#[cfg(feature = "net")]
+pub(crate) const PROBES: [Probe; 10] = [
+    probe(
+        3,
+        Order::Forward,
+        false,
+        false,
+        Support::Tls12,
+        Order::Reverse,
+    ),
+    probe(
+        3,
+        Order::Reverse,
+        false,
+        false,
+        Support::Tls12,
+        Order::Forward,
+    ),
+    probe(
+        3,
+        Order::TopHalf,
+        false,
+        false,
+        Support::None,
+        Order::Forward,
+    ),
+    probe(
+        3,
+        Order::BottomHalf,
+        false,
+        true,
+        Support::None,
+        Order::Forward,
+    ),
+    probe(
+        3,
+        Order::MiddleOut,
+        true,
+        true,
+        Support::None,
+        Order::Reverse,
+    ),
+    probe(
+        2,
+        Order::Forward,
+        false,
+        false,
+        Support::None,
+        Order::Forward,
+    ),
+    probe(
+        4,
+        Order::Forward,
+        false,
+        false,
+        Support::Tls13,
+        Order::Reverse,
+    ),
+    probe(
+        4,
+        Order::Reverse,
+        false,
+        false,
+        Support::Tls13,
+        Order::Forward,
+    ),
+    Probe {
+        tls13_ciphers: false,
+        ..probe(
+            4,
+            Order::Forward,
+            false,
+            false,
+            Support::Tls13,
+            Order::Forward,
+        )
+    },
+    probe(
+        4,
+        Order::MiddleOut,
+        true,
+        false,
+        Support::Tls13,
+        Order::Reverse,
+    ),
+];
+
+#[cfg(feature = "net")]
+fn mung<T: Clone>(items: &[T], order: Order) -> Vec<T> {
+    let n = items.len();
+    match order {
+        Order::Forward => items.to_vec(),
+        Order::Reverse => items.iter().rev().cloned().collect(),
+        Order::BottomHalf => items[n / 2 + n % 2..].to_vec(),
+        Order::TopHalf => {
+            let mut out = vec![];
+            if n % 2 == 1 {
+                out.push(items[n / 2].clone());
+            }
+            out.extend(mung(&mung(items, Order::Reverse), Order::BottomHalf));
+            out
+        }
+        Order::MiddleOut => {
+            let middle = n / 2;
+            let mut out = vec![];
+            if n % 2 == 1 {
+                out.push(items[middle].clone());
+                for i in 1..=middle {
+                    out.push(items[middle + i].clone());
+                    out.push(items[middle - i].clone());
+                }
+            } else {
+                for i in 1..=middle {
+                    out.push(items[middle - 1 + i].clone());
+                    out.push(items[middle - i].clone());
+                }
+            }
+            out
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+fn random_bytes(n: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    while bytes.len() < n {
+        bytes.extend(&RandomState::new().build_hasher().finish().to_be_bytes());
+    }
+    bytes.truncate(n);
+    bytes
+}
+
+/// random GREASE value (RFC 8701)
+#[cfg(feature = "net")]
+fn grease() -> [u8; 2] {
+    let x = random_bytes(1)[0] & 0xf0 | 0x0a;
+    [x, x]
+}
+
+#[cfg(feature = "net")]
+fn u16_bytes(n: usize) -> [u8; 2] {
+    (n as u16).to_be_bytes()
+}
+
+#[cfg(feature = "net")]
+fn extensions(host: &str, probe: &Probe) -> Vec<u8> {
+    let mut ext = vec![];
+    if probe.grease {
+        ext.extend(&grease());
+        ext.extend(&[0, 0]);
+    }
+
+    // server_name
+    ext.extend(&[0, 0]);
+    ext.extend(&u16_bytes(host.len() + 5));
+    ext.extend(&u16_bytes(host.len() + 3));
+    ext.push(0);
+    ext.extend(&u16_bytes(host.len()));
+    ext.extend(host.as_bytes());
+
+    // extended_master_secret, max_fragment_length, renegotiation_info, supported_groups,
+    // ec_point_formats and session_ticket
+    ext.extend(&[0x00, 0x17, 0x00, 0x00]);
+    ext.extend(&[0x00, 0x01, 0x00, 0x01, 0x01]);
+    ext.extend(&[0xff, 0x01, 0x00, 0x01, 0x00]);
+    ext.extend(&[
+        0x00, 0x0a, 0x00, 0x0a, 0x00, 0x08, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18, 0x00, 0x19,
+    ]);
+    ext.extend(&[0x00, 0x0b, 0x00, 0x02, 0x01, 0x00]);
+    ext.extend(&[0x00, 0x23, 0x00, 0x00]);
+
+    // application_layer_protocol_negotiation
+    let alpns: &[&str] = if probe.rare_alpn { &RARE_ALPNS } else { &ALPNS };
+    let mut list = vec![];
+    for alpn in mung(alpns, probe.extension_order) {
+        list.push(alpn.len() as u8);
+        list.extend(alpn.as_bytes());
+    }
+    ext.extend(&[0x00, 0x10]);
+    ext.extend(&u16_bytes(list.len() + 2));
+    ext.extend(&u16_bytes(list.len()));
+    ext.extend(list);
+
+    // signature_algorithms
+    ext.extend(&[
+        0x00, 0x0d, 0x00, 0x14, 0x00, 0x12, 0x04, 0x03, 0x08, 0x04, 0x04, 0x01, 0x05, 0x03, 0x08,
+        0x05, 0x05, 0x01, 0x08, 0x06, 0x06, 0x01, 0x02, 0x01,
+    ]);
+
+    // key_share (x25519)
+    let mut share = vec![];
+    if probe.grease {
+        share.extend(&grease());
+        share.extend(&[0, 1, 0]);
+    }
+    share.extend(&[0x00, 0x1d, 0x00, 0x20]);
+    share.extend(random_bytes(32));
+    ext.extend(&[0x00, 0x33]);
+    ext.extend(&u16_bytes(share.len() + 2));
+    ext.extend(&u16_bytes(share.len()));
+    ext.extend(share);
+
+    // psk_key_exchange_modes
+    ext.extend(&[0x00, 0x2d, 0x00, 0x02, 0x01, 0x01]);
+
+    // supported_versions
+    let versions: &[u16] = match probe.support {
+        Support::None => &[],
+        Support::Tls12 => &[0x0301, 0x0302, 0x0303],
+        Support::Tls13 => &[0x0301, 0x0302, 0x0303, 0x0304],
+    };
+    if !versions.is_empty() {
+        let mut list: Vec<u8> = vec![];
+        if probe.grease {
+            list.extend(&grease());
+        }
+        for v in mung(versions, probe.extension_order) {
+            list.extend(&v.to_be_bytes());
+        }
+        ext.extend(&[0x00, 0x2b]);
+        ext.extend(&u16_bytes(list.len() + 1));
+        ext.push(list.len() as u8);
+        ext.extend(list);
+    }
+
+    let mut out = u16_bytes(ext.len()).to_vec();
+    out.extend(ext);
+    out
+}
+
+/// record of client hello of the probe
+#[cfg(feature = "net")]
+pub(crate) fn client_hello(host: &str, probe: &Probe) -> Vec<u8> {
+    // TLS 1.3 hellos are sent as TLS 1.2 in TLS 1.0 records
+    let (record_version, hello_version) = match probe.version {
+        4 => (1, 3),
+        x => (x, x),
+    };
+    let mut hello = vec![3, hello_version];
+    hello.extend(random_bytes(32));
+    hello.push(32);
+    hello.extend(random_bytes(32));
+
+    let mut ciphers: Vec<u16> = CIPHERS
+        .iter()
+        .copied()
+        .filter(|x| probe.tls13_ciphers || x >> 8 != 0x13)
+        .collect();
+    ciphers = mung(&ciphers, probe.cipher_order);
+    if probe.grease {
+        ciphers.insert(0, u16::from_be_bytes(grease()));
+    }
+    hello.extend(&u16_bytes(ciphers.len() * 2));
+    for c in ciphers {
+        hello.extend(&c.to_be_bytes());
+    }
+    // null compression only
+    hello.extend(&[1, 0]);
+    hello.extend(extensions(host, probe));
+
+    let mut handshake = vec![1];
+    handshake.extend(&(hello.len() as u32).to_be_bytes()[1..]);
+    handshake.extend(hello);
+    let mut record = vec![0x16, 3, record_version];
+    record.extend(&u16_bytes(handshake.len()));
+    record.extend(handshake);
+    record
+}
+
+/// `alpn|extension-types` of server hello
+fn extension_info(data: &[u8], counter: usize, server_hello_length: usize) -> Option<String> {
+    if *data.get(counter + 47)? == 11
+        || data.get(counter + 50..counter + 53) == Some(&[0x0e, 0xac, 0x0b])
+        || data.get(82..85) == Some(&[0x0f, 0xf0, 0x0b])
+        || counter + 42 >= server_hello_length
+    {
+        return None;
+    }
+    let field = |i: usize| -> Option<usize> {
+        Some(usize::from(u16::from_be_bytes([
+            *data.get(i)?,
+            *data.get(i + 1)?,
+        ])))
+    };
+    let mut count = counter + 49;
+    let maximum = field(counter + 47)? + count - 1;
+    let mut extensions: Vec<(&[u8], &[u8])> = vec![];
+    while count < maximum {
+        let ext_type = data.get(count..count + 2)?;
+        let len = field(count + 2)?;
+        let start = (count + 4).min(data.len());
+        let end = (count + 4 + len).min(data.len());
+        extensions.push((ext_type, &data[start..end]));
+        count += 4 + len;
+    }
+    let alpn = extensions
+        .iter()
+        .find(|(t, _)| *t == [0x00, 0x10])
+        .map(|(_, v)| String::from_utf8_lossy(v.get(3..).unwrap_or_default()).into_owned())
+        .unwrap_or_default();
+    let types: Vec<String> = extensions.iter().map(|(t, _)| hex(t, "")).collect();
+    Some(format!("{}|{}", alpn, types.join("-")))
+}
+
+/// JARM component (`cipher|version|alpn|extensions`) of the response to a probe
+/// (`|||` unless server hello, e.g. alert)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::tls::{jarm_component, jarm_hash};
+///
+/// // server hello of TLS 1.2 selecting TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256 and ALPN `h2`
+/// let mut hello = vec![0x16, 3, 3, 0, 58, 2, 0, 0, 54, 3, 3];
+/// hello.extend(&[0; 32]);
+/// hello.extend(&[0, 0xc0, 0x2f, 0, 0, 14]);
+/// hello.extend(&[0xff, 0x01, 0, 1, 0, 0x00, 0x10, 0, 5, 0, 3, 2, b'h', b'2']);
+///
+/// let component = jarm_component(&hello);
+/// assert_eq!(component, "c02f|0303|h2|ff01-0010");
+/// assert_eq!(jarm_component(&[0x15, 3, 3, 0, 2, 2, 40]), "|||");
+///
+/// let raw = [component.as_str(); 10].join(",");
+/// assert_eq!(jarm_hash(&raw), "29d29d29d29d29d29d29d29d29d29dba2508893bc0298318f0aa1ba91aaf03");
+/// ```
+pub fn jarm_component(data: &[u8]) -> String {
+    let component = || -> Option<String> {
+        if *data.first()? != 22 || *data.get(5)? != 2 {
+            return None;
+        }
+        let server_hello_length = usize::from(u16::from_be_bytes([data[3], data[4]]));
+        let counter = usize::from(*data.get(43)?);
+        let cipher = data.get(counter + 44..counter + 46)?;
+        let version = data.get(9..11)?;
+        let extensions =
+            extension_info(data, counter, server_hello_length).unwrap_or_else(|| "|".to_owned());
+        Some(format!(
+            "{}|{}|{}",
+            hex(cipher, ""),
+            hex(version, ""),
+            extensions
+        ))
+    };
+    component().unwrap_or_else(|| "|||".to_owned())
+}
+
+/// JARM of components of the 10 probes joined by `,`
+///
+/// # Example
+///
+/// ```
+/// use iocutil::tls::jarm_hash;
+///
+/// assert_eq!(jarm_hash(&["|||"; 10].join(",")), "0".repeat(62));
+/// ```
+pub fn jarm_hash(raw: &str) -> String {
+    if raw.split(',').all(|x| x == "|||") {
+        return NO_JARM.to_owned();
+    }
+    let mut fuzzy = String::new();
+    let mut alpns_and_extensions = String::new();
+    for handshake in raw.split(',') {
+        let components: Vec<&str> = handshake.split('|').collect();
+        let part = |i: usize| components.get(i).copied().unwrap_or_default();
+
+        fuzzy.push_str(&if part(0).is_empty() {
+            "00".to_owned()
+        } else {
+            let position = JARM_CIPHERS
+                .iter()
+                .position(|c| format!("{:04x}", c) == part(0))
+                .unwrap_or(JARM_CIPHERS.len());
+            format!("{:02x}", position + 1)
+        });
+        fuzzy.push(
+            part(1)
+                .chars()
+                .nth(3)
+                .and_then(|x| x.to_digit(10))
+                .and_then(|x| "abcdef".chars().nth(x as usize))
+                .unwrap_or('0'),
+        );
+        alpns_and_extensions.push_str(part(2));
+        alpns_and_extensions.push_str(part(3));
+    }
+    let digest = format!("{:x}", Sha256::digest(alpns_and_extensions.as_bytes()));
+    fuzzy.push_str(&digest[..32]);
+    fuzzy
+}
+
+/// certificates in the Certificate message of TLS 1.2 (leaf first)
+#[cfg(feature = "net")]
+fn certificate_list(body: &[u8]) -> Result<Vec<Certificate>, TlsError> {
+    let len = |b: &[u8]| usize::from(b[0]) << 16 | usize::from(b[1]) << 8 | usize::from(b[2]);
+    let list = body
+        .get(3..)
+        .filter(|x| body.len() >= 3 && x.len() == len(body))
+        .ok_or(TlsError::Malformed("invalid certificate list"))?;
+    let mut certificates = vec![];
+    let mut rest = list;
+    while rest.len() >= 3 {
+        let n = len(rest);
+        let der = rest
+            .get(3..3 + n)
+            .ok_or(TlsError::Malformed("truncated certificate"))?;
+        certificates.extend(Certificate::from_der(der));
+        rest = &rest[3 + n..];
+    }
+    if certificates.is_empty() {
+        return Err(TlsError::NoCertificate);
+    }
+    Ok(certificates)
+}
+
+/// certificates presented in records received so far (`None` until the message is complete)
+#[cfg(feature = "net")]
+pub(crate) fn presented_certificates(records: &[u8]) -> Result<Option<Vec<Certificate>>, TlsError> {
+    let mut handshake = vec![];
+    let mut rest = records;
+    while rest.len() >= 5 {
+        let len = usize::from(u16::from_be_bytes([rest[3], rest[4]]));
+        let fragment = match rest.get(5..5 + len) {
+            Some(x) => x,
+            None => break,
+        };
+        match rest[0] {
+            21 => {
+                return Err(TlsError::Alert(
+                    fragment.get(1).copied().unwrap_or_default(),
+                ))
+            }
+            22 => handshake.extend(fragment),
+            _ => return Err(TlsError::Malformed("unexpected record in handshake")),
+        }
+        rest = &rest[5 + len..];
+    }
+
+    let mut pos = 0;
+    while let Some(header) = handshake.get(pos..pos + 4) {
+        let len =
+            usize::from(header[1]) << 16 | usize::from(header[2]) << 8 | usize::from(header[3]);
+        let body = match handshake.get(pos + 4..pos + 4 + len) {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        match header[0] {
+            // Certificate
+            11 => return certificate_list(body).map(Some),
+            // ServerHelloDone
+            14 => return Err(TlsError::NoCertificate),
+            _ => {}
+        }
+        pos += 4 + len;
+    }
+    Ok(None)
+}
+
+/// certificates and JARM of a server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsProbe {
+    pub host: String,
+    pub port: u16,
+    /// certificates presented by the server (leaf first, empty if none could be read)
+    pub certificates: Vec<Certificate>,
+    pub jarm: Fingerprint,
+}
+
+impl TlsProbe {
+    /// thumbprints of the certificates, and JARM
+    pub fn fingerprints(&self) -> Vec<Fingerprint> {
+        let mut fingerprints: Vec<Fingerprint> =
+            self.certificates.iter().map(|x| x.thumbprint()).collect();
+        fingerprints.push(self.jarm.clone());
+        fingerprints
+    }
+
+    /// domains / addresses in subject alternative names (and common name) of the leaf certificate,
+    /// as the host `shares-certificate-with` them (wildcards are replaced with their parents)
+    pub fn relationships(&self) -> Vec<Relationship> {
+        let source = match Ioc::new(&self.host) {
+            Ok(x) => x,
+            Err(_) => return vec![],
+        };
+        let leaf = match self.certificates.first() {
+            Some(x) => x,
+            None => return vec![],
+        };
+        let mut relationships: Vec<Relationship> = vec![];
+        let names = leaf
+            .subject_alt_names
+            .iter()
+            .map(|x| x.as_str())
+            .chain(leaf.common_name());
+        for name in names {
+            let target = match Ioc::new(name.trim_start_matches("*.")) {
+                Ok(x) if matches!(x.kind(), IocKind::Domain | IocKind::IpAddress) => x,
+                _ => continue,
+            };
+            if target == source || relationships.iter().any(|x| x.target == target) {
+                continue;
+            }
+            relationships.push(Relationship {
+                source: source.clone(),
+                target,
+                relationship_type: "shares-certificate-with".to_owned(),
+            });
+        }
+        relationships
+    }
+}
+
+#[cfg(feature = "net")]
+pub use self::client::TlsClient;
+
+#[cfg(feature = "net")]
+mod client {
+    use std::time::Duration;
+
+    use super::TlsProbe;
+    use crate::fingerprint::Fingerprint;
+    use crate::http::block_on;
+    use crate::ioc::Indicator;
+    use crate::nonblocking;
+    use crate::x509::Certificate;
+    use crate::GenericResult;
+
+    /// client grabbing certificates and JARM of servers (refuses to connect unless `allow_active_probing`)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::tls::TlsClient;
+    /// use iocutil::prelude::*;
+    ///
+    /// let client = TlsClient::new().allow_active_probing();
+    /// let probe = client.probe("evil.example.com").unwrap();
+    /// for c in &probe.certificates {
+    ///     println!("{} {} {:?}", c.sha1, c.subject, c.subject_alt_names);
+    /// }
+    ///
+    /// // thumbprints and JARM to pivot on, and other domains on the certificate
+    /// println!("{:?}", probe.fingerprints());
+    /// println!("{:?}", probe.relationships());
+    /// ```
+    #[derive(Clone, Default)]
+    pub struct TlsClient {
+        inner: nonblocking::TlsClient,
+    }
+
+    impl TlsClient {
+        /// client connecting to port 443 (connections are refused until `allow_active_probing`)
+        pub fn new() -> Self {
+            TlsClient::default()
+        }
+
+        /// allow connections, accepting that servers of adversaries may notice them
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use iocutil::tls::TlsClient;
+        ///
+        /// assert!(TlsClient::new().certificates("evil.example.com").is_err());
+        /// ```
+        pub fn allow_active_probing(mut self) -> Self {
+            self.inner = self.inner.allow_active_probing();
+            self
+        }
+
+        /// port to connect (default 443)
+        pub fn with_port(mut self, port: u16) -> Self {
+            self.inner = self.inner.with_port(port);
+            self
+        }
+
+        /// timeout of each connection including handshake (default 5 seconds)
+        pub fn with_timeout(mut self, timeout: Duration) -> Self {
+            self.inner = self.inner.with_timeout(timeout);
+            self
+        }
+
+        /// certificates presented by the server (leaf first)
+        pub fn certificates(&self, host: impl AsRef<str>) -> GenericResult<Vec<Certificate>> {
+            block_on(self.inner.certificates(host))
+        }
+
+        /// JARM of the server
+        pub fn jarm(&self, host: impl AsRef<str>) -> GenericResult<Fingerprint> {
+            block_on(self.inner.jarm(host))
+        }
+
+        /// certificates and JARM of the server
+        pub fn probe(&self, host: impl AsRef<str>) -> GenericResult<TlsProbe> {
+            block_on(self.inner.probe(host))
+        }
+
+        /// probe the domain or IP address (or host of URL) of indicator (`None` for other kinds)
+        pub fn probe_indicator(&self, indicator: &Indicator) -> GenericResult<Option<TlsProbe>> {
+            block_on(self.inner.probe_indicator(indicator))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use super::*;
+
+    /// self-signed certificate of `evil.example.com` (SANs `*.c2.example.net` and `192.0.2.1`)
+    const CERTIFICATE: &str = "\
+308201c330820168a00302010202147a9c02cfebe153dec959c407349a995f478c1871300a06082a8648ce3d04030230\
+1b3119301706035504030c106576696c2e6578616d706c652e636f6d301e170d3236313031343131353434325a170d33\
+36313031313131353434325a301b3119301706035504030c106576696c2e6578616d706c652e636f6d3059301306072a\
+8648ce3d020106082a8648ce3d03010703420004576c361dd14457a2f3261286101b113f8b05924e2edf1604ed6a569b\
+a81a7426d421695667a01ca6041cf14a53ac272532c6a9ee0f2cf7d538c4b2d2eaa27908a38189308186301d0603551d\
+0e0416041425d3c295215ecf399f07557ce6b869d38075f970301f0603551d2304183016801425d3c295215ecf399f07\
+557ce6b869d38075f970300f0603551d130101ff040530030101ff30330603551d11042c302a82106576696c2e657861\
+6d706c652e636f6d82102a2e63322e6578616d706c652e6e65748704c0000201300a06082a8648ce3d04030203490030\
+46022100fc1bef7619e90ba178ab11eb1ad9e87330117c36a39fd4bc5ee4dbe70def2974022100e00c81d87ed38f548d\
+33f3156105ebd5de8e91ed807cc27e56b48aed2cc8d508";
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn u24(n: usize) -> Vec<u8> {
+        (n as u32).to_be_bytes()[1..].to_vec()
+    }
+
+    #[test]
+    fn certificates_in_fragmented_records() {
+        let der = unhex(CERTIFICATE);
+        let mut handshake = vec![2, 0, 0, 2, 3, 3];
+        let mut list = u24(der.len());
+        list.extend(&der);
+        let mut body = u24(list.len());
+        body.extend(list);
+        handshake.push(11);
+        handshake.extend(u24(body.len()));
+        handshake.extend(body);
+
+        // fragmented as if max_fragment_length is 512
+        let mut records = vec![];
+        for fragment in handshake.chunks(300) {
+            records.extend(&[22, 3, 3]);
+            records.extend(&u16_bytes(fragment.len()));
+            records.extend(fragment);
+        }
+        assert!(presented_certificates(&records[..320]).unwrap().is_none());
+
+        let certificates = presented_certificates(&records).unwrap().unwrap();
+        let leaf = &certificates[0];
+        assert_eq!(leaf.subject, "/CN=evil.example.com");
+        assert!(leaf.is_self_signed());
+        assert_eq!(
+            leaf.subject_alt_names,
+            vec!["evil.example.com", "*.c2.example.net", "192.0.2.1"]
+        );
+
+        let probe = TlsProbe {
+            host: "evil.example.com".to_owned(),
+            port: 443,
+            certificates,
+            jarm: Fingerprint::Jarm(NO_JARM.to_owned()),
+        };
+        let targets: Vec<String> = probe
+            .relationships()
+            .into_iter()
+            .map(|x| x.target.value())
+            .collect();
+        assert_eq!(targets, vec!["c2.example.net", "192.0.2.1"]);
+        assert_eq!(probe.fingerprints().len(), 2);
+    }
+
+    #[test]
+    fn alert_in_handshake() {
+        let records = [21, 3, 3, 0, 2, 2, 70];
+        assert!(matches!(
+            presented_certificates(&records),
+            Err(TlsError::Alert(70))
+        ));
+    }
+}
@@ -0,0 +1,473 @@
+//! TLSH (trend micro locality sensitive hash) computation and nearest-neighbor search
+//!
+//! Digests are compatible with [TLSH](https://github.com/trendmicro/tlsh) 4.x
+//! (128 buckets and 1 byte checksum, e.g. `T1...`), and `Tlsh::distance` gives the same score as `tlsh -c`.
+
+use failure::Fail;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::GenericResult;
+
+const WINDOW: usize = 5;
+const BUCKETS: usize = 128;
+const CODE_SIZE: usize = BUCKETS / 4;
+const MIN_DATA_LENGTH: u64 = 50;
+
+/// Pearson's sample random table
+const V_TABLE: [u8; 256] = [
+    1, 87, 49, 12, 176, 178, 102, 166, 121, 193, 6, 84, 249, 230, 44, 163, 14, 197, 213, 181, 161,
+    85, 218, 80, 64, 239, 24, 226, 236, 142, 38, 200, 110, 177, 104, 103, 141, 253, 255, 50, 77,
+    101, 81, 18, 45, 96, 31, 222, 25, 107, 190, 70, 86, 237, 240, 34, 72, 242, 20, 214, 244, 227,
+    149, 235, 97, 234, 57, 22, 60, 250, 82, 175, 208, 5, 127, 199, 111, 62, 135, 248, 174, 169,
+    211, 58, 66, 154, 106, 195, 245, 171, 17, 187, 182, 179, 0, 243, 132, 56, 148, 75, 128, 133,
+    158, 100, 130, 126, 91, 13, 153, 246, 216, 219, 119, 68, 223, 78, 83, 88, 201, 99, 122, 11, 92,
+    32, 136, 114, 52, 10, 138, 30, 48, 183, 156, 35, 61, 26, 143, 74, 251, 94, 129, 162, 63, 152,
+    170, 7, 115, 167, 241, 206, 3, 150, 55, 59, 151, 220, 90, 53, 23, 131, 125, 173, 15, 238, 79,
+    95, 89, 16, 105, 137, 225, 224, 217, 160, 37, 123, 118, 73, 2, 157, 46, 116, 9, 145, 134, 228,
+    207, 212, 202, 215, 69, 229, 27, 188, 67, 124, 168, 252, 42, 4, 29, 108, 21, 247, 19, 205, 39,
+    203, 233, 40, 186, 147, 198, 192, 155, 33, 164, 191, 98, 204, 165, 180, 117, 76, 140, 36, 210,
+    172, 41, 54, 159, 8, 185, 232, 113, 196, 231, 47, 146, 120, 51, 65, 28, 144, 254, 221, 93, 189,
+    194, 139, 112, 43, 71, 109, 184, 209,
+];
+
+/// upper bounds of data length for each length value (`l_capturing`)
+const TOP_VALUES: [u32; 170] = [
+    1, 2, 3, 5, 7, 11, 17, 25, 38, 57, 86, 129, 194, 291, 437, 656, 854, 1110, 1443, 1876, 2439,
+    3171, 3475, 3823, 4205, 4626, 5088, 5597, 6157, 6772, 7450, 8195, 9014, 9916, 10907, 11998,
+    13198, 14518, 15970, 17567, 19323, 21256, 23382, 25720, 28292, 31121, 34233, 37656, 41422,
+    45564, 50121, 55133, 60646, 66711, 73382, 80721, 88793, 97672, 107439, 118183, 130002, 143002,
+    157302, 173032, 190335, 209369, 230306, 253337, 278670, 306538, 337191, 370911, 408002, 448802,
+    493682, 543050, 597356, 657091, 722800, 795081, 874589, 962048, 1058252, 1164078, 1280486,
+    1408534, 1549388, 1704327, 1874759, 2062236, 2268459, 2495305, 2744836, 3019320, 3321252,
+    3653374, 4018711, 4420582, 4862641, 5348905, 5883796, 6472176, 7119394, 7831333, 8614467,
+    9475909, 10423501, 11465851, 12612437, 13873681, 15261050, 16787154, 18465870, 20312458,
+    22343706, 24578077, 27035886, 29739474, 32713425, 35984770, 39583245, 43541573, 47895730,
+    52685306, 57953837, 63749221, 70124148, 77136564, 84850228, 93335252, 102668779, 112935659,
+    124229227, 136652151, 150317384, 165349128, 181884040, 200072456, 220079703, 242087671,
+    266296456, 292926096, 322218735, 354440623, 389884688, 428873168, 471760495, 518936559,
+    570830240, 627913311, 690704607, 759775136, 835752671, 919327967, 1011260767, 1112386880,
+    1223623232, 1345985727, 1480584256, 1628642751, 1791507135, 1970657856, 2167723648, 2384496256,
+    2622945920, 2885240448, 3173764736, 3491141248, 3840255616, 4224281216,
+];
+
+/// Errors in computing or parsing TLSH
+#[derive(Fail, Debug)]
+pub enum TlshError {
+    #[fail(display = "data is too short for TLSH: {} bytes", _0)]
+    TooShort(u64),
+
+    #[fail(display = "data has not enough variety for TLSH")]
+    NotEnoughVariety,
+
+    #[fail(display = "invalid TLSH digest: {}", _0)]
+    InvalidDigest(String),
+}
+
+/// Pearson hash of a triplet with salt
+fn b_mapping(salt: u8, i: u8, j: u8, k: u8) -> u8 {
+    let h = V_TABLE[usize::from(salt)];
+    let h = V_TABLE[usize::from(h ^ i)];
+    let h = V_TABLE[usize::from(h ^ j)];
+    V_TABLE[usize::from(h ^ k)]
+}
+
+fn swap_nibbles(x: u8) -> u8 {
+    x.rotate_left(4)
+}
+
+/// circular distance in range
+fn mod_diff(x: u8, y: u8, range: u32) -> u32 {
+    let (x, y) = (u32::from(x), u32::from(y));
+    let d = x.abs_diff(y);
+    d.min(range - d)
+}
+
+/// distance of codes in 2 bits pairs (differences of 3 are weighted 6)
+fn code_distance(x: u8, y: u8) -> u32 {
+    (0..4)
+        .map(|i| {
+            let (a, b) = ((x >> (i * 2)) & 3, (y >> (i * 2)) & 3);
+            match a.abs_diff(b) {
+                3 => 6,
+                d => u32::from(d),
+            }
+        })
+        .sum()
+}
+
+/// length value and quartile ratios of digest
+type Header = (u8, u8, u8);
+
+/// distance of headers
+fn header_distance(x: Header, y: Header) -> u32 {
+    let ldiff = match mod_diff(x.0, y.0, 256) {
+        d if d <= 1 => d,
+        d => d * 12,
+    };
+    let qdiff = |a, b| match mod_diff(a, b, 16) {
+        d if d <= 1 => d,
+        d => (d - 1) * 12,
+    };
+    ldiff + qdiff(x.1, y.1) + qdiff(x.2, y.2)
+}
+
+/// bucket counts over sliding windows
+pub(crate) struct State {
+    buckets: [u32; 256],
+    window: [u8; WINDOW],
+    checksum: u8,
+    len: u64,
+}
+
+impl State {
+    pub(crate) fn new() -> Self {
+        State {
+            buckets: [0; 256],
+            window: [0; WINDOW],
+            checksum: 0,
+            len: 0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for &c in bytes {
+            let i = (self.len % WINDOW as u64) as usize;
+            self.window[i] = c;
+            self.len += 1;
+            if self.len < WINDOW as u64 {
+                continue;
+            }
+
+            // bytes in window (from the newest to the oldest)
+            let w = |n: usize| self.window[(i + WINDOW - n) % WINDOW];
+            let (c0, c1, c2, c3, c4) = (w(0), w(1), w(2), w(3), w(4));
+
+            self.checksum = b_mapping(0, c0, c1, self.checksum);
+            for &(salt, a, b) in &[
+                (2, c1, c2),
+                (3, c1, c3),
+                (5, c2, c3),
+                (7, c2, c4),
+                (11, c1, c4),
+                (13, c3, c4),
+            ] {
+                self.buckets[usize::from(b_mapping(salt, c0, a, b))] += 1;
+            }
+        }
+    }
+
+    pub(crate) fn finish(&self) -> Result<Tlsh, TlshError> {
+        if self.len < MIN_DATA_LENGTH {
+            return Err(TlshError::TooShort(self.len));
+        }
+
+        let buckets = &self.buckets[..BUCKETS];
+        let mut sorted = buckets.to_vec();
+        sorted.sort_unstable();
+        let (q1, q2, q3) = (
+            sorted[BUCKETS / 4 - 1],
+            sorted[BUCKETS / 2 - 1],
+            sorted[BUCKETS - BUCKETS / 4 - 1],
+        );
+        // more than half of buckets must be non-zero
+        if q3 == 0 || buckets.iter().filter(|&&x| x > 0).count() <= BUCKETS / 2 {
+            return Err(TlshError::NotEnoughVariety);
+        }
+
+        let mut code = [0u8; CODE_SIZE];
+        for (c, chunk) in code.iter_mut().zip(buckets.chunks(4)) {
+            for (j, &k) in chunk.iter().enumerate() {
+                let v = if k > q3 {
+                    3
+                } else if k > q2 {
+                    2
+                } else if k > q1 {
+                    1
+                } else {
+                    0
+                };
+                *c |= v << (j * 2);
+            }
+        }
+
+        // same rounding as reference implementation
+        let ratio = |q: u32| ((((u64::from(q) * 100) as f32) / (q3 as f32)) as u32 % 16) as u8;
+        let lvalue = TOP_VALUES
+            .iter()
+            .position(|&t| self.len <= u64::from(t))
+            .unwrap_or(TOP_VALUES.len() - 1) as u8;
+
+        Ok(Tlsh {
+            checksum: self.checksum,
+            lvalue,
+            q1_ratio: ratio(q1),
+            q2_ratio: ratio(q2),
+            code,
+        })
+    }
+}
+
+/// TLSH digest
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tlsh {
+    checksum: u8,
+    lvalue: u8,
+    q1_ratio: u8,
+    q2_ratio: u8,
+    code: [u8; CODE_SIZE],
+}
+
+impl Tlsh {
+    /// TLSH of bytes (at least 50 bytes with some variety are required)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::tlsh::Tlsh;
+    ///
+    /// let t = Tlsh::of_bytes(b"Lorem ipsum dolor sit amet, consectetur adipiscing elit").unwrap();
+    /// assert_eq!(t.to_string(), "T12D900249414E0BD59A46503F3ADA802AE50825242B2590561CF690599112214C051556");
+    ///
+    /// assert!(Tlsh::of_bytes(b"too short").is_err());
+    /// assert!(Tlsh::of_bytes(vec![0u8; 1024]).is_err());
+    /// ```
+    pub fn of_bytes(bytes: impl AsRef<[u8]>) -> GenericResult<Self> {
+        let mut state = State::new();
+        state.update(bytes.as_ref());
+        Ok(state.finish()?)
+    }
+
+    /// TLSH of all content in reader
+    pub fn of_reader(mut reader: impl Read) -> GenericResult<Self> {
+        let mut state = State::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                return Ok(state.finish()?);
+            }
+            state.update(&buf[..n]);
+        }
+    }
+
+    /// TLSH of file content
+    pub fn of_file(path: impl AsRef<Path>) -> GenericResult<Self> {
+        Tlsh::of_reader(std::fs::File::open(path)?)
+    }
+
+    /// length value and quartile ratios
+    fn header(&self) -> Header {
+        (self.lvalue, self.q1_ratio, self.q2_ratio)
+    }
+
+    /// distance of two digests (0 for identical, larger for more different, usually up to about 1000)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::tlsh::Tlsh;
+    ///
+    /// let a: Tlsh = "T12D900249414E0BD59A46503F3ADA802AE50825242B2590561CF690599112214C051556".parse().unwrap();
+    /// let b = Tlsh::of_bytes(
+    ///     "Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat \
+    ///      nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia",
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(a.distance(&a), 0);
+    /// assert_eq!(a.distance(&b), 280);
+    /// ```
+    pub fn distance(&self, other: &Tlsh) -> u32 {
+        let checksum = if self.checksum == other.checksum {
+            0
+        } else {
+            1
+        };
+        header_distance(self.header(), other.header())
+            + checksum
+            + self
+                .code
+                .iter()
+                .zip(other.code.iter())
+                .map(|(&x, &y)| code_distance(x, y))
+                .sum::<u32>()
+    }
+
+    /// distance of two digests up to `limit` (`None` if the distance exceeds it)
+    fn distance_within(&self, other: &Tlsh, header: u32, limit: u32) -> Option<u32> {
+        let checksum = if self.checksum == other.checksum {
+            0
+        } else {
+            1
+        };
+        let mut d = header + checksum;
+        for chunk in self.code.chunks(8).zip(other.code.chunks(8)) {
+            d += chunk
+                .0
+                .iter()
+                .zip(chunk.1.iter())
+                .map(|(&x, &y)| code_distance(x, y))
+                .sum::<u32>();
+            if d > limit {
+                return None;
+            }
+        }
+        Some(d)
+    }
+}
+
+impl std::fmt::Display for Tlsh {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "T1{:02X}{:02X}{:X}{:X}",
+            swap_nibbles(self.checksum),
+            swap_nibbles(self.lvalue),
+            self.q1_ratio,
+            self.q2_ratio
+        )?;
+        for c in self.code.iter().rev() {
+            write!(f, "{:02X}", c)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Tlsh {
+    type Err = failure::Error;
+
+    /// parse digest (with or without `T1` prefix)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TlshError::InvalidDigest(s.to_owned());
+        let hex = s.trim();
+        let hex = if hex.len() == 72 && hex[..2].eq_ignore_ascii_case("t1") {
+            &hex[2..]
+        } else {
+            hex
+        };
+        if hex.len() != 70 || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid().into());
+        }
+
+        let byte = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+        let mut code = [0u8; CODE_SIZE];
+        for (i, c) in code.iter_mut().rev().enumerate() {
+            *c = byte(i + 3);
+        }
+        Ok(Tlsh {
+            checksum: swap_nibbles(byte(0)),
+            lvalue: swap_nibbles(byte(1)),
+            q1_ratio: byte(2) >> 4,
+            q2_ratio: byte(2) & 0x0f,
+            code,
+        })
+    }
+}
+
+/// index of TLSH digests for nearest-neighbor search
+///
+/// Digests are grouped by their headers (length and quartile ratios), so groups too far from the query
+/// are skipped without comparing codes.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::tlsh::{Tlsh, TlshIndex};
+///
+/// let text = |n: usize| -> String { (0..200).map(|i| format!("line {} of sample {}\n", i, n % 3)).collect() };
+///
+/// let mut index = TlshIndex::new();
+/// for n in 0..3 {
+///     index.insert(Tlsh::of_bytes(text(n)).unwrap(), format!("sample{}", n));
+/// }
+///
+/// let query = Tlsh::of_bytes(text(1).replace("line 100 ", "line 999 ")).unwrap();
+/// let (tlsh, name, distance) = index.nearest(&query, 100).unwrap();
+/// assert_eq!(name, "sample1");
+/// assert_eq!(distance, query.distance(tlsh));
+///
+/// assert_eq!(index.within(&query, 1000).len(), 3);
+/// assert!(index.nearest(&query, 0).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TlshIndex<T> {
+    entries: Vec<(Tlsh, T)>,
+    groups: HashMap<Header, Vec<usize>>,
+}
+
+impl<T> Default for TlshIndex<T> {
+    fn default() -> Self {
+        TlshIndex {
+            entries: vec![],
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl<T> TlshIndex<T> {
+    /// empty index
+    pub fn new() -> Self {
+        TlshIndex::default()
+    }
+
+    /// add a digest with associated value (e.g. sha256 of the sample)
+    pub fn insert(&mut self, tlsh: Tlsh, value: T) {
+        self.groups
+            .entry(tlsh.header())
+            .or_default()
+            .push(self.entries.len());
+        self.entries.push((tlsh, value));
+    }
+
+    /// number of digests in the index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// index has no digests or not
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// every digest within `max_distance` from the query (ordered by distance)
+    pub fn within(&self, query: &Tlsh, max_distance: u32) -> Vec<(&Tlsh, &T, u32)> {
+        let mut found = vec![];
+        for (key, ids) in &self.groups {
+            let header = header_distance(query.header(), *key);
+            if header > max_distance {
+                continue;
+            }
+            for &i in ids {
+                let (tlsh, value) = &self.entries[i];
+                if let Some(d) = query.distance_within(tlsh, header, max_distance) {
+                    found.push((tlsh, value, d));
+                }
+            }
+        }
+        found.sort_by_key(|x| x.2);
+        found
+    }
+
+    /// the nearest digest within `max_distance` from the query
+    pub fn nearest(&self, query: &Tlsh, max_distance: u32) -> Option<(&Tlsh, &T, u32)> {
+        let mut best: Option<(&Tlsh, &T, u32)> = None;
+        for (key, ids) in &self.groups {
+            let header = header_distance(query.header(), *key);
+            for &i in ids {
+                // search range shrinks as nearer one is found
+                let limit = best.map(|x| x.2.saturating_sub(1)).unwrap_or(max_distance);
+                if header > limit || best.map(|x| x.2 == 0).unwrap_or(false) {
+                    break;
+                }
+                let (tlsh, value) = &self.entries[i];
+                if let Some(d) = query.distance_within(tlsh, header, limit) {
+                    best = Some((tlsh, value, d));
+                }
+            }
+        }
+        best
+    }
+}
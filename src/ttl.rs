@@ -0,0 +1,130 @@
+//! expiration of indicators (`valid_until` and per-kind TTLs)
+//!
+//! Indicators decay at different rates: IP addresses are reassigned within weeks and domains are
+//! re-registered within months, while a hash identifies the same sample forever.
+//! `TtlPolicy` derives `valid_until` from the last sighting by kind of indicator.
+//!
+//! Expired indicators (`valid_until` in the past) are excluded from exports and uploads (CSV,
+//! STIX bundle, MISP event, Zeek intel, Suricata, KQL, SPL, Sigma, YARA, Elasticsearch queries,
+//! OpenIOC, CEF / LEEF lines, plain-text feed, report, Microsoft Graph and Falcon) unless
+//! `include_expired` of their options is set. JSON Lines and Parquet keep them, as lossless dumps.
+
+use chrono::{DateTime, Duration, Utc};
+use derive_builder::Builder;
+
+use crate::ioc::{Indicator, IocKind};
+
+/// TTLs of indicators by kind (`None`: never expires)
+///
+/// # Example
+///
+/// ```
+/// use chrono::Duration;
+/// use iocutil::prelude::*;
+/// use iocutil::ttl::{TtlPolicy, TtlPolicyBuilder};
+///
+/// let mut ip = Indicator::new(Ioc::new("192.0.2.1").unwrap());
+/// ip.meta.last_seen = Some(day!(2019, 11, 1).unwrap().into());
+/// let mut hash = Indicator::new(Ioc::new("d41d8cd98f00b204e9800998ecf8427e").unwrap());
+/// hash.meta.last_seen = Some(day!(2019, 11, 1).unwrap().into());
+///
+/// let policy = TtlPolicy::default();
+/// assert_eq!(policy.valid_until(&ip), Some(day!(2019, 12, 1).unwrap().into()));
+/// assert_eq!(policy.valid_until(&hash), None);
+///
+/// let policy = TtlPolicyBuilder::default().ip_address(Duration::days(7)).build().unwrap();
+/// policy.apply(&mut ip);
+/// assert_eq!(ip.meta.valid_until, Some(day!(2019, 11, 8).unwrap().into()));
+/// assert!(ip.is_expired());
+/// ```
+#[derive(Builder, Debug, Clone, Eq, PartialEq)]
+pub struct TtlPolicy {
+    #[builder(setter(into), default = "None")]
+    hash: Option<Duration>,
+    #[builder(setter(into), default = "Some(Duration::days(30))")]
+    ip_address: Option<Duration>,
+    #[builder(setter(into), default = "Some(Duration::days(90))")]
+    domain: Option<Duration>,
+    #[builder(setter(into), default = "Some(Duration::days(60))")]
+    url: Option<Duration>,
+    #[builder(setter(into), default = "Some(Duration::days(180))")]
+    email_address: Option<Duration>,
+}
+
+impl Default for TtlPolicy {
+    /// IP addresses 30 days, URLs 60 days, domains 90 days, email addresses 180 days and hashes
+    /// never expire
+    fn default() -> Self {
+        TtlPolicyBuilder::default().build().unwrap()
+    }
+}
+
+impl TtlPolicy {
+    /// TTL of the kind of indicator
+    pub fn ttl(&self, kind: IocKind) -> Option<Duration> {
+        match kind {
+            IocKind::Hash => self.hash,
+            IocKind::IpAddress => self.ip_address,
+            IocKind::Domain => self.domain,
+            IocKind::Url => self.url,
+            IocKind::EmailAddress => self.email_address,
+        }
+    }
+
+    /// time of expiration (`valid_until` in metadata, or TTL after last sighting)
+    pub fn valid_until(&self, indicator: &Indicator) -> Option<DateTime<Utc>> {
+        let meta = &indicator.meta;
+        meta.valid_until.or_else(|| {
+            let seen = meta.last_seen.or(meta.first_seen)?;
+            Some(seen + self.ttl(indicator.ioc.kind())?)
+        })
+    }
+
+    /// fill `valid_until` of the indicator (kept if already set)
+    pub fn apply(&self, indicator: &mut Indicator) {
+        indicator.meta.valid_until = self.valid_until(indicator);
+    }
+
+    /// the indicator is expired at the time?
+    pub fn is_expired(&self, indicator: &Indicator, now: DateTime<Utc>) -> bool {
+        self.valid_until(indicator).is_some_and(|x| x <= now)
+    }
+
+    /// remove indicators expired at the time from collection (removed ones are returned)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::prelude::*;
+    /// use iocutil::ttl::TtlPolicy;
+    ///
+    /// let seen = |value: &str| {
+    ///     let mut i = Indicator::new(Ioc::new(value).unwrap());
+    ///     i.meta.last_seen = Some(day!(2019, 11, 1).unwrap().into());
+    ///     i
+    /// };
+    /// let mut indicators = vec![seen("192.0.2.1"), seen("example.com"), seen("d41d8cd98f00b204e9800998ecf8427e")];
+    ///
+    /// let expired = TtlPolicy::default().expire(&mut indicators, day!(2020, 1, 1).unwrap().into());
+    /// assert_eq!(expired.len(), 1);
+    /// assert_eq!(expired[0].ioc, Ioc::new("192.0.2.1").unwrap());
+    /// assert_eq!(indicators.len(), 2);
+    /// ```
+    pub fn expire(&self, indicators: &mut Vec<Indicator>, now: DateTime<Utc>) -> Vec<Indicator> {
+        let (expired, alive) = indicators.drain(..).partition(|x| self.is_expired(x, now));
+        *indicators = alive;
+        expired
+    }
+}
+
+/// indicators not expired (by `valid_until` in metadata) unless `include_expired`
+pub(crate) fn unexpired(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    include_expired: bool,
+) -> impl Iterator<Item = Indicator> {
+    let now = Utc::now();
+    indicators
+        .into_iter()
+        .map(|x| x.into())
+        .filter(move |x: &Indicator| include_expired || !x.is_expired_at(now))
+}
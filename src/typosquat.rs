@@ -0,0 +1,342 @@
+//! typosquatting and homoglyph detection of domains targeting protected brands
+//!
+//! Candidates are compared to each protected domain by the label of registrable domain
+//! (e.g. `paypal` of `www.paypal.com`), and scored from 0 to 1 by the technique found:
+//!
+//! - `Homoglyph`: confusable characters (e.g. `pаypal` with Cyrillic `а`, `paypa1`, `rnicrosoft`)
+//! - `OtherSuffix`: the same label under another public suffix (e.g. `paypal.co`)
+//! - `KeyboardTypo`: a key replaced by its neighbour on QWERTY keyboards (e.g. `paypak`)
+//! - `EditDistance`: insertions, deletions, replacements and transpositions (e.g. `papyal`)
+//! - `AddedKeyword`: the label with added words (e.g. `paypal-login`, `securepaypal`)
+//! - `Subdomain`: the label as a subdomain of another domain (e.g. `paypal.com.evil.net`)
+//!
+//! Subdomains of the protected domains themselves are never matched.
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::DomainName;
+use crate::ioc::{host_of_url, Indicator, Ioc};
+use crate::psl;
+use crate::GenericResult;
+
+/// tag of indicators matched by `TyposquatDetector::flag`
+pub const TYPOSQUAT_TAG: &str = "typosquat";
+
+/// words commonly added to brands by phishing domains
+pub const KEYWORDS: &[&str] = &[
+    "account", "app", "auth", "billing", "cloud", "confirm", "customer", "help", "id", "login",
+    "mail", "my", "online", "pay", "payment", "portal", "recovery", "secure", "security",
+    "service", "signin", "support", "team", "update", "verify", "wallet", "web", "www",
+];
+
+/// rows of QWERTY keyboards
+const KEYBOARD: [&str; 4] = ["1234567890-", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// technique of typosquatting
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Technique {
+    Homoglyph,
+    OtherSuffix,
+    KeyboardTypo,
+    EditDistance,
+    AddedKeyword,
+    Subdomain,
+}
+
+/// candidate domain matched with a protected domain
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TyposquatMatch {
+    /// candidate domain (ACE form)
+    pub domain: String,
+    /// protected domain targeted
+    pub protected: String,
+    pub technique: Technique,
+    /// from 0 to 1 (1 is identical in appearance)
+    pub score: f64,
+}
+
+struct Protected {
+    domain: String,
+    label: String,
+    skeleton: String,
+}
+
+/// detector of domains targeting protected domains
+///
+/// # Example
+///
+/// ```
+/// use iocutil::typosquat::{Technique, TyposquatDetector};
+///
+/// let detector = TyposquatDetector::new(&["paypal.com", "example-bank.co.uk"]).unwrap();
+///
+/// // "pаypal" with Cyrillic "а"
+/// let m = detector.check("xn--pypal-4ve.com").unwrap();
+/// assert_eq!(m.protected, "paypal.com");
+/// assert_eq!(m.technique, Technique::Homoglyph);
+///
+/// assert_eq!(detector.check("paypa1.com").unwrap().technique, Technique::Homoglyph);
+/// assert_eq!(detector.check("paypal.net").unwrap().technique, Technique::OtherSuffix);
+/// assert_eq!(detector.check("paypak.com").unwrap().technique, Technique::KeyboardTypo);
+/// assert_eq!(detector.check("papyal.com").unwrap().technique, Technique::EditDistance);
+/// assert_eq!(detector.check("paypal-secure-login.com").unwrap().technique, Technique::AddedKeyword);
+/// assert_eq!(detector.check("example-bank.co.uk.evil.net").unwrap().technique, Technique::Subdomain);
+/// assert_eq!(detector.check("examplebank.co.uk").unwrap().protected, "example-bank.co.uk");
+///
+/// assert!(detector.check("www.paypal.com").is_none());
+/// assert!(detector.check("wikipedia.org").is_none());
+/// ```
+pub struct TyposquatDetector {
+    protected: Vec<Protected>,
+    threshold: f64,
+}
+
+/// label and public suffix of registrable domain, in unicode form
+fn split_registrable(domain: &DomainName) -> Option<(String, String)> {
+    let registrable = psl::registrable_domain(domain.ascii())?;
+    let unicode = idna::domain_to_unicode(&registrable).0;
+    let (label, suffix) = unicode.split_once('.')?;
+    Some((label.to_owned(), suffix.to_owned()))
+}
+
+/// ASCII character which the character is confusable with
+fn confusable(c: char) -> char {
+    match c {
+        'а' | 'α' | 'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ɑ' => 'a',
+        'Ь' | 'ь' | 'β' => 'b',
+        'с' | 'ϲ' | 'ç' => 'c',
+        'ԁ' | 'ɗ' | 'đ' => 'd',
+        'е' | 'ε' | 'è' | 'é' | 'ê' | 'ë' | 'ё' | '3' => 'e',
+        'ɡ' | 'ğ' => 'g',
+        'һ' | 'ħ' => 'h',
+        'і' | 'ι' | 'ì' | 'í' | 'î' | 'ï' | 'ı' => 'i',
+        'ј' | 'ʝ' => 'j',
+        'κ' | 'к' => 'k',
+        'ӏ' | 'ł' | '1' | 'ĺ' | 'ľ' => 'l',
+        'ո' | 'ñ' | 'ń' | 'η' => 'n',
+        'о' | 'ο' | 'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | '0' => 'o',
+        'р' | 'ρ' => 'p',
+        'ԛ' => 'q',
+        'ѕ' | 'ś' | 'š' | '5' => 's',
+        'τ' | 'ť' => 't',
+        'υ' | 'ù' | 'ú' | 'û' | 'ü' | 'μ' => 'u',
+        'ν' | 'ѵ' => 'v',
+        'ԝ' | 'ω' | 'ẃ' => 'w',
+        'х' | 'χ' => 'x',
+        'у' | 'ý' | 'ÿ' | 'γ' => 'y',
+        'ż' | 'ź' | 'ž' => 'z',
+        c => c,
+    }
+}
+
+/// label as compared for homoglyphs (confusable characters and sequences replaced)
+fn skeleton(label: &str) -> String {
+    let s: String = label.chars().map(confusable).collect();
+    s.replace("rn", "m").replace("vv", "w").replace("cl", "d")
+}
+
+fn key_position(c: char) -> Option<(i32, i32)> {
+    KEYBOARD
+        .iter()
+        .enumerate()
+        .find_map(|(row, keys)| keys.find(c).map(|col| (row as i32, col as i32)))
+}
+
+/// neighbouring keys on QWERTY keyboards (rows are staggered to the right)
+fn is_adjacent_key(a: char, b: char) -> bool {
+    match (key_position(a), key_position(b)) {
+        (Some((ra, ca)), Some((rb, cb))) => match rb - ra {
+            0 => (ca - cb).abs() == 1,
+            1 => cb == ca || cb == ca - 1,
+            -1 => cb == ca || cb == ca + 1,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// optimal string alignment distance (edits and transpositions of adjacent characters)
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut x = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                x = x.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = x;
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// the label is the protected label with added words (score higher for known keywords)
+fn keyword_score(label: &str, protected: &Protected) -> Option<f64> {
+    let squashed = skeleton(label);
+    let pos = squashed.find(&protected.skeleton)?;
+    let rest = [
+        &squashed[..pos],
+        &squashed[pos + protected.skeleton.len()..],
+    ];
+    let words: Vec<&str> = rest
+        .iter()
+        .flat_map(|x| x.split('-'))
+        .filter(|x| !x.is_empty())
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    let known = words
+        .iter()
+        .all(|x| KEYWORDS.iter().any(|k| x.starts_with(k) || x.ends_with(k)));
+    Some(if known { 0.9 } else { 0.7 })
+}
+
+impl TyposquatDetector {
+    /// detector of the protected domains (matches scored 0.75 or higher by default)
+    pub fn new(protected: &[impl AsRef<str>]) -> GenericResult<Self> {
+        let mut list = vec![];
+        for x in protected {
+            let domain = DomainName::new(x)?;
+            let (label, _) = split_registrable(&domain)
+                .ok_or_else(|| crate::error::Error::parse("domain", x.as_ref()))?;
+            list.push(Protected {
+                domain: psl::registrable_domain(domain.ascii())
+                    .unwrap_or_else(|| domain.ascii().to_owned()),
+                skeleton: skeleton(&label),
+                label,
+            });
+        }
+        Ok(TyposquatDetector {
+            protected: list,
+            threshold: 0.75,
+        })
+    }
+
+    /// minimum score of matches (default 0.75)
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    fn score(&self, domain: &DomainName, protected: &Protected) -> Option<(Technique, f64)> {
+        let (label, suffix) = split_registrable(domain)?;
+        let registrable = format!("{}.{}", label, suffix);
+        if idna::domain_to_unicode(&protected.domain).0 == registrable {
+            return None;
+        }
+
+        let unicode = domain.unicode();
+        let subdomains = unicode[..unicode.len() - registrable.len()].trim_end_matches('.');
+        let mut best: Vec<(Technique, f64)> = vec![];
+        if subdomains.split('.').any(|x| x == protected.label) {
+            best.push((Technique::Subdomain, 0.8));
+        }
+        if label == protected.label {
+            best.push((Technique::OtherSuffix, 0.9));
+        } else if skeleton(&label) == protected.skeleton {
+            best.push((Technique::Homoglyph, 1.0));
+        }
+
+        let (a, b): (Vec<char>, Vec<char>) =
+            (label.chars().collect(), protected.label.chars().collect());
+        // edits of short labels match too many domains
+        if b.len() >= 4 {
+            match distance(&a, &b) {
+                0 => {}
+                1 if a.len() == b.len()
+                    && a.iter()
+                        .zip(&b)
+                        .find(|(x, y)| x != y)
+                        .is_some_and(|(x, y)| is_adjacent_key(*x, *y)) =>
+                {
+                    best.push((Technique::KeyboardTypo, 0.9))
+                }
+                d => best.push((
+                    Technique::EditDistance,
+                    1.0 - d as f64 / a.len().max(b.len()) as f64,
+                )),
+            }
+        }
+        if let Some(score) = keyword_score(&label, protected) {
+            best.push((Technique::AddedKeyword, score));
+        }
+        best.into_iter()
+            .fold(None, |acc: Option<(Technique, f64)>, x| match acc {
+                Some(y) if y.1 >= x.1 => Some(y),
+                _ => Some(x),
+            })
+    }
+
+    /// best match of the domain (`None` if scored lower than the threshold)
+    pub fn check(&self, domain: impl AsRef<str>) -> Option<TyposquatMatch> {
+        let domain = DomainName::new(domain).ok()?;
+        let mut best: Option<TyposquatMatch> = None;
+        for protected in &self.protected {
+            if let Some((technique, score)) = self.score(&domain, protected) {
+                if score >= self.threshold && !best.as_ref().is_some_and(|x| x.score >= score) {
+                    best = Some(TyposquatMatch {
+                        domain: domain.ascii().to_owned(),
+                        protected: protected.domain.clone(),
+                        technique,
+                        score,
+                    });
+                }
+            }
+        }
+        best
+    }
+
+    /// best match of domain or host of URL of the indicator
+    pub fn check_indicator(&self, indicator: &Indicator) -> Option<TyposquatMatch> {
+        match &indicator.ioc {
+            Ioc::Domain(x) => self.check(x),
+            Ioc::Url(x) => match host_of_url(x)? {
+                Ioc::Domain(x) => self.check(x),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// tag matched indicators by `TYPOSQUAT_TAG` and return their matches
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::ioc::{Indicator, Ioc};
+    /// use iocutil::typosquat::{TyposquatDetector, TYPOSQUAT_TAG};
+    ///
+    /// let mut indicators = vec![
+    ///     Indicator::new(Ioc::new("https://micros0ft-support.com/login").unwrap()),
+    ///     Indicator::new(Ioc::new("github.com").unwrap()),
+    /// ];
+    /// let detector = TyposquatDetector::new(&["microsoft.com"]).unwrap();
+    /// let matches = detector.flag(&mut indicators);
+    /// assert_eq!(matches.len(), 1);
+    /// assert_eq!(matches[0].domain, "micros0ft-support.com");
+    /// assert_eq!(indicators[0].meta.tags, vec![TYPOSQUAT_TAG]);
+    /// assert!(indicators[1].meta.tags.is_empty());
+    /// ```
+    pub fn flag(&self, indicators: &mut [Indicator]) -> Vec<TyposquatMatch> {
+        let mut matches = vec![];
+        for indicator in indicators.iter_mut() {
+            if let Some(m) = self.check_indicator(indicator) {
+                let tags = &mut indicator.meta.tags;
+                if !tags.iter().any(|x| x == TYPOSQUAT_TAG) {
+                    tags.push(TYPOSQUAT_TAG.to_owned());
+                }
+                matches.push(m);
+            }
+        }
+        matches
+    }
+}
@@ -0,0 +1,267 @@
+//! expansion of shortened URLs (bit.ly, t.co and so on) by following their redirect chains
+//!
+//! Expansion is active: the servers of hops (which adversaries may watch) see our requests, so
+//! `UrlExpander` (feature `net`) refuses to send them until `allow_active_probing` is called
+//! explicitly. Bodies are never read, and `only_shorteners` stops before hops outside known
+//! shortening services.
+
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+
+use crate::graph::Relationship;
+use crate::ioc::{host_of_url, Ioc};
+
+/// Errors in expanding URLs
+#[derive(Fail, Debug)]
+pub enum UnshortenError {
+    #[fail(
+        display = "active URL expansion is not allowed (see UrlExpander::allow_active_probing)"
+    )]
+    NotAllowed,
+}
+
+/// domains of URL shortening services
+pub const SHORTENERS: &[&str] = &[
+    "bit.ly",
+    "bitly.com",
+    "bl.ink",
+    "buff.ly",
+    "cutt.ly",
+    "dlvr.it",
+    "goo.gl",
+    "is.gd",
+    "j.mp",
+    "lnkd.in",
+    "ow.ly",
+    "qrco.de",
+    "rb.gy",
+    "rebrand.ly",
+    "s.id",
+    "shorturl.at",
+    "t.co",
+    "t.ly",
+    "tiny.cc",
+    "tinyurl.com",
+    "trib.al",
+    "v.gd",
+];
+
+/// the URL is on a URL shortening service in `SHORTENERS` (or its subdomain)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::unshorten::is_shortened;
+///
+/// assert!(is_shortened("https://bit.ly/3xYzAbC"));
+/// assert!(is_shortened("http://www.tinyurl.com/abc"));
+/// assert!(!is_shortened("https://evil.example.com/bit.ly"));
+/// ```
+pub fn is_shortened(url: impl AsRef<str>) -> bool {
+    match host_of_url(url.as_ref()) {
+        Some(Ioc::Domain(host)) => SHORTENERS
+            .iter()
+            .any(|x| host == *x || host.ends_with(&format!(".{}", x))),
+        _ => false,
+    }
+}
+
+/// why expansion stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainEnd {
+    /// the last URL responded without redirect
+    Resolved,
+    /// the last URL is outside known shorteners (not requested, see `only_shorteners`)
+    LeftShorteners,
+    /// redirects reached the maximum hops
+    MaxHops,
+    /// the total time cap elapsed
+    TimedOut,
+    /// the last URL redirected to a URL already in the chain
+    Loop,
+    /// the last URL could not be requested (e.g. taken down)
+    Unreachable,
+}
+
+/// redirect chain of a URL
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedirectChain {
+    /// URL expanded
+    pub url: String,
+    /// URLs redirected to in order
+    pub hops: Vec<String>,
+    /// status of responses in order (the last URL is not requested unless `Resolved`)
+    pub statuses: Vec<u16>,
+    pub end: ChainEnd,
+}
+
+impl RedirectChain {
+    /// the last URL of the chain
+    pub fn final_url(&self) -> &str {
+        self.hops.last().unwrap_or(&self.url)
+    }
+
+    /// the URL and hops in order
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str()).chain(self.hops.iter().map(|x| x.as_str()))
+    }
+
+    /// every hop as URL `redirects-to` the next one (URLs which are not valid indicators are skipped)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::unshorten::{ChainEnd, RedirectChain};
+    ///
+    /// let chain = RedirectChain {
+    ///     url: "https://bit.ly/3xYzAbC".to_owned(),
+    ///     hops: vec![
+    ///         "https://tinyurl.com/abc".to_owned(),
+    ///         "https://evil.example.com/payload".to_owned(),
+    ///     ],
+    ///     statuses: vec![301, 302],
+    ///     end: ChainEnd::LeftShorteners,
+    /// };
+    /// assert_eq!(chain.final_url(), "https://evil.example.com/payload");
+    ///
+    /// let r = chain.relationships();
+    /// assert_eq!(r.len(), 2);
+    /// assert_eq!(r[1].target.value(), "https://evil.example.com/payload");
+    /// assert_eq!(r[1].relationship_type, "redirects-to");
+    /// ```
+    pub fn relationships(&self) -> Vec<Relationship> {
+        let urls: Vec<Option<Ioc>> = self.urls().map(|x| Ioc::new(x).ok()).collect();
+        urls.windows(2)
+            .filter_map(|w| match w {
+                [Some(source), Some(target)] => Some(Relationship {
+                    source: source.clone(),
+                    target: target.clone(),
+                    relationship_type: "redirects-to".to_owned(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "net")]
+pub use self::client::UrlExpander;
+
+#[cfg(feature = "net")]
+mod client {
+    use std::time::Duration;
+
+    use super::RedirectChain;
+    use crate::http::{block_on, ProxyConfig};
+    use crate::nonblocking;
+    use crate::GenericResult;
+
+    /// expander of shortened URLs (refuses to send requests unless `allow_active_probing`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::unshorten::{ChainEnd, UrlExpander};
+    /// use iocutil::vcr::{clear_cassette, set_cassette, Cassette};
+    ///
+    /// let path = std::env::temp_dir().join("iocutil-unshorten-doctest.json");
+    /// std::fs::write(
+    ///     &path,
+    ///     r#"{"interactions": [{
+    ///         "provider": "unshorten",
+    ///         "method": "GET",
+    ///         "url": "https://bit.ly/3xYzAbC",
+    ///         "status": 301,
+    ///         "headers": [["location", "https://tinyurl.com/abc"]],
+    ///         "response": ""
+    ///     }, {
+    ///         "provider": "unshorten",
+    ///         "method": "GET",
+    ///         "url": "https://tinyurl.com/abc",
+    ///         "status": 302,
+    ///         "headers": [["location", "https://evil.example.com/payload"]],
+    ///         "response": ""
+    ///     }]}"#,
+    /// )
+    /// .unwrap();
+    /// set_cassette(Cassette::replay(&path).unwrap());
+    ///
+    /// // evil.example.com is never requested
+    /// let expander = UrlExpander::new().allow_active_probing().only_shorteners();
+    /// let chain = expander.expand("https://bit.ly/3xYzAbC").unwrap();
+    /// assert_eq!(chain.final_url(), "https://evil.example.com/payload");
+    /// assert_eq!(chain.statuses, vec![301, 302]);
+    /// assert_eq!(chain.end, ChainEnd::LeftShorteners);
+    ///
+    /// let chain = UrlExpander::new().allow_active_probing().with_max_hops(1).expand("https://bit.ly/3xYzAbC").unwrap();
+    /// assert_eq!(chain.hops, vec!["https://tinyurl.com/abc"]);
+    /// assert_eq!(chain.end, ChainEnd::MaxHops);
+    /// clear_cassette();
+    /// ```
+    #[derive(Clone, Default)]
+    pub struct UrlExpander {
+        inner: nonblocking::UrlExpander,
+    }
+
+    impl UrlExpander {
+        /// expander following 10 hops at most, 5 seconds per hop and 20 seconds in total
+        /// (requests are refused until `allow_active_probing`)
+        pub fn new() -> Self {
+            UrlExpander::default()
+        }
+
+        /// allow requests, accepting that servers of adversaries may notice them
+        pub fn allow_active_probing(mut self) -> Self {
+            self.inner = self.inner.allow_active_probing();
+            self
+        }
+
+        /// stop before the first hop outside known shorteners (it is not requested)
+        pub fn only_shorteners(mut self) -> Self {
+            self.inner = self.inner.only_shorteners();
+            self
+        }
+
+        /// use the HTTP client (redirects should be disabled in it, as the expander follows them)
+        pub fn with_client(mut self, client: reqwest::Client) -> Self {
+            self.inner = self.inner.with_client(client);
+            self
+        }
+
+        /// send requests via the proxy
+        pub fn with_proxy(mut self, proxy: &ProxyConfig) -> GenericResult<Self> {
+            self.inner = self.inner.with_proxy(proxy)?;
+            Ok(self)
+        }
+
+        /// `User-Agent` of requests (default `iocutil.rs/<version>`)
+        pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Self {
+            self.inner = self.inner.with_user_agent(user_agent);
+            self
+        }
+
+        /// redirects followed at most (default 10)
+        pub fn with_max_hops(mut self, max: usize) -> Self {
+            self.inner = self.inner.with_max_hops(max);
+            self
+        }
+
+        /// timeout of each request (default 5 seconds)
+        pub fn with_hop_timeout(mut self, timeout: Duration) -> Self {
+            self.inner = self.inner.with_hop_timeout(timeout);
+            self
+        }
+
+        /// cap of total time of expansion (default 20 seconds, the chain so far is returned)
+        pub fn with_total_timeout(mut self, timeout: Duration) -> Self {
+            self.inner = self.inner.with_total_timeout(timeout);
+            self
+        }
+
+        /// redirect chain of the URL
+        pub fn expand(&self, url: impl AsRef<str>) -> GenericResult<RedirectChain> {
+            block_on(self.inner.expand(url))
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! normalizing URLs (RFC 3986) to compare and deduplicate URL indicators
+//!
+//! Normalization is syntax-based (RFC 3986 section 6.2.2) and scheme-based (section 6.2.3):
+//! scheme and host are lowercased, percent-encodings are uppercased (and decoded if they are
+//! unreserved characters), dot segments are removed, default port is stripped and empty path
+//! becomes `/`. Tracking parameters (e.g. `utm_source`) can be removed as well.
+//!
+//! Near-duplicates (e.g. URLs generated by phishing kits per victim) are collapsed by
+//! `collapse_urls`, comparing URLs by `url_pattern`.
+
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::entropy;
+
+/// query parameters only for tracking visitors (`utm_` parameters are removed as well)
+const TRACKING_PARAMS: &[&str] = &[
+    "_ga", "_gl", "_hsenc", "_hsmi", "dclid", "fbclid", "gclid", "gclsrc", "igshid", "mc_cid",
+    "mc_eid", "mkt_tok", "msclkid", "yclid",
+];
+
+/// query parameters of sessions and cache busting (values of random or long numbers are as well)
+const VOLATILE_PARAMS: &[&str] = &[
+    "_",
+    "cache",
+    "cachebuster",
+    "cb",
+    "jsessionid",
+    "nocache",
+    "nonce",
+    "phpsessid",
+    "r",
+    "rand",
+    "random",
+    "sessid",
+    "session",
+    "session_id",
+    "sessionid",
+    "sid",
+    "t",
+    "timestamp",
+    "token",
+    "ts",
+];
+
+/// options of URL normalization
+///
+/// # Example
+///
+/// ```
+/// use iocutil::urlnorm::UrlNormalizerBuilder;
+///
+/// let normalizer = UrlNormalizerBuilder::default().strip_tracking(true).build().unwrap();
+/// assert_eq!(
+///     normalizer.normalize("https://example.com/a?utm_source=mail&id=1&fbclid=x"),
+///     "https://example.com/a?id=1"
+/// );
+/// assert_eq!(normalizer.normalize("https://example.com/?utm_medium=x"), "https://example.com/");
+/// ```
+#[derive(Builder, Debug, Clone, Eq, PartialEq)]
+pub struct UrlNormalizer {
+    /// remove tracking parameters (e.g. `utm_source` and `fbclid`) from query
+    #[builder(default = "false")]
+    strip_tracking: bool,
+    /// remove fragment (which is not sent to servers)
+    #[builder(default = "false")]
+    strip_fragment: bool,
+}
+
+impl Default for UrlNormalizer {
+    /// syntax-based and scheme-based normalization only
+    fn default() -> Self {
+        UrlNormalizerBuilder::default().build().unwrap()
+    }
+}
+
+/// default port of scheme
+fn default_port(scheme: &str) -> Option<&'static str> {
+    match scheme {
+        "http" | "ws" => Some("80"),
+        "https" | "wss" => Some("443"),
+        "ftp" => Some("21"),
+        _ => None,
+    }
+}
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~".contains(&b)
+}
+
+/// uppercase percent-encodings, and decode ones of unreserved characters
+fn normalize_percent(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        let decoded = match bytes.get(i + 1..i + 3) {
+            // from_str_radix accepts a sign (`%+1`)
+            Some(hex) if bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit) => {
+                std::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|x| u8::from_str_radix(x, 16).ok())
+            }
+            _ => None,
+        };
+        match decoded {
+            Some(b) if is_unreserved(b) => {
+                out.push(b as char);
+                i += 3;
+            }
+            Some(b) => {
+                out.push_str(&format!("%{:02X}", b));
+                i += 3;
+            }
+            None => {
+                let c = s[i..].chars().next().unwrap();
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// remove `.` and `..` segments of absolute path (RFC 3986 section 5.2.4)
+fn remove_dot_segments(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    let mut out: Vec<&str> = vec![];
+    for (i, seg) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        match *seg {
+            "." => {}
+            ".." => {
+                out.pop();
+            }
+            x => {
+                out.push(x);
+                continue;
+            }
+        }
+        if last {
+            out.push("");
+        }
+    }
+    format!("/{}", out.join("/"))
+}
+
+fn is_tracking(param: &str) -> bool {
+    let name = param.split('=').next().unwrap_or_default().to_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
+impl UrlNormalizer {
+    /// normalized URL (text without scheme is returned as is)
+    pub fn normalize(&self, url: impl AsRef<str>) -> String {
+        let url = url.as_ref().trim();
+        let (scheme, rest) = match url.find("://") {
+            Some(idx)
+                if idx > 0
+                    && url[..idx]
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b)) =>
+            {
+                (url[..idx].to_ascii_lowercase(), &url[idx + 3..])
+            }
+            _ => return url.to_owned(),
+        };
+
+        let (rest, fragment) = match rest.split_once('#') {
+            Some((x, f)) => (x, Some(f)),
+            None => (rest, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((x, q)) => (x, Some(q)),
+            None => (rest, None),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, ""),
+        };
+
+        let (userinfo, hostport) = match authority.rsplit_once('@') {
+            Some((u, h)) => (Some(u), h),
+            None => (None, authority),
+        };
+        let (host, port) = match hostport.strip_prefix('[') {
+            // ipv6 literal
+            Some(literal) => match literal.split_once(']') {
+                Some((h, p)) => (format!("[{}]", h), p.strip_prefix(':')),
+                None => (hostport.to_owned(), None),
+            },
+            None => match hostport.split_once(':') {
+                Some((h, p)) => (h.to_owned(), Some(p)),
+                None => (hostport.to_owned(), None),
+            },
+        };
+        let port = port.filter(|p| !p.is_empty() && Some(*p) != default_port(&scheme));
+
+        let mut out = format!("{}://", scheme);
+        if let Some(u) = userinfo {
+            out.push_str(&normalize_percent(u));
+            out.push('@');
+        }
+        out.push_str(&normalize_percent(&host.to_lowercase()));
+        if let Some(p) = port {
+            out.push(':');
+            out.push_str(p);
+        }
+        out.push_str(&remove_dot_segments(&normalize_percent(path)));
+
+        if let Some(q) = query {
+            let q = normalize_percent(q);
+            let q = if self.strip_tracking {
+                q.split('&')
+                    .filter(|x| !x.is_empty() && !is_tracking(x))
+                    .collect::<Vec<_>>()
+                    .join("&")
+            } else {
+                q
+            };
+            if !q.is_empty() || !self.strip_tracking {
+                out.push('?');
+                out.push_str(&q);
+            }
+        }
+        if let Some(f) = fragment.filter(|_| !self.strip_fragment) {
+            out.push('#');
+            out.push_str(&normalize_percent(f));
+        }
+        out
+    }
+}
+
+/// normalized URL by default options (without removing tracking parameters)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::urlnorm::normalize_url;
+///
+/// assert_eq!(normalize_url("HTTP://Example.COM:80"), "http://example.com/");
+/// assert_eq!(normalize_url("https://example.com:443/a/./b/../c"), "https://example.com/a/c");
+/// assert_eq!(normalize_url("http://example.com:8080/%7euser/%2fx%3f"), "http://example.com:8080/~user/%2Fx%3F");
+/// assert_eq!(normalize_url("http://[2001:DB8::1]:80/"), "http://[2001:db8::1]/");
+/// assert_eq!(normalize_url("http://Ex%2fample.com/%+1"), "http://ex%2Fample.com/%+1");
+/// assert_eq!(normalize_url("http://User@Example.com/Path?Q=1#Top"), "http://User@example.com/Path?Q=1#Top");
+/// assert_eq!(normalize_url("example.com/a"), "example.com/a");
+/// ```
+pub fn normalize_url(url: impl AsRef<str>) -> String {
+    UrlNormalizer::default().normalize(url)
+}
+
+fn is_volatile(param: &str) -> bool {
+    let (name, value) = param.split_once('=').unwrap_or((param, ""));
+    VOLATILE_PARAMS.contains(&name.to_lowercase().as_str())
+        || (value.len() >= 6 && value.bytes().all(|b| b.is_ascii_digit()))
+        || entropy::analyze(value).is_random()
+}
+
+/// URL with numeric path segments replaced by `{n}`, and values of session and cache busting
+/// parameters by `{}` (after normalization, removing tracking parameters and fragment)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::urlnorm::url_pattern;
+///
+/// assert_eq!(
+///     url_pattern("https://evil.example.com/u/48213/login.php?sid=a8Fk2Lq9xZ&lang=en#top"),
+///     "https://evil.example.com/u/{n}/login.php?sid={}&lang=en"
+/// );
+/// assert_eq!(url_pattern("https://example.com/a.js?_=1699999999123"), "https://example.com/a.js?_={}");
+/// ```
+pub fn url_pattern(url: impl AsRef<str>) -> String {
+    let normalizer = UrlNormalizerBuilder::default()
+        .strip_tracking(true)
+        .strip_fragment(true)
+        .build()
+        .unwrap();
+    let url = normalizer.normalize(url);
+    let (scheme, rest) = match url.split_once("://") {
+        Some(x) => x,
+        None => return url,
+    };
+    let (rest, query) = match rest.split_once('?') {
+        Some((x, q)) => (x, Some(q)),
+        None => (rest, None),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, ""),
+    };
+
+    let path: Vec<&str> = path
+        .split('/')
+        .map(|x| {
+            if !x.is_empty() && x.bytes().all(|b| b.is_ascii_digit()) {
+                "{n}"
+            } else {
+                x
+            }
+        })
+        .collect();
+    let mut out = format!("{}://{}{}", scheme, authority, path.join("/"));
+    if let Some(q) = query {
+        let params: Vec<String> = q
+            .split('&')
+            .map(|x| match x.split_once('=') {
+                Some((name, _)) if is_volatile(x) => format!("{}={{}}", name),
+                _ => x.to_owned(),
+            })
+            .collect();
+        out.push('?');
+        out.push_str(&params.join("&"));
+    }
+    out
+}
+
+/// near-duplicate URLs collapsed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollapsedUrl {
+    /// the first URL seen (normalized)
+    pub representative: String,
+    /// pattern shared by the URLs (see `url_pattern`)
+    pub pattern: String,
+    /// number of URLs collapsed (including the representative and duplicates)
+    pub count: usize,
+}
+
+/// collapse URLs differing only in session / cache busting parameters or numeric path segments
+/// (in order of first seen)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::urlnorm::collapse_urls;
+///
+/// let urls = [
+///     "https://evil.example.com/u/48213/login.php?sid=a8Fk2Lq9xZ",
+///     "https://evil.example.com/u/48214/login.php?sid=Qz7Lp2Xk9f",
+///     "https://evil.example.com/u/48215/login.php?sid=7hX2kQ9pWm",
+///     "https://evil.example.com/u/48215/logout.php",
+/// ];
+/// let collapsed = collapse_urls(&urls);
+/// assert_eq!(collapsed.len(), 2);
+/// assert_eq!(collapsed[0].representative, urls[0]);
+/// assert_eq!(collapsed[0].count, 3);
+/// assert_eq!(collapsed[1].count, 1);
+/// ```
+pub fn collapse_urls(urls: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<CollapsedUrl> {
+    let mut collapsed: Vec<CollapsedUrl> = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for url in urls {
+        let pattern = url_pattern(&url);
+        match index.get(&pattern) {
+            Some(&i) => collapsed[i].count += 1,
+            None => {
+                index.insert(pattern.clone(), collapsed.len());
+                collapsed.push(CollapsedUrl {
+                    representative: normalize_url(url),
+                    pattern,
+                    count: 1,
+                });
+            }
+        }
+    }
+    collapsed
+}
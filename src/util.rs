@@ -8,6 +8,26 @@ pub fn unwrap_try_into(hash: impl TryInto<SampleHash>) -> Result<SampleHash, Err
     hash.try_into().or(Err(Error::from(ErrorKind::InvalidData)))
 }
 
+/// namespace of deterministic identifiers (same as STIX 2.1 SCO namespace)
+const NAMESPACE: uuid::Uuid = uuid::Uuid::from_bytes([
+    0x00, 0xab, 0xed, 0xb4, 0xaa, 0x42, 0x46, 0x6c, 0x9c, 0x01, 0xfe, 0xd2, 0x33, 0x15, 0xa9, 0xb7,
+]);
+
+/// deterministic uuid from seed (same seed makes same id, so exported documents are stable)
+pub fn deterministic_uuid(seed: impl AsRef<str>) -> uuid::Uuid {
+    uuid::Uuid::new_v5(&NAMESPACE, seed.as_ref().as_bytes())
+}
+
+/// escape text for xml
+pub fn escape_xml(text: impl AsRef<str>) -> String {
+    text.as_ref()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
 /// check target is expected hashtype
 #[macro_export]
 macro_rules! check_hashtype {
@@ -0,0 +1,405 @@
+//! validating and classifying indicators (only `core` and `alloc` are required)
+//!
+//! This module is available without the `std` feature, so that agents on embedded gateways
+//! (or other `no_std` environments with an allocator) can share the validation with `iocutil::ioc`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::net::IpAddr;
+
+use crate::ipscope::{IpAddressExt, IpScope};
+
+/// kind of indicator
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Ord, PartialOrd)]
+pub enum IocKind {
+    Hash,
+    Domain,
+    Url,
+    IpAddress,
+    EmailAddress,
+}
+
+impl fmt::Display for IocKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            IocKind::Hash => write!(f, "hash"),
+            IocKind::Domain => write!(f, "domain"),
+            IocKind::Url => write!(f, "url"),
+            IocKind::IpAddress => write!(f, "ip"),
+            IocKind::EmailAddress => write!(f, "email"),
+        }
+    }
+}
+
+fn is_hex_digest(target: &str, len: usize) -> bool {
+    target.len() == len && target.bytes().all(|c| c.is_ascii_hexdigit())
+}
+
+/// is specified text is md5 hex digest?
+pub fn is_md5(target: impl AsRef<str>) -> bool {
+    is_hex_digest(target.as_ref(), 32)
+}
+
+/// is specified text is sha1 hex digest?
+pub fn is_sha1(target: impl AsRef<str>) -> bool {
+    is_hex_digest(target.as_ref(), 40)
+}
+
+/// is specified text is sha256 hex digest?
+pub fn is_sha256(target: impl AsRef<str>) -> bool {
+    is_hex_digest(target.as_ref(), 64)
+}
+
+/// is specified text looks like a domain name?
+///
+/// # Example
+///
+/// ```
+/// use iocutil::validate::is_domain;
+///
+/// assert!(is_domain("example.com"));
+/// assert!(is_domain("xn--r8jz45g.xn--zckzah"));
+/// assert!(!is_domain("example"));
+/// assert!(!is_domain("-example.com"));
+/// assert!(!is_domain("192.0.2.1"));
+/// ```
+pub fn is_domain(target: impl AsRef<str>) -> bool {
+    let target = target.as_ref().trim_end_matches('.');
+    if target.len() > 253 || !target.contains('.') {
+        return false;
+    }
+
+    let valid_label = |l: &str| {
+        !l.is_empty()
+            && l.len() <= 63
+            && !l.starts_with('-')
+            && !l.ends_with('-')
+            && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+
+    let tld = target.rsplit('.').next().unwrap_or_default();
+    let valid_tld =
+        tld.starts_with("xn--") || (tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()));
+
+    target.split('.').all(valid_label) && valid_tld
+}
+
+/// is specified text looks like an email address?
+///
+/// # Example
+///
+/// ```
+/// use iocutil::validate::is_email_address;
+///
+/// assert!(is_email_address("alice@example.com"));
+/// assert!(!is_email_address("alice@"));
+/// assert!(!is_email_address("example.com"));
+/// ```
+pub fn is_email_address(target: impl AsRef<str>) -> bool {
+    let mut parts = target.as_ref().splitn(2, '@');
+    match (parts.next(), parts.next()) {
+        (Some(local), Some(domain)) => {
+            !local.is_empty() && !local.chars().any(|c| c.is_whitespace()) && is_domain(domain)
+        }
+        _ => false,
+    }
+}
+
+/// is specified text looks like an url?
+///
+/// # Example
+///
+/// ```
+/// use iocutil::validate::is_url;
+///
+/// assert!(is_url("https://example.com/index.html"));
+/// assert!(is_url("hxxp://example.com/")); // defanged scheme is also accepted
+/// assert!(!is_url("example.com/index.html"));
+/// ```
+pub fn is_url(target: impl AsRef<str>) -> bool {
+    let target = target.as_ref();
+    match target.find("://") {
+        Some(idx) => {
+            let (scheme, rest) = (&target[..idx], &target[idx + 3..]);
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+                && !rest.is_empty()
+                && !rest.chars().any(|c| c.is_whitespace())
+        }
+        None => false,
+    }
+}
+
+/// kind of indicator (same detection as parsing `Ioc`; surrounding whitespaces are ignored)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::validate::{kind_of, IocKind};
+///
+/// assert_eq!(kind_of("d41d8cd98f00b204e9800998ecf8427e"), Some(IocKind::Hash));
+/// assert_eq!(kind_of(" 2001:db8::1 "), Some(IocKind::IpAddress));
+/// assert_eq!(kind_of("http://example.com/a"), Some(IocKind::Url));
+/// assert_eq!(kind_of("alice@example.com"), Some(IocKind::EmailAddress));
+/// assert_eq!(kind_of("Example.COM"), Some(IocKind::Domain));
+/// assert_eq!(kind_of("not an indicator"), None);
+/// ```
+pub fn kind_of(value: impl AsRef<str>) -> Option<IocKind> {
+    let value = value.as_ref().trim();
+    if is_md5(value) || is_sha1(value) || is_sha256(value) {
+        Some(IocKind::Hash)
+    } else if value.parse::<IpAddr>().is_ok() {
+        Some(IocKind::IpAddress)
+    } else if is_url(value) {
+        Some(IocKind::Url)
+    } else if is_email_address(value) {
+        Some(IocKind::EmailAddress)
+    } else if is_domain(value) {
+        Some(IocKind::Domain)
+    } else {
+        None
+    }
+}
+
+/// host part of url (without brackets of ipv6 literal)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::validate::url_host;
+///
+/// assert_eq!(url_host("https://user@Example.com:8443/a?b#c"), Some("Example.com"));
+/// assert_eq!(url_host("http://[2001:db8::1]:80/"), Some("2001:db8::1"));
+/// assert_eq!(url_host("example.com/a"), None);
+/// ```
+pub fn url_host(url: &str) -> Option<&str> {
+    let rest = &url[url.find("://")? + 3..];
+    let authority = rest.split(&['/', '?', '#'][..]).next()?;
+    let hostport = authority.rsplit('@').next()?;
+
+    if let Some(literal) = hostport.strip_prefix('[') {
+        // ipv6 literal
+        Some(&literal[..literal.find(']')?])
+    } else {
+        hostport.split(':').next()
+    }
+}
+
+/// defanged value of an indicator, which is not clickable nor resolvable (hashes are kept as is)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::validate::{defang, IocKind};
+///
+/// assert_eq!(defang(IocKind::Url, "https://evil.example.com/a.b"), "hxxps://evil[.]example[.]com/a.b");
+/// assert_eq!(defang(IocKind::IpAddress, "2001:db8::1"), "2001[:]db8[:][:]1");
+/// ```
+pub fn defang(kind: IocKind, value: &str) -> String {
+    let dots = |x: &str| x.replace('.', "[.]");
+    match kind {
+        IocKind::Hash => value.to_string(),
+        IocKind::Domain => dots(value),
+        IocKind::IpAddress if value.contains(':') => value.replace(':', "[:]"),
+        IocKind::IpAddress => dots(value),
+        IocKind::EmailAddress => dots(&value.replace('@', "[@]")),
+        IocKind::Url => {
+            let idx = match value.find("://") {
+                Some(idx) => idx,
+                None => return dots(value),
+            };
+            let (scheme, rest) = (&value[..idx], &value[idx + 3..]);
+            let scheme = match scheme.to_lowercase().as_str() {
+                "http" => "hxxp".to_string(),
+                "https" => "hxxps".to_string(),
+                "ftp" => "fxp".to_string(),
+                _ => scheme.to_string(),
+            };
+            // only authority is defanged to keep path readable
+            let end = rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len());
+            format!("{}://{}{}", scheme, dots(&rest[..end]), &rest[end..])
+        }
+    }
+}
+
+/// reason why a value is rejected as indicator
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Rejection {
+    Empty,
+    /// hex digits whose number is not of md5 / sha1 / sha256 digest
+    BadChecksum {
+        digits: usize,
+    },
+    /// dotted or colon separated numbers which are not a valid IP address
+    InvalidIpAddress,
+    /// IP address (or host of URL) is not globally routable
+    NonRoutable(IpScope),
+    InvalidScheme,
+    MissingHost,
+    /// empty local part of email address
+    InvalidEmailAddress,
+    InvalidCharacter(char),
+    /// domain is longer than 253 characters
+    DomainTooLong(usize),
+    EmptyLabel,
+    /// label is longer than 63 characters
+    LabelTooLong(usize),
+    /// label starts or ends with `-`
+    InvalidHyphen,
+    /// single label without top level domain
+    MissingTld,
+    InvalidTld(String),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Empty => write!(f, "empty value"),
+            Rejection::BadChecksum { digits } => write!(
+                f,
+                "bad checksum: {} hex digits is not md5, sha1 nor sha256",
+                digits
+            ),
+            Rejection::InvalidIpAddress => write!(f, "invalid IP address"),
+            Rejection::NonRoutable(scope) => write!(f, "non-routable address ({:?})", scope),
+            Rejection::InvalidScheme => write!(f, "invalid URL scheme"),
+            Rejection::MissingHost => write!(f, "URL without host"),
+            Rejection::InvalidEmailAddress => write!(f, "email address without local part"),
+            Rejection::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            Rejection::DomainTooLong(len) => {
+                write!(f, "domain too long ({} characters, up to 253)", len)
+            }
+            Rejection::EmptyLabel => write!(f, "empty label"),
+            Rejection::LabelTooLong(len) => {
+                write!(f, "label too long ({} characters, up to 63)", len)
+            }
+            Rejection::InvalidHyphen => write!(f, "label starts or ends with hyphen"),
+            Rejection::MissingTld => write!(f, "missing top level domain"),
+            Rejection::InvalidTld(tld) => write!(f, "invalid top level domain {:?}", tld),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Rejection {}
+
+/// why the domain is rejected
+fn domain_rejection(domain: &str) -> Rejection {
+    let domain = domain.trim_end_matches('.');
+    if domain.len() > 253 {
+        return Rejection::DomainTooLong(domain.len());
+    }
+    for label in domain.split('.') {
+        if label.is_empty() {
+            return Rejection::EmptyLabel;
+        }
+        if let Some(c) = label
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-'))
+        {
+            return Rejection::InvalidCharacter(c);
+        }
+        if label.len() > 63 {
+            return Rejection::LabelTooLong(label.len());
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Rejection::InvalidHyphen;
+        }
+    }
+    match domain.rsplit_once('.') {
+        Some((_, tld)) => Rejection::InvalidTld(tld.to_string()),
+        None => Rejection::MissingTld,
+    }
+}
+
+/// IP address or host is not routable?
+fn check_routable(ip: &IpAddr) -> Result<(), Rejection> {
+    match ip.scope() {
+        IpScope::Public => Ok(()),
+        scope => Err(Rejection::NonRoutable(scope)),
+    }
+}
+
+/// kind of indicator, or why the value is rejected (stricter than `kind_of`, IP addresses and
+/// URL hosts have to be globally routable)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::ipscope::IpScope;
+/// use iocutil::validate::{check, IocKind, Rejection};
+///
+/// assert_eq!(check("evil.example.com"), Ok(IocKind::Domain));
+/// assert_eq!(check("d41d8cd98f00b204e9800998ecf842"), Err(Rejection::BadChecksum { digits: 30 }));
+/// assert_eq!(check("example.c0m"), Err(Rejection::InvalidTld("c0m".to_owned())));
+/// assert_eq!(check(&format!("{}.com", "a".repeat(64))), Err(Rejection::LabelTooLong(64)));
+/// assert_eq!(check("10.0.0.1"), Err(Rejection::NonRoutable(IpScope::Private)));
+/// assert_eq!(check("http://127.0.0.1:8080/"), Err(Rejection::NonRoutable(IpScope::Loopback)));
+/// assert_eq!(check("192.0.2.256"), Err(Rejection::InvalidIpAddress));
+/// assert_eq!(check("http://ex ample.com/"), Err(Rejection::InvalidCharacter(' ')));
+/// assert_eq!(check("localhost"), Err(Rejection::MissingTld));
+/// ```
+pub fn check(value: impl AsRef<str>) -> Result<IocKind, Rejection> {
+    let value = value.as_ref().trim();
+    if value.is_empty() {
+        return Err(Rejection::Empty);
+    }
+    if let Some(kind) = kind_of(value) {
+        match kind {
+            IocKind::IpAddress => {
+                check_routable(&value.parse().map_err(|_| Rejection::InvalidIpAddress)?)?
+            }
+            IocKind::Url => {
+                let host = url_host(value).ok_or(Rejection::MissingHost)?;
+                if let Ok(ip) = host.parse::<IpAddr>() {
+                    check_routable(&ip)?;
+                } else if host.is_empty() {
+                    return Err(Rejection::MissingHost);
+                } else if !is_domain(host) {
+                    return Err(domain_rejection(host));
+                }
+            }
+            _ => {}
+        }
+        return Ok(kind);
+    }
+
+    if let Some(c) = value.chars().find(|c| c.is_whitespace()) {
+        return Err(Rejection::InvalidCharacter(c));
+    }
+    if value.bytes().all(|b| b.is_ascii_hexdigit()) && value.len() >= 16 {
+        return Err(Rejection::BadChecksum {
+            digits: value.len(),
+        });
+    }
+    if let Some(idx) = value.find("://") {
+        return Err(match value[idx + 3..].is_empty() {
+            true => Rejection::MissingHost,
+            false => Rejection::InvalidScheme,
+        });
+    }
+    if let Some((local, domain)) = value.split_once('@') {
+        if local.is_empty() {
+            return Err(Rejection::InvalidEmailAddress);
+        }
+        return Err(domain_rejection(domain));
+    }
+    let numeric = |sep: char| {
+        value.contains(sep)
+            && value
+                .split(sep)
+                .all(|x| !x.is_empty() && x.bytes().all(|b| b.is_ascii_hexdigit()))
+    };
+    if (numeric('.')
+        && value
+            .split('.')
+            .all(|x| x.bytes().all(|b| b.is_ascii_digit())))
+        || (value.contains("::") || numeric(':'))
+    {
+        return Err(Rejection::InvalidIpAddress);
+    }
+    Err(domain_rejection(value))
+}
@@ -0,0 +1,418 @@
+//! recording provider HTTP responses into fixtures and replaying them (VCR-style)
+//!
+//! While a `Cassette` is installed by `set_cassette`, all API clients record their responses
+//! into it, or are served from it without sending requests (so tests of pipelines using this
+//! crate run deterministically without live keys).
+//!
+//! API keys never reach fixtures: request headers are not recorded, and secret-looking query /
+//! form parameters (e.g. `apikey`) and JSON fields (e.g. `access_token`) are replaced with
+//! `SCRUBBED`, as well as values given by `with_secret`. Recorded requests are matched by
+//! method, scrubbed URL and scrubbed body, so replaying works with any (dummy) key.
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::Error;
+use crate::GenericResult;
+
+/// replaced value of secrets
+const SCRUBBED: &str = "SCRUBBED";
+
+/// names of query / form parameters and JSON fields holding secrets
+const SECRET_NAMES: &[&str] = &[
+    "access_token",
+    "api-key",
+    "api_key",
+    "apikey",
+    "auth-key",
+    "client_secret",
+    "key",
+    "password",
+    "refresh_token",
+    "token",
+];
+
+/// response headers not recorded
+const SKIPPED_HEADERS: &[&str] = &["set-cookie", "content-length", "transfer-encoding"];
+
+/// what installed cassette does
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// send requests, and record responses into fixtures file
+    Record,
+    /// serve recorded responses (requests not recorded fail without being sent)
+    Replay,
+}
+
+/// a recorded request and its response
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Interaction {
+    provider: String,
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    status: u16,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    /// body of response (hex if it is not UTF-8, e.g. downloaded samples)
+    response: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    hex: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Fixtures {
+    interactions: Vec<Interaction>,
+}
+
+struct Tape {
+    path: PathBuf,
+    mode: Mode,
+    fixtures: Fixtures,
+    secrets: Vec<String>,
+    /// number of times each request was replayed
+    played: HashMap<(String, String, Option<String>), usize>,
+}
+
+/// fixtures file of recorded responses (clones share it)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::vcr::{clear_cassette, set_cassette, Cassette};
+///
+/// let path = std::env::temp_dir().join("iocutil-vcr-doctest.json");
+/// std::fs::write(
+///     &path,
+///     r#"{"interactions": [{
+///         "provider": "virustotal",
+///         "method": "GET",
+///         "url": "https://www.virustotal.com/vtapi/v2/file/report?apikey=SCRUBBED&allinfo=false&resource=d41d8cd98f00b204e9800998ecf8427e",
+///         "status": 200,
+///         "headers": [["content-type", "application/json"]],
+///         "response": "{\"response_code\": 0, \"resource\": \"d41d8cd98f00b204e9800998ecf8427e\", \"verbose_msg\": \"not found\"}"
+///     }]}"#,
+/// )
+/// .unwrap();
+///
+/// // served from fixtures with a dummy key
+/// set_cassette(Cassette::replay(&path).unwrap());
+/// let client = VirusTotalClient::new("dummy");
+/// let e = client.query_filereport("d41d8cd98f00b204e9800998ecf8427e").unwrap_err();
+/// assert_eq!(e.to_string(), "VT not returned status code 1");
+/// // requests not recorded fail without being sent
+/// let e = client.query_filereport("44d88612fea8a8f36de82e1278abb02f").unwrap_err();
+/// assert!(e.to_string().contains("no recorded response"));
+/// clear_cassette();
+/// ```
+#[derive(Clone)]
+pub struct Cassette {
+    tape: Arc<Mutex<Tape>>,
+}
+
+impl Cassette {
+    fn new(path: &Path, mode: Mode, fixtures: Fixtures) -> Self {
+        Cassette {
+            tape: Arc::new(Mutex::new(Tape {
+                path: path.to_owned(),
+                mode,
+                fixtures,
+                secrets: vec![],
+                played: HashMap::new(),
+            })),
+        }
+    }
+
+    /// record responses into the file (overwritten on every recorded response)
+    pub fn record(path: impl AsRef<Path>) -> Self {
+        Cassette::new(path.as_ref(), Mode::Record, Fixtures::default())
+    }
+
+    /// replay responses recorded in the file
+    pub fn replay(path: impl AsRef<Path>) -> GenericResult<Self> {
+        let path = path.as_ref();
+        let fixtures = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(Cassette::new(path, Mode::Replay, fixtures))
+    }
+
+    /// replay the file if it exists, record into it otherwise
+    pub fn open(path: impl AsRef<Path>) -> GenericResult<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            Cassette::replay(path)
+        } else {
+            Ok(Cassette::record(path))
+        }
+    }
+
+    /// scrub the value (e.g. API key in URL path) as well
+    pub fn with_secret(self, secret: impl AsRef<str>) -> Self {
+        let secret = secret.as_ref();
+        if !secret.is_empty() {
+            self.tape.lock().unwrap().secrets.push(secret.to_owned());
+        }
+        self
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.tape.lock().unwrap().mode
+    }
+
+    /// number of recorded responses
+    pub fn len(&self) -> usize {
+        self.tape.lock().unwrap().fixtures.interactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// send the request by `send` and record its response, or replay the recorded one
+    pub(crate) async fn send<F, Fut>(
+        &self,
+        provider: &'static str,
+        req: reqwest::RequestBuilder,
+        send: F,
+    ) -> Result<reqwest::Response, Error>
+    where
+        F: FnOnce(reqwest::RequestBuilder) -> Fut,
+        Fut: Future<Output = Result<reqwest::Response, Error>>,
+    {
+        let mode = self.mode();
+        let (method, url, body) = match req.try_clone().and_then(|x| x.build().ok()) {
+            Some(r) => {
+                let body = r
+                    .body()
+                    .and_then(|b| b.as_bytes())
+                    .map(|b| String::from_utf8_lossy(b).into_owned());
+                (r.method().to_string(), r.url().to_string(), body)
+            }
+            // requests with streaming body are sent without being recorded
+            None if mode == Mode::Record => return send(req).await,
+            None => return Err(unrecordable(provider)),
+        };
+        let key = {
+            let tape = self.tape.lock().unwrap();
+            (
+                method,
+                tape.scrub_query(&url),
+                body.map(|x| tape.scrub_form(&x)),
+            )
+        };
+
+        match mode {
+            Mode::Replay => self.tape.lock().unwrap().replay(provider, key),
+            Mode::Record => {
+                let res = send(req).await?;
+                let status = res.status();
+                let headers = res.headers().clone();
+                let body = res
+                    .bytes()
+                    .await
+                    .map_err(|e| Error::transport(provider, e))?;
+                self.tape
+                    .lock()
+                    .unwrap()
+                    .record(provider, key, status, &headers, &body)?;
+
+                let mut res = http::Response::builder().status(status);
+                if let Some(h) = res.headers_mut() {
+                    *h = headers;
+                }
+                response(provider, res, body.to_vec())
+            }
+        }
+    }
+}
+
+fn unrecordable(provider: &'static str) -> Error {
+    Error::Transport {
+        provider,
+        retryable: false,
+        source: "request with streaming body cannot be replayed".into(),
+    }
+}
+
+fn response(
+    provider: &'static str,
+    builder: http::response::Builder,
+    body: Vec<u8>,
+) -> Result<reqwest::Response, Error> {
+    let res = builder.body(body).map_err(|e| Error::Transport {
+        provider,
+        retryable: false,
+        source: Box::new(e),
+    })?;
+    Ok(reqwest::Response::from(res))
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = percent_encoding::percent_decode_str(name).decode_utf8_lossy();
+    SECRET_NAMES.contains(&name.to_lowercase().as_str())
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl Tape {
+    fn scrub(&self, s: &str) -> String {
+        self.secrets
+            .iter()
+            .fold(s.to_owned(), |acc, x| acc.replace(x.as_str(), SCRUBBED))
+    }
+
+    /// scrub `name=value` pairs separated by `&`
+    fn scrub_form(&self, form: &str) -> String {
+        let scrubbed: Vec<String> = form
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if is_secret_name(name) => format!("{}={}", name, SCRUBBED),
+                _ => pair.to_owned(),
+            })
+            .collect();
+        self.scrub(&scrubbed.join("&"))
+    }
+
+    fn scrub_query(&self, url: &str) -> String {
+        match url.split_once('?') {
+            Some((base, query)) => format!("{}?{}", self.scrub(base), self.scrub_form(query)),
+            None => self.scrub(url),
+        }
+    }
+
+    fn scrub_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (k, v) in map.iter_mut() {
+                    if is_secret_name(k) && v.is_string() {
+                        *v = serde_json::Value::String(SCRUBBED.to_owned());
+                    } else {
+                        self.scrub_json(v);
+                    }
+                }
+            }
+            serde_json::Value::Array(xs) => xs.iter_mut().for_each(|x| self.scrub_json(x)),
+            _ => {}
+        }
+    }
+
+    fn replay(
+        &mut self,
+        provider: &'static str,
+        key: (String, String, Option<String>),
+    ) -> Result<reqwest::Response, Error> {
+        let matched: Vec<&Interaction> = self
+            .fixtures
+            .interactions
+            .iter()
+            .filter(|x| x.method == key.0 && x.url == key.1 && x.body == key.2)
+            .collect();
+        let played = self.played.entry(key.clone()).or_insert(0);
+        // repeated requests get recorded responses in order (and the last one after that)
+        let found = match matched.get(*played).or_else(|| matched.last()) {
+            Some(x) => *x,
+            None => {
+                return Err(Error::Transport {
+                    provider,
+                    retryable: false,
+                    source: format!("no recorded response for {} {}", key.0, key.1).into(),
+                })
+            }
+        };
+        *played += 1;
+
+        let body = if found.hex {
+            hex_decode(&found.response).unwrap_or_default()
+        } else {
+            found.response.clone().into_bytes()
+        };
+        let mut res = http::Response::builder().status(found.status);
+        for (name, value) in &found.headers {
+            res = res.header(name.as_str(), value.as_str());
+        }
+        response(provider, res, body)
+    }
+
+    fn record(
+        &mut self,
+        provider: &'static str,
+        key: (String, String, Option<String>),
+        status: reqwest::StatusCode,
+        headers: &reqwest::header::HeaderMap,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        let (response, hex) = match std::str::from_utf8(body) {
+            Ok(s) => match serde_json::from_str::<serde_json::Value>(s) {
+                Ok(mut json) => {
+                    self.scrub_json(&mut json);
+                    (self.scrub(&json.to_string()), false)
+                }
+                Err(_) => (self.scrub(s), false),
+            },
+            Err(_) => (hex_encode(body), true),
+        };
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_owned(), self.scrub(value.to_str().ok()?)))
+            })
+            .collect();
+        self.fixtures.interactions.push(Interaction {
+            provider: provider.to_owned(),
+            method: key.0,
+            url: key.1,
+            body: key.2,
+            status: status.as_u16(),
+            headers,
+            response,
+            hex,
+        });
+        self.save().map_err(|e| Error::Transport {
+            provider,
+            retryable: false,
+            source: format!("failed to save fixtures: {}", e).into(),
+        })
+    }
+
+    fn save(&self) -> GenericResult<()> {
+        if let Some(dir) = self.path.parent().filter(|x| !x.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.fixtures)?)?;
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref CASSETTE: RwLock<Option<Cassette>> = RwLock::new(None);
+}
+
+/// install the cassette which all API clients record into / replay from (replaces previous one)
+pub fn set_cassette(cassette: Cassette) {
+    *CASSETTE.write().unwrap() = Some(cassette);
+}
+
+/// stop recording / replaying (requests are sent as usual)
+pub fn clear_cassette() {
+    *CASSETTE.write().unwrap() = None;
+}
+
+/// installed cassette (if any)
+pub(crate) fn current() -> Option<Cassette> {
+    CASSETTE.read().unwrap().clone()
+}
@@ -1,14 +1,18 @@
 //! VirusBay client (exprimental)
 
-use crate::util::unwrap_try_into;
+use crate::http::{block_on, ClientConfig};
+use crate::ioc::{Ioc, IocKind};
+use crate::nonblocking;
+use crate::provider::{Capabilities, IocProvider, ProviderReport, Verdict};
 use crate::{GenericResult, SampleHash};
-use failure::Fail;
 use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
 
 /// client for VirusBay API
 #[derive(Default)]
-pub struct VirusBayClient;
+pub struct VirusBayClient {
+    inner: nonblocking::VirusBayClient,
+}
 
 /// record in response
 #[derive(Serialize, Deserialize, Debug)]
@@ -76,16 +80,11 @@ pub struct Response {
     pub search: Vec<SearchResult>,
 }
 
-/// Errors in operating VirusBay
-#[derive(Fail, Debug)]
-pub enum VirusBayError {
-    #[fail(display = "sample not found on VirusBay")]
-    NotFoundOnVirusBay,
-}
-
 impl VirusBayClient {
-    fn query_url(&self, hash: impl AsRef<str>) -> String {
-        format!("https://beta.virusbay.io/sample/search?q={}", hash.as_ref())
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.inner = self.inner.with_config(config);
+        self
     }
 
     /// get raw json
@@ -101,7 +100,7 @@ impl VirusBayClient {
     ///     .expect("failed to get report");
     /// ```
     pub fn get_raw_json(&self, hash: impl TryInto<SampleHash>) -> GenericResult<String> {
-        Ok(reqwest::get(self.query_url(unwrap_try_into(hash)?).as_str())?.text()?)
+        block_on(self.inner.get_raw_json(hash))
     }
 
     /// query a sample (free format)
@@ -120,7 +119,7 @@ impl VirusBayClient {
     where
         T: serde::de::DeserializeOwned,
     {
-        Ok(reqwest::get(self.query_url(unwrap_try_into(hash)?).as_str())?.json()?)
+        block_on(self.inner.query(hash))
     }
 
     /// query a sample (formatted)
@@ -136,11 +135,49 @@ impl VirusBayClient {
     /// client.fquery("9fbdc5eca123e81571e8966b9b4e4a1e").expect("failed to retrieve sample 9fbdc5eca123e81571e8966b9b4e4a1e");
     /// ```
     pub fn fquery(&self, hash: impl TryInto<SampleHash>) -> GenericResult<Vec<SearchResult>> {
-        let r: Response = self.query(hash)?;
-        if r.search.is_empty() {
-            return Err(VirusBayError::NotFoundOnVirusBay.into());
-        }
+        block_on(self.inner.fquery(hash))
+    }
+}
+
+impl IocProvider for VirusBayClient {
+    fn name(&self) -> &str {
+        "virusbay"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::lookup_only(vec![IocKind::Hash])
+    }
+
+    fn lookup(&self, ioc: &Ioc) -> GenericResult<ProviderReport> {
+        self.ensure_lookup(ioc)?;
+
+        let r: Response = self.query(ioc.value())?;
+        let found = match r.search.into_iter().next() {
+            Some(x) => x,
+            None => return Ok(ProviderReport::not_found(self.name(), ioc.clone())),
+        };
+
+        let verdict = match &found.vt_report {
+            Some(VTReport {
+                positives: Some(p),
+                total: Some(t),
+                ..
+            }) if *p >= 0 && *t >= 0 => Verdict::from_detections(*p as u32, *t as u32),
+            _ => Verdict::Unknown,
+        };
 
-        Ok(r.search)
+        let mut report = ProviderReport::new(self.name(), ioc.clone(), verdict);
+        report.permalink = Some(format!(
+            "https://beta.virusbay.io/sample/browse/{}",
+            found.id
+        ));
+        report.tags = found
+            .tags
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|x| !x.isHash)
+            .map(|x| x.name)
+            .collect();
+        Ok(report)
     }
 }
@@ -2,54 +2,52 @@
 
 use chrono::Utc;
 use failure::Fail;
-use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::TryInto;
 
-use crate::contenthash::ContentHash;
-use crate::util::unwrap_try_into;
+use crate::contenthash::{ContentHash, SampleHashes};
+use crate::http::{block_on, ClientConfig};
+use crate::ioc::{Ioc, IocKind};
+use crate::keys::ApiKeyProvider;
+use crate::nonblocking;
+use crate::paginate::Paginated;
+use crate::provider::{Capabilities, IocProvider, ProviderReport, Verdict};
+use crate::resolve::HashResolver;
 use crate::{GenericResult, SampleHash};
 
 /// client for VirusTotal API (default use `$VTAPIKEY` environment variable as apikey)
+#[derive(Default)]
 pub struct VirusTotalClient {
-    apikey: String,
+    inner: nonblocking::VirusTotalClient,
 }
 
 impl VirusTotalClient {
     /// new client with apikey
     pub fn new(apikey: impl AsRef<str>) -> Self {
         VirusTotalClient {
-            apikey: apikey.as_ref().to_owned(),
+            inner: nonblocking::VirusTotalClient::new(apikey),
         }
     }
 
-    fn file_report_url(&self, resource: impl AsRef<str>, allinfo: bool) -> String {
-        format!(
-            "https://www.virustotal.com/vtapi/v2/file/report?apikey={}&allinfo={}&resource={}",
-            self.apikey,
-            allinfo,
-            resource.as_ref()
-        )
+    /// new client with apikey of the provider (e.g. `iocutil::keys::ConfigFile`)
+    pub fn from_keys(keys: impl ApiKeyProvider) -> GenericResult<Self> {
+        Ok(VirusTotalClient {
+            inner: nonblocking::VirusTotalClient::from_keys(keys)?,
+        })
     }
 
-    fn download_url(&self, hash: impl AsRef<str>) -> String {
-        format!(
-            "https://www.virustotal.com/vtapi/v2/file/download?apikey={}&hash={}",
-            self.apikey,
-            hash.as_ref()
-        )
+    /// use the HTTP settings (see `iocutil::http::ClientConfig`)
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.inner = self.inner.with_config(config);
+        self
     }
 
     fn internal_query<T>(&self, resource: impl AsRef<str>, allinfo: bool) -> GenericResult<T>
     where
         T: serde::de::DeserializeOwned,
     {
-        let mut res = reqwest::get(self.file_report_url(resource, allinfo).as_str())?;
-        if res.status().is_success() == false {
-            return Err(VTError::RequestFailed.into());
-        }
-        Ok(res.json()?)
+        block_on(self.inner.internal_query(resource, allinfo))
     }
 
     /// get file report of VirusTotal (with allinfo option)
@@ -76,7 +74,7 @@ impl VirusTotalClient {
     where
         T: serde::de::DeserializeOwned,
     {
-        self.internal_query(resource, true)
+        block_on(self.inner.query_filereport_allinfo(resource))
     }
 
     /// get raw filereport as text
@@ -96,11 +94,7 @@ impl VirusTotalClient {
         resource: impl AsRef<str>,
         allinfo: bool,
     ) -> GenericResult<String> {
-        let mut res = reqwest::get(self.file_report_url(resource, allinfo).as_str())?;
-        if res.status().is_success() == false {
-            return Err(VTError::RequestFailed.into());
-        }
-        Ok(res.text()?)
+        block_on(self.inner.get_raw_filereport_json(resource, allinfo))
     }
 
     /// get raw filereport json at specified datetime
@@ -123,9 +117,10 @@ impl VirusTotalClient {
         allinfo: bool,
         datetime: chrono::DateTime<Utc>,
     ) -> GenericResult<String> {
-        let hash = unwrap_try_into(hash)?;
-        let r = scan_id(hash, datetime);
-        self.get_raw_filereport_json(r, allinfo)
+        block_on(
+            self.inner
+                .get_raw_filereport_json_at(hash, allinfo, datetime),
+        )
     }
 
     /// query_filereport_at
@@ -146,9 +141,7 @@ impl VirusTotalClient {
         hash: impl TryInto<SampleHash>,
         datetime: chrono::DateTime<Utc>,
     ) -> GenericResult<FileReport> {
-        let hash = unwrap_try_into(hash)?;
-        let r = scan_id(hash, datetime);
-        self.query_filereport(r)
+        block_on(self.inner.query_filereport_at(hash, datetime))
     }
 
     /// query file report (without allinfo)
@@ -166,8 +159,7 @@ impl VirusTotalClient {
         &self,
         resource: impl AsRef<str>,
     ) -> Result<FileReport, failure::Error> {
-        let report: RawFileReport = self.internal_query(resource, false)?;
-        Ok(report.try_into()?)
+        block_on(self.inner.query_filereport(resource))
     }
 
     /// batch query file report
@@ -198,16 +190,7 @@ impl VirusTotalClient {
     where
         T: serde::de::DeserializeOwned,
     {
-        resources
-            .into_iter()
-            .enumerate()
-            .inspect(|(idx, _)| {
-                if *idx != 0 {
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                }
-            })
-            .map(|(_idx, item)| self.query_filereport_allinfo(item))
-            .collect()
+        block_on(self.inner.batch_query_allinfo(resources))
     }
 
     /// batch query file report
@@ -230,21 +213,7 @@ impl VirusTotalClient {
         resources: impl IntoIterator<Item = impl AsRef<str>>,
         public_api: bool,
     ) -> Vec<Result<FileReport, failure::Error>> {
-        let sleeptime = if public_api {
-            std::time::Duration::from_secs(15)
-        } else {
-            std::time::Duration::from_secs(1)
-        };
-        resources
-            .into_iter()
-            .enumerate()
-            .inspect(|(idx, _item)| {
-                if *idx != 0 {
-                    std::thread::sleep(sleeptime);
-                }
-            })
-            .map(|(_idx, item)| self.query_filereport(item))
-            .collect()
+        block_on(self.inner.batch_query(resources, public_api))
     }
 
     /// download a file from hash
@@ -268,18 +237,25 @@ impl VirusTotalClient {
         hash: impl TryInto<SampleHash>,
         into: impl AsRef<std::path::Path>,
     ) -> Result<(), failure::Error> {
-        let h = unwrap_try_into(hash)?;
-        let h = h.as_ref();
-
-        let mut res = reqwest::get(self.download_url(h).as_str())?;
-        if !res.status().is_success() {
-            return Err(VTError::DownloadFailed(h.to_owned()).into());
-        }
-
-        let mut f = std::fs::File::create(into)?;
-        std::io::copy(&mut res, &mut f)?;
+        block_on(self.inner.download(hash, into))
+    }
 
-        Ok(())
+    /// search samples page by page lazily (Private API required)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use iocutil::prelude::*;
+    ///
+    /// let client = VirusTotalClient::default();
+    /// let hashes: Vec<SampleHash> = client
+    ///     .search_paginated("type:peexe positives:5+")
+    ///     .limit(1000)
+    ///     .filter_map(|x| x.ok())
+    ///     .collect();
+    /// ```
+    pub fn search_paginated(&self, query: impl AsRef<str>) -> Paginated<'_, SampleHash> {
+        self.inner.search_paginated(query)
     }
 
     /// search by page (Private API required)
@@ -297,7 +273,9 @@ impl VirusTotalClient {
     /// assert_eq!(samples.len(), 300)
     /// ```
     pub fn search_by_pages(&self, query: impl AsRef<str>, goal: Option<usize>) -> Search {
-        Search::new(&self.apikey, query, goal)
+        Search {
+            inner: self.inner.search_by_pages(query, goal),
+        }
     }
 
     /// search samples (Private API required)
@@ -326,44 +304,14 @@ impl VirusTotalClient {
 
 /// context object for search api
 pub struct Search {
-    apikey: String,
-    query: String,
-    goal: Option<usize>,
-    offset: Option<String>,
-    current: usize,
-    has_done: bool,
+    inner: nonblocking::virustotal::Search,
 }
 
 impl Search {
     /// create new object
     pub fn new(apikey: impl AsRef<str>, query: impl AsRef<str>, goal: Option<usize>) -> Self {
         Search {
-            apikey: apikey.as_ref().to_owned(),
-            query: Search::escape_search_query(query),
-            offset: None,
-            current: 0,
-            has_done: false,
-            goal,
-        }
-    }
-
-    fn escape_search_query(query: impl AsRef<str>) -> String {
-        utf8_percent_encode(query.as_ref(), NON_ALPHANUMERIC).to_string()
-    }
-
-    fn search_url(&self, offset: &Option<String>) -> String {
-        match offset {
-            Some(o) => format!(
-                "https://www.virustotal.com/vtapi/v2/file/search?apikey={}&query={}&offset={}",
-                self.apikey.as_str(),
-                self.query.as_str(),
-                o,
-            ),
-            None => format!(
-                "https://www.virustotal.com/vtapi/v2/file/search?apikey={}&query={}",
-                self.apikey.as_str(),
-                self.query.as_str(),
-            ),
+            inner: nonblocking::virustotal::Search::new(apikey, query, goal),
         }
     }
 
@@ -372,38 +320,7 @@ impl Search {
     where
         T: std::iter::FromIterator<SampleHash>,
     {
-        if self.has_done {
-            return Err(VTError::AlreadyReachToGoal.into());
-        }
-
-        let url = self.search_url(&self.offset);
-
-        let mut res = reqwest::get(url.as_str())?;
-        if !res.status().is_success() {
-            return Err(VTError::RequestFailed.into());
-        }
-
-        let result: SearchResponse = res.json()?;
-        if result.response_code != 1 {
-            return Err(VTError::ResponseCodeError(result.response_code).into());
-        }
-
-        let hashes = result.hashes.ok_or(VTError::RequestFailed)?;
-
-        if let Some(x) = self.goal {
-            self.current += hashes.len();
-            if x <= self.current {
-                self.has_done = true;
-            }
-        }
-
-        if result.offset.is_none() {
-            self.has_done = true;
-        }
-
-        self.offset = result.offset;
-
-        SampleHash::try_map(hashes)
+        block_on(self.inner.do_search())
     }
 }
 
@@ -434,15 +351,6 @@ pub fn scan_id(sample: crate::SampleHash, datetime: impl Into<chrono::DateTime<U
     format!("{}-{}", sample.as_ref(), datetime.into().timestamp())
 }
 
-impl Default for VirusTotalClient {
-    fn default() -> Self {
-        VirusTotalClient {
-            apikey: std::env::var("VTAPIKEY")
-                .expect("please set VirusTotal API key to environment var $VTAPIKEY"),
-        }
-    }
-}
-
 /// Errors in VirusTotal operation
 #[derive(Fail, Debug)]
 pub enum VTError {
@@ -452,14 +360,6 @@ pub enum VTError {
     #[fail(display = "record missing field(s)")]
     MissingFields(String),
 
-    #[fail(display = "download failed")]
-    DownloadFailed(String),
-
-    #[fail(
-        display = "request failed. Usually, it caused by wrong query. Or it's a Private API if you use public API key."
-    )]
-    RequestFailed,
-
     #[fail(display = "already reach to goal")]
     AlreadyReachToGoal,
 }
@@ -547,11 +447,52 @@ impl Into<ContentHash> for FileReport {
     }
 }
 
+impl IocProvider for VirusTotalClient {
+    fn name(&self) -> &str {
+        "virustotal"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::lookup_only(vec![IocKind::Hash])
+    }
+
+    fn lookup(&self, ioc: &Ioc) -> GenericResult<ProviderReport> {
+        self.ensure_lookup(ioc)?;
+
+        let raw: RawFileReport = self.internal_query(ioc.value(), false)?;
+        if raw.response_code == 0 {
+            // not found on VirusTotal
+            return Ok(ProviderReport::not_found(self.name(), ioc.clone()));
+        }
+
+        let report: FileReport = raw.try_into()?;
+        let mut r = ProviderReport::new(
+            self.name(),
+            ioc.clone(),
+            Verdict::from_detections(report.positives, report.total),
+        );
+        r.permalink = Some(report.permalink);
+        Ok(r)
+    }
+}
+
+impl HashResolver for VirusTotalClient {
+    fn resolve(&self, hash: &SampleHash) -> GenericResult<Option<SampleHashes>> {
+        let raw: RawFileReport = self.internal_query(hash, false)?;
+        if raw.response_code == 0 {
+            return Ok(None);
+        }
+        let report: FileReport = raw.try_into()?;
+        let hashes: ContentHash = report.into();
+        Ok(Some(hashes.into()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SearchResponse {
-    response_code: i32,
-    offset: Option<String>,
-    hashes: Option<Vec<String>>,
+    pub(crate) response_code: i32,
+    pub(crate) offset: Option<String>,
+    pub(crate) hashes: Option<Vec<String>>,
 }
 
 /// first submission search modifier macro
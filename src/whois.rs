@@ -0,0 +1,308 @@
+//! registration of domains and IP addresses by RDAP (WHOIS as fallback)
+//!
+//! `WhoisClient` (feature `net`) looks up registrar, creation date and registrant organization
+//! by RDAP through the bootstrap redirector (`https://rdap.org`), and falls back to WHOIS
+//! (port 43, following referrals from `whois.iana.org`) when RDAP is not available.
+//!
+//! Registrations are annotated in `Metadata::registration`, and domains registered recently
+//! (within 30 days by default) are tagged by `NEWLY_REGISTERED_TAG`, so exports carry the flag
+//! as tags / labels.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// tag of newly registered domains
+pub const NEWLY_REGISTERED_TAG: &str = "newly-registered-domain";
+
+/// registration of a domain or IP address (network)
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Registration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registrar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    /// organization (or name) of registrant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registrant_org: Option<String>,
+}
+
+impl Registration {
+    /// no field is known?
+    pub fn is_empty(&self) -> bool {
+        self == &Registration::default()
+    }
+
+    /// registered within the window before the time?
+    pub fn is_newly_registered(&self, now: DateTime<Utc>, window: Duration) -> bool {
+        self.created.is_some_and(|x| x <= now && now - x < window)
+    }
+
+    /// registration in RDAP response (domain or ip network object of RFC 9083)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iocutil::whois::Registration;
+    ///
+    /// let json = serde_json::json!({
+    ///     "objectClassName": "domain",
+    ///     "ldhName": "example.com",
+    ///     "events": [
+    ///         {"eventAction": "registration", "eventDate": "1995-08-14T04:00:00Z"},
+    ///         {"eventAction": "expiration", "eventDate": "2025-08-13T04:00:00Z"}
+    ///     ],
+    ///     "entities": [
+    ///         {
+    ///             "roles": ["registrar"],
+    ///             "vcardArray": ["vcard", [["version", {}, "text", "4.0"], ["fn", {}, "text", "RESERVED-Internet Assigned Numbers Authority"]]]
+    ///         },
+    ///         {
+    ///             "roles": ["registrant"],
+    ///             "vcardArray": ["vcard", [["fn", {}, "text", "Domain Administrator"], ["org", {}, "text", "Internet Assigned Numbers Authority"]]]
+    ///         }
+    ///     ]
+    /// });
+    ///
+    /// let r = Registration::from_rdap(&json);
+    /// assert_eq!(r.registrar.as_deref(), Some("RESERVED-Internet Assigned Numbers Authority"));
+    /// assert_eq!(r.created.unwrap().to_rfc3339(), "1995-08-14T04:00:00+00:00");
+    /// assert_eq!(r.registrant_org.as_deref(), Some("Internet Assigned Numbers Authority"));
+    /// ```
+    pub fn from_rdap(json: &serde_json::Value) -> Self {
+        let created = json["events"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|x| x["eventAction"] == "registration")
+            .and_then(|x| x["eventDate"].as_str())
+            .and_then(parse_date);
+        let entity = |role| find_entity(json, role);
+        Registration {
+            registrar: entity("registrar").and_then(|x| vcard(x, "fn")),
+            created,
+            registrant_org: entity("registrant")
+                .and_then(|x| vcard(x, "org").or_else(|| vcard(x, "fn"))),
+        }
+    }
+
+    /// registration in WHOIS response (`key: value` lines of registries and RIRs)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chrono::Duration;
+    /// use iocutil::prelude::*;
+    /// use iocutil::whois::Registration;
+    ///
+    /// let text = "Domain Name: EXAMPLE.NET\r\n\
+    ///     Registrar: Example Registrar, Inc.\r\n\
+    ///     Creation Date: 2019-11-01T12:34:56Z\r\n\
+    ///     Registrant Organization: Example Org\r\n";
+    /// let r = Registration::from_whois(text);
+    /// assert_eq!(r.registrar.as_deref(), Some("Example Registrar, Inc."));
+    /// assert_eq!(r.created.unwrap().to_rfc3339(), "2019-11-01T12:34:56+00:00");
+    /// assert_eq!(r.registrant_org.as_deref(), Some("Example Org"));
+    /// assert!(r.is_newly_registered(day!(2019, 11, 15).unwrap().into(), Duration::days(30)));
+    /// assert!(!r.is_newly_registered(day!(2020, 1, 1).unwrap().into(), Duration::days(30)));
+    ///
+    /// // ARIN
+    /// let r = Registration::from_whois("NetRange: 192.0.2.0 - 192.0.2.255\nOrgName: Example Net\nRegDate: 2014-03-28\n");
+    /// assert_eq!(r.registrant_org.as_deref(), Some("Example Net"));
+    /// assert_eq!(r.created.unwrap().to_rfc3339(), "2014-03-28T00:00:00+00:00");
+    /// ```
+    pub fn from_whois(text: impl AsRef<str>) -> Self {
+        let fields: Vec<(String, &str)> = text
+            .as_ref()
+            .lines()
+            .filter(|x| !x.starts_with(['%', '#', '>']))
+            .filter_map(|x| x.split_once(':'))
+            .map(|(k, v)| (k.trim().to_lowercase(), v.trim()))
+            .filter(|(_, v)| !v.is_empty())
+            .collect();
+        let field = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| fields.iter().find(|(k, _)| k == key).map(|(_, v)| *v))
+        };
+
+        Registration {
+            registrar: field(&["registrar", "registrar name", "sponsoring registrar"])
+                .map(|x| x.to_owned()),
+            created: field(&[
+                "creation date",
+                "created",
+                "created on",
+                "registered on",
+                "registration time",
+                "domain registration date",
+                "regdate",
+            ])
+            .and_then(parse_date),
+            registrant_org: field(&[
+                "registrant organization",
+                "registrant organisation",
+                "registrant",
+                "org-name",
+                "orgname",
+                "organization",
+                "owner",
+            ])
+            .map(|x| x.to_owned()),
+        }
+    }
+
+    /// fields of newer registration merged into this one (known fields win)
+    pub fn merge(self, new: Registration) -> Registration {
+        Registration {
+            registrar: new.registrar.or(self.registrar),
+            created: new.created.or(self.created),
+            registrant_org: new.registrant_org.or(self.registrant_org),
+        }
+    }
+}
+
+/// entity of the role (searched recursively, as registrants may be nested in registrars)
+fn find_entity<'a>(json: &'a serde_json::Value, role: &str) -> Option<&'a serde_json::Value> {
+    let entities = json["entities"].as_array()?;
+    entities
+        .iter()
+        .find(|x| {
+            x["roles"]
+                .as_array()
+                .is_some_and(|roles| roles.iter().any(|r| r == role))
+        })
+        .or_else(|| entities.iter().find_map(|x| find_entity(x, role)))
+}
+
+/// text property of jCard (RFC 7095) in entity
+fn vcard(entity: &serde_json::Value, name: &str) -> Option<String> {
+    let value = entity["vcardArray"][1]
+        .as_array()?
+        .iter()
+        .find(|x| x[0] == name)?
+        .get(3)?;
+    let text = match value {
+        serde_json::Value::String(x) => x.clone(),
+        // structured value (e.g. organization and its units)
+        serde_json::Value::Array(xs) => xs
+            .iter()
+            .filter_map(|x| x.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => return None,
+    };
+    Some(text.trim().to_owned()).filter(|x| !x.is_empty())
+}
+
+/// date in RDAP / WHOIS (RFC 3339 or common formats of WHOIS servers, UTC if no timezone)
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(x) = DateTime::parse_from_rfc3339(text) {
+        return Some(x.into());
+    }
+    // e.g. `2019-11-01 12:34:56 UTC` and `2019-11-01T12:34:56.0Z`
+    let head = text.get(..19).unwrap_or(text);
+    for format in &["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(x) = NaiveDateTime::parse_from_str(head, format) {
+            return Some(DateTime::<Utc>::from_utc(x, Utc));
+        }
+    }
+    let head = text.split_whitespace().next()?;
+    ["%Y-%m-%d", "%d-%b-%Y", "%Y.%m.%d", "%Y/%m/%d", "%Y%m%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(head, format).ok())
+        .and_then(|x| x.and_hms_opt(0, 0, 0))
+        .map(|x| DateTime::<Utc>::from_utc(x, Utc))
+}
+
+#[cfg(feature = "net")]
+pub use self::client::WhoisClient;
+
+#[cfg(feature = "net")]
+mod client {
+    use chrono::Duration;
+    use std::net::IpAddr;
+
+    use super::Registration;
+    use crate::http::{block_on, ClientConfig};
+    use crate::ioc::Indicator;
+    use crate::nonblocking;
+
+    use crate::GenericResult;
+
+    /// client of RDAP (WHOIS as fallback)
+    #[derive(Default, Clone)]
+    pub struct WhoisClient {
+        inner: nonblocking::WhoisClient,
+    }
+
+    impl WhoisClient {
+        pub fn new() -> Self {
+            WhoisClient::default()
+        }
+
+        /// use the HTTP settings (see `iocutil::http::ClientConfig`, timeout applies to WHOIS
+        /// queries too, while others are for RDAP only)
+        pub fn with_config(mut self, config: ClientConfig) -> Self {
+            self.inner = self.inner.with_config(config);
+            self
+        }
+
+        /// base URL of RDAP bootstrap service (default `https://rdap.org`)
+        pub fn with_rdap_base(mut self, base: impl AsRef<str>) -> Self {
+            self.inner = self.inner.with_rdap_base(base);
+            self
+        }
+
+        /// do not fall back to WHOIS (e.g. where port 43 is blocked)
+        pub fn without_whois(mut self) -> Self {
+            self.inner = self.inner.without_whois();
+            self
+        }
+
+        /// domains registered within the window are newly registered (default 30 days)
+        pub fn with_newly_registered_window(mut self, window: Duration) -> Self {
+            self.inner = self.inner.with_newly_registered_window(window);
+            self
+        }
+
+        /// registration of the (registrable) domain (`None` if not registered)
+        ///
+        /// # Example
+        ///
+        /// ```ignore
+        /// use iocutil::whois::WhoisClient;
+        ///
+        /// let client = WhoisClient::new();
+        /// let r = client.domain("www.example.com").expect("failed to query").unwrap();
+        /// println!("{:?} {:?}", r.registrar, r.created);
+        /// ```
+        pub fn domain(&self, domain: impl AsRef<str>) -> GenericResult<Option<Registration>> {
+            block_on(self.inner.domain(domain))
+        }
+
+        /// registration of the network of IP address
+        pub fn ip(&self, ip: IpAddr) -> GenericResult<Option<Registration>> {
+            block_on(self.inner.ip(ip))
+        }
+
+        /// annotate a domain or IP address (or URL / email address on them) with `registration`
+        /// of metadata, and tag newly registered domains. returns true if found.
+        ///
+        /// # Example
+        ///
+        /// ```ignore
+        /// use iocutil::prelude::*;
+        /// use iocutil::whois::{WhoisClient, NEWLY_REGISTERED_TAG};
+        ///
+        /// let mut i = Indicator::new(Ioc::new("http://evil.example.com/a").unwrap());
+        /// WhoisClient::new().enrich(&mut i).expect("failed to query");
+        /// if i.meta.tags.iter().any(|x| x == NEWLY_REGISTERED_TAG) {
+        ///     println!("newly registered");
+        /// }
+        /// ```
+        pub fn enrich(&self, indicator: &mut Indicator) -> GenericResult<bool> {
+            block_on(self.inner.enrich(indicator))
+        }
+    }
+}
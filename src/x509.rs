@@ -0,0 +1,221 @@
+//! X.509 certificates (of authenticode signatures and TLS servers)
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::net::IpAddr;
+
+use crate::der::{self, Tlv};
+use crate::fingerprint::Fingerprint;
+
+/// an X.509 certificate
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Certificate {
+    /// subject in OpenSSL one-line format (e.g. `/C=US/O=Example/CN=Example`)
+    pub subject: String,
+    /// issuer in OpenSSL one-line format
+    pub issuer: String,
+    /// serial number in colon separated hex (e.g. `03:9f:1b`)
+    pub serial_number: String,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+    /// DNS names and IP addresses in subject alternative name extension
+    pub subject_alt_names: Vec<String>,
+    /// sha1 of the certificate (thumbprint)
+    pub sha1: String,
+    /// sha256 of the certificate
+    pub sha256: String,
+    /// certificate in DER
+    pub der: Vec<u8>,
+    pub(crate) raw_subject: Vec<u8>,
+    pub(crate) raw_issuer: Vec<u8>,
+    pub(crate) raw_serial: Vec<u8>,
+}
+
+pub(crate) fn hex(bytes: &[u8], separator: &str) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn attribute_name(oid: &str) -> String {
+    match oid {
+        "2.5.4.3" => "CN",
+        "2.5.4.4" => "SN",
+        "2.5.4.5" => "serialNumber",
+        "2.5.4.6" => "C",
+        "2.5.4.7" => "L",
+        "2.5.4.8" => "ST",
+        "2.5.4.9" => "street",
+        "2.5.4.10" => "O",
+        "2.5.4.11" => "OU",
+        "2.5.4.12" => "title",
+        "2.5.4.15" => "businessCategory",
+        "2.5.4.17" => "postalCode",
+        "2.5.4.42" => "GN",
+        "1.2.840.113549.1.9.1" => "emailAddress",
+        "0.9.2342.19200300.100.1.25" => "DC",
+        "0.9.2342.19200300.100.1.1" => "UID",
+        "1.3.6.1.4.1.311.60.2.1.1" => "jurisdictionL",
+        "1.3.6.1.4.1.311.60.2.1.2" => "jurisdictionST",
+        "1.3.6.1.4.1.311.60.2.1.3" => "jurisdictionC",
+        x => x,
+    }
+    .to_owned()
+}
+
+/// text of directory string
+fn string_value(x: &Tlv<'_>) -> String {
+    match x.tag {
+        // BMPString (UTF-16BE)
+        0x1e => {
+            let units: Vec<u16> = x
+                .content
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        // T61String (treated as latin-1)
+        0x14 => x.content.iter().map(|&c| c as char).collect(),
+        _ => String::from_utf8_lossy(x.content).into_owned(),
+    }
+}
+
+/// distinguished name in OpenSSL one-line format
+fn name(x: &Tlv<'_>) -> Option<String> {
+    let mut s = String::new();
+    for rdn in der::children(x.content)?
+        .iter()
+        .filter(|x| x.tag == der::SET)
+    {
+        for atv in der::children(rdn.content)? {
+            let parts = der::children(atv.content)?;
+            let (oid, value) = (parts.first()?, parts.get(1)?);
+            s.push('/');
+            s.push_str(&attribute_name(&der::oid(oid.content)));
+            s.push('=');
+            s.push_str(&string_value(value));
+        }
+    }
+    Some(s)
+}
+
+fn time(x: &Tlv<'_>) -> Option<DateTime<Utc>> {
+    let s = std::str::from_utf8(x.content).ok()?;
+    let s = s.strip_suffix('Z')?;
+    let (year, rest) = match x.tag {
+        der::UTC_TIME => {
+            let yy: i32 = s.get(..2)?.parse().ok()?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, s.get(2..)?)
+        }
+        der::GENERALIZED_TIME => (s.get(..4)?.parse().ok()?, s.get(4..)?),
+        _ => return None,
+    };
+    let field = |i: usize| -> Option<u32> { rest.get(i..i + 2)?.parse().ok() };
+    let naive = NaiveDate::from_ymd_opt(year, field(0)?, field(2)?)?.and_hms_opt(
+        field(4)?,
+        field(6)?,
+        field(8).unwrap_or(0),
+    )?;
+    Some(DateTime::from_utc(naive, Utc))
+}
+
+/// DNS names and IP addresses in `[3] Extensions` (empty if no subject alternative name)
+fn subject_alt_names(extensions: &Tlv<'_>) -> Option<Vec<String>> {
+    let (list, _) = der::expect(extensions.content, der::SEQUENCE)?;
+    for extension in der::children(list.content)? {
+        // Extension { extnID, critical (optional), extnValue }
+        let fields = der::children(extension.content)?;
+        if fields.first()?.tag != der::OID || der::oid(fields[0].content) != "2.5.29.17" {
+            continue;
+        }
+        let value = fields.iter().find(|x| x.tag == der::OCTET_STRING)?;
+        let (general_names, _) = der::expect(value.content, der::SEQUENCE)?;
+        let mut names = vec![];
+        for general_name in der::children(general_names.content)? {
+            match general_name.tag {
+                // dNSName [2] IA5String
+                0x82 => names.push(String::from_utf8_lossy(general_name.content).to_lowercase()),
+                // iPAddress [7] OCTET STRING
+                0x87 => match general_name.content.len() {
+                    4 => {
+                        let mut octets = [0; 4];
+                        octets.copy_from_slice(general_name.content);
+                        names.push(IpAddr::from(octets).to_string());
+                    }
+                    16 => {
+                        let mut octets = [0; 16];
+                        octets.copy_from_slice(general_name.content);
+                        names.push(IpAddr::from(octets).to_string());
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        return Some(names);
+    }
+    Some(vec![])
+}
+
+impl Certificate {
+    /// certificate in DER (`None` if malformed)
+    pub fn from_der(raw: &[u8]) -> Option<Self> {
+        let (cert, _) = der::expect(raw, der::SEQUENCE)?;
+        let (tbs, _) = der::expect(cert.content, der::SEQUENCE)?;
+        let mut fields = der::children(tbs.content)?.into_iter();
+        let mut serial = fields.next()?;
+        // version is optional
+        if serial.tag == der::context(0) {
+            serial = fields.next()?;
+        }
+        if serial.tag != der::INTEGER {
+            return None;
+        }
+        let _algorithm = fields.next()?;
+        let issuer = fields.next()?;
+        let validity = der::children(fields.next()?.content)?;
+        let subject = fields.next()?;
+        // subjectPublicKeyInfo, and optional unique identifiers before extensions
+        let subject_alt_names = fields
+            .find(|x| x.tag == der::context(3))
+            .and_then(|x| subject_alt_names(&x))
+            .unwrap_or_default();
+
+        Some(Certificate {
+            subject: name(&subject)?,
+            issuer: name(&issuer)?,
+            serial_number: hex(serial.content, ":"),
+            not_before: validity.first().and_then(time),
+            not_after: validity.get(1).and_then(time),
+            subject_alt_names,
+            sha1: format!("{:x}", Sha1::digest(cert.raw)),
+            sha256: format!("{:x}", Sha256::digest(cert.raw)),
+            der: cert.raw.to_vec(),
+            raw_subject: subject.raw.to_vec(),
+            raw_issuer: issuer.raw.to_vec(),
+            raw_serial: serial.content.to_vec(),
+        })
+    }
+
+    /// thumbprint (sha1) of the certificate
+    pub fn thumbprint(&self) -> Fingerprint {
+        Fingerprint::CertificateThumbprint(self.sha1.clone())
+    }
+
+    /// common name in subject (e.g. `evil.example.com`)
+    pub fn common_name(&self) -> Option<&str> {
+        self.subject
+            .split('/')
+            .rev()
+            .find_map(|x| x.strip_prefix("CN="))
+    }
+
+    /// issued by itself (subject is the issuer, e.g. roots and certificates generated by C2 frameworks)
+    pub fn is_self_signed(&self) -> bool {
+        self.raw_subject == self.raw_issuer
+    }
+}
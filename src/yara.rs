@@ -0,0 +1,170 @@
+//! YARA rule generation
+
+use chrono::Utc;
+use derive_builder::Builder;
+use std::collections::HashSet;
+
+use crate::allowlist::suppress;
+use crate::ioc::{Indicator, Ioc};
+use crate::ttl::unexpired;
+use crate::SampleHash;
+
+/// options of generated YARA rule
+///
+/// `{date}` (YYYYMMDD) and `{count}` (number of hashes) in name and meta values are replaced.
+#[derive(Builder, Debug, Clone)]
+pub struct YaraRuleOptions {
+    /// name of rule (invalid characters are replaced with `_`)
+    #[builder(setter(into), default = "\"iocutil_hashes_{date}\".to_owned()")]
+    name: String,
+    /// tags of rule
+    #[builder(default = "vec![]")]
+    tags: Vec<String>,
+    /// meta section in order
+    #[builder(
+        default = "vec![(\"description\".to_owned(), \"{count} hashes generated by iocutil.rs\".to_owned())]"
+    )]
+    meta: Vec<(String, String)>,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for YaraRuleOptions {
+    fn default() -> Self {
+        YaraRuleOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// make text an identifier of YARA
+fn identifier(text: impl AsRef<str>) -> String {
+    let id: String = text
+        .as_ref()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match id.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", id),
+        None => "_".to_owned(),
+        _ => id,
+    }
+}
+
+fn escape(text: impl AsRef<str>) -> String {
+    text.as_ref().replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// generate YARA rule matching any of hashes (using `hash` module)
+///
+/// Indicators other than hashes are skipped, hashes allowed by the export allowlist (e.g.
+/// `knowngood::KnownGoodHashes`) are suppressed, and expired ones are excluded unless
+/// `include_expired`.
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::yara::{to_yara_rule, YaraRuleOptionsBuilder};
+///
+/// let hashes = vec![
+///     SampleHash::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+///     SampleHash::new("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap(),
+/// ];
+///
+/// let options = YaraRuleOptionsBuilder::default()
+///     .name("2019-campaign")
+///     .tags(vec!["apt".to_owned()])
+///     .build()
+///     .unwrap();
+///
+/// let rule = to_yara_rule(hashes, &options);
+/// assert!(rule.starts_with("import \"hash\"\n\nrule _2019_campaign : apt\n{"));
+/// assert!(rule.contains("description = \"2 hashes generated by iocutil.rs\""));
+/// assert!(rule.contains("hash.md5(0, filesize) == \"d41d8cd98f00b204e9800998ecf8427e\" or\n"));
+/// assert!(rule.contains("hash.sha256(0, filesize) == \"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\"\n"));
+/// ```
+///
+/// known good hashes are not ruled
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::allowlist;
+/// use iocutil::knowngood::KnownGoodHashes;
+/// use iocutil::yara::to_yara_rule;
+///
+/// let nsrl = "\"SHA-1\",\"MD5\",\"FileName\"\n\"\",\"1D6EBB5A789ABD108FF578263E1F40F3\",\"setup.exe\"";
+/// let mut known = KnownGoodHashes::new();
+/// known.load_nsrl(nsrl.as_bytes()).unwrap();
+/// allowlist::set_export_allowlist(known);
+///
+/// let hashes = vec![
+///     SampleHash::new("1d6ebb5a789abd108ff578263e1f40f3").unwrap(),
+///     SampleHash::new("d41d8cd98f00b204e9800998ecf8427e").unwrap(),
+/// ];
+/// let (rule, suppressed) = allowlist::audited(|| to_yara_rule(hashes, &Default::default()));
+/// assert!(!rule.contains("1d6ebb5a789abd108ff578263e1f40f3"));
+/// assert!(rule.contains("d41d8cd98f00b204e9800998ecf8427e"));
+/// assert_eq!(suppressed[0].list, "nsrl");
+/// ```
+pub fn to_yara_rule(
+    hashes: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &YaraRuleOptions,
+) -> String {
+    let mut seen = HashSet::new();
+    let hashes: Vec<SampleHash> = suppress(unexpired(hashes, options.include_expired))
+        .into_iter()
+        .filter_map(|x| match x.ioc {
+            Ioc::Hash(h) => Some(h),
+            _ => None,
+        })
+        .filter(|x| seen.insert(x.clone()))
+        .collect();
+
+    let date = Utc::now().format("%Y%m%d").to_string();
+    let count = hashes.len().to_string();
+    let fill = |x: &str| x.replace("{date}", &date).replace("{count}", &count);
+
+    let mut rule = format!(
+        "import \"hash\"\n\nrule {}",
+        identifier(fill(&options.name))
+    );
+    if !options.tags.is_empty() {
+        let tags: Vec<_> = options.tags.iter().map(identifier).collect();
+        rule.push_str(&format!(" : {}", tags.join(" ")));
+    }
+    rule.push_str("\n{\n");
+
+    if !options.meta.is_empty() {
+        rule.push_str("    meta:\n");
+        for (k, v) in &options.meta {
+            rule.push_str(&format!(
+                "        {} = \"{}\"\n",
+                identifier(k),
+                escape(fill(v))
+            ));
+        }
+    }
+
+    let conditions: Vec<_> = hashes
+        .iter()
+        .map(|h| {
+            let f = match h {
+                SampleHash::Md5(_) => "md5",
+                SampleHash::Sha1(_) => "sha1",
+                SampleHash::Sha256(_) => "sha256",
+            };
+            format!("        hash.{}(0, filesize) == \"{}\"", f, h)
+        })
+        .collect();
+
+    rule.push_str("    condition:\n");
+    if conditions.is_empty() {
+        rule.push_str("        false\n");
+    } else {
+        rule.push_str(&conditions.join(" or\n"));
+        rule.push('\n');
+    }
+    rule.push_str("}\n");
+
+    rule
+}
@@ -0,0 +1,121 @@
+//! Zeek Intel Framework file export
+
+use derive_builder::Builder;
+use std::io::Write;
+
+use crate::allowlist::suppress;
+use crate::ioc::{Indicator, Ioc};
+use crate::ttl::unexpired;
+
+/// options of intel file export
+#[derive(Builder, Debug, Clone)]
+pub struct ZeekIntelOptions {
+    /// meta.source of indicators without source
+    #[builder(setter(into), default = "\"iocutil.rs\".to_owned()")]
+    default_source: String,
+    /// add meta.do_notice column (requires `frameworks/intel/do_notice` policy)
+    #[builder(default = "None")]
+    do_notice: Option<bool>,
+    /// export expired indicators too (see `iocutil::ttl`)
+    #[builder(default = "false")]
+    include_expired: bool,
+}
+
+impl Default for ZeekIntelOptions {
+    fn default() -> Self {
+        ZeekIntelOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// indicator and its type in Zeek
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::zeek::intel_type;
+///
+/// assert_eq!(intel_type(&Ioc::new("http://example.com/a").unwrap()), ("example.com/a".to_owned(), "Intel::URL"));
+/// assert_eq!(intel_type(&Ioc::new("192.0.2.1").unwrap()), ("192.0.2.1".to_owned(), "Intel::ADDR"));
+/// ```
+pub fn intel_type(ioc: &Ioc) -> (String, &'static str) {
+    match ioc {
+        Ioc::Hash(x) => (x.to_string(), "Intel::FILE_HASH"),
+        Ioc::Domain(x) => (x.to_owned(), "Intel::DOMAIN"),
+        // urls are matched without scheme
+        Ioc::Url(x) => (
+            x.find("://").map(|i| &x[i + 3..]).unwrap_or(x).to_owned(),
+            "Intel::URL",
+        ),
+        Ioc::IpAddress(x) => (x.to_string(), "Intel::ADDR"),
+        Ioc::EmailAddress(x) => (x.to_owned(), "Intel::EMAIL"),
+    }
+}
+
+/// make text a field (tab and newline are separators, and empty field is `-`)
+fn field(text: impl AsRef<str>) -> String {
+    let f = text.as_ref().replace(&['\t', '\r', '\n'][..], " ");
+    if f.is_empty() {
+        "-".to_owned()
+    } else {
+        f
+    }
+}
+
+/// write indicators as intel file
+pub fn write_zeek_intel(
+    mut writer: impl Write,
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &ZeekIntelOptions,
+) -> std::io::Result<()> {
+    let mut header = vec!["indicator", "indicator_type", "meta.source", "meta.desc"];
+    if options.do_notice.is_some() {
+        header.push("meta.do_notice");
+    }
+    writeln!(writer, "#fields\t{}", header.join("\t"))?;
+
+    for indicator in suppress(unexpired(indicators, options.include_expired)) {
+        let indicator: Indicator = indicator;
+        let (value, t) = intel_type(&indicator.ioc);
+        let source = indicator
+            .meta
+            .source
+            .as_ref()
+            .unwrap_or(&options.default_source);
+        let desc = indicator.meta.description.as_deref().unwrap_or_default();
+
+        let mut row = vec![field(value), t.to_owned(), field(source), field(desc)];
+        if let Some(n) = options.do_notice {
+            row.push(if n { "T" } else { "F" }.to_owned());
+        }
+        writeln!(writer, "{}", row.join("\t"))?;
+    }
+
+    writer.flush()
+}
+
+/// export indicators as intel file (`intel.dat`)
+///
+/// # Example
+///
+/// ```
+/// use iocutil::prelude::*;
+/// use iocutil::zeek::{to_zeek_intel, ZeekIntelOptions};
+///
+/// let mut i = Indicator::new(Ioc::new("evil.example.com").unwrap());
+/// i.meta.description = Some("C2\tserver".to_owned());
+///
+/// assert_eq!(
+///     to_zeek_intel(vec![i], &ZeekIntelOptions::default()),
+///     "#fields\tindicator\tindicator_type\tmeta.source\tmeta.desc\nevil.example.com\tIntel::DOMAIN\tiocutil.rs\tC2 server\n"
+/// );
+/// ```
+pub fn to_zeek_intel(
+    indicators: impl IntoIterator<Item = impl Into<Indicator>>,
+    options: &ZeekIntelOptions,
+) -> String {
+    let mut buf = vec![];
+    // writing into vec never fails
+    write_zeek_intel(&mut buf, indicators, options).unwrap();
+    String::from_utf8(buf).unwrap()
+}